@@ -1,10 +1,13 @@
 //! Действия приложения
 
-use super::state::{App, DialogResult, TargetInfo};
+use super::state::{App, DialogResult, ResumeProbe, TargetInfo};
 use std::sync::atomic::Ordering;
 use toolza_sender::network;
-use toolza_sender::protocol::{FileInfo, FileStatus, collect_files_from_folder};
-use toolza_sender::utils::format_size;
+use toolza_sender::pattern::Pattern;
+use toolza_sender::protocol::{CollectOptions, FileInfo, FileStatus, collect_files_from_folder_with_options};
+use toolza_sender::utils::{format_size, parse_size};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use tokio::sync::mpsc;
 
 impl App {
@@ -18,6 +21,7 @@ impl App {
         // Проверяем, не добавлен ли уже
         if !self.targets.iter().any(|t| t.address == address) {
             self.targets.push(TargetInfo::new(address));
+            self.resume_probe_stale = true;
         }
     }
     
@@ -26,12 +30,14 @@ impl App {
     pub fn remove_target(&mut self, index: usize) {
         if index < self.targets.len() {
             self.targets.remove(index);
+            self.resume_probe_stale = true;
         }
     }
     
     /// Очистить список получателей
     pub fn clear_targets(&mut self) {
         self.targets.clear();
+        self.resume_probe_stale = true;
     }
     
     // === Управление файлами ===
@@ -84,6 +90,7 @@ impl App {
                             Ok(info) => {
                                 if !self.files.iter().any(|f| f.path == path) {
                                     self.files.push(info);
+                                    self.resume_probe_stale = true;
                                 }
                             }
                             Err(e) => {
@@ -93,7 +100,8 @@ impl App {
                     }
                 }
                 DialogResult::Folder(folder) => {
-                    match collect_files_from_folder(&folder) {
+                    let options = CollectOptions { include_hidden: self.include_hidden, ..Default::default() };
+                    match collect_files_from_folder_with_options(&folder, &options) {
                         Ok(files) => {
                             let folder_name = folder
                                 .file_name()
@@ -106,6 +114,7 @@ impl App {
                                     self.files.push(file);
                                 }
                             }
+                            self.resume_probe_stale = true;
                             self.log(format!("Добавлена папка '{}': {} файл(ов)", folder_name, count));
                         }
                         Err(e) => {
@@ -119,16 +128,50 @@ impl App {
                 DialogResult::ArchiveFile(path) => {
                     self.extract_archive_path = Some(path);
                     self.extract_result = None;
+                    self.extract_listing = None;
+                    self.extract_selected.clear();
                 }
                 DialogResult::ExtractDestination(path) => {
                     self.extract_destination = path;
                 }
+                DialogResult::HistoryCsv(path) => {
+                    match self.history.export_csv(&path) {
+                        Ok(()) => self.status_message = format!("История сохранена: {}", path.display()),
+                        Err(e) => self.log(format!("❌ Ошибка экспорта истории: {}", e)),
+                    }
+                }
+                DialogResult::CompletionSound(path) => {
+                    self.settings.completion_sound = true;
+                    self.settings.completion_sound_path = Some(path);
+                    if let Err(e) = self.settings.save() {
+                        self.log(format!("❌ Не удалось сохранить настройки: {}", e));
+                    }
+                }
                 DialogResult::ExtractComplete(result) => {
                     self.extract_running = false;
                     self.extract_result = Some(result.clone());
                     self.status_message = result.clone();
                     self.log(result);
                 }
+                DialogResult::ArchiveListing(result) => {
+                    self.extract_listing_running = false;
+                    match result {
+                        Ok(entries) => self.extract_listing = Some(entries),
+                        Err(e) => self.extract_result = Some(format!("❌ Ошибка чтения архива: {}", e)),
+                    }
+                }
+                DialogResult::ResumeProbe(key, target, result) => {
+                    // Ответ на устаревший набор файлов или ошибка (получатель
+                    // выключен или старый) - просто не предлагаем докачку
+                    if key != self.resume_probe_key || self.is_running {
+                        continue;
+                    }
+                    if let Ok(offsets) = result {
+                        if offsets.iter().any(|&offset| offset > 0) {
+                            self.resume_probe = Some(ResumeProbe { target, offsets });
+                        }
+                    }
+                }
             }
         }
     }
@@ -190,17 +233,20 @@ impl App {
         
         let tx = self.dialog_tx.clone();
         let stop_flag = self.extract_stop_flag.clone();
+        // Отмеченные файлы - точные пути, без раскрытия `*` в именах
+        let include: Vec<Pattern> = self.extract_selected.iter().map(|name| Pattern::literal(name)).collect();
         
         // Запускаем распаковку в отдельном потоке (потоковая, не грузит в RAM)
         std::thread::spawn(move || {
-            let result_msg = match toolza_sender::extract::extract_archive_streaming(
-                &archive_path, 
-                &output_dir,
-                Some(stop_flag)
-            ) {
+            let result = if include.is_empty() {
+                toolza_sender::extract::extract_archive_streaming(&archive_path, &output_dir, Some(stop_flag))
+            } else {
+                toolza_sender::extract::extract_entries(&archive_path, &output_dir, &include, Some(stop_flag))
+            };
+            let result_msg = match result {
                 Ok(result) => {
-                    format!("✅ Распаковано: {} файлов, {}", 
-                        result.files_count, format_size(result.total_size))
+                    format!("✅ Распаковано: {}, {}", 
+                        result.summary(), format_size(result.total_size))
                 }
                 Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
                     "⏹ Распаковка отменена".to_string()
@@ -213,6 +259,20 @@ impl App {
         });
     }
     
+    /// Прочитать содержимое выбранного архива (в отдельном потоке)
+    pub fn load_archive_listing(&mut self) {
+        let Some(archive_path) = self.extract_archive_path.clone() else {
+            return;
+        };
+        
+        self.extract_listing_running = true;
+        let tx = self.dialog_tx.clone();
+        std::thread::spawn(move || {
+            let result = toolza_sender::extract::list_archive(&archive_path).map_err(|e| e.to_string());
+            let _ = tx.send(DialogResult::ArchiveListing(result));
+        });
+    }
+    
     /// Остановить распаковку
     pub fn stop_extraction(&mut self) {
         self.extract_stop_flag.store(true, std::sync::atomic::Ordering::SeqCst);
@@ -224,12 +284,14 @@ impl App {
     pub fn remove_file(&mut self, index: usize) {
         if index < self.files.len() {
             self.files.remove(index);
+            self.resume_probe_stale = true;
         }
     }
     
     /// Очистить список файлов
     pub fn clear_files(&mut self) {
         self.files.clear();
+        self.resume_probe_stale = true;
     }
     
     /// Выбрать папку сохранения (асинхронно)
@@ -245,10 +307,150 @@ impl App {
         });
     }
     
+    /// Выбрать файл для экспорта истории в CSV
+    pub fn export_history_dialog(&mut self) {
+        let tx = self.dialog_tx.clone();
+        std::thread::spawn(move || {
+            if let Some(path) = rfd::FileDialog::new()
+                .set_title("Экспорт истории")
+                .set_file_name("toolza_history.csv")
+                .add_filter("CSV", &["csv"])
+                .save_file()
+            {
+                let _ = tx.send(DialogResult::HistoryCsv(path));
+            }
+        });
+    }
+    
+    /// Выбрать свой звук завершения
+    pub fn select_completion_sound_dialog(&mut self) {
+        let tx = self.dialog_tx.clone();
+        std::thread::spawn(move || {
+            if let Some(path) = rfd::FileDialog::new()
+                .set_title("Звук завершения")
+                .add_filter("Audio", &["wav", "ogg"])
+                .pick_file()
+            {
+                let _ = tx.send(DialogResult::CompletionSound(path));
+            }
+        });
+    }
+    
+    /// Применить введённый дневной лимит и сохранить настройки
+    pub fn apply_daily_quota(&mut self) {
+        let input = self.daily_quota_input.trim();
+        let quota = if input.is_empty() {
+            None
+        } else {
+            match parse_size(input) {
+                Some(limit) => Some(limit),
+                None => {
+                    self.log(format!("❌ Неверный размер лимита: {}", input));
+                    return;
+                }
+            }
+        };
+        if quota != self.settings.daily_quota {
+            self.settings.daily_quota = quota;
+            if let Err(e) = self.settings.save() {
+                self.log(format!("❌ Не удалось сохранить настройки: {}", e));
+            }
+        }
+    }
+    
+    /// Открыть путь в файловом менеджере (ошибку пишем в лог)
+    pub fn open_in_file_manager(&mut self, path: &std::path::Path) {
+        if let Err(e) = toolza_sender::utils::reveal_in_file_manager(path) {
+            self.log(format!("❌ {}", e));
+        }
+    }
+    
     // === Отправка ===
     
-    /// Начать отправку файлов
+    /// Адреса получателей (порт из поля порта, если не указан в адресе)
+    fn target_addresses(&self, port: u16) -> Result<Vec<String>, String> {
+        self.targets
+            .iter()
+            .map(|t| network::parse_target_addr(&t.address, port))
+            .collect()
+    }
+    
+    /// Файлы в том виде, в каком их сохранит получатель
+    fn prepared_files(&self) -> Result<Vec<FileInfo>, String> {
+        // Если не сохраняем структуру, используем только имена
+        let mut files: Vec<FileInfo> = if self.preserve_structure {
+            self.files.clone()
+        } else {
+            self.files.iter().map(|f| {
+                let mut file = f.clone();
+                file.relative_path = file.name.clone(); // Только имя файла
+                file
+            }).collect()
+        };
+        
+        // Другое имя у получателя (поле видно только для одного файла)
+        let output_name = self.output_name_input.trim();
+        if files.len() == 1 && !output_name.is_empty() {
+            files[0].set_remote_name(output_name)?;
+        }
+        Ok(files)
+    }
+    
+    /// Спросить первого получателя, что из выбранных файлов у него уже есть.
+    /// Повторно спрашиваем только при смене файлов, получателя или протокола
+    /// (`resume_probe_stale`) - не каждый кадр
+    pub fn update_resume_probe(&mut self) {
+        if !self.resume_probe_stale || !self.can_edit() {
+            return;
+        }
+        self.resume_probe_stale = false;
+        if self.files.is_empty() || self.targets.is_empty() {
+            return;
+        }
+        let Ok(port) = self.target_port.parse::<u16>() else {
+            return;
+        };
+        let Ok(files) = self.prepared_files() else {
+            return;
+        };
+        let Ok(mut targets) = self.target_addresses(port) else {
+            return;
+        };
+        let target = targets.swap_remove(0);
+        
+        let mut hasher = DefaultHasher::new();
+        target.hash(&mut hasher);
+        self.transport_type.name().hash(&mut hasher);
+        for file in &files {
+            file.path.hash(&mut hasher);
+            file.size.hash(&mut hasher);
+            file.relative_path.hash(&mut hasher);
+        }
+        let key = hasher.finish();
+        if key == self.resume_probe_key {
+            return;
+        }
+        self.resume_probe_key = key;
+        self.resume_probe = None;
+        
+        let options = network::sender::SendOptions {
+            transport_type: self.transport_type,
+            ..Default::default()
+        };
+        let tx = self.dialog_tx.clone();
+        self.runtime.spawn(async move {
+            let result = network::probe_resume(&target, &files, &options).await;
+            let _ = tx.send(DialogResult::ResumeProbe(key, target, result));
+        });
+    }
+    
+    /// Начать отправку файлов (уже полученное пропускается, прерванное докачивается)
     pub fn start_send(&mut self) {
+        self.start_send_with_resume(true);
+    }
+    
+    /// Начать отправку; `resume = false` - передать всё заново
+    pub fn start_send_with_resume(&mut self, resume: bool) {
         if self.files.is_empty() {
             self.status_message = "Добавьте файлы для отправки".to_string();
             return;
@@ -268,26 +470,21 @@ impl App {
         };
         
         // Формируем список адресов
-        let targets: Vec<String> = self.targets
-            .iter()
-            .map(|t| {
-                if t.address.contains(':') {
-                    t.address.clone()
-                } else {
-                    format!("{}:{}", t.address, port)
-                }
-            })
-            .collect();
+        let targets = match self.target_addresses(port) {
+            Ok(targets) => targets,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
         
-        // Подготавливаем файлы - если не сохраняем структуру, используем только имена
-        let files: Vec<FileInfo> = if self.preserve_structure {
-            self.files.clone()
-        } else {
-            self.files.iter().map(|f| {
-                let mut file = f.clone();
-                file.relative_path = file.name.clone(); // Только имя файла
-                file
-            }).collect()
+        // Подготавливаем файлы
+        let files = match self.prepared_files() {
+            Ok(files) => files,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
         };
         
         let (tx, rx) = mpsc::unbounded_channel();
@@ -303,6 +500,7 @@ impl App {
         }
         
         self.is_running = true;
+        self.reset_activity();
         
         // Сбрасываем флаг остановки
         self.reset_stop_flag();
@@ -310,20 +508,37 @@ impl App {
         // Инициализируем статистику
         self.reset_stats();
         
-        let compression_str = if self.use_compression { " (LZ4)" } else { "" };
+        let compression = match (self.use_compression, self.auto_compression) {
+            (false, _) => network::CompressionMode::Off,
+            (true, false) => network::CompressionMode::On,
+            (true, true) => network::CompressionMode::Auto,
+        };
+        let compression_str = match compression {
+            network::CompressionMode::Off => "",
+            network::CompressionMode::On => " (LZ4)",
+            network::CompressionMode::Auto => " (LZ4 авто)",
+        };
         let structure_str = if self.preserve_structure { "" } else { " [плоско]" };
         let transport_str = format!(" [{}]", self.transport_type.name());
         self.status_message = format!("Отправка на {} получателей{}{}{}...", targets.len(), compression_str, structure_str, transport_str);
         self.log(format!("Начинаем отправку на {} получателей{}{}{}", targets.len(), compression_str, structure_str, transport_str));
         
+        self.resume_probe = None;
+        
         let options = toolza_sender::network::sender::SendOptions {
-            use_compression: self.use_compression,
-            enable_resume: true,
+            compression,
+            enable_resume: resume,
             transport_type: self.transport_type,
+            timeout: Some(toolza_sender::network::transport::DEFAULT_IO_TIMEOUT),
+            checksum: true,
+            daily_quota: self.settings.daily_quota,
+            ..Default::default()
         };
         let stop_flag = self.stop_flag.clone();
+        self.target_cancels = network::TargetCancels::new();
+        let cancels = self.target_cancels.clone();
         let handle = self.runtime.spawn(async move {
-            network::send_files_to_multiple_with_stop(targets, files, options, tx, stop_flag).await;
+            network::send_files_to_multiple_with_targets(targets, files, options, tx, stop_flag, cancels).await;
         });
         self.current_task = Some(handle);
     }
@@ -340,12 +555,22 @@ impl App {
             }
         };
         
+        // Неподдерживаемые форматы распаковки - до запуска сервера
+        let options = self.server_options();
+        if let Err(e) = options.extract_options.validate() {
+            self.status_message = format!("❌ {}", e);
+            return;
+        }
+        
         let save_dir = self.save_directory.clone();
         let (tx, rx) = mpsc::unbounded_channel();
         self.event_rx = Some(rx);
         
         self.is_running = true;
         self.received_files.clear();
+        self.incoming_files.clear();
+        self.disk_full = None;
+        self.reset_activity();
         
         // Сбрасываем флаг остановки
         self.reset_stop_flag();
@@ -355,6 +580,7 @@ impl App {
         if self.auto_extract_tar_lz4 { extract_formats.push("tar.lz4"); }
         if self.auto_extract_tar_zst { extract_formats.push("tar.zst"); }
         if self.auto_extract_lz4 { extract_formats.push("lz4"); }
+        if self.auto_extract_zst { extract_formats.push("zst"); }
         if self.auto_extract_tar { extract_formats.push("tar"); }
         if self.auto_extract_zip { extract_formats.push("zip"); }
         if self.auto_extract_rar { extract_formats.push("rar"); }
@@ -368,24 +594,31 @@ impl App {
         self.status_message = format!("Ожидание подключений на порту {}{}{}...", port, extract_str, transport_str);
         self.log(format!("Сервер запущен на порту {}{}{}", port, extract_str, transport_str));
         
-        let options = network::ServerOptions {
+        let stop_flag = self.stop_flag.clone();
+        let handle = self.runtime.spawn(async move {
+            let _ = network::run_server_with_options_and_stop(port, save_dir, options, tx, stop_flag).await;
+        });
+        self.current_task = Some(handle);
+    }
+    
+    /// Опции сервера из настроек режима приёма
+    pub fn server_options(&self) -> network::ServerOptions {
+        network::ServerOptions {
             extract_options: network::ExtractOptions {
                 tar_lz4: self.auto_extract_tar_lz4,
                 tar_zst: self.auto_extract_tar_zst,
                 lz4: self.auto_extract_lz4,
+                zst: self.auto_extract_zst,
                 tar: self.auto_extract_tar,
                 zip: self.auto_extract_zip,
                 rar: self.auto_extract_rar,
+                strip_components: self.extract_strip_components,
             },
-            enable_resume: true,
             transport_type: self.transport_type,
             save_archive_for_resume: self.save_archive_for_resume,
-        };
-        let stop_flag = self.stop_flag.clone();
-        let handle = self.runtime.spawn(async move {
-            let _ = network::run_server_with_options_and_stop(port, save_dir, options, tx, stop_flag).await;
-        });
-        self.current_task = Some(handle);
+            timeout: Some(network::transport::DEFAULT_IO_TIMEOUT),
+            ..Default::default()
+        }
     }
     
     // === Сканирование ===
@@ -406,6 +639,7 @@ impl App {
         self.is_scanning = true;
         self.scan_progress = 0;
         self.found_servers.clear();
+        self.server_names.clear();
         
         // Сбрасываем флаг остановки
         self.reset_stop_flag();
@@ -448,6 +682,13 @@ impl App {
         // Устанавливаем флаг остановки
         self.stop_flag.store(true, Ordering::SeqCst);
         
+        // Спидтест не прерываем: по флагу он сам отправит Cancel серверу
+        // и завершится событием SpeedTestCancelled
+        if self.speedtest_running && self.current_task.as_ref().is_some_and(|task| !task.is_finished()) {
+            self.status_message = "⏳ Остановка спидтеста...".to_string();
+            return;
+        }
+        
         // Отменяем текущую задачу
         if let Some(handle) = self.current_task.take() {
             handle.abort();
@@ -461,6 +702,14 @@ impl App {
         self.log("⏹ Операция остановлена");
     }
     
+    /// Остановить отправку на одного получателя, остальные продолжают
+    pub fn cancel_target(&mut self, index: usize) {
+        if let Some(target) = self.targets.get(index) {
+            self.target_cancels.cancel_target(index);
+            self.log(format!("⏹ Отправка на {} остановлена", target.address));
+        }
+    }
+    
     /// Сбросить флаг остановки перед новой операцией
     fn reset_stop_flag(&mut self) {
         self.stop_flag.store(false, Ordering::SeqCst);
@@ -486,10 +735,12 @@ impl App {
             }
         };
         
-        let target = if self.speedtest_target.contains(':') {
-            self.speedtest_target.clone()
-        } else {
-            format!("{}:{}", self.speedtest_target, port)
+        let target = match network::parse_target_addr(&self.speedtest_target, port) {
+            Ok(target) => target,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
         };
         
         let (tx, rx) = mpsc::unbounded_channel();
@@ -507,8 +758,9 @@ impl App {
         self.log(format!("Начинаем спидтест к {}", target));
         
         let size = network::DEFAULT_SPEEDTEST_SIZE;
+        let stop_flag = self.stop_flag.clone();
         let handle = self.runtime.spawn(async move {
-            let _ = network::run_speedtest(&target, size, tx).await;
+            let _ = network::run_speedtest_with_stop(&target, size, Default::default(), tx, stop_flag).await;
         });
         self.current_task = Some(handle);
     }
@@ -1,19 +1,21 @@
 //! Обработка событий от сетевого модуля
 
-use super::state::{App, TargetStatus};
-use toolza_sender::history::HistoryEntry;
-use toolza_sender::network::TransferEvent;
+use super::state::{App, IncomingFile, TargetStatus};
+use toolza_sender::extract::ExtractResult;
+use toolza_sender::history::{HistoryEntry, TargetOutcome};
+use toolza_sender::network::{SkipReason, TransferEvent, TransportType};
 use toolza_sender::protocol::FileStatus;
+use toolza_sender::utils::play_notification_sound;
 
 impl App {
-    /// Обработать все ожидающие события
-    pub fn process_events(&mut self) {
+    /// Обработать все ожидающие события. Возвращает true, если события были
+    pub fn process_events(&mut self) -> bool {
         // Обрабатываем dropped файлы
         self.process_dropped_files();
         
         // Собираем все события в вектор
         let events: Vec<TransferEvent> = {
-            let Some(rx) = &mut self.event_rx else { return };
+            let Some(rx) = &mut self.event_rx else { return false };
             let mut events = Vec::new();
             while let Ok(event) = rx.try_recv() {
                 events.push(event);
@@ -22,9 +24,11 @@ impl App {
         };
         
         // Обрабатываем события
+        let received = !events.is_empty();
         for event in events {
             self.handle_event(event);
         }
+        received
     }
     
     /// Обработать dropped файлы (Drag & Drop)
@@ -40,10 +44,12 @@ impl App {
                 if let Ok(info) = toolza_sender::protocol::FileInfo::new(path.clone()) {
                     if !self.files.iter().any(|f| f.path == path) {
                         self.files.push(info);
+                        self.resume_probe_stale = true;
                     }
                 }
             } else if path.is_dir() {
-                if let Ok(files) = toolza_sender::protocol::collect_files_from_folder(&path) {
+                let options = toolza_sender::protocol::CollectOptions { include_hidden: self.include_hidden, ..Default::default() };
+                if let Ok(files) = toolza_sender::protocol::collect_files_from_folder_with_options(&path, &options) {
                     let folder_name = path.file_name()
                         .map(|n| n.to_string_lossy().to_string())
                         .unwrap_or_else(|| "folder".to_string());
@@ -54,6 +60,7 @@ impl App {
                             self.files.push(file);
                         }
                     }
+                    self.resume_probe_stale = true;
                     self.log(format!("📁 Добавлена папка '{}': {} файл(ов)", folder_name, count));
                 }
             }
@@ -62,13 +69,26 @@ impl App {
     
     fn handle_event(&mut self, event: TransferEvent) {
         match event {
+            TransferEvent::TargetQueued(target_id) => {
+                self.on_target_queued(target_id);
+            }
+            TransferEvent::Connecting(target_id) => {
+                self.on_connecting(target_id);
+            }
+            TransferEvent::ConnectionRetrying(target_id, attempt) => {
+                self.on_connection_retrying(target_id, attempt);
+            }
             TransferEvent::Connected(target_id, addr) => {
                 self.on_connected(target_id, addr);
             }
+            TransferEvent::Handshaking(target_id) => {
+                self.on_handshaking(target_id);
+            }
             TransferEvent::FileStarted(target_id, file_idx) => {
                 self.on_file_started(target_id, file_idx);
             }
-            TransferEvent::Progress(_target_id, file_idx, transferred, original, compressed) => {
+            TransferEvent::Progress(target_id, file_idx, transferred, original, compressed) => {
+                self.mark_transferring(target_id);
                 self.on_progress(file_idx, transferred, original, compressed);
             }
             TransferEvent::FileCompleted(target_id, file_idx) => {
@@ -77,6 +97,11 @@ impl App {
             TransferEvent::FileError(target_id, file_idx, err) => {
                 self.on_file_error(target_id, file_idx, err);
             }
+            // Следом приходит FileError с причиной - он и попадёт в журнал
+            TransferEvent::FileCorrupted(..) => {}
+            TransferEvent::TargetChecksum(_, checksum) => {
+                self.transfer_checksum.get_or_insert(checksum);
+            }
             TransferEvent::TargetCompleted(target_id) => {
                 self.on_target_completed(target_id);
             }
@@ -86,30 +111,79 @@ impl App {
             TransferEvent::ConnectionError(target_id, err) => {
                 self.on_connection_error(target_id, err);
             }
-            TransferEvent::FileSkipped(target_id, file_idx) => {
-                self.on_file_skipped(target_id, file_idx);
+            TransferEvent::FileSkipped(target_id, file_idx, reason) => {
+                self.on_file_skipped(target_id, file_idx, reason);
             }
             TransferEvent::FileResumed(target_id, file_idx, offset) => {
                 self.on_file_resumed(target_id, file_idx, offset);
             }
-            TransferEvent::Disconnected => {
+            TransferEvent::FileDeduplicated(target_id, file_idx, original_idx) => {
+                self.on_file_deduplicated(target_id, file_idx, original_idx);
+            }
+            TransferEvent::FileWarning(_, file_idx, warning) => {
+                let name = self.files.get(file_idx).map(|f| f.name.as_str()).unwrap_or("?");
+                self.log(format!("⚠️ {}: {}", name, warning.description()));
+            }
+            TransferEvent::ClientConnected(addr, intent) => {
+                self.mark_activity();
+                self.log(format!("🔗 Подключено: {} ({})", addr, intent.name()));
+            }
+            TransferEvent::ClientDisconnected(..) => {
                 self.log("Клиент отключился");
+                self.last_activity = None;
+            }
+            TransferEvent::ClientProbed(_) => {
+                // Проверки доступности при сканировании сети - не шумим в логе
+            }
+            TransferEvent::FileReceiveStarted(file_idx, name, size) => {
+                self.on_file_receive_started(file_idx, name, size);
+            }
+            TransferEvent::FileReceiveEnded(file_idx) => {
+                self.incoming_files.retain(|f| f.file_idx != file_idx);
             }
             TransferEvent::FileReceived(name, size) => {
                 self.on_file_received(name, size);
             }
+            TransferEvent::DiskFull(name, kept) => {
+                self.on_disk_full(name, kept);
+            }
+            TransferEvent::FileRenamed(name, saved_as) => {
+                self.log(format!("✏️ {} сохранён как {}", name, saved_as));
+            }
+            TransferEvent::FileRejected(name, reason) => {
+                self.log(format!("🚫 Отклонён {}: {}", name, reason));
+            }
+            TransferEvent::FileDeleted(name) => {
+                self.log(format!("🗑 Удалён (синхронизация): {}", name));
+            }
             TransferEvent::ExtractionStarted(name) => {
                 self.on_extraction_started(name);
             }
-            TransferEvent::ExtractionCompleted(name, files_count, total_size) => {
-                self.on_extraction_completed(name, files_count, total_size);
+            TransferEvent::ExtractionCompleted(name, result) => {
+                self.on_extraction_completed(name, result);
             }
             TransferEvent::ExtractionError(name, err) => {
                 self.on_extraction_error(name, err);
             }
+            TransferEvent::ExtractionEntrySkipped(name, entry, reason) => {
+                self.log(format!("⚠️ {}: пропущен {} ({})", name, entry, reason));
+            }
+            TransferEvent::TransportFallback(target_id, transport_type, reason) => {
+                self.on_transport_fallback(target_id, transport_type, reason);
+            }
+            TransferEvent::HookCompleted(path, output) => {
+                self.on_hook_completed(path, output);
+            }
+            TransferEvent::HookError(path, err) => {
+                self.log(format!("❌ Post-hook для {}: {}", path, err));
+            }
             TransferEvent::ServerFound(addr) => {
                 self.on_server_found(addr);
             }
+            TransferEvent::ServerFoundNamed(addr, hostname) => {
+                self.log(format!("🏷 {}: {}", addr, hostname));
+                self.server_names.insert(addr, hostname);
+            }
             TransferEvent::ScanProgress(current_ip, progress) => {
                 self.on_scan_progress(current_ip, progress);
             }
@@ -128,18 +202,57 @@ impl App {
             TransferEvent::SpeedTestError(err) => {
                 self.on_speedtest_error(err);
             }
+            TransferEvent::SpeedTestCancelled => {
+                self.on_speedtest_cancelled();
+            }
         }
     }
     
     // === Обработчики событий отправки ===
     
+    fn on_target_queued(&mut self, target_id: usize) {
+        if let Some(target) = self.targets.get(target_id) {
+            self.log(format!("⏳ {} в очереди: ждёт свободного слота", target.address));
+        }
+    }
+    
+    fn on_connecting(&mut self, target_id: usize) {
+        self.mark_activity();
+        if let Some(target) = self.targets.get_mut(target_id) {
+            target.set_status(TargetStatus::Connecting);
+        }
+    }
+    
+    fn on_connection_retrying(&mut self, target_id: usize, attempt: u32) {
+        self.mark_activity();
+        if let Some(target) = self.targets.get(target_id) {
+            self.log(format!("🔁 {}: получатель недоступен, повтор #{}", target.address, attempt));
+        }
+    }
+    
     fn on_connected(&mut self, target_id: usize, addr: String) {
-        if target_id < self.targets.len() {
-            self.targets[target_id].status = TargetStatus::Transferring;
+        self.mark_activity();
+        if let Some(target) = self.targets.get_mut(target_id) {
+            target.set_status(TargetStatus::Transferring);
         }
         self.log(format!("🔗 Подключено: {}", addr));
     }
     
+    fn on_handshaking(&mut self, target_id: usize) {
+        if let Some(target) = self.targets.get_mut(target_id) {
+            target.set_status(TargetStatus::Handshaking);
+        }
+    }
+    
+    /// Первый ответ получателя завершает согласование
+    fn mark_transferring(&mut self, target_id: usize) {
+        if let Some(target) = self.targets.get_mut(target_id) {
+            if target.status == TargetStatus::Handshaking {
+                target.set_status(TargetStatus::Transferring);
+            }
+        }
+    }
+    
     fn on_file_started(&mut self, target_id: usize, file_idx: usize) {
         if target_id < self.targets.len() {
             self.targets[target_id].current_file = file_idx;
@@ -156,11 +269,19 @@ impl App {
     }
     
     fn on_progress(&mut self, file_idx: usize, transferred: u64, original: u64, compressed: u64) {
+        self.mark_activity();
         if file_idx < self.files.len() {
             if transferred > self.files[file_idx].transferred {
                 self.files[file_idx].transferred = transferred;
             }
         }
+        // Приёмная сторона: прогресс по каждому принимаемому файлу
+        if self.files.is_empty() {
+            if let Some(incoming) = self.incoming_files.iter_mut().find(|f| f.file_idx == file_idx) {
+                incoming.received = transferred;
+            }
+            self.incoming_files.retain(|f| f.received < f.size);
+        }
         
         // Обновляем общую статистику
         self.bytes_original = self.bytes_original.max(original);
@@ -184,8 +305,8 @@ impl App {
         self.stats.update(total_transferred, original, compressed);
         
         // Обновляем статус
-        let speed = self.stats.speed_formatted();
-        let eta = self.stats.eta_formatted();
+        let speed = self.stats.speed_formatted_in(self.language);
+        let eta = self.stats.eta_clock_formatted_in(self.language);
         let progress_str = if original > 0 {
             let pct = (transferred as f64 / original as f64 * 100.0).min(100.0);
             format!(" ({:.1}%)", pct)
@@ -196,6 +317,7 @@ impl App {
     }
     
     fn on_file_completed(&mut self, target_id: usize, file_idx: usize) {
+        self.mark_transferring(target_id);
         if target_id < self.targets.len() {
             self.targets[target_id].files_completed += 1;
         }
@@ -212,7 +334,8 @@ impl App {
         }
     }
     
-    fn on_file_skipped(&mut self, target_id: usize, file_idx: usize) {
+    fn on_file_skipped(&mut self, target_id: usize, file_idx: usize, reason: SkipReason) {
+        self.mark_transferring(target_id);
         if target_id < self.targets.len() {
             self.targets[target_id].files_completed += 1;
         }
@@ -221,12 +344,28 @@ impl App {
             self.files[file_idx].status = FileStatus::Completed;
             self.files[file_idx].transferred = self.files[file_idx].size;
             let name = &self.files[file_idx].name;
-            self.log(format!("⏭️ Пропущен (актуален): {}", name));
+            self.log(format!("⏭️ Пропущен ({}): {}", reason.description(), name));
+            self.stats.file_completed();
+        }
+    }
+    
+    fn on_file_deduplicated(&mut self, target_id: usize, file_idx: usize, original_idx: usize) {
+        self.mark_transferring(target_id);
+        if target_id < self.targets.len() {
+            self.targets[target_id].files_completed += 1;
+        }
+        
+        if file_idx < self.files.len() {
+            self.files[file_idx].status = FileStatus::Completed;
+            self.files[file_idx].transferred = self.files[file_idx].size;
+            let original = self.files.get(original_idx).map(|f| f.name.clone()).unwrap_or_default();
+            self.log(format!("🔗 Дубликат {}: {}", original, self.files[file_idx].name));
             self.stats.file_completed();
         }
     }
     
     fn on_file_resumed(&mut self, target_id: usize, file_idx: usize, offset: u64) {
+        self.mark_transferring(target_id);
         if file_idx < self.files.len() {
             self.files[file_idx].transferred = offset;
             let name = &self.files[file_idx].name;
@@ -245,6 +384,14 @@ impl App {
             .map(|t| t.address.as_str())
             .unwrap_or("?");
         self.log(format!("❌ Ошибка {} → {}: {}", file_name, target_addr, err));
+        
+        // Учитываем файл как обработанный, чтобы не сбить подсчёт по следующим
+        if target_id < self.targets.len() {
+            self.targets[target_id].files_completed += 1;
+        }
+        if file_idx < self.files.len() {
+            self.files[file_idx].status = FileStatus::Error(err);
+        }
     }
     
     fn on_target_completed(&mut self, target_id: usize) {
@@ -261,6 +408,7 @@ impl App {
     
     fn on_all_completed(&mut self) {
         self.is_running = false;
+        self.notify_completion();
         
         let successful = self.targets.iter()
             .filter(|t| t.status == TargetStatus::Completed)
@@ -282,6 +430,13 @@ impl App {
             .filter(|t| t.status == TargetStatus::Completed)
             .map(|t| t.address.clone())
             .collect();
+        let outcomes = self.targets.iter()
+            .map(|t| match &t.status {
+                TargetStatus::Completed => TargetOutcome::new(t.address.clone(), true, None),
+                TargetStatus::Error(e) => TargetOutcome::new(t.address.clone(), false, Some(e.clone())),
+                _ => TargetOutcome::new(t.address.clone(), false, None),
+            })
+            .collect();
         
         let entry = HistoryEntry::new_send(
             self.files.len(),
@@ -291,12 +446,14 @@ impl App {
             addresses,
             successful > 0,
             None,
-        );
+        ).with_checksum(self.transfer_checksum)
+        .with_throughput(self.stats.throughput_summary())
+        .with_targets(outcomes);
         self.history.add(entry);
         
         // Форматируем статистику
-        let elapsed = self.stats.elapsed_formatted();
-        let speed = self.stats.speed_formatted();
+        let elapsed = self.stats.elapsed_formatted_in(self.language);
+        let speed = self.stats.speed_formatted_in(self.language);
         let compression = if self.use_compression && compression_ratio < 0.99 {
             format!(", сжатие {:.0}%", (1.0 - compression_ratio) * 100.0)
         } else {
@@ -305,9 +462,20 @@ impl App {
         
         self.status_message = format!("✅ Готово! {} за {}{}", speed, elapsed, compression);
         self.log(format!("Передача завершена: {} файлов за {}", self.files.len(), elapsed));
+        if let Some(throughput) = self.stats.throughput_summary() {
+            self.log(format!("📈 Скорость: {}", throughput.formatted_in(self.language)));
+        }
+        
+        let failed: Vec<String> = self.files.iter()
+            .filter(|f| matches!(f.status, FileStatus::Error(_)))
+            .map(|f| f.name.clone())
+            .collect();
+        if !failed.is_empty() {
+            self.log(format!("⚠️ Не отправлено {} файл(ов): {}", failed.len(), failed.join(", ")));
+        }
         
         if successful > 0 {
-            for file in &mut self.files {
+            for file in self.files.iter_mut().filter(|f| !matches!(f.status, FileStatus::Error(_))) {
                 file.status = FileStatus::Completed;
                 file.transferred = file.size;
             }
@@ -315,30 +483,63 @@ impl App {
     }
     
     fn on_connection_error(&mut self, target_id: usize, err: String) {
+        self.transport_error = true;
         if target_id < self.targets.len() {
             self.targets[target_id].status = TargetStatus::Error(err.clone());
             self.log(format!("❌ Ошибка {}: {}", self.targets[target_id].address, err));
         }
     }
     
+    fn on_transport_fallback(&mut self, target_id: usize, transport_type: TransportType, reason: String) {
+        if let Some(target) = self.targets.get(target_id) {
+            self.log(format!("🔀 {}: {} - переключение на {}", target.address, reason, transport_type.name()));
+        }
+        self.mark_activity();
+    }
+    
     // === Обработчики событий приёма ===
     
+    fn on_file_receive_started(&mut self, file_idx: usize, name: String, size: u64) {
+        self.mark_activity();
+        self.incoming_files.push(IncomingFile { file_idx, name, size, received: 0 });
+    }
+    
     fn on_file_received(&mut self, name: String, size: u64) {
+        // Строка прогресса больше не нужна (под другим именем её уберёт FileReceiveEnded)
+        if let Some(pos) = self.incoming_files.iter().position(|f| f.name == name) {
+            self.incoming_files.remove(pos);
+        }
         self.received_files.push((name.clone(), size));
         self.log(format!("📥 Получен: {} ({})", name, format_size(size)));
     }
     
+    fn on_disk_full(&mut self, name: String, kept: u64) {
+        self.status_message = format!("💾 Диск заполнен: {}", name);
+        self.log(format!("💾 Диск заполнен: {} сохранён частично ({}), освободите место и повторите отправку", name, format_size(kept)));
+        self.disk_full = Some((name, kept));
+    }
+    
     fn on_extraction_started(&mut self, name: String) {
         self.status_message = format!("📦 Распаковка {}...", name);
         self.log(format!("📦 Распаковка: {}", name));
     }
     
-    fn on_extraction_completed(&mut self, name: String, files_count: usize, total_size: u64) {
+    /// Звук завершения, если включён в настройках
+    fn notify_completion(&self) {
+        if self.settings.completion_sound {
+            play_notification_sound(self.settings.completion_sound_path.as_deref());
+        }
+    }
+    
+    fn on_extraction_completed(&mut self, name: String, result: ExtractResult) {
         self.status_message = "Ожидание подключений...".to_string();
-        self.log(format!("✅ Распаковано {}: {} файл(ов), {}", name, files_count, format_size(total_size)));
+        self.log(format!("✅ Распаковано {}: {}, {}", name, result.summary(), format_size(result.total_size)));
+        self.notify_completion();
         // Обновляем финальное состояние окна
-        self.extraction_files_count = files_count;
-        self.extraction_total_size = total_size;
+        self.extraction_files_count = result.files_count;
+        self.extraction_total_size = result.total_size;
+        self.extraction_dirs_count = result.dirs_count;
+        self.extraction_symlinks_count = result.symlinks_count;
         self.extraction_current_file = String::new();
         // Закрываем окно через небольшую задержку (можно закрыть сразу или через таймер)
         // Пока оставим открытым, чтобы пользователь видел результат
@@ -351,6 +552,14 @@ impl App {
         self.extraction_window_open = false;
     }
     
+    fn on_hook_completed(&mut self, path: String, output: String) {
+        if output.is_empty() {
+            self.log(format!("🪝 Post-hook выполнен: {}", path));
+        } else {
+            self.log(format!("🪝 Post-hook выполнен: {}\n{}", path, output));
+        }
+    }
+    
     // === Обработчики событий сканирования ===
     
     fn on_server_found(&mut self, addr: String) {
@@ -417,6 +626,13 @@ impl App {
         self.status_message = format!("❌ Ошибка спидтеста: {}", err);
         self.log(format!("Ошибка спидтеста: {}", err));
     }
+    
+    fn on_speedtest_cancelled(&mut self) {
+        self.speedtest_running = false;
+        self.event_rx = None;
+        self.status_message = "Остановлено".to_string();
+        self.log("⏹ Спидтест отменён");
+    }
 }
 
 fn format_size(bytes: u64) -> String {
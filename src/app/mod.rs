@@ -4,6 +4,6 @@ mod state;
 mod actions;
 mod event_handler;
 
-pub use state::{App, Mode, TargetStatus};
+pub use state::{App, HistoryFilter, HistoryPeriod, Mode, TargetStatus, TransportHealth};
 // DialogResult используется внутри модуля actions
 
@@ -1,11 +1,14 @@
 //! Состояние приложения
 
+use toolza_sender::extract::ArchiveEntry;
 use toolza_sender::history::TransferHistory;
+use toolza_sender::settings::Settings;
 use toolza_sender::i18n::{Language, Translations, t};
-use toolza_sender::network::{TransferEvent, TransportType};
+use toolza_sender::network::{TargetCancels, TransferEvent, TransportType};
 use toolza_sender::protocol::{FileInfo, DEFAULT_PORT};
 use toolza_sender::stats::TransferStats;
-use toolza_sender::utils::get_local_ip_string;
+use toolza_sender::utils::{format_size_with, get_local_ip_string, UnitStyle};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
@@ -21,6 +24,18 @@ pub enum DialogResult {
     ArchiveFile(PathBuf),
     ExtractDestination(PathBuf),
     ExtractComplete(String),
+    ArchiveListing(Result<Vec<ArchiveEntry>, String>),
+    HistoryCsv(PathBuf),
+    CompletionSound(PathBuf),
+    /// Ответ получателя на проверку докачки: (ключ набора файлов, адрес, offset'ы)
+    ResumeProbe(u64, String, Result<Vec<u64>, String>),
+}
+
+/// Что из выбранных файлов уже есть у получателя
+pub struct ResumeProbe {
+    pub target: String,
+    /// Offset докачки по файлам (0 - с начала, size - уже получен)
+    pub offsets: Vec<u64>,
 }
 
 /// Режим работы приложения
@@ -33,6 +48,38 @@ pub enum Mode {
     SpeedTest,
 }
 
+/// Какие передачи показывать в истории
+#[derive(PartialEq, Clone, Copy, Default)]
+pub enum HistoryFilter {
+    #[default]
+    All,
+    Sent,
+    Received,
+    Failed,
+}
+
+/// За какой срок показывать историю
+#[derive(PartialEq, Clone, Copy, Default)]
+pub enum HistoryPeriod {
+    #[default]
+    All,
+    Day,
+    Week,
+    Month,
+}
+
+impl HistoryPeriod {
+    /// Длина срока в секундах (None - без ограничения)
+    pub fn secs(self) -> Option<u64> {
+        match self {
+            HistoryPeriod::All => None,
+            HistoryPeriod::Day => Some(86400),
+            HistoryPeriod::Week => Some(7 * 86400),
+            HistoryPeriod::Month => Some(30 * 86400),
+        }
+    }
+}
+
 /// Информация о получателе
 #[derive(Clone)]
 pub struct TargetInfo {
@@ -40,6 +87,8 @@ pub struct TargetInfo {
     pub status: TargetStatus,
     pub current_file: usize,
     pub files_completed: usize,
+    /// Момент перехода в текущий статус
+    pub status_since: Option<std::time::Instant>,
 }
 
 impl TargetInfo {
@@ -49,14 +98,34 @@ impl TargetInfo {
             status: TargetStatus::Pending,
             current_file: 0,
             files_completed: 0,
+            status_since: None,
         }
     }
     
     pub fn reset(&mut self) {
-        self.status = TargetStatus::Connecting;
+        self.set_status(TargetStatus::Connecting);
         self.current_file = 0;
         self.files_completed = 0;
     }
+    
+    pub fn set_status(&mut self, status: TargetStatus) {
+        self.status = status;
+        self.status_since = Some(std::time::Instant::now());
+    }
+    
+    /// Сколько секунд получатель находится в текущем статусе
+    pub fn status_secs(&self) -> u64 {
+        self.status_since.map(|t| t.elapsed().as_secs()).unwrap_or(0)
+    }
+}
+
+/// Файл, который сейчас принимается
+#[derive(Clone)]
+pub struct IncomingFile {
+    pub file_idx: usize,
+    pub name: String,
+    pub size: u64,
+    pub received: u64,
 }
 
 /// Статус получателя
@@ -64,15 +133,32 @@ impl TargetInfo {
 pub enum TargetStatus {
     Pending,
     Connecting,
+    Handshaking,
     Transferring,
     Completed,
     Error(String),
 }
 
+/// Через сколько без прогресса передача считается зависшей
+pub const STALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Состояние соединения для индикатора транспорта
+#[derive(Clone, Copy, PartialEq)]
+pub enum TransportHealth {
+    /// Нет активной передачи
+    Idle,
+    /// Данные идут
+    Flowing,
+    /// Нет прогресса дольше STALL_TIMEOUT
+    Stalled,
+    Error,
+}
+
 /// Главная структура приложения
 pub struct App {
-    // Язык интерфейса
+    // Язык интерфейса и его переводы (встроенные или из lang/<код>.toml)
     pub language: Language,
+    pub translations: &'static Translations,
     
     // Режим работы
     pub mode: Mode,
@@ -84,10 +170,24 @@ pub struct App {
     pub files: Vec<FileInfo>,
     /// Использовать LZ4 сжатие при передаче
     pub use_compression: bool,
+    /// Решать о сжатии по каждому файлу (при включённом LZ4)
+    pub auto_compression: bool,
     /// Сохранять структуру папок при передаче
     pub preserve_structure: bool,
     /// Режим синхронизации (передавать только изменённые файлы)
     pub sync_mode: bool,
+    /// Добавлять скрытые/системные файлы при выборе папки
+    pub include_hidden: bool,
+    /// Поле ввода дневного лимита отправки (пусто - без лимита)
+    pub daily_quota_input: String,
+    /// Имя единственного файла у получателя (пусто - как есть)
+    pub output_name_input: String,
+    /// Найденная у первого получателя докачка (None - нечего докачивать)
+    pub resume_probe: Option<ResumeProbe>,
+    /// Ключ последней запрошенной проверки докачки
+    pub resume_probe_key: u64,
+    /// Файлы, получатели или протокол изменились - проверку докачки нужно повторить
+    pub resume_probe_stale: bool,
     /// Тип транспортного протокола (TCP/QUIC)
     pub transport_type: TransportType,
     
@@ -95,12 +195,18 @@ pub struct App {
     pub listen_port: String,
     pub save_directory: PathBuf,
     pub received_files: Vec<(String, u64)>,
+    /// Принимаемые сейчас файлы (по FileReceiveStarted и Progress)
+    pub incoming_files: Vec<IncomingFile>,
+    /// Диск заполнился посреди файла: (имя, сохранено байт) - до закрытия предупреждения
+    pub disk_full: Option<(String, u64)>,
     /// Автоматически распаковывать tar.lz4 архивы
     pub auto_extract_tar_lz4: bool,
     /// Автоматически распаковывать tar.zst архивы
     pub auto_extract_tar_zst: bool,
     /// Автоматически распаковывать .lz4 файлы (не tar)
     pub auto_extract_lz4: bool,
+    /// Автоматически распаковывать .zst файлы (не tar)
+    pub auto_extract_zst: bool,
     /// Автоматически распаковывать tar/tar.gz архивы
     pub auto_extract_tar: bool,
     /// Автоматически распаковывать zip архивы
@@ -109,6 +215,8 @@ pub struct App {
     pub auto_extract_rar: bool,
     /// Сохранять архив при потоковой распаковке (для резюме)
     pub save_archive_for_resume: bool,
+    /// Сколько ведущих папок отбрасывать при распаковке
+    pub extract_strip_components: usize,
     
     // === Общее состояние ===
     pub is_running: bool,
@@ -119,6 +227,8 @@ pub struct App {
     pub is_scanning: bool,
     pub scan_progress: u8,
     pub found_servers: Vec<String>,
+    /// Имена найденных серверов по обратному DNS (адрес -> имя)
+    pub server_names: HashMap<String, String>,
     pub local_ip: String,
     /// Подсети для сканирования (пустая строка = автоопределение)
     pub subnets_input: String,
@@ -126,13 +236,25 @@ pub struct App {
     // === Статистика ===
     pub stats: TransferStats,
     pub transfer_start_time: Option<Instant>,
+    /// Последняя активность соединения (подключение, прогресс)
+    pub last_activity: Option<Instant>,
+    /// Последнее событие соединения - ошибка
+    pub transport_error: bool,
     /// Байты до сжатия (оригинал)
     pub bytes_original: u64,
     /// Байты после сжатия (по сети)
     pub bytes_compressed: u64,
+    /// Контрольная сумма переданного содержимого (от первого получателя)
+    pub transfer_checksum: Option<u64>,
     
     // === История ===
     pub history: TransferHistory,
+    /// Фильтры просмотра истории: вид передач, срок и поиск по адресу
+    pub history_filter: HistoryFilter,
+    pub history_period: HistoryPeriod,
+    pub history_search: String,
+    /// Сохраняемые настройки интерфейса
+    pub settings: Settings,
     
     // === Drag & Drop ===
     pub dropped_files: Vec<PathBuf>,
@@ -155,6 +277,12 @@ pub struct App {
     pub extract_result: Option<String>,
     /// Флаг остановки распаковки
     pub extract_stop_flag: Arc<AtomicBool>,
+    /// Содержимое выбранного архива (None - ещё не загружено)
+    pub extract_listing: Option<Vec<ArchiveEntry>>,
+    /// Идёт чтение содержимого архива
+    pub extract_listing_running: bool,
+    /// Отмеченные в списке файлы (пусто - распаковать всё)
+    pub extract_selected: HashSet<String>,
     
     // === Окно распаковки на лету ===
     /// Показывать окно распаковки
@@ -165,6 +293,10 @@ pub struct App {
     pub extraction_files_count: usize,
     /// Общий размер распакованных данных
     pub extraction_total_size: u64,
+    /// Количество созданных папок
+    pub extraction_dirs_count: usize,
+    /// Количество созданных символических ссылок
+    pub extraction_symlinks_count: usize,
     /// Текущий файл в процессе распаковки
     pub extraction_current_file: String,
     
@@ -173,6 +305,8 @@ pub struct App {
     pub event_rx: Option<mpsc::UnboundedReceiver<TransferEvent>>,
     /// Флаг для остановки текущей операции
     pub stop_flag: Arc<AtomicBool>,
+    /// Остановка отдельных получателей текущей отправки
+    pub target_cancels: TargetCancels,
     /// Handle текущей задачи для возможности отмены
     pub current_task: Option<JoinHandle<()>>,
     
@@ -186,46 +320,70 @@ impl App {
         let save_dir = dirs::download_dir()
             .unwrap_or_else(|| PathBuf::from("."));
         
-        // Загружаем историю
+        // Загружаем историю и настройки
         let history = TransferHistory::load();
+        let settings = Settings::load();
         
         // Канал для результатов файловых диалогов
         let (dialog_tx, dialog_rx) = mpsc::unbounded_channel();
+        let language = Language::from_system_locale();
         
-        Self {
-            language: Language::default(),
+        let mut app = Self {
+            language,
+            translations: t(language),
             mode: Mode::Send,
             new_target_address: String::new(),
             target_port: DEFAULT_PORT.to_string(),
             targets: Vec::new(),
             files: Vec::new(),
             use_compression: false,
+            auto_compression: false,
             preserve_structure: false,
             sync_mode: false,
+            include_hidden: true,
+            daily_quota_input: settings.daily_quota
+                .map(|limit| format_size_with(limit, UnitStyle::Binary, Language::English))
+                .unwrap_or_default(),
+            output_name_input: String::new(),
+            resume_probe: None,
+            resume_probe_key: 0,
+            resume_probe_stale: true,
             transport_type: TransportType::default(),
             listen_port: DEFAULT_PORT.to_string(),
             save_directory: save_dir.clone(),
             received_files: Vec::new(),
+            incoming_files: Vec::new(),
+            disk_full: None,
             auto_extract_tar_lz4: false,
             auto_extract_tar_zst: false,
             auto_extract_lz4: false,
+            auto_extract_zst: false,
             auto_extract_tar: false,
             auto_extract_zip: false,
             auto_extract_rar: false,
             save_archive_for_resume: false,
+            extract_strip_components: 0,
             is_running: false,
             status_message: String::new(),
             log_messages: Vec::new(),
             is_scanning: false,
             scan_progress: 0,
             found_servers: Vec::new(),
+            server_names: HashMap::new(),
             local_ip: get_local_ip_string(),
             subnets_input: String::new(),
             stats: TransferStats::default(),
             transfer_start_time: None,
+            last_activity: None,
+            transport_error: false,
             bytes_original: 0,
             bytes_compressed: 0,
+            transfer_checksum: None,
             history,
+            history_filter: HistoryFilter::default(),
+            history_period: HistoryPeriod::default(),
+            history_search: String::new(),
+            settings,
             dropped_files: Vec::new(),
             speedtest_target: String::new(),
             speedtest_running: false,
@@ -237,18 +395,39 @@ impl App {
             extract_running: false,
             extract_result: None,
             extract_stop_flag: Arc::new(AtomicBool::new(false)),
+            extract_listing: None,
+            extract_listing_running: false,
+            extract_selected: HashSet::new(),
             extraction_window_open: false,
             extraction_filename: String::new(),
             extraction_files_count: 0,
             extraction_total_size: 0,
+            extraction_dirs_count: 0,
+            extraction_symlinks_count: 0,
             extraction_current_file: String::new(),
             runtime: tokio::runtime::Runtime::new().unwrap(),
             event_rx: None,
             stop_flag: Arc::new(AtomicBool::new(false)),
+            target_cancels: TargetCancels::new(),
             current_task: None,
             dialog_tx,
             dialog_rx: Some(dialog_rx),
-        }
+        };
+        app.set_language(language);
+        app
+    }
+    
+    /// Сменить язык интерфейса. Файл перевода с ошибкой пишется в лог,
+    /// тогда остаются встроенные переводы
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+        self.translations = match Translations::load(language) {
+            Ok(translations) => translations,
+            Err(e) => {
+                self.log(format!("⚠️ Файл перевода не загружен: {}", e));
+                t(language)
+            }
+        };
     }
     
     /// Добавить сообщение в лог
@@ -261,37 +440,31 @@ impl App {
         !self.is_running && !self.is_scanning
     }
     
-    /// Получить текущую скорость передачи
-    pub fn current_speed(&self) -> String {
-        if self.is_running {
-            self.stats.speed_formatted()
-        } else {
-            "—".to_string()
+    /// Состояние соединения по последним событиям
+    pub fn transport_health(&self) -> TransportHealth {
+        if !self.is_running {
+            return TransportHealth::Idle;
+        }
+        if self.transport_error {
+            return TransportHealth::Error;
+        }
+        match self.last_activity {
+            None => TransportHealth::Idle,
+            Some(at) if at.elapsed() > STALL_TIMEOUT => TransportHealth::Stalled,
+            Some(_) => TransportHealth::Flowing,
         }
     }
     
-    /// Получить ETA
-    pub fn current_eta(&self) -> String {
-        if self.is_running {
-            self.stats.eta_formatted()
-        } else {
-            "—".to_string()
-        }
+    /// Отметить активность соединения
+    pub fn mark_activity(&mut self) {
+        self.last_activity = Some(Instant::now());
+        self.transport_error = false;
     }
     
-    /// Получить статистику сжатия
-    pub fn compression_stats(&self) -> String {
-        if self.bytes_original > 0 && self.use_compression {
-            let ratio = self.bytes_compressed as f64 / self.bytes_original as f64;
-            if ratio < 0.99 {
-                let saved = (1.0 - ratio) * 100.0;
-                format!("{:.1}% экономия", saved)
-            } else {
-                "без эффекта".to_string()
-            }
-        } else {
-            "—".to_string()
-        }
+    /// Сбросить индикатор соединения перед новой операцией
+    pub fn reset_activity(&mut self) {
+        self.last_activity = None;
+        self.transport_error = false;
     }
     
     /// Сбросить статистику для новой передачи
@@ -301,6 +474,7 @@ impl App {
         self.transfer_start_time = Some(Instant::now());
         self.bytes_original = 0;
         self.bytes_compressed = 0;
+        self.transfer_checksum = None;
     }
 }
 
@@ -313,6 +487,11 @@ impl Default for App {
 impl App {
     /// Получить переводы для текущего языка
     pub fn t(&self) -> &'static Translations {
-        t(self.language)
+        self.translations
+    }
+    
+    /// Размер с подписями единиц на языке интерфейса
+    pub fn format_size(&self, size: u64) -> String {
+        format_size_with(size, UnitStyle::Binary, self.language)
     }
 }
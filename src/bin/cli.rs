@@ -1,11 +1,56 @@
 //! Toolza CLI - консольная версия для передачи файлов
 
-use clap::{Parser, Subcommand, ValueEnum};
-use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use toolza_sender::network::{self, TransferEvent, TransportType};
-use toolza_sender::protocol::{FileInfo, collect_files_from_folder, DEFAULT_PORT};
-use toolza_sender::utils::{format_size, get_local_ip_string};
+use toolza_sender::extract::{self, PathLimits, DEFAULT_MAX_PATH_COMPONENTS, DEFAULT_MAX_PATH_LEN};
+use toolza_sender::i18n::Language;
+use toolza_sender::network::{self, JsonEvent, SocketTuning, TransferEvent, TransportType};
+use toolza_sender::pattern;
+use toolza_sender::protocol::{CollectOptions, FileInfo, collect_files_from_folder_with_options, DEFAULT_PORT};
+use toolza_sender::stats::TransferStats;
+use toolza_sender::utils::{format_size, get_local_ip_string, parse_selection, parse_size};
+
+/// Режим `--json`: stdout занят событиями (строка JSON на событие),
+/// поэтому текст для человека уходит в stderr
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Строка текста для человека: в stdout, а в режиме `--json` - в stderr
+macro_rules! human {
+    ($($arg:tt)*) => {
+        if JSON_OUTPUT.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Как `human!`, но без перевода строки (запросы и строка прогресса)
+macro_rules! human_inline {
+    ($($arg:tt)*) => {
+        if JSON_OUTPUT.load(Ordering::Relaxed) {
+            eprint!($($arg)*)
+        } else {
+            print!($($arg)*)
+        }
+    };
+}
+
+/// Вывести событие строкой JSON, если включён `--json`
+fn emit_json(event: &TransferEvent) {
+    if !JSON_OUTPUT.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Ok(line) = serde_json::to_string(&JsonEvent::from(event)) {
+        println!("{}", line);
+    }
+}
 
 /// Тип транспорта для CLI
 #[derive(Clone, Copy, Debug, ValueEnum, Default)]
@@ -36,12 +81,42 @@ impl From<Transport> for TransportType {
     }
 }
 
+/// Настройки TCP-сокета (send, receive, speedtest)
+#[derive(Args, Clone, Copy)]
+struct TuningArgs {
+    /// Буфер отправки сокета SO_SNDBUF (например: 4MB); помогает на каналах с большим RTT
+    #[arg(long, value_name = "SIZE", value_parser = parse_byte_size)]
+    send_buf: Option<usize>,
+    
+    /// Буфер приёма сокета SO_RCVBUF (например: 4MB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_byte_size)]
+    recv_buf: Option<usize>,
+    
+    /// Не выставлять TCP_NODELAY (оставить алгоритм Нейгла)
+    #[arg(long)]
+    no_nodelay: bool,
+}
+
+impl From<TuningArgs> for SocketTuning {
+    fn from(args: TuningArgs) -> Self {
+        SocketTuning {
+            nodelay: !args.no_nodelay,
+            send_buf: args.send_buf,
+            recv_buf: args.recv_buf,
+        }
+    }
+}
+
 #[derive(Parser)]
 #[command(name = "toolza_cli")]
 #[command(author = "toolza")]
 #[command(version = "1.0")]
 #[command(about = "Быстрая передача файлов по локальной сети", long_about = None)]
 struct Cli {
+    /// Печатать события строками JSON в stdout (ndjson), текст - в stderr
+    #[arg(long, global = true)]
+    json: bool,
+    
     #[command(subcommand)]
     command: Commands,
 }
@@ -54,10 +129,30 @@ enum Commands {
         #[arg(short, long, value_delimiter = ',')]
         targets: Vec<String>,
         
+        /// Найти получателей сканированием сети и выбрать из списка (вместо -t)
+        #[arg(long, conflicts_with = "targets")]
+        discover: bool,
+        
         /// Файлы и папки для отправки
-        #[arg(required = true)]
+        #[arg(required_unless_present = "from_file")]
         files: Vec<PathBuf>,
         
+        /// Список путей из файла: строка - путь (можно `путь => имя/у/получателя`), # - комментарий
+        #[arg(long)]
+        from_file: Option<PathBuf>,
+        
+        /// Пропускать отсутствующие пути из --from-file вместо ошибки
+        #[arg(long, requires = "from_file")]
+        skip_missing: bool,
+        
+        /// Отправлять только файлы, чей путь у получателя совпал с шаблоном (можно несколько: docs/**)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+        
+        /// Не отправлять файлы, чей путь у получателя совпал с шаблоном (можно несколько: **/*.tmp)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        
         /// Порт (по умолчанию 9527)
         #[arg(short, long, default_value_t = DEFAULT_PORT)]
         port: u16,
@@ -66,6 +161,14 @@ enum Commands {
         #[arg(short = 'c', long)]
         compress: bool,
         
+        /// Сжимать только то, что сжимается (решение по каждому файлу)
+        #[arg(long, conflicts_with = "compress")]
+        auto_compress: bool,
+        
+        /// Сжимать zstd с указанным уровнем вместо LZ4 (включает сжатие)
+        #[arg(long, value_name = "LEVEL")]
+        zstd: Option<i32>,
+        
         /// Не сохранять структуру папок (все файлы в одну папку)
         #[arg(long)]
         flat: bool,
@@ -74,6 +177,105 @@ enum Commands {
         #[arg(short = 's', long)]
         sync: bool,
         
+        /// Только показать, что передаст синхронизация, ничего не отправляя
+        #[arg(long, requires = "sync")]
+        dry_run: bool,
+        
+        /// С --sync: удалить у получателя файлы, которых нет у отправителя
+        #[arg(long, requires = "sync")]
+        delete: bool,
+        
+        /// Пропускать скрытые и системные файлы в папках
+        #[arg(long)]
+        skip_hidden: bool,
+        
+        /// Имя файла у получателя (только для одного файла), можно с подпутём: docs/report.txt
+        #[arg(long, visible_alias = "as")]
+        output_name: Option<String>,
+        
+        /// Передавать одинаковые файлы заново (без дедупликации)
+        #[arg(long)]
+        no_dedupe: bool,
+        
+        /// Перед дедупликацией сравнивать файлы побайтно
+        #[arg(long, conflicts_with = "no_dedupe")]
+        verify_dedupe: bool,
+        
+        /// Фиксированный размер чанка без адаптации (например: 1MB, 256K)
+        #[arg(long, value_parser = parse_byte_size)]
+        chunk_size: Option<usize>,
+        
+        /// Таймаут подключения к получателю в секундах
+        #[arg(long, default_value_t = 10)]
+        connect_timeout: u64,
+        
+        /// Повторить неудавшееся подключение до N раз (например, пока получатель загружается)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retries: u32,
+        
+        /// Пауза перед первым повтором в секундах, дальше удваивается (до 60 с)
+        #[arg(long, value_name = "SECS", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        retry_delay: u64,
+        
+        /// Обрывать передачу, если получатель молчит дольше N секунд (по умолчанию ждать без ограничения)
+        #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+        timeout: Option<u64>,
+        
+        /// Не прерывать передачу, если файл не удалось открыть
+        #[arg(long)]
+        continue_on_error: bool,
+        
+        /// Посчитать контрольную сумму переданного содержимого
+        #[arg(long)]
+        checksum: bool,
+        
+        /// Просить получателя не распаковывать архивы (сохранить как есть)
+        #[arg(long)]
+        no_extract: bool,
+        
+        /// Не распаковывать у получателя только архивы, чей путь у получателя совпал с шаблоном (можно несколько)
+        #[arg(long, value_name = "GLOB")]
+        keep_packed: Vec<String>,
+        
+        /// Компактная раскладка кадров, если получатель её поддерживает (меньше накладных расходов на мелких файлах)
+        #[arg(long)]
+        compact: bool,
+        
+        /// Передать расширенные атрибуты user.* - только Unix; получатель должен быть запущен с receive --xattrs
+        #[arg(long)]
+        xattrs: bool,
+        
+        /// Запоминать переданные файлы: повторная отправка той же папки сразу пропускает их
+        #[arg(long)]
+        remember_progress: bool,
+        
+        /// Проверять SHA-256 каждого файла у получателя (нужен receive --verify-integrity)
+        #[arg(long)]
+        verify_integrity: bool,
+        
+        /// Предел скорости отправки в секунду на получателя (например: 20M)
+        #[arg(long, value_parser = parse_byte_size)]
+        limit: Option<usize>,
+        
+        /// Дневной лимит отправленного трафика (например: 5GB), учёт общий для всех запусков
+        #[arg(long, value_parser = parse_byte_size)]
+        daily_quota: Option<usize>,
+        
+        /// Сколько получателей передаются одновременно (остальные ждут в очереди)
+        #[arg(long, default_value_t = network::DEFAULT_MAX_PARALLEL_TARGETS)]
+        max_parallel: usize,
+        
+        /// Сколько соединений открывать к каждому получателю (ускоряет мелкие файлы)
+        #[arg(long, default_value_t = 1)]
+        connections: usize,
+        
+        /// Запасные транспорты при обрыве (через запятую), передача продолжится с места обрыва
+        #[arg(long, value_enum, value_delimiter = ',')]
+        fallback: Vec<Transport>,
+        
+        #[command(flatten)]
+        tuning: TuningArgs,
+        
         /// Транспортный протокол (tcp, quic, kcp)
         #[arg(long, value_enum, default_value_t = Transport::Tcp)]
         transport: Transport,
@@ -85,6 +287,14 @@ enum Commands {
         #[arg(short, long, default_value_t = DEFAULT_PORT)]
         port: u16,
         
+        /// Слушать только на этом интерфейсе (например адрес VPN 10.8.0.2; по умолчанию все IPv4, :: - все)
+        #[arg(long, value_name = "IP", default_value_t = network::DEFAULT_BIND_ADDR)]
+        bind: IpAddr,
+        
+        /// Слушать на [::] - все интерфейсы IPv6 и IPv4 (то же, что --bind ::)
+        #[arg(short = '6', long, conflicts_with = "bind")]
+        ipv6: bool,
+        
         /// Папка для сохранения файлов
         #[arg(short, long)]
         dir: Option<PathBuf>,
@@ -93,6 +303,111 @@ enum Commands {
         #[arg(short = 'x', long)]
         extract: bool,
         
+        /// Распаковывать архивы в эту папку, а не в --dir
+        #[arg(long, value_name = "PATH")]
+        extract_to: Option<PathBuf>,
+        
+        /// Папка для недокачанных архивов (включает сохранение архива для резюме)
+        #[arg(long)]
+        temp_dir: Option<PathBuf>,
+        
+        /// Команда после приёма файла: "программа аргументы", {path} - путь, {dir} - папка.
+        /// Выполняется на файлах от отправителя - используйте только в доверенной сети
+        #[arg(long, value_name = "CMD")]
+        post_hook: Option<String>,
+        
+        /// Убивать post-hook, который не завершился за N секунд
+        #[arg(long, value_name = "SECS", default_value_t = network::DEFAULT_HOOK_TIMEOUT.as_secs(), value_parser = clap::value_parser!(u64).range(1..))]
+        hook_timeout: u64,
+        
+        /// Сколько post-hook выполнять одновременно, остальные ждут очереди
+        #[arg(long, value_name = "N", default_value_t = network::DEFAULT_MAX_HOOKS)]
+        max_hooks: usize,
+        
+        /// Отбросить N ведущих папок у записей архива (как tar --strip-components)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        strip: usize,
+        
+        /// Максимум уровней вложенности записи архива (глубже - запись пропускается)
+        #[arg(long, default_value_t = DEFAULT_MAX_PATH_COMPONENTS)]
+        max_path_depth: usize,
+        
+        /// Максимальная длина пути распакованного файла в байтах
+        #[arg(long, default_value_t = DEFAULT_MAX_PATH_LEN)]
+        max_path_len: usize,
+        
+        /// Windows: писать длинные пути через префикс \\?\ (снимает лимит 260 символов)
+        #[arg(long)]
+        long_paths: bool,
+        
+        /// Отдавать метрики Prometheus на этом адресе (например 0.0.0.0:9100; нужна сборка с --features metrics)
+        #[arg(long, value_name = "ADDR")]
+        metrics_addr: Option<SocketAddr>,
+        
+        /// Отключать отправителя, который молчит дольше N секунд (по умолчанию ждать без ограничения)
+        #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+        timeout: Option<u64>,
+        
+        /// Шаблон имени принятых файлов: {name}, {sender} (IP отправителя), {ts} (дата), например "{sender}_{name}"
+        #[arg(long, value_name = "TEMPLATE")]
+        name_template: Option<String>,
+        
+        /// Журнал аудита: подключения, принятые файлы и отказы строками JSON (ротация при 10 MB)
+        #[arg(long, value_name = "PATH")]
+        audit_log: Option<PathBuf>,
+        
+        /// Исправлять имена, недопустимые на Windows (report:v2.txt -> report_v2.txt, aux -> aux_).
+        /// На Windows включено всегда
+        #[arg(long)]
+        safe_names: bool,
+        
+        /// Пересылать каждый принятый файл этим получателям (IP или IP:порт, через запятую)
+        #[arg(long, value_name = "LIST", value_delimiter = ',')]
+        relay_to: Vec<String>,
+        
+        /// Страница загрузки файлов из браузера на этом адресе (например 0.0.0.0:8080; нужна сборка с --features webui)
+        #[arg(long, value_name = "ADDR")]
+        web_addr: Option<SocketAddr>,
+        
+        /// Имена страницы загрузки, кроме IP, localhost и имени машины (через запятую)
+        #[arg(long, value_name = "LIST", value_delimiter = ',')]
+        web_host: Vec<String>,
+        
+        /// Сверять SHA-256 принятых файлов и удалять повреждённые (отправитель - send --verify-integrity)
+        #[arg(long)]
+        verify_integrity: bool,
+        
+        /// Не принимать файлы с этими расширениями (например: exe,msi,bat)
+        #[arg(long, value_name = "LIST", value_delimiter = ',')]
+        block_ext: Vec<String>,
+        
+        /// Не принимать файлы больше этого размера (например: 2GB)
+        #[arg(long, value_name = "SIZE", value_parser = parse_byte_size)]
+        max_file_size: Option<usize>,
+        
+        /// Объявлять получателя в mDNS (_toolza._tcp.local; нужна сборка с --features mdns)
+        #[arg(long)]
+        mdns: bool,
+        
+        /// Показывать проверки доступности (сканирование сети другими отправителями)
+        #[arg(long)]
+        log_probes: bool,
+        
+        /// Записывать расширенные атрибуты user.* от send --xattrs (только Unix)
+        #[arg(long)]
+        xattrs: bool,
+        
+        /// Разрешить отправителям удалять файлы в папке приёма (send --sync --delete)
+        #[arg(long)]
+        allow_delete: bool,
+        
+        /// С --allow-delete: сколько файлов можно удалить одним запросом, больший список отклоняется
+        #[arg(long, value_name = "N", default_value_t = network::DEFAULT_MAX_DELETE_FILES)]
+        max_deletes: usize,
+        
+        #[command(flatten)]
+        tuning: TuningArgs,
+        
         /// Транспортный протокол (tcp, quic, kcp)
         #[arg(long, value_enum, default_value_t = Transport::Tcp)]
         transport: Transport,
@@ -104,10 +419,18 @@ enum Commands {
         #[arg(short, long, default_value_t = DEFAULT_PORT)]
         port: u16,
         
-        /// Подсети для сканирования (например: 192.168.1.0,10.0.0.0)
+        /// Подсети, диапазоны или хосты (например: 192.168.1.0,10.0.0.10-20,10.0.0.5)
         /// Если не указаны, сканируется локальная подсеть
         #[arg(short, long, value_delimiter = ',')]
         subnets: Option<Vec<String>>,
+        
+        /// Файл со списком адресов (по одному на строку, IP или IP:порт)
+        #[arg(long, conflicts_with = "subnets")]
+        hosts: Option<PathBuf>,
+        
+        /// Искать получателей через mDNS вместо перебора подсети (нужна сборка с --features mdns)
+        #[arg(long, conflicts_with_all = ["subnets", "hosts"])]
+        mdns: bool,
     },
     
     /// Тест скорости соединения с сервером
@@ -124,51 +447,330 @@ enum Commands {
         #[arg(short = 'm', long, default_value_t = 10)]
         size: u64,
         
+        #[command(flatten)]
+        tuning: TuningArgs,
+        
         /// Транспортный протокол (tcp, quic, kcp)
         #[arg(long, value_enum, default_value_t = Transport::Tcp)]
         transport: Transport,
     },
+    
+    /// Показать содержимое архива без распаковки
+    List {
+        /// Путь к архиву
+        #[arg(required = true)]
+        archive: PathBuf,
+    },
+    
+    /// Распаковать архив (целиком или только выбранные записи)
+    Extract {
+        /// Путь к архиву
+        #[arg(required = true)]
+        archive: PathBuf,
+        
+        /// Папка назначения
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        
+        /// Распаковать только записи, совпавшие с шаблоном (можно несколько): '**/config.toml'
+        #[arg(long, value_name = "GLOB")]
+        only: Vec<String>,
+    },
+}
+
+/// Разбор --post-hook: программа и аргументы через пробел
+fn parse_hook(cmd: &str) -> Vec<String> {
+    cmd.split_whitespace().map(String::from).collect()
+}
+
+/// Вывести нестандартные настройки TCP-сокета
+fn print_tuning(tuning: &SocketTuning) {
+    if *tuning == SocketTuning::default() {
+        return;
+    }
+    let buf = |size: Option<usize>| size.map_or("авто".to_string(), |s| format_size(s as u64));
+    human!("   TCP: nodelay={}, SO_SNDBUF={}, SO_RCVBUF={}", tuning.nodelay, buf(tuning.send_buf), buf(tuning.recv_buf));
+}
+
+/// Как часто печатать общий прогресс отправки
+const PROGRESS_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Разбор размеров в байтах (--chunk-size, --send-buf, --recv-buf)
+fn parse_byte_size(s: &str) -> Result<usize, String> {
+    parse_size(s)
+        .map(|size| size as usize)
+        .ok_or_else(|| format!("Неверный размер: {}", s))
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    JSON_OUTPUT.store(cli.json, Ordering::Relaxed);
     
     match cli.command {
-        Commands::Send { targets, files, port, compress, flat, sync, transport } => {
+        Commands::Send { targets, discover, files, from_file, skip_missing, include, exclude, port, compress, auto_compress, zstd, flat, sync, skip_hidden, output_name, dry_run, delete, no_dedupe, verify_dedupe, chunk_size, connect_timeout, retries, retry_delay, timeout, continue_on_error, checksum, no_extract, keep_packed, compact, xattrs, remember_progress, verify_integrity, limit, daily_quota, max_parallel, connections, fallback, tuning, transport } => {
             let preserve_structure = !flat;
-            send_files(targets, files, port, compress, preserve_structure, sync, transport.into()).await;
+            let collect_options = CollectOptions { include_hidden: !skip_hidden, ..Default::default() };
+            let filter = pattern::PathFilter::new(&include, &exclude).unwrap_or_else(|e| {
+                eprintln!("Ошибка: {}", e);
+                std::process::exit(1);
+            });
+            let keep_packed = pattern::parse_patterns(&keep_packed).unwrap_or_else(|e| {
+                eprintln!("Ошибка: {}", e);
+                std::process::exit(1);
+            });
+            let mut builder = network::SendOptions::builder()
+                .compression(if auto_compress {
+                    network::CompressionMode::Auto
+                } else {
+                    (compress || zstd.is_some()).into()
+                })
+                .codec(zstd.map_or(network::CompressionCodec::Lz4, |level| network::CompressionCodec::Zstd { level }))
+                .transport(transport.into())
+                .dedupe(!no_dedupe)
+                .verify_dedupe(verify_dedupe)
+                .connect_timeout(std::time::Duration::from_secs(connect_timeout))
+                .connect_retries(retries)
+                .retry_delay(std::time::Duration::from_secs(retry_delay))
+                .continue_on_error(continue_on_error)
+                .transport_fallback(fallback.into_iter().map(TransportType::from).collect())
+                .socket_tuning(tuning.into())
+                .no_extract(no_extract)
+                .checksum(checksum)
+                .compact_framing(compact)
+                .preserve_xattrs(xattrs)
+                .remember_progress(remember_progress)
+                .verify_integrity(verify_integrity)
+                .max_parallel_targets(max_parallel)
+                .connections_per_target(connections);
+            if let Some(chunk_size) = chunk_size {
+                builder = builder.fixed_chunk_size(chunk_size);
+            }
+            if let Some(daily_quota) = daily_quota {
+                builder = builder.daily_quota(daily_quota as u64);
+            }
+            if let Some(limit) = limit {
+                builder = builder.max_bytes_per_sec(limit as u64);
+            }
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(std::time::Duration::from_secs(timeout));
+            }
+            let options = builder.build().unwrap_or_else(|e| {
+                eprintln!("Ошибка: {}", e);
+                std::process::exit(1);
+            });
+            let mut entries: Vec<SendEntry> = files.into_iter()
+                .map(|path| SendEntry { path, remote: None })
+                .collect();
+            if let Some(manifest) = from_file {
+                match read_manifest(&manifest, skip_missing) {
+                    Ok(manifest_entries) => entries.extend(manifest_entries),
+                    Err(e) => {
+                        eprintln!("Ошибка: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            let targets = if discover {
+                discover_targets(port).await
+            } else {
+                targets
+            };
+            let job = SendJob {
+                targets,
+                entries,
+                port,
+                preserve_structure,
+                sync_mode: sync,
+                dry_run,
+                delete,
+                collect_options,
+                filter,
+                keep_packed,
+                output_name,
+            };
+            send_files(job, options).await;
+        }
+        Commands::Receive { port, bind, ipv6, dir, extract, extract_to, temp_dir, post_hook, hook_timeout, max_hooks, strip, max_path_depth, max_path_len, long_paths, metrics_addr, timeout, name_template, audit_log, safe_names, relay_to, web_addr, web_host, verify_integrity, block_ext, max_file_size, mdns, log_probes, xattrs, allow_delete, max_deletes, tuning, transport } => {
+            let path_limits = PathLimits {
+                max_components: max_path_depth,
+                max_path_len,
+                long_paths,
+                strip_components: strip,
+                xattrs: false,
+            };
+            let bind = if ipv6 { IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED) } else { bind };
+            // Опции - до баннера: ошибка в опциях - без запуска сервера
+            let mut builder = network::ServerOptions::builder()
+                .auto_extract_tar_lz4(extract)
+                .auto_extract_tar_zst(extract)
+                .strip_components(path_limits.strip_components)
+                .transport(transport.into())
+                .bind_addr(bind)
+                .path_limits(path_limits)
+                .socket_tuning(tuning.into())
+                .sanitize_names(safe_names || cfg!(windows))
+                .relay_to(relay_to)
+                .web_hosts(web_host)
+                .verify_integrity(verify_integrity)
+                .blocked_extensions(block_ext)
+                .advertise_mdns(mdns)
+                .accept_xattrs(xattrs)
+                .allow_delete(allow_delete)
+                .max_delete_files(max_deletes)
+                .hook_timeout(std::time::Duration::from_secs(hook_timeout))
+                .max_hooks(max_hooks);
+            if let Some(size) = max_file_size {
+                builder = builder.max_file_size(size as u64);
+            }
+            // В CLI по умолчанию чистая потоковая распаковка; с --temp-dir архив сохраняется для резюме
+            if let Some(temp_dir) = temp_dir {
+                builder = builder.temp_dir(temp_dir);
+            }
+            if let Some(extract_to) = extract_to {
+                builder = builder.extract_dir(extract_to);
+            }
+            if let Some(hook) = post_hook {
+                builder = builder.post_hook(parse_hook(&hook));
+            }
+            if let Some(addr) = metrics_addr {
+                builder = builder.metrics_addr(addr);
+            }
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(std::time::Duration::from_secs(timeout));
+            }
+            if let Some(addr) = web_addr {
+                builder = builder.web_addr(addr);
+            }
+            if let Some(template) = name_template {
+                builder = builder.name_template(template);
+            }
+            if let Some(path) = audit_log {
+                builder = builder.audit_log(path);
+            }
+            let options = builder.build().unwrap_or_else(|e| {
+                eprintln!("Ошибка: {}", e);
+                std::process::exit(1);
+            });
+            receive_files(port, dir, options, log_probes).await;
+        }
+        Commands::Scan { port, subnets, hosts, mdns } => {
+            scan_network(port, subnets, hosts, mdns).await;
         }
-        Commands::Receive { port, dir, extract, transport } => {
-            receive_files(port, dir, extract, transport.into()).await;
+        Commands::Speedtest { target, port, size, tuning, transport } => {
+            run_speedtest(target, port, size, tuning.into(), transport.into()).await;
         }
-        Commands::Scan { port, subnets } => {
-            scan_network(port, subnets).await;
+        Commands::List { archive } => {
+            list_archive(&archive);
         }
-        Commands::Speedtest { target, port, size, transport } => {
-            run_speedtest(target, port, size, transport.into()).await;
+        Commands::Extract { archive, dir, only } => {
+            extract_archive(&archive, &dir, &only);
         }
     }
 }
 
-async fn send_files(targets: Vec<String>, paths: Vec<PathBuf>, port: u16, use_compression: bool, preserve_structure: bool, _sync_mode: bool, transport_type: TransportType) {
+/// Путь для отправки и, если задано, его имя у получателя
+struct SendEntry {
+    path: PathBuf,
+    remote: Option<String>,
+}
+
+/// Прочитать список отправки: одна запись на строку, `путь => имя/у/получателя`
+/// для переименования, пустые строки и строки с # пропускаются.
+/// Относительные пути - от папки файла списка
+fn read_manifest(manifest: &Path, skip_missing: bool) -> Result<Vec<SendEntry>, String> {
+    let content = std::fs::read_to_string(manifest)
+        .map_err(|e| format!("Ошибка чтения файла '{}': {}", manifest.display(), e))?;
+    let base_dir = manifest.parent().unwrap_or(Path::new("."));
+    
+    let mut entries = Vec::new();
+    let mut missing = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        
+        let (path, remote) = match line.split_once("=>") {
+            Some((path, remote)) if !remote.trim().is_empty() => (path.trim(), Some(remote.trim().to_string())),
+            Some(_) => return Err(format!("{}:{}: пустое имя после =>", manifest.display(), number + 1)),
+            None => (line, None),
+        };
+        let path = base_dir.join(path);
+        if !path.exists() {
+            missing.push(format!("{}:{}: {}", manifest.display(), number + 1, path.display()));
+            continue;
+        }
+        entries.push(SendEntry { path, remote });
+    }
+    
+    if !missing.is_empty() {
+        if !skip_missing {
+            return Err(format!("пути не существуют (--skip-missing, чтобы пропустить):\n  {}", missing.join("\n  ")));
+        }
+        for line in &missing {
+            eprintln!("⚠️ Пропущен отсутствующий путь: {}", line);
+        }
+    }
+    human!("📋 Список '{}': {} записей", manifest.display(), entries.len());
+    Ok(entries)
+}
+
+/// Переименовать файлы записи списка: файл получает имя целиком,
+/// у файлов папки имя папки заменяется на `remote`
+fn remap_entry(files: &mut [FileInfo], remote: &str, is_dir: bool) -> Result<(), String> {
+    for file in files {
+        let name = if is_dir {
+            let inner = file.relative_path.split_once('/').map_or(file.relative_path.as_str(), |(_, rest)| rest);
+            format!("{}/{}", remote, inner)
+        } else {
+            remote.to_string()
+        };
+        file.set_remote_name(&name)?;
+    }
+    Ok(())
+}
+
+/// Что и куда отправить: всё из аргументов `send`, кроме опций передачи
+struct SendJob {
+    targets: Vec<String>,
+    entries: Vec<SendEntry>,
+    port: u16,
+    preserve_structure: bool,
+    sync_mode: bool,
+    dry_run: bool,
+    delete: bool,
+    collect_options: CollectOptions,
+    filter: pattern::PathFilter,
+    keep_packed: Vec<pattern::Pattern>,
+    output_name: Option<String>,
+}
+
+async fn send_files(job: SendJob, options: network::SendOptions) {
+    let SendJob { targets, entries, port, preserve_structure, sync_mode, dry_run, delete, collect_options, filter, keep_packed, output_name } = job;
     if targets.is_empty() {
         eprintln!("Ошибка: укажите хотя бы один адрес получателя (-t)");
         std::process::exit(1);
     }
     
-    if paths.is_empty() {
+    if entries.is_empty() {
         eprintln!("Ошибка: укажите файлы для отправки");
         std::process::exit(1);
     }
     
     // Собираем файлы
     let mut files: Vec<FileInfo> = Vec::new();
-    for path in paths {
+    // Папки, которые синхронизируются целиком, - даже если в них не осталось файлов
+    let mut sync_roots: Vec<String> = Vec::new();
+    for SendEntry { path, remote } in entries {
+        let first = files.len();
+        if path.is_dir() && preserve_structure && remote.is_none() {
+            sync_roots.extend(path.file_name().map(|n| n.to_string_lossy().to_string()));
+        }
         if path.is_dir() {
-            match collect_files_from_folder(&path) {
+            match collect_files_from_folder_with_options(&path, &collect_options) {
                 Ok(folder_files) => {
-                    println!("📁 Папка '{}': {} файл(ов)", path.display(), folder_files.len());
+                    human!("📁 Папка '{}': {} файл(ов)", path.display(), folder_files.len());
                     files.extend(folder_files);
                 }
                 Err(e) => {
@@ -187,62 +789,162 @@ async fn send_files(targets: Vec<String>, paths: Vec<PathBuf>, port: u16, use_co
         } else {
             eprintln!("Путь не существует: {}", path.display());
         }
+        
+        // Если не сохраняем структуру - используем только имена файлов
+        if !preserve_structure {
+            for file in &mut files[first..] {
+                file.relative_path = file.name.clone();
+            }
+        }
+        // Имя из списка (`путь => имя`) важнее --flat
+        if let Some(remote) = remote {
+            if let Err(e) = remap_entry(&mut files[first..], &remote, path.is_dir()) {
+                eprintln!("Ошибка: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    
+    // --include/--exclude - по пути у получателя, уже с учётом --flat и `=> имя`
+    if !filter.is_empty() {
+        let before = files.len();
+        files.retain(|file| filter.allows(&file.relative_path));
+        human!("🔎 Отфильтровано: {} файл(ов), осталось {}", before - files.len(), files.len());
+    }
+    for file in &mut files {
+        file.no_extract = pattern::matches_any(&keep_packed, &file.relative_path);
     }
     
-    if files.is_empty() {
+    // С --delete пустая папка - тоже синхронизация: у получателя её нужно очистить
+    if files.is_empty() && (!delete || sync_roots.is_empty()) {
         eprintln!("Нет файлов для отправки");
         std::process::exit(1);
     }
     
-    // Если не сохраняем структуру - используем только имена файлов
-    if !preserve_structure {
-        for file in &mut files {
-            file.relative_path = file.name.clone();
+    // Другое имя у получателя - только для одиночного файла
+    if let Some(name) = output_name {
+        if files.len() != 1 {
+            eprintln!("Ошибка: --output-name можно указать только для одного файла (выбрано {})", files.len());
+            std::process::exit(1);
+        }
+        if let Err(e) = files[0].set_remote_name(&name) {
+            eprintln!("Ошибка: {}", e);
+            std::process::exit(1);
         }
+        human!("📝 Имя у получателя: {}", files[0].relative_path);
     }
     
-    // Добавляем порт к адресам если нужно
-    let targets: Vec<String> = targets
-        .into_iter()
-        .map(|t| {
-            if t.contains(':') {
-                t
-            } else {
-                format!("{}:{}", t, port)
+    // Добавляем порт к адресам если нужно (IPv6 - в скобках)
+    let targets: Vec<String> = match targets.iter().map(|t| network::parse_target_addr(t, port)).collect() {
+        Ok(targets) => targets,
+        Err(e) => {
+            eprintln!("Ошибка: {}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    // Синхронизация: у каждого получателя спрашиваем, что у него уже есть,
+    // и отправляем всем только то, чего не хватает хотя бы одному
+    if sync_mode {
+        let mut needed: HashSet<String> = HashSet::new();
+        let mut deletions: Vec<(String, Vec<String>)> = Vec::new();
+        for target in &targets {
+            let diff = match network::fetch_sync_diff(target, &files, &sync_roots, &options).await {
+                Ok(diff) => diff,
+                Err(e) => {
+                    eprintln!("Ошибка синхронизации с {}: {}", target, e);
+                    std::process::exit(1);
+                }
+            };
+            human!("🔄 {}: передать {} ({}), без изменений {}, только у получателя {}",
+                target,
+                diff.to_transfer.len(),
+                format_size(diff.transfer_size()),
+                diff.unchanged.len(),
+                diff.remote_only.len()
+            );
+            // Отфильтрованное --include/--exclude не отправляется, но и не удаляется
+            let remote_only: Vec<String> = diff.remote_only.into_iter()
+                .filter(|path| filter.allows(path))
+                .collect();
+            if dry_run {
+                for file in &diff.to_transfer {
+                    human!("   → {} ({})", file.relative_path, format_size(file.size));
+                }
+                if delete {
+                    for path in &remote_only {
+                        human!("   ✗ {}", path);
+                    }
+                }
             }
-        })
-        .collect();
+            needed.extend(diff.to_transfer.into_iter().map(|f| f.relative_path));
+            if delete && !remote_only.is_empty() {
+                deletions.push((target.clone(), remote_only));
+            }
+        }
+        if dry_run {
+            return;
+        }
+        for (target, paths) in deletions {
+            // Локально пусто - удаление очистит всё у получателя: только с явным подтверждением
+            if files.is_empty() && !confirm_wipe(&target, paths.len()) {
+                eprintln!("Удаление у {} отменено", target);
+                std::process::exit(1);
+            }
+            match network::delete_remote_files(&target, &paths, &options).await {
+                Ok(()) => human!("🗑 {}: удалено {} файл(ов)", target, paths.len()),
+                Err(e) => eprintln!("Ошибка удаления у {}: {}", target, e),
+            }
+        }
+        files.retain(|file| needed.contains(&file.relative_path));
+        if files.is_empty() {
+            human!("✅ Всё уже синхронизировано");
+            return;
+        }
+    }
     
     let total_size: u64 = files.iter().map(|f| f.size).sum();
     
-    println!();
-    println!("🚀 Отправка {} файл(ов) ({}) на {} получателей", 
+    human!();
+    human!("🚀 Отправка {} файл(ов) ({}) на {} получателей", 
         files.len(), 
         format_size(total_size),
         targets.len()
     );
-    println!("🔌 Протокол: {}", transport_type.name());
-    if use_compression {
-        println!("🗜  LZ4 сжатие: включено");
+    human!("🔌 Протокол: {}", options.transport_type.name());
+    let codec = options.codec.name();
+    match options.compression {
+        network::CompressionMode::On => human!("🗜  {} сжатие: включено", codec),
+        network::CompressionMode::Auto => human!("🗜  {} сжатие: авто (по каждому файлу)", codec),
+        network::CompressionMode::Off => {}
+    }
+    if options.no_extract {
+        human!("📦 Архивы: без распаковки у получателя");
+    }
+    if let Some(chunk_size) = options.fixed_chunk_size() {
+        human!("📏 Размер чанка: {} (фиксированный)", format_size(chunk_size as u64));
+    }
+    if let Some(limit) = options.daily_quota {
+        let quota = network::QuotaTracker::shared(limit);
+        human!("📊 Дневной лимит: осталось {} из {}", format_size(quota.remaining()), format_size(limit));
     }
     if preserve_structure {
-        println!("📂 Структура папок: сохраняется");
+        human!("📂 Структура папок: сохраняется");
     } else {
-        println!("📂 Структура папок: плоская (все файлы в одну папку)");
+        human!("📂 Структура папок: плоская (все файлы в одну папку)");
     }
-    if _sync_mode {
-        println!("🔄 Режим синхронизации: только изменённые файлы");
+    if sync_mode {
+        human!("🔄 Режим синхронизации: только изменённые файлы");
     }
-    println!();
+    human!();
     
     let (tx, mut rx) = mpsc::unbounded_channel();
     
-    // Создаём опции
-    let options = network::SendOptions {
-        use_compression,
-        enable_resume: true,
-        transport_type,
-    };
+    // Общий прогресс по всем получателям - для скорости и ETA
+    let total_bytes = files.iter().map(|f| f.size).sum::<u64>() * targets.len() as u64;
+    let mut stats = TransferStats::new(total_bytes, files.len());
+    let mut file_progress: HashMap<(usize, usize), u64> = HashMap::new();
+    let mut last_report = Instant::now();
     
     // Запускаем отправку
     let files_clone = files.clone();
@@ -253,52 +955,101 @@ async fn send_files(targets: Vec<String>, paths: Vec<PathBuf>, port: u16, use_co
     
     // Обрабатываем события
     let mut completed_targets = 0;
+    let mut failed_files: Vec<(usize, String)> = Vec::new();
     let total_targets = files.len();
     
     while let Some(event) = rx.recv().await {
+        emit_json(&event);
         match event {
+            TransferEvent::TargetQueued(target_id) => {
+                human!("⏳ [{}] В очереди...", target_id);
+            }
+            TransferEvent::Connecting(target_id) => {
+                human!("🔄 [{}] Подключение...", target_id);
+            }
+            TransferEvent::ConnectionRetrying(target_id, attempt) => {
+                eprintln!("🔁 [{}] Получатель недоступен, повтор #{}", target_id, attempt);
+            }
             TransferEvent::Connected(_, addr) => {
-                println!("✅ Подключено: {}", addr);
+                human!("✅ Подключено: {}", addr);
+            }
+            TransferEvent::Handshaking(target_id) => {
+                human!("🤝 [{}] Согласование...", target_id);
             }
             TransferEvent::FileStarted(target_id, file_idx) => {
                 if let Some(file) = files.get(file_idx) {
-                    println!("📤 [{}] Отправка: {} ({})", 
+                    human!("📤 [{}] Отправка: {} ({})", 
                         target_id, file.relative_path, format_size(file.size));
                 }
             }
+            TransferEvent::Progress(target_id, file_idx, transferred, _, _) => {
+                file_progress.insert((target_id, file_idx), transferred);
+                stats.update(file_progress.values().sum(), 0, 0);
+                if last_report.elapsed() >= PROGRESS_REPORT_INTERVAL {
+                    human!("⏱️ {:.0}% | {} | ETA: {}",
+                        stats.progress_percent(),
+                        stats.speed_formatted_in(Language::Russian),
+                        stats.eta_clock_formatted_in(Language::Russian));
+                    last_report = Instant::now();
+                }
+            }
             TransferEvent::FileCompleted(target_id, file_idx) => {
                 if let Some(file) = files.get(file_idx) {
-                    println!("✅ [{}] Завершено: {}", target_id, file.relative_path);
+                    human!("✅ [{}] Завершено: {}", target_id, file.relative_path);
                 }
             }
-            TransferEvent::FileSkipped(target_id, file_idx) => {
+            TransferEvent::FileSkipped(target_id, file_idx, reason) => {
                 if let Some(file) = files.get(file_idx) {
-                    println!("⏭️ [{}] Пропущен (актуален): {}", target_id, file.relative_path);
+                    human!("⏭️ [{}] Пропущен ({}): {}", target_id, reason.description(), file.relative_path);
+                }
+            }
+            TransferEvent::FileDeduplicated(target_id, file_idx, original_idx) => {
+                if let (Some(file), Some(original)) = (files.get(file_idx), files.get(original_idx)) {
+                    human!("🔗 [{}] Дубликат {}: {}", target_id, original.relative_path, file.relative_path);
+                }
+            }
+            TransferEvent::FileWarning(target_id, file_idx, warning) => {
+                if let Some(file) = files.get(file_idx) {
+                    eprintln!("⚠️ [{}] {}: {}", target_id, file.relative_path, warning.description());
                 }
             }
             TransferEvent::FileResumed(target_id, file_idx, offset) => {
                 if let Some(file) = files.get(file_idx) {
-                    println!("🔄 [{}] Возобновление: {} @ {}", 
+                    human!("🔄 [{}] Возобновление: {} @ {}", 
                         target_id, file.relative_path, format_size(offset));
                 }
             }
+            TransferEvent::TargetChecksum(target_id, checksum) => {
+                human!("🔐 [{}] Контрольная сумма: {:016x}", target_id, checksum);
+            }
             TransferEvent::TargetCompleted(target_id) => {
                 completed_targets += 1;
-                println!("🎉 Получатель {} завершён ({}/{})", 
+                human!("🎉 Получатель {} завершён ({}/{})", 
                     target_id, completed_targets, total_targets);
             }
             TransferEvent::ConnectionError(target_id, err) => {
                 eprintln!("❌ [{}] Ошибка: {}", target_id, err);
             }
+            TransferEvent::TransportFallback(target_id, transport_type, reason) => {
+                eprintln!("⚠️ [{}] {}", target_id, reason);
+                human!("🔀 [{}] Переключение на {}, продолжаем с места обрыва", target_id, transport_type.name());
+            }
             TransferEvent::FileError(target_id, file_idx, err) => {
                 if let Some(file) = files.get(file_idx) {
                     eprintln!("❌ [{}] Ошибка отправки {}: {}", 
                         target_id, file.relative_path, err);
+                    failed_files.push((target_id, file.relative_path.clone()));
                 }
             }
             TransferEvent::AllCompleted => {
-                println!();
-                println!("✅ Передача завершена!");
+                human!();
+                if !failed_files.is_empty() {
+                    eprintln!("⚠️ Не отправлено файлов: {}", failed_files.len());
+                    for (target_id, name) in &failed_files {
+                        eprintln!("   [{}] {}", target_id, name);
+                    }
+                }
+                human!("✅ Передача завершена!");
                 break;
             }
             _ => {}
@@ -306,43 +1057,54 @@ async fn send_files(targets: Vec<String>, paths: Vec<PathBuf>, port: u16, use_co
     }
 }
 
-async fn receive_files(port: u16, save_dir: Option<PathBuf>, auto_extract: bool, transport_type: TransportType) {
+async fn receive_files(port: u16, save_dir: Option<PathBuf>, options: network::ServerOptions, log_probes: bool) {
     let save_dir = save_dir.unwrap_or_else(|| {
         dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
     });
     
     let local_ip = get_local_ip_string();
     
-    println!();
-    println!("📥 Сервер запущен");
-    println!("   IP: {}", local_ip);
-    println!("   Порт: {}", port);
-    println!("   Протокол: {}", transport_type.name());
-    println!("   Сохранение в: {}", save_dir.display());
-    if auto_extract {
-        println!("   📦 Авто-распаковка tar.lz4: включена");
+    human!();
+    human!("📥 Сервер запущен");
+    if options.bind_addr.is_unspecified() {
+        human!("   IP: {}", local_ip);
+    } else {
+        human!("   IP: {} (только этот интерфейс)", options.bind_addr);
+    }
+    human!("   Порт: {}", port);
+    human!("   Протокол: {}", options.transport_type.name());
+    print_tuning(&options.socket_tuning);
+    human!("   Сохранение в: {}", save_dir.display());
+    if let Some(ref temp_dir) = options.temp_dir {
+        human!("   Временная папка: {}", temp_dir.display());
+    }
+    if options.extract_options.tar_lz4 {
+        human!("   📦 Авто-распаковка tar.lz4: включена");
+        if let Some(ref extract_to) = options.extract_dir {
+            human!("   📂 Распаковка в: {}", extract_to.display());
+        }
+        if options.extract_options.strip_components > 0 {
+            human!("   ✂️  Убирать верхних папок: {}", options.extract_options.strip_components);
+        }
+    }
+    if let Some(ref hook) = options.post_hook {
+        human!("   🪝 Post-hook: {}", hook.join(" "));
+    }
+    if let Some(ref template) = options.name_template {
+        human!("   🏷  Имена файлов: {}", template);
+    }
+    if let Some(ref path) = options.audit_log {
+        human!("   📝 Журнал аудита: {}", path.display());
+    }
+    if !options.relay_to.is_empty() {
+        human!("   ↪ Ретрансляция на: {}", options.relay_to.join(", "));
     }
-    println!();
-    println!("Ожидание подключений... (Ctrl+C для выхода)");
-    println!();
+    human!();
+    human!("Ожидание подключений... (Ctrl+C для выхода)");
+    human!();
     
     let (tx, mut rx) = mpsc::unbounded_channel();
     
-    // Создаём опции
-    let options = network::ServerOptions {
-        extract_options: network::ExtractOptions {
-            tar_lz4: auto_extract,
-            tar_zst: auto_extract,
-            lz4: false,
-            tar: false,
-            zip: false,
-            rar: false,
-        },
-        enable_resume: true,
-        transport_type,
-        save_archive_for_resume: false, // В CLI по умолчанию чистая потоковая распаковка
-    };
-    
     // Запускаем сервер
     let save_dir_clone = save_dir.clone();
     let stop_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
@@ -354,25 +1116,54 @@ async fn receive_files(port: u16, save_dir: Option<PathBuf>, auto_extract: bool,
     
     // Обрабатываем события
     while let Some(event) = rx.recv().await {
+        emit_json(&event);
         match event {
-            TransferEvent::Connected(_, addr) => {
-                println!("🔗 Подключение: {}", addr);
+            TransferEvent::ClientConnected(addr, intent) => {
+                human!("🔗 Подключение: {} ({})", addr, intent.name());
+            }
+            TransferEvent::ClientProbed(addr) if log_probes => {
+                human!("🔎 Проверка доступности: {}", addr);
             }
             TransferEvent::FileReceived(name, size) => {
-                println!("📥 Получен: {} ({})", name, format_size(size));
+                human!("📥 Получен: {} ({})", name, format_size(size));
+            }
+            TransferEvent::FileRenamed(name, saved_as) => {
+                eprintln!("✏️ {} недопустимо на этой системе, сохранён как {}", name, saved_as);
+            }
+            TransferEvent::FileRejected(name, reason) => {
+                eprintln!("🚫 Отклонён {}: {}", name, reason);
+            }
+            TransferEvent::FileDeleted(name) => {
+                human!("🗑 Удалён (синхронизация): {}", name);
             }
             TransferEvent::ExtractionStarted(name) => {
-                println!("📦 Распаковка: {}", name);
+                human!("📦 Распаковка: {}", name);
             }
-            TransferEvent::ExtractionCompleted(name, files_count, total_size) => {
-                println!("✅ Распаковано {}: {} файл(ов), {}", name, files_count, format_size(total_size));
+            TransferEvent::ExtractionCompleted(name, result) => {
+                human!("✅ Распаковано {}: {}, {}", name, result.summary(), format_size(result.total_size));
             }
             TransferEvent::ExtractionError(name, err) => {
                 eprintln!("❌ Ошибка распаковки {}: {}", name, err);
             }
-            TransferEvent::Disconnected => {
-                println!("🔌 Клиент отключился");
-                println!();
+            TransferEvent::ExtractionEntrySkipped(name, entry, reason) => {
+                eprintln!("⚠️ {}: пропущен {} ({})", name, entry, reason);
+            }
+            TransferEvent::HookCompleted(path, output) => {
+                human!("🪝 Post-hook: {}", path);
+                if !output.is_empty() {
+                    human!("{}", output);
+                }
+            }
+            TransferEvent::HookError(path, err) => {
+                eprintln!("❌ Post-hook для {}: {}", path, err);
+            }
+            TransferEvent::DiskFull(name, kept) => {
+                eprintln!("💾 Диск заполнен: {} сохранён частично ({})", name, format_size(kept));
+                eprintln!("   Освободите место - повторная отправка продолжит с места остановки");
+            }
+            TransferEvent::ClientDisconnected(..) => {
+                human!("🔌 Клиент отключился");
+                human!();
             }
             TransferEvent::ConnectionError(_, err) => {
                 eprintln!("❌ Ошибка: {}", err);
@@ -382,18 +1173,103 @@ async fn receive_files(port: u16, save_dir: Option<PathBuf>, auto_extract: bool,
     }
 }
 
-async fn scan_network(port: u16, subnets_input: Option<Vec<String>>) {
+/// `send --sync --delete` при пустом локальном наборе: удалить все `count` файлов
+/// у получателя можно, только набрав `yes` в терминале
+fn confirm_wipe(target: &str, count: usize) -> bool {
+    if !std::io::stdin().is_terminal() {
+        eprintln!("Ошибка: локально нет ни одного файла, а у {} удалились бы все {} файл(ов); \
+            без интерактивного подтверждения это не выполняется", target, count);
+        return false;
+    }
+    human_inline!("⚠️ Локально нет ни одного файла: удалить у {} все {} файл(ов)? Введите yes: ", target, count);
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).unwrap_or(0) > 0 && line.trim() == "yes"
+}
+
+/// `send --discover`: просканировать сеть и выбрать получателей из списка
+async fn discover_targets(port: u16) -> Vec<String> {
+    // Без терминала выбрать некому - получателей нужно указать явно
+    if !std::io::stdin().is_terminal() {
+        eprintln!("Ошибка: --discover работает только в интерактивном терминале, укажите получателей через -t");
+        std::process::exit(1);
+    }
+    
+    let found = scan_network(port, None, None, false).await;
+    if found.is_empty() {
+        eprintln!("Ошибка: получатели не найдены, укажите их через -t");
+        std::process::exit(1);
+    }
+    
+    human!();
+    for (i, addr) in found.iter().enumerate() {
+        human!("  {}) {}", i + 1, addr);
+    }
+    loop {
+        human_inline!("Кому отправить (номера через запятую, диапазон 1-3 или all): ");
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            eprintln!("Отменено");
+            std::process::exit(1);
+        }
+        match parse_selection(&line, found.len()) {
+            Ok(selected) => return selected.into_iter().map(|i| found[i].clone()).collect(),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+/// Найти серверы; возвращает их адреса
+async fn scan_network(port: u16, subnets_input: Option<Vec<String>>, hosts_file: Option<PathBuf>, mdns: bool) -> Vec<String> {
     let local_ip = get_local_ip_string();
     
-    println!();
-    println!("🔍 Сканирование сети...");
-    println!("   Ваш IP: {}", local_ip);
-    println!("   Порт: {}", port);
+    human!();
+    human!("🔍 Сканирование сети...");
+    human!("   Ваш IP: {}", local_ip);
+    human!("   Порт: {}", port);
     
     let (tx, mut rx) = mpsc::unbounded_channel();
     
-    // Парсим подсети или используем автоопределение
-    if let Some(subnets_str) = subnets_input {
+    // mDNS, список адресов из файла, подсети или автоопределение
+    if mdns {
+        human!("   Поиск: mDNS ({})", network::MDNS_SERVICE_TYPE);
+        human!();
+        
+        tokio::spawn(async move {
+            if let Err(e) = network::discover_mdns(network::MDNS_BROWSE_TIMEOUT, tx).await {
+                eprintln!("Ошибка: {}", e);
+            }
+        });
+    } else if let Some(hosts_file) = hosts_file {
+        let content = match std::fs::read_to_string(&hosts_file) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Ошибка чтения файла '{}': {}", hosts_file.display(), e);
+                std::process::exit(1);
+            }
+        };
+        
+        // Одна запись на строку, строки с # - комментарии
+        let hosts: Vec<String> = content
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_string())
+            .collect();
+        
+        if hosts.is_empty() {
+            eprintln!("Ошибка: в файле '{}' нет адресов", hosts_file.display());
+            std::process::exit(1);
+        }
+        
+        human!("   Адресов из файла: {}", hosts.len());
+        human!();
+        
+        tokio::spawn(async move {
+            let _ = network::scan_hosts(hosts, port, tx).await;
+        });
+    } else if let Some(subnets_str) = subnets_input {
         let input = subnets_str.join(",");
         let subnets = network::parse_subnets(&input);
         
@@ -402,18 +1278,18 @@ async fn scan_network(port: u16, subnets_input: Option<Vec<String>>) {
             std::process::exit(1);
         }
         
-        println!("   Подсети:");
+        human!("   Подсети:");
         for subnet in &subnets {
-            println!("     - {}", subnet);
+            human!("     - {}", subnet);
         }
-        println!();
+        human!();
         
         tokio::spawn(async move {
             let _ = network::scan_subnets(subnets, port, tx).await;
         });
     } else {
-        println!("   Подсеть: автоопределение");
-        println!();
+        human!("   Подсеть: автоопределение");
+        human!();
         
         tokio::spawn(async move {
             let _ = network::scan_network(port, tx).await;
@@ -421,27 +1297,33 @@ async fn scan_network(port: u16, subnets_input: Option<Vec<String>>) {
     }
     
     let mut found = Vec::new();
+    let mut names = std::collections::HashMap::new();
     
     // Обрабатываем события
     while let Some(event) = rx.recv().await {
+        emit_json(&event);
         match event {
             TransferEvent::ServerFound(addr) => {
-                println!("\r🟢 Найден сервер: {}                    ", addr);
+                human!("\r🟢 Найден сервер: {}                    ", addr);
                 found.push(addr);
             }
+            TransferEvent::ServerFoundNamed(addr, hostname) => {
+                human!("\r🏷  {}: {}                    ", addr, hostname);
+                names.insert(addr, hostname);
+            }
             TransferEvent::ScanProgress(ip, progress) => {
-                print!("\r   Проверка: {} ({}%)    ", ip, progress);
+                human_inline!("\r   Проверка: {} ({}%)    ", ip, progress);
                 let _ = std::io::Write::flush(&mut std::io::stdout());
             }
             TransferEvent::ScanCompleted => {
-                println!();
-                println!();
+                human!();
+                human!();
                 if found.is_empty() {
-                    println!("Серверы не найдены");
+                    human!("Серверы не найдены");
                 } else {
-                    println!("Найдено серверов: {}", found.len());
+                    human!("Найдено серверов: {}", found.len());
                     for server in &found {
-                        println!("  - {}", server);
+                        human!("  - {}", network::server_label(server, names.get(server).map(String::as_str)));
                     }
                 }
                 break;
@@ -449,52 +1331,66 @@ async fn scan_network(port: u16, subnets_input: Option<Vec<String>>) {
             _ => {}
         }
     }
+    found
 }
 
-async fn run_speedtest(target: String, port: u16, size_mb: u64, transport_type: TransportType) {
-    let target_addr = if target.contains(':') {
-        target
-    } else {
-        format!("{}:{}", target, port)
+async fn run_speedtest(target: String, port: u16, size_mb: u64, socket_tuning: SocketTuning, transport_type: TransportType) {
+    let target_addr = match network::parse_target_addr(&target, port) {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("Ошибка: {}", e);
+            std::process::exit(1);
+        }
     };
     
     let size = size_mb * 1024 * 1024;
     
-    println!();
-    println!("🚀 Спидтест");
-    println!("   Сервер: {}", target_addr);
-    println!("   Протокол: {}", transport_type.name());
-    println!("   Размер данных: {} MB", size_mb);
-    println!();
-    println!("💡 Убедитесь, что на сервере запущен режим \"receive\" с тем же протоколом");
-    println!();
+    human!();
+    human!("🚀 Спидтест");
+    human!("   Сервер: {}", target_addr);
+    human!("   Протокол: {}", transport_type.name());
+    print_tuning(&socket_tuning);
+    human!("   Размер данных: {} MB", size_mb);
+    human!();
+    human!("💡 Убедитесь, что на сервере запущен режим \"receive\" с тем же протоколом");
+    human!();
     
     let (tx, mut rx) = mpsc::unbounded_channel();
     
+    // Ctrl+C - штатная отмена: сервер получит Cancel и не останется ждать данных
+    let stop_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let ctrl_c_flag = stop_flag.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            ctrl_c_flag.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    });
+    
     let target_addr_clone = target_addr.clone();
     let handle = tokio::spawn(async move {
-        network::run_speedtest(&target_addr_clone, size, tx).await
+        network::run_speedtest_with_stop(&target_addr_clone, size, socket_tuning, tx, stop_flag).await
     });
     
     // Обрабатываем события
     while let Some(event) = rx.recv().await {
+        emit_json(&event);
         match event {
             TransferEvent::SpeedTestStarted(addr) => {
-                println!("🔗 Подключено к {}", addr);
+                human!("🔗 Подключено к {}", addr);
             }
             TransferEvent::SpeedTestProgress(direction, progress) => {
                 let dir_str = if direction == "upload" { "⬆️  Upload" } else { "⬇️  Download" };
-                print!("\r   {} {}%      ", dir_str, progress);
+                human_inline!("\r   {} {}%      ", dir_str, progress);
                 let _ = std::io::Write::flush(&mut std::io::stdout());
             }
             TransferEvent::SpeedTestCompleted(upload, download, latency) => {
-                println!("\r                              ");
-                println!();
-                println!("📊 Результаты:");
-                println!("   ⬆️  Upload:   {:.1} MB/s", upload);
-                println!("   ⬇️  Download: {:.1} MB/s", download);
-                println!("   🏓 Ping:     {:.2} ms", latency);
-                println!();
+                human!("\r                              ");
+                human!();
+                human!("📊 Результаты:");
+                human!("   ⬆️  Upload:   {:.1} MB/s", upload);
+                human!("   ⬇️  Download: {:.1} MB/s", download);
+                human!("   🏓 Ping:     {:.2} ms", latency);
+                human!();
                 
                 // Оценка качества
                 let avg_speed = (upload + download) / 2.0;
@@ -509,13 +1405,17 @@ async fn run_speedtest(target: String, port: u16, size_mb: u64, transport_type:
                 } else {
                     "❌ Медленно"
                 };
-                println!("   Качество соединения: {}", quality);
+                human!("   Качество соединения: {}", quality);
                 break;
             }
             TransferEvent::SpeedTestError(err) => {
                 eprintln!("\n❌ Ошибка: {}", err);
                 break;
             }
+            TransferEvent::SpeedTestCancelled => {
+                human!("\n⏹ Спидтест отменён");
+                break;
+            }
             _ => {}
         }
     }
@@ -523,3 +1423,55 @@ async fn run_speedtest(target: String, port: u16, size_mb: u64, transport_type:
     let _ = handle.await;
 }
 
+fn list_archive(archive: &std::path::Path) {
+    let entries = match extract::list_archive(archive) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("❌ Ошибка чтения {}: {}", archive.display(), e);
+            std::process::exit(1);
+        }
+    };
+    
+    let mut files = 0;
+    let mut total_size = 0u64;
+    for entry in &entries {
+        if entry.is_dir {
+            human!("{:>10}  {}/", "", entry.name.trim_end_matches('/'));
+        } else {
+            human!("{:>10}  {}", format_size(entry.size), entry.name);
+            files += 1;
+            total_size += entry.size;
+        }
+    }
+    
+    human!();
+    human!("📦 {} файл(ов), {}", files, format_size(total_size));
+}
+
+fn extract_archive(archive: &std::path::Path, output_dir: &std::path::Path, only: &[String]) {
+    let result = if only.is_empty() {
+        extract::extract_archive(archive, output_dir)
+    } else {
+        let include = match pattern::parse_patterns(only) {
+            Ok(include) => include,
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        };
+        extract::extract_entries(archive, output_dir, &include, None)
+    };
+    
+    match result {
+        Ok(result) => {
+            for (entry, reason) in &result.skipped {
+                human!("⚠️ Пропущено {}: {}", entry, reason);
+            }
+            human!("✅ Распаковано в {}: {}, {}", output_dir.display(), result.summary(), format_size(result.total_size));
+        }
+        Err(e) => {
+            eprintln!("❌ Ошибка распаковки {}: {}", archive.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Toolza Daemon - фоновый приёмник с управляющим API
+//!
+//! Принимает файлы как `toolza_cli receive` и слушает JSON-RPC на 127.0.0.1:
+//! `start_send`, `status`, `stop`, `history` (см. модуль `control`).
+//! Запросы принимаются только с токеном из файла `--token-file` (права 0600).
+
+use async_trait::async_trait;
+use clap::Parser;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use toolza_sender::control::{
+    self, ControlHandler, ControlRequest, DaemonStatus, SendStatus, StartSendParams, DEFAULT_CONTROL_PORT,
+};
+use toolza_sender::history::{HistoryEntry, TargetOutcome, TransferHistory};
+use toolza_sender::network::{self, CancelToken, TransferEvent, TransportType};
+use toolza_sender::protocol::{collect_files_from_folder, FileInfo, DEFAULT_PORT};
+use toolza_sender::utils::{format_size, get_local_ip_string, parse_size};
+
+#[derive(Parser)]
+#[command(name = "toolza_daemon")]
+#[command(author = "toolza")]
+#[command(version = "1.0")]
+#[command(about = "Фоновый приём файлов с управляющим API", long_about = None)]
+struct Args {
+    /// Порт для приёма файлов
+    #[arg(short, long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+    
+    /// Папка для сохранения файлов
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+    
+    /// Автоматически распаковывать tar.lz4 и tar.zst архивы
+    #[arg(short = 'x', long)]
+    extract: bool,
+    
+    /// Команда после приёма файла: "программа аргументы", {path} - путь, {dir} - папка.
+    /// Выполняется на файлах от отправителя - используйте только в доверенной сети
+    #[arg(long, value_name = "CMD")]
+    post_hook: Option<String>,
+    
+    /// Убивать post-hook, который не завершился за N секунд
+    #[arg(long, value_name = "SECS", default_value_t = network::DEFAULT_HOOK_TIMEOUT.as_secs(), value_parser = clap::value_parser!(u64).range(1..))]
+    hook_timeout: u64,
+    
+    /// Сколько post-hook выполнять одновременно, остальные ждут очереди
+    #[arg(long, value_name = "N", default_value_t = network::DEFAULT_MAX_HOOKS)]
+    max_hooks: usize,
+    
+    /// Отдавать метрики Prometheus на этом адресе (например 0.0.0.0:9100; нужна сборка с --features metrics)
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<SocketAddr>,
+    
+    /// Журнал аудита: подключения, принятые файлы и отказы строками JSON
+    #[arg(long, value_name = "PATH")]
+    audit_log: Option<PathBuf>,
+    
+    /// Пересылать принятые файлы этим получателям (IP или IP:порт, через запятую)
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    relay_to: Vec<String>,
+    
+    /// Страница загрузки файлов из браузера на этом адресе (например 0.0.0.0:8080; нужна сборка с --features webui)
+    #[arg(long, value_name = "ADDR")]
+    web_addr: Option<SocketAddr>,
+    
+    /// Имена страницы загрузки, кроме IP, localhost и имени машины (через запятую)
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    web_host: Vec<String>,
+    
+    /// Не принимать файлы с этими расширениями (например: exe,msi,bat)
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    block_ext: Vec<String>,
+    
+    /// Не принимать файлы больше этого размера (например: 2GB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_max_size)]
+    max_file_size: Option<u64>,
+    
+    /// Объявлять получателя в mDNS (_toolza._tcp.local; нужна сборка с --features mdns)
+    #[arg(long)]
+    mdns: bool,
+    
+    /// Транспортный протокол (tcp, udp, quic, kcp)
+    #[arg(long, default_value = "tcp", value_parser = parse_transport)]
+    transport: TransportType,
+    
+    /// Порт управляющего API (слушает только 127.0.0.1)
+    #[arg(long, default_value_t = DEFAULT_CONTROL_PORT)]
+    control_port: u16,
+    
+    /// Файл токена управляющего API: создаётся при запуске с правами только для владельца
+    /// (по умолчанию daemon.token рядом с историей)
+    #[arg(long, value_name = "PATH")]
+    token_file: Option<PathBuf>,
+}
+
+/// Разбор --transport
+fn parse_transport(s: &str) -> Result<TransportType, String> {
+    TransportType::from_str(s).ok_or_else(|| format!("Неизвестный протокол: {}", s))
+}
+
+fn parse_max_size(s: &str) -> Result<u64, String> {
+    parse_size(s).ok_or_else(|| format!("Неверный размер: {}", s))
+}
+
+/// Общее состояние демона
+struct Daemon {
+    status: Arc<Mutex<DaemonStatus>>,
+    /// Флаг остановки текущей отправки
+    send_cancel: Arc<Mutex<Option<CancelToken>>>,
+    port: u16,
+    transport_type: TransportType,
+}
+
+impl Daemon {
+    /// Начать отправку: собрать файлы и запустить передачу в фоне
+    fn start_send(&self, params: StartSendParams) -> Result<Value, String> {
+        if self.status.lock().unwrap().send.as_ref().is_some_and(|s| s.running) {
+            return Err("Отправка уже идёт".to_string());
+        }
+        if params.targets.is_empty() {
+            return Err("Не указаны получатели".to_string());
+        }
+        
+        let mut files: Vec<FileInfo> = Vec::new();
+        for path in &params.paths {
+            if path.is_dir() {
+                let folder_files = collect_files_from_folder(path)
+                    .map_err(|e| format!("Ошибка сканирования папки '{}': {}", path.display(), e))?;
+                files.extend(folder_files);
+            } else {
+                let info = FileInfo::new(path.clone())
+                    .map_err(|e| format!("Ошибка чтения файла '{}': {}", path.display(), e))?;
+                files.push(info);
+            }
+        }
+        if files.is_empty() {
+            return Err("Нет файлов для отправки".to_string());
+        }
+        
+        let targets: Vec<String> = params.targets
+            .iter()
+            .map(|t| network::parse_target_addr(t, self.port))
+            .collect::<Result<_, _>>()?;
+        
+        let files_total = files.len();
+        let total_bytes: u64 = files.iter().map(|f| f.size).sum();
+        
+        self.status.lock().unwrap().send = Some(SendStatus {
+            running: true,
+            targets: targets.clone(),
+            files_total,
+            files_completed: 0,
+            total_bytes,
+            errors: Vec::new(),
+        });
+        
+        let cancel = CancelToken::new();
+        *self.send_cancel.lock().unwrap() = Some(cancel.clone());
+        
+        let options = network::SendOptions {
+            compression: params.compress.into(),
+            transport_type: self.transport_type,
+            no_extract: params.no_extract,
+            checksum: true,
+            ..Default::default()
+        };
+        
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(network::send_files_to_multiple_with_cancel(targets.clone(), files, options, tx, cancel));
+        tokio::spawn(track_send(rx, self.status.clone(), targets, files_total, total_bytes));
+        
+        Ok(json!({ "files": files_total, "total_bytes": total_bytes }))
+    }
+    
+    /// Остановить текущую отправку
+    fn stop(&self) -> Value {
+        let running = self.status.lock().unwrap().send.as_ref().is_some_and(|s| s.running);
+        if running {
+            if let Some(cancel) = self.send_cancel.lock().unwrap().as_ref() {
+                cancel.cancel();
+            }
+        }
+        json!({ "stopped": running })
+    }
+}
+
+#[async_trait]
+impl ControlHandler for Daemon {
+    async fn handle(&self, request: ControlRequest) -> Result<Value, String> {
+        match request {
+            ControlRequest::StartSend(params) => self.start_send(params),
+            ControlRequest::Status => {
+                let status = self.status.lock().unwrap().clone();
+                serde_json::to_value(status).map_err(|e| e.to_string())
+            }
+            ControlRequest::Stop => Ok(self.stop()),
+            ControlRequest::History(params) => {
+                let history = TransferHistory::load();
+                let entries = control::history_slice(&history.entries, params.limit);
+                serde_json::to_value(entries).map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// Следить за событиями отправки и записать итог в историю
+async fn track_send(
+    mut rx: mpsc::UnboundedReceiver<TransferEvent>,
+    status: Arc<Mutex<DaemonStatus>>,
+    targets: Vec<String>,
+    files_total: usize,
+    total_bytes: u64,
+) {
+    let start = Instant::now();
+    let mut completed_targets = HashSet::new();
+    let mut target_errors = HashMap::new();
+    let mut first_error = None;
+    let mut checksum = None;
+    
+    while let Some(event) = rx.recv().await {
+        let mut status = status.lock().unwrap();
+        let Some(send) = status.send.as_mut() else { continue };
+        
+        match event {
+            TransferEvent::FileCompleted(..)
+            | TransferEvent::FileSkipped(..)
+            | TransferEvent::FileDeduplicated(..) => {
+                send.files_completed += 1;
+            }
+            TransferEvent::FileError(target_id, _, e) | TransferEvent::ConnectionError(target_id, e) => {
+                let message = format!("{}: {}", targets.get(target_id).map(String::as_str).unwrap_or("?"), e);
+                first_error.get_or_insert_with(|| message.clone());
+                target_errors.entry(target_id).or_insert(e);
+                send.errors.push(message);
+            }
+            TransferEvent::TargetChecksum(_, value) => {
+                checksum.get_or_insert(value);
+            }
+            TransferEvent::TargetCompleted(target_id) => {
+                completed_targets.insert(target_id);
+            }
+            TransferEvent::AllCompleted => {
+                send.running = false;
+                break;
+            }
+            _ => {}
+        }
+    }
+    
+    let addresses: Vec<String> = targets
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| completed_targets.contains(i))
+        .map(|(_, addr)| addr.clone())
+        .collect();
+    let success = !addresses.is_empty();
+    let outcomes = targets
+        .iter()
+        .enumerate()
+        .map(|(i, addr)| {
+            let success = completed_targets.contains(&i);
+            let error = if success { None } else { target_errors.remove(&i) };
+            TargetOutcome::new(addr.clone(), success, error)
+        })
+        .collect();
+    
+    let mut history = TransferHistory::load();
+    history.add(HistoryEntry::new_send(
+        files_total,
+        total_bytes,
+        start.elapsed().as_secs_f64(),
+        1.0,
+        addresses,
+        success,
+        first_error,
+    ).with_checksum(checksum).with_targets(outcomes));
+}
+
+/// Следить за событиями приёма
+async fn track_receive(mut rx: mpsc::UnboundedReceiver<TransferEvent>, status: Arc<Mutex<DaemonStatus>>) {
+    // Объявленный размер принимаемых файлов: засчитываются по FileReceiveEnded.
+    // FileReceived - и строки журнала, поэтому для счётчиков не годится
+    let mut receiving: HashMap<usize, u64> = HashMap::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            TransferEvent::ClientConnected(addr, intent) => {
+                println!("🔗 Подключение: {} ({})", addr, intent.name());
+                status.lock().unwrap().active_connections += 1;
+            }
+            TransferEvent::ClientDisconnected(..) => {
+                let mut status = status.lock().unwrap();
+                status.active_connections = status.active_connections.saturating_sub(1);
+            }
+            TransferEvent::FileReceived(name, size) => {
+                println!("📥 Получен: {} ({})", name, format_size(size));
+            }
+            TransferEvent::FileReceiveStarted(file_idx, _, size) => {
+                receiving.insert(file_idx, size);
+            }
+            TransferEvent::FileReceiveEnded(file_idx) => {
+                if let Some(size) = receiving.remove(&file_idx) {
+                    let mut status = status.lock().unwrap();
+                    status.files_received += 1;
+                    status.bytes_received += size;
+                }
+            }
+            TransferEvent::ConnectionError(_, e) => {
+                eprintln!("❌ Ошибка: {}", e);
+            }
+            TransferEvent::HookCompleted(path, _) => {
+                println!("🪝 Post-hook: {}", path);
+            }
+            TransferEvent::HookError(path, e) => {
+                eprintln!("❌ Post-hook для {}: {}", path, e);
+            }
+            TransferEvent::FileRejected(name, reason) => {
+                eprintln!("🚫 Отклонён {}: {}", name, reason);
+            }
+            TransferEvent::FileDeleted(name) => {
+                println!("🗑 Удалён (синхронизация): {}", name);
+            }
+            TransferEvent::DiskFull(name, kept) => {
+                eprintln!("💾 Диск заполнен: {} сохранён частично ({})", name, format_size(kept));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let save_dir = args.dir.unwrap_or_else(|| {
+        dirs::download_dir().unwrap_or_else(|| PathBuf::from("."))
+    });
+    
+    let control_addr = format!("127.0.0.1:{}", args.control_port);
+    let control_listener = match TcpListener::bind(&control_addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Не удалось открыть управляющий порт {}: {}", control_addr, e);
+            std::process::exit(1);
+        }
+    };
+    
+    let token_file = args.token_file.unwrap_or_else(control::token_file_path);
+    let token = match control::write_token_file(&token_file) {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("Не удалось записать токен управления {}: {}", token_file.display(), e);
+            std::process::exit(1);
+        }
+    };
+    
+    println!("🛰  Демон запущен");
+    println!("   IP: {}", get_local_ip_string());
+    println!("   Порт приёма: {}", args.port);
+    println!("   Протокол: {}", args.transport.name());
+    println!("   Сохранение в: {}", save_dir.display());
+    println!("   Управление: {} (токен: {})", control_addr, token_file.display());
+    
+    let status = Arc::new(Mutex::new(DaemonStatus {
+        port: args.port,
+        save_dir: save_dir.clone(),
+        transport: args.transport.name().to_string(),
+        ..Default::default()
+    }));
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    
+    let options = network::ServerOptions {
+        extract_options: network::ExtractOptions {
+            tar_lz4: args.extract,
+            tar_zst: args.extract,
+            ..Default::default()
+        },
+        transport_type: args.transport,
+        post_hook: args.post_hook.map(|cmd| cmd.split_whitespace().map(String::from).collect()),
+        hook_timeout: Some(Duration::from_secs(args.hook_timeout)),
+        max_hooks: args.max_hooks,
+        metrics_addr: args.metrics_addr,
+        audit_log: args.audit_log,
+        relay_to: args.relay_to,
+        web_addr: args.web_addr,
+        web_hosts: args.web_host,
+        blocked_extensions: args.block_ext,
+        max_file_size: args.max_file_size,
+        advertise_mdns: args.mdns,
+        ..Default::default()
+    };
+    
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(track_receive(rx, status.clone()));
+    
+    let server_stop = stop_flag.clone();
+    let port = args.port;
+    let server = tokio::spawn(async move {
+        network::run_server_with_options_and_stop(port, save_dir, options, tx, server_stop).await
+    });
+    
+    let daemon = Arc::new(Daemon {
+        status,
+        send_cancel: Arc::new(Mutex::new(None)),
+        port: args.port,
+        transport_type: args.transport,
+    });
+    tokio::spawn(control::serve_control(control_listener, daemon.clone(), token, stop_flag.clone()));
+    
+    tokio::select! {
+        result = server => {
+            if let Ok(Err(e)) = result {
+                eprintln!("Ошибка сервера: {}", e);
+                std::process::exit(1);
+            }
+        }
+        _ = tokio::signal::ctrl_c() => {
+            println!();
+            println!("Остановка...");
+        }
+    }
+    
+    daemon.stop();
+    stop_flag.store(true, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_track_receive_counts_only_files() {
+        let status = Arc::new(Mutex::new(DaemonStatus::default()));
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send(TransferEvent::FileReceived("[DEBUG] FileStart: a.txt".to_string(), 0)).unwrap();
+        tx.send(TransferEvent::FileReceiveStarted(7, "a.txt".to_string(), 1000)).unwrap();
+        tx.send(TransferEvent::FileReceived("✅ Докачано: 0.00 ГБ".to_string(), 400)).unwrap();
+        tx.send(TransferEvent::FileReceived("a.txt".to_string(), 1000)).unwrap();
+        tx.send(TransferEvent::FileReceiveEnded(7)).unwrap();
+        drop(tx);
+        
+        track_receive(rx, status.clone()).await;
+        
+        let status = status.lock().unwrap();
+        assert_eq!(status.files_received, 1);
+        assert_eq!(status.bytes_received, 1000);
+    }
+}
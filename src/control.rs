@@ -0,0 +1,534 @@
+//! Управляющий API демона - JSON-RPC 2.0 поверх локального TCP
+//!
+//! Один запрос или ответ - одна строка JSON. Поддерживаемые методы:
+//! `start_send`, `status`, `stop`, `history`.
+//!
+//! Каждый запрос несёт `token` - секрет из файла демона (`token_file_path`),
+//! доступного только его владельцу: порт на 127.0.0.1 открыт и другим
+//! пользователям машины, и страницам в браузере. Первая строка, которая
+//! не разобралась или не прошла проверку токена, закрывает соединение -
+//! HTTP-запрос браузера не доходит до JSON в своём теле.
+
+use crate::history::HistoryEntry;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Порт управляющего API по умолчанию
+pub const DEFAULT_CONTROL_PORT: u16 = 9530;
+
+/// Коды ошибок JSON-RPC
+pub const PARSE_ERROR: i32 = -32700;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+pub const SERVER_ERROR: i32 = -32000;
+/// Нет токена или он неверный
+pub const UNAUTHORIZED: i32 = -32001;
+
+/// Запрос JSON-RPC в том виде, в каком он приходит по сокету
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpcRequest {
+    #[serde(default)]
+    pub jsonrpc: Option<String>,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    /// Секрет демона из `token_file_path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// Ответ JSON-RPC
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+/// Ошибка JSON-RPC
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcResponse {
+    /// Успешный ответ
+    pub fn ok(id: Value, result: Value) -> Self {
+        Self { jsonrpc: "2.0".to_string(), id, result: Some(result), error: None }
+    }
+    
+    /// Ответ с ошибкой
+    pub fn err(id: Value, code: i32, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(RpcError { code, message: message.into() }),
+        }
+    }
+}
+
+/// Параметры `start_send`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StartSendParams {
+    /// Адреса получателей (IP или IP:порт)
+    pub targets: Vec<String>,
+    /// Файлы и папки для отправки (пути на машине демона)
+    pub paths: Vec<PathBuf>,
+    /// Использовать LZ4 сжатие
+    #[serde(default)]
+    pub compress: bool,
+    /// Просить получателя не распаковывать архивы
+    #[serde(default)]
+    pub no_extract: bool,
+}
+
+/// Параметры `history`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistoryParams {
+    /// Сколько последних записей вернуть (None - все)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Команда управляющего API
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlRequest {
+    /// Начать отправку файлов
+    StartSend(StartSendParams),
+    /// Состояние демона
+    Status,
+    /// Остановить текущую отправку
+    Stop,
+    /// Последние записи истории передач
+    History(HistoryParams),
+}
+
+impl ControlRequest {
+    /// Имя метода JSON-RPC
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::StartSend(_) => "start_send",
+            Self::Status => "status",
+            Self::Stop => "stop",
+            Self::History(_) => "history",
+        }
+    }
+    
+    /// Параметры для JSON-RPC
+    pub fn params(&self) -> Value {
+        match self {
+            Self::StartSend(params) => serde_json::to_value(params).unwrap_or(Value::Null),
+            Self::History(params) => serde_json::to_value(params).unwrap_or(Value::Null),
+            Self::Status | Self::Stop => Value::Null,
+        }
+    }
+    
+    /// Разобрать команду из запроса JSON-RPC
+    pub fn from_rpc(request: &RpcRequest) -> Result<Self, RpcError> {
+        let invalid = |e: serde_json::Error| RpcError {
+            code: INVALID_PARAMS,
+            message: format!("Неверные параметры: {}", e),
+        };
+        
+        match request.method.as_str() {
+            "start_send" => serde_json::from_value(request.params.clone())
+                .map(Self::StartSend)
+                .map_err(invalid),
+            "status" => Ok(Self::Status),
+            "stop" => Ok(Self::Stop),
+            "history" => {
+                if request.params.is_null() {
+                    Ok(Self::History(HistoryParams::default()))
+                } else {
+                    serde_json::from_value(request.params.clone())
+                        .map(Self::History)
+                        .map_err(invalid)
+                }
+            }
+            other => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Неизвестный метод: {}", other),
+            }),
+        }
+    }
+}
+
+/// Состояние текущей (или последней) отправки
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SendStatus {
+    /// Отправка ещё идёт
+    pub running: bool,
+    /// Получатели
+    pub targets: Vec<String>,
+    /// Всего файлов
+    pub files_total: usize,
+    /// Завершено файлов (по всем получателям)
+    pub files_completed: usize,
+    /// Общий размер в байтах
+    pub total_bytes: u64,
+    /// Ошибки по ходу передачи
+    pub errors: Vec<String>,
+}
+
+/// Ответ на `status`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    /// Порт приёма файлов
+    pub port: u16,
+    /// Папка для принятых файлов
+    pub save_dir: PathBuf,
+    /// Протокол приёма
+    pub transport: String,
+    /// Активных входящих подключений
+    pub active_connections: usize,
+    /// Принято файлов с момента запуска
+    pub files_received: usize,
+    /// Принято байт с момента запуска
+    pub bytes_received: u64,
+    /// Текущая или последняя отправка
+    pub send: Option<SendStatus>,
+}
+
+/// Обработчик команд управляющего API
+#[async_trait]
+pub trait ControlHandler: Send + Sync {
+    /// Выполнить команду и вернуть результат
+    async fn handle(&self, request: ControlRequest) -> Result<Value, String>;
+}
+
+/// Обработать одну строку запроса; выполняется только запрос с верным `token`
+pub async fn handle_line(line: &str, token: &str, handler: &dyn ControlHandler) -> RpcResponse {
+    let request: RpcRequest = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return RpcResponse::err(Value::Null, PARSE_ERROR, format!("Неверный JSON: {}", e)),
+    };
+    if !request.token.as_deref().is_some_and(|sent| tokens_equal(sent, token)) {
+        return RpcResponse::err(request.id, UNAUTHORIZED, "Неверный токен управляющего API");
+    }
+    
+    let command = match ControlRequest::from_rpc(&request) {
+        Ok(command) => command,
+        Err(e) => return RpcResponse::err(request.id, e.code, e.message),
+    };
+    
+    match handler.handle(command).await {
+        Ok(result) => RpcResponse::ok(request.id, result),
+        Err(e) => RpcResponse::err(request.id, SERVER_ERROR, e),
+    }
+}
+
+/// Сравнить токены за время, не зависящее от места первого расхождения
+fn tokens_equal(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Принимать управляющие подключения до установки флага остановки.
+/// `token` - секрет, который должен быть в каждом запросе
+pub async fn serve_control(
+    listener: TcpListener,
+    handler: Arc<dyn ControlHandler>,
+    token: String,
+    stop_flag: Arc<AtomicBool>,
+) {
+    let token: Arc<str> = token.into();
+    while !stop_flag.load(Ordering::SeqCst) {
+        let accepted = tokio::time::timeout(Duration::from_millis(100), listener.accept()).await;
+        let stream = match accepted {
+            Ok(Ok((stream, _))) => stream,
+            Ok(Err(_)) | Err(_) => continue,
+        };
+        
+        let (handler, token) = (handler.clone(), token.clone());
+        tokio::spawn(async move {
+            let _ = serve_connection(stream, &token, handler.as_ref()).await;
+        });
+    }
+}
+
+/// Обслужить одно управляющее подключение: запрос за запросом до закрытия
+/// или до первой строки, которая не разобралась или не прошла проверку токена
+async fn serve_connection(stream: TcpStream, token: &str, handler: &dyn ControlHandler) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        
+        let response = handle_line(&line, token, handler).await;
+        let rejected = response.error.as_ref().is_some_and(|e| e.code == PARSE_ERROR || e.code == UNAUTHORIZED);
+        let mut json = serde_json::to_string(&response)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        json.push('\n');
+        writer.write_all(json.as_bytes()).await?;
+        if rejected {
+            break;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Отправить команду демону с его токеном и дождаться результата
+pub async fn call(addr: &str, token: &str, request: &ControlRequest) -> Result<Value, String> {
+    let stream = TcpStream::connect(addr)
+        .await
+        .map_err(|e| format!("Не удалось подключиться к демону {}: {}", addr, e))?;
+    let (reader, mut writer) = stream.into_split();
+    
+    let rpc = RpcRequest {
+        jsonrpc: Some("2.0".to_string()),
+        id: Value::from(1),
+        method: request.method().to_string(),
+        params: request.params(),
+        token: Some(token.to_string()),
+    };
+    let mut json = serde_json::to_string(&rpc).map_err(|e| e.to_string())?;
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await.map_err(|e| e.to_string())?;
+    
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Демон закрыл соединение без ответа".to_string())?;
+    
+    let response: RpcResponse = serde_json::from_str(&line)
+        .map_err(|e| format!("Неверный ответ демона: {}", e))?;
+    
+    match (response.result, response.error) {
+        (_, Some(error)) => Err(error.message),
+        (Some(result), None) => Ok(result),
+        (None, None) => Ok(Value::Null),
+    }
+}
+
+/// Файл токена демона по умолчанию
+pub fn token_file_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("toolza_sender")
+        .join("daemon.token")
+}
+
+/// Создать новый токен и записать его в `path` с правами только для владельца (0600).
+/// Прежний файл заменяется: токен действителен, пока работает этот демон
+pub fn write_token_file(path: &std::path::Path) -> std::io::Result<String> {
+    use std::io::Write;
+    
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let token = crate::utils::random_token()?;
+    // Старый файл мог быть создан с другими правами - права задаются только при создании
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+/// Прочитать токен демона из `path`
+pub fn read_token_file(path: &std::path::Path) -> std::io::Result<String> {
+    Ok(std::fs::read_to_string(path)?.trim().to_string())
+}
+
+/// Последние `limit` записей истории (новые первыми)
+pub fn history_slice(entries: &[HistoryEntry], limit: Option<usize>) -> &[HistoryEntry] {
+    let count = limit.unwrap_or(entries.len()).min(entries.len());
+    &entries[..count]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    struct EchoHandler;
+    
+    #[async_trait]
+    impl ControlHandler for EchoHandler {
+        async fn handle(&self, request: ControlRequest) -> Result<Value, String> {
+            match request {
+                ControlRequest::Status => Ok(serde_json::to_value(DaemonStatus {
+                    port: 9527,
+                    ..Default::default()
+                }).unwrap()),
+                ControlRequest::Stop => Err("нечего останавливать".to_string()),
+                other => Ok(Value::from(other.method())),
+            }
+        }
+    }
+    
+    #[test]
+    fn test_parse_requests() {
+        let request: RpcRequest = serde_json::from_str(r#"{"id":1,"method":"status"}"#).unwrap();
+        assert_eq!(ControlRequest::from_rpc(&request).unwrap(), ControlRequest::Status);
+        
+        let request: RpcRequest = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":2,"method":"start_send","params":{"targets":["10.0.0.2"],"paths":["/tmp/a"]}}"#
+        ).unwrap();
+        assert_eq!(
+            ControlRequest::from_rpc(&request).unwrap(),
+            ControlRequest::StartSend(StartSendParams {
+                targets: vec!["10.0.0.2".to_string()],
+                paths: vec![PathBuf::from("/tmp/a")],
+                compress: false,
+                no_extract: false,
+            })
+        );
+        
+        let request: RpcRequest = serde_json::from_str(r#"{"id":3,"method":"history"}"#).unwrap();
+        assert_eq!(
+            ControlRequest::from_rpc(&request).unwrap(),
+            ControlRequest::History(HistoryParams { limit: None })
+        );
+    }
+    
+    #[test]
+    fn test_parse_errors() {
+        let request: RpcRequest = serde_json::from_str(r#"{"id":1,"method":"reboot"}"#).unwrap();
+        assert_eq!(ControlRequest::from_rpc(&request).unwrap_err().code, METHOD_NOT_FOUND);
+        
+        let request: RpcRequest = serde_json::from_str(r#"{"id":1,"method":"start_send","params":{}}"#).unwrap();
+        assert_eq!(ControlRequest::from_rpc(&request).unwrap_err().code, INVALID_PARAMS);
+    }
+    
+    #[test]
+    fn test_request_roundtrip() {
+        let requests = vec![
+            ControlRequest::StartSend(StartSendParams {
+                targets: vec!["a".to_string()],
+                paths: vec![PathBuf::from("b")],
+                compress: true,
+                no_extract: true,
+            }),
+            ControlRequest::Status,
+            ControlRequest::Stop,
+            ControlRequest::History(HistoryParams { limit: Some(5) }),
+        ];
+        
+        for request in requests {
+            let rpc = RpcRequest {
+                jsonrpc: None,
+                id: Value::Null,
+                method: request.method().to_string(),
+                params: request.params(),
+                token: None,
+            };
+            assert_eq!(ControlRequest::from_rpc(&rpc).unwrap(), request);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_handle_line() {
+        let response = handle_line("not json", "secret", &EchoHandler).await;
+        assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+        
+        let response = handle_line(r#"{"id":7,"method":"stop","token":"secret"}"#, "secret", &EchoHandler).await;
+        assert_eq!(response.id, Value::from(7));
+        assert_eq!(response.error.unwrap().code, SERVER_ERROR);
+        
+        // Без токена или с чужим команда не выполняется
+        let response = handle_line(r#"{"id":8,"method":"status"}"#, "secret", &EchoHandler).await;
+        assert_eq!(response.error.unwrap().code, UNAUTHORIZED);
+        let response = handle_line(r#"{"id":9,"method":"status","token":"secreT"}"#, "secret", &EchoHandler).await;
+        assert_eq!(response.error.unwrap().code, UNAUTHORIZED);
+    }
+    
+    #[tokio::test]
+    async fn test_call_over_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        tokio::spawn(serve_control(listener, Arc::new(EchoHandler), "secret".to_string(), stop_flag.clone()));
+        
+        let result = call(&addr, "secret", &ControlRequest::Status).await.unwrap();
+        let status: DaemonStatus = serde_json::from_value(result).unwrap();
+        assert_eq!(status.port, 9527);
+        
+        let error = call(&addr, "secret", &ControlRequest::Stop).await.unwrap_err();
+        assert_eq!(error, "нечего останавливать");
+        
+        let error = call(&addr, "wrong", &ControlRequest::Status).await.unwrap_err();
+        assert_eq!(error, "Неверный токен управляющего API");
+        
+        stop_flag.store(true, Ordering::SeqCst);
+    }
+    
+    #[tokio::test]
+    async fn test_http_request_closes_connection() {
+        use tokio::io::AsyncReadExt;
+        
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        tokio::spawn(serve_control(listener, Arc::new(EchoHandler), "secret".to_string(), stop_flag.clone()));
+        
+        // fetch() из браузера: строка запроса, заголовки, JSON в теле
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(
+            b"POST / HTTP/1.1\r\nHost: 127.0.0.1\r\nContent-Type: text/plain\r\n\r\n{\"id\":1,\"method\":\"status\",\"token\":\"secret\"}\n"
+        ).await.unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).await.unwrap();
+        
+        // Одна ошибка разбора на строку запроса - и соединение закрыто
+        assert_eq!(reply.lines().count(), 1);
+        let response: RpcResponse = serde_json::from_str(reply.trim()).unwrap();
+        assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+        
+        stop_flag.store(true, Ordering::SeqCst);
+    }
+    
+    #[test]
+    fn test_token_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("daemon.token");
+        std::fs::write(&path, "old").unwrap();
+        
+        let token = write_token_file(&path).unwrap();
+        assert_eq!(token.len(), 64);
+        assert_eq!(read_token_file(&path).unwrap(), token);
+        assert_ne!(write_token_file(&path).unwrap(), token);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+    
+    #[test]
+    fn test_history_slice() {
+        let entries: Vec<HistoryEntry> = (0..3)
+            .map(|i| HistoryEntry::new_send(i, 0, 0.0, 1.0, vec![], true, None))
+            .collect();
+        assert_eq!(history_slice(&entries, None).len(), 3);
+        assert_eq!(history_slice(&entries, Some(2)).len(), 2);
+        assert_eq!(history_slice(&entries, Some(10)).len(), 3);
+    }
+}
@@ -0,0 +1,247 @@
+//! Ограничения на длину и вложенность путей при распаковке
+//!
+//! Архив может содержать записи с очень глубокой вложенностью или длинными
+//! путями, которые файловая система не примет (PATH_MAX на Unix, 260 символов
+//! на Windows). Такие записи пропускаются по одной, остальной архив распаковывается.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Максимум уровней вложенности записи по умолчанию
+pub const DEFAULT_MAX_PATH_COMPONENTS: usize = 128;
+/// Максимальная длина итогового пути по умолчанию (PATH_MAX на Linux)
+pub const DEFAULT_MAX_PATH_LEN: usize = 4096;
+/// Лимит Windows без префикса `\\?\` (MAX_PATH без завершающего нуля)
+pub const WINDOWS_MAX_PATH: usize = 259;
+
+/// Лимиты путей для записей архива
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathLimits {
+    /// Максимум компонентов относительного пути записи
+    pub max_components: usize,
+    /// Максимальная длина итогового пути (папка распаковки + запись), в байтах
+    pub max_path_len: usize,
+    /// Windows: писать через префикс `\\?\`, снимая ограничение в 260 символов.
+    /// На других системах не влияет
+    pub long_paths: bool,
+    /// Отбросить N ведущих компонентов пути записи (как `tar --strip-components`)
+    pub strip_components: usize,
+    /// Восстанавливать расширенные атрибуты файлов из PAX-заголовков tar
+    /// (`SCHILY.xattr.*`, как пишет `tar --xattrs`). Только Unix
+    pub xattrs: bool,
+}
+
+impl Default for PathLimits {
+    fn default() -> Self {
+        Self {
+            max_components: DEFAULT_MAX_PATH_COMPONENTS,
+            max_path_len: DEFAULT_MAX_PATH_LEN,
+            long_paths: false,
+            strip_components: 0,
+            xattrs: false,
+        }
+    }
+}
+
+impl PathLimits {
+    /// Действующий лимит длины с учётом MAX_PATH на Windows
+    pub fn effective_max_len(&self) -> usize {
+        if cfg!(windows) && !self.long_paths {
+            self.max_path_len.min(WINDOWS_MAX_PATH)
+        } else {
+            self.max_path_len
+        }
+    }
+    
+    /// Проверить запись архива и получить путь для записи на диск.
+    ///
+    /// Ok(None) - запись целиком срезана `strip_components` (например, сама
+    /// верхняя папка), Err(причина) - запись нужно пропустить с предупреждением.
+    pub fn resolve(&self, output_dir: &Path, entry_path: &Path) -> Result<Option<PathBuf>, String> {
+        // Запись `./` - сама папка архива, писать нечего
+        if entry_path.components().all(|c| c == Component::CurDir) {
+            return Ok(None);
+        }
+        let sanitized = sanitize_relative_path(entry_path)?;
+        let stripped;
+        let entry_path = if self.strip_components > 0 {
+            match strip_leading_components(&sanitized, self.strip_components)? {
+                Some(path) => {
+                    stripped = path;
+                    stripped.as_path()
+                }
+                None => return Ok(None),
+            }
+        } else {
+            sanitized.as_path()
+        };
+        
+        let depth = entry_path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .count();
+        if depth > self.max_components {
+            return Err(format!(
+                "слишком глубокая вложенность: {} уровней (максимум {})",
+                depth, self.max_components
+            ));
+        }
+        
+        let full_path = output_dir.join(entry_path);
+        let len = full_path.as_os_str().len();
+        let max_len = self.effective_max_len();
+        if len > max_len {
+            return Err(format!("слишком длинный путь: {} байт (максимум {})", len, max_len));
+        }
+        
+        Ok(Some(self.apply_long_path_prefix(full_path)))
+    }
+    
+    /// Добавить префикс `\\?\` к абсолютному пути (только Windows и long_paths)
+    #[cfg(windows)]
+    fn apply_long_path_prefix(&self, path: PathBuf) -> PathBuf {
+        if !self.long_paths {
+            return path;
+        }
+        let Ok(absolute) = std::path::absolute(&path) else {
+            return path;
+        };
+        let s = absolute.to_string_lossy();
+        if s.starts_with(r"\\?\") {
+            absolute
+        } else if let Some(unc) = s.strip_prefix(r"\\") {
+            PathBuf::from(format!(r"\\?\UNC\{}", unc))
+        } else {
+            PathBuf::from(format!(r"\\?\{}", s))
+        }
+    }
+    
+    #[cfg(not(windows))]
+    fn apply_long_path_prefix(&self, path: PathBuf) -> PathBuf {
+        path
+    }
+}
+
+/// Проверить относительный путь от отправителя или из архива, прежде чем
+/// приклеить его к папке получателя.
+///
+/// Абсолютные пути, `..` и префиксы дисков (`C:`, в том числе на Unix - такой путь
+/// пришёл бы с Windows) отклоняются целиком; `.` отбрасывается. Пустой путь - ошибка
+pub fn sanitize_relative_path(path: &Path) -> Result<PathBuf, String> {
+    let mut sanitized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => {
+                if sanitized.as_os_str().is_empty() && is_drive_prefix(&name.to_string_lossy()) {
+                    return Err(format!("небезопасный путь: {}", path.display()));
+                }
+                sanitized.push(name);
+            }
+            Component::CurDir => {}
+            _ => return Err(format!("небезопасный путь: {}", path.display())),
+        }
+    }
+    
+    if sanitized.as_os_str().is_empty() {
+        return Err(format!("пустой путь: '{}'", path.display()));
+    }
+    Ok(sanitized)
+}
+
+/// Начинается с буквы диска: `C:`, `c:foo`, `C:\Windows`
+fn is_drive_prefix(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// Отбросить `count` ведущих компонентов пути.
+///
+/// Срезать можно только обычные имена: путь с `..`, корнем или префиксом диска
+/// отклоняется целиком, иначе после среза он мог бы выйти за папку распаковки.
+/// Ok(None) - у записи не больше `count` компонентов.
+pub fn strip_leading_components(path: &Path, count: usize) -> Result<Option<PathBuf>, String> {
+    let mut names = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => names.push(name),
+            Component::CurDir => {}
+            _ => return Err(format!("небезопасный путь: {}", path.display())),
+        }
+    }
+    
+    if names.len() <= count {
+        return Ok(None);
+    }
+    Ok(Some(names[count..].iter().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_resolve_accepts_normal_path() {
+        let limits = PathLimits::default();
+        let path = limits.resolve(Path::new("/out"), Path::new("a/b/c.txt")).unwrap();
+        assert_eq!(path, Some(Path::new("/out").join("a/b/c.txt")));
+    }
+    
+    #[test]
+    fn test_resolve_rejects_deep_nesting() {
+        let limits = PathLimits { max_components: 3, ..Default::default() };
+        assert!(limits.resolve(Path::new("/out"), Path::new("a/b/c")).is_ok());
+        
+        let err = limits.resolve(Path::new("/out"), Path::new("a/b/c/d")).unwrap_err();
+        assert!(err.contains("вложенность"));
+    }
+    
+    #[test]
+    fn test_resolve_rejects_long_path() {
+        let limits = PathLimits { max_path_len: 20, ..Default::default() };
+        assert!(limits.resolve(Path::new("/out"), Path::new("short.txt")).is_ok());
+        
+        let long_name = "x".repeat(30);
+        let err = limits.resolve(Path::new("/out"), Path::new(&long_name)).unwrap_err();
+        assert!(err.contains("длинный путь"));
+    }
+    
+    #[test]
+    fn test_strip_components() {
+        let limits = PathLimits { strip_components: 1, ..Default::default() };
+        let out = Path::new("/out");
+        
+        assert_eq!(
+            limits.resolve(out, Path::new("project-1.2.3/src/main.rs")).unwrap(),
+            Some(out.join("src/main.rs"))
+        );
+        assert_eq!(limits.resolve(out, Path::new("./project-1.2.3/README")).unwrap(), Some(out.join("README")));
+        // Сама верхняя папка и файлы рядом с ней отбрасываются
+        assert_eq!(limits.resolve(out, Path::new("project-1.2.3/")).unwrap(), None);
+        assert_eq!(limits.resolve(out, Path::new("top.txt")).unwrap(), None);
+    }
+    
+    #[test]
+    fn test_sanitize_relative_path() {
+        assert_eq!(sanitize_relative_path(Path::new("a/./b.txt")).unwrap(), PathBuf::from("a/b.txt"));
+        
+        for path in ["../etc/passwd", "a/../../etc/passwd", "/etc/passwd", "C:\\Windows\\win.ini", "c:evil.txt", "", "./."] {
+            assert!(sanitize_relative_path(Path::new(path)).is_err(), "{}", path);
+        }
+        // Двоеточие не в начале - обычное имя
+        assert!(sanitize_relative_path(Path::new("notes/10:30.txt")).is_ok());
+    }
+    
+    #[test]
+    fn test_resolve_rejects_escaping_paths() {
+        let limits = PathLimits::default();
+        assert!(limits.resolve(Path::new("/out"), Path::new("../../etc/passwd")).is_err());
+        assert!(limits.resolve(Path::new("/out"), Path::new("/etc/passwd")).is_err());
+        assert_eq!(limits.resolve(Path::new("/out"), Path::new("./")).unwrap(), None);
+    }
+    
+    #[test]
+    fn test_strip_rejects_escaping_paths() {
+        let limits = PathLimits { strip_components: 1, ..Default::default() };
+        assert!(limits.resolve(Path::new("/out"), Path::new("a/../../etc/passwd")).is_err());
+        assert!(limits.resolve(Path::new("/out"), Path::new("/abs/etc/passwd")).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! Папки и символические ссылки при распаковке
+//!
+//! Запись архива не должна попасть за папку распаковки ни через ссылку из того же
+//! архива (`link -> /etc`, затем `link/passwd`), ни через ссылку, которая уже
+//! лежала в папке. Поэтому папки создаются по одной без перехода по ссылкам,
+//! а ссылки из архива создаются, только если их цель остаётся внутри папки.
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+
+/// Создать папку `dir` и недостающие папки над ней внутри `output_dir`.
+/// Компонент пути, который уже есть и является ссылкой, - ошибка
+pub(crate) fn create_dir_within(output_dir: &Path, dir: &Path) -> io::Result<()> {
+    let Ok(relative) = dir.strip_prefix(output_dir) else {
+        // Путь с префиксом `\\?\` (long_paths на Windows): ссылки из архивов там не создаются
+        return fs::create_dir_all(dir);
+    };
+    fs::create_dir_all(output_dir)?;
+    let mut current = output_dir.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(meta) if meta.file_type().is_symlink() => return Err(through_symlink(&current)),
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => match fs::create_dir(&current) {
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+                _ => {}
+            },
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Подготовить место для файла из архива: папки над ним - через `create_dir_within`,
+/// ссылка на месте самого файла удаляется, чтобы запись не ушла по ней
+pub(crate) fn prepare_file_path(output_dir: &Path, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_within(output_dir, parent)?;
+    }
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+/// Создать ссылку `path` -> `target` из архива.
+///
+/// Err(причина) - цель абсолютная или через `..` выходит за `output_dir`:
+/// запись пропускается. Ok(false) - ссылку создать не удалось (например,
+/// без прав на Windows), её тоже пропускаем
+pub(crate) fn unpack_symlink(output_dir: &Path, path: &Path, target: &Path) -> Result<bool, String> {
+    // Глубина папки ссылки внутри output_dir: столько раз цель может подняться через `..`
+    let mut depth = path
+        .strip_prefix(output_dir)
+        .map_or(0, |relative| relative.components().count().saturating_sub(1));
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return Err(format!("ссылка ведёт за папку распаковки: {}", target.display())),
+        }
+    }
+    
+    if prepare_file_path(output_dir, path).is_err() {
+        return Ok(false);
+    }
+    #[cfg(unix)]
+    let created = std::os::unix::fs::symlink(target, path).is_ok();
+    #[cfg(not(unix))]
+    let created = false;
+    Ok(created)
+}
+
+fn through_symlink(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("путь проходит через символическую ссылку: {}", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_symlink_checks_target() {
+        let out = tempfile::tempdir().unwrap();
+        let out = out.path();
+        
+        assert_eq!(unpack_symlink(out, &out.join("sub/up"), Path::new("../file.txt")), Ok(true));
+        assert_eq!(unpack_symlink(out, &out.join("same"), Path::new("./sub/up")), Ok(true));
+        for target in ["/etc/passwd", "../outside", "sub/../../outside"] {
+            assert!(unpack_symlink(out, &out.join("link"), Path::new(target)).is_err(), "{}", target);
+        }
+        assert!(!out.join("link").exists());
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_no_writes_through_symlinked_dirs() {
+        let out = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(elsewhere.path(), out.path().join("escape")).unwrap();
+        
+        assert!(create_dir_within(out.path(), &out.path().join("escape/new")).is_err());
+        assert!(prepare_file_path(out.path(), &out.path().join("escape/file.txt")).is_err());
+        assert!(!elsewhere.path().join("new").exists());
+        
+        // Ссылка на месте самого файла заменяется файлом
+        let victim = elsewhere.path().join("victim.txt");
+        std::fs::write(&victim, b"keep").unwrap();
+        std::os::unix::fs::symlink(&victim, out.path().join("file.txt")).unwrap();
+        prepare_file_path(out.path(), &out.path().join("file.txt")).unwrap();
+        std::fs::write(out.path().join("file.txt"), b"new").unwrap();
+        assert_eq!(std::fs::read(&victim).unwrap(), b"keep");
+    }
+}
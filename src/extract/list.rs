@@ -0,0 +1,172 @@
+//! Просмотр содержимого архива без распаковки
+
+use super::types::ArchiveType;
+use lz4_flex::frame::FrameDecoder;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+/// Запись архива
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Путь внутри архива
+    pub name: String,
+    /// Размер (распакованный)
+    pub size: u64,
+    /// Запись - папка
+    pub is_dir: bool,
+    /// Unix-права (если архив их хранит)
+    pub mode: Option<u32>,
+}
+
+/// Получить список записей архива (автоопределение типа).
+///
+/// Архив читается теми же декодерами, что и при распаковке, но ничего не пишется
+/// на диск. tar.lz4/tar.zst читаются потоково, zip - по центральному каталогу.
+pub fn list_archive(archive_path: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    let filename = archive_path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    
+    let archive_type = ArchiveType::from_filename(filename);
+    
+    if let Some(reader) = open_tar_stream(archive_path, archive_type)? {
+        return list_tar(reader);
+    }
+    
+    match archive_type {
+        ArchiveType::Zip => list_zip(archive_path),
+        ArchiveType::Lz4 | ArchiveType::Zst => {
+            // Одиночный файл: размер известен только после декодирования
+            let file = BufReader::new(File::open(archive_path)?);
+            let size = super::single::decompress_single(file, archive_type, &mut io::sink())?;
+            let name = filename[..filename.len() - 4].to_string();
+            Ok(vec![ArchiveEntry { name, size, is_dir: false, mode: None }])
+        }
+        ArchiveType::Rar | ArchiveType::SevenZip => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Просмотр rar/7z не поддерживается",
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Неизвестный формат архива",
+        )),
+    }
+}
+
+/// Открыть tar-поток архива с нужным декодером (None - архив не tar)
+pub(super) fn open_tar_stream(archive_path: &Path, archive_type: ArchiveType) -> io::Result<Option<Box<dyn Read>>> {
+    let open = || File::open(archive_path).map(BufReader::new);
+    
+    let reader: Box<dyn Read> = match archive_type {
+        ArchiveType::TarLz4 => Box::new(FrameDecoder::new(open()?)),
+        ArchiveType::TarZst => {
+            let mut decoder = zstd::stream::Decoder::with_buffer(open()?)?;
+            decoder.window_log_max(31)?;
+            Box::new(decoder)
+        }
+        ArchiveType::Tar => Box::new(open()?),
+        ArchiveType::TarGz => Box::new(flate2::read::GzDecoder::new(open()?)),
+        _ => return Ok(None),
+    };
+    Ok(Some(reader))
+}
+
+/// Записи tar-потока
+fn list_tar<R: Read>(reader: R) -> io::Result<Vec<ArchiveEntry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        entries.push(ArchiveEntry {
+            name: entry.path()?.to_string_lossy().to_string(),
+            size: header.size()?,
+            is_dir: header.entry_type().is_dir(),
+            mode: header.mode().ok(),
+        });
+    }
+    
+    Ok(entries)
+}
+
+/// Записи zip по центральному каталогу (без распаковки данных)
+fn list_zip(archive_path: &Path) -> io::Result<Vec<ArchiveEntry>> {
+    let file = File::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        entries.push(ArchiveEntry {
+            name: file.name().to_string(),
+            size: file.size(),
+            is_dir: file.is_dir(),
+            mode: file.unix_mode(),
+        });
+    }
+    
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    
+    fn build_tar() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        
+        let mut dir = tar::Header::new_gnu();
+        dir.set_entry_type(tar::EntryType::Directory);
+        dir.set_mode(0o755);
+        dir.set_size(0);
+        builder.append_data(&mut dir, "docs/", io::empty()).unwrap();
+        
+        let mut file = tar::Header::new_gnu();
+        file.set_mode(0o644);
+        file.set_size(5);
+        builder.append_data(&mut file, "docs/a.txt", &b"hello"[..]).unwrap();
+        
+        builder.into_inner().unwrap()
+    }
+    
+    #[test]
+    fn test_list_tar_lz4() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.tar.lz4");
+        
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(File::create(&path).unwrap());
+        encoder.write_all(&build_tar()).unwrap();
+        encoder.finish().unwrap();
+        
+        let entries = list_archive(&path).unwrap();
+        assert_eq!(entries, vec![
+            ArchiveEntry { name: "docs/".to_string(), size: 0, is_dir: true, mode: Some(0o755) },
+            ArchiveEntry { name: "docs/a.txt".to_string(), size: 5, is_dir: false, mode: Some(0o644) },
+        ]);
+        // Листинг ничего не распаковывает
+        assert!(!dir.path().join("docs").exists());
+    }
+    
+    #[test]
+    fn test_list_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.zip");
+        
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        writer.add_directory("docs/", zip::write::SimpleFileOptions::default()).unwrap();
+        writer.start_file("docs/a.txt", zip::write::SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"hello").unwrap();
+        writer.finish().unwrap();
+        
+        let entries = list_archive(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[1].name, "docs/a.txt");
+        assert_eq!(entries[1].size, 5);
+    }
+}
@@ -1,5 +1,7 @@
 //! Распаковка LZ4 и tar.lz4 архивов
 
+use super::limits::PathLimits;
+use super::links::{create_dir_within, prepare_file_path, unpack_symlink};
 use super::types::ExtractResult;
 use lz4_flex::frame::FrameDecoder;
 use std::fs::{self, File};
@@ -78,19 +80,21 @@ pub fn extract_lz4_streaming(
     Ok(ExtractResult {
         files_count: 1,
         total_size,
+        ..Default::default()
     })
 }
 
 /// Распаковать tar.lz4 архив (потоковая распаковка - не грузит в RAM)
 pub fn extract_tar_lz4(archive_path: &Path, output_dir: &Path) -> io::Result<ExtractResult> {
-    extract_tar_lz4_streaming(archive_path, output_dir, None)
+    extract_tar_lz4_streaming(archive_path, output_dir, None, &PathLimits::default())
 }
 
 /// Распаковать tar.lz4 архив с поддержкой остановки (потоковая)
 pub fn extract_tar_lz4_streaming(
     archive_path: &Path, 
     output_dir: &Path,
-    stop_flag: Option<Arc<AtomicBool>>
+    stop_flag: Option<Arc<AtomicBool>>,
+    limits: &PathLimits,
 ) -> io::Result<ExtractResult> {
     let file = File::open(archive_path)?;
     let reader = BufReader::with_capacity(READ_BUFFER_SIZE, file); // 64MB буфер
@@ -103,6 +107,9 @@ pub fn extract_tar_lz4_streaming(
     
     let mut files_count = 0;
     let mut total_size = 0u64;
+    let mut dirs_count = 0;
+    let mut symlinks_count = 0;
+    let mut skipped = Vec::new();
     
     for entry in archive.entries()? {
         // Проверяем флаг остановки
@@ -116,14 +123,29 @@ pub fn extract_tar_lz4_streaming(
         }
         
         let mut entry = entry?;
-        let path = output_dir.join(entry.path()?);
+        let entry_path = entry.path()?.into_owned();
+        let path = match limits.resolve(output_dir, &entry_path) {
+            Ok(Some(path)) => path,
+            Ok(None) => continue,
+            Err(reason) => {
+                skipped.push((entry_path.display().to_string(), reason));
+                continue;
+            }
+        };
         
-        if entry.header().entry_type().is_dir() {
-            fs::create_dir_all(&path)?;
-        } else if entry.header().entry_type().is_file() {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            create_dir_within(output_dir, &path)?;
+            dirs_count += 1;
+        } else if entry_type.is_symlink() {
+            let target = entry.link_name()?.unwrap_or_default().into_owned();
+            match unpack_symlink(output_dir, &path, &target) {
+                Ok(true) => symlinks_count += 1,
+                Ok(false) => {}
+                Err(reason) => skipped.push((entry_path.display().to_string(), reason)),
             }
+        } else if entry_type.is_file() {
+            prepare_file_path(output_dir, &path)?;
             let size = entry.header().size()?;
             
             // Ручная распаковка с большим буфером записи для NAS
@@ -131,13 +153,16 @@ pub fn extract_tar_lz4_streaming(
             let mut writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, out_file);
             buffered_copy(&mut entry, &mut writer)?;
             writer.flush()?;
+            if limits.xattrs {
+                let _ = crate::utils::apply_xattrs(&path, &crate::utils::pax_xattrs(&mut entry));
+            }
             
             files_count += 1;
             total_size += size;
         }
     }
     
-    Ok(ExtractResult { files_count, total_size })
+    Ok(ExtractResult { files_count, total_size, dirs_count, symlinks_count, skipped })
 }
 
 /// Копирование с большим буфером (16 МБ чанки)
@@ -159,7 +184,7 @@ fn buffered_copy<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> io::Resul
 
 /// Синхронная распаковка tar.lz4 (алиас для потоковой версии)
 pub fn extract_tar_lz4_simple(archive_path: &Path, output_dir: &Path) -> io::Result<ExtractResult> {
-    extract_tar_lz4_streaming(archive_path, output_dir, None)
+    extract_tar_lz4_streaming(archive_path, output_dir, None, &PathLimits::default())
 }
 
 
@@ -1,21 +1,33 @@
 //! Модуль распаковки архивов
 //!
 //! Поддерживаемые форматы:
-//! - tar.lz4, tar.zst, lz4
+//! - tar.lz4, tar.zst, lz4, zst
 //! - tar, tar.gz
 //! - zip
 
 mod types;
+mod limits;
+mod links;
+mod list;
+mod select;
+mod repack;
 mod tar;
 mod lz4;
 mod zst;
+mod single;
 mod zip;
 
 pub use types::{ArchiveType, ExtractResult, ExtractOptions};
+pub use list::{list_archive, ArchiveEntry};
+pub use select::extract_entries;
+pub use repack::repack_tar_without;
+pub use limits::{sanitize_relative_path, PathLimits, DEFAULT_MAX_PATH_COMPONENTS, DEFAULT_MAX_PATH_LEN, WINDOWS_MAX_PATH};
 pub use tar::{extract_tar, extract_tar_gz, extract_tar_streaming, extract_tar_gz_streaming};
 pub use lz4::{extract_lz4, extract_lz4_streaming, extract_tar_lz4, extract_tar_lz4_streaming, extract_tar_lz4_simple};
 pub use zst::{extract_tar_zst, extract_tar_zst_streaming, extract_tar_zst_simple};
-pub use zip::extract_zip;
+pub use single::{decompress_single, decompress_single_to_file, extract_zst, single_file_output_name};
+pub use zip::{extract_zip, extract_zip_with_limits};
+pub(crate) use links::{create_dir_within, prepare_file_path, unpack_symlink};
 
 use std::io;
 use std::path::Path;
@@ -42,18 +54,32 @@ pub fn extract_archive_streaming(
     archive_path: &Path, 
     output_dir: &Path,
     stop_flag: Option<Arc<AtomicBool>>
+) -> io::Result<ExtractResult> {
+    extract_archive_with_limits(archive_path, output_dir, stop_flag, &PathLimits::default())
+}
+
+/// Распаковать архив с остановкой и лимитами пути.
+///
+/// Записи со слишком глубокой вложенностью или длинным путём пропускаются
+/// (см. `ExtractResult::skipped`), остальные распаковываются.
+pub fn extract_archive_with_limits(
+    archive_path: &Path,
+    output_dir: &Path,
+    stop_flag: Option<Arc<AtomicBool>>,
+    limits: &PathLimits,
 ) -> io::Result<ExtractResult> {
     let filename = archive_path.file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("");
     
     match ArchiveType::from_filename(filename) {
-        ArchiveType::TarLz4 => extract_tar_lz4_streaming(archive_path, output_dir, stop_flag),
-        ArchiveType::TarZst => extract_tar_zst_streaming(archive_path, output_dir, stop_flag),
+        ArchiveType::TarLz4 => extract_tar_lz4_streaming(archive_path, output_dir, stop_flag, limits),
+        ArchiveType::TarZst => extract_tar_zst_streaming(archive_path, output_dir, stop_flag, limits),
         ArchiveType::Lz4 => extract_lz4_streaming(archive_path, output_dir, stop_flag),
-        ArchiveType::Tar => extract_tar_streaming(archive_path, output_dir, stop_flag),
-        ArchiveType::TarGz => extract_tar_gz_streaming(archive_path, output_dir, stop_flag),
-        ArchiveType::Zip => extract_zip(archive_path, output_dir), // zip не имеет streaming версии пока
+        ArchiveType::Zst => extract_zst(archive_path, output_dir),
+        ArchiveType::Tar => extract_tar_streaming(archive_path, output_dir, stop_flag, limits),
+        ArchiveType::TarGz => extract_tar_gz_streaming(archive_path, output_dir, stop_flag, limits),
+        ArchiveType::Zip => extract_zip_with_limits(archive_path, output_dir, limits), // zip не имеет streaming версии пока
         ArchiveType::Rar => Err(io::Error::new(
             io::ErrorKind::Unsupported,
             "RAR распаковка требует внешнего unrar. Используйте: unrar x archive.rar"
@@ -89,6 +115,8 @@ mod tests {
     fn test_archive_type() {
         assert_eq!(ArchiveType::from_filename("test.tar.lz4"), ArchiveType::TarLz4);
         assert_eq!(ArchiveType::from_filename("test.lz4"), ArchiveType::Lz4);
+        assert_eq!(ArchiveType::from_filename("test.tar.zst"), ArchiveType::TarZst);
+        assert_eq!(ArchiveType::from_filename("test.zst"), ArchiveType::Zst);
         assert_eq!(ArchiveType::from_filename("test.tar"), ArchiveType::Tar);
         assert_eq!(ArchiveType::from_filename("test.tar.gz"), ArchiveType::TarGz);
         assert_eq!(ArchiveType::from_filename("test.zip"), ArchiveType::Zip);
@@ -102,9 +130,156 @@ mod tests {
         let result = ExtractResult {
             files_count: 10,
             total_size: 1024,
+            dirs_count: 2,
+            symlinks_count: 1,
+            skipped: Vec::new(),
         };
         assert_eq!(result.files_count, 10);
         assert_eq!(result.total_size, 1024);
+        assert_eq!(result.summary(), "10 файл(ов), 2 папок, 1 ссылок");
+        
+        let only_files = ExtractResult { files_count: 3, ..Default::default() };
+        assert_eq!(only_files.summary(), "3 файл(ов)");
+        
+        let with_skipped = ExtractResult {
+            files_count: 2,
+            skipped: vec![("a/b/c".to_string(), "слишком глубоко".to_string())],
+            ..Default::default()
+        };
+        assert_eq!(with_skipped.summary(), "2 файл(ов), 1 пропущено");
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_tar_counts_dirs_and_symlinks() {
+        let src = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let archive_path = src.path().join("test.tar");
+        
+        {
+            let mut builder = ::tar::Builder::new(std::fs::File::create(&archive_path).unwrap());
+            
+            let mut dir = ::tar::Header::new_gnu();
+            dir.set_entry_type(::tar::EntryType::Directory);
+            dir.set_mode(0o755);
+            dir.set_size(0);
+            builder.append_data(&mut dir, "sub/", std::io::empty()).unwrap();
+            
+            let data = b"hello";
+            let mut file = ::tar::Header::new_gnu();
+            file.set_mode(0o644);
+            file.set_size(data.len() as u64);
+            builder.append_data(&mut file, "sub/a.txt", &data[..]).unwrap();
+            
+            let mut link = ::tar::Header::new_gnu();
+            link.set_entry_type(::tar::EntryType::Symlink);
+            link.set_size(0);
+            builder.append_link(&mut link, "sub/link", "a.txt").unwrap();
+            
+            builder.finish().unwrap();
+        }
+        
+        let result = extract_archive(&archive_path, out.path()).unwrap();
+        assert_eq!(result.files_count, 1);
+        assert_eq!(result.total_size, 5);
+        assert_eq!(result.dirs_count, 1);
+        assert_eq!(result.symlinks_count, 1);
+        assert!(out.path().join("sub/link").symlink_metadata().unwrap().file_type().is_symlink());
+    }
+    
+    /// Ссылка наружу пропускается, и запись под её именем остаётся в папке распаковки
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_tar_rejects_escaping_symlink() {
+        let src = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let archive_path = src.path().join("evil.tar");
+        
+        {
+            let mut builder = ::tar::Builder::new(std::fs::File::create(&archive_path).unwrap());
+            
+            let mut link = ::tar::Header::new_gnu();
+            link.set_entry_type(::tar::EntryType::Symlink);
+            link.set_size(0);
+            builder.append_link(&mut link, "escape", elsewhere.path()).unwrap();
+            
+            let data = b"pwned";
+            let mut file = ::tar::Header::new_gnu();
+            file.set_mode(0o644);
+            file.set_size(data.len() as u64);
+            builder.append_data(&mut file, "escape/pwned.txt", &data[..]).unwrap();
+            
+            builder.finish().unwrap();
+        }
+        
+        let result = extract_archive(&archive_path, out.path()).unwrap();
+        assert_eq!(result.symlinks_count, 0);
+        assert_eq!(result.skipped.len(), 1);
+        assert!(!elsewhere.path().join("pwned.txt").exists());
+        assert_eq!(std::fs::read(out.path().join("escape/pwned.txt")).unwrap(), b"pwned");
+    }
+    
+    #[test]
+    fn test_extract_skips_entries_over_path_limits() {
+        let src = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let archive_path = src.path().join("deep.tar");
+        
+        {
+            let mut builder = ::tar::Builder::new(std::fs::File::create(&archive_path).unwrap());
+            for name in ["ok.txt", "a/b/c/d/e/deep.txt", "sub/also_ok.txt"] {
+                let mut header = ::tar::Header::new_gnu();
+                header.set_mode(0o644);
+                header.set_size(2);
+                builder.append_data(&mut header, name, &b"hi"[..]).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+        
+        let limits = PathLimits { max_components: 3, ..Default::default() };
+        let result = extract_archive_with_limits(&archive_path, out.path(), None, &limits).unwrap();
+        
+        assert_eq!(result.files_count, 2);
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].0, "a/b/c/d/e/deep.txt");
+        assert_eq!(result.summary(), "2 файл(ов), 1 пропущено");
+        assert!(out.path().join("ok.txt").exists());
+        assert!(out.path().join("sub/also_ok.txt").exists());
+        assert!(!out.path().join("a").exists());
+    }
+    
+    #[test]
+    fn test_extract_strip_components() {
+        let src = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let archive_path = src.path().join("project.tar");
+        
+        {
+            let mut builder = ::tar::Builder::new(std::fs::File::create(&archive_path).unwrap());
+            let mut dir = ::tar::Header::new_gnu();
+            dir.set_entry_type(::tar::EntryType::Directory);
+            dir.set_mode(0o755);
+            dir.set_size(0);
+            builder.append_data(&mut dir, "project-1.2.3/", std::io::empty()).unwrap();
+            for name in ["project-1.2.3/README", "project-1.2.3/src/main.rs"] {
+                let mut header = ::tar::Header::new_gnu();
+                header.set_mode(0o644);
+                header.set_size(2);
+                builder.append_data(&mut header, name, &b"hi"[..]).unwrap();
+            }
+            builder.finish().unwrap();
+        }
+        
+        let limits = PathLimits { strip_components: 1, ..Default::default() };
+        let result = extract_archive_with_limits(&archive_path, out.path(), None, &limits).unwrap();
+        
+        assert_eq!(result.files_count, 2);
+        assert_eq!(result.dirs_count, 0);
+        assert_eq!(result.skipped.len(), 0);
+        assert!(out.path().join("README").exists());
+        assert!(out.path().join("src/main.rs").exists());
+        assert!(!out.path().join("project-1.2.3").exists());
     }
     
     #[test]
@@ -0,0 +1,120 @@
+//! Пересборка архива tar без части записей
+//!
+//! Нужна для докачки потоковой распаковки: получатель присылает список уже
+//! распакованных записей (`Message::ExtractManifest`), и отправитель высылает
+//! архив без них в том же формате
+
+use super::list::open_tar_stream;
+use super::types::ArchiveType;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Пересобрать tar.lz4/tar.zst `archive_path` в `output`, пропустив файлы, для
+/// которых `skip(путь, размер, mtime)` вернул true. Папки, ссылки и PAX-заголовки
+/// остальных записей сохраняются. Возвращает число пропущенных файлов
+pub fn repack_tar_without<W: Write>(
+    archive_path: &Path,
+    archive_type: ArchiveType,
+    output: W,
+    skip: impl Fn(&str, u64, u64) -> bool,
+) -> io::Result<usize> {
+    let reader = match archive_type {
+        ArchiveType::TarLz4 | ArchiveType::TarZst => open_tar_stream(archive_path, archive_type)?,
+        _ => None,
+    };
+    let Some(reader) = reader else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Пересборка {} не поддерживается", archive_type.name()),
+        ));
+    };
+    
+    if archive_type == ArchiveType::TarLz4 {
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(output);
+        let skipped = copy_entries(reader, &mut encoder, skip)?;
+        encoder.finish().map_err(io::Error::other)?;
+        Ok(skipped)
+    } else {
+        let mut encoder = zstd::stream::Encoder::new(output, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        let skipped = copy_entries(reader, &mut encoder, skip)?;
+        encoder.finish()?;
+        Ok(skipped)
+    }
+}
+
+fn copy_entries<R: Read, W: Write>(reader: R, output: W, skip: impl Fn(&str, u64, u64) -> bool) -> io::Result<usize> {
+    let mut archive = tar::Archive::new(reader);
+    let mut builder = tar::Builder::new(output);
+    let mut skipped = 0;
+    
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut header = entry.header().clone();
+        
+        if header.entry_type().is_file()
+            && path.to_str().is_some_and(|p| skip(p, header.size().unwrap_or(0), header.mtime().unwrap_or(0)))
+        {
+            skipped += 1;
+            continue;
+        }
+        
+        if let Some(extensions) = entry.pax_extensions()? {
+            let extensions = extensions
+                .map(|ext| ext.map(|ext| (ext.key_bytes().to_vec(), ext.value_bytes().to_vec())))
+                .collect::<io::Result<Vec<_>>>()?;
+            builder.append_pax_extensions(
+                extensions
+                    .iter()
+                    .filter_map(|(key, value)| Some((std::str::from_utf8(key).ok()?, &value[..]))),
+            )?;
+        }
+        if header.entry_type().is_symlink() || header.entry_type().is_hard_link() {
+            let target = entry.link_name()?.unwrap_or_default().into_owned();
+            builder.append_link(&mut header, &path, target)?;
+        } else {
+            builder.append_data(&mut header, &path, &mut entry)?;
+        }
+    }
+    
+    builder.into_inner()?;
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_repack_skips_listed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data, mtime) in [("a.txt", &b"aaa"[..], 100), ("sub/b.txt", b"bbbb", 200), ("c.txt", b"c", 300)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mtime(mtime);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, data).unwrap();
+        }
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+        let archive = dir.path().join("data.tar.lz4");
+        std::fs::write(&archive, encoder.finish().unwrap()).unwrap();
+        
+        // b.txt с тем же размером, но другим mtime - не пропускается
+        let done = [("a.txt", 3, 100), ("sub/b.txt", 4, 999)];
+        let mut repacked = Vec::new();
+        let skipped = repack_tar_without(&archive, ArchiveType::TarLz4, &mut repacked, |path, size, mtime| {
+            done.contains(&(path, size, mtime))
+        }).unwrap();
+        assert_eq!(skipped, 1);
+        
+        let mut tar = tar::Archive::new(lz4_flex::frame::FrameDecoder::new(&repacked[..]));
+        let names: Vec<String> = tar.entries().unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect();
+        assert_eq!(names, ["sub/b.txt", "c.txt"]);
+        
+        assert!(repack_tar_without(&archive, ArchiveType::Zip, Vec::new(), |_, _, _| false).is_err());
+    }
+}
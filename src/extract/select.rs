@@ -0,0 +1,205 @@
+//! Выборочная распаковка: только записи, совпавшие с шаблонами
+
+use super::limits::{strip_leading_components, PathLimits};
+use super::links::{create_dir_within, prepare_file_path, unpack_symlink};
+use super::list::open_tar_stream;
+use super::types::{ArchiveType, ExtractResult};
+use crate::pattern::{matches_any, Pattern};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Распаковать только записи, совпавшие хотя бы с одним шаблоном `include`.
+///
+/// Родительские папки совпавших файлов создаются автоматически. Записи с `..`
+/// или абсолютным путём пропускаются, лимиты пути - как при обычной распаковке.
+pub fn extract_entries(
+    archive_path: &Path,
+    output_dir: &Path,
+    include: &[Pattern],
+    stop_flag: Option<Arc<AtomicBool>>,
+) -> io::Result<ExtractResult> {
+    let filename = archive_path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    let archive_type = ArchiveType::from_filename(filename);
+    let limits = PathLimits::default();
+    
+    if let Some(reader) = open_tar_stream(archive_path, archive_type)? {
+        return extract_tar_entries(reader, output_dir, include, stop_flag, &limits);
+    }
+    
+    match archive_type {
+        ArchiveType::Zip => extract_zip_entries(archive_path, output_dir, include, stop_flag, &limits),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Выборочная распаковка {} не поддерживается", archive_type.name()),
+        )),
+    }
+}
+
+/// Путь записи для выборочной распаковки: None - запись не выбрана или небезопасна
+fn selected_path(
+    output_dir: &Path,
+    entry_path: &Path,
+    include: &[Pattern],
+    limits: &PathLimits,
+) -> Option<PathBuf> {
+    // Только обычные компоненты: `..` и абсолютные пути не проходят
+    let relative = strip_leading_components(entry_path, 0).ok()??;
+    if !matches_any(include, &relative.to_string_lossy()) {
+        return None;
+    }
+    limits.resolve(output_dir, &relative).ok()?
+}
+
+fn check_stop(stop_flag: &Option<Arc<AtomicBool>>) -> io::Result<()> {
+    if stop_flag.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "Распаковка отменена"));
+    }
+    Ok(())
+}
+
+fn extract_tar_entries<R: Read>(
+    reader: R,
+    output_dir: &Path,
+    include: &[Pattern],
+    stop_flag: Option<Arc<AtomicBool>>,
+    limits: &PathLimits,
+) -> io::Result<ExtractResult> {
+    let mut archive = tar::Archive::new(reader);
+    let mut result = ExtractResult::default();
+    
+    for entry in archive.entries()? {
+        check_stop(&stop_flag)?;
+        
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let Some(path) = selected_path(output_dir, &entry_path, include, limits) else {
+            continue;
+        };
+        
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            create_dir_within(output_dir, &path)?;
+            result.dirs_count += 1;
+        } else if entry_type.is_symlink() {
+            let target = entry.link_name()?.unwrap_or_default().into_owned();
+            match unpack_symlink(output_dir, &path, &target) {
+                Ok(true) => result.symlinks_count += 1,
+                Ok(false) => {}
+                Err(reason) => {
+                    result.skipped.push((entry_path.display().to_string(), reason));
+                }
+            }
+        } else if entry_type.is_file() {
+            prepare_file_path(output_dir, &path)?;
+            let size = entry.header().size()?;
+            entry.unpack(&path)?;
+            result.files_count += 1;
+            result.total_size += size;
+        }
+    }
+    
+    Ok(result)
+}
+
+fn extract_zip_entries(
+    archive_path: &Path,
+    output_dir: &Path,
+    include: &[Pattern],
+    stop_flag: Option<Arc<AtomicBool>>,
+    limits: &PathLimits,
+) -> io::Result<ExtractResult> {
+    let file = File::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let mut result = ExtractResult::default();
+    
+    for i in 0..archive.len() {
+        check_stop(&stop_flag)?;
+        
+        let mut file = archive.by_index(i)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let Some(path) = file.enclosed_name().and_then(|name| selected_path(output_dir, &name, include, limits)) else {
+            continue;
+        };
+        
+        if file.is_dir() {
+            create_dir_within(output_dir, &path)?;
+            result.dirs_count += 1;
+        } else if !file.is_symlink() {
+            prepare_file_path(output_dir, &path)?;
+            let size = io::copy(&mut file, &mut File::create(&path)?)?;
+            result.files_count += 1;
+            result.total_size += size;
+        }
+    }
+    
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    
+    fn build_tar_zst(path: &Path) {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in [
+            ("backup/app/config.toml", &b"key = 1"[..]),
+            ("backup/app/data.bin", &b"0123456789"[..]),
+            ("backup/other/config.toml", &b"key = 2"[..]),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(data.len() as u64);
+            builder.append_data(&mut header, name, data).unwrap();
+        }
+        let tar_data = builder.into_inner().unwrap();
+        
+        let mut encoder = zstd::stream::Encoder::new(File::create(path).unwrap(), 3).unwrap();
+        encoder.write_all(&tar_data).unwrap();
+        encoder.finish().unwrap();
+    }
+    
+    #[test]
+    fn test_extract_only_matching_entries() {
+        let src = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let archive_path = src.path().join("backup.tar.zst");
+        build_tar_zst(&archive_path);
+        
+        let include = vec![Pattern::new("**/app/config.toml").unwrap()];
+        let result = extract_entries(&archive_path, out.path(), &include, None).unwrap();
+        
+        assert_eq!(result.files_count, 1);
+        assert_eq!(std::fs::read(out.path().join("backup/app/config.toml")).unwrap(), b"key = 1");
+        assert!(!out.path().join("backup/app/data.bin").exists());
+        assert!(!out.path().join("backup/other").exists());
+    }
+    
+    #[test]
+    fn test_extract_entries_zip() {
+        let src = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let archive_path = src.path().join("test.zip");
+        
+        let mut writer = zip::ZipWriter::new(File::create(&archive_path).unwrap());
+        for name in ["a/config.toml", "a/readme.md", "b/config.toml"] {
+            writer.start_file(name, zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(b"x").unwrap();
+        }
+        writer.finish().unwrap();
+        
+        let include = vec![Pattern::new("**/config.toml").unwrap()];
+        let result = extract_entries(&archive_path, out.path(), &include, None).unwrap();
+        
+        assert_eq!(result.files_count, 2);
+        assert!(out.path().join("a/config.toml").exists());
+        assert!(out.path().join("b/config.toml").exists());
+        assert!(!out.path().join("a/readme.md").exists());
+    }
+}
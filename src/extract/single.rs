@@ -0,0 +1,92 @@
+//! Одиночные сжатые файлы без tar (.lz4, .zst)
+
+use super::types::{ArchiveType, ExtractResult};
+use lz4_flex::frame::FrameDecoder;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Размер буфера записи распакованного файла
+const WRITE_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// Имя распакованного файла: без `.lz4`/`.zst` (None - не одиночный сжатый файл)
+pub fn single_file_output_name(filename: &str) -> Option<&str> {
+    let archive_type = ArchiveType::from_filename(filename);
+    if !archive_type.is_single_file() {
+        return None;
+    }
+    // Оба расширения - 4 байта ASCII, граница символа не разрывается
+    let name = &filename[..filename.len() - 4];
+    (!name.is_empty() && !name.ends_with('/')).then_some(name)
+}
+
+/// Распаковать поток одиночного сжатого файла в `writer`.
+/// Возвращает размер распакованных данных
+pub fn decompress_single<R: Read, W: Write>(reader: R, archive_type: ArchiveType, writer: &mut W) -> io::Result<u64> {
+    match archive_type {
+        ArchiveType::Lz4 => io::copy(&mut FrameDecoder::new(reader), writer),
+        ArchiveType::Zst => {
+            let mut decoder = zstd::stream::Decoder::new(reader)?;
+            // Файлы, сжатые с --long=31 (окно до 2GB)
+            decoder.window_log_max(31)?;
+            io::copy(&mut decoder, writer)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} - не одиночный сжатый файл", archive_type.name()),
+        )),
+    }
+}
+
+/// Распаковать поток одиночного сжатого файла в `output_path` (папки создаются)
+pub fn decompress_single_to_file<R: Read>(reader: R, archive_type: ArchiveType, output_path: &Path) -> io::Result<ExtractResult> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, File::create(output_path)?);
+    let total_size = decompress_single(reader, archive_type, &mut writer)?;
+    writer.flush()?;
+    Ok(ExtractResult { files_count: 1, total_size, ..Default::default() })
+}
+
+/// Распаковать .zst файл (не архив) рядом: `output_dir/<имя без .zst>`
+pub fn extract_zst(archive_path: &Path, output_dir: &Path) -> io::Result<ExtractResult> {
+    let filename = archive_path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    let output_name = single_file_output_name(filename)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Ожидался файл .zst"))?;
+    let reader = BufReader::new(File::open(archive_path)?);
+    decompress_single_to_file(reader, ArchiveType::Zst, &output_dir.join(output_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_single_file_output_name() {
+        assert_eq!(single_file_output_name("data/dump.sql.zst"), Some("data/dump.sql"));
+        assert_eq!(single_file_output_name("image.LZ4"), Some("image"));
+        assert_eq!(single_file_output_name("backup.tar.zst"), None);
+        assert_eq!(single_file_output_name(".zst"), None);
+        assert_eq!(single_file_output_name("notes.txt"), None);
+    }
+    
+    #[test]
+    fn test_decompress_single_roundtrip() {
+        let data = b"hello single file ".repeat(1000);
+        
+        let zst = zstd::encode_all(&data[..], 3).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(decompress_single(&zst[..], ArchiveType::Zst, &mut out).unwrap(), data.len() as u64);
+        assert_eq!(out, data);
+        
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(&data).unwrap();
+        let lz4 = encoder.finish().unwrap();
+        let mut out = Vec::new();
+        decompress_single(&lz4[..], ArchiveType::Lz4, &mut out).unwrap();
+        assert_eq!(out, data);
+    }
+}
@@ -1,7 +1,9 @@
 //! Распаковка tar и tar.gz архивов
 
+use super::limits::PathLimits;
+use super::links::{create_dir_within, prepare_file_path, unpack_symlink};
 use super::types::ExtractResult;
-use std::fs::{self, File};
+use std::fs::File;
 use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::path::Path;
 use std::sync::Arc;
@@ -16,20 +18,24 @@ const COPY_CHUNK_SIZE: usize = 16 * 1024 * 1024;
 
 /// Распаковать обычный tar архив
 pub fn extract_tar(archive_path: &Path, output_dir: &Path) -> io::Result<ExtractResult> {
-    extract_tar_streaming(archive_path, output_dir, None)
+    extract_tar_streaming(archive_path, output_dir, None, &PathLimits::default())
 }
 
 /// Распаковать tar с поддержкой остановки
 pub fn extract_tar_streaming(
     archive_path: &Path, 
     output_dir: &Path,
-    stop_flag: Option<Arc<AtomicBool>>
+    stop_flag: Option<Arc<AtomicBool>>,
+    limits: &PathLimits,
 ) -> io::Result<ExtractResult> {
     let file = File::open(archive_path)?;
     let mut archive = tar::Archive::new(BufReader::with_capacity(READ_BUFFER_SIZE, file));
     
     let mut files_count = 0;
     let mut total_size = 0u64;
+    let mut dirs_count = 0;
+    let mut symlinks_count = 0;
+    let mut skipped = Vec::new();
     
     for entry in archive.entries()? {
         if let Some(ref flag) = stop_flag {
@@ -39,14 +45,29 @@ pub fn extract_tar_streaming(
         }
         
         let mut entry = entry?;
-        let path = output_dir.join(entry.path()?);
+        let entry_path = entry.path()?.into_owned();
+        let path = match limits.resolve(output_dir, &entry_path) {
+            Ok(Some(path)) => path,
+            Ok(None) => continue,
+            Err(reason) => {
+                skipped.push((entry_path.display().to_string(), reason));
+                continue;
+            }
+        };
         
-        if entry.header().entry_type().is_dir() {
-            fs::create_dir_all(&path)?;
-        } else if entry.header().entry_type().is_file() {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            create_dir_within(output_dir, &path)?;
+            dirs_count += 1;
+        } else if entry_type.is_symlink() {
+            let target = entry.link_name()?.unwrap_or_default().into_owned();
+            match unpack_symlink(output_dir, &path, &target) {
+                Ok(true) => symlinks_count += 1,
+                Ok(false) => {}
+                Err(reason) => skipped.push((entry_path.display().to_string(), reason)),
             }
+        } else if entry_type.is_file() {
+            prepare_file_path(output_dir, &path)?;
             let size = entry.header().size()?;
             
             // Ручная распаковка с большим буфером записи для NAS
@@ -54,13 +75,16 @@ pub fn extract_tar_streaming(
             let mut writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, out_file);
             buffered_copy(&mut entry, &mut writer)?;
             writer.flush()?;
+            if limits.xattrs {
+                let _ = crate::utils::apply_xattrs(&path, &crate::utils::pax_xattrs(&mut entry));
+            }
             
             files_count += 1;
             total_size += size;
         }
     }
     
-    Ok(ExtractResult { files_count, total_size })
+    Ok(ExtractResult { files_count, total_size, dirs_count, symlinks_count, skipped })
 }
 
 /// Копирование с большим буфером (16 МБ чанки)
@@ -82,14 +106,15 @@ fn buffered_copy<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> io::Resul
 
 /// Распаковать tar.gz архив
 pub fn extract_tar_gz(archive_path: &Path, output_dir: &Path) -> io::Result<ExtractResult> {
-    extract_tar_gz_streaming(archive_path, output_dir, None)
+    extract_tar_gz_streaming(archive_path, output_dir, None, &PathLimits::default())
 }
 
 /// Распаковать tar.gz с поддержкой остановки
 pub fn extract_tar_gz_streaming(
     archive_path: &Path, 
     output_dir: &Path,
-    stop_flag: Option<Arc<AtomicBool>>
+    stop_flag: Option<Arc<AtomicBool>>,
+    limits: &PathLimits,
 ) -> io::Result<ExtractResult> {
     let file = File::open(archive_path)?;
     let gz = flate2::read::GzDecoder::new(BufReader::with_capacity(READ_BUFFER_SIZE, file));
@@ -97,6 +122,9 @@ pub fn extract_tar_gz_streaming(
     
     let mut files_count = 0;
     let mut total_size = 0u64;
+    let mut dirs_count = 0;
+    let mut symlinks_count = 0;
+    let mut skipped = Vec::new();
     
     for entry in archive.entries()? {
         if let Some(ref flag) = stop_flag {
@@ -106,14 +134,29 @@ pub fn extract_tar_gz_streaming(
         }
         
         let mut entry = entry?;
-        let path = output_dir.join(entry.path()?);
+        let entry_path = entry.path()?.into_owned();
+        let path = match limits.resolve(output_dir, &entry_path) {
+            Ok(Some(path)) => path,
+            Ok(None) => continue,
+            Err(reason) => {
+                skipped.push((entry_path.display().to_string(), reason));
+                continue;
+            }
+        };
         
-        if entry.header().entry_type().is_dir() {
-            fs::create_dir_all(&path)?;
-        } else if entry.header().entry_type().is_file() {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            create_dir_within(output_dir, &path)?;
+            dirs_count += 1;
+        } else if entry_type.is_symlink() {
+            let target = entry.link_name()?.unwrap_or_default().into_owned();
+            match unpack_symlink(output_dir, &path, &target) {
+                Ok(true) => symlinks_count += 1,
+                Ok(false) => {}
+                Err(reason) => skipped.push((entry_path.display().to_string(), reason)),
             }
+        } else if entry_type.is_file() {
+            prepare_file_path(output_dir, &path)?;
             let size = entry.header().size()?;
             
             // Ручная распаковка с большим буфером записи для NAS
@@ -121,12 +164,15 @@ pub fn extract_tar_gz_streaming(
             let mut writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, out_file);
             buffered_copy(&mut entry, &mut writer)?;
             writer.flush()?;
+            if limits.xattrs {
+                let _ = crate::utils::apply_xattrs(&path, &crate::utils::pax_xattrs(&mut entry));
+            }
             
             files_count += 1;
             total_size += size;
         }
     }
     
-    Ok(ExtractResult { files_count, total_size })
+    Ok(ExtractResult { files_count, total_size, dirs_count, symlinks_count, skipped })
 }
 
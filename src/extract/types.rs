@@ -6,6 +6,8 @@ pub enum ArchiveType {
     TarLz4,
     TarZst,
     Lz4,
+    /// Одиночный файл, сжатый zstd (не tar)
+    Zst,
     Tar,
     TarGz,
     Zip,
@@ -25,6 +27,8 @@ impl ArchiveType {
             Self::TarZst
         } else if lower.ends_with(".lz4") {
             Self::Lz4
+        } else if lower.ends_with(".zst") {
+            Self::Zst
         } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
             Self::TarGz
         } else if lower.ends_with(".tar") {
@@ -40,12 +44,28 @@ impl ArchiveType {
         }
     }
     
+    /// Умеет ли эта сборка распаковывать формат (rar и 7z - только внешними утилитами)
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, Self::Rar | Self::SevenZip | Self::Unknown)
+    }
+    
+    /// Одиночный сжатый файл без tar (.lz4, .zst)
+    pub fn is_single_file(&self) -> bool {
+        matches!(self, Self::Lz4 | Self::Zst)
+    }
+    
+    /// Можно ли распаковывать на лету во время приёма
+    pub fn is_streamable(&self) -> bool {
+        matches!(self, Self::TarLz4 | Self::TarZst | Self::Lz4 | Self::Zst)
+    }
+    
     /// Имя формата
     pub fn name(&self) -> &'static str {
         match self {
             Self::TarLz4 => "tar.lz4",
             Self::TarZst => "tar.zst",
             Self::Lz4 => "lz4",
+            Self::Zst => "zst",
             Self::Tar => "tar",
             Self::TarGz => "tar.gz",
             Self::Zip => "zip",
@@ -57,9 +77,36 @@ impl ArchiveType {
 }
 
 /// Результат распаковки
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
 pub struct ExtractResult {
+    /// Количество обычных файлов
     pub files_count: usize,
+    /// Суммарный размер обычных файлов
     pub total_size: u64,
+    /// Количество созданных папок (записи-директории архива)
+    pub dirs_count: usize,
+    /// Количество созданных символических ссылок
+    pub symlinks_count: usize,
+    /// Пропущенные записи (превышены лимиты пути, см. `PathLimits`): (путь записи, причина).
+    /// Потоковая распаковка вдобавок сразу шлёт по каждой `ExtractionEntrySkipped`
+    pub skipped: Vec<(String, String)>,
+}
+
+impl ExtractResult {
+    /// Краткое описание структуры: "10 файл(ов), 2 папок, 1 ссылок"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{} файл(ов)", self.files_count)];
+        if self.dirs_count > 0 {
+            parts.push(format!("{} папок", self.dirs_count));
+        }
+        if self.symlinks_count > 0 {
+            parts.push(format!("{} ссылок", self.symlinks_count));
+        }
+        if !self.skipped.is_empty() {
+            parts.push(format!("{} пропущено", self.skipped.len()));
+        }
+        parts.join(", ")
+    }
 }
 
 /// Опции автораспаковки
@@ -78,7 +125,7 @@ impl ExtractOptions {
         match archive_type {
             ArchiveType::TarLz4 => self.tar_lz4,
             ArchiveType::TarZst => self.tar_lz4, // Используем ту же опцию что и tar.lz4
-            ArchiveType::Lz4 => self.lz4,
+            ArchiveType::Lz4 | ArchiveType::Zst => self.lz4,
             ArchiveType::Tar | ArchiveType::TarGz => self.tar,
             ArchiveType::Zip => self.zip,
             ArchiveType::Rar => self.rar,
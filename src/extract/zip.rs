@@ -1,34 +1,63 @@
 //! Распаковка ZIP архивов
 
+use super::limits::PathLimits;
+use super::links::{create_dir_within, prepare_file_path, unpack_symlink};
 use super::types::ExtractResult;
 use std::fs::{self, File};
-use std::io::{self, BufReader};
+use std::io::{self, BufReader, Read};
 use std::path::Path;
 
 /// Распаковать ZIP архив
 pub fn extract_zip(archive_path: &Path, output_dir: &Path) -> io::Result<ExtractResult> {
+    extract_zip_with_limits(archive_path, output_dir, &PathLimits::default())
+}
+
+/// Распаковать ZIP архив, пропуская записи сверх лимитов пути
+pub fn extract_zip_with_limits(
+    archive_path: &Path,
+    output_dir: &Path,
+    limits: &PathLimits,
+) -> io::Result<ExtractResult> {
     let file = File::open(archive_path)?;
     let mut archive = zip::ZipArchive::new(BufReader::new(file))
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
     
     let mut files_count = 0;
     let mut total_size = 0u64;
+    let mut dirs_count = 0;
+    let mut symlinks_count = 0;
+    let mut skipped = Vec::new();
     
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
         
         let outpath = match file.enclosed_name() {
-            Some(path) => output_dir.join(path),
+            Some(path) => match limits.resolve(output_dir, &path) {
+                Ok(Some(outpath)) => outpath,
+                Ok(None) => continue,
+                Err(reason) => {
+                    skipped.push((file.name().to_string(), reason));
+                    continue;
+                }
+            },
             None => continue,
         };
         
         if file.is_dir() {
-            fs::create_dir_all(&outpath)?;
-        } else {
-            if let Some(parent) = outpath.parent() {
-                fs::create_dir_all(parent)?;
+            create_dir_within(output_dir, &outpath)?;
+            dirs_count += 1;
+        } else if file.is_symlink() {
+            // Содержимое записи-ссылки - путь, на который она указывает
+            let mut target = String::new();
+            file.read_to_string(&mut target)?;
+            match unpack_symlink(output_dir, &outpath, Path::new(&target)) {
+                Ok(true) => symlinks_count += 1,
+                Ok(false) => {}
+                Err(reason) => skipped.push((file.name().to_string(), reason)),
             }
+        } else {
+            prepare_file_path(output_dir, &outpath)?;
             
             let mut outfile = File::create(&outpath)?;
             let size = io::copy(&mut file, &mut outfile)?;
@@ -46,6 +75,6 @@ pub fn extract_zip(archive_path: &Path, output_dir: &Path) -> io::Result<Extract
         }
     }
     
-    Ok(ExtractResult { files_count, total_size })
+    Ok(ExtractResult { files_count, total_size, dirs_count, symlinks_count, skipped })
 }
 
@@ -1,7 +1,9 @@
 //! Распаковка ZST и tar.zst архивов
 
+use super::limits::PathLimits;
+use super::links::{create_dir_within, prepare_file_path, unpack_symlink};
 use super::types::ExtractResult;
-use std::fs::{self, File};
+use std::fs::File;
 use std::io::{self, BufReader};
 use std::path::Path;
 use std::sync::Arc;
@@ -9,14 +11,15 @@ use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Распаковать tar.zst архив (потоковая распаковка - не грузит в RAM)
 pub fn extract_tar_zst(archive_path: &Path, output_dir: &Path) -> io::Result<ExtractResult> {
-    extract_tar_zst_streaming(archive_path, output_dir, None)
+    extract_tar_zst_streaming(archive_path, output_dir, None, &PathLimits::default())
 }
 
 /// Распаковать tar.zst архив с поддержкой остановки (потоковая)
 pub fn extract_tar_zst_streaming(
     archive_path: &Path, 
     output_dir: &Path,
-    stop_flag: Option<Arc<AtomicBool>>
+    stop_flag: Option<Arc<AtomicBool>>,
+    limits: &PathLimits,
 ) -> io::Result<ExtractResult> {
     let file = File::open(archive_path)?;
     let reader = BufReader::with_capacity(1024 * 1024, file); // 1MB буфер
@@ -31,6 +34,9 @@ pub fn extract_tar_zst_streaming(
     
     let mut files_count = 0;
     let mut total_size = 0u64;
+    let mut dirs_count = 0;
+    let mut symlinks_count = 0;
+    let mut skipped = Vec::new();
     
     for entry in archive.entries()? {
         // Проверяем флаг остановки
@@ -44,25 +50,43 @@ pub fn extract_tar_zst_streaming(
         }
         
         let mut entry = entry?;
-        let path = output_dir.join(entry.path()?);
+        let entry_path = entry.path()?.into_owned();
+        let path = match limits.resolve(output_dir, &entry_path) {
+            Ok(Some(path)) => path,
+            Ok(None) => continue,
+            Err(reason) => {
+                skipped.push((entry_path.display().to_string(), reason));
+                continue;
+            }
+        };
         
-        if entry.header().entry_type().is_dir() {
-            fs::create_dir_all(&path)?;
-        } else if entry.header().entry_type().is_file() {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            create_dir_within(output_dir, &path)?;
+            dirs_count += 1;
+        } else if entry_type.is_symlink() {
+            let target = entry.link_name()?.unwrap_or_default().into_owned();
+            match unpack_symlink(output_dir, &path, &target) {
+                Ok(true) => symlinks_count += 1,
+                Ok(false) => {}
+                Err(reason) => skipped.push((entry_path.display().to_string(), reason)),
             }
+        } else if entry_type.is_file() {
+            prepare_file_path(output_dir, &path)?;
             let size = entry.header().size()?;
             entry.unpack(&path)?;
+            if limits.xattrs {
+                let _ = crate::utils::apply_xattrs(&path, &crate::utils::pax_xattrs(&mut entry));
+            }
             files_count += 1;
             total_size += size;
         }
     }
     
-    Ok(ExtractResult { files_count, total_size })
+    Ok(ExtractResult { files_count, total_size, dirs_count, symlinks_count, skipped })
 }
 
 /// Синхронная распаковка tar.zst (алиас для потоковой версии)
 pub fn extract_tar_zst_simple(archive_path: &Path, output_dir: &Path) -> io::Result<ExtractResult> {
-    extract_tar_zst_streaming(archive_path, output_dir, None)
+    extract_tar_zst_streaming(archive_path, output_dir, None, &PathLimits::default())
 }
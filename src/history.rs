@@ -1,5 +1,8 @@
 //! История передач - сохранение и загрузка
 
+use crate::i18n::Language;
+use crate::stats::ThroughputSummary;
+use chrono::{DateTime, Local, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -33,6 +36,30 @@ pub struct HistoryEntry {
     pub success: bool,
     /// Сообщение об ошибке (если есть)
     pub error: Option<String>,
+    /// Контрольная сумма переданного содержимого (hex), см. `network::transfer_checksum`
+    #[serde(default)]
+    pub checksum: Option<String>,
+    /// Итоги скорости по ходу передачи (перцентили, мин/макс)
+    #[serde(default)]
+    pub throughput: Option<ThroughputSummary>,
+    /// Итог по каждому получателю отправки (в старых записях пусто)
+    #[serde(default)]
+    pub targets: Vec<TargetOutcome>,
+}
+
+/// Чем закончилась отправка одному получателю
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TargetOutcome {
+    pub address: String,
+    pub success: bool,
+    /// Причина неудачи (None - остановлено до подключения или успех)
+    pub error: Option<String>,
+}
+
+impl TargetOutcome {
+    pub fn new(address: String, success: bool, error: Option<String>) -> Self {
+        Self { address, success, error }
+    }
 }
 
 /// Тип операции
@@ -43,7 +70,7 @@ pub enum OperationType {
 }
 
 /// Направление передачи
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 pub enum Direction {
     Send,
     Receive,
@@ -78,6 +105,9 @@ impl HistoryEntry {
             addresses,
             success,
             error,
+            checksum: None,
+            throughput: None,
+            targets: Vec::new(),
         }
     }
     
@@ -108,38 +138,75 @@ impl HistoryEntry {
             addresses: vec![address],
             success,
             error,
+            checksum: None,
+            throughput: None,
+            targets: Vec::new(),
         }
     }
     
+    /// Добавить контрольную сумму передачи
+    pub fn with_checksum(mut self, checksum: Option<u64>) -> Self {
+        self.checksum = checksum.map(|c| format!("{:016x}", c));
+        self
+    }
+    
+    /// Добавить итоги по каждому получателю
+    pub fn with_targets(mut self, targets: Vec<TargetOutcome>) -> Self {
+        self.targets = targets;
+        self
+    }
+    
+    /// Добавить итоги скорости (см. `TransferStats::throughput_summary`)
+    pub fn with_throughput(mut self, throughput: Option<ThroughputSummary>) -> Self {
+        self.throughput = throughput;
+        self
+    }
+    
     /// Форматировать дату/время
     pub fn formatted_time(&self) -> String {
-        // Простое форматирование без внешних зависимостей
-        let secs_per_day = 86400u64;
-        let secs_per_hour = 3600u64;
-        let secs_per_min = 60u64;
-        
-        // Приблизительное время (без учёта часовых поясов)
-        let total_days = self.timestamp / secs_per_day;
-        let day_secs = self.timestamp % secs_per_day;
-        let hours = day_secs / secs_per_hour;
-        let mins = (day_secs % secs_per_hour) / secs_per_min;
-        
-        // Вычисляем дату (приблизительно, начиная с 1970)
-        let mut year = 1970u32;
-        let mut remaining_days = total_days;
-        
-        loop {
-            let days_in_year = if is_leap_year(year) { 366 } else { 365 };
-            if remaining_days < days_in_year {
-                break;
-            }
-            remaining_days -= days_in_year;
-            year += 1;
+        self.formatted_time_in(Language::Russian)
+    }
+    
+    /// Форматировать местные дату/время в принятом для языка порядке
+    pub fn formatted_time_in(&self, language: Language) -> String {
+        self.formatted_time_in_zone(&Local, language)
+    }
+    
+    /// Форматировать дату/время в часовом поясе `tz`
+    pub fn formatted_time_in_zone<Tz: TimeZone>(&self, tz: &Tz, language: Language) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let time = utc_datetime(self.timestamp).with_timezone(tz);
+        if language.units().day_first {
+            time.format("%d.%m.%Y %H:%M").to_string()
+        } else {
+            time.format("%Y-%m-%d %H:%M").to_string()
         }
-        
-        let (month, day) = days_to_month_day(remaining_days as u32, is_leap_year(year));
-        
-        format!("{:02}.{:02}.{} {:02}:{:02}", day, month, year, hours, mins)
+    }
+    
+    /// Передача была в промежутке `from..=to` (Unix timestamp)
+    pub fn is_in_range(&self, from: u64, to: u64) -> bool {
+        (from..=to).contains(&self.timestamp)
+    }
+    
+    /// Один из адресов (в том числе неудавшихся получателей) содержит `query`
+    /// без учёта регистра. Пустой запрос подходит всем
+    pub fn matches_address(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        query.is_empty() || self.addresses.iter()
+            .chain(self.targets.iter().map(|target| &target.address))
+            .any(|addr| addr.to_lowercase().contains(&query))
+    }
+    
+    /// Сколько времени прошло с передачи: "5м назад"
+    pub fn formatted_time_relative(&self) -> String {
+        self.formatted_time_relative_in(Language::Russian)
+    }
+    
+    /// Сколько времени прошло с передачи, на заданном языке
+    pub fn formatted_time_relative_in(&self, language: Language) -> String {
+        format_elapsed(current_timestamp().saturating_sub(self.timestamp), language)
     }
     
     /// Форматировать размер
@@ -147,14 +214,29 @@ impl HistoryEntry {
         crate::utils::format_size(self.total_size)
     }
     
+    /// Форматировать размер на заданном языке
+    pub fn formatted_size_in(&self, language: Language) -> String {
+        crate::utils::format_size_with(self.total_size, crate::utils::UnitStyle::Binary, language)
+    }
+    
     /// Форматировать скорость
     pub fn formatted_speed(&self) -> String {
         crate::stats::format_speed(self.avg_speed)
     }
     
+    /// Форматировать скорость на заданном языке
+    pub fn formatted_speed_in(&self, language: Language) -> String {
+        crate::stats::format_speed_with(self.avg_speed, language)
+    }
+    
     /// Форматировать длительность
     pub fn formatted_duration(&self) -> String {
-        crate::stats::format_duration(std::time::Duration::from_secs_f64(self.duration_secs))
+        self.formatted_duration_in(Language::Russian)
+    }
+    
+    /// Форматировать длительность на заданном языке
+    pub fn formatted_duration_in(&self, language: Language) -> String {
+        crate::stats::format_duration_with(std::time::Duration::from_secs_f64(self.duration_secs), language)
     }
 }
 
@@ -217,12 +299,79 @@ impl TransferHistory {
         let _ = self.save();
     }
     
+    /// Экспорт в CSV (заголовок + строка на запись)
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "timestamp,direction,operation,files,total_bytes,duration_secs,avg_speed,compression_ratio,addresses,success,checksum,error\n",
+        );
+        
+        for entry in &self.entries {
+            let direction = match entry.direction {
+                Direction::Send => "send",
+                Direction::Receive => "receive",
+            };
+            let operation = match entry.operation {
+                OperationType::Transfer => "transfer",
+                OperationType::Sync => "sync",
+            };
+            csv.push_str(&format!(
+                "{},{},{},{},{},{:.3},{:.0},{:.3},{},{},{},{}\n",
+                entry.timestamp,
+                direction,
+                operation,
+                entry.files_count,
+                entry.total_size,
+                entry.duration_secs,
+                entry.avg_speed,
+                entry.compression_ratio,
+                csv_field(&entry.addresses.join(" ")),
+                entry.success,
+                entry.checksum.as_deref().unwrap_or(""),
+                csv_field(entry.error.as_deref().unwrap_or("")),
+            ));
+        }
+        
+        csv
+    }
+    
+    /// Сохранить историю в CSV-файл
+    pub fn export_csv(&self, path: &std::path::Path) -> std::io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+    
+    /// Записи одного направления
+    pub fn filter_by_direction(&self, direction: Direction) -> impl Iterator<Item = &HistoryEntry> + '_ {
+        self.entries.iter().filter_by_direction(direction)
+    }
+    
+    /// Записи в промежутке `from..=to` (Unix timestamp)
+    pub fn filter_by_date_range(&self, from: u64, to: u64) -> impl Iterator<Item = &HistoryEntry> + '_ {
+        self.entries.iter().filter_by_date_range(from, to)
+    }
+    
+    /// Неудачные передачи
+    pub fn filter_failed(&self) -> impl Iterator<Item = &HistoryEntry> + '_ {
+        self.entries.iter().filter_failed()
+    }
+    
+    /// Записи, в адресах которых есть `query` (без учёта регистра)
+    pub fn search_address<'a>(&'a self, query: &'a str) -> impl Iterator<Item = &'a HistoryEntry> + 'a {
+        self.entries.iter().search_address(query)
+    }
+    
     /// Получить общую статистику
     pub fn total_stats(&self) -> HistoryStats {
+        Self::stats_for(&self.entries)
+    }
+    
+    /// Статистика по части записей, например отобранных фильтрами
+    pub fn stats_for<'a>(entries: impl IntoIterator<Item = &'a HistoryEntry>) -> HistoryStats {
         let mut stats = HistoryStats::default();
         
-        for entry in &self.entries {
+        for entry in entries {
+            stats.total_transfers += 1;
             if entry.success {
+                stats.successful_transfers += 1;
                 match entry.direction {
                     Direction::Send => {
                         stats.total_sent += entry.total_size;
@@ -236,13 +385,36 @@ impl TransferHistory {
             }
         }
         
-        stats.total_transfers = self.entries.len();
-        stats.successful_transfers = self.entries.iter().filter(|e| e.success).count();
-        
         stats
     }
 }
 
+/// Фильтры записей истории для любого итератора по ним, чтобы их можно было
+/// соединять: `history.filter_failed().search_address("nas")`
+pub trait HistoryFilters<'a>: Iterator<Item = &'a HistoryEntry> + Sized {
+    /// Записи одного направления
+    fn filter_by_direction(self, direction: Direction) -> impl Iterator<Item = &'a HistoryEntry> {
+        self.filter(move |entry| entry.direction == direction)
+    }
+    
+    /// Записи в промежутке `from..=to` (Unix timestamp)
+    fn filter_by_date_range(self, from: u64, to: u64) -> impl Iterator<Item = &'a HistoryEntry> {
+        self.filter(move |entry| entry.is_in_range(from, to))
+    }
+    
+    /// Неудачные передачи
+    fn filter_failed(self) -> impl Iterator<Item = &'a HistoryEntry> {
+        self.filter(|entry| !entry.success)
+    }
+    
+    /// Записи, в адресах которых есть `query` (без учёта регистра)
+    fn search_address(self, query: &str) -> impl Iterator<Item = &'a HistoryEntry> {
+        self.filter(move |entry| entry.matches_address(query))
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a HistoryEntry>> HistoryFilters<'a> for I {}
+
 /// Статистика истории
 #[derive(Clone, Debug, Default)]
 pub struct HistoryStats {
@@ -262,36 +434,52 @@ fn history_file_path() -> PathBuf {
         .join("history.json")
 }
 
+/// Поле CSV: в кавычках, если есть запятая, кавычка или перевод строки
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 /// Текущий Unix timestamp
-fn current_timestamp() -> u64 {
+pub fn current_timestamp() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs())
         .unwrap_or(0)
 }
 
-/// Проверка високосного года
-fn is_leap_year(year: u32) -> bool {
-    (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
+/// Unix timestamp как момент в UTC (вне диапазона chrono - начало эпохи)
+fn utc_datetime(timestamp: u64) -> DateTime<Utc> {
+    i64::try_from(timestamp).ok().and_then(|secs| DateTime::from_timestamp(secs, 0)).unwrap_or_default()
 }
 
-/// Преобразовать день года в месяц и день
-fn days_to_month_day(day_of_year: u32, leap: bool) -> (u32, u32) {
-    let days_in_months: [u32; 12] = if leap {
-        [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31]
-    } else {
-        [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31]
-    };
+/// Дата (год, месяц, день) для Unix timestamp, в UTC
+pub(crate) fn civil_date(timestamp: u64) -> (u32, u32, u32) {
+    use chrono::Datelike;
     
-    let mut remaining = day_of_year;
-    for (month, &days) in days_in_months.iter().enumerate() {
-        if remaining < days {
-            return (month as u32 + 1, remaining + 1);
-        }
-        remaining -= days;
-    }
-    
-    (12, 31)
+    let date = utc_datetime(timestamp).date_naive();
+    (date.year() as u32, date.month(), date.day())
+}
+
+/// Прошедший срок в самых крупных целых единицах: "только что", "5м назад", "3д назад"
+fn format_elapsed(secs: u64, language: Language) -> String {
+    let units = language.units();
+    let (value, unit) = match secs {
+        0..=59 => return units.just_now.to_string(),
+        60..=3599 => (secs / 60, units.minutes),
+        3600..=86399 => (secs / 3600, units.hours),
+        _ => (secs / 86400, units.days),
+    };
+    units.ago.replace("{}", &format!("{}{}", value, unit))
+}
+
+/// Текущая дата в UTC в виде YYYY-MM-DD
+pub(crate) fn current_date() -> String {
+    let (year, month, day) = civil_date(current_timestamp());
+    format!("{}-{:02}-{:02}", year, month, day)
 }
 
 #[cfg(test)]
@@ -431,6 +619,38 @@ mod tests {
         assert_eq!(stats.files_received, 3);
     }
     
+    #[test]
+    fn test_transfer_history_filters() {
+        let mut history = TransferHistory::new();
+        let mut sent = HistoryEntry::new_send(2, 100, 1.0, 1.0, vec!["192.168.1.5:9527".to_string()], true, None);
+        sent.timestamp = 1000;
+        let mut failed = HistoryEntry::new_send(1, 50, 1.0, 1.0, vec!["NAS.local:9527".to_string()], false, Some("e".to_string()));
+        failed.timestamp = 2000;
+        let mut received = HistoryEntry::new_receive(4, 300, 1.0, "192.168.1.7".to_string(), true, None);
+        received.timestamp = 3000;
+        history.entries = vec![sent, failed, received];
+        
+        let timestamps = |entries: Vec<&HistoryEntry>| entries.iter().map(|e| e.timestamp).collect::<Vec<_>>();
+        assert_eq!(timestamps(history.filter_by_direction(Direction::Send).collect()), vec![1000, 2000]);
+        assert_eq!(timestamps(history.filter_by_date_range(1500, 3000).collect()), vec![2000, 3000]);
+        assert_eq!(timestamps(history.filter_failed().collect()), vec![2000]);
+        assert_eq!(timestamps(history.search_address("nas").collect()), vec![2000]);
+        assert_eq!(timestamps(history.search_address("192.168.1.").collect()), vec![1000, 3000]);
+        assert_eq!(history.search_address("").count(), 3);
+        
+        // Фильтры соединяются
+        let chained = history.filter_by_date_range(0, 2500).search_address("192.168.1.").filter_by_direction(Direction::Send);
+        assert_eq!(timestamps(chained.collect()), vec![1000]);
+        assert_eq!(history.search_address("192.168.1.").filter_failed().count(), 0);
+        
+        // Статистика считается только по отобранным записям
+        let stats = TransferHistory::stats_for(history.filter_by_direction(Direction::Send));
+        assert_eq!((stats.total_transfers, stats.successful_transfers), (2, 1));
+        assert_eq!((stats.total_sent, stats.total_received), (100, 0));
+        let stats = TransferHistory::stats_for(&history.entries[2..]);
+        assert_eq!((stats.total_transfers, stats.files_received), (1, 4));
+    }
+    
     #[test]
     fn test_transfer_history_stats_empty() {
         let history = TransferHistory::new();
@@ -445,29 +665,52 @@ mod tests {
     // === Тесты вспомогательных функций ===
     
     #[test]
-    fn test_is_leap_year() {
-        assert!(!is_leap_year(1900)); // Делится на 100, но не на 400
-        assert!(is_leap_year(2000));  // Делится на 400
-        assert!(is_leap_year(2024));  // Делится на 4
-        assert!(!is_leap_year(2023)); // Не делится на 4
+    fn test_history_entry_localized_formatting() {
+        let mut entry = HistoryEntry::new_send(1, 2048, 3725.0, 1.0, vec![], true, None);
+        entry.timestamp = 86400 * 31 + 3600 * 5 + 60 * 7; // 01.02.1970 05:07
+        
+        assert_eq!(entry.formatted_time_in_zone(&Utc, Language::Russian), "01.02.1970 05:07");
+        assert_eq!(entry.formatted_time_in_zone(&Utc, Language::English), "1970-02-01 05:07");
+        assert_eq!(entry.formatted_duration_in(Language::Ukrainian), "1г 2хв");
+        assert_eq!(entry.formatted_duration_in(Language::English), "1h 2m");
+        assert_eq!(entry.formatted_size_in(Language::English), "2.00 KB");
+        assert_eq!(entry.formatted_size_in(Language::Russian), "2.00 КБ");
     }
     
     #[test]
-    fn test_days_to_month_day() {
-        // 1 января (день 0)
-        assert_eq!(days_to_month_day(0, false), (1, 1));
-        
-        // 31 января (день 30)
-        assert_eq!(days_to_month_day(30, false), (1, 31));
-        
-        // 1 февраля (день 31)
-        assert_eq!(days_to_month_day(31, false), (2, 1));
+    fn test_formatted_time_known_epoch_in_utc() {
+        let mut entry = HistoryEntry::new_send(1, 1, 1.0, 1.0, vec![], true, None);
+        entry.timestamp = 1_709_649_130; // 2024-03-05 14:32:10 UTC
         
-        // 29 февраля в високосный год (день 59)
-        assert_eq!(days_to_month_day(59, true), (2, 29));
+        assert_eq!(entry.formatted_time_in_zone(&Utc, Language::Russian), "05.03.2024 14:32");
+        assert_eq!(entry.formatted_time_in_zone(&Utc, Language::English), "2024-03-05 14:32");
         
-        // 1 марта в обычный год (день 59)
-        assert_eq!(days_to_month_day(59, false), (3, 1));
+        // Сдвиг пояса переносит и дату
+        let east = chrono::FixedOffset::east_opt(10 * 3600).unwrap();
+        assert_eq!(entry.formatted_time_in_zone(&east, Language::English), "2024-03-06 00:32");
+    }
+    
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(86400 * 30), (1970, 1, 31));
+        assert_eq!(civil_date(86400 * 31), (1970, 2, 1));
+        assert_eq!(civil_date(951_782_400), (2000, 2, 29)); // Делится на 400
+        assert_eq!(civil_date(1_709_164_800), (2024, 2, 29)); // Делится на 4
+        assert_eq!(civil_date(1_677_628_800), (2023, 3, 1)); // Не високосный
+    }
+    
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(5, Language::English), "just now");
+        assert_eq!(format_elapsed(5 * 60 + 59, Language::English), "5m ago");
+        assert_eq!(format_elapsed(2 * 3600, Language::Russian), "2ч назад");
+        assert_eq!(format_elapsed(3 * 86400, Language::Ukrainian), "3д тому");
+        assert_eq!(format_elapsed(2 * 3600, Language::German), "vor 2h");
+        assert_eq!(format_elapsed(10 * 60, Language::French), "il y a 10min");
+        
+        let entry = HistoryEntry::new_send(1, 1, 1.0, 1.0, vec![], true, None);
+        assert_eq!(entry.formatted_time_relative(), "только что");
     }
     
     #[test]
@@ -493,6 +736,51 @@ mod tests {
         assert_eq!(deserialized.entries[0].files_count, 5);
     }
     
+    #[test]
+    fn test_old_history_without_checksum() {
+        let json = r#"{"entries":[{"timestamp":1,"operation":"Transfer","direction":"Send",
+            "files_count":1,"total_size":10,"duration_secs":1.0,"avg_speed":10.0,
+            "compression_ratio":1.0,"addresses":[],"success":true,"error":null}]}"#;
+        let history: TransferHistory = serde_json::from_str(json).unwrap();
+        assert_eq!(history.entries[0].checksum, None);
+        assert!(history.entries[0].targets.is_empty());
+    }
+    
+    #[test]
+    fn test_history_per_target_outcomes() {
+        let entry = HistoryEntry::new_send(1, 10, 1.0, 1.0, vec!["a:9527".to_string()], true, None)
+            .with_targets(vec![
+                TargetOutcome::new("a:9527".to_string(), true, None),
+                TargetOutcome::new("b:9527".to_string(), false, Some("refused".to_string())),
+            ]);
+        
+        let json = serde_json::to_string(&entry).unwrap();
+        let restored: HistoryEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.targets, entry.targets);
+        
+        // Неудавшийся получатель находится поиском, хотя его нет в `addresses`
+        assert!(restored.matches_address("b:95"));
+    }
+    
+    #[test]
+    fn test_history_csv_export() {
+        let mut history = TransferHistory::new();
+        history.entries.push(
+            HistoryEntry::new_send(2, 100, 2.0, 1.0, vec!["a".to_string(), "b".to_string()], true, None)
+                .with_checksum(Some(0xabc)),
+        );
+        history.entries.push(HistoryEntry::new_receive(
+            1, 10, 1.0, "c".to_string(), false, Some("bad, \"very\"".to_string()),
+        ));
+        
+        let csv = history.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains(",checksum,"));
+        assert!(lines[1].ends_with(",a b,true,0000000000000abc,"));
+        assert!(lines[2].ends_with(",c,false,,\"bad, \"\"very\"\"\""));
+    }
+    
     #[test]
     fn test_direction_equality() {
         assert_eq!(Direction::Send, Direction::Send);
@@ -1,18 +1,24 @@
 //! Модуль интернационализации (i18n)
 //! 
-//! Поддерживаемые языки: русский, украинский, английский
+//! Поддерживаемые языки: русский, украинский, английский, немецкий, французский
 
 mod translations;
 
 pub use translations::*;
 
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
 /// Поддерживаемые языки
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum Language {
     #[default]
     Russian,
     Ukrainian,
     English,
+    German,
+    French,
 }
 
 impl Language {
@@ -22,6 +28,8 @@ impl Language {
             Language::Russian => "Русский",
             Language::Ukrainian => "Українська",
             Language::English => "English",
+            Language::German => "Deutsch",
+            Language::French => "Français",
         }
     }
     
@@ -31,6 +39,8 @@ impl Language {
             Language::Russian => "🇷🇺",
             Language::Ukrainian => "🇺🇦",
             Language::English => "🇬🇧",
+            Language::German => "🇩🇪",
+            Language::French => "🇫🇷",
         }
     }
     
@@ -40,15 +50,206 @@ impl Language {
             Language::Russian => "ru",
             Language::Ukrainian => "uk",
             Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
         }
     }
     
     /// Все доступные языки
     pub fn all() -> &'static [Language] {
-        &[Language::Russian, Language::Ukrainian, Language::English]
+        &[Language::Russian, Language::Ukrainian, Language::English, Language::German, Language::French]
+    }
+    
+    /// Язык по локали системы: LC_ALL, LC_MESSAGES или LANG, на Windows затем
+    /// язык пользователя ОС. Неизвестная или не заданная локаль - английский
+    pub fn from_system_locale() -> Language {
+        system_locale().as_deref().and_then(Language::from_locale).unwrap_or(Language::English)
+    }
+    
+    /// Язык по строке локали: "de_DE.UTF-8", "fr-FR", "uk" (None - не поддерживается)
+    pub fn from_locale(locale: &str) -> Option<Language> {
+        let code = locale.split(['_', '-', '.', '@']).next()?.to_lowercase();
+        Language::all().iter().copied().find(|lang| lang.code() == code)
+    }
+    
+    /// Подписи единиц измерения для этого языка
+    pub fn units(&self) -> &'static Units {
+        match self {
+            Language::Russian => &UNITS_RU,
+            Language::Ukrainian => &UNITS_UK,
+            Language::English => &UNITS_EN,
+            Language::German => &UNITS_DE,
+            Language::French => &UNITS_FR,
+        }
+    }
+}
+
+/// Откуда берутся переводы языка
+#[derive(Debug, Clone, PartialEq)]
+pub enum LanguageSource {
+    /// Встроенные в программу
+    BuiltIn,
+    /// Файл `lang/<код>.toml`; ключи, которых в нём нет, берутся из встроенных
+    File(PathBuf),
+}
+
+impl LanguageSource {
+    /// Файл перевода для языка: `<папка данных>/toolza_sender/lang/<код>.toml`,
+    /// затем `lang/<код>.toml` рядом с программой. Файла нет - встроенные переводы
+    pub fn find(lang: Language) -> Self {
+        let file_name = format!("{}.toml", lang.code());
+        lang_dirs()
+            .into_iter()
+            .map(|dir| dir.join(&file_name))
+            .find(|path| path.is_file())
+            .map_or(LanguageSource::BuiltIn, LanguageSource::File)
     }
 }
 
+/// Папки с файлами переводов в порядке приоритета
+fn lang_dirs() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(data_dir) = dirs::data_local_dir() {
+        candidates.push(data_dir.join("toolza_sender").join("lang"));
+    }
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        candidates.push(exe_dir.join("lang"));
+    }
+    candidates
+}
+
+/// Локаль из переменных окружения (первая непустая), на Windows - из настроек ОС
+fn system_locale() -> Option<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty());
+    #[cfg(windows)]
+    let locale = locale.or_else(sys_locale::get_locale);
+    locale
+}
+
+/// Подписи единиц измерения (размер, скорость, время)
+#[derive(Debug)]
+pub struct Units {
+    /// Байты, КБ, МБ, ГБ
+    pub bytes: [&'static str; 4],
+    /// Суффикс скорости ("/с", "/s")
+    pub per_second: &'static str,
+    pub seconds: &'static str,
+    pub minutes: &'static str,
+    pub hours: &'static str,
+    pub days: &'static str,
+    /// Предлог перед сроком ("через 2ч", "in 2h")
+    pub in_time: &'static str,
+    /// Шаблон прошедшего срока, {} - срок ("{} назад", "vor {}")
+    pub ago: &'static str,
+    /// Меньше минуты назад
+    pub just_now: &'static str,
+    /// Порядок даты: true - день.месяц.год, false - год-месяц-день
+    pub day_first: bool,
+}
+
+const UNITS_RU: Units = Units {
+    bytes: ["Б", "КБ", "МБ", "ГБ"],
+    per_second: "/с",
+    seconds: "с",
+    minutes: "м",
+    hours: "ч",
+    days: "д",
+    in_time: "через",
+    ago: "{} назад",
+    just_now: "только что",
+    day_first: true,
+};
+
+const UNITS_UK: Units = Units {
+    bytes: ["Б", "КБ", "МБ", "ГБ"],
+    per_second: "/с",
+    seconds: "с",
+    minutes: "хв",
+    hours: "г",
+    days: "д",
+    in_time: "через",
+    ago: "{} тому",
+    just_now: "щойно",
+    day_first: true,
+};
+
+const UNITS_EN: Units = Units {
+    bytes: ["B", "KB", "MB", "GB"],
+    per_second: "/s",
+    seconds: "s",
+    minutes: "m",
+    hours: "h",
+    days: "d",
+    in_time: "in",
+    ago: "{} ago",
+    just_now: "just now",
+    day_first: false,
+};
+
+const UNITS_DE: Units = Units {
+    bytes: ["B", "KB", "MB", "GB"],
+    per_second: "/s",
+    seconds: "s",
+    minutes: "min",
+    hours: "h",
+    days: "T",
+    in_time: "in",
+    ago: "vor {}",
+    just_now: "gerade eben",
+    day_first: true,
+};
+
+const UNITS_FR: Units = Units {
+    bytes: ["o", "Ko", "Mo", "Go"],
+    per_second: "/s",
+    seconds: "s",
+    minutes: "min",
+    hours: "h",
+    days: "j",
+    in_time: "dans",
+    ago: "il y a {}",
+    just_now: "à l'instant",
+    day_first: true,
+};
+
+/// Объявляет структуру переводов и список её полей для проверки полноты
+macro_rules! translations_struct {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $( $(#[$field_meta:meta])* pub $field:ident: &'static str, )*
+        }
+    ) => {
+        $(#[$meta])*
+        pub struct $name {
+            $( $(#[$field_meta])* pub $field: &'static str, )*
+        }
+        
+        impl $name {
+            /// Все поля в виде (имя, значение)
+            pub fn fields(&self) -> Vec<(&'static str, &'static str)> {
+                vec![$( (stringify!($field), self.$field), )*]
+            }
+            
+            /// Копия, в которой поля из `values` (ключ - имя поля) заменены.
+            /// Новые строки живут до конца программы, как и встроенные
+            pub fn with_overrides(&self, values: &HashMap<String, String>) -> Self {
+                let mut translations = self.clone();
+                $(
+                    if let Some(value) = values.get(stringify!($field)) {
+                        translations.$field = Box::leak(value.clone().into_boxed_str());
+                    }
+                )*
+                translations
+            }
+        }
+    };
+}
+
+translations_struct! {
 /// Структура с переводами всех строк интерфейса
 #[derive(Debug, Clone)]
 pub struct Translations {
@@ -79,12 +280,30 @@ pub struct Translations {
     pub options: &'static str,
     pub lz4_compression: &'static str,
     pub lz4_tooltip: &'static str,
+    pub auto_compression: &'static str,
+    pub auto_compression_tooltip: &'static str,
     pub preserve_structure: &'static str,
     pub preserve_structure_tooltip: &'static str,
     pub sync_mode: &'static str,
     pub sync_mode_tooltip: &'static str,
+    pub include_hidden: &'static str,
+    pub include_hidden_tooltip: &'static str,
     pub protocol: &'static str,
+    pub daily_quota: &'static str,
+    pub daily_quota_hint: &'static str,
+    pub quota_remaining: &'static str,
+    pub output_name: &'static str,
+    pub output_name_hint: &'static str,
+    pub output_name_tooltip: &'static str,
+    pub resume_found: &'static str,
+    pub resume_summary: &'static str,
+    pub resume_continue: &'static str,
+    pub resume_continue_tooltip: &'static str,
+    pub resume_fresh: &'static str,
+    pub resume_fresh_tooltip: &'static str,
+    pub resume_already_there: &'static str,
     pub stop: &'static str,
+    pub cancel_target: &'static str,
     pub send_to_recipients: &'static str,
     pub files_to_send: &'static str,
     pub or_drag_drop: &'static str,
@@ -96,17 +315,38 @@ pub struct Translations {
     pub your_address: &'static str,
     pub save_folder: &'static str,
     pub choose: &'static str,
+    pub open_folder: &'static str,
+    pub copy_path: &'static str,
+    pub receiver_command: &'static str,
+    pub sender_command: &'static str,
+    pub copy_command: &'static str,
+    pub show_in_folder: &'static str,
+    pub compact_mode: &'static str,
+    pub max_fps: &'static str,
+    pub completion_sound: &'static str,
+    pub choose_sound: &'static str,
+    pub default_sound: &'static str,
+    pub shortcut: &'static str,
+    pub health_idle: &'static str,
+    pub health_flowing: &'static str,
+    pub health_stalled: &'static str,
+    pub health_error: &'static str,
     pub auto_extract: &'static str,
     pub start_server: &'static str,
     pub stop_server: &'static str,
     pub received_files: &'static str,
+    pub disk_full: &'static str,
+    pub disk_full_hint: &'static str,
     pub extract_tooltip_tar_lz4: &'static str,
     pub extract_tooltip_lz4: &'static str,
+    pub extract_tooltip_zst: &'static str,
     pub extract_tooltip_tar: &'static str,
     pub extract_tooltip_zip: &'static str,
     pub extract_tooltip_rar: &'static str,
     pub save_archive_for_resume: &'static str,
     pub save_archive_tooltip: &'static str,
+    pub strip_components: &'static str,
+    pub strip_components_tooltip: &'static str,
     
     // === История ===
     pub history_title: &'static str,
@@ -114,6 +354,14 @@ pub struct Translations {
     pub no_history: &'static str,
     pub direction_sent: &'static str,
     pub direction_received: &'static str,
+    pub history_filter_all: &'static str,
+    pub history_filter_failed: &'static str,
+    pub history_period_all: &'static str,
+    pub history_period_day: &'static str,
+    pub history_period_week: &'static str,
+    pub history_period_month: &'static str,
+    pub history_search_hint: &'static str,
+    pub history_no_matches: &'static str,
     
     // === Спидтест ===
     pub speedtest_title: &'static str,
@@ -132,6 +380,7 @@ pub struct Translations {
     pub success: &'static str,
     pub connecting: &'static str,
     pub connected: &'static str,
+    pub handshaking: &'static str,
     pub disconnected: &'static str,
     pub transferring: &'static str,
     pub completed: &'static str,
@@ -160,6 +409,10 @@ pub struct Translations {
     pub no_archive_selected: &'static str,
     pub archive_path: &'static str,
     pub supported_formats: &'static str,
+    pub show_contents: &'static str,
+    pub archive_contents: &'static str,
+    pub selected_entries: &'static str,
+}
 }
 
 impl Translations {
@@ -169,7 +422,48 @@ impl Translations {
             Language::Russian => &translations::RU,
             Language::Ukrainian => &translations::UK,
             Language::English => &translations::EN,
+            Language::German => &translations::DE,
+            Language::French => &translations::FR,
+        }
+    }
+    
+    /// Переводы из TOML-файла со строками `ключ = "перевод"` (ключи - имена полей).
+    /// Отсутствующие и пустые ключи берутся из `fallback`, неизвестный ключ - ошибка
+    pub fn from_toml(path: &Path, fallback: &Translations) -> Result<Translations, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut values: HashMap<String, String> = toml::from_str(&text)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        values.retain(|_, value| !value.trim().is_empty());
+        
+        let fields = fallback.fields();
+        let mut unknown: Vec<&str> = values.keys()
+            .map(String::as_str)
+            .filter(|key| !fields.iter().any(|(field, _)| field == key))
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            return Err(format!("{}: неизвестные ключи: {}", path.display(), unknown.join(", ")));
         }
+        
+        Ok(fallback.with_overrides(&values))
+    }
+    
+    /// Переводы для языка с учётом файла перевода (`LanguageSource::find`).
+    /// Загруженный файл запоминается до конца программы; при ошибке в файле
+    /// вызывающий остаётся со встроенными переводами (`for_language`)
+    pub fn load(lang: Language) -> Result<&'static Translations, String> {
+        static LOADED: OnceLock<Mutex<HashMap<Language, &'static Translations>>> = OnceLock::new();
+        
+        let mut loaded = LOADED.get_or_init(Default::default).lock().unwrap();
+        if let Some(translations) = loaded.get(&lang) {
+            return Ok(translations);
+        }
+        let translations = match LanguageSource::find(lang) {
+            LanguageSource::BuiltIn => Self::for_language(lang),
+            LanguageSource::File(path) => Box::leak(Box::new(Self::from_toml(&path, Self::for_language(lang))?)),
+        };
+        loaded.insert(lang, translations);
+        Ok(translations)
     }
 }
 
@@ -178,3 +472,73 @@ pub fn t(lang: Language) -> &'static Translations {
     Translations::for_language(lang)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Поля, которые в этом языке законно совпадают с английским вариантом
+    /// (названия, сокращения, одинаковые слова)
+    fn same_as_english(lang: Language) -> &'static [&'static str] {
+        match lang {
+            Language::English => &[],
+            Language::Russian | Language::Ukrainian => {
+                &["app_title", "ip_address", "lz4_compression", "eta", "compression_stats"]
+            }
+            Language::German => &["app_title", "ip_address", "lz4_compression", "compression_stats", "port"],
+            Language::French => &["app_title", "ip_address", "lz4_compression", "compression_stats"],
+        }
+    }
+    
+    #[test]
+    fn test_translations_not_empty() {
+        for &lang in Language::all() {
+            for (field, value) in t(lang).fields() {
+                assert!(!value.trim().is_empty(), "{}: пустой перевод поля {}", lang.code(), field);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_language_from_locale() {
+        assert_eq!(Language::from_locale("de_DE.UTF-8"), Some(Language::German));
+        assert_eq!(Language::from_locale("fr-FR"), Some(Language::French));
+        assert_eq!(Language::from_locale("uk_UA@euro"), Some(Language::Ukrainian));
+        assert_eq!(Language::from_locale("RU"), Some(Language::Russian));
+        assert_eq!(Language::from_locale("C"), None);
+        assert_eq!(Language::from_locale("ja_JP.UTF-8"), None);
+        assert_eq!(Language::from_locale(""), None);
+    }
+    
+    #[test]
+    fn test_translations_from_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("uk.toml");
+        std::fs::write(&path, "# Спільнота\nmode_send = \"Надсилання\"\nno_history = \"\"\n").unwrap();
+        
+        // Заданный ключ заменён, пустой и отсутствующие - из встроенных
+        let uk = Translations::from_toml(&path, t(Language::Ukrainian)).unwrap();
+        assert_eq!(uk.mode_send, "Надсилання");
+        assert_eq!(uk.no_history, t(Language::Ukrainian).no_history);
+        assert_eq!(uk.mode_receive, t(Language::Ukrainian).mode_receive);
+        
+        std::fs::write(&path, "mode_sned = \"Надсилання\"\n").unwrap();
+        let err = Translations::from_toml(&path, t(Language::Ukrainian)).unwrap_err();
+        assert!(err.contains("mode_sned"), "{}", err);
+        
+        std::fs::write(&path, "mode_send = 1\n").unwrap();
+        assert!(Translations::from_toml(&path, t(Language::Ukrainian)).is_err());
+    }
+    
+    #[test]
+    fn test_translations_not_english_placeholders() {
+        let english = t(Language::English).fields();
+        for &lang in Language::all().iter().filter(|&&l| l != Language::English) {
+            for ((field, value), (_, en_value)) in t(lang).fields().into_iter().zip(&english) {
+                if same_as_english(lang).contains(&field) {
+                    continue;
+                }
+                assert_ne!(value, *en_value, "{}: поле {} не переведено", lang.code(), field);
+            }
+        }
+    }
+}
@@ -31,12 +31,30 @@ pub static RU: Translations = Translations {
     options: "Опции:",
     lz4_compression: "🗜 LZ4",
     lz4_tooltip: "Ускоряет передачу больших файлов",
+    auto_compression: "авто",
+    auto_compression_tooltip: "Сжимать только то, что сжимается: пропускать\nархивы, фото, видео и файлы с высокой энтропией",
     preserve_structure: "📂 Структура",
     preserve_structure_tooltip: "Сохранять структуру папок при передаче",
-    sync_mode: "🔄 Sync",
+    sync_mode: "🔄 Синхр.",
     sync_mode_tooltip: "Передавать только изменённые файлы",
+    include_hidden: "👻 Скрытые",
+    include_hidden_tooltip: "Добавлять скрытые и системные файлы при выборе папки",
     protocol: "Протокол:",
+    daily_quota: "📊 Лимит в день:",
+    daily_quota_hint: "без лимита",
+    quota_remaining: "осталось",
+    output_name: "📝 Имя у получателя:",
+    output_name_hint: "как есть",
+    output_name_tooltip: "Сохранить файл у получателя под другим именем; можно с подпутём: docs/report.txt",
+    resume_found: "↻ У {} уже есть часть файлов:",
+    resume_summary: "получено {}, прервано {}",
+    resume_continue: "▶ Продолжить",
+    resume_continue_tooltip: "Пропустить полученные файлы и докачать прерванные",
+    resume_fresh: "🆕 Заново",
+    resume_fresh_tooltip: "Передать все файлы с начала, перезаписав копии у получателя",
+    resume_already_there: "уже у получателя",
     stop: "⏹ Остановить",
+    cancel_target: "Остановить отправку только на этого получателя",
     send_to_recipients: "🚀 Отправить на {} получателей",
     files_to_send: "Файлы для отправки:",
     or_drag_drop: " (или перетащите сюда)",
@@ -48,17 +66,38 @@ pub static RU: Translations = Translations {
     your_address: "Ваш адрес для подключения:",
     save_folder: "Папка сохранения:",
     choose: "📁 Выбрать",
+    open_folder: "📂 Открыть папку",
+    copy_path: "📋 Копировать путь",
+    receiver_command: "Команда для получателя:",
+    sender_command: "Команда для отправителя:",
+    copy_command: "📋 Копировать",
+    show_in_folder: "📂 Показать в папке",
+    compact_mode: "Компактный режим (без боковой панели и лога)",
+    max_fps: "Максимум кадров в секунду во время передачи",
+    completion_sound: "Звук по завершении передачи (ПКМ - выбрать файл)",
+    choose_sound: "🎵 Выбрать звук...",
+    default_sound: "↺ Встроенный звук",
+    shortcut: "Горячая клавиша",
+    health_idle: "Нет активной передачи",
+    health_flowing: "Данные передаются",
+    health_stalled: "Нет прогресса больше 3 секунд",
+    health_error: "Ошибка соединения",
     auto_extract: "Авто-распаковка:",
     start_server: "▶ Запустить сервер",
     stop_server: "⏹ Остановить сервер",
     received_files: "Полученные файлы:",
+    disk_full: "💾 Диск заполнен: {} сохранён частично ({})",
+    disk_full_hint: "Освободите место - при повторной отправке файл докачается",
     extract_tooltip_tar_lz4: "Распаковывать .tar.lz4 архивы",
     extract_tooltip_lz4: "Распаковывать .lz4 файлы (не архивы)",
+    extract_tooltip_zst: "Распаковывать .zst файлы (не архивы) на лету",
     extract_tooltip_tar: "Распаковывать .tar и .tar.gz архивы",
     extract_tooltip_zip: "Распаковывать .zip архивы",
-    extract_tooltip_rar: "Распаковывать .rar архивы\n(требует установленный unrar)",
+    extract_tooltip_rar: "Распаковка .rar не поддерживается\n(распакуйте вручную: unrar x archive.rar)",
     save_archive_for_resume: "💾 Сохранять архив (для резюме)",
     save_archive_tooltip: "Сохранять .tar.lz4 на диск для возможности\nвозобновить при обрыве соединения",
+    strip_components: "Убрать верхних папок:",
+    strip_components_tooltip: "Отбросить N ведущих папок у файлов архива\n(как tar --strip-components): project-1.2/src → src",
     
     // === История ===
     history_title: "📜 История передач",
@@ -66,6 +105,14 @@ pub static RU: Translations = Translations {
     no_history: "История пуста",
     direction_sent: "📤 Отправлено",
     direction_received: "📥 Получено",
+    history_filter_all: "Все передачи",
+    history_filter_failed: "❌ С ошибками",
+    history_period_all: "За всё время",
+    history_period_day: "За сутки",
+    history_period_week: "За неделю",
+    history_period_month: "За месяц",
+    history_search_hint: "Поиск по адресу",
+    history_no_matches: "Нет записей по фильтру",
     
     // === Спидтест ===
     speedtest_title: "⚡ Тест скорости",
@@ -84,6 +131,7 @@ pub static RU: Translations = Translations {
     success: "Успешно",
     connecting: "Подключение...",
     connected: "Подключено",
+    handshaking: "Согласование...",
     disconnected: "Отключено",
     transferring: "Передача...",
     completed: "Завершено",
@@ -112,6 +160,9 @@ pub static RU: Translations = Translations {
     no_archive_selected: "Не выбран архив",
     archive_path: "Архив:",
     supported_formats: "Поддерживаемые форматы: .tar.lz4, .lz4, .tar, .tar.gz, .zip, .rar, .7z",
+    show_contents: "📋 Содержимое",
+    archive_contents: "Содержимое архива:",
+    selected_entries: "Выбрано для распаковки:",
 };
 
 /// Українська мова
@@ -143,12 +194,30 @@ pub static UK: Translations = Translations {
     options: "Опції:",
     lz4_compression: "🗜 LZ4",
     lz4_tooltip: "Прискорює передачу великих файлів",
+    auto_compression: "авто",
+    auto_compression_tooltip: "Стискати лише те, що стискається: пропускати\nархіви, фото, відео та файли з високою ентропією",
     preserve_structure: "📂 Структура",
     preserve_structure_tooltip: "Зберігати структуру тек при передачі",
-    sync_mode: "🔄 Sync",
+    sync_mode: "🔄 Синхр.",
     sync_mode_tooltip: "Передавати тільки змінені файли",
+    include_hidden: "👻 Приховані",
+    include_hidden_tooltip: "Додавати приховані та системні файли при виборі теки",
     protocol: "Протокол:",
+    daily_quota: "📊 Ліміт на день:",
+    daily_quota_hint: "без ліміту",
+    quota_remaining: "залишилось",
+    output_name: "📝 Ім'я в отримувача:",
+    output_name_hint: "як є",
+    output_name_tooltip: "Зберегти файл в отримувача під іншим ім'ям; можна з підшляхом: docs/report.txt",
+    resume_found: "↻ У {} вже є частина файлів:",
+    resume_summary: "отримано {}, перервано {}",
+    resume_continue: "▶ Продовжити",
+    resume_continue_tooltip: "Пропустити отримані файли та докачати перервані",
+    resume_fresh: "🆕 Наново",
+    resume_fresh_tooltip: "Передати всі файли з початку, перезаписавши копії в отримувача",
+    resume_already_there: "вже в отримувача",
     stop: "⏹ Зупинити",
+    cancel_target: "Зупинити надсилання лише цьому отримувачу",
     send_to_recipients: "🚀 Надіслати на {} отримувачів",
     files_to_send: "Файли для надсилання:",
     or_drag_drop: " (або перетягніть сюди)",
@@ -160,17 +229,38 @@ pub static UK: Translations = Translations {
     your_address: "Ваша адреса для підключення:",
     save_folder: "Тека збереження:",
     choose: "📁 Вибрати",
+    open_folder: "📂 Відкрити теку",
+    copy_path: "📋 Копіювати шлях",
+    receiver_command: "Команда для отримувача:",
+    sender_command: "Команда для відправника:",
+    copy_command: "📋 Копіювати",
+    show_in_folder: "📂 Показати в теці",
+    compact_mode: "Компактний режим (без бічної панелі та журналу)",
+    max_fps: "Максимум кадрів на секунду під час передачі",
+    completion_sound: "Звук після завершення передачі (ПКМ - вибрати файл)",
+    choose_sound: "🎵 Вибрати звук...",
+    default_sound: "↺ Вбудований звук",
+    shortcut: "Гаряча клавіша",
+    health_idle: "Немає активної передачі",
+    health_flowing: "Дані передаються",
+    health_stalled: "Немає прогресу понад 3 секунди",
+    health_error: "Помилка з'єднання",
     auto_extract: "Авто-розпакування:",
     start_server: "▶ Запустити сервер",
     stop_server: "⏹ Зупинити сервер",
     received_files: "Отримані файли:",
+    disk_full: "💾 Диск заповнено: {} збережено частково ({})",
+    disk_full_hint: "Звільніть місце - під час повторного надсилання файл докачається",
     extract_tooltip_tar_lz4: "Розпаковувати .tar.lz4 архіви",
     extract_tooltip_lz4: "Розпаковувати .lz4 файли (не архіви)",
+    extract_tooltip_zst: "Розпаковувати .zst файли (не архіви) на льоту",
     extract_tooltip_tar: "Розпаковувати .tar та .tar.gz архіви",
     extract_tooltip_zip: "Розпаковувати .zip архіви",
-    extract_tooltip_rar: "Розпаковувати .rar архіви\n(потрібен встановлений unrar)",
+    extract_tooltip_rar: "Розпакування .rar не підтримується\n(розпакуйте вручну: unrar x archive.rar)",
     save_archive_for_resume: "💾 Зберігати архів (для резюме)",
     save_archive_tooltip: "Зберігати .tar.lz4 на диск для можливості\nвідновити при обриві з'єднання",
+    strip_components: "Прибрати верхніх тек:",
+    strip_components_tooltip: "Відкинути N провідних тек у файлів архіву\n(як tar --strip-components): project-1.2/src → src",
     
     // === Історія ===
     history_title: "📜 Історія передач",
@@ -178,6 +268,14 @@ pub static UK: Translations = Translations {
     no_history: "Історія порожня",
     direction_sent: "📤 Надіслано",
     direction_received: "📥 Отримано",
+    history_filter_all: "Усі передачі",
+    history_filter_failed: "❌ З помилками",
+    history_period_all: "За весь час",
+    history_period_day: "За добу",
+    history_period_week: "За тиждень",
+    history_period_month: "За місяць",
+    history_search_hint: "Пошук за адресою",
+    history_no_matches: "Немає записів за фільтром",
     
     // === Спідтест ===
     speedtest_title: "⚡ Тест швидкості",
@@ -196,6 +294,7 @@ pub static UK: Translations = Translations {
     success: "Успішно",
     connecting: "Підключення...",
     connected: "Підключено",
+    handshaking: "Узгодження...",
     disconnected: "Відключено",
     transferring: "Передача...",
     completed: "Завершено",
@@ -224,6 +323,9 @@ pub static UK: Translations = Translations {
     no_archive_selected: "Не вибрано архів",
     archive_path: "Архів:",
     supported_formats: "Підтримувані формати: .tar.lz4, .lz4, .tar, .tar.gz, .zip, .rar, .7z",
+    show_contents: "📋 Вміст",
+    archive_contents: "Вміст архіву:",
+    selected_entries: "Вибрано для розпакування:",
 };
 
 /// English language
@@ -255,12 +357,30 @@ pub static EN: Translations = Translations {
     options: "Options:",
     lz4_compression: "🗜 LZ4",
     lz4_tooltip: "Speeds up transfer of large files",
+    auto_compression: "auto",
+    auto_compression_tooltip: "Compress only what compresses: skip archives,\nphotos, videos and high-entropy files",
     preserve_structure: "📂 Structure",
     preserve_structure_tooltip: "Preserve folder structure during transfer",
     sync_mode: "🔄 Sync",
     sync_mode_tooltip: "Transfer only modified files",
+    include_hidden: "👻 Hidden",
+    include_hidden_tooltip: "Include hidden and system files when adding a folder",
     protocol: "Protocol:",
+    daily_quota: "📊 Daily cap:",
+    daily_quota_hint: "no limit",
+    quota_remaining: "left",
+    output_name: "📝 Remote name:",
+    output_name_hint: "as is",
+    output_name_tooltip: "Save the file under another name on the receiver; a subpath like docs/report.txt is allowed",
+    resume_found: "↻ {} already has some of these files:",
+    resume_summary: "{} received, {} interrupted",
+    resume_continue: "▶ Resume",
+    resume_continue_tooltip: "Skip received files and continue the interrupted ones",
+    resume_fresh: "🆕 Start fresh",
+    resume_fresh_tooltip: "Send every file from the start, overwriting the receiver's copies",
+    resume_already_there: "already on receiver",
     stop: "⏹ Stop",
+    cancel_target: "Stop sending to this recipient only",
     send_to_recipients: "🚀 Send to {} recipients",
     files_to_send: "Files to send:",
     or_drag_drop: " (or drag & drop here)",
@@ -272,17 +392,38 @@ pub static EN: Translations = Translations {
     your_address: "Your connection address:",
     save_folder: "Save folder:",
     choose: "📁 Choose",
+    open_folder: "📂 Open folder",
+    copy_path: "📋 Copy path",
+    receiver_command: "Receiver command:",
+    sender_command: "Sender command:",
+    copy_command: "📋 Copy",
+    show_in_folder: "📂 Show in folder",
+    compact_mode: "Compact mode (no sidebar or log)",
+    max_fps: "Max frames per second during transfers",
+    completion_sound: "Sound when a transfer finishes (right-click to choose a file)",
+    choose_sound: "🎵 Choose sound...",
+    default_sound: "↺ Built-in sound",
+    shortcut: "Shortcut",
+    health_idle: "No active transfer",
+    health_flowing: "Data is flowing",
+    health_stalled: "No progress for over 3 seconds",
+    health_error: "Connection error",
     auto_extract: "Auto-extract:",
     start_server: "▶ Start Server",
     stop_server: "⏹ Stop Server",
     received_files: "Received files:",
+    disk_full: "💾 Disk full: {} was saved partially ({})",
+    disk_full_hint: "Free some space - sending again will resume the file",
     extract_tooltip_tar_lz4: "Extract .tar.lz4 archives",
     extract_tooltip_lz4: "Extract .lz4 files (not archives)",
+    extract_tooltip_zst: "Extract .zst files (not archives) on the fly",
     extract_tooltip_tar: "Extract .tar and .tar.gz archives",
     extract_tooltip_zip: "Extract .zip archives",
-    extract_tooltip_rar: "Extract .rar archives\n(requires unrar installed)",
+    extract_tooltip_rar: "Extracting .rar is not supported\n(extract manually: unrar x archive.rar)",
     save_archive_for_resume: "💾 Save archive (for resume)",
     save_archive_tooltip: "Save .tar.lz4 to disk to allow\nresume on connection failure",
+    strip_components: "Strip leading folders:",
+    strip_components_tooltip: "Drop N leading folders from archive entries\n(like tar --strip-components): project-1.2/src → src",
     
     // === History ===
     history_title: "📜 Transfer History",
@@ -290,6 +431,14 @@ pub static EN: Translations = Translations {
     no_history: "History is empty",
     direction_sent: "📤 Sent",
     direction_received: "📥 Received",
+    history_filter_all: "All transfers",
+    history_filter_failed: "❌ Failed",
+    history_period_all: "All time",
+    history_period_day: "Last 24 hours",
+    history_period_week: "Last week",
+    history_period_month: "Last month",
+    history_search_hint: "Search by address",
+    history_no_matches: "No entries match the filter",
     
     // === Speedtest ===
     speedtest_title: "⚡ Speed Test",
@@ -308,6 +457,7 @@ pub static EN: Translations = Translations {
     success: "Success",
     connecting: "Connecting...",
     connected: "Connected",
+    handshaking: "Negotiating...",
     disconnected: "Disconnected",
     transferring: "Transferring...",
     completed: "Completed",
@@ -336,5 +486,334 @@ pub static EN: Translations = Translations {
     no_archive_selected: "No archive selected",
     archive_path: "Archive:",
     supported_formats: "Supported formats: .tar.lz4, .lz4, .tar, .tar.gz, .zip, .rar, .7z",
+    show_contents: "📋 Contents",
+    archive_contents: "Archive contents:",
+    selected_entries: "Selected for extraction:",
+};
+
+/// Deutsche Sprache
+pub static DE: Translations = Translations {
+    // === Hauptmenü ===
+    app_title: "Toolza Sender",
+    mode_send: "Senden",
+    mode_receive: "Empfangen",
+    mode_extract: "Entpacken",
+    mode_history: "Verlauf",
+    mode_speedtest: "Geschwindigkeitstest",
+    
+    // === Senden ===
+    send_title: "📤 Dateien senden",
+    your_ip: "Ihre IP:",
+    port: "Port:",
+    recipients: "Empfänger:",
+    ip_address: "IP:",
+    add: "➕ Hinzufügen",
+    subnets: "Subnetze:",
+    subnets_hint: "automatisch (oder 192.168.1, 10.0.0)",
+    subnets_tooltip: "Leer lassen für automatische Erkennung.\nMehrere Subnetze mit Komma trennen.",
+    find_servers: "🔍 Server suchen",
+    cancel: "⏹ Abbrechen",
+    clear: "🗑 Leeren",
+    found_servers: "Gefundene Server (zum Hinzufügen klicken):",
+    files: "➕ Dateien",
+    folder: "📁 Ordner",
+    options: "Optionen:",
+    lz4_compression: "🗜 LZ4",
+    lz4_tooltip: "Beschleunigt die Übertragung großer Dateien",
+    auto_compression: "automatisch",
+    auto_compression_tooltip: "Nur Komprimierbares komprimieren: Archive, Fotos,\nVideos und Dateien mit hoher Entropie überspringen",
+    preserve_structure: "📂 Struktur",
+    preserve_structure_tooltip: "Ordnerstruktur bei der Übertragung beibehalten",
+    sync_mode: "🔄 Abgleich",
+    sync_mode_tooltip: "Nur geänderte Dateien übertragen",
+    include_hidden: "👻 Versteckte",
+    include_hidden_tooltip: "Versteckte und Systemdateien beim Hinzufügen eines Ordners einschließen",
+    protocol: "Protokoll:",
+    daily_quota: "📊 Tageslimit:",
+    daily_quota_hint: "kein Limit",
+    quota_remaining: "übrig",
+    output_name: "📝 Name beim Empfänger:",
+    output_name_hint: "unverändert",
+    output_name_tooltip: "Datei beim Empfänger unter anderem Namen speichern; ein Unterpfad wie docs/report.txt ist erlaubt",
+    resume_found: "↻ {} hat bereits einen Teil dieser Dateien:",
+    resume_summary: "{} empfangen, {} unterbrochen",
+    resume_continue: "▶ Fortsetzen",
+    resume_continue_tooltip: "Empfangene Dateien überspringen und unterbrochene fortsetzen",
+    resume_fresh: "🆕 Neu beginnen",
+    resume_fresh_tooltip: "Alle Dateien von vorn senden und die Kopien des Empfängers überschreiben",
+    resume_already_there: "bereits beim Empfänger",
+    stop: "⏹ Stoppen",
+    cancel_target: "Nur das Senden an diesen Empfänger stoppen",
+    send_to_recipients: "🚀 An {} Empfänger senden",
+    files_to_send: "Zu sendende Dateien:",
+    or_drag_drop: " (oder hierher ziehen)",
+    eta: "⏱ Restzeit:",
+    compression_stats: "📦",
+    
+    // === Empfangen ===
+    receive_title: "📥 Dateien empfangen",
+    your_address: "Ihre Verbindungsadresse:",
+    save_folder: "Speicherordner:",
+    choose: "📁 Auswählen",
+    open_folder: "📂 Ordner öffnen",
+    copy_path: "📋 Pfad kopieren",
+    receiver_command: "Befehl für den Empfänger:",
+    sender_command: "Befehl für den Absender:",
+    copy_command: "📋 Kopieren",
+    show_in_folder: "📂 Im Ordner zeigen",
+    compact_mode: "Kompaktmodus (ohne Seitenleiste und Protokoll)",
+    max_fps: "Maximale Bilder pro Sekunde während der Übertragung",
+    completion_sound: "Ton nach Abschluss der Übertragung (Rechtsklick - Datei wählen)",
+    choose_sound: "🎵 Ton wählen...",
+    default_sound: "↺ Eingebauter Ton",
+    shortcut: "Tastenkürzel",
+    health_idle: "Keine aktive Übertragung",
+    health_flowing: "Daten werden übertragen",
+    health_stalled: "Seit über 3 Sekunden kein Fortschritt",
+    health_error: "Verbindungsfehler",
+    auto_extract: "Automatisch entpacken:",
+    start_server: "▶ Server starten",
+    stop_server: "⏹ Server stoppen",
+    received_files: "Empfangene Dateien:",
+    disk_full: "💾 Datenträger voll: {} wurde teilweise gespeichert ({})",
+    disk_full_hint: "Geben Sie Speicherplatz frei - erneutes Senden setzt die Datei fort",
+    extract_tooltip_tar_lz4: ".tar.lz4-Archive entpacken",
+    extract_tooltip_lz4: ".lz4-Dateien (keine Archive) entpacken",
+    extract_tooltip_zst: ".zst-Dateien (keine Archive) direkt entpacken",
+    extract_tooltip_tar: ".tar- und .tar.gz-Archive entpacken",
+    extract_tooltip_zip: ".zip-Archive entpacken",
+    extract_tooltip_rar: "Entpacken von .rar wird nicht unterstützt\n(manuell entpacken: unrar x archive.rar)",
+    save_archive_for_resume: "💾 Archiv speichern (zum Fortsetzen)",
+    save_archive_tooltip: ".tar.lz4 auf der Festplatte speichern, um nach\neinem Verbindungsabbruch fortsetzen zu können",
+    strip_components: "Führende Ordner entfernen:",
+    strip_components_tooltip: "N führende Ordner der Archiveinträge verwerfen\n(wie tar --strip-components): project-1.2/src → src",
+    
+    // === Verlauf ===
+    history_title: "📜 Übertragungsverlauf",
+    clear_history: "🗑 Verlauf löschen",
+    no_history: "Der Verlauf ist leer",
+    direction_sent: "📤 Gesendet",
+    direction_received: "📥 Empfangen",
+    history_filter_all: "Alle Übertragungen",
+    history_filter_failed: "❌ Fehlgeschlagen",
+    history_period_all: "Gesamter Zeitraum",
+    history_period_day: "Letzte 24 Stunden",
+    history_period_week: "Letzte Woche",
+    history_period_month: "Letzter Monat",
+    history_search_hint: "Nach Adresse suchen",
+    history_no_matches: "Keine Einträge für diesen Filter",
+    
+    // === Geschwindigkeitstest ===
+    speedtest_title: "⚡ Geschwindigkeitstest",
+    target_address: "Serveradresse:",
+    start_test: "▶ Test starten",
+    testing: "Test läuft...",
+    ping: "Latenz:",
+    upload: "Hochladen:",
+    download: "Herunterladen:",
+    test_results: "Testergebnisse:",
+    
+    // === Allgemein ===
+    status: "Zustand:",
+    log: "Protokoll:",
+    error: "Fehler",
+    success: "Erfolgreich",
+    connecting: "Verbinde...",
+    connected: "Verbunden",
+    handshaking: "Aushandlung...",
+    disconnected: "Getrennt",
+    transferring: "Übertrage...",
+    completed: "Abgeschlossen",
+    pending: "Wartend",
+    waiting_connections: "Warte auf Verbindungen an Port {}...",
+    server_started: "Server an Port {} gestartet",
+    file_received: "📥 Empfangen:",
+    extraction_started: "📦 Entpacke:",
+    extraction_completed: "✅ Entpackt",
+    extraction_error: "❌ Fehler beim Entpacken",
+    invalid_port: "Ungültiger Port",
+    no_files_selected: "Keine Dateien ausgewählt",
+    no_recipients: "Keine Empfänger angegeben",
+    
+    // === Protokolle ===
+    tcp_description: "Zuverlässiges Standardprotokoll",
+    udp_description: "Schnell, ohne Zustellgarantie (zum Testen)",
+    quic_description: "Schnell mit Verschlüsselung (UDP)",
+    kcp_description: "Sehr schnell, geringe Latenz (UDP)",
+    
+    // === Entpacken ===
+    extract_title: "📦 Archive entpacken",
+    select_archive: "📂 Archiv wählen",
+    extract_destination: "Zielordner:",
+    start_extraction: "🚀 Entpacken",
+    no_archive_selected: "Kein Archiv ausgewählt",
+    archive_path: "Archiv:",
+    supported_formats: "Unterstützte Formate: .tar.lz4, .lz4, .tar, .tar.gz, .zip, .rar, .7z",
+    show_contents: "📋 Inhalt",
+    archive_contents: "Archivinhalt:",
+    selected_entries: "Zum Entpacken ausgewählt:",
+};
+
+/// Langue française
+pub static FR: Translations = Translations {
+    // === Menu principal ===
+    app_title: "Toolza Sender",
+    mode_send: "Envoi",
+    mode_receive: "Réception",
+    mode_extract: "Extraction",
+    mode_history: "Historique",
+    mode_speedtest: "Test de débit",
+    
+    // === Envoi ===
+    send_title: "📤 Envoyer des fichiers",
+    your_ip: "Votre IP :",
+    port: "Port :",
+    recipients: "Destinataires :",
+    ip_address: "IP:",
+    add: "➕ Ajouter",
+    subnets: "Sous-réseaux :",
+    subnets_hint: "auto (ou 192.168.1, 10.0.0)",
+    subnets_tooltip: "Laisser vide pour la détection automatique.\nPlusieurs sous-réseaux séparés par des virgules.",
+    find_servers: "🔍 Chercher des serveurs",
+    cancel: "⏹ Annuler",
+    clear: "🗑 Vider",
+    found_servers: "Serveurs trouvés (cliquer pour ajouter) :",
+    files: "➕ Fichiers",
+    folder: "📁 Dossier",
+    options: "Options :",
+    lz4_compression: "🗜 LZ4",
+    lz4_tooltip: "Accélère le transfert des gros fichiers",
+    auto_compression: "automatique",
+    auto_compression_tooltip: "Ne compresser que ce qui se compresse : ignorer les archives,\nphotos, vidéos et fichiers à forte entropie",
+    preserve_structure: "📂 Arborescence",
+    preserve_structure_tooltip: "Conserver l'arborescence des dossiers lors du transfert",
+    sync_mode: "🔄 Synchro",
+    sync_mode_tooltip: "Ne transférer que les fichiers modifiés",
+    include_hidden: "👻 Cachés",
+    include_hidden_tooltip: "Inclure les fichiers cachés et système lors de l'ajout d'un dossier",
+    protocol: "Protocole :",
+    daily_quota: "📊 Plafond quotidien :",
+    daily_quota_hint: "sans limite",
+    quota_remaining: "restant",
+    output_name: "📝 Nom chez le destinataire :",
+    output_name_hint: "tel quel",
+    output_name_tooltip: "Enregistrer le fichier sous un autre nom chez le destinataire ; un sous-chemin comme docs/report.txt est permis",
+    resume_found: "↻ {} a déjà une partie de ces fichiers :",
+    resume_summary: "{} reçus, {} interrompus",
+    resume_continue: "▶ Reprendre",
+    resume_continue_tooltip: "Ignorer les fichiers reçus et reprendre ceux qui ont été interrompus",
+    resume_fresh: "🆕 Recommencer",
+    resume_fresh_tooltip: "Tout renvoyer depuis le début en écrasant les copies du destinataire",
+    resume_already_there: "déjà chez le destinataire",
+    stop: "⏹ Arrêter",
+    cancel_target: "Arrêter l'envoi à ce destinataire seulement",
+    send_to_recipients: "🚀 Envoyer à {} destinataires",
+    files_to_send: "Fichiers à envoyer :",
+    or_drag_drop: " (ou glissez-les ici)",
+    eta: "⏱ Temps restant :",
+    compression_stats: "📦",
+    
+    // === Réception ===
+    receive_title: "📥 Recevoir des fichiers",
+    your_address: "Votre adresse de connexion :",
+    save_folder: "Dossier d'enregistrement :",
+    choose: "📁 Choisir",
+    open_folder: "📂 Ouvrir le dossier",
+    copy_path: "📋 Copier le chemin",
+    receiver_command: "Commande du destinataire :",
+    sender_command: "Commande de l'expéditeur :",
+    copy_command: "📋 Copier",
+    show_in_folder: "📂 Afficher dans le dossier",
+    compact_mode: "Mode compact (sans panneau latéral ni journal)",
+    max_fps: "Images par seconde maximum pendant les transferts",
+    completion_sound: "Son à la fin d'un transfert (clic droit pour choisir un fichier)",
+    choose_sound: "🎵 Choisir un son...",
+    default_sound: "↺ Son intégré",
+    shortcut: "Raccourci",
+    health_idle: "Aucun transfert en cours",
+    health_flowing: "Les données circulent",
+    health_stalled: "Aucune progression depuis plus de 3 secondes",
+    health_error: "Erreur de connexion",
+    auto_extract: "Extraction auto :",
+    start_server: "▶ Démarrer le serveur",
+    stop_server: "⏹ Arrêter le serveur",
+    received_files: "Fichiers reçus :",
+    disk_full: "💾 Disque plein : {} a été enregistré partiellement ({})",
+    disk_full_hint: "Libérez de l'espace - un nouvel envoi reprendra le fichier",
+    extract_tooltip_tar_lz4: "Extraire les archives .tar.lz4",
+    extract_tooltip_lz4: "Extraire les fichiers .lz4 (pas les archives)",
+    extract_tooltip_zst: "Extraire les fichiers .zst (pas les archives) à la volée",
+    extract_tooltip_tar: "Extraire les archives .tar et .tar.gz",
+    extract_tooltip_zip: "Extraire les archives .zip",
+    extract_tooltip_rar: "L'extraction des .rar n'est pas prise en charge\n(extraire à la main : unrar x archive.rar)",
+    save_archive_for_resume: "💾 Garder l'archive (pour reprendre)",
+    save_archive_tooltip: "Enregistrer le .tar.lz4 sur le disque pour pouvoir\nreprendre après une coupure de connexion",
+    strip_components: "Retirer les dossiers de tête :",
+    strip_components_tooltip: "Retirer N dossiers de tête des entrées de l'archive\n(comme tar --strip-components) : project-1.2/src → src",
+    
+    // === Historique ===
+    history_title: "📜 Historique des transferts",
+    clear_history: "🗑 Effacer l'historique",
+    no_history: "L'historique est vide",
+    direction_sent: "📤 Envoyé",
+    direction_received: "📥 Reçu",
+    history_filter_all: "Tous les transferts",
+    history_filter_failed: "❌ Échoués",
+    history_period_all: "Depuis le début",
+    history_period_day: "Dernières 24 heures",
+    history_period_week: "Dernière semaine",
+    history_period_month: "Dernier mois",
+    history_search_hint: "Chercher par adresse",
+    history_no_matches: "Aucune entrée ne correspond au filtre",
+    
+    // === Test de débit ===
+    speedtest_title: "⚡ Test de débit",
+    target_address: "Adresse du serveur :",
+    start_test: "▶ Lancer le test",
+    testing: "Test en cours...",
+    ping: "Latence :",
+    upload: "Envoi :",
+    download: "Réception :",
+    test_results: "Résultats du test :",
+    
+    // === Commun ===
+    status: "État :",
+    log: "Journal :",
+    error: "Erreur",
+    success: "Réussi",
+    connecting: "Connexion...",
+    connected: "Connecté",
+    handshaking: "Négociation...",
+    disconnected: "Déconnecté",
+    transferring: "Transfert...",
+    completed: "Terminé",
+    pending: "En attente",
+    waiting_connections: "En attente de connexions sur le port {}...",
+    server_started: "Serveur démarré sur le port {}",
+    file_received: "📥 Reçu :",
+    extraction_started: "📦 Extraction :",
+    extraction_completed: "✅ Extrait",
+    extraction_error: "❌ Erreur d'extraction",
+    invalid_port: "Port invalide",
+    no_files_selected: "Aucun fichier sélectionné",
+    no_recipients: "Aucun destinataire indiqué",
+    
+    // === Protocoles ===
+    tcp_description: "Protocole standard et fiable",
+    udp_description: "Rapide, sans garantie de livraison (pour les tests)",
+    quic_description: "Rapide et chiffré (UDP)",
+    kcp_description: "Ultra-rapide, faible latence (UDP)",
+    
+    // === Extraction ===
+    extract_title: "📦 Extraction d'archives",
+    select_archive: "📂 Choisir une archive",
+    extract_destination: "Dossier de destination :",
+    start_extraction: "🚀 Extraire",
+    no_archive_selected: "Aucune archive sélectionnée",
+    archive_path: "Archive :",
+    supported_formats: "Formats pris en charge : .tar.lz4, .lz4, .tar, .tar.gz, .zip, .rar, .7z",
+    show_contents: "📋 Contenu",
+    archive_contents: "Contenu de l'archive :",
+    selected_entries: "Sélectionné pour l'extraction :",
 };
 
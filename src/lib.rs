@@ -7,16 +7,22 @@
 //! - `protocol` - протокол передачи файлов
 //! - `utils` - вспомогательные функции
 //! - `extract` - распаковка tar.lz4 архивов
+//! - `pattern` - glob-шаблоны путей (`*`, `?`, `**`)
 //! - `stats` - статистика передачи (скорость, ETA)
 //! - `history` - история передач
+//! - `settings` - сохраняемые настройки интерфейса
 //! - `sync` - режим синхронизации
+//! - `control` - управляющий API демона (JSON-RPC)
 //! - `i18n` - интернационализация (русский, украинский, английский)
 
+pub mod control;
 pub mod extract;
 pub mod history;
 pub mod i18n;
 pub mod network;
+pub mod pattern;
 pub mod protocol;
+pub mod settings;
 pub mod stats;
 pub mod sync;
 pub mod utils;
@@ -0,0 +1,75 @@
+//! Разбор адресов получателей: IPv4, IPv6 и имена хостов, с портом и без
+
+use std::net::{IpAddr, SocketAddr};
+
+/// Привести адрес получателя к виду `хост:порт`, подставив `default_port`, если порт не указан.
+///
+/// Понимает `192.168.1.5`, `192.168.1.5:8000`, `fe80::1`, `[fe80::1]`, `[fe80::1]:8000`,
+/// `host` и `host:8000`. IPv6 возвращается в скобках (`[fe80::1]:9527`), имя хоста - как есть:
+/// оно разрешается при подключении
+pub fn parse_target_addr(input: &str, default_port: u16) -> Result<String, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Пустой адрес".to_string());
+    }
+    
+    // IP с портом: 192.168.1.5:8000, [fe80::1]:8000
+    if let Ok(addr) = input.parse::<SocketAddr>() {
+        return Ok(addr.to_string());
+    }
+    
+    // IP без порта: 192.168.1.5, fe80::1, [fe80::1]
+    let bare = input.strip_prefix('[').and_then(|s| s.strip_suffix(']')).unwrap_or(input);
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, default_port).to_string());
+    }
+    
+    // Имя хоста: host или host:8000. Несколько двоеточий - не IPv6 и не имя
+    match input.split_once(':') {
+        None if is_hostname(input) => Ok(format!("{}:{}", input, default_port)),
+        Some((host, port)) if is_hostname(host) && !port.contains(':') => {
+            let port: u16 = port.parse().map_err(|_| format!("Неверный порт в адресе '{}'", input))?;
+            Ok(format!("{}:{}", host, port))
+        }
+        _ => Err(format!("Неверный адрес: '{}'", input)),
+    }
+}
+
+/// Допустимое имя хоста: буквы, цифры, `-`, `.` и `_`
+fn is_hostname(host: &str) -> bool {
+    !host.is_empty() && host.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '.' | '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_ipv4() {
+        assert_eq!(parse_target_addr("192.168.1.5", 9527).unwrap(), "192.168.1.5:9527");
+        assert_eq!(parse_target_addr(" 192.168.1.5:8000 ", 9527).unwrap(), "192.168.1.5:8000");
+    }
+    
+    #[test]
+    fn test_parse_ipv6() {
+        assert_eq!(parse_target_addr("fe80::1", 9527).unwrap(), "[fe80::1]:9527");
+        assert_eq!(parse_target_addr("::1", 9527).unwrap(), "[::1]:9527");
+        assert_eq!(parse_target_addr("[fe80::1]", 9527).unwrap(), "[fe80::1]:9527");
+        assert_eq!(parse_target_addr("[fe80::1]:8000", 9527).unwrap(), "[fe80::1]:8000");
+    }
+    
+    #[test]
+    fn test_parse_hostname() {
+        assert_eq!(parse_target_addr("nas.local", 9527).unwrap(), "nas.local:9527");
+        assert_eq!(parse_target_addr("nas.local:8000", 9527).unwrap(), "nas.local:8000");
+    }
+    
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_target_addr("", 9527).is_err());
+        assert!(parse_target_addr("nas.local:port", 9527).is_err());
+        assert!(parse_target_addr("nas.local:99999", 9527).is_err());
+        assert!(parse_target_addr("[fe80::1]:", 9527).is_err());
+        assert!(parse_target_addr("a:b:c", 9527).is_err());
+    }
+}
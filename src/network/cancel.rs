@@ -0,0 +1,127 @@
+//! Токен отмены для отправки и приёма
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Токен отмены передачи.
+///
+/// Клонируется и передаётся в `send_files_to_multiple_with_cancel` или
+/// `run_server_with_cancel`; `cancel()` из любого места останавливает передачу
+/// штатно (с событиями и закрытием соединений), в отличие от `JoinHandle::abort()`.
+/// Внутри - тот же флаг, что принимают варианты `_with_stop`.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Новый, не отменённый токен
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Отменить передачу
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+    
+    /// Была ли отмена
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+    
+    /// Флаг остановки для API `_with_stop`
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        self.flag.clone()
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancelToken {
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self { flag }
+    }
+}
+
+/// Отмена отдельных получателей при рассылке на несколько адресов.
+///
+/// Флаг на каждый `target_id`; передаётся в `send_files_to_multiple_with_targets`.
+/// `cancel_target(id)` останавливает только этого получателя, остальные
+/// продолжают. Общая остановка рассылки выставляет все флаги
+#[derive(Clone, Debug, Default)]
+pub struct TargetCancels {
+    flags: Arc<Mutex<Vec<Arc<AtomicBool>>>>,
+}
+
+impl TargetCancels {
+    /// Ни один получатель не отменён
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Флаг остановки получателя `target_id` для `send_files_to_target_with_stop`
+    pub fn flag(&self, target_id: usize) -> Arc<AtomicBool> {
+        let mut flags = self.flags.lock().unwrap();
+        if flags.len() <= target_id {
+            flags.resize_with(target_id + 1, Default::default);
+        }
+        flags[target_id].clone()
+    }
+    
+    /// Остановить передачу на получателя `target_id`
+    pub fn cancel_target(&self, target_id: usize) {
+        self.flag(target_id).store(true, Ordering::SeqCst);
+    }
+    
+    /// Был ли отменён получатель `target_id`
+    pub fn is_target_cancelled(&self, target_id: usize) -> bool {
+        self.flags.lock().unwrap().get(target_id).is_some_and(|flag| flag.load(Ordering::SeqCst))
+    }
+    
+    /// Остановить всех получателей
+    pub fn cancel_all(&self) {
+        for flag in self.flags.lock().unwrap().iter() {
+            flag.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_cancel_shared_between_clones() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        let flag = token.flag();
+        assert!(!clone.is_cancelled());
+        
+        token.cancel();
+        assert!(clone.is_cancelled());
+        assert!(flag.load(Ordering::SeqCst));
+    }
+    
+    #[test]
+    fn test_from_existing_flag() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let token = CancelToken::from(flag.clone());
+        flag.store(true, Ordering::SeqCst);
+        assert!(token.is_cancelled());
+    }
+    
+    #[test]
+    fn test_target_cancels_independent() {
+        let cancels = TargetCancels::new();
+        let first = cancels.flag(0);
+        let second = cancels.clone().flag(1);
+        
+        cancels.cancel_target(1);
+        assert!(!first.load(Ordering::SeqCst));
+        assert!(second.load(Ordering::SeqCst));
+        assert!(cancels.is_target_cancelled(1));
+        assert!(!cancels.is_target_cancelled(5));
+        
+        cancels.cancel_all();
+        assert!(first.load(Ordering::SeqCst));
+    }
+}
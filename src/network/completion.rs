@@ -0,0 +1,207 @@
+//! Карта завершённых файлов для докачки папки целиком
+//!
+//! Отправитель отмечает полностью переданные файлы в битовой карте, которая
+//! хранится в папке настроек под ключом "получатель + набор файлов". При
+//! повторном запуске отмеченные файлы пропускаются сразу, без хэширования у
+//! получателя; остальные проверяются как обычно (`check_resume`). Если набор
+//! файлов, их размеры или время изменения другие - ключ другой, и карта не
+//! используется. Когда передано всё, карта удаляется
+
+use super::sender::FnvHasher;
+use crate::protocol::FileInfo;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Как часто карта сохраняется на диск во время передачи
+const COMPLETION_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Сохраняемое состояние: число файлов и по биту на файл
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CompletionState {
+    files: u64,
+    done: Vec<u8>,
+}
+
+/// Битовая карта переданных файлов одного пакета
+#[derive(Debug)]
+pub struct CompletionMap {
+    path: PathBuf,
+    state: CompletionState,
+    /// Есть отметки, ещё не сохранённые на диск
+    dirty: bool,
+    last_save: Instant,
+}
+
+impl CompletionMap {
+    /// Загрузить карту для отправки `files` на `addr` из папки `dir`
+    /// (None - папка по умолчанию рядом с настройками). Нет карты - пустая
+    pub fn load(dir: Option<&Path>, addr: &str, files: &[FileInfo]) -> Self {
+        let dir = dir.map(Path::to_path_buf).unwrap_or_else(default_dir);
+        let path = dir.join(format!("{:016x}.bin", completion_key(addr, files)));
+        let state = fs::read(&path)
+            .ok()
+            .and_then(|data| bincode::deserialize::<CompletionState>(&data).ok())
+            .filter(|state| state.files == files.len() as u64 && state.done.len() == files.len().div_ceil(8))
+            .unwrap_or_else(|| CompletionState {
+                files: files.len() as u64,
+                done: vec![0; files.len().div_ceil(8)],
+            });
+        Self { path, state, dirty: false, last_save: Instant::now() }
+    }
+    
+    /// Передан ли файл `idx` в прошлый раз
+    pub fn is_done(&self, idx: usize) -> bool {
+        self.state.done.get(idx / 8).is_some_and(|byte| byte & (1 << (idx % 8)) != 0)
+    }
+    
+    /// Сколько файлов отмечено
+    pub fn done_count(&self) -> usize {
+        self.state.done.iter().map(|byte| byte.count_ones() as usize).sum()
+    }
+    
+    /// Отметить файл переданным. На диск - не чаще раза в секунду
+    pub fn mark(&mut self, idx: usize) {
+        if let Some(byte) = self.state.done.get_mut(idx / 8) {
+            *byte |= 1 << (idx % 8);
+            self.dirty = true;
+        }
+        if self.last_save.elapsed() >= COMPLETION_SAVE_INTERVAL {
+            let _ = self.save();
+        }
+    }
+    
+    /// Завершить пакет: если передано всё, карта больше не нужна
+    pub fn finish(mut self) {
+        if self.done_count() as u64 == self.state.files {
+            self.dirty = false;
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+    
+    /// Сохранить несохранённые отметки
+    pub fn save(&mut self) -> std::io::Result<()> {
+        self.last_save = Instant::now();
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = bincode::serialize(&self.state).map_err(std::io::Error::other)?;
+        // Через временный файл: оборванная запись не испортит карту
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+impl Drop for CompletionMap {
+    /// Передача прервана (ошибка, остановка) - сохраняем то, что успели
+    fn drop(&mut self) {
+        let _ = self.save();
+    }
+}
+
+/// Ключ карты: адрес получателя, пути, размеры и время изменения файлов
+fn completion_key(addr: &str, files: &[FileInfo]) -> u64 {
+    let mut hasher = FnvHasher::new();
+    hasher.update(addr.as_bytes());
+    for file in files {
+        let modified = fs::metadata(&file.path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |time| time.as_nanos() as u64);
+        hasher.update(&[0]);
+        hasher.update(file.relative_path.as_bytes());
+        hasher.update(&file.size.to_le_bytes());
+        hasher.update(&modified.to_le_bytes());
+    }
+    hasher.finish()
+}
+
+/// Папка карт по умолчанию
+fn default_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("toolza_sender")
+        .join("completion")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn files(dir: &Path, names: &[&str]) -> Vec<FileInfo> {
+        names
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                fs::write(&path, name.as_bytes()).unwrap();
+                FileInfo::new(path).unwrap()
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn test_completion_map_persists() {
+        let src = tempfile::tempdir().unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let list = files(src.path(), &["a.txt", "b.txt", "c.txt"]);
+        
+        let mut map = CompletionMap::load(Some(store.path()), "10.0.0.5:9527", &list);
+        assert_eq!(map.done_count(), 0);
+        map.mark(0);
+        map.mark(2);
+        drop(map);
+        
+        let map = CompletionMap::load(Some(store.path()), "10.0.0.5:9527", &list);
+        assert!(map.is_done(0));
+        assert!(!map.is_done(1));
+        assert!(map.is_done(2));
+        
+        // Другой получатель - своя карта
+        let other = CompletionMap::load(Some(store.path()), "10.0.0.6:9527", &list);
+        assert_eq!(other.done_count(), 0);
+    }
+    
+    #[test]
+    fn test_completion_map_invalidated_by_changes() {
+        let src = tempfile::tempdir().unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let list = files(src.path(), &["a.txt", "b.txt"]);
+        
+        let mut map = CompletionMap::load(Some(store.path()), "host", &list);
+        map.mark(0);
+        drop(map);
+        
+        // Изменился размер файла
+        fs::write(&list[1].path, b"longer content").unwrap();
+        let changed = vec![list[0].clone(), FileInfo::new(list[1].path.clone()).unwrap()];
+        assert_eq!(CompletionMap::load(Some(store.path()), "host", &changed).done_count(), 0);
+        
+        // Добавился файл
+        let mut extended = list.clone();
+        extended.extend(files(src.path(), &["c.txt"]));
+        assert_eq!(CompletionMap::load(Some(store.path()), "host", &extended).done_count(), 0);
+    }
+    
+    #[test]
+    fn test_completion_map_removed_when_complete() {
+        let src = tempfile::tempdir().unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let list = files(src.path(), &["a.txt", "b.txt"]);
+        
+        let mut map = CompletionMap::load(Some(store.path()), "host", &list);
+        map.mark(0);
+        map.save().unwrap();
+        assert_eq!(fs::read_dir(store.path()).unwrap().count(), 1);
+        map.mark(1);
+        map.finish();
+        assert_eq!(fs::read_dir(store.path()).unwrap().count(), 0);
+    }
+}
@@ -1,6 +1,7 @@
-//! LZ4 сжатие для быстрой передачи
+//! Сжатие чанков при передаче: LZ4 (по умолчанию) или zstd
 
 use lz4_flex::{compress_prepend_size, decompress_size_prepended};
+use serde::{Deserialize, Serialize};
 
 /// Сжать данные с LZ4
 pub fn compress(data: &[u8]) -> Vec<u8> {
@@ -13,6 +14,146 @@ pub fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
         .map_err(|e| format!("Ошибка распаковки: {}", e))
 }
 
+/// Предел распакованного чанка zstd (защита от zstd-бомбы): не больше самого крупного чанка
+const ZSTD_MAX_CHUNK: usize = crate::stats::MAX_FIXED_CHUNK_SIZE;
+
+/// Чем сжимаются чанки файла. Сжимать ли вообще - `CompressionMode`.
+///
+/// Кодек, отличный от LZ4, объявляется получателю `Message::FileCodec` перед FileStart
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompressionCodec {
+    /// Быстрое сжатие, понятно любому получателю
+    #[default]
+    Lz4,
+    /// Zstandard с уровнем 1-22: медленнее, но заметно сильнее на тексте
+    Zstd { level: i32 },
+}
+
+impl CompressionCodec {
+    /// Сжать чанк
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Lz4 => compress(data),
+            // Ошибка возможна только при неверном уровне - его проверяет SendOptions
+            Self::Zstd { level } => zstd::bulk::compress(data, level).unwrap_or_else(|_| compress(data)),
+        }
+    }
+    
+    /// Распаковать чанк
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Lz4 => decompress(data),
+            Self::Zstd { .. } => zstd::bulk::decompress(data, ZSTD_MAX_CHUNK)
+                .map_err(|e| format!("Ошибка распаковки zstd: {}", e)),
+        }
+    }
+    
+    /// Допустим ли уровень сжатия
+    pub fn validate(self) -> Result<(), String> {
+        let max = *zstd::compression_level_range().end();
+        match self {
+            Self::Zstd { level } if !(1..=max).contains(&level) => {
+                Err(format!("Уровень zstd должен быть от 1 до {}, указан {}", max, level))
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Название для журнала
+    pub fn name(self) -> String {
+        match self {
+            Self::Lz4 => "LZ4".to_string(),
+            Self::Zstd { level } => format!("zstd-{}", level),
+        }
+    }
+}
+
+/// Режим сжатия при отправке
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompressionMode {
+    /// Без сжатия
+    #[default]
+    Off,
+    /// Сжимать все файлы
+    On,
+    /// Решать по каждому файлу: расширение, энтропия и пробное сжатие первых байт
+    Auto,
+}
+
+impl From<bool> for CompressionMode {
+    fn from(enabled: bool) -> Self {
+        if enabled { Self::On } else { Self::Off }
+    }
+}
+
+/// Сколько байт с начала файла смотреть в режиме Auto
+pub const COMPRESSION_SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Энтропия (бит на байт), выше которой данные считаем уже сжатыми
+const MAX_COMPRESSIBLE_ENTROPY: f64 = 7.5;
+
+/// Доля исходного размера после пробного сжатия, выше которой сжимать не стоит
+const MAX_WORTHWHILE_RATIO: f64 = 0.95;
+
+/// Форматы, которые уже сжаты: LZ4 их не уменьшит, только потратит CPU
+const INCOMPRESSIBLE_EXTENSIONS: &[&str] = &[
+    // Архивы
+    "zip", "gz", "tgz", "bz2", "xz", "zst", "lz4", "tlz4", "7z", "rar", "br",
+    // Изображения
+    "jpg", "jpeg", "png", "gif", "webp", "heic", "heif", "avif",
+    // Видео и аудио
+    "mp4", "mkv", "avi", "mov", "webm", "m4v", "mp3", "aac", "m4a", "ogg", "opus", "flac",
+    // Контейнеры на основе zip
+    "docx", "xlsx", "pptx", "odt", "ods", "epub", "jar", "apk",
+];
+
+/// Энтропия Шеннона выборки в битах на байт (0.0 - 8.0)
+pub fn entropy(sample: &[u8]) -> f64 {
+    if sample.is_empty() {
+        return 0.0;
+    }
+    
+    let mut counts = [0usize; 256];
+    for &byte in sample {
+        counts[byte as usize] += 1;
+    }
+    
+    let len = sample.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Коэффициент сжатия выборки LZ4: сжатый размер к исходному (1.0 и выше - не сжимается)
+pub fn compression_ratio(sample: &[u8]) -> f64 {
+    if sample.is_empty() {
+        return 1.0;
+    }
+    compress(sample).len() as f64 / sample.len() as f64
+}
+
+/// Стоит ли сжимать файл: не из списка сжатых форматов, начало файла
+/// не похоже на случайные данные и пробное сжатие выигрывает хотя бы 5%
+pub fn is_worth_compressing(sample: &[u8], filename: &str) -> bool {
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+    if INCOMPRESSIBLE_EXTENSIONS.contains(&extension.as_str()) {
+        return false;
+    }
+    
+    // Энтропия дешевле пробного сжатия и сразу отсекает случайные данные
+    !sample.is_empty()
+        && entropy(sample) < MAX_COMPRESSIBLE_ENTROPY
+        && compression_ratio(sample) <= MAX_WORTHWHILE_RATIO
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -24,5 +165,77 @@ mod tests {
         let decompressed = decompress(&compressed).unwrap();
         assert_eq!(original.as_slice(), decompressed.as_slice());
     }
+    
+    #[test]
+    fn test_codec_round_trip() {
+        let text = b"2024-01-01 INFO request handled in 12ms\n".repeat(1000);
+        let lz4 = CompressionCodec::Lz4.compress(&text);
+        let zstd = CompressionCodec::Zstd { level: 19 }.compress(&text);
+        assert!(zstd.len() < lz4.len());
+        assert_eq!(CompressionCodec::Lz4.decompress(&lz4).unwrap(), text);
+        assert_eq!(CompressionCodec::Zstd { level: 19 }.decompress(&zstd).unwrap(), text);
+        
+        // Кодек получателя должен совпадать с кодеком отправителя
+        assert!(CompressionCodec::Zstd { level: 3 }.decompress(&lz4).is_err());
+        
+        assert!(CompressionCodec::Zstd { level: 19 }.validate().is_ok());
+        assert!(CompressionCodec::Zstd { level: 0 }.validate().is_err());
+        assert!(CompressionCodec::Zstd { level: 23 }.validate().is_err());
+    }
+    
+    #[test]
+    fn test_entropy_bounds() {
+        assert_eq!(entropy(&[]), 0.0);
+        assert_eq!(entropy(&[7u8; 1000]), 0.0);
+        
+        let all_bytes: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+        assert!((entropy(&all_bytes) - 8.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_is_worth_compressing() {
+        let log = b"2024-01-01 INFO request handled in 12ms\n".repeat(100);
+        assert!(is_worth_compressing(&log, "server.log"));
+        
+        // Известный сжатый формат - без анализа содержимого
+        assert!(!is_worth_compressing(&log, "photo.JPG"));
+        
+        // Псевдослучайные данные (как у зашифрованного или сжатого файла)
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let random: Vec<u8> = (0..COMPRESSION_SAMPLE_SIZE)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        assert!(!is_worth_compressing(&random, "data.bin"));
+        
+        assert!(!is_worth_compressing(&[], "empty.txt"));
+    }
+    
+    #[test]
+    fn test_compression_ratio_decides() {
+        let zeros = vec![0u8; COMPRESSION_SAMPLE_SIZE];
+        assert!(compression_ratio(&zeros) < 0.05);
+        assert!(is_worth_compressing(&zeros, "disk.img"));
+        
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        };
+        let random: Vec<u8> = (0..COMPRESSION_SAMPLE_SIZE).map(|_| next()).collect();
+        assert!(compression_ratio(&random) > 0.95);
+        assert!(!is_worth_compressing(&random, "disk.img"));
+        
+        // Случайные символы из 64: энтропия низкая, но LZ4 повторов не найдёт
+        let base64_like: Vec<u8> = (0..COMPRESSION_SAMPLE_SIZE).map(|_| b'0' + next() % 64).collect();
+        assert!(entropy(&base64_like) < MAX_COMPRESSIBLE_ENTROPY);
+        assert!(!is_worth_compressing(&base64_like, "token.txt"));
+    }
 }
 
@@ -1,12 +1,27 @@
 //! События сетевого модуля для GUI
 
-/// События передачи для GUI
+use serde::Serialize;
+use crate::extract::ExtractResult;
+use crate::protocol::ConnectionIntent;
+use crate::network::transport::TransportType;
+
+/// События передачи для GUI. В JSON (`toolza_cli --json`) выводятся через `JsonEvent`
 #[derive(Debug, Clone)]
 pub enum TransferEvent {
     // === События отправки ===
     
+    /// Получатель ждёт свободного слота, см. `SendOptions::max_parallel_targets` (target_id).
+    /// Когда слот освободится, придёт Connecting
+    TargetQueued(usize),
+    /// Начато подключение к получателю (target_id)
+    Connecting(usize),
+    /// Подключиться не удалось, следующая попытка после паузы (target_id, номер повтора).
+    /// Только при `SendOptions::connect_retries`
+    ConnectionRetrying(usize, u32),
     /// Соединение установлено (target_id, адрес)
     Connected(usize, String),
+    /// Согласование первого файла с получателем (target_id)
+    Handshaking(usize),
     /// Начало передачи файла (target_id, file_idx)
     FileStarted(usize, usize),
     /// Прогресс передачи (target_id, file_idx, transferred, original_bytes, compressed_bytes)
@@ -15,34 +30,78 @@ pub enum TransferEvent {
     FileCompleted(usize, usize),
     /// Ошибка файла (target_id, file_idx, error)
     FileError(usize, usize, String),
+    /// SHA-256 у получателя не совпал, копия удалена (target_id, file_idx).
+    /// Только при `SendOptions::verify_integrity`, следом приходит FileError
+    FileCorrupted(usize, usize),
+    /// Контрольная сумма переданного содержимого (target_id, checksum).
+    /// Только при `SendOptions::checksum`, перед TargetCompleted
+    TargetChecksum(usize, u64),
     /// Все файлы переданы на получателя (target_id)
     TargetCompleted(usize),
     /// Все получатели завершены
     AllCompleted,
     /// Ошибка соединения (target_id, error)
     ConnectionError(usize, String),
-    /// Файл пропущен (уже актуален) - для sync режима
-    FileSkipped(usize, usize),
+    /// Файл пропущен (target_id, file_idx, причина)
+    FileSkipped(usize, usize, SkipReason),
     /// Файл возобновлён с позиции (target_id, file_idx, offset)
     FileResumed(usize, usize, u64),
+    /// Файл не передавался - совпадает с уже отправленным (target_id, file_idx, original_idx)
+    FileDeduplicated(usize, usize, usize),
+    /// Файл передаётся, но получатель не поддерживает часть возможностей
+    /// (target_id, file_idx, что именно)
+    FileWarning(usize, usize, FileWarning),
+    /// Транспорт отказал посреди передачи, переподключение через запасной (target_id, новый транспорт, причина)
+    TransportFallback(usize, TransportType, String),
     
     // === События приёма ===
     
-    /// Клиент отключился
-    Disconnected,
+    /// Клиент подключился (адрес, вид: передача или спидтест). Приходит с первым
+    /// сообщением клиента - проверки доступности при сканировании его не вызывают
+    ClientConnected(String, ConnectionIntent),
+    /// Клиент отключился (адрес, вид)
+    ClientDisconnected(String, ConnectionIntent),
+    /// Проверка доступности: клиент подключился только узнать, что получатель
+    /// на месте (адрес). Обычно не показывается
+    ClientProbed(String),
+    /// Начат приём файла (file_idx, имя, размер). Следом идут `Progress(0, file_idx, ..)`;
+    /// номер уникален в пределах процесса, даже при нескольких клиентах
+    FileReceiveStarted(usize, String, u64),
+    /// Приём файла закончен - получен, отклонён или оборван (file_idx). Приходит
+    /// с началом следующего файла того же клиента или с его отключением
+    FileReceiveEnded(usize),
     /// Получен файл (имя, размер)
     FileReceived(String, u64),
+    /// Имя файла недопустимо на получателе и изменено (исходное имя, под которым сохранён)
+    FileRenamed(String, String),
+    /// Файл отклонён правилами получателя, на диск ничего не записано (имя, причина)
+    FileRejected(String, String),
+    /// Файл удалён по запросу синхронизации (`Message::DeleteFiles`): его больше нет
+    /// у отправителя (путь от папки приёма)
+    FileDeleted(String),
+    /// Диск получателя заполнен посреди файла (имя, сколько байт сохранено).
+    /// Частичный файл оставлен: после освобождения места повторная отправка его докачает
+    DiskFull(String, u64),
     /// Начата распаковка архива (имя файла)
     ExtractionStarted(String),
-    /// Распаковка завершена (имя файла, кол-во файлов, общий размер)
-    ExtractionCompleted(String, usize, u64),
+    /// Распаковка завершена (имя файла, результат: файлы, папки, ссылки, размер)
+    ExtractionCompleted(String, ExtractResult),
     /// Ошибка распаковки (имя файла, ошибка)
     ExtractionError(String, String),
+    /// Запись архива пропущена из-за лимитов пути (архив, путь записи, причина)
+    ExtractionEntrySkipped(String, String, String),
+    /// Post-hook выполнен (путь, вывод команды)
+    HookCompleted(String, String),
+    /// Post-hook завершился с ошибкой (путь, ошибка)
+    HookError(String, String),
     
     // === События сканирования ===
     
     /// Найден сервер
     ServerFound(String),
+    /// Имя найденного сервера по обратному DNS (адрес, имя). Приходит после
+    /// ServerFound и до ScanCompleted; нет имени - нет события
+    ServerFoundNamed(String, String),
     /// Прогресс сканирования (текущий IP, процент)
     ScanProgress(String, u8),
     /// Сканирование завершено
@@ -58,5 +117,203 @@ pub enum TransferEvent {
     SpeedTestCompleted(f64, f64, f64),
     /// Ошибка спидтеста
     SpeedTestError(String),
+    /// Спидтест отменён пользователем
+    SpeedTestCancelled,
+}
+
+/// Причина пропуска файла (в JSON - `"up_to_date"` и т.д.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// У получателя уже есть такой же файл (совпали размер и быстрый хэш)
+    UpToDate,
+    /// Файл уже полностью получен при предыдущей попытке
+    AlreadyComplete,
+    /// Исключён фильтром отправителя
+    Excluded,
+    /// Тот же файл уже передан в этой передаче
+    Duplicate,
+}
+
+/// Что получатель не смог сделать с файлом (в JSON - `"lossy_name"` и т.д.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileWarning {
+    /// Имя не в UTF-8, а получатель не знает `Message::RawFileName`:
+    /// файл сохранится под lossy-именем
+    LossyName,
+    /// SHA-256 не сверен (`SendOptions::verify_integrity`): получатель не
+    /// поддерживает проверку, она у него выключена или архив распакован на лету
+    NotVerified,
+}
+
+/// Событие в JSON (`toolza_cli --json`): `{"event": "<имя_варианта>", <поля>}`.
+///
+/// Повторяет `TransferEvent` с именованными полями; имя события в snake_case.
+/// Имена событий и полей - часть интерфейса: новые добавляются, существующие не меняются
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JsonEvent<'a> {
+    TargetQueued { target: usize },
+    Connecting { target: usize },
+    ConnectionRetrying { target: usize, attempt: u32 },
+    Connected { target: usize, address: &'a str },
+    Handshaking { target: usize },
+    FileStarted { target: usize, file: usize },
+    Progress { target: usize, file: usize, transferred: u64, original_bytes: u64, compressed_bytes: u64 },
+    FileCompleted { target: usize, file: usize },
+    FileError { target: usize, file: usize, error: &'a str },
+    FileCorrupted { target: usize, file: usize },
+    TargetChecksum { target: usize, checksum: u64 },
+    TargetCompleted { target: usize },
+    AllCompleted,
+    ConnectionError { target: usize, error: &'a str },
+    FileSkipped { target: usize, file: usize, reason: SkipReason },
+    FileResumed { target: usize, file: usize, offset: u64 },
+    FileDeduplicated { target: usize, file: usize, original: usize },
+    FileWarning { target: usize, file: usize, warning: FileWarning },
+    TransportFallback { target: usize, transport: TransportType, reason: &'a str },
+    ClientConnected { address: &'a str, intent: ConnectionIntent },
+    ClientDisconnected { address: &'a str, intent: ConnectionIntent },
+    ClientProbed { address: &'a str },
+    FileReceiveStarted { file: usize, name: &'a str, size: u64 },
+    FileReceiveEnded { file: usize },
+    FileReceived { name: &'a str, size: u64 },
+    FileRenamed { name: &'a str, saved_as: &'a str },
+    FileRejected { name: &'a str, reason: &'a str },
+    FileDeleted { path: &'a str },
+    DiskFull { name: &'a str, saved: u64 },
+    ExtractionStarted { archive: &'a str },
+    ExtractionCompleted { archive: &'a str, result: &'a ExtractResult },
+    ExtractionError { archive: &'a str, error: &'a str },
+    ExtractionEntrySkipped { archive: &'a str, entry: &'a str, reason: &'a str },
+    HookCompleted { path: &'a str, output: &'a str },
+    HookError { path: &'a str, error: &'a str },
+    ServerFound { address: &'a str },
+    ServerFoundNamed { address: &'a str, name: &'a str },
+    ScanProgress { address: &'a str, percent: u8 },
+    ScanCompleted,
+    SpeedTestStarted { address: &'a str },
+    SpeedTestProgress { direction: &'a str, percent: u8 },
+    SpeedTestCompleted { upload_mbps: f64, download_mbps: f64, latency_ms: f64 },
+    SpeedTestError { error: &'a str },
+    SpeedTestCancelled,
 }
 
+impl<'a> From<&'a TransferEvent> for JsonEvent<'a> {
+    fn from(event: &'a TransferEvent) -> Self {
+        use TransferEvent as E;
+        match event {
+            E::TargetQueued(target) => Self::TargetQueued { target: *target },
+            E::Connecting(target) => Self::Connecting { target: *target },
+            E::ConnectionRetrying(target, attempt) => Self::ConnectionRetrying { target: *target, attempt: *attempt },
+            E::Connected(target, address) => Self::Connected { target: *target, address },
+            E::Handshaking(target) => Self::Handshaking { target: *target },
+            E::FileStarted(target, file) => Self::FileStarted { target: *target, file: *file },
+            E::Progress(target, file, transferred, original_bytes, compressed_bytes) => Self::Progress {
+                target: *target,
+                file: *file,
+                transferred: *transferred,
+                original_bytes: *original_bytes,
+                compressed_bytes: *compressed_bytes,
+            },
+            E::FileCompleted(target, file) => Self::FileCompleted { target: *target, file: *file },
+            E::FileError(target, file, error) => Self::FileError { target: *target, file: *file, error },
+            E::FileCorrupted(target, file) => Self::FileCorrupted { target: *target, file: *file },
+            E::TargetChecksum(target, checksum) => Self::TargetChecksum { target: *target, checksum: *checksum },
+            E::TargetCompleted(target) => Self::TargetCompleted { target: *target },
+            E::AllCompleted => Self::AllCompleted,
+            E::ConnectionError(target, error) => Self::ConnectionError { target: *target, error },
+            E::FileSkipped(target, file, reason) => Self::FileSkipped { target: *target, file: *file, reason: *reason },
+            E::FileResumed(target, file, offset) => Self::FileResumed { target: *target, file: *file, offset: *offset },
+            E::FileDeduplicated(target, file, original) => {
+                Self::FileDeduplicated { target: *target, file: *file, original: *original }
+            }
+            E::FileWarning(target, file, warning) => Self::FileWarning { target: *target, file: *file, warning: *warning },
+            E::TransportFallback(target, transport, reason) => {
+                Self::TransportFallback { target: *target, transport: *transport, reason }
+            }
+            E::ClientConnected(address, intent) => Self::ClientConnected { address, intent: *intent },
+            E::ClientDisconnected(address, intent) => Self::ClientDisconnected { address, intent: *intent },
+            E::ClientProbed(address) => Self::ClientProbed { address },
+            E::FileReceiveStarted(file, name, size) => Self::FileReceiveStarted { file: *file, name, size: *size },
+            E::FileReceiveEnded(file) => Self::FileReceiveEnded { file: *file },
+            E::FileReceived(name, size) => Self::FileReceived { name, size: *size },
+            E::FileRenamed(name, saved_as) => Self::FileRenamed { name, saved_as },
+            E::FileRejected(name, reason) => Self::FileRejected { name, reason },
+            E::FileDeleted(path) => Self::FileDeleted { path },
+            E::DiskFull(name, saved) => Self::DiskFull { name, saved: *saved },
+            E::ExtractionStarted(archive) => Self::ExtractionStarted { archive },
+            E::ExtractionCompleted(archive, result) => Self::ExtractionCompleted { archive, result },
+            E::ExtractionError(archive, error) => Self::ExtractionError { archive, error },
+            E::ExtractionEntrySkipped(archive, entry, reason) => Self::ExtractionEntrySkipped { archive, entry, reason },
+            E::HookCompleted(path, output) => Self::HookCompleted { path, output },
+            E::HookError(path, error) => Self::HookError { path, error },
+            E::ServerFound(address) => Self::ServerFound { address },
+            E::ServerFoundNamed(address, name) => Self::ServerFoundNamed { address, name },
+            E::ScanProgress(address, percent) => Self::ScanProgress { address, percent: *percent },
+            E::ScanCompleted => Self::ScanCompleted,
+            E::SpeedTestStarted(address) => Self::SpeedTestStarted { address },
+            E::SpeedTestProgress(direction, percent) => Self::SpeedTestProgress { direction, percent: *percent },
+            E::SpeedTestCompleted(upload_mbps, download_mbps, latency_ms) => Self::SpeedTestCompleted {
+                upload_mbps: *upload_mbps,
+                download_mbps: *download_mbps,
+                latency_ms: *latency_ms,
+            },
+            E::SpeedTestError(error) => Self::SpeedTestError { error },
+            E::SpeedTestCancelled => Self::SpeedTestCancelled,
+        }
+    }
+}
+
+impl SkipReason {
+    /// Короткое описание для лога
+    pub fn description(&self) -> &'static str {
+        match self {
+            SkipReason::UpToDate => "актуален",
+            SkipReason::AlreadyComplete => "уже получен",
+            SkipReason::Excluded => "исключён",
+            SkipReason::Duplicate => "дубликат",
+        }
+    }
+}
+
+impl FileWarning {
+    /// Короткое описание для лога
+    pub fn description(&self) -> &'static str {
+        match self {
+            FileWarning::LossyName => "получатель сохранит файл под изменённым именем",
+            FileWarning::NotVerified => "SHA-256 у получателя не проверен",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_event_json_shape() {
+        let json = |event: TransferEvent| serde_json::to_string(&JsonEvent::from(&event)).unwrap();
+        assert_eq!(
+            json(TransferEvent::Progress(0, 2, 512, 1024, 300)),
+            r#"{"event":"progress","target":0,"file":2,"transferred":512,"original_bytes":1024,"compressed_bytes":300}"#
+        );
+        assert_eq!(json(TransferEvent::TargetCompleted(1)), r#"{"event":"target_completed","target":1}"#);
+        assert_eq!(json(TransferEvent::AllCompleted), r#"{"event":"all_completed"}"#);
+        assert_eq!(
+            json(TransferEvent::FileSkipped(0, 3, SkipReason::UpToDate)),
+            r#"{"event":"file_skipped","target":0,"file":3,"reason":"up_to_date"}"#
+        );
+        assert_eq!(
+            json(TransferEvent::FileWarning(1, 4, FileWarning::LossyName)),
+            r#"{"event":"file_warning","target":1,"file":4,"warning":"lossy_name"}"#
+        );
+        assert_eq!(
+            json(TransferEvent::Connected(0, "10.0.0.2:9527".to_string())),
+            r#"{"event":"connected","target":0,"address":"10.0.0.2:9527"}"#
+        );
+        assert!(json(TransferEvent::ExtractionCompleted("a.tar.zst".to_string(), ExtractResult::default()))
+            .starts_with(r#"{"event":"extraction_completed","archive":"a.tar.zst","result":{"files_count":0,"#));
+    }
+}
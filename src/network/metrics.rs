@@ -0,0 +1,206 @@
+//! Метрики приёмника в формате Prometheus
+//!
+//! Счётчики глобальные (`METRICS`) и обновляются обработчиками подключений
+//! и распаковщиками. HTTP-экспорт (`GET /metrics`) - только с фичей `metrics`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Счётчики приёмника
+#[derive(Debug, Default)]
+pub struct ReceiverMetrics {
+    bytes_received: AtomicU64,
+    active_connections: AtomicU64,
+    transfers_completed: AtomicU64,
+    transfers_failed: AtomicU64,
+    extractions: AtomicU64,
+    extractions_failed: AtomicU64,
+    /// Суммарное время распаковки в микросекундах
+    extraction_micros: AtomicU64,
+}
+
+/// Глобальные метрики приёмника
+pub static METRICS: ReceiverMetrics = ReceiverMetrics::new();
+
+impl ReceiverMetrics {
+    /// Нулевые счётчики
+    pub const fn new() -> Self {
+        Self {
+            bytes_received: AtomicU64::new(0),
+            active_connections: AtomicU64::new(0),
+            transfers_completed: AtomicU64::new(0),
+            transfers_failed: AtomicU64::new(0),
+            extractions: AtomicU64::new(0),
+            extractions_failed: AtomicU64::new(0),
+            extraction_micros: AtomicU64::new(0),
+        }
+    }
+    
+    /// Учесть принятые байты
+    pub fn add_bytes_received(&self, bytes: u64) {
+        self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+    }
+    
+    /// Новое подключение: счётчик активных уменьшится при drop гарда
+    pub fn connection_opened(&self) -> ConnectionGuard<'_> {
+        self.active_connections.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard { metrics: self }
+    }
+    
+    /// Учесть итог приёма файла
+    pub fn record_transfer(&self, ok: bool) {
+        let counter = if ok { &self.transfers_completed } else { &self.transfers_failed };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Учесть распаковку архива
+    pub fn record_extraction(&self, duration: Duration, ok: bool) {
+        self.extractions.fetch_add(1, Ordering::Relaxed);
+        self.extraction_micros.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        if !ok {
+            self.extractions_failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    /// Текст в формате Prometheus (text exposition 0.0.4)
+    pub fn render(&self) -> String {
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let seconds = get(&self.extraction_micros) as f64 / 1_000_000.0;
+        
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+            out.push_str(&format!("{} {}\n", name, value));
+        };
+        metric("toolza_received_bytes_total", "counter", "Bytes received", get(&self.bytes_received).to_string());
+        metric("toolza_active_connections", "gauge", "Open sender connections", get(&self.active_connections).to_string());
+        metric("toolza_transfers_completed_total", "counter", "Files received successfully", get(&self.transfers_completed).to_string());
+        metric("toolza_transfers_failed_total", "counter", "Files failed to receive", get(&self.transfers_failed).to_string());
+        metric("toolza_extractions_failed_total", "counter", "Archive extractions that failed", get(&self.extractions_failed).to_string());
+        
+        out.push_str("# HELP toolza_extraction_duration_seconds Archive extraction time\n");
+        out.push_str("# TYPE toolza_extraction_duration_seconds summary\n");
+        out.push_str(&format!("toolza_extraction_duration_seconds_sum {}\n", seconds));
+        out.push_str(&format!("toolza_extraction_duration_seconds_count {}\n", get(&self.extractions)));
+        out
+    }
+}
+
+/// Гард активного подключения
+pub struct ConnectionGuard<'a> {
+    metrics: &'a ReceiverMetrics,
+}
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        self.metrics.active_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Выполнить распаковку, записав её длительность и итог в `METRICS`
+pub fn time_extraction<T, E>(extract: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    let started = Instant::now();
+    let result = extract();
+    METRICS.record_extraction(started.elapsed(), result.is_ok());
+    result
+}
+
+/// Запустить HTTP-экспорт метрик на `addr` (`GET /metrics`).
+///
+/// Адрес занимается сразу (ошибка - если порт занят), запросы обслуживаются
+/// в фоне до установки `stop_flag`. Возвращает фактический адрес.
+#[cfg(feature = "metrics")]
+pub async fn spawn_exporter(
+    addr: std::net::SocketAddr,
+    stop_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
+) -> Result<std::net::SocketAddr, String> {
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper_util::rt::TokioIo;
+    
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Не удалось запустить экспорт метрик на {}: {}", addr, e))?;
+    let local_addr = listener.local_addr().map_err(|e| e.to_string())?;
+    
+    tokio::spawn(async move {
+        while !stop_flag.load(Ordering::SeqCst) {
+            // Таймаут - чтобы вовремя заметить остановку
+            let Ok(Ok((stream, _))) = tokio::time::timeout(Duration::from_millis(200), listener.accept()).await else {
+                continue;
+            };
+            tokio::spawn(async move {
+                let service = service_fn(|request| async move {
+                    Ok::<_, std::convert::Infallible>(respond(&request))
+                });
+                let _ = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await;
+            });
+        }
+    });
+    
+    Ok(local_addr)
+}
+
+#[cfg(feature = "metrics")]
+fn respond<B>(request: &hyper::Request<B>) -> hyper::Response<http_body_util::Full<hyper::body::Bytes>> {
+    use hyper::{Method, Response, StatusCode};
+    
+    let (status, body) = if request.method() == Method::GET && request.uri().path() == "/metrics" {
+        (StatusCode::OK, METRICS.render())
+    } else {
+        (StatusCode::NOT_FOUND, "not found\n".to_string())
+    };
+    
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(body.into())
+        .expect("корректный ответ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_render_counters() {
+        let metrics = ReceiverMetrics::new();
+        metrics.add_bytes_received(1024);
+        metrics.record_transfer(true);
+        metrics.record_transfer(false);
+        metrics.record_extraction(Duration::from_millis(1500), true);
+        
+        let guard = metrics.connection_opened();
+        let text = metrics.render();
+        assert!(text.contains("toolza_received_bytes_total 1024\n"));
+        assert!(text.contains("toolza_active_connections 1\n"));
+        assert!(text.contains("toolza_transfers_completed_total 1\n"));
+        assert!(text.contains("toolza_transfers_failed_total 1\n"));
+        assert!(text.contains("toolza_extraction_duration_seconds_sum 1.5\n"));
+        assert!(text.contains("toolza_extraction_duration_seconds_count 1\n"));
+        assert!(text.contains("# TYPE toolza_received_bytes_total counter\n"));
+        
+        drop(guard);
+        assert!(metrics.render().contains("toolza_active_connections 0\n"));
+    }
+    
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn test_exporter_serves_metrics() {
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicBool;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let addr = spawn_exporter("127.0.0.1:0".parse().unwrap(), stop_flag.clone()).await.unwrap();
+        
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("toolza_received_bytes_total"));
+        stop_flag.store(true, Ordering::SeqCst);
+    }
+}
@@ -1,17 +1,29 @@
 //! Сетевой модуль - отправка, приём и сканирование
 
+pub mod addr;
+pub mod cancel;
+pub mod completion;
 pub mod compression;
+pub mod metrics;
+pub mod quota;
 pub mod transport;
 pub mod sender;
 pub mod receiver;
 mod events;
 mod scanner;
 pub mod speedtest;
+pub mod throttle;
 
-pub use events::TransferEvent;
-pub use sender::{send_files_to_multiple, send_files_to_multiple_with_stop, SendOptions};
-pub use receiver::{run_server, run_server_with_stop, run_server_with_options_and_stop, ServerOptions, ExtractOptions};
-pub use scanner::{scan_network, scan_subnets, parse_subnets, Subnet};
-pub use speedtest::{run_speedtest, SpeedTestResult, DEFAULT_SPEEDTEST_SIZE};
-pub use transport::TransportType;
+pub use addr::parse_target_addr;
+pub use cancel::{CancelToken, TargetCancels};
+pub use compression::{CompressionCodec, CompressionMode};
+pub use quota::QuotaTracker;
+pub use events::{FileWarning, JsonEvent, SkipReason, TransferEvent};
+pub use sender::{delete_remote_files, fetch_sync_diff, probe_resume, Connection, send_files_to_multiple, send_files_to_multiple_with_cancel, send_files_to_multiple_with_stop, send_files_to_multiple_with_targets, transfer_checksum, SendOptions, SendOptionsBuilder, DEFAULT_MAX_PARALLEL_TARGETS, DEFAULT_RETRY_DELAY};
+pub use receiver::{run_server, run_server_with_cancel, run_server_with_stop, run_server_with_options_and_stop, bind_server, serve, ServerOptions, ServerOptionsBuilder, ExtractOptions, ExtractConfigError, DEFAULT_BIND_ADDR, DEFAULT_HOOK_TIMEOUT, DEFAULT_MAX_DELETE_FILES, DEFAULT_MAX_HOOKS};
+pub use scanner::{scan_network, scan_subnets, scan_hosts, discover_mdns, parse_subnets, lookup_hostname, server_label, Subnet, MDNS_BROWSE_TIMEOUT, MDNS_SERVICE_TYPE};
+#[cfg(feature = "mdns")]
+pub use scanner::{advertise_mdns, MdnsAdvertisement};
+pub use speedtest::{run_speedtest, run_speedtest_tuned, run_speedtest_with_stop, SpeedTestResult, DEFAULT_SPEEDTEST_SIZE};
+pub use transport::{SocketTuning, TransportType};
 
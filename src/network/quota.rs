@@ -0,0 +1,147 @@
+//! Дневной лимит трафика отправки
+//!
+//! Счётчик отправленных за день байт хранится в `quota.json` рядом с настройками
+//! и сбрасывается при смене даты (UTC). Все отправки процесса делят один счётчик.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Учёт отправленных байт за день с лимитом
+#[derive(Clone, Debug)]
+pub struct QuotaTracker {
+    limit: u64,
+    path: PathBuf,
+    state: Arc<Mutex<QuotaState>>,
+}
+
+/// Сохраняемое состояние: дата (YYYY-MM-DD) и байты за неё
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct QuotaState {
+    date: String,
+    bytes: u64,
+}
+
+/// Общий счётчик процесса (файл в папке настроек)
+static SHARED_STATE: OnceLock<Arc<Mutex<QuotaState>>> = OnceLock::new();
+
+impl QuotaTracker {
+    /// Общий счётчик процесса с лимитом `limit` байт в день
+    pub fn shared(limit: u64) -> Self {
+        let path = quota_file_path();
+        let state = SHARED_STATE
+            .get_or_init(|| Arc::new(Mutex::new(read_state(&path))))
+            .clone();
+        Self { limit, path, state }
+    }
+    
+    /// Отдельный счётчик в указанном файле
+    pub fn with_file(path: PathBuf, limit: u64) -> Self {
+        let state = Arc::new(Mutex::new(read_state(&path)));
+        Self { limit, path, state }
+    }
+    
+    /// Лимит в байтах
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+    
+    /// Отправлено сегодня
+    pub fn used(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        rollover(&mut state);
+        state.bytes
+    }
+    
+    /// Осталось до лимита сегодня
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used())
+    }
+    
+    /// Проверить, что лимит ещё не исчерпан
+    pub fn check(&self) -> Result<(), String> {
+        if self.remaining() == 0 {
+            return Err(format!(
+                "Дневной лимит трафика исчерпан ({}), отправка возобновится завтра",
+                crate::utils::format_size(self.limit)
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Учесть отправленные байты
+    pub fn add(&self, bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        rollover(&mut state);
+        state.bytes = state.bytes.saturating_add(bytes);
+    }
+    
+    /// Сохранить счётчик в файл
+    pub fn save(&self) -> std::io::Result<()> {
+        let json = serde_json::to_string(&*self.state.lock().unwrap())?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, json)
+    }
+}
+
+/// Новый день - счётчик с нуля
+fn rollover(state: &mut QuotaState) {
+    let today = crate::history::current_date();
+    if state.date != today {
+        *state = QuotaState { date: today, bytes: 0 };
+    }
+}
+
+fn read_state(path: &Path) -> QuotaState {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Путь к файлу счётчика
+fn quota_file_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("toolza_sender")
+        .join("quota.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_quota_persisted_and_exhausted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("quota.json");
+        
+        let quota = QuotaTracker::with_file(path.clone(), 1000);
+        quota.add(600);
+        assert_eq!(quota.remaining(), 400);
+        assert!(quota.check().is_ok());
+        quota.save().unwrap();
+        
+        // Новый процесс видит уже отправленное сегодня
+        let restored = QuotaTracker::with_file(path, 1000);
+        assert_eq!(restored.used(), 600);
+        restored.add(500);
+        assert_eq!(restored.remaining(), 0);
+        assert!(restored.check().is_err());
+    }
+    
+    #[test]
+    fn test_quota_daily_rollover() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("quota.json");
+        let stale = QuotaState { date: "2000-01-01".to_string(), bytes: 5000 };
+        fs::write(&path, serde_json::to_string(&stale).unwrap()).unwrap();
+        
+        let quota = QuotaTracker::with_file(path, 1000);
+        assert_eq!(quota.used(), 0);
+        assert!(quota.check().is_ok());
+    }
+}
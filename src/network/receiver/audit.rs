@@ -0,0 +1,264 @@
+//! Журнал аудита получателя: кто подключался, что прислал и что было отклонено
+//!
+//! Записи - строки JSON в файле `ServerOptions::audit_log`. Запись идёт в отдельном
+//! потоке, поэтому приём файлов не ждёт диска; при превышении размера файл
+//! переименовывается в `<имя>.1`, и журнал начинается заново
+
+use crate::network::events::TransferEvent;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+/// Размер журнала, после которого он ротируется (10 MB)
+pub(crate) const AUDIT_LOG_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Одна запись журнала
+#[derive(Debug, Serialize)]
+struct AuditRecord {
+    /// Время, секунды Unix
+    ts: u64,
+    event: &'static str,
+    peer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+/// Дескриптор журнала аудита. Клонируется в каждое соединение;
+/// без `audit_log` все методы ничего не делают
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AuditLog {
+    tx: Option<std_mpsc::Sender<AuditRecord>>,
+}
+
+impl AuditLog {
+    /// Запустить поток записи в `path` (None - журнал выключен).
+    ///
+    /// Если файл недоступен для записи, приходит одно предупреждение событием,
+    /// записи отбрасываются, а открыть файл пробуем снова при следующей
+    pub(crate) fn start(
+        path: Option<&Path>,
+        max_size: u64,
+        event_tx: &mpsc::UnboundedSender<TransferEvent>,
+    ) -> Self {
+        let Some(path) = path else {
+            return Self::default();
+        };
+        let (tx, rx) = std_mpsc::channel();
+        let writer = AuditWriter {
+            path: path.to_path_buf(),
+            max_size,
+            file: None,
+            warned: false,
+            event_tx: event_tx.clone(),
+        };
+        std::thread::spawn(move || writer.run(rx));
+        Self { tx: Some(tx) }
+    }
+    
+    /// Новое подключение
+    pub(crate) fn connected(&self, peer: &str) {
+        self.record("connected", peer, None, None, None);
+    }
+    
+    /// Файл принят и сохранён (или распакован)
+    pub(crate) fn file_received(&self, peer: &str, file: &str, size: u64) {
+        self.record("file_received", peer, Some(file), Some(size), None);
+    }
+    
+    /// Файл удалён по запросу синхронизации
+    pub(crate) fn file_deleted(&self, peer: &str, file: &str) {
+        self.record("file_deleted", peer, Some(file), None, None);
+    }
+    
+    /// Запрос отклонён получателем, соединение продолжается
+    pub(crate) fn rejected(&self, peer: &str, file: Option<&str>, reason: &str) {
+        self.record("rejected", peer, file, None, Some(reason));
+    }
+    
+    /// Соединение завершилось ошибкой
+    pub(crate) fn error(&self, peer: &str, reason: &str) {
+        self.record("error", peer, None, None, Some(reason));
+    }
+    
+    /// Отключение
+    pub(crate) fn disconnected(&self, peer: &str) {
+        self.record("disconnected", peer, None, None, None);
+    }
+    
+    fn record(&self, event: &'static str, peer: &str, file: Option<&str>, size: Option<u64>, reason: Option<&str>) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let _ = tx.send(AuditRecord {
+            ts,
+            event,
+            peer: peer.to_string(),
+            file: file.map(String::from),
+            size,
+            reason: reason.map(String::from),
+        });
+    }
+}
+
+/// Поток записи журнала
+struct AuditWriter {
+    path: PathBuf,
+    max_size: u64,
+    file: Option<File>,
+    /// Предупреждение о недоступном файле уже отправлено
+    warned: bool,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+}
+
+impl AuditWriter {
+    /// Писать записи, пока живы дескрипторы `AuditLog`
+    fn run(mut self, rx: std_mpsc::Receiver<AuditRecord>) {
+        for record in rx {
+            let Ok(mut line) = serde_json::to_string(&record) else {
+                continue;
+            };
+            line.push('\n');
+            if let Err(e) = self.write(line.as_bytes()) {
+                self.file = None;
+                if !self.warned {
+                    self.warned = true;
+                    let _ = self.event_tx.send(TransferEvent::FileReceived(
+                        format!("⚠️ Журнал аудита {} недоступен: {}", self.path.display(), e), 0
+                    ));
+                }
+            }
+        }
+    }
+    
+    fn write(&mut self, line: &[u8]) -> std::io::Result<()> {
+        self.rotate_if_full()?;
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(OpenOptions::new().create(true).append(true).open(&self.path)?),
+        };
+        file.write_all(line)?;
+        file.flush()
+    }
+    
+    /// Переименовать журнал в `<имя>.1`, если он вырос до `max_size`
+    fn rotate_if_full(&mut self) -> std::io::Result<()> {
+        let size = match std::fs::metadata(&self.path) {
+            Ok(meta) => meta.len(),
+            Err(_) => return Ok(()), // Файла ещё нет
+        };
+        if size < self.max_size {
+            return Ok(());
+        }
+        self.file = None;
+        let mut rotated = self.path.as_os_str().to_os_string();
+        rotated.push(".1");
+        std::fs::rename(&self.path, PathBuf::from(rotated))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    /// Дождаться, пока поток записи допишет `lines` строк
+    fn wait_lines(path: &Path, lines: usize) -> Vec<serde_json::Value> {
+        for _ in 0..100 {
+            if let Ok(text) = std::fs::read_to_string(path) {
+                if text.lines().count() >= lines {
+                    return text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+                }
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("журнал не дописан");
+    }
+    
+    #[test]
+    fn test_audit_log_writes_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let audit = AuditLog::start(Some(&path), AUDIT_LOG_MAX_SIZE, &tx);
+        
+        audit.connected("10.0.0.5:5000");
+        audit.file_received("10.0.0.5:5000", "docs/a.txt", 42);
+        audit.rejected("10.0.0.5:5000", Some("bad\u{1}name"), "Имя файла не представимо");
+        audit.disconnected("10.0.0.5:5000");
+        
+        let records = wait_lines(&path, 4);
+        assert_eq!(records[0]["event"], "connected");
+        assert_eq!(records[0]["peer"], "10.0.0.5:5000");
+        assert!(records[0].get("file").is_none());
+        assert_eq!(records[1]["file"], "docs/a.txt");
+        assert_eq!(records[1]["size"], 42);
+        assert_eq!(records[2]["event"], "rejected");
+        assert_eq!(records[2]["reason"], "Имя файла не представимо");
+        assert!(records[3]["ts"].as_u64().unwrap() > 0);
+    }
+    
+    #[test]
+    fn test_audit_log_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let audit = AuditLog::start(Some(&path), 200, &tx);
+        
+        for _ in 0..10 {
+            audit.connected("10.0.0.5:5000");
+        }
+        audit.disconnected("10.0.0.5:5000");
+        drop(audit);
+        
+        let mut rotated = path.as_os_str().to_os_string();
+        rotated.push(".1");
+        for _ in 0..100 {
+            let text = std::fs::read_to_string(&path).unwrap_or_default();
+            if text.contains("disconnected") {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(PathBuf::from(rotated).exists());
+        // После ротации файл снова маленький: не больше лимита и одной записи
+        assert!(std::fs::metadata(&path).unwrap().len() < 300);
+    }
+    
+    #[test]
+    fn test_audit_log_unwritable_warns_once() {
+        let dir = tempfile::tempdir().unwrap();
+        // Путь внутри файла - открыть нельзя
+        let blocker = dir.path().join("file");
+        std::fs::write(&blocker, b"").unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let audit = AuditLog::start(Some(&blocker.join("audit.jsonl")), AUDIT_LOG_MAX_SIZE, &tx);
+        
+        audit.connected("10.0.0.5:5000");
+        audit.disconnected("10.0.0.5:5000");
+        drop(audit);
+        std::thread::sleep(Duration::from_millis(100));
+        
+        let mut warnings = 0;
+        while let Ok(event) = rx.try_recv() {
+            warnings += matches!(event, TransferEvent::FileReceived(ref text, 0) if text.contains("аудита")) as usize;
+        }
+        assert_eq!(warnings, 1);
+    }
+    
+    #[test]
+    fn test_audit_log_disabled() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let audit = AuditLog::start(None, AUDIT_LOG_MAX_SIZE, &tx);
+        audit.connected("10.0.0.5:5000");
+        assert!(audit.tx.is_none());
+    }
+}
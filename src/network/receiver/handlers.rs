@@ -1,19 +1,46 @@
 //! Обработчики клиентских подключений
 
 use crate::extract;
-use crate::network::compression;
+use crate::network::compression::{self, CompressionCodec};
+use crate::network::metrics::{self, METRICS};
 use crate::network::events::TransferEvent;
-use crate::network::transport::TransportStream;
-use crate::protocol::Message;
-use std::path::PathBuf;
+use crate::network::transport::{CompactStream, TcpStreamWrapper, TimeoutStream, TransportStream};
+use crate::protocol::{ConnectionIntent, Message, SyncFileEntry, FEATURE_CHUNK_PROBE, FEATURE_COMPACT_FRAMING, FEATURE_EXTRACT_MANIFEST, FEATURE_FILE_META, FEATURE_NO_EXTRACT, FEATURE_PACKED_LISTS, FEATURE_VERIFY_INTEGRITY, LIST_PACK_THRESHOLD};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt, AsyncSeekExt};
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
 
 use super::options::ServerOptions;
-use super::streaming::{FnvHasher, receive_and_extract_streaming_transport, receive_and_extract_streaming_tcp};
+use super::relay::Relay;
+use super::session::ClientSession;
+use super::hook::PostHook;
+use super::streaming::{FnvHasher, IncomingArchive, receive_and_extract_streaming_transport, report_skipped_entries};
+
+/// Относительный путь от отправителя (`/` - разделитель) для записи под папкой приёма.
+/// Абсолютный путь, `..` или префикс диска - ошибка, которая обрывает передачу
+pub(crate) fn safe_relative_path(name: &str) -> Result<PathBuf, String> {
+    extract::sanitize_relative_path(Path::new(name))
+        .map_err(|e| format!("Отклонено имя файла от отправителя: {}", e))
+}
+
+/// Расширение недокачанного файла
+pub(crate) const PART_EXTENSION: &str = "toolza-part";
+
+/// Путь к недокачанному файлу: `file.bin` -> `file.bin.toolza-part`
+///
+/// Данные пишутся сюда и переименовываются в итоговое имя только на `FileEnd`,
+/// поэтому файл с настоящим именем всегда полный.
+pub(crate) fn part_path(file_path: &Path) -> PathBuf {
+    let mut name = file_path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(PART_EXTENSION);
+    PathBuf::from(name)
+}
 
 /// Отправить Ack через транспорт
 pub(crate) async fn send_ack_transport(stream: &mut dyn TransportStream) -> Result<(), String> {
@@ -30,14 +57,60 @@ pub(crate) async fn send_cancel_transport(stream: &mut dyn TransportStream) -> R
     Ok(())
 }
 
+/// Имя сохраняемого файла: по шаблону получателя и допустимое на этой системе.
+/// Если имя пришлось исправить, приходит `FileRenamed`
+pub(crate) fn save_name(
+    options: &ServerOptions,
+    filename: &str,
+    peer: &str,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) -> String {
+    let name = options.templated_name(filename, peer);
+    let safe = options.safe_name(&name);
+    if safe != name {
+        let _ = event_tx.send(TransferEvent::FileRenamed(name, safe.clone()));
+    }
+    safe
+}
+
+/// Итоговое имя файла (`save_name`) и точный путь из RawFileName по тому же шаблону.
+/// Правила получателя проверяются по тому, что окажется на диске: шаблон и
+/// исправление имени могут дать расширение, которого не было у отправителя.
+/// При `sanitize_names` точный путь не нужен - сохраняется исправленное имя.
+/// Err(причина) - файл не принимаем
+pub(crate) fn checked_save_name(
+    options: &ServerOptions,
+    filename: &str,
+    raw_name: Option<PathBuf>,
+    size: u64,
+    peer: &str,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) -> Result<(String, Option<PathBuf>), String> {
+    let name = save_name(options, filename, peer, event_tx);
+    let raw_name = raw_name
+        .filter(|_| !options.sanitize_names)
+        .map(|path| options.templated_path(&path, peer));
+    let final_name = raw_name.as_ref().map_or_else(|| name.clone(), |path| path.to_string_lossy().into_owned());
+    match options.reject_reason(&final_name, size) {
+        Some(reason) => Err(reason),
+        None => Ok((name, raw_name)),
+    }
+}
+
 /// Обработчик клиента через абстрактный транспорт
 pub(crate) async fn handle_client_transport(
-    mut stream: Box<dyn TransportStream>,
+    stream: Box<dyn TransportStream>,
     save_dir: PathBuf,
     options: ServerOptions,
     event_tx: mpsc::UnboundedSender<TransferEvent>,
     stop_flag: Arc<AtomicBool>,
+    session: &ClientSession,
 ) -> Result<(), String> {
+    let _connection = METRICS.connection_opened();
+    let (peer, audit) = (session.peer.clone(), &session.audit);
+    // Замолчавший отправитель не держит соединение (и потоковую распаковку) вечно
+    let mut stream = crate::network::transport::with_io_timeout(stream, options.timeout);
+    
     // Логируем опции для диагностики
     let _ = event_tx.send(TransferEvent::FileReceived(
         format!("[DEBUG] handle_client_transport: extract_options={:?} transport={}", 
@@ -45,6 +118,21 @@ pub(crate) async fn handle_client_transport(
         0
     ));
     
+    // Путь из RawFileName для следующего FileStart (имя не в UTF-8)
+    let mut raw_name: Option<PathBuf> = None;
+    // Расширенные атрибуты из FileMeta для следующего FileStart
+    let mut file_meta: Option<Vec<(String, Vec<u8>)>> = None;
+    // Кодек из FileCodec для следующего FileStart (без него - LZ4)
+    let mut next_codec: Option<CompressionCodec> = None;
+    // Просьба NoExtract для следующего FileStart
+    let mut next_no_extract = false;
+    // Возможности, согласованные в Hello (`FEATURE_*`); без Hello - никаких
+    let mut session_features = 0;
+    // Пересылка на relay_to - подключаемся при первом файле
+    let mut relay: Option<Relay> = None;
+    // Сколько узлов файлы этой сессии уже прошли (RelayHops от ретранслятора)
+    let mut relay_hops = 0;
+    
     loop {
         // Проверяем флаг остановки
         if stop_flag.load(Ordering::SeqCst) {
@@ -60,6 +148,9 @@ pub(crate) async fn handle_client_transport(
         match stream.read_exact(&mut len_buf).await {
             Ok(_) => {}
             Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                if let Some(relay) = relay.take() {
+                    relay.finish().await;
+                }
                 return Ok(()); // Клиент отключился
             }
             Err(e) => return Err(e.to_string()),
@@ -69,16 +160,48 @@ pub(crate) async fn handle_client_transport(
         let mut data = vec![0u8; len];
         stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
         
+        // Сжатый запрос или согласованное сжатие - отправитель поймёт и сжатый ответ
+        let packed = Message::is_packed(&data) || session_features & FEATURE_PACKED_LISTS != 0;
         let msg = Message::from_bytes(&data).map_err(|e| e.to_string())?;
+        session.observe(&msg);
         
         match msg {
             Message::FileStart { filename, size, compressed, offset: _, quick_hash } => {
+                // Правила получателя: отказ до любых действий с диском (имя - по шаблону получателя)
+                // Нижестоящим уходит исходный путь: шаблон у каждого свой
+                let relay_raw_name = raw_name.clone();
+                let (save_name, raw_name) = match checked_save_name(&options, &filename, raw_name.take(), size, &peer, &event_tx) {
+                    Ok(resolved) => resolved,
+                    Err(reason) => {
+                        file_meta = None;
+                        next_codec = None;
+                        next_no_extract = false;
+                        audit.rejected(&peer, Some(&filename), &reason);
+                        let _ = event_tx.send(TransferEvent::FileRejected(filename, reason.clone()));
+                        let data = Message::Rejected { reason }.to_bytes().map_err(|e| e.to_string())?;
+                        stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                        continue;
+                    }
+                };
+                let file_idx = session.start_file(&filename, size);
+                if relay.is_none() && !options.relay_to.is_empty() {
+                    relay = Some(Relay::connect(&options.relay_to, options.transport_type, session_features, relay_hops, &event_tx).await);
+                }
+                let file_meta = file_meta.take();
+                let no_extract = std::mem::take(&mut next_no_extract);
+                let codec = next_codec.take().unwrap_or_default();
+                let codec = compressed.then_some(codec);
+                // Отправитель с FileMeta просит восстановить и xattr из PAX-заголовков архива
+                let mut path_limits = options.extract_limits();
+                path_limits.xattrs |= file_meta.is_some();
+                
                 // Определяем тип архива и нужна ли распаковка
                 let archive_type = extract::ArchiveType::from_filename(&filename);
-                let should_extract = options.should_extract(&filename);
-                let is_tar_lz4 = archive_type == extract::ArchiveType::TarLz4;
-                let is_tar_zst = archive_type == extract::ArchiveType::TarZst;
-                let stream_extract = should_extract && (is_tar_lz4 || is_tar_zst);
+                // Отправитель может попросить сохранить этот архив как есть
+                let should_extract = !no_extract && options.should_extract(&filename);
+                // tar.lz4/tar.zst и одиночные .lz4/.zst распаковываются на лету
+                // (кроме ретрансляции: куски архива уходят дальше как обычный файл)
+                let stream_extract = should_extract && archive_type.is_streamable() && relay.is_none();
                 
                 let _ = event_tx.send(TransferEvent::FileReceived(
                     format!("[DEBUG] FileStart: {} size={:.1}GB type={} extract={}", 
@@ -92,16 +215,20 @@ pub(crate) async fn handle_client_transport(
                 
                 if stream_extract {
                     // Истинная потоковая распаковка
-                    let result = receive_and_extract_streaming_transport(
-                        &mut *stream,
-                        &save_dir,
-                        &filename,
+                    let archive = IncomingArchive {
+                        filename: &filename,
                         size,
-                        compressed,
-                        options.save_archive_for_resume,
-                        &event_tx,
-                        &stop_flag,
-                    ).await;
+                        codec,
+                        extract_dir: &options.extract_output_dir(&save_dir),
+                        archive_dir: &options.archive_dir(&save_dir),
+                        save_archive: options.save_archive_for_resume,
+                        offer_manifest: session_features & FEATURE_EXTRACT_MANIFEST != 0,
+                        post_hook: &session.hook,
+                        path_limits,
+                        file_idx,
+                    };
+                    let result = receive_and_extract_streaming_transport(&mut *stream, archive, &event_tx, &stop_flag).await;
+                    METRICS.record_transfer(result.is_ok());
                     
                     if let Err(e) = result {
                         if stop_flag.load(Ordering::SeqCst) {
@@ -111,54 +238,73 @@ pub(crate) async fn handle_client_transport(
                         return Err(e);
                     }
                     
+                    audit.file_received(&peer, &filename, size);
                     let _ = event_tx.send(TransferEvent::FileReceived(
                         "[DEBUG] Распаковка завершена, ожидаем Done".to_string(), 0
                     ));
                 } else {
-                    // Обычное сохранение файла
+                    // Обычное сохранение файла. Нижестоящим уходит исходное имя: шаблон у каждого свой
+                    if let Some(relay) = relay.as_mut() {
+                        if let Some(path) = &relay_raw_name {
+                            let bytes = path.iter().map(crate::protocol::os_str_to_wire).collect::<Vec<_>>().join(&b'/');
+                            relay.send(&Message::RawFileName { bytes }).await;
+                        }
+                        if let Some(xattrs) = &file_meta {
+                            relay.send(&Message::FileMeta { xattrs: xattrs.clone() }).await;
+                        }
+                        // Куски уходят как пришли - нижестоящим нужен тот же кодек
+                        if let Some(codec @ CompressionCodec::Zstd { .. }) = codec {
+                            relay.send(&Message::FileCodec { codec }).await;
+                        }
+                        if no_extract {
+                            relay.send(&Message::NoExtract).await;
+                        }
+                        relay.send(&Message::FileStart {
+                            filename: filename.clone(),
+                            size,
+                            compressed,
+                            offset: 0,
+                            quick_hash: 0,
+                        }).await;
+                    }
+                    let file = IncomingFile {
+                        filename: &save_name,
+                        raw_name: raw_name.as_deref(),
+                        size,
+                        codec,
+                        quick_hash,
+                        // Докачка с середины оставила бы нижестоящих без начала файла
+                        enable_resume: options.enable_resume && relay.is_none(),
+                        verify_integrity: session_features & FEATURE_VERIFY_INTEGRITY != 0,
+                        file_idx,
+                    };
                     let result = receive_file_transport(
                         &mut *stream,
                         &save_dir,
-                        &filename,
-                        size,
-                        compressed,
-                        quick_hash,
-                        options.enable_resume,
+                        file,
+                        relay.as_mut(),
                         &event_tx,
                         &stop_flag,
                     ).await;
+                    METRICS.record_transfer(matches!(result, Ok(Some(_))));
                     
                     match result {
-                        Ok(file_path) => {
-                            // Если нужно распаковать (tar, zip, rar - не tar.lz4 и не tar.zst)
-                            if should_extract && !is_tar_lz4 && !is_tar_zst {
-                                let _ = event_tx.send(TransferEvent::ExtractionStarted(filename.clone()));
-                                
-                                let output_dir = save_dir.clone();
-                                let event_tx_clone = event_tx.clone();
-                                let filename_clone = filename.clone();
-                                let file_path_clone = file_path.clone();
-                                
-                                // Распаковываем в отдельном потоке
-                                tokio::task::spawn_blocking(move || {
-                                    match extract::extract_archive(&file_path_clone, &output_dir) {
-                                        Ok(result) => {
-                                            let _ = event_tx_clone.send(TransferEvent::ExtractionCompleted(
-                                                filename_clone,
-                                                result.files_count,
-                                                result.total_size,
-                                            ));
-                                            // Удаляем архив после распаковки
-                                            let _ = std::fs::remove_file(&file_path_clone);
-                                        }
-                                        Err(e) => {
-                                            let _ = event_tx_clone.send(TransferEvent::ExtractionError(
-                                                filename_clone,
-                                                e.to_string(),
-                                            ));
-                                        }
-                                    }
-                                });
+                        // Копия повреждена и удалена, отправитель знает - ждём следующий файл
+                        Ok(None) => {
+                            audit.error(&peer, &format!("{}: SHA-256 не совпал, файл удалён", save_name));
+                        }
+                        Ok(Some(file_path)) => {
+                            audit.file_received(&peer, &save_name, size);
+                            if let Some(xattrs) = &file_meta {
+                                if let Err(e) = crate::utils::apply_xattrs(&file_path, xattrs) {
+                                    let _ = event_tx.send(TransferEvent::FileReceived(format!("⚠️ {}", e), 0));
+                                }
+                            }
+                            // Если нужно распаковать (tar, zip, rar - то, что не распаковывается на лету)
+                            if should_extract && !stream_extract {
+                                spawn_extract(file_path, filename, &options, &save_dir, path_limits, &session.hook, &event_tx);
+                            } else {
+                                session.hook.spawn(&file_path, &event_tx);
                             }
                         }
                         Err(e) => {
@@ -175,6 +321,9 @@ pub(crate) async fn handle_client_transport(
                 let _ = event_tx.send(TransferEvent::FileReceived(
                     "[DEBUG] Получен Done, завершаем".to_string(), 0
                 ));
+                if let Some(relay) = relay.take() {
+                    relay.finish().await;
+                }
                 return Ok(());
             }
             Message::Ack => {
@@ -183,9 +332,184 @@ pub(crate) async fn handle_client_transport(
                 stream.write_all(&ack).await.map_err(|e| e.to_string())?;
             }
             Message::SpeedTestRequest { size } => {
-                crate::network::speedtest::handle_speedtest_server_transport(&mut *stream, size).await?;
+                crate::network::speedtest::handle_speedtest_server_transport(&mut *stream, size, &stop_flag).await?;
+            }
+            Message::ChunkProbe { .. } => {
+                // Проба размера чанка: ответом служит Ack на следующий ping
+            }
+            Message::RawFileName { bytes } => {
+                match crate::protocol::relative_path_from_wire(&bytes) {
+                    Some(path) => {
+                        raw_name = Some(path);
+                        send_ack_transport(&mut *stream).await?;
+                    }
+                    None => {
+                        let lossy = String::from_utf8_lossy(&bytes).to_string();
+                        audit.rejected(&peer, Some(&lossy), "Имя файла не представимо");
+                        let _ = event_tx.send(TransferEvent::FileReceived(
+                            format!("⚠️ Имя {} не представимо на этой системе, файл будет сохранён под изменённым именем", lossy),
+                            0
+                        ));
+                        let data = Message::Error("Имя файла не представимо".to_string())
+                            .to_bytes().map_err(|e| e.to_string())?;
+                        stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                    }
+                }
+            }
+            Message::FileMeta { xattrs } => {
+                // Атрибуты пишутся только с согласия получателя, подтверждённого в Hello
+                if session_features & FEATURE_FILE_META == 0 {
+                    audit.rejected(&peer, None, "Атрибуты файлов не принимаются");
+                    let data = Message::Error("Атрибуты файлов не принимаются".to_string())
+                        .to_bytes().map_err(|e| e.to_string())?;
+                    stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                    continue;
+                }
+                file_meta = Some(xattrs);
+                send_ack_transport(&mut *stream).await?;
+            }
+            Message::FileCodec { codec } => {
+                next_codec = Some(codec);
+                send_ack_transport(&mut *stream).await?;
+            }
+            Message::NoExtract => {
+                next_no_extract = true;
+                send_ack_transport(&mut *stream).await?;
+            }
+            Message::RelayHops { hops } => {
+                relay_hops = hops;
+                send_ack_transport(&mut *stream).await?;
+            }
+            Message::ResumeProbe { files } => {
+                // Те же пути и проверки, что при FileStart
+                let mut offsets = Vec::with_capacity(files.len());
+                for entry in files {
+                    let name = options.safe_name(&options.templated_name(&entry.relative_path, &peer));
+                    // Небезопасный путь всё равно не будет принят - докачивать нечего
+                    let file_path = safe_relative_path(&name).ok().map(|path| save_dir.join(path));
+                    offsets.push(match file_path {
+                        Some(file_path) if options.enable_resume => {
+                            check_resume(&file_path, entry.size, entry.quick_hash).await
+                        }
+                        _ => 0,
+                    });
+                }
+                let result = Message::ResumeProbeResult { offsets };
+                let data = if packed { result.to_bytes_packed(LIST_PACK_THRESHOLD) } else { result.to_bytes() };
+                let data = data.map_err(|e| e.to_string())?;
+                stream.write_all(&data).await.map_err(|e| e.to_string())?;
+            }
+            msg @ (Message::SyncRequest | Message::SyncRoots { .. }) => {
+                // Список того, что уже лежит в папке приёма - отправитель сам считает разницу.
+                // Новый отправитель называет свои корни: остальные папки не обходятся
+                let roots = match msg {
+                    Message::SyncRoots { roots } => Some(roots),
+                    _ => None,
+                };
+                let dir = save_dir.clone();
+                let listing = tokio::task::spawn_blocking(move || {
+                    crate::sync::collect_remote_listing(&dir, roots.as_deref(), crate::sync::MAX_REMOTE_LISTING)
+                })
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let reply = match listing {
+                    Ok(files) => Message::SyncFileList {
+                        files: files.into_iter()
+                            .map(|f| SyncFileEntry {
+                                relative_path: f.relative_path,
+                                size: f.size,
+                                quick_hash: f.quick_hash,
+                            })
+                            .collect(),
+                    },
+                    Err(e) => Message::Error(format!("Не удалось прочитать папку: {}", e)),
+                };
+                let data = if packed { reply.to_bytes_packed(LIST_PACK_THRESHOLD) } else { reply.to_bytes() };
+                let data = data.map_err(|e| e.to_string())?;
+                stream.write_all(&data).await.map_err(|e| e.to_string())?;
+            }
+            Message::DeleteFiles { paths } => {
+                let refusal = if !options.allow_delete {
+                    Some("Удаление файлов на получателе выключено (receive --allow-delete)".to_string())
+                } else if paths.len() > options.max_delete_files {
+                    Some(format!(
+                        "Слишком много файлов к удалению: {} (максимум {}, receive --max-deletes)",
+                        paths.len(), options.max_delete_files
+                    ))
+                } else {
+                    None
+                };
+                if let Some(reason) = refusal {
+                    audit.rejected(&peer, None, &reason);
+                    let data = Message::Error(reason).to_bytes().map_err(|e| e.to_string())?;
+                    stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                    continue;
+                }
+                let (deleted, failed) = delete_synced_files(&save_dir, &paths).await;
+                for path in deleted {
+                    audit.file_deleted(&peer, &path);
+                    let _ = event_tx.send(TransferEvent::FileDeleted(path));
+                }
+                if failed.is_empty() {
+                    send_ack_transport(&mut *stream).await?;
+                } else {
+                    let err = Message::Error(format!("Не удалось удалить: {}", failed.join(", ")));
+                    let data = err.to_bytes().map_err(|e| e.to_string())?;
+                    stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                }
+            }
+            Message::Hello { features, intent } => {
+                // Ответ ещё в обычном формате, дальше - в согласованном
+                let features = features & options.supported_features();
+                let data = Message::HelloAck { features }.to_bytes().map_err(|e| e.to_string())?;
+                stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                stream.flush().await.map_err(|e| e.to_string())?;
+                // Проверке доступности ответ и нужен - больше она ничего не пришлёт
+                if intent == ConnectionIntent::Probe {
+                    return Ok(());
+                }
+                session_features = features;
+                if features & FEATURE_COMPACT_FRAMING != 0 {
+                    stream = Box::new(CompactStream::new(stream));
+                }
+            }
+            Message::FileLink { from: sent_from, to: sent_to } => {
+                // Оба имени - как их сохраняет получатель: `from` уже лежит под своим итоговым именем
+                let from = options.safe_name(&options.templated_name(&sent_from, &peer));
+                let to = match checked_save_name(&options, &sent_to, None, 0, &peer, &event_tx) {
+                    Ok((to, _)) => to,
+                    Err(reason) => {
+                        // Отправитель передаст файл целиком, и отказ придёт уже на FileStart
+                        let data = Message::Error(reason).to_bytes().map_err(|e| e.to_string())?;
+                        stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                        continue;
+                    }
+                };
+                match link_file(&save_dir, &from, &to).await {
+                    Ok(size) => {
+                        send_ack_transport(&mut *stream).await?;
+                        audit.file_received(&peer, &to, size);
+                        let _ = event_tx.send(TransferEvent::FileReceived(
+                            format!("{} (копия {})", to, from),
+                            size
+                        ));
+                        session.hook.spawn(&save_dir.join(&to), &event_tx);
+                        if relay.is_none() && !options.relay_to.is_empty() {
+                            relay = Some(Relay::connect(&options.relay_to, options.transport_type, session_features, relay_hops, &event_tx).await);
+                        }
+                        if let Some(relay) = relay.as_mut() {
+                            relay.send_link(&sent_from, &sent_to, save_dir.join(&to)).await;
+                        }
+                    }
+                    Err(e) => {
+                        // Отправитель передаст файл целиком
+                        let data = Message::Error(e).to_bytes().map_err(|e| e.to_string())?;
+                        stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                    }
+                }
             }
             _ => {
+                audit.rejected(&peer, None, "Неожиданное сообщение");
                 let err = Message::Error("Неожиданное сообщение".to_string());
                 let data = err.to_bytes().map_err(|e| e.to_string())?;
                 stream.write_all(&data).await.map_err(|e| e.to_string())?;
@@ -194,21 +518,84 @@ pub(crate) async fn handle_client_transport(
     }
 }
 
-/// Приём файла через абстрактный транспорт
+/// Распаковать сохранённый архив в фоне (то, что не распаковывается на лету).
+/// После распаковки архив удаляется и запускается post-hook
+pub(crate) fn spawn_extract(
+    file_path: PathBuf,
+    filename: String,
+    options: &ServerOptions,
+    save_dir: &Path,
+    path_limits: extract::PathLimits,
+    hook: &PostHook,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) {
+    let _ = event_tx.send(TransferEvent::ExtractionStarted(filename.clone()));
+    
+    let output_dir = options.extract_output_dir(save_dir);
+    let event_tx = event_tx.clone();
+    let hook = hook.clone();
+    
+    // Распаковываем в отдельном потоке
+    tokio::task::spawn_blocking(move || {
+        match metrics::time_extraction(|| extract::extract_archive_with_limits(&file_path, &output_dir, None, &path_limits)) {
+            Ok(result) => {
+                report_skipped_entries(&filename, &result, &event_tx);
+                let _ = event_tx.send(TransferEvent::ExtractionCompleted(
+                    filename,
+                    result,
+                ));
+                // Удаляем архив после распаковки
+                let _ = std::fs::remove_file(&file_path);
+                hook.spawn(&output_dir, &event_tx);
+            }
+            Err(e) => {
+                let _ = event_tx.send(TransferEvent::ExtractionError(
+                    filename,
+                    e.to_string(),
+                ));
+            }
+        }
+    });
+}
+
+/// Принимаемый файл: что объявил отправитель и как его сохранять
+pub(crate) struct IncomingFile<'a> {
+    /// Имя для сохранения (уже по шаблону получателя)
+    pub filename: &'a str,
+    /// Точный путь из RawFileName, если имя не в UTF-8
+    pub raw_name: Option<&'a Path>,
+    pub size: u64,
+    /// Кодек чанков, None - без сжатия
+    pub codec: Option<CompressionCodec>,
+    pub quick_hash: u64,
+    pub enable_resume: bool,
+    /// Сверять SHA-256 из `FileHash` (согласован `FEATURE_VERIFY_INTEGRITY`)
+    pub verify_integrity: bool,
+    /// Номер файла для Progress, см. FileReceiveStarted
+    pub file_idx: usize,
+}
+
+/// Приём файла через абстрактный транспорт.
+///
+/// `Ok(None)` - SHA-256 из FileHash не совпал с принятым (`verify_integrity`):
+/// копия удалена, отправителю ушёл ChecksumMismatch
 pub(crate) async fn receive_file_transport(
     stream: &mut dyn TransportStream,
     save_dir: &PathBuf,
-    filename: &str,
-    size: u64,
-    compressed: bool,
-    quick_hash: u64,
-    enable_resume: bool,
+    file: IncomingFile<'_>,
+    mut relay: Option<&mut Relay>, // Пересылка кусков на relay_to
     event_tx: &mpsc::UnboundedSender<TransferEvent>,
     stop_flag: &Arc<AtomicBool>,
-) -> Result<PathBuf, String> {
-    // Нормализуем путь
-    let normalized_path = filename.replace('/', std::path::MAIN_SEPARATOR_STR);
-    let file_path = save_dir.join(&normalized_path);
+) -> Result<Option<PathBuf>, String> {
+    let IncomingFile { filename, raw_name, size, codec, quick_hash, enable_resume, verify_integrity, file_idx } = file;
+    // Путь от отправителя не должен выйти за папку приёма
+    let file_path = match raw_name {
+        Some(raw_name) => save_dir.join(
+            extract::sanitize_relative_path(raw_name)
+                .map_err(|e| format!("Отклонено имя файла от отправителя: {}", e))?
+        ),
+        None => save_dir.join(safe_relative_path(filename)?),
+    };
     
     // Создаём родительские папки если нужно
     if let Some(parent) = file_path.parent() {
@@ -225,22 +612,32 @@ pub(crate) async fn receive_file_transport(
     };
     
     // Если файл уже полностью получен и хэш совпадает
-    if resume_offset >= size {
+    if is_complete(&file_path, size, resume_offset, enable_resume).await {
         let resume_ack = Message::ResumeAck { offset: size };
         let data = resume_ack.to_bytes().map_err(|e| e.to_string())?;
         stream.write_all(&data).await.map_err(|e| e.to_string())?;
         
         let _ = event_tx.send(TransferEvent::FileReceived(filename.to_string(), size));
-        return Ok(file_path);
+        return Ok(Some(file_path));
     }
     
+    // SHA-256 принятого: при докачке сначала уже лежащее на диске начало
+    let mut sha = verify_integrity.then(Sha256::new);
+    let mut expected_hash = None;
+    
     // Открываем/создаём файл
+    // Пишем во временный .toolza-part, итоговое имя появится только после FileEnd
+    let part_file_path = part_path(&file_path);
     let mut file = if resume_offset > 0 {
-        let f = tokio::fs::OpenOptions::new()
+        let mut f = tokio::fs::OpenOptions::new()
+            .read(true)
             .write(true)
-            .open(&file_path)
+            .open(&part_file_path)
             .await
             .map_err(|e| format!("Не удалось открыть файл для resume: {}", e))?;
+        if let Some(sha) = sha.as_mut() {
+            crate::utils::sha256_prefix(&mut f, resume_offset, sha).await.map_err(|e| e.to_string())?;
+        }
         
         let resume_ack = Message::ResumeAck { offset: resume_offset };
         let data = resume_ack.to_bytes().map_err(|e| e.to_string())?;
@@ -248,7 +645,7 @@ pub(crate) async fn receive_file_transport(
         
         f
     } else {
-        let f = tokio::fs::File::create(&file_path)
+        let f = tokio::fs::File::create(&part_file_path)
             .await
             .map_err(|e| format!("Не удалось создать файл: {}", e))?;
         
@@ -283,41 +680,94 @@ pub(crate) async fn receive_file_transport(
         let msg = Message::from_bytes(&data).map_err(|e| e.to_string())?;
         
         match msg {
-            Message::FileChunk { data, original_size: _ } => {
+            Message::FileChunk { data, original_size } => {
                 if stop_flag.load(Ordering::SeqCst) {
                     return Err("⛔ Остановлено пользователем".to_string());
                 }
+                if let Some(relay) = relay.as_deref_mut() {
+                    relay.send(&Message::FileChunk { data: data.clone(), original_size }).await;
+                }
                 
-                let write_data = if compressed {
-                    compression::decompress(&data)?
-                } else {
-                    data
+                let write_data = match codec {
+                    Some(codec) => codec.decompress(&data)?,
+                    None => data,
                 };
                 received_bytes += write_data.len() as u64;
-                file.write_all(&write_data).await.map_err(|e| e.to_string())?;
+                METRICS.add_bytes_received(write_data.len() as u64);
+                if let Some(sha) = sha.as_mut() {
+                    sha.update(&write_data);
+                }
+                if let Err(e) = file.write_all(&write_data).await {
+                    if !is_disk_full(&e) {
+                        return Err(e.to_string());
+                    }
+                    drop(file);
+                    return Err(abort_disk_full(stream, filename, &part_file_path, true, event_tx).await);
+                }
                 
                 if last_progress_update.elapsed().as_secs() >= 1 {
                     let _ = event_tx.send(TransferEvent::Progress(
-                        0, 0, received_bytes, size, received_bytes,
+                        0, file_idx, received_bytes, size, received_bytes,
                     ));
                     last_progress_update = std::time::Instant::now();
                 }
             }
+            Message::FileHash { sha256 } => {
+                // Хэш пригодится, только если принятое тоже хэшируется
+                if sha.is_none() {
+                    let data = Message::Error("SHA-256 не сверяется".to_string()).to_bytes().map_err(|e| e.to_string())?;
+                    stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                    continue;
+                }
+                expected_hash = Some(sha256);
+                send_ack_transport(stream).await?;
+                // Нижестоящие сверяют хэш сами
+                if let Some(relay) = relay.as_deref_mut() {
+                    relay.send(&Message::FileHash { sha256 }).await;
+                }
+            }
             Message::FileEnd => {
-                file.flush().await.map_err(|e| e.to_string())?;
+                // Отложенная запись может упереться в место только сейчас
+                if let Err(e) = file.flush().await {
+                    if !is_disk_full(&e) {
+                        return Err(e.to_string());
+                    }
+                    drop(file);
+                    return Err(abort_disk_full(stream, filename, &part_file_path, false, event_tx).await);
+                }
+                drop(file);
+                if let Some(relay) = relay {
+                    relay.send(&Message::FileEnd).await;
+                }
+                
+                // Хэш есть, только если отправитель его прислал
+                if let (Some(sha), Some(expected)) = (sha, expected_hash) {
+                    if <[u8; 32]>::from(sha.finalize()) != expected {
+                        let _ = tokio::fs::remove_file(&part_file_path).await;
+                        let data = Message::ChecksumMismatch.to_bytes().map_err(|e| e.to_string())?;
+                        stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                        let _ = event_tx.send(TransferEvent::FileReceived(
+                            format!("⚠️ {}: SHA-256 не совпал, файл удалён", filename),
+                            0,
+                        ));
+                        return Ok(None);
+                    }
+                }
+                
+                finalize_part(&part_file_path, &file_path).await?;
                 send_ack_transport(stream).await?;
                 
                 let elapsed = start_time.elapsed().as_secs_f64();
                 let speed_mbps = if elapsed > 0.0 { received_bytes as f64 / elapsed / 1024.0 / 1024.0 } else { 0.0 };
                 
                 let _ = event_tx.send(TransferEvent::Progress(
-                    0, 0, received_bytes, size, received_bytes,
+                    0, file_idx, received_bytes, size, received_bytes,
                 ));
                 let _ = event_tx.send(TransferEvent::FileReceived(
                     format!("{} ({:.1} MB/s)", filename, speed_mbps),
                     size
                 ));
-                return Ok(file_path);
+                return Ok(Some(file_path));
             }
             _ => {
                 return Err("Неожиданное сообщение при получении файла".to_string());
@@ -326,28 +776,166 @@ pub(crate) async fn receive_file_transport(
     }
 }
 
+/// Ошибка записи из-за нехватки места на диске
+fn is_disk_full(e: &std::io::Error) -> bool {
+    e.kind() == std::io::ErrorKind::StorageFull
+}
+
+/// Диск заполнен посреди файла: `.toolza-part` остаётся для докачки, отправителю
+/// уходит Cancel. Если FileEnd ещё не пришёл (`drain`), оставшиеся чанки
+/// дочитываются и отбрасываются - иначе отправитель упрётся в полный буфер
+/// сокета и не дойдёт до ответа. Возвращает текст ошибки для ConnectionError
+async fn abort_disk_full(
+    stream: &mut dyn TransportStream,
+    filename: &str,
+    part_file_path: &Path,
+    drain: bool,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) -> String {
+    let kept = tokio::fs::metadata(part_file_path).await.map(|meta| meta.len()).unwrap_or(0);
+    let _ = event_tx.send(TransferEvent::DiskFull(filename.to_string(), kept));
+    let _ = send_cancel_transport(stream).await;
+    
+    if drain {
+        loop {
+            let mut len_buf = [0u8; 4];
+            if stream.read_exact(&mut len_buf).await.is_err() {
+                break;
+            }
+            let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+            if stream.read_exact(&mut data).await.is_err() {
+                break;
+            }
+            if !matches!(Message::from_bytes(&data), Ok(Message::FileChunk { .. })) {
+                break;
+            }
+        }
+    }
+    
+    format!(
+        "💾 Диск заполнен: {} сохранён частично ({} байт). Освободите место - повторная отправка продолжит с места остановки",
+        filename, kept
+    )
+}
+
+/// Скопировать уже полученный файл `from` в `to` (дедупликация отправителя).
+/// Возвращает размер скопированного файла
+pub(crate) async fn link_file(save_dir: &Path, from: &str, to: &str) -> Result<u64, String> {
+    let src = save_dir.join(safe_relative_path(from)?);
+    let dst = save_dir.join(safe_relative_path(to)?);
+    
+    if !src.is_file() {
+        return Err(format!("Исходный файл для копии не найден: {}", from));
+    }
+    
+    if let Some(parent) = dst.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Не удалось создать папку: {}", e))?;
+    }
+    
+    // Копируем через .toolza-part, чтобы не было видно неполной копии
+    let part_file_path = part_path(&dst);
+    let size = tokio::fs::copy(&src, &part_file_path)
+        .await
+        .map_err(|e| format!("Не удалось скопировать {}: {}", from, e))?;
+    finalize_part(&part_file_path, &dst).await?;
+    
+    Ok(size)
+}
+
+/// Удалить файлы из `Message::DeleteFiles`. Пути, выходящие за `save_dir`
+/// (абсолютные, с `..`, через ссылку на папку снаружи), и всё, что не обычный
+/// файл, не трогаются. Возвращает удалённое и то, что удалить не удалось (с причиной)
+pub(crate) async fn delete_synced_files(save_dir: &Path, paths: &[String]) -> (Vec<String>, Vec<String>) {
+    let (mut deleted, mut failed) = (Vec::new(), Vec::new());
+    let root = match tokio::fs::canonicalize(save_dir).await {
+        Ok(root) => root,
+        Err(e) => return (deleted, paths.iter().map(|path| format!("{} ({})", path, e)).collect()),
+    };
+    for path in paths {
+        let relative = match safe_relative_path(path) {
+            Ok(relative) => relative,
+            Err(e) => {
+                failed.push(format!("{} ({})", path, e));
+                continue;
+            }
+        };
+        let file_path = save_dir.join(relative);
+        // Папка файла со всеми ссылками по пути должна остаться внутри папки приёма
+        let parent = file_path.parent().unwrap_or(save_dir);
+        match tokio::fs::canonicalize(parent).await {
+            Ok(parent) if parent.starts_with(&root) => {}
+            Ok(_) => {
+                failed.push(format!("{} (вне папки приёма)", path));
+                continue;
+            }
+            Err(e) => {
+                failed.push(format!("{} ({})", path, e));
+                continue;
+            }
+        }
+        match tokio::fs::symlink_metadata(&file_path).await {
+            Ok(meta) if meta.is_file() => {}
+            Ok(_) => {
+                failed.push(format!("{} (не файл)", path));
+                continue;
+            }
+            Err(e) => {
+                failed.push(format!("{} ({})", path, e));
+                continue;
+            }
+        }
+        match tokio::fs::remove_file(&file_path).await {
+            Ok(()) => deleted.push(path.clone()),
+            Err(e) => failed.push(format!("{} ({})", path, e)),
+        }
+    }
+    (deleted, failed)
+}
+
+/// Переименовать полностью полученный .toolza-part в итоговое имя
+pub(crate) async fn finalize_part(part_file_path: &Path, file_path: &Path) -> Result<(), String> {
+    tokio::fs::rename(part_file_path, file_path)
+        .await
+        .map_err(|e| format!("Не удалось переименовать {}: {}", part_file_path.display(), e))
+}
+
 /// Проверка возможности возобновления загрузки
+///
+/// Готовый файл с совпадающим хэшем - передавать нечего (offset = размер).
+/// Иначе продолжаем с конца `.toolza-part`, если он есть.
 pub(crate) async fn check_resume(file_path: &PathBuf, expected_size: u64, quick_hash: u64) -> u64 {
     if quick_hash == 0 {
         return 0;
     }
     
-    match tokio::fs::metadata(file_path).await {
-        Ok(meta) => {
-            let current_size = meta.len();
-            if current_size >= expected_size {
-                // Файл уже полный - проверяем хэш
-                if let Ok(file_hash) = compute_quick_hash(file_path).await {
-                    if file_hash == quick_hash {
-                        return expected_size;
-                    }
+    if let Ok(meta) = tokio::fs::metadata(file_path).await {
+        if meta.len() == expected_size {
+            if let Ok(file_hash) = compute_quick_hash(file_path).await {
+                if file_hash == quick_hash {
+                    return expected_size;
                 }
-                return 0; // Хэш не совпал - качаем заново
             }
-            current_size
         }
-        Err(_) => 0,
     }
+    
+    match tokio::fs::metadata(part_path(file_path)).await {
+        Ok(meta) if meta.len() < expected_size => meta.len(),
+        _ => 0, // Нет частичного файла или он больше ожидаемого - качаем заново
+    }
+}
+
+/// Есть ли у получателя готовый файл и передачу можно пропустить.
+///
+/// У пустого файла quick_hash равен 0 и `check_resume` его не сравнивает,
+/// поэтому пустой файл считается полученным, только если он уже лежит на диске
+async fn is_complete(file_path: &Path, size: u64, resume_offset: u64, enable_resume: bool) -> bool {
+    if size == 0 {
+        return enable_resume
+            && matches!(tokio::fs::metadata(file_path).await, Ok(meta) if meta.is_file() && meta.len() == 0);
+    }
+    resume_offset >= size
 }
 
 /// Быстрый хэш файла (первые + последние 4KB)
@@ -361,6 +949,9 @@ pub(crate) async fn compute_quick_hash(file_path: &PathBuf) -> Result<u64, Strin
     
     let mut hasher = FnvHasher::new();
     
+    // Хэшируем размер (так же, как отправитель)
+    hasher.update(&size.to_le_bytes());
+    
     // Читаем первые 4KB
     let first_block_size = (size.min(4096)) as usize;
     let mut first_block = vec![0u8; first_block_size];
@@ -384,8 +975,14 @@ pub(crate) async fn handle_client_tcp(
     save_dir: PathBuf,
     options: ServerOptions,
     event_tx: mpsc::UnboundedSender<TransferEvent>,
+    stop_flag: Arc<AtomicBool>,
+    session: &ClientSession,
 ) -> Result<(), String> {
+    let _connection = METRICS.connection_opened();
+    let (peer, audit) = (session.peer.clone(), &session.audit);
     let (mut reader, mut writer) = stream.into_split();
+    // Просьба NoExtract для следующего FileStart
+    let mut next_no_extract = false;
     
     loop {
         let mut len_buf = [0u8; 4];
@@ -402,36 +999,66 @@ pub(crate) async fn handle_client_tcp(
         reader.read_exact(&mut data).await.map_err(|e| e.to_string())?;
         
         let msg = Message::from_bytes(&data).map_err(|e| e.to_string())?;
+        session.observe(&msg);
         
         match msg {
             Message::FileStart { filename, size, compressed, offset: _, quick_hash } => {
+                let no_extract = std::mem::take(&mut next_no_extract);
+                let save_name = match checked_save_name(&options, &filename, None, size, &peer, &event_tx) {
+                    Ok((save_name, _)) => save_name,
+                    Err(reason) => {
+                        audit.rejected(&peer, Some(&filename), &reason);
+                        let _ = event_tx.send(TransferEvent::FileRejected(filename, reason.clone()));
+                        let data = Message::Rejected { reason }.to_bytes().map_err(|e| e.to_string())?;
+                        writer.write_all(&data).await.map_err(|e| e.to_string())?;
+                        continue;
+                    }
+                };
+                let file_idx = session.start_file(&filename, size);
                 let archive_type = extract::ArchiveType::from_filename(&filename);
-                let is_tar_lz4 = archive_type == extract::ArchiveType::TarLz4;
-                let is_tar_zst = archive_type == extract::ArchiveType::TarZst;
-                let stream_extract = (options.extract_options.tar_lz4 && is_tar_lz4) || (options.extract_options.tar_zst && is_tar_zst);
+                let stream_extract = !no_extract
+                    && options.should_extract(&filename)
+                    && archive_type.is_streamable();
                 
                 if stream_extract {
-                    receive_and_extract_streaming_tcp(
-                        &mut reader,
-                        &mut writer,
-                        &save_dir,
-                        &filename,
+                    // Потоковая распаковка (и её докачка) - общая с транспортным обработчиком,
+                    // с тем же ограничением ожидания замолчавшего отправителя
+                    let mut stream = TimeoutStream::new(
+                        Box::new(TcpStreamWrapper::new(reader.reunite(writer).map_err(|e| e.to_string())?)),
+                        options.timeout.unwrap_or(Duration::MAX),
+                    );
+                    let archive = IncomingArchive {
+                        filename: &filename,
                         size,
-                        compressed,
-                        &event_tx,
-                    ).await?;
+                        // Устаревший обработчик FileCodec не принимает - только LZ4
+                        codec: compressed.then_some(CompressionCodec::Lz4),
+                        extract_dir: &options.extract_output_dir(&save_dir),
+                        archive_dir: &options.archive_dir(&save_dir),
+                        save_archive: options.save_archive_for_resume,
+                        offer_manifest: false,
+                        post_hook: &session.hook,
+                        path_limits: options.extract_limits(),
+                        file_idx,
+                    };
+                    let result = receive_and_extract_streaming_transport(&mut stream, archive, &event_tx, &stop_flag).await;
+                    METRICS.record_transfer(result.is_ok());
+                    result?;
+                    (reader, writer) = stream.into_inner().into_inner().into_split();
+                    audit.file_received(&peer, &filename, size);
                 } else {
-                    let _file_path = receive_file_tcp(
-                        &mut reader,
-                        &mut writer,
-                        &save_dir,
-                        &filename,
+                    let file = IncomingFile {
+                        filename: &save_name,
+                        raw_name: None,
                         size,
-                        compressed,
+                        codec: compressed.then_some(CompressionCodec::Lz4),
                         quick_hash,
-                        options.enable_resume,
-                        &event_tx,
-                    ).await?;
+                        enable_resume: options.enable_resume,
+                        verify_integrity: false,
+                        file_idx,
+                    };
+                    let file_path = receive_file_tcp(&mut reader, &mut writer, &save_dir, file, &event_tx).await?;
+                    audit.file_received(&peer, &save_name, size);
+                    session.hook.spawn(&file_path, &event_tx);
                 }
             }
             Message::Done => {
@@ -442,9 +1069,24 @@ pub(crate) async fn handle_client_tcp(
                 writer.write_all(&ack).await.map_err(|e| e.to_string())?;
             }
             Message::SpeedTestRequest { size } => {
-                crate::network::speedtest::handle_speedtest_server(&mut reader, &mut writer, size).await?;
+                crate::network::speedtest::handle_speedtest_server(&mut reader, &mut writer, size, &stop_flag).await?;
+            }
+            Message::ChunkProbe { .. } => {}
+            Message::Hello { features, intent } => {
+                let features = features & (FEATURE_NO_EXTRACT | FEATURE_CHUNK_PROBE);
+                let data = Message::HelloAck { features }.to_bytes().map_err(|e| e.to_string())?;
+                writer.write_all(&data).await.map_err(|e| e.to_string())?;
+                if intent == ConnectionIntent::Probe {
+                    return Ok(());
+                }
+            }
+            Message::NoExtract => {
+                next_no_extract = true;
+                let ack = Message::Ack.to_bytes().map_err(|e| e.to_string())?;
+                writer.write_all(&ack).await.map_err(|e| e.to_string())?;
             }
             _ => {
+                audit.rejected(&peer, None, "Неожиданное сообщение");
                 let err = Message::Error("Неожиданное сообщение".to_string());
                 let data = err.to_bytes().map_err(|e| e.to_string())?;
                 writer.write_all(&data).await.map_err(|e| e.to_string())?;
@@ -453,20 +1095,17 @@ pub(crate) async fn handle_client_tcp(
     }
 }
 
-/// Приём файла для TCP (устаревший)
+/// Приём файла для TCP (устаревший): только LZ4, без RawFileName и проверки SHA-256
 async fn receive_file_tcp(
     reader: &mut tokio::net::tcp::OwnedReadHalf,
     writer: &mut tokio::net::tcp::OwnedWriteHalf,
     save_dir: &PathBuf,
-    filename: &str,
-    size: u64,
-    compressed: bool,
-    quick_hash: u64,
-    enable_resume: bool,
+    file: IncomingFile<'_>,
     event_tx: &mpsc::UnboundedSender<TransferEvent>,
 ) -> Result<PathBuf, String> {
-    let normalized_path = filename.replace('/', std::path::MAIN_SEPARATOR_STR);
-    let file_path = save_dir.join(&normalized_path);
+    let IncomingFile { filename, size, codec, quick_hash, enable_resume, file_idx, .. } = file;
+    let compressed = codec.is_some();
+    let file_path = save_dir.join(safe_relative_path(filename)?);
     
     if let Some(parent) = file_path.parent() {
         tokio::fs::create_dir_all(parent)
@@ -480,7 +1119,7 @@ async fn receive_file_tcp(
         0
     };
     
-    if resume_offset >= size {
+    if is_complete(&file_path, size, resume_offset, enable_resume).await {
         let resume_ack = Message::ResumeAck { offset: size };
         let data = resume_ack.to_bytes().map_err(|e| e.to_string())?;
         writer.write_all(&data).await.map_err(|e| e.to_string())?;
@@ -489,10 +1128,12 @@ async fn receive_file_tcp(
         return Ok(file_path);
     }
     
+    // Пишем во временный .toolza-part, итоговое имя появится только после FileEnd
+    let part_file_path = part_path(&file_path);
     let mut file = if resume_offset > 0 {
         let f = tokio::fs::OpenOptions::new()
             .write(true)
-            .open(&file_path)
+            .open(&part_file_path)
             .await
             .map_err(|e| format!("Не удалось открыть файл для resume: {}", e))?;
         
@@ -502,7 +1143,7 @@ async fn receive_file_tcp(
         
         f
     } else {
-        let f = tokio::fs::File::create(&file_path)
+        let f = tokio::fs::File::create(&part_file_path)
             .await
             .map_err(|e| format!("Не удалось создать файл: {}", e))?;
         
@@ -537,17 +1178,20 @@ async fn receive_file_tcp(
                     data
                 };
                 received_bytes += write_data.len() as u64;
+                METRICS.add_bytes_received(write_data.len() as u64);
                 file.write_all(&write_data).await.map_err(|e| e.to_string())?;
                 
                 if last_progress_update.elapsed().as_secs() >= 1 {
                     let _ = event_tx.send(TransferEvent::Progress(
-                        0, 0, received_bytes, size, received_bytes,
+                        0, file_idx, received_bytes, size, received_bytes,
                     ));
                     last_progress_update = std::time::Instant::now();
                 }
             }
             Message::FileEnd => {
                 file.flush().await.map_err(|e| e.to_string())?;
+                drop(file);
+                finalize_part(&part_file_path, &file_path).await?;
                 
                 let ack = Message::Ack.to_bytes().map_err(|e| e.to_string())?;
                 writer.write_all(&ack).await.map_err(|e| e.to_string())?;
@@ -556,7 +1200,7 @@ async fn receive_file_tcp(
                 let speed_mbps = if elapsed > 0.0 { received_bytes as f64 / elapsed / 1024.0 / 1024.0 } else { 0.0 };
                 
                 let _ = event_tx.send(TransferEvent::Progress(
-                    0, 0, received_bytes, size, received_bytes,
+                    0, file_idx, received_bytes, size, received_bytes,
                 ));
                 let _ = event_tx.send(TransferEvent::FileReceived(
                     format!("{} ({:.1} MB/s)", filename, speed_mbps),
@@ -571,3 +1215,105 @@ async fn receive_file_tcp(
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_part_path() {
+        let path = part_path(Path::new("dir/file.bin"));
+        assert_eq!(path, PathBuf::from("dir/file.bin.toolza-part"));
+    }
+    
+    #[test]
+    fn test_checked_save_name_uses_final_name() {
+        let (event_tx, _event_rx) = mpsc::unbounded_channel();
+        let options = ServerOptions {
+            name_template: Some("{name}.exe".to_string()),
+            blocked_extensions: vec!["exe".to_string()],
+            sanitize_names: false,
+            ..Default::default()
+        };
+        // Отправитель прислал .txt, но на диске было бы .exe
+        assert!(checked_save_name(&options, "a.txt", None, 1, "peer", &event_tx).is_err());
+        assert!(checked_save_name(&options, "a.txt", Some(PathBuf::from("dir/a.txt")), 1, "peer", &event_tx).is_err());
+        
+        let options = ServerOptions { name_template: Some("in_{name}".to_string()), ..options };
+        let (name, raw) = checked_save_name(&options, "dir/a.txt", Some(PathBuf::from("dir/a.txt")), 1, "peer", &event_tx).unwrap();
+        assert_eq!(name, "dir/in_a.txt");
+        assert_eq!(raw, Some(PathBuf::from("dir/in_a.txt")));
+        
+        // С исправлением имён точный путь не используется
+        let options = ServerOptions { sanitize_names: true, ..options };
+        let (_, raw) = checked_save_name(&options, "a.txt", Some(PathBuf::from("a.txt")), 1, "peer", &event_tx).unwrap();
+        assert_eq!(raw, None);
+    }
+    
+    #[tokio::test]
+    async fn test_link_file_copies_received_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("a")).unwrap();
+        std::fs::write(dir.path().join("a").join("big.bin"), b"payload").unwrap();
+        
+        let size = link_file(dir.path(), "a/big.bin", "b/copy.bin").await.unwrap();
+        assert_eq!(size, 7);
+        assert_eq!(std::fs::read(dir.path().join("b").join("copy.bin")).unwrap(), b"payload");
+        
+        // Исходного файла нет - ошибка, отправитель передаст файл целиком
+        assert!(link_file(dir.path(), "missing.bin", "x.bin").await.is_err());
+        
+        // Пути отправителя не выходят за папку приёма
+        assert!(link_file(dir.path(), "../a/big.bin", "x.bin").await.is_err());
+        assert!(link_file(dir.path(), "a/big.bin", "../escaped.bin").await.is_err());
+        assert!(link_file(dir.path(), "a/big.bin", "/tmp/escaped.bin").await.is_err());
+        assert!(link_file(dir.path(), "a/big.bin", "C:\\escaped.bin").await.is_err());
+        assert!(!dir.path().parent().unwrap().join("escaped.bin").exists());
+    }
+    
+    #[tokio::test]
+    async fn test_delete_synced_files_stays_inside_save_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let save_dir = root.path().join("save");
+        std::fs::create_dir_all(save_dir.join("docs")).unwrap();
+        std::fs::write(save_dir.join("docs").join("old.txt"), b"old").unwrap();
+        std::fs::write(root.path().join("outside.txt"), b"keep").unwrap();
+        
+        let paths = ["docs/old.txt", "../outside.txt", "/etc/passwd", "docs", "missing.txt"].map(String::from);
+        let (deleted, failed) = delete_synced_files(&save_dir, &paths).await;
+        
+        assert_eq!(deleted, vec!["docs/old.txt"]);
+        assert_eq!(failed.len(), 4);
+        assert!(!save_dir.join("docs").join("old.txt").exists());
+        assert!(save_dir.join("docs").is_dir());
+        assert!(root.path().join("outside.txt").exists());
+        
+        // Ссылка на папку снаружи не уводит удаление за папку приёма
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.path(), save_dir.join("up")).unwrap();
+            let (deleted, failed) = delete_synced_files(&save_dir, &["up/outside.txt".to_string()]).await;
+            assert!(deleted.is_empty());
+            assert!(failed[0].contains("вне папки приёма"), "{:?}", failed);
+            assert!(root.path().join("outside.txt").exists());
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_check_resume_uses_part_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("data.bin");
+        
+        // Нет ни файла, ни .toolza-part
+        assert_eq!(check_resume(&file_path, 100, 42).await, 0);
+        
+        // Частичный файл - продолжаем с его конца
+        std::fs::write(part_path(&file_path), vec![1u8; 40]).unwrap();
+        assert_eq!(check_resume(&file_path, 100, 42).await, 40);
+        
+        // Полный файл с совпадающим хэшем - передавать нечего
+        std::fs::write(&file_path, vec![7u8; 100]).unwrap();
+        let hash = compute_quick_hash(&file_path).await.unwrap();
+        assert_eq!(check_resume(&file_path, 100, hash).await, 100);
+    }
+}
@@ -0,0 +1,173 @@
+//! Пользовательская команда после приёма файла или распаковки архива (post-hook)
+
+use crate::network::events::TransferEvent;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+
+use super::options::ServerOptions;
+
+/// Подставить `{path}` и `{dir}` в шаблон команды.
+///
+/// `{dir}` - папка, в которой лежит `path` (для папки распаковки - она сама).
+/// Возвращает None для пустого шаблона.
+pub(crate) fn expand_hook(template: &[String], path: &Path) -> Option<(String, Vec<String>)> {
+    let path_str = path.to_string_lossy();
+    let dir = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    let dir_str = dir.to_string_lossy();
+    
+    let mut parts = template
+        .iter()
+        .map(|arg| arg.replace("{path}", &path_str).replace("{dir}", &dir_str));
+    
+    let program = parts.next().filter(|p| !p.is_empty())?;
+    Some((program, parts.collect()))
+}
+
+/// Post-hook сервера: шаблон команды, таймаут и общий лимит одновременных команд.
+/// Клонируется в каждое соединение; без `post_hook` ничего не запускает
+#[derive(Clone, Debug)]
+pub(crate) struct PostHook {
+    template: Option<Arc<[String]>>,
+    timeout: Option<Duration>,
+    /// Свободные места для команд (`ServerOptions::max_hooks`)
+    slots: Arc<Semaphore>,
+}
+
+impl PostHook {
+    pub(crate) fn new(options: &ServerOptions) -> Self {
+        Self {
+            template: options.post_hook.as_deref().map(Arc::from),
+            timeout: options.hook_timeout,
+            slots: Arc::new(Semaphore::new(options.max_hooks.max(1))),
+        }
+    }
+    
+    /// Запустить команду для пути, если она задана.
+    ///
+    /// Команда выполняется в отдельной задаче и не блокирует приём; сверх
+    /// `max_hooks` команды ждут очереди. Не завершившаяся за `hook_timeout`
+    /// команда убивается. Вывод или ошибка приходят событием `HookCompleted`/`HookError`.
+    pub(crate) fn spawn(&self, path: &Path, event_tx: &mpsc::UnboundedSender<TransferEvent>) {
+        let Some((program, args)) = self.template.as_deref().and_then(|t| expand_hook(t, path)) else {
+            return;
+        };
+        
+        let name = path.to_string_lossy().to_string();
+        let event_tx = event_tx.clone();
+        let timeout = self.timeout.unwrap_or(Duration::MAX);
+        let slots = self.slots.clone();
+        
+        tokio::spawn(async move {
+            let Ok(_slot) = slots.acquire_owned().await else {
+                return;
+            };
+            // При таймауте future с процессом отбрасывается, и kill_on_drop его убивает
+            let output = tokio::process::Command::new(&program)
+                .args(&args)
+                .stdin(Stdio::null())
+                .kill_on_drop(true)
+                .output();
+            
+            let event = match tokio::time::timeout(timeout, output).await {
+                Ok(Ok(output)) => {
+                    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                    if output.status.success() {
+                        TransferEvent::HookCompleted(name, stdout)
+                    } else {
+                        TransferEvent::HookError(name, format!("{} ({}): {}", program, output.status, stderr))
+                    }
+                }
+                Ok(Err(e)) => TransferEvent::HookError(name, format!("не удалось запустить {}: {}", program, e)),
+                Err(_) => TransferEvent::HookError(
+                    name,
+                    format!("{} не завершился за {} с и остановлен", program, timeout.as_secs()),
+                ),
+            };
+            let _ = event_tx.send(event);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn template(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+    
+    #[test]
+    fn test_expand_hook_tokens() {
+        let (program, args) = expand_hook(
+            &template(&["import", "--src={path}", "{dir}"]),
+            Path::new("/data/photos/a.jpg"),
+        ).unwrap();
+        
+        assert_eq!(program, "import");
+        assert_eq!(args, vec!["--src=/data/photos/a.jpg", "/data/photos"]);
+    }
+    
+    #[test]
+    fn test_expand_empty_hook() {
+        assert!(expand_hook(&[], Path::new("/data/a")).is_none());
+        assert!(expand_hook(&template(&[""]), Path::new("/data/a")).is_none());
+    }
+    
+    fn post_hook(args: &[&str], timeout: Duration, max_hooks: usize) -> PostHook {
+        PostHook::new(&ServerOptions {
+            post_hook: Some(template(args)),
+            hook_timeout: Some(timeout),
+            max_hooks,
+            ..Default::default()
+        })
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_hook_reports_output() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let hook = post_hook(&["echo", "got {path}"], Duration::from_secs(10), 1);
+        
+        hook.spawn(Path::new("/tmp/file.txt"), &tx);
+        
+        match rx.recv().await {
+            Some(TransferEvent::HookCompleted(name, output)) => {
+                assert_eq!(name, "/tmp/file.txt");
+                assert_eq!(output, "got /tmp/file.txt");
+            }
+            other => panic!("ожидалось HookCompleted, получено {:?}", other),
+        }
+        
+        post_hook(&["false"], Duration::from_secs(10), 1).spawn(Path::new("/tmp/file.txt"), &tx);
+        assert!(matches!(rx.recv().await, Some(TransferEvent::HookError(..))));
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hung_hook_killed_and_queued() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        // Одно место: вторая команда ждёт, пока первую не остановит таймаут
+        let hook = post_hook(&["sleep", "30"], Duration::from_millis(300), 1);
+        let started = std::time::Instant::now();
+        
+        hook.spawn(Path::new("/tmp/a.txt"), &tx);
+        hook.spawn(Path::new("/tmp/b.txt"), &tx);
+        
+        for name in ["/tmp/a.txt", "/tmp/b.txt"] {
+            let event = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await.unwrap();
+            match event {
+                Some(TransferEvent::HookError(hook_name, reason)) => {
+                    assert_eq!(hook_name, name);
+                    assert!(reason.contains("остановлен"), "{}", reason);
+                }
+                other => panic!("ожидалось HookError, получено {:?}", other),
+            }
+        }
+        assert!(started.elapsed() >= Duration::from_millis(600));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
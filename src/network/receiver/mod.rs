@@ -3,16 +3,31 @@
 //! Подмодули:
 //! - `options` - опции сервера и автораспаковки
 //! - `handlers` - обработчики клиентских подключений
+//! - `hook` - пользовательская команда после приёма (post-hook)
+//! - `audit` - журнал аудита подключений
+//! - `session` - вид входящего соединения и события о нём
+//! - `relay` - пересылка принятых файлов дальше
 //! - `streaming` - потоковая распаковка архивов
+//! - `web` - веб-страница загрузки (фича `webui`)
 
 mod options;
 mod handlers;
+mod hook;
+mod audit;
+mod relay;
+mod session;
 mod streaming;
+#[cfg(feature = "webui")]
+mod web;
 
-pub use options::{ExtractOptions, ServerOptions};
+pub use options::{ExtractConfigError, ExtractOptions, ServerOptions, ServerOptionsBuilder, DEFAULT_BIND_ADDR, DEFAULT_HOOK_TIMEOUT, DEFAULT_MAX_DELETE_FILES, DEFAULT_MAX_HOOKS};
 
+use audit::{AuditLog, AUDIT_LOG_MAX_SIZE};
+use hook::PostHook;
+use session::ClientSession;
+use crate::network::cancel::CancelToken;
 use crate::network::events::TransferEvent;
-use crate::network::transport::TransportType;
+use crate::network::transport::TransportListener;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -35,9 +50,7 @@ pub async fn run_server(
             tar_lz4: auto_extract,
             ..Default::default()
         },
-        enable_resume: true,
-        transport_type: TransportType::default(),
-        save_archive_for_resume: false,
+        ..Default::default()
     };
     
     run_server_with_options(port, save_dir, options, event_tx).await
@@ -56,13 +69,22 @@ pub async fn run_server_with_stop(
             tar_lz4: auto_extract,
             ..Default::default()
         },
-        enable_resume: true,
-        transport_type: TransportType::default(),
-        save_archive_for_resume: false,
+        ..Default::default()
     };
     run_server_with_options_and_stop(port, save_dir, options, event_tx, stop_flag).await
 }
 
+/// Запустить сервер с полными опциями и отменой через `CancelToken`
+pub async fn run_server_with_cancel(
+    port: u16,
+    save_dir: PathBuf,
+    options: ServerOptions,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+    cancel: CancelToken,
+) -> Result<(), String> {
+    run_server_with_options_and_stop(port, save_dir, options, event_tx, cancel.flag()).await
+}
+
 /// Запустить сервер с полными опциями и поддержкой остановки
 pub async fn run_server_with_options_and_stop(
     port: u16,
@@ -71,9 +93,46 @@ pub async fn run_server_with_options_and_stop(
     event_tx: mpsc::UnboundedSender<TransferEvent>,
     stop_flag: Arc<AtomicBool>,
 ) -> Result<(), String> {
-    let mut listener = crate::network::transport::bind(options.transport_type, port)
+    let listener = bind_server(port, &options).await?;
+    serve(listener, save_dir, options, event_tx, stop_flag).await
+}
+
+/// Занять порт сервера, ещё не принимая соединений. Порт 0 - любой свободный:
+/// фактический адрес - `local_addr()` слушателя. Подключения до `serve` ждут в очереди
+pub async fn bind_server(port: u16, options: &ServerOptions) -> Result<Box<dyn TransportListener>, String> {
+    // Неподдерживаемый формат - ошибка сразу, а не при первом архиве
+    options.extract_options.validate().map_err(|e| e.to_string())?;
+    
+    let bind_addr = std::net::SocketAddr::new(options.bind_addr, port);
+    crate::network::transport::bind_tuned(options.transport_type, bind_addr, options.socket_tuning)
         .await
-        .map_err(|e| format!("Не удалось запустить сервер [{}]: {}", options.transport_type.name(), e))?;
+        .map_err(|e| format!("Не удалось запустить сервер [{}]: {}", options.transport_type.name(), e))
+}
+
+/// Принимать соединения на занятом `bind_server` порту до остановки
+pub async fn serve(
+    mut listener: Box<dyn TransportListener>,
+    save_dir: PathBuf,
+    options: ServerOptions,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+    stop_flag: Arc<AtomicBool>,
+) -> Result<(), String> {
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    options.prepare_dirs(&save_dir)?;
+    if let Some(warning) = options.prepare_temp_dir(&save_dir)? {
+        let _ = event_tx.send(TransferEvent::FileReceived(warning, 0));
+    }
+    
+    if let Some(addr) = options.metrics_addr {
+        start_metrics(addr, &stop_flag, &event_tx).await?;
+    }
+    let audit = AuditLog::start(options.audit_log.as_deref(), AUDIT_LOG_MAX_SIZE, &event_tx);
+    let hook = PostHook::new(&options);
+    if let Some(addr) = options.web_addr {
+        start_web_ui(addr, &save_dir, &options, &stop_flag, &event_tx, &audit, &hook).await?;
+    }
+    // Объявление снимается, когда сервер останавливается
+    let _mdns = options.advertise_mdns.then(|| start_mdns(port, &event_tx));
     
     loop {
         // Проверяем флаг остановки
@@ -84,7 +143,9 @@ pub async fn run_server_with_options_and_stop(
         // Используем timeout для периодической проверки флага остановки
         match listener.accept_timeout(Duration::from_millis(100)).await {
             Ok(Some((stream, addr))) => {
-                let _ = event_tx.send(TransferEvent::Connected(0, format!("{} [{}]", addr, options.transport_type.name())));
+                // О подключении сообщит сессия, когда станет ясно, проверка это или передача
+                let label = format!("{} [{}]", addr, options.transport_type.name());
+                let session = ClientSession::new(addr, label, event_tx.clone(), audit.clone(), hook.clone());
                 
                 let save_dir = save_dir.clone();
                 let options = options.clone();
@@ -92,10 +153,11 @@ pub async fn run_server_with_options_and_stop(
                 let stop_flag = stop_flag.clone();
                 
                 tokio::spawn(async move {
-                    if let Err(e) = handlers::handle_client_transport(stream, save_dir, options, event_tx.clone(), stop_flag).await {
+                    if let Err(e) = handlers::handle_client_transport(stream, save_dir, options, event_tx.clone(), stop_flag, &session).await {
+                        session.audit.error(&session.peer, &e);
                         let _ = event_tx.send(TransferEvent::ConnectionError(0, e));
                     }
-                    let _ = event_tx.send(TransferEvent::Disconnected);
+                    session.close();
                 });
             }
             Ok(None) => {
@@ -109,6 +171,87 @@ pub async fn run_server_with_options_and_stop(
     }
 }
 
+/// Запустить экспорт метрик вместе с сервером
+#[cfg(feature = "metrics")]
+async fn start_metrics(
+    addr: std::net::SocketAddr,
+    stop_flag: &Arc<AtomicBool>,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) -> Result<(), String> {
+    let addr = crate::network::metrics::spawn_exporter(addr, stop_flag.clone()).await?;
+    let _ = event_tx.send(TransferEvent::FileReceived(format!("📊 Метрики: http://{}/metrics", addr), 0));
+    Ok(())
+}
+
+/// Без фичи `metrics` экспорт недоступен - только предупреждение
+#[cfg(not(feature = "metrics"))]
+async fn start_metrics(
+    _addr: std::net::SocketAddr,
+    _stop_flag: &Arc<AtomicBool>,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) -> Result<(), String> {
+    let _ = event_tx.send(TransferEvent::FileReceived(
+        "⚠️ Экспорт метрик недоступен: соберите с --features metrics".to_string(), 0
+    ));
+    Ok(())
+}
+
+/// Запустить веб-страницу загрузки вместе с сервером
+#[cfg(feature = "webui")]
+async fn start_web_ui(
+    addr: std::net::SocketAddr,
+    save_dir: &std::path::Path,
+    options: &ServerOptions,
+    stop_flag: &Arc<AtomicBool>,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+    audit: &AuditLog,
+    hook: &PostHook,
+) -> Result<(), String> {
+    let addr = web::spawn_web_ui(addr, save_dir.to_path_buf(), options.clone(), stop_flag.clone(), event_tx, audit, hook).await?;
+    let _ = event_tx.send(TransferEvent::FileReceived(format!("🌐 Веб-интерфейс: http://{}/", addr), 0));
+    Ok(())
+}
+
+/// Без фичи `webui` веб-страница недоступна - только предупреждение
+#[cfg(not(feature = "webui"))]
+async fn start_web_ui(
+    _addr: std::net::SocketAddr,
+    _save_dir: &std::path::Path,
+    _options: &ServerOptions,
+    _stop_flag: &Arc<AtomicBool>,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+    _audit: &AuditLog,
+    _hook: &PostHook,
+) -> Result<(), String> {
+    let _ = event_tx.send(TransferEvent::FileReceived(
+        "⚠️ Веб-интерфейс недоступен: соберите с --features webui".to_string(), 0
+    ));
+    Ok(())
+}
+
+/// Объявить сервер в mDNS; ошибка не мешает приёму - только предупреждение
+#[cfg(feature = "mdns")]
+fn start_mdns(port: u16, event_tx: &mpsc::UnboundedSender<TransferEvent>) -> Option<crate::network::MdnsAdvertisement> {
+    match crate::network::advertise_mdns(port) {
+        Ok(advertisement) => {
+            let _ = event_tx.send(TransferEvent::FileReceived(format!("📡 mDNS: {}", advertisement.fullname()), 0));
+            Some(advertisement)
+        }
+        Err(e) => {
+            let _ = event_tx.send(TransferEvent::FileReceived(format!("⚠️ {}", e), 0));
+            None
+        }
+    }
+}
+
+/// Без фичи `mdns` объявление недоступно - только предупреждение
+#[cfg(not(feature = "mdns"))]
+fn start_mdns(_port: u16, event_tx: &mpsc::UnboundedSender<TransferEvent>) {
+    let _ = event_tx.send(TransferEvent::FileReceived(
+        "⚠️ Объявление в mDNS недоступно: соберите с --features mdns".to_string(), 0
+    ));
+}
+
 /// Запустить сервер с расширенными опциями (без поддержки остановки)
 pub async fn run_server_with_options(
     port: u16,
@@ -116,25 +259,36 @@ pub async fn run_server_with_options(
     options: ServerOptions,
     event_tx: mpsc::UnboundedSender<TransferEvent>,
 ) -> Result<(), String> {
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", port))
+    let listener = TcpListener::bind((options.bind_addr, port))
         .await
         .map_err(|e| format!("Не удалось запустить сервер: {}", e))?;
+    options.prepare_dirs(&save_dir)?;
+    let audit = AuditLog::start(options.audit_log.as_deref(), AUDIT_LOG_MAX_SIZE, &event_tx);
+    let hook = PostHook::new(&options);
+    // Этот сервер не останавливается, но флаг общий для веб-страницы и обработчиков
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    if let Some(addr) = options.web_addr {
+        start_web_ui(addr, &save_dir, &options, &stop_flag, &event_tx, &audit, &hook).await?;
+    }
+    let _mdns = options.advertise_mdns.then(|| start_mdns(port, &event_tx));
     
     loop {
         match listener.accept().await {
             Ok((stream, addr)) => {
-                let _ = event_tx.send(TransferEvent::Connected(0, addr.to_string()));
+                let session = ClientSession::new(addr.to_string(), addr.to_string(), event_tx.clone(), audit.clone(), hook.clone());
                 stream.set_nodelay(true).ok();
                 
                 let save_dir = save_dir.clone();
                 let options = options.clone();
                 let event_tx = event_tx.clone();
+                let stop_flag = stop_flag.clone();
                 
                 tokio::spawn(async move {
-                    if let Err(e) = handlers::handle_client_tcp(stream, save_dir, options, event_tx.clone()).await {
+                    if let Err(e) = handlers::handle_client_tcp(stream, save_dir, options, event_tx.clone(), stop_flag, &session).await {
+                        session.audit.error(&session.peer, &e);
                         let _ = event_tx.send(TransferEvent::ConnectionError(0, e));
                     }
-                    let _ = event_tx.send(TransferEvent::Disconnected);
+                    session.close();
                 });
             }
             Err(e) => {
@@ -144,3 +298,48 @@ pub async fn run_server_with_options(
     }
 }
 
+
+/// Тестовый получатель на свободном порту `127.0.0.1`
+#[cfg(test)]
+pub(crate) struct TestServer {
+    /// Фактический адрес: `127.0.0.1:<порт>`
+    pub addr: String,
+    pub port: u16,
+    stop_flag: Arc<AtomicBool>,
+    handle: Option<tokio::task::JoinHandle<Result<(), String>>>,
+}
+
+#[cfg(test)]
+impl TestServer {
+    /// Остановить сервер и дождаться его завершения
+    pub async fn stop(mut self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.await;
+        }
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Запустить получатель для теста. Порт занят до возврата, поэтому ждать
+/// готовности не нужно: ранние подключения ждут в очереди
+#[cfg(test)]
+pub(crate) async fn spawn_test_server(
+    save_dir: &std::path::Path,
+    options: ServerOptions,
+) -> (TestServer, mpsc::UnboundedReceiver<TransferEvent>) {
+    let options = ServerOptions { bind_addr: std::net::Ipv4Addr::LOCALHOST.into(), ..options };
+    let listener = bind_server(0, &options).await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (event_tx, event_rx) = mpsc::unbounded_channel();
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let handle = tokio::spawn(serve(listener, save_dir.to_path_buf(), options, event_tx, stop_flag.clone()));
+    let server = TestServer { addr: format!("127.0.0.1:{}", port), port, stop_flag, handle: Some(handle) };
+    (server, event_rx)
+}
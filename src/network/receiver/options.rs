@@ -1,6 +1,25 @@
 //! Опции сервера для приёма файлов
 
-use crate::network::transport::TransportType;
+use crate::extract::{ArchiveType, PathLimits};
+use crate::network::transport::{SocketTuning, TransportType};
+use crate::protocol::{FEATURE_FILE_META, FEATURE_VERIFY_INTEGRITY, SUPPORTED_FEATURES};
+use crate::utils::{sanitize_filename, TargetOs};
+use std::ffi::{OsStr, OsString};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Адрес приёма по умолчанию: все интерфейсы IPv4
+pub const DEFAULT_BIND_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+/// Сколько файлов по умолчанию можно удалить одним запросом синхронизации
+pub const DEFAULT_MAX_DELETE_FILES: usize = 1000;
+
+/// Сколько по умолчанию ждать завершения post-hook
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Сколько post-hook по умолчанию выполняется одновременно
+pub const DEFAULT_MAX_HOOKS: usize = 4;
 
 /// Опции автораспаковки
 #[derive(Clone, Debug, Default)]
@@ -8,25 +27,153 @@ pub struct ExtractOptions {
     pub tar_lz4: bool,
     pub tar_zst: bool,
     pub lz4: bool,
+    /// Одиночный файл .zst (не tar)
+    pub zst: bool,
     pub tar: bool,
     pub zip: bool,
     pub rar: bool,
+    /// Отбросить N ведущих папок у записей архива (как `tar --strip-components`)
+    pub strip_components: usize,
 }
 
 impl ExtractOptions {
     pub fn any_enabled(&self) -> bool {
-        self.tar_lz4 || self.tar_zst || self.lz4 || self.tar || self.zip || self.rar
+        self.tar_lz4 || self.tar_zst || self.lz4 || self.zst || self.tar || self.zip || self.rar
+    }
+    
+    /// Проверить, что все включённые форматы поддерживаются этой сборкой.
+    /// Вызывается при запуске сервера, а не при получении первого архива
+    pub fn validate(&self) -> Result<(), ExtractConfigError> {
+        let requested = [
+            (self.tar_lz4, ArchiveType::TarLz4),
+            (self.tar_zst, ArchiveType::TarZst),
+            (self.lz4, ArchiveType::Lz4),
+            (self.zst, ArchiveType::Zst),
+            (self.tar, ArchiveType::Tar),
+            (self.zip, ArchiveType::Zip),
+            (self.rar, ArchiveType::Rar),
+        ];
+        if let Some(&(_, archive_type)) = requested.iter().find(|(enabled, t)| *enabled && !t.is_supported()) {
+            return Err(ExtractConfigError::UnsupportedFormat(archive_type));
+        }
+        if self.strip_components > 0 && !self.any_enabled() {
+            return Err(ExtractConfigError::StripWithoutExtraction);
+        }
+        Ok(())
+    }
+}
+
+/// Ошибка настройки автораспаковки
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtractConfigError {
+    /// Формат включён, но эта сборка не умеет его распаковывать
+    UnsupportedFormat(ArchiveType),
+    /// `strip_components` без единого включённого формата
+    StripWithoutExtraction,
+}
+
+impl std::fmt::Display for ExtractConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedFormat(ArchiveType::Rar) => write!(
+                f,
+                "Автораспаковка rar не поддерживается: выключите её и распакуйте вручную (unrar x archive.rar)"
+            ),
+            Self::UnsupportedFormat(archive_type) => write!(
+                f,
+                "Автораспаковка {} не поддерживается этой сборкой: выключите её",
+                archive_type.name()
+            ),
+            Self::StripWithoutExtraction => write!(f, "strip_components действует только с автораспаковкой"),
+        }
     }
 }
 
+impl std::error::Error for ExtractConfigError {}
+
 /// Опции сервера
 #[derive(Clone, Debug)]
 pub struct ServerOptions {
     pub extract_options: ExtractOptions,
     pub enable_resume: bool,
     pub transport_type: TransportType,
+    /// Интерфейс, на котором слушает сервер (`0.0.0.0` - все IPv4, `::` - все).
+    /// Например, только адрес VPN, чтобы не принимать из публичной сети
+    pub bind_addr: IpAddr,
     /// Сохранять архив при потоковой распаковке (для возможности резюме)
     pub save_archive_for_resume: bool,
+    /// Папка для недокачанных архивов (None - рядом с файлами в save_dir)
+    pub temp_dir: Option<PathBuf>,
+    /// Куда распаковывать архивы (None - в save_dir, вместе с обычными файлами)
+    pub extract_dir: Option<PathBuf>,
+    /// Команда после приёма файла или распаковки архива: программа и аргументы,
+    /// `{path}` заменяется на путь, `{dir}` - на его папку (None - выключено).
+    ///
+    /// Внимание: команда запускается с правами получателя на файлах, пришедших
+    /// по сети. Не подставляйте `{path}` в shell-строку (`sh -c`) - имя файла
+    /// выбирает отправитель
+    pub post_hook: Option<Vec<String>>,
+    /// Сколько ждать завершения post-hook (None - без ограничения): зависшая
+    /// команда убивается, приходит `HookError`
+    pub hook_timeout: Option<Duration>,
+    /// Сколько post-hook выполняется одновременно: остальные ждут очереди,
+    /// чтобы поток мелких файлов не запустил тысячи процессов
+    pub max_hooks: usize,
+    /// Лимиты вложенности и длины путей при распаковке: записи сверх лимитов
+    /// пропускаются с событием `ExtractionEntrySkipped`
+    pub path_limits: PathLimits,
+    /// Адрес HTTP-экспорта метрик Prometheus (`GET /metrics`, фича `metrics`)
+    pub metrics_addr: Option<SocketAddr>,
+    /// Настройки TCP-сокета для принятых соединений
+    pub socket_tuning: SocketTuning,
+    /// Сколько ждать данных от отправителя (None - без ограничения): замолчавший
+    /// посреди передачи отправитель отключается, потоковая распаковка прерывается
+    pub timeout: Option<Duration>,
+    /// Шаблон имени сохраняемого файла: `{name}` - исходное имя, `{sender}` - IP
+    /// отправителя, `{ts}` - дата приёма (YYYY-MM-DD, UTC). Меняется только имя,
+    /// папки остаются; имя детерминировано в пределах дня, поэтому резюме работает.
+    /// Архивы с потоковой распаковкой не переименовываются
+    pub name_template: Option<String>,
+    /// Журнал аудита: подключения, принятые файлы и отказы строками JSON.
+    /// Пишется в фоне, при 10 MB ротируется в `<имя>.1` (None - выключен)
+    pub audit_log: Option<PathBuf>,
+    /// Приводить имена к правилам Windows (`report:v2.txt` -> `report_v2.txt`,
+    /// `aux` -> `aux_`) с событием `FileRenamed`. По умолчанию включено на Windows
+    pub sanitize_names: bool,
+    /// Ретрансляция: каждый принятый файл сразу пересылается этим получателям
+    /// (`IP` или `IP:порт`, без порта - `DEFAULT_PORT`) тем же транспортом.
+    /// Файл по-прежнему сохраняется здесь; докачка и распаковка на лету для
+    /// таких соединений выключены - архивы распаковываются после приёма
+    pub relay_to: Vec<String>,
+    /// Адрес веб-страницы загрузки (фича `webui`): файлы, перетащенные в браузере,
+    /// сохраняются как при обычной передаче, включая распаковку архивов
+    pub web_addr: Option<SocketAddr>,
+    /// Имена, под которыми открывают страницу загрузки, кроме IP-адресов, `localhost`
+    /// и имени машины. Запрос с другим `Host` отклоняется (защита от DNS rebinding)
+    pub web_hosts: Vec<String>,
+    /// Сверять SHA-256 из `FileHash` с принятым и удалять повреждённые копии
+    /// (отправитель получает `ChecksumMismatch`). Хэш шлёт только отправитель с
+    /// `SendOptions::verify_integrity`; архивы с потоковой распаковкой не проверяются,
+    /// о чём отправитель узнаёт
+    pub verify_integrity: bool,
+    /// Не принимать файлы с этими расширениями (`exe`, `.msi`, `tar.gz`; без учёта регистра).
+    /// Отказ - до записи на диск, отправитель получает причину
+    pub blocked_extensions: Vec<String>,
+    /// Не принимать файлы больше этого размера, байт
+    pub max_file_size: Option<u64>,
+    /// Объявлять сервер в mDNS как `_toolza._tcp.local` (фича `mdns`), чтобы
+    /// отправители находили его без сканирования подсети
+    pub advertise_mdns: bool,
+    /// Записывать на принятые файлы расширенные атрибуты `user.*` от отправителя
+    /// (`Message::FileMeta`, в том числе из PAX-заголовков архивов). Без этого
+    /// получатель не подтверждает `FEATURE_FILE_META`, и отправитель их не шлёт
+    pub accept_xattrs: bool,
+    /// Выполнять запросы на удаление от `send --sync --delete` (`Message::DeleteFiles`).
+    /// Без этого запрос отклоняется и ничего не удаляется
+    pub allow_delete: bool,
+    /// Сколько файлов можно удалить одним запросом: больший список отклоняется
+    /// целиком, чтобы ошибка отправителя не вычистила всю папку приёма
+    pub max_delete_files: usize,
 }
 
 impl Default for ServerOptions {
@@ -35,12 +182,158 @@ impl Default for ServerOptions {
             extract_options: ExtractOptions::default(),
             enable_resume: true,
             transport_type: TransportType::default(),
+            bind_addr: DEFAULT_BIND_ADDR,
             save_archive_for_resume: false, // По умолчанию чистая потоковая распаковка
+            temp_dir: None,
+            extract_dir: None,
+            post_hook: None,
+            hook_timeout: Some(DEFAULT_HOOK_TIMEOUT),
+            max_hooks: DEFAULT_MAX_HOOKS,
+            path_limits: PathLimits::default(),
+            metrics_addr: None,
+            socket_tuning: SocketTuning::default(),
+            timeout: None,
+            name_template: None,
+            audit_log: None,
+            sanitize_names: cfg!(windows),
+            relay_to: Vec::new(),
+            web_addr: None,
+            web_hosts: Vec::new(),
+            verify_integrity: false,
+            blocked_extensions: Vec::new(),
+            max_file_size: None,
+            advertise_mdns: false,
+            accept_xattrs: false,
+            allow_delete: false,
+            max_delete_files: DEFAULT_MAX_DELETE_FILES,
         }
     }
 }
 
 impl ServerOptions {
+    /// Построитель с настройками по умолчанию
+    pub fn builder() -> ServerOptionsBuilder {
+        ServerOptionsBuilder::default()
+    }
+    
+    /// Проверить согласованность опций
+    pub fn validate(&self) -> Result<(), String> {
+        if self.post_hook.as_ref().is_some_and(|hook| hook.is_empty()) {
+            return Err("Пустая команда post-hook".to_string());
+        }
+        if self.hook_timeout.is_some_and(|t| t.is_zero()) {
+            return Err("Таймаут post-hook должен быть больше нуля".to_string());
+        }
+        if self.max_hooks == 0 {
+            return Err("Нужно хотя бы одно место для post-hook".to_string());
+        }
+        if self.timeout.is_some_and(|t| t.is_zero()) {
+            return Err("Таймаут ожидания данных должен быть больше нуля".to_string());
+        }
+        if self.name_template.as_ref().is_some_and(|t| t.trim().is_empty()) {
+            return Err("Пустой шаблон имени файла".to_string());
+        }
+        self.extract_options.validate().map_err(|e| e.to_string())
+    }
+    
+    /// Возможности (`FEATURE_*`), которые этот получатель подтверждает в HelloAck
+    pub fn supported_features(&self) -> u32 {
+        let mut features = SUPPORTED_FEATURES;
+        if !self.accept_xattrs {
+            features &= !FEATURE_FILE_META;
+        }
+        if !self.verify_integrity {
+            features &= !FEATURE_VERIFY_INTEGRITY;
+        }
+        features
+    }
+    
+    /// Лимиты путей для распаковки с учётом `strip_components`
+    pub fn extract_limits(&self) -> PathLimits {
+        PathLimits {
+            strip_components: self.extract_options.strip_components,
+            ..self.path_limits
+        }
+    }
+    
+    /// Папка, куда пишутся сырые архивы для резюме
+    pub fn archive_dir(&self, save_dir: &Path) -> PathBuf {
+        self.temp_dir.clone().unwrap_or_else(|| save_dir.to_path_buf())
+    }
+    
+    /// Папка, куда распаковываются архивы
+    pub fn extract_output_dir(&self, save_dir: &Path) -> PathBuf {
+        self.extract_dir.clone().unwrap_or_else(|| save_dir.to_path_buf())
+    }
+    
+    /// Создать папку сохранения и папку распаковки и проверить, что в них можно писать.
+    /// Вызывается при запуске сервера, а не при первом файле
+    pub fn prepare_dirs(&self, save_dir: &Path) -> Result<(), String> {
+        check_writable_dir(save_dir)?;
+        if let Some(extract_dir) = &self.extract_dir {
+            check_writable_dir(extract_dir)?;
+        }
+        Ok(())
+    }
+    
+    /// Подготовить временную папку: создать её и проверить, что она
+    /// на той же файловой системе, что и save_dir.
+    ///
+    /// Возвращает предупреждение, если папки на разных устройствах.
+    pub fn prepare_temp_dir(&self, save_dir: &Path) -> Result<Option<String>, String> {
+        let Some(temp_dir) = &self.temp_dir else {
+            return Ok(None);
+        };
+        
+        std::fs::create_dir_all(temp_dir)
+            .map_err(|e| format!("Не удалось создать временную папку {}: {}", temp_dir.display(), e))?;
+        
+        if same_device(temp_dir, save_dir) == Some(false) {
+            return Ok(Some(format!(
+                "⚠️ Временная папка {} на другом диске, чем {} - перенос файлов не будет атомарным",
+                temp_dir.display(),
+                save_dir.display()
+            )));
+        }
+        
+        Ok(None)
+    }
+    
+    /// Относительный путь (через `/`) с применённым `name_template`
+    pub fn templated_name(&self, filename: &str, sender: &str) -> String {
+        let Some(template) = &self.name_template else {
+            return filename.to_string();
+        };
+        let (dir, name) = match filename.rsplit_once('/') {
+            Some((dir, name)) => (Some(dir), name),
+            None => (None, filename),
+        };
+        let name = render_name_template(template, OsStr::new(name), sender).to_string_lossy().into_owned();
+        match dir {
+            Some(dir) => format!("{}/{}", dir, name),
+            None => name,
+        }
+    }
+    
+    /// Относительный путь (через `/`), допустимый на Windows, если включено `sanitize_names`
+    pub fn safe_name(&self, name: &str) -> String {
+        if !self.sanitize_names {
+            return name.to_string();
+        }
+        name.split('/')
+            .map(|part| sanitize_filename(part, TargetOs::Windows))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+    
+    /// Путь из RawFileName с применённым `name_template` (имя не в UTF-8)
+    pub fn templated_path(&self, path: &Path, sender: &str) -> PathBuf {
+        match (&self.name_template, path.file_name()) {
+            (Some(template), Some(name)) => path.with_file_name(render_name_template(template, name, sender)),
+            _ => path.to_path_buf(),
+        }
+    }
+    
     /// Проверить, нужно ли распаковывать файл
     pub fn should_extract(&self, filename: &str) -> bool {
         let archive_type = crate::extract::ArchiveType::from_filename(filename);
@@ -48,11 +341,449 @@ impl ServerOptions {
             crate::extract::ArchiveType::TarLz4 => self.extract_options.tar_lz4,
             crate::extract::ArchiveType::TarZst => self.extract_options.tar_zst,
             crate::extract::ArchiveType::Lz4 => self.extract_options.lz4,
+            crate::extract::ArchiveType::Zst => self.extract_options.zst,
             crate::extract::ArchiveType::Tar | crate::extract::ArchiveType::TarGz => self.extract_options.tar,
             crate::extract::ArchiveType::Zip => self.extract_options.zip,
             crate::extract::ArchiveType::Rar => self.extract_options.rar,
             _ => false,
         }
     }
+    
+    /// Причина отказа в приёме файла (`blocked_extensions`, `max_file_size`), None - принимаем
+    pub fn reject_reason(&self, filename: &str, size: u64) -> Option<String> {
+        let name = filename.rsplit(['/', '\\']).next().unwrap_or(filename).to_lowercase();
+        let blocked = self.blocked_extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .find(|ext| !ext.is_empty() && name.ends_with(&format!(".{}", ext)));
+        if let Some(ext) = blocked {
+            return Some(format!("файлы .{} не принимаются", ext));
+        }
+        match self.max_file_size {
+            Some(max) if size > max => Some(format!("файл больше {}", crate::utils::format_size(max))),
+            _ => None,
+        }
+    }
 }
 
+/// Построитель `ServerOptions`: начинает с `Default`, проверяет опции в `build()`
+#[derive(Clone, Debug, Default)]
+pub struct ServerOptionsBuilder {
+    options: ServerOptions,
+}
+
+impl ServerOptionsBuilder {
+    /// Все опции автораспаковки разом
+    pub fn extract_options(mut self, extract_options: ExtractOptions) -> Self {
+        self.options.extract_options = extract_options;
+        self
+    }
+    
+    /// Распаковывать .tar.lz4
+    pub fn auto_extract_tar_lz4(mut self, enabled: bool) -> Self {
+        self.options.extract_options.tar_lz4 = enabled;
+        self
+    }
+    
+    /// Распаковывать .tar.zst
+    pub fn auto_extract_tar_zst(mut self, enabled: bool) -> Self {
+        self.options.extract_options.tar_zst = enabled;
+        self
+    }
+    
+    /// Распаковывать .lz4
+    pub fn auto_extract_lz4(mut self, enabled: bool) -> Self {
+        self.options.extract_options.lz4 = enabled;
+        self
+    }
+    
+    /// Распаковывать одиночные .zst
+    pub fn auto_extract_zst(mut self, enabled: bool) -> Self {
+        self.options.extract_options.zst = enabled;
+        self
+    }
+    
+    /// Распаковывать .tar и .tar.gz
+    pub fn auto_extract_tar(mut self, enabled: bool) -> Self {
+        self.options.extract_options.tar = enabled;
+        self
+    }
+    
+    /// Распаковывать .zip
+    pub fn auto_extract_zip(mut self, enabled: bool) -> Self {
+        self.options.extract_options.zip = enabled;
+        self
+    }
+    
+    /// Распаковывать .rar
+    pub fn auto_extract_rar(mut self, enabled: bool) -> Self {
+        self.options.extract_options.rar = enabled;
+        self
+    }
+    
+    /// Отбросить N ведущих папок у записей архива
+    pub fn strip_components(mut self, count: usize) -> Self {
+        self.options.extract_options.strip_components = count;
+        self
+    }
+    
+    pub fn enable_resume(mut self, enabled: bool) -> Self {
+        self.options.enable_resume = enabled;
+        self
+    }
+    
+    pub fn transport(mut self, transport_type: TransportType) -> Self {
+        self.options.transport_type = transport_type;
+        self
+    }
+    
+    /// Сохранять архив при потоковой распаковке (для резюме)
+    pub fn save_archive_for_resume(mut self, enabled: bool) -> Self {
+        self.options.save_archive_for_resume = enabled;
+        self
+    }
+    
+    /// Папка для недокачанных архивов; включает `save_archive_for_resume`
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.temp_dir = Some(dir.into());
+        self.options.save_archive_for_resume = true;
+        self
+    }
+    
+    /// Распаковывать архивы в отдельную папку
+    pub fn extract_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.extract_dir = Some(dir.into());
+        self
+    }
+    
+    /// Команда после приёма: программа и аргументы (см. `ServerOptions::post_hook`)
+    pub fn post_hook(mut self, command: Vec<String>) -> Self {
+        self.options.post_hook = Some(command);
+        self
+    }
+    
+    /// Убивать post-hook, который не завершился за `timeout`
+    pub fn hook_timeout(mut self, timeout: Duration) -> Self {
+        self.options.hook_timeout = Some(timeout);
+        self
+    }
+    
+    /// Сколько post-hook выполнять одновременно
+    pub fn max_hooks(mut self, count: usize) -> Self {
+        self.options.max_hooks = count;
+        self
+    }
+    
+    pub fn path_limits(mut self, path_limits: PathLimits) -> Self {
+        self.options.path_limits = path_limits;
+        self
+    }
+    
+    /// Адрес экспорта метрик Prometheus
+    pub fn metrics_addr(mut self, addr: SocketAddr) -> Self {
+        self.options.metrics_addr = Some(addr);
+        self
+    }
+    
+    pub fn socket_tuning(mut self, socket_tuning: SocketTuning) -> Self {
+        self.options.socket_tuning = socket_tuning;
+        self
+    }
+    
+    /// Отключать отправителя, который молчит дольше `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+    
+    /// Слушать только на этом интерфейсе
+    pub fn bind_addr(mut self, addr: IpAddr) -> Self {
+        self.options.bind_addr = addr;
+        self
+    }
+    
+    /// Шаблон имени сохраняемого файла (см. `ServerOptions::name_template`)
+    pub fn name_template(mut self, template: impl Into<String>) -> Self {
+        self.options.name_template = Some(template.into());
+        self
+    }
+    
+    /// Приводить имена файлов к правилам Windows
+    pub fn sanitize_names(mut self, enabled: bool) -> Self {
+        self.options.sanitize_names = enabled;
+        self
+    }
+    
+    /// Писать журнал аудита в этот файл
+    pub fn audit_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.audit_log = Some(path.into());
+        self
+    }
+    
+    /// Пересылать принятые файлы этим получателям
+    pub fn relay_to(mut self, addrs: Vec<String>) -> Self {
+        self.options.relay_to = addrs;
+        self
+    }
+    
+    /// Адрес веб-страницы загрузки
+    pub fn web_addr(mut self, addr: SocketAddr) -> Self {
+        self.options.web_addr = Some(addr);
+        self
+    }
+    
+    /// Дополнительные имена страницы загрузки (например, `files.lan`)
+    pub fn web_hosts(mut self, names: Vec<String>) -> Self {
+        self.options.web_hosts = names;
+        self
+    }
+    
+    /// Проверять SHA-256 принятых файлов
+    pub fn verify_integrity(mut self, enabled: bool) -> Self {
+        self.options.verify_integrity = enabled;
+        self
+    }
+    
+    /// Не принимать файлы с этими расширениями
+    pub fn blocked_extensions(mut self, extensions: Vec<String>) -> Self {
+        self.options.blocked_extensions = extensions;
+        self
+    }
+    
+    /// Не принимать файлы больше `size` байт
+    pub fn max_file_size(mut self, size: u64) -> Self {
+        self.options.max_file_size = Some(size);
+        self
+    }
+    
+    /// Объявлять сервер в mDNS
+    pub fn advertise_mdns(mut self, enabled: bool) -> Self {
+        self.options.advertise_mdns = enabled;
+        self
+    }
+    
+    /// Записывать расширенные атрибуты `user.*` от отправителя
+    pub fn accept_xattrs(mut self, enabled: bool) -> Self {
+        self.options.accept_xattrs = enabled;
+        self
+    }
+    
+    /// Выполнять удаление файлов по запросу синхронизации
+    pub fn allow_delete(mut self, enabled: bool) -> Self {
+        self.options.allow_delete = enabled;
+        self
+    }
+    
+    /// Не больше `count` удалений за запрос
+    pub fn max_delete_files(mut self, count: usize) -> Self {
+        self.options.max_delete_files = count;
+        self
+    }
+    
+    /// Собрать опции, проверив их согласованность
+    pub fn build(self) -> Result<ServerOptions, String> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
+/// Имя файла по шаблону. Разделители путей из шаблона и адреса заменяются на `_`,
+/// чтобы имя не вышло за пределы папки; `.`/`..`/пустое имя - исходное имя
+fn render_name_template(template: &str, name: &OsStr, sender: &str) -> OsString {
+    // IP без порта (порт меняется от подключения к подключению), `:` IPv6 недопустим в Windows
+    let sender = sender.parse::<SocketAddr>()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|_| sender.to_string())
+        .replace(':', "-");
+    let date = crate::history::current_date();
+    
+    let mut rendered = OsString::new();
+    for (i, part) in template.split("{name}").enumerate() {
+        if i > 0 {
+            rendered.push(name);
+        }
+        let part = part.replace("{sender}", &sender).replace("{ts}", &date);
+        rendered.push(part.replace(['/', '\\'], "_"));
+    }
+    
+    if rendered.is_empty() || rendered == "." || rendered == ".." {
+        return name.to_os_string();
+    }
+    rendered
+}
+
+/// Создать папку (если её нет) и проверить запись пробным файлом
+fn check_writable_dir(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Не удалось создать папку {}: {}", dir.display(), e))?;
+    let probe = dir.join(".toolza-write-test");
+    std::fs::write(&probe, b"")
+        .map_err(|e| format!("Нет прав на запись в {}: {}", dir.display(), e))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Находятся ли два пути на одном устройстве (None - определить нельзя)
+#[cfg(unix)]
+fn same_device(a: &Path, b: &Path) -> Option<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let a = std::fs::metadata(a).ok()?;
+    let b = std::fs::metadata(b).ok()?;
+    Some(a.dev() == b.dev())
+}
+
+#[cfg(not(unix))]
+fn same_device(_a: &Path, _b: &Path) -> Option<bool> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_archive_dir_defaults_to_save_dir() {
+        let options = ServerOptions::default();
+        assert_eq!(options.archive_dir(Path::new("/data")), PathBuf::from("/data"));
+        
+        let options = ServerOptions {
+            temp_dir: Some(PathBuf::from("/data/.tmp")),
+            ..Default::default()
+        };
+        assert_eq!(options.archive_dir(Path::new("/data")), PathBuf::from("/data/.tmp"));
+    }
+    
+    #[test]
+    fn test_prepare_temp_dir_creates_folder() {
+        let save_dir = tempfile::tempdir().unwrap();
+        let temp_dir = save_dir.path().join("partial");
+        let options = ServerOptions {
+            temp_dir: Some(temp_dir.clone()),
+            ..Default::default()
+        };
+        
+        // Та же файловая система - без предупреждения
+        assert_eq!(options.prepare_temp_dir(save_dir.path()), Ok(None));
+        assert!(temp_dir.is_dir());
+    }
+    
+    #[test]
+    fn test_prepare_dirs() {
+        let save_dir = tempfile::tempdir().unwrap();
+        let extract_dir = save_dir.path().join("unpacked");
+        let options = ServerOptions {
+            extract_dir: Some(extract_dir.clone()),
+            ..Default::default()
+        };
+        assert_eq!(options.extract_output_dir(save_dir.path()), extract_dir);
+        assert_eq!(options.prepare_dirs(save_dir.path()), Ok(()));
+        assert!(extract_dir.is_dir());
+        assert_eq!(std::fs::read_dir(&extract_dir).unwrap().count(), 0);
+        
+        // Папку не создать: на её месте файл
+        let blocker = save_dir.path().join("file");
+        std::fs::write(&blocker, b"").unwrap();
+        let options = ServerOptions {
+            extract_dir: Some(blocker.join("unpacked")),
+            ..Default::default()
+        };
+        assert!(options.prepare_dirs(save_dir.path()).is_err());
+        assert_eq!(ServerOptions::default().extract_output_dir(save_dir.path()), save_dir.path());
+    }
+    
+    #[test]
+    fn test_name_template() {
+        let options = ServerOptions {
+            name_template: Some("{sender}_{name}".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(options.templated_name("docs/report.pdf", "10.0.0.5:50123"), "docs/10.0.0.5_report.pdf");
+        assert_eq!(options.templated_name("report.pdf", "[::1]:50123"), "--1_report.pdf");
+        assert_eq!(
+            options.templated_path(Path::new("docs/report.pdf"), "10.0.0.5:1"),
+            Path::new("docs").join("10.0.0.5_report.pdf"),
+        );
+        
+        // Дата меняется раз в день - имя при повторном подключении то же
+        let dated = ServerOptions { name_template: Some("{ts}-{name}".to_string()), ..Default::default() };
+        assert_eq!(dated.templated_name("a.txt", "x"), format!("{}-a.txt", crate::history::current_date()));
+        
+        assert_eq!(ServerOptions::default().templated_name("docs/a.txt", "x"), "docs/a.txt");
+    }
+    
+    #[test]
+    fn test_safe_name() {
+        let options = ServerOptions { sanitize_names: true, ..Default::default() };
+        assert_eq!(options.safe_name("docs/aux/report:v2.txt"), "docs/aux_/report_v2.txt");
+        assert_eq!(options.safe_name("docs/a.txt"), "docs/a.txt");
+        
+        let options = ServerOptions { sanitize_names: false, ..Default::default() };
+        assert_eq!(options.safe_name("docs/report:v2.txt"), "docs/report:v2.txt");
+    }
+    
+    #[test]
+    fn test_name_template_cannot_escape() {
+        let options = ServerOptions {
+            name_template: Some("../../{name}".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(options.templated_name("a.txt", "x"), ".._.._a.txt");
+        
+        let options = ServerOptions { name_template: Some("..".to_string()), ..Default::default() };
+        assert_eq!(options.templated_name("a.txt", "x"), "a.txt");
+    }
+    
+    #[test]
+    fn test_builder() {
+        let options = ServerOptions::builder()
+            .auto_extract_tar_lz4(true)
+            .strip_components(1)
+            .temp_dir("/data/.tmp")
+            .name_template("{sender}_{name}")
+            .build()
+            .unwrap();
+        assert!(options.extract_options.tar_lz4);
+        assert!(!options.extract_options.zip);
+        assert_eq!(options.extract_options.strip_components, 1);
+        assert!(options.save_archive_for_resume);
+        assert!(options.enable_resume);
+        assert_eq!(options.temp_dir, Some(PathBuf::from("/data/.tmp")));
+        
+        assert!(ServerOptions::builder().strip_components(1).build().is_err());
+        assert!(ServerOptions::builder().post_hook(Vec::new()).build().is_err());
+        assert!(ServerOptions::builder().hook_timeout(Duration::ZERO).build().is_err());
+        assert!(ServerOptions::builder().max_hooks(0).build().is_err());
+        assert!(ServerOptions::builder().name_template(" ").build().is_err());
+    }
+    
+    #[test]
+    fn test_reject_reason() {
+        let options = ServerOptions {
+            blocked_extensions: vec!["exe".to_string(), ".MSI".to_string(), "tar.gz".to_string()],
+            max_file_size: Some(1024),
+            ..Default::default()
+        };
+        assert_eq!(options.reject_reason("setup.EXE", 10).as_deref(), Some("файлы .exe не принимаются"));
+        assert!(options.reject_reason("tools/install.msi", 10).is_some());
+        assert!(options.reject_reason("backup.tar.gz", 10).is_some());
+        assert!(options.reject_reason("big.bin", 1025).is_some());
+        
+        assert_eq!(options.reject_reason("notes.txt", 1024), None);
+        assert_eq!(options.reject_reason("exe", 10), None); // Имя, а не расширение
+        assert_eq!(options.reject_reason("exe.d/readme", 10), None);
+        assert_eq!(ServerOptions::default().reject_reason("setup.exe", u64::MAX), None);
+    }
+    
+    #[test]
+    fn test_extract_options_validate() {
+        let supported = ExtractOptions { tar_lz4: true, zip: true, strip_components: 1, ..Default::default() };
+        assert_eq!(supported.validate(), Ok(()));
+        
+        let rar = ExtractOptions { tar_lz4: true, rar: true, ..Default::default() };
+        assert_eq!(rar.validate(), Err(ExtractConfigError::UnsupportedFormat(ArchiveType::Rar)));
+        assert!(rar.validate().unwrap_err().to_string().contains("unrar"));
+        
+        let strip = ExtractOptions { strip_components: 2, ..Default::default() };
+        assert_eq!(strip.validate(), Err(ExtractConfigError::StripWithoutExtraction));
+    }
+}
@@ -0,0 +1,506 @@
+//! Ретрансляция: получатель пересылает принятые файлы дальше (`ServerOptions::relay_to`)
+//!
+//! Куски `FileChunk` уходят нижестоящим получателям как пришли (сжатые - сжатыми),
+//! диск повторно не читается. У каждого нижестоящего свой поток записи с
+//! ограниченной очередью: медленный получатель притормаживает приём, а зависший
+//! дольше `RELAY_STALL_TIMEOUT` отключается, чтобы не держать остальных.
+//!
+//! Нижестоящему сообщается, сколько узлов файл уже прошёл (`Message::RelayHops`):
+//! после `MAX_RELAY_HOPS` дальше не пересылается, и петля в `relay_to` затухает
+
+use crate::network::events::TransferEvent;
+use crate::network::transport::{self, SocketTuning, TransportStream, TransportType, DEFAULT_CONNECT_TIMEOUT};
+use crate::network::sender::send_hello;
+use crate::protocol::{Message, DEFAULT_PORT, FEATURE_FILE_CODEC, FEATURE_FILE_LINK, FEATURE_FILE_META, FEATURE_NO_EXTRACT, FEATURE_RAW_FILE_NAME, FEATURE_RELAY_HOPS, FEATURE_VERIFY_INTEGRITY};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Сколько сообщений может ждать отправки одному нижестоящему получателю
+const RELAY_QUEUE_LEN: usize = 16;
+
+/// Сколько ждать места в очереди нижестоящего, прежде чем его отключить
+const RELAY_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Сколько узлов может пройти файл: дальше ретранслятор его не пересылает
+pub(crate) const MAX_RELAY_HOPS: u32 = 8;
+
+/// Размер куска при передаче копии с диска ретранслятора
+const RELAY_COPY_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Что уходит нижестоящему
+enum Outgoing {
+    Message(Message),
+    /// FileLink; если у нижестоящего нет `from`, файл `to` передаётся с диска ретранслятора
+    Link { from: String, to: String, path: PathBuf },
+}
+
+/// Нижестоящий получатель
+struct Downstream {
+    addr: String,
+    tx: mpsc::Sender<Outgoing>,
+    /// Поток записи; возвращает число принятых получателем файлов
+    task: JoinHandle<Result<usize, String>>,
+}
+
+/// Пересылка принятых файлов на `relay_to` в пределах одного входящего соединения
+pub(crate) struct Relay {
+    downstreams: Vec<Downstream>,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+}
+
+impl Relay {
+    /// Подключиться ко всем адресам; недоступные пропускаются с предупреждением.
+    /// `session_features` - согласованное с отправителем: о том же (с атрибутами,
+    /// с проверкой SHA-256) просим нижестоящих. `hops` - сколько узлов файлы уже прошли
+    pub(crate) async fn connect(
+        addrs: &[String],
+        transport_type: TransportType,
+        session_features: u32,
+        hops: u32,
+        event_tx: &mpsc::UnboundedSender<TransferEvent>,
+    ) -> Self {
+        let mut downstreams = Vec::with_capacity(addrs.len());
+        if hops >= MAX_RELAY_HOPS {
+            let _ = event_tx.send(TransferEvent::FileReceived(
+                format!("⚠️ Ретрансляция пропущена: файлы уже прошли {} узлов (петля в --relay-to?)", hops), 0
+            ));
+            return Self { downstreams, event_tx: event_tx.clone() };
+        }
+        // Куски уходят как пришли, поэтому zstd нужен и нижестоящим
+        let features = FEATURE_FILE_CODEC | FEATURE_RELAY_HOPS | FEATURE_FILE_LINK | FEATURE_RAW_FILE_NAME | FEATURE_NO_EXTRACT
+            | session_features & (FEATURE_FILE_META | FEATURE_VERIFY_INTEGRITY);
+        for addr in addrs {
+            let addr = crate::network::parse_target_addr(addr, DEFAULT_PORT).unwrap_or_else(|_| addr.clone());
+            match connect_downstream(transport_type, &addr, features, hops + 1).await {
+                Ok((stream, features)) => {
+                    let (tx, rx) = mpsc::channel(RELAY_QUEUE_LEN);
+                    let task = tokio::spawn(run_downstream(stream, features, rx));
+                    downstreams.push(Downstream { addr, tx, task });
+                }
+                Err(e) => {
+                    let _ = event_tx.send(TransferEvent::FileReceived(
+                        format!("⚠️ Ретрансляция на {} недоступна: {}", addr, e), 0
+                    ));
+                }
+            }
+        }
+        Self { downstreams, event_tx: event_tx.clone() }
+    }
+    
+    /// Переслать сообщение всем нижестоящим
+    pub(crate) async fn send(&mut self, msg: &Message) {
+        self.send_outgoing(|| Outgoing::Message(msg.clone())).await;
+    }
+    
+    /// Переслать FileLink: `path` - уже готовая копия на ретрансляторе
+    pub(crate) async fn send_link(&mut self, from: &str, to: &str, path: PathBuf) {
+        self.send_outgoing(|| Outgoing::Link { from: from.to_string(), to: to.to_string(), path: path.clone() }).await;
+    }
+    
+    /// Положить сообщение в очередь каждого нижестоящего. Ждёт места в заполненных
+    /// (так медленный получатель сдерживает приём), но общий срок - один
+    /// `RELAY_STALL_TIMEOUT` на всех; отвалившиеся и зависшие отключаются
+    async fn send_outgoing(&mut self, item: impl Fn() -> Outgoing) {
+        let mut failed = Vec::new();
+        let mut full = Vec::new();
+        for (idx, downstream) in self.downstreams.iter().enumerate() {
+            match downstream.tx.try_send(item()) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(item)) => full.push((idx, item)),
+                Err(mpsc::error::TrySendError::Closed(_)) => failed.push((idx, None)),
+            }
+        }
+        // Очереди разбираются независимо: пока ждём одну, остальные тоже освобождаются
+        let deadline = tokio::time::Instant::now() + RELAY_STALL_TIMEOUT;
+        for (idx, item) in full {
+            match tokio::time::timeout_at(deadline, self.downstreams[idx].tx.send(item)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(_)) => failed.push((idx, None)),
+                Err(_) => failed.push((idx, Some(format!("нет ответа {} с", RELAY_STALL_TIMEOUT.as_secs())))),
+            }
+        }
+        failed.sort_by_key(|(idx, _)| *idx);
+        for (idx, reason) in failed.into_iter().rev() {
+            let downstream = self.downstreams.remove(idx);
+            downstream.task.abort();
+            let reason = match reason {
+                Some(reason) => reason,
+                None => match downstream.task.await {
+                    Ok(Err(e)) => e,
+                    _ => "соединение закрыто".to_string(),
+                },
+            };
+            let _ = self.event_tx.send(TransferEvent::FileReceived(
+                format!("⚠️ Ретрансляция на {} прервана: {}", downstream.addr, reason), 0
+            ));
+        }
+    }
+    
+    /// Завершить сессии с нижестоящими (Done) и дождаться, пока они всё примут
+    pub(crate) async fn finish(mut self) {
+        self.send(&Message::Done).await;
+        for downstream in self.downstreams {
+            drop(downstream.tx);
+            let text = match downstream.task.await {
+                Ok(Ok(files)) => format!("↪ Ретрансляция на {}: {} файл(ов)", downstream.addr, files),
+                Ok(Err(e)) => format!("⚠️ Ретрансляция на {} прервана: {}", downstream.addr, e),
+                Err(e) => format!("⚠️ Ретрансляция на {} прервана: {}", downstream.addr, e),
+            };
+            let _ = self.event_tx.send(TransferEvent::FileReceived(text, 0));
+        }
+    }
+}
+
+/// Подключиться к нижестоящему и согласовать `features`: возвращает поток и то,
+/// что получатель подтвердил. Старый получатель закрывает соединение на Hello -
+/// тогда подключаемся заново без него. Подтвердившему `FEATURE_RELAY_HOPS`
+/// сообщается число узлов `hops`
+async fn connect_downstream(
+    transport_type: TransportType,
+    addr: &str,
+    features: u32,
+    hops: u32,
+) -> Result<(Box<dyn TransportStream>, u32), String> {
+    let connect = || transport::connect_with_timeout(transport_type, addr, DEFAULT_CONNECT_TIMEOUT, SocketTuning::default());
+    let mut stream = connect().await.map_err(|e| e.to_string())?;
+    let features = match send_hello(&mut *stream, features).await {
+        Ok(Message::HelloAck { features }) => features,
+        Ok(_) => 0,
+        Err(_) => return Ok((connect().await.map_err(|e| e.to_string())?, 0)),
+    };
+    if features & FEATURE_RELAY_HOPS != 0 {
+        let data = Message::RelayHops { hops }.to_bytes().map_err(|e| e.to_string())?;
+        stream.write_all(&data).await.map_err(|e| e.to_string())?;
+        match read_reply(&mut *stream).await? {
+            Message::Ack => {}
+            Message::Error(e) => return Err(e),
+            _ => return Err("Неожиданный ответ".to_string()),
+        }
+    }
+    Ok((stream, features))
+}
+
+/// Поток записи одному нижестоящему: сообщения из очереди - в соединение,
+/// ответы на FileStart и FileEnd проверяются
+async fn run_downstream(
+    mut stream: Box<dyn TransportStream>,
+    features: u32,
+    mut rx: mpsc::Receiver<Outgoing>,
+) -> Result<usize, String> {
+    let mut files = 0;
+    // Файл уже есть у получателя: его куски и FileEnd не пересылаются
+    let mut skipping = false;
+    
+    while let Some(item) = rx.recv().await {
+        let msg = match item {
+            Outgoing::Message(msg) => msg,
+            // Нижестоящий без FEATURE_FILE_LINK получает копию целиком
+            Outgoing::Link { to, path, .. } if features & FEATURE_FILE_LINK == 0 => {
+                send_copy(&mut *stream, &to, &path).await?;
+                files += 1;
+                continue;
+            }
+            Outgoing::Link { from, to, path } => {
+                let data = Message::FileLink { from, to: to.clone() }.to_bytes().map_err(|e| e.to_string())?;
+                stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                match read_reply(&mut *stream).await? {
+                    Message::Ack => {}
+                    // Исходного файла у нижестоящего нет - передаём копию целиком
+                    Message::Error(_) => send_copy(&mut *stream, &to, &path).await?,
+                    Message::Cancel => return Err("получатель отменил передачу".to_string()),
+                    _ => return Err("Неожиданный ответ".to_string()),
+                }
+                files += 1;
+                continue;
+            }
+        };
+        match &msg {
+            // Нижестоящий без FEATURE_FILE_META атрибуты не принимает - файл уйдёт без них
+            Message::FileMeta { .. } if features & FEATURE_FILE_META == 0 => continue,
+            // Без FEATURE_RAW_FILE_NAME файл сохранится под lossy-именем из FileStart
+            Message::RawFileName { .. } if features & FEATURE_RAW_FILE_NAME == 0 => continue,
+            // Без FEATURE_NO_EXTRACT нижестоящий распакует архив по своим правилам
+            Message::NoExtract if features & FEATURE_NO_EXTRACT == 0 => continue,
+            // Нижестоящий без FEATURE_VERIFY_INTEGRITY примет файл без проверки;
+            // файла, который он пропустил, хэш тоже не касается
+            Message::FileHash { .. } if skipping || features & FEATURE_VERIFY_INTEGRITY == 0 => continue,
+            Message::RawFileName { .. } | Message::FileMeta { .. } | Message::FileHash { .. } | Message::NoExtract => {
+                let data = msg.to_bytes().map_err(|e| e.to_string())?;
+                stream.write_all(&data).await.map_err(|e| e.to_string())?;
+                // Error не мешает: файл сохранится под lossy-именем, без атрибутов или без проверки
+                match read_reply(&mut *stream).await? {
+                    Message::Ack | Message::Error(_) => {}
+                    Message::Cancel => return Err("получатель отменил передачу".to_string()),
+                    _ => return Err("Неожиданный ответ".to_string()),
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let size = match &msg {
+            Message::FileStart { size, .. } => {
+                skipping = false;
+                Some(*size)
+            }
+            Message::FileChunk { .. } | Message::FileEnd if skipping => continue,
+            _ => None,
+        };
+        let is_codec = matches!(msg, Message::FileCodec { .. });
+        // Нижестоящий не подтвердил FEATURE_FILE_CODEC - FileCodec он не прочитает
+        if is_codec && features & FEATURE_FILE_CODEC == 0 {
+            return Err("получатель не поддерживает zstd".to_string());
+        }
+        let is_end = matches!(msg, Message::FileEnd);
+        let is_done = matches!(msg, Message::Done);
+        
+        let data = msg.to_bytes().map_err(|e| e.to_string())?;
+        stream.write_all(&data).await.map_err(|e| e.to_string())?;
+        
+        if let Some(size) = size {
+            match read_reply(&mut *stream).await? {
+                Message::Ack => {}
+                Message::ResumeAck { offset } if offset >= size => {
+                    skipping = true;
+                    files += 1;
+                }
+                Message::ResumeAck { offset: 0 } => {}
+                // Правила нижестоящего: этот файл ему не нужен, остальные - как обычно
+                Message::Rejected { .. } => skipping = true,
+                Message::Error(e) => return Err(e),
+                Message::Cancel => return Err("получатель отменил передачу".to_string()),
+                _ => return Err("Неожиданный ответ".to_string()),
+            }
+        } else if is_codec {
+            match read_reply(&mut *stream).await? {
+                Message::Ack => {}
+                // Куски уже сжаты zstd - пересжать их LZ4 ретранслятор не может
+                Message::Error(_) => return Err("получатель не поддерживает zstd".to_string()),
+                Message::Cancel => return Err("получатель отменил передачу".to_string()),
+                _ => return Err("Неожиданный ответ".to_string()),
+            }
+        } else if is_end {
+            match read_reply(&mut *stream).await? {
+                Message::Ack => files += 1,
+                Message::ChecksumMismatch => return Err("копия повреждена (SHA-256 не совпал)".to_string()),
+                Message::Error(e) => return Err(e),
+                Message::Cancel => return Err("получатель отменил передачу".to_string()),
+                _ => return Err("Неожиданный ответ".to_string()),
+            }
+        } else if is_done {
+            stream.flush().await.map_err(|e| e.to_string())?;
+        }
+    }
+    
+    let _ = stream.shutdown().await;
+    Ok(files)
+}
+
+/// Передать копию `path` под именем `filename` (без сжатия) - для FileLink,
+/// который нижестоящий выполнить не смог
+async fn send_copy(stream: &mut dyn TransportStream, filename: &str, path: &std::path::Path) -> Result<(), String> {
+    let mut file = tokio::fs::File::open(path).await.map_err(|e| format!("{}: {}", path.display(), e))?;
+    let size = file.metadata().await.map_err(|e| e.to_string())?.len();
+    let start = Message::FileStart { filename: filename.to_string(), size, compressed: false, offset: 0, quick_hash: 0 };
+    stream.write_all(&start.to_bytes().map_err(|e| e.to_string())?).await.map_err(|e| e.to_string())?;
+    match read_reply(stream).await? {
+        Message::Ack | Message::ResumeAck { offset: 0 } => {}
+        Message::ResumeAck { .. } | Message::Rejected { .. } => return Ok(()), // Уже есть целиком или не нужен
+        Message::Error(e) => return Err(e),
+        Message::Cancel => return Err("получатель отменил передачу".to_string()),
+        _ => return Err("Неожиданный ответ".to_string()),
+    }
+    
+    let mut buffer = vec![0u8; RELAY_COPY_CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buffer).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        let chunk = Message::FileChunk { data: buffer[..n].to_vec(), original_size: n };
+        stream.write_all(&chunk.to_bytes().map_err(|e| e.to_string())?).await.map_err(|e| e.to_string())?;
+    }
+    let end = Message::FileEnd.to_bytes().map_err(|e| e.to_string())?;
+    stream.write_all(&end).await.map_err(|e| e.to_string())?;
+    match read_reply(stream).await? {
+        Message::Ack => Ok(()),
+        Message::Error(e) => Err(e),
+        Message::Cancel => Err("получатель отменил передачу".to_string()),
+        _ => Err("Неожиданный ответ".to_string()),
+    }
+}
+
+/// Прочитать один ответ нижестоящего получателя
+async fn read_reply(stream: &mut dyn TransportStream) -> Result<Message, String> {
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+    let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+    stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    Message::from_bytes(&data).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::receiver::spawn_test_server;
+    use crate::network::sender::send_files_to_target_with_options;
+    use crate::network::{CompressionMode, SendOptions, ServerOptions};
+    use crate::protocol::FileInfo;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+    
+    #[tokio::test]
+    async fn test_relay_forwards_files() {
+        let src = tempfile::tempdir().unwrap();
+        let hub = tempfile::tempdir().unwrap();
+        let leaf = tempfile::tempdir().unwrap();
+        
+        let big = b"relay chunk ".repeat(200_000);
+        std::fs::create_dir(src.path().join("docs")).unwrap();
+        std::fs::write(src.path().join("docs/big.bin"), &big).unwrap();
+        std::fs::write(src.path().join("empty.txt"), b"").unwrap();
+        let mut files = vec![
+            FileInfo::new(src.path().join("docs/big.bin")).unwrap(),
+            FileInfo::new(src.path().join("empty.txt")).unwrap(),
+        ];
+        files[0].relative_path = "docs/big.bin".to_string();
+        
+        let (leaf_server, _leaf_rx) = spawn_test_server(leaf.path(), ServerOptions::default()).await;
+        // Второй нижестоящий недоступен: пересылка на первый продолжается
+        let hub_options = ServerOptions::builder()
+            .relay_to(vec![leaf_server.addr.clone(), format!("127.0.0.1:{}", free_port())])
+            .build()
+            .unwrap();
+        let (hub_server, _hub_rx) = spawn_test_server(hub.path(), hub_options).await;
+        
+        let options = SendOptions { compression: CompressionMode::On, ..Default::default() };
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, hub_server.addr.clone(), files, options, tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        
+        for dir in [hub.path(), leaf.path()] {
+            assert_eq!(std::fs::read(dir.join("docs/big.bin")).unwrap(), big);
+            assert_eq!(std::fs::read(dir.join("empty.txt")).unwrap(), b"");
+        }
+        
+        hub_server.stop().await;
+        leaf_server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_relay_forwards_links_and_raw_names() {
+        let src = tempfile::tempdir().unwrap();
+        let hub = tempfile::tempdir().unwrap();
+        let leaf = tempfile::tempdir().unwrap();
+        
+        std::fs::write(src.path().join("a.txt"), b"same").unwrap();
+        std::fs::write(src.path().join("b.txt"), b"same").unwrap(); // Уйдёт как FileLink
+        let mut files = vec![
+            FileInfo::new(src.path().join("a.txt")).unwrap(),
+            FileInfo::new(src.path().join("b.txt")).unwrap(),
+        ];
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let name = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+            std::fs::write(src.path().join(name), b"raw").unwrap();
+            files.push(FileInfo::new(src.path().join(name)).unwrap());
+        }
+        
+        let (leaf_server, mut leaf_rx) = spawn_test_server(leaf.path(), ServerOptions::default()).await;
+        let hub_options = ServerOptions::builder().relay_to(vec![leaf_server.addr.clone()]).build().unwrap();
+        let (hub_server, _hub_rx) = spawn_test_server(hub.path(), hub_options).await;
+        
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, hub_server.addr.clone(), files, SendOptions::default(), tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        
+        assert_eq!(std::fs::read(leaf.path().join("b.txt")).unwrap(), b"same");
+        let mut linked = false;
+        while let Ok(event) = leaf_rx.try_recv() {
+            if let TransferEvent::FileReceived(name, _) = event {
+                linked |= name == "b.txt (копия a.txt)";
+            }
+        }
+        assert!(linked);
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let name = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+            assert_eq!(std::fs::read(leaf.path().join(name)).unwrap(), b"raw");
+        }
+        
+        hub_server.stop().await;
+        leaf_server.stop().await;
+    }
+    
+    /// Нижестоящий без FEATURE_FILE_LINK: вместо FileLink - копия с диска ретранслятора
+    #[tokio::test]
+    async fn test_link_sent_as_copy_without_feature() {
+        let hub = tempfile::tempdir().unwrap();
+        let leaf = tempfile::tempdir().unwrap();
+        std::fs::write(hub.path().join("b.txt"), b"same").unwrap();
+        // У нижестоящего исходный файл есть: FileLink он бы выполнил
+        std::fs::write(leaf.path().join("a.txt"), b"same").unwrap();
+        
+        let (leaf_server, mut leaf_rx) = spawn_test_server(leaf.path(), ServerOptions::default()).await;
+        let (stream, features) = connect_downstream(TransportType::Tcp, &leaf_server.addr, FEATURE_RELAY_HOPS, 1)
+            .await
+            .unwrap();
+        assert_eq!(features & (FEATURE_FILE_LINK | FEATURE_RAW_FILE_NAME), 0);
+        let (tx, rx) = mpsc::channel(RELAY_QUEUE_LEN);
+        let task = tokio::spawn(run_downstream(stream, features, rx));
+        tx.send(Outgoing::Message(Message::RawFileName { bytes: b"b.txt".to_vec() })).await.unwrap();
+        tx.send(Outgoing::Link { from: "a.txt".to_string(), to: "b.txt".to_string(), path: hub.path().join("b.txt") })
+            .await
+            .unwrap();
+        tx.send(Outgoing::Message(Message::Done)).await.unwrap();
+        drop(tx);
+        assert_eq!(task.await.unwrap().unwrap(), 1);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        assert_eq!(std::fs::read(leaf.path().join("b.txt")).unwrap(), b"same");
+        let mut linked = false;
+        while let Ok(event) = leaf_rx.try_recv() {
+            if let TransferEvent::FileReceived(name, _) = event {
+                linked |= name.contains("копия");
+            }
+        }
+        assert!(!linked);
+        
+        leaf_server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_relay_stops_after_max_hops() {
+        let src = tempfile::tempdir().unwrap();
+        let hub = tempfile::tempdir().unwrap();
+        let leaf = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join("hop.txt"), b"hop").unwrap();
+        
+        let (leaf_server, _leaf_rx) = spawn_test_server(leaf.path(), ServerOptions::default()).await;
+        let hub_options = ServerOptions::builder().relay_to(vec![leaf_server.addr.clone()]).build().unwrap();
+        let (hub_server, _hub_rx) = spawn_test_server(hub.path(), hub_options).await;
+        
+        // Ретранслятор перед ним уже был последним допустимым узлом
+        let (mut stream, features) = connect_downstream(TransportType::Tcp, &hub_server.addr, FEATURE_RELAY_HOPS, MAX_RELAY_HOPS)
+            .await
+            .unwrap();
+        assert_ne!(features & FEATURE_RELAY_HOPS, 0);
+        send_copy(&mut *stream, "hop.txt", &src.path().join("hop.txt")).await.unwrap();
+        stream.write_all(&Message::Done.to_bytes().unwrap()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        
+        assert_eq!(std::fs::read(hub.path().join("hop.txt")).unwrap(), b"hop");
+        assert!(!leaf.path().join("hop.txt").exists());
+        
+        hub_server.stop().await;
+        leaf_server.stop().await;
+    }
+}
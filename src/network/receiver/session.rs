@@ -0,0 +1,154 @@
+//! Жизненный цикл входящего соединения
+//!
+//! Вид соединения (передача, спидтест, проверка при сканировании) становится
+//! известен по первому сообщению. Только тогда приходит `ClientConnected` и
+//! пишется запись аудита; проверки доступности дают одно `ClientProbed` и в
+//! аудит не попадают
+
+use crate::network::events::TransferEvent;
+use crate::protocol::{ConnectionIntent, Message};
+use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::mpsc;
+
+use super::audit::AuditLog;
+use super::hook::PostHook;
+
+/// Номер следующего принимаемого файла (общий для всех соединений)
+static NEXT_FILE_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+/// Входящее соединение: адрес клиента, вид и куда сообщать о нём
+pub(crate) struct ClientSession {
+    /// Адрес клиента (для аудита и шаблонов имён)
+    pub(crate) peer: String,
+    /// Адрес для событий (с транспортом)
+    label: String,
+    intent: OnceLock<ConnectionIntent>,
+    /// Номер файла, который принимается сейчас
+    current_file: Mutex<Option<usize>>,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+    pub(crate) audit: AuditLog,
+    pub(crate) hook: PostHook,
+}
+
+impl ClientSession {
+    pub(crate) fn new(
+        peer: String,
+        label: String,
+        event_tx: mpsc::UnboundedSender<TransferEvent>,
+        audit: AuditLog,
+        hook: PostHook,
+    ) -> Self {
+        Self { peer, label, intent: OnceLock::new(), current_file: Mutex::new(None), event_tx, audit, hook }
+    }
+    
+    /// Учесть очередное сообщение: первое определяет вид соединения
+    pub(crate) fn observe(&self, msg: &Message) {
+        if self.intent.get().is_none() {
+            self.classify(ConnectionIntent::of_first_message(msg));
+        }
+    }
+    
+    /// Вид соединения известен заранее (веб-загрузка) или из первого сообщения.
+    /// Учитывается только первый вызов
+    pub(crate) fn classify(&self, intent: ConnectionIntent) {
+        if self.intent.set(intent).is_err() {
+            return;
+        }
+        if intent == ConnectionIntent::Probe {
+            let _ = self.event_tx.send(TransferEvent::ClientProbed(self.label.clone()));
+        } else {
+            self.audit.connected(&self.peer);
+            let _ = self.event_tx.send(TransferEvent::ClientConnected(self.label.clone(), intent));
+        }
+    }
+    
+    /// Начат приём файла: выдать ему номер для `Progress` и сообщить `FileReceiveStarted`.
+    /// Предыдущий файл клиента на этом закончен
+    pub(crate) fn start_file(&self, name: &str, size: u64) -> usize {
+        let file_idx = NEXT_FILE_INDEX.fetch_add(1, Ordering::Relaxed);
+        self.end_file();
+        let _ = self.event_tx.send(TransferEvent::FileReceiveStarted(file_idx, name.to_string(), size));
+        *self.current_file.lock().unwrap_or_else(|e| e.into_inner()) = Some(file_idx);
+        file_idx
+    }
+    
+    /// Сообщить `FileReceiveEnded` для текущего файла, если он есть
+    fn end_file(&self) {
+        if let Some(file_idx) = self.current_file.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let _ = self.event_tx.send(TransferEvent::FileReceiveEnded(file_idx));
+        }
+    }
+    
+    /// Соединение закрыто
+    pub(crate) fn close(self) {
+        self.end_file();
+        match self.intent.get() {
+            Some(ConnectionIntent::Probe) => {}
+            Some(&intent) => {
+                self.audit.disconnected(&self.peer);
+                let _ = self.event_tx.send(TransferEvent::ClientDisconnected(self.label, intent));
+            }
+            // Подключился и ушёл молча - сканер старой версии или просто проверка порта
+            None => {
+                let _ = self.event_tx.send(TransferEvent::ClientProbed(self.label));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::scanner::scan_hosts;
+    use crate::network::sender::send_files_to_target_with_options;
+    use crate::network::receiver::spawn_test_server;
+    use crate::network::{SendOptions, ServerOptions};
+    use crate::protocol::FileInfo;
+    use std::time::Duration;
+    
+    #[tokio::test]
+    async fn test_probe_and_transfer_classified() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join("a.txt"), b"content").unwrap();
+        let files = vec![FileInfo::new(src.path().join("a.txt")).unwrap()];
+        
+        let (server, mut server_rx) = spawn_test_server(dst.path(), ServerOptions::default()).await;
+        let addr = server.addr.clone();
+        
+        // Сканирование: одна проверка, без подключения и отключения
+        let (tx, _rx) = mpsc::unbounded_channel();
+        assert_eq!(scan_hosts(vec![addr.clone()], server.port, tx).await.unwrap(), vec![addr.clone()]);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let mut probes = 0;
+        while let Ok(event) = server_rx.try_recv() {
+            match event {
+                TransferEvent::ClientProbed(_) => probes += 1,
+                TransferEvent::ClientConnected(..) | TransferEvent::ClientDisconnected(..) => panic!("проверка посчитана подключением"),
+                _ => {}
+            }
+        }
+        assert_eq!(probes, 1);
+        
+        // Передача
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, SendOptions::default(), tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let mut lifecycle = Vec::new();
+        while let Ok(event) = server_rx.try_recv() {
+            match event {
+                TransferEvent::ClientConnected(_, intent) => lifecycle.push(("connected", intent)),
+                TransferEvent::ClientDisconnected(_, intent) => lifecycle.push(("disconnected", intent)),
+                TransferEvent::ClientProbed(_) => lifecycle.push(("probed", ConnectionIntent::Probe)),
+                _ => {}
+            }
+        }
+        assert_eq!(lifecycle, vec![
+            ("connected", ConnectionIntent::Transfer),
+            ("disconnected", ConnectionIntent::Transfer),
+        ]);
+        
+        server.stop().await;
+    }
+}
@@ -1,16 +1,25 @@
 //! Потоковая распаковка архивов
 
-use crate::network::compression;
+use crate::extract::{create_dir_within, prepare_file_path, unpack_symlink, ArchiveType, ExtractResult, PathLimits};
+use crate::network::compression::CompressionCodec;
 use crate::network::events::TransferEvent;
+use crate::network::metrics::{self, METRICS};
 use crate::network::transport::TransportStream;
-use crate::protocol::Message;
+use crate::protocol::{ManifestEntry, Message};
 use lz4_flex::frame::FrameDecoder;
-use std::io::Read;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
 use std::time::Instant;
-use tokio::io::AsyncReadExt;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+
+use super::hook::PostHook;
+
+/// Сколько принятых, но ещё не распакованных байт может ждать распаковщика.
+/// Дальше приём из сети ждёт, и отправитель упирается в управление потоком транспорта
+pub(crate) const EXTRACT_BUFFER_LIMIT: usize = 32 * 1024 * 1024;
 
 /// FNV-1a хэшер для быстрого хэширования
 pub(crate) struct FnvHasher {
@@ -35,19 +44,53 @@ impl FnvHasher {
     }
 }
 
+/// Чанк в очереди распаковщика; разрешение возвращает его объём в лимит
+type QueuedChunk = (Vec<u8>, OwnedSemaphorePermit);
+
+/// Приёмная сторона канала распаковщика
+pub(crate) type ChunkReceiver = std_mpsc::Receiver<QueuedChunk>;
+
+/// Отправка чанков распаковщику с ограничением объёма очереди в байтах.
+/// Пока распаковщик отстаёт, `send` ждёт асинхронно - поток рантайма не блокируется
+pub(crate) struct ChunkSender {
+    tx: std_mpsc::Sender<QueuedChunk>,
+    budget: Arc<Semaphore>,
+    limit: usize,
+}
+
+impl ChunkSender {
+    /// Поставить чанк в очередь. Err - распаковщик уже завершился
+    pub async fn send(&self, chunk: Vec<u8>) -> Result<(), ()> {
+        // Чанк больше лимита занимает весь лимит, пустой - одно разрешение
+        let permits = chunk.len().clamp(1, self.limit) as u32;
+        let permit = self.budget.clone().acquire_many_owned(permits).await.map_err(|_| ())?;
+        self.tx.send((chunk, permit)).map_err(|_| ())
+    }
+}
+
+/// Канал распаковщика: в очереди не больше `limit` байт
+pub(crate) fn chunk_channel(limit: usize) -> (ChunkSender, ChunkReceiver) {
+    let limit = limit.clamp(1, Semaphore::MAX_PERMITS.min(u32::MAX as usize));
+    let (tx, rx) = std_mpsc::channel();
+    (ChunkSender { tx, budget: Arc::new(Semaphore::new(limit)), limit }, rx)
+}
+
 /// Адаптер для чтения из канала как из std::io::Read
 pub(crate) struct ChannelReader {
-    receiver: std_mpsc::Receiver<Vec<u8>>,
+    receiver: ChunkReceiver,
     buffer: Vec<u8>,
     pos: usize,
+    /// Разрешение текущего чанка: объём освобождается, когда чанк прочитан
+    permit: Option<OwnedSemaphorePermit>,
 }
 
 impl ChannelReader {
-    pub fn new(receiver: std_mpsc::Receiver<Vec<u8>>) -> Self {
+    pub fn new(receiver: ChunkReceiver) -> Self {
         Self {
             receiver,
             buffer: Vec::new(),
             pos: 0,
+            permit: None,
         }
     }
 }
@@ -56,10 +99,12 @@ impl Read for ChannelReader {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         // Если буфер пуст или весь прочитан, получаем следующий chunk
         if self.pos >= self.buffer.len() {
+            self.permit = None;
             match self.receiver.recv() {
-                Ok(data) => {
+                Ok((data, permit)) => {
                     self.buffer = data;
                     self.pos = 0;
+                    self.permit = Some(permit);
                 }
                 Err(_) => {
                     // Канал закрыт - EOF
@@ -77,11 +122,163 @@ impl Read for ChannelReader {
     }
 }
 
+/// Восстановить xattr файла из PAX-заголовков его записи tar
+fn restore_pax_xattrs<R: Read>(
+    entry: &mut tar::Entry<R>,
+    path: &Path,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) {
+    if let Err(e) = crate::utils::apply_xattrs(path, &crate::utils::pax_xattrs(entry)) {
+        let _ = event_tx.send(TransferEvent::FileReceived(format!("⚠️ {}", e), 0));
+    }
+}
+
+/// Расширение журнала потоковой распаковки: `a.tar.lz4` -> `a.tar.lz4.toolza-extract`
+pub(crate) const JOURNAL_EXTENSION: &str = "toolza-extract";
+
+/// Запись журнала: размер и mtime из заголовка tar, mtime файла на диске сразу после записи
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct JournalEntry {
+    size: u64,
+    mtime: u64,
+    written: u64,
+}
+
+impl JournalEntry {
+    /// Файл на диске тот же, что был записан: размер и mtime не изменились
+    fn on_disk(&self, full_path: &Path) -> bool {
+        disk_state(full_path) == Some((self.size, self.written))
+    }
+}
+
+/// Размер и mtime файла на диске (в наносекундах)
+fn disk_state(path: &Path) -> Option<(u64, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    let modified = meta.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some((meta.len(), modified.as_nanos() as u64))
+}
+
+/// Журнал потоковой распаковки tar: какие файлы архива уже полностью записаны.
+///
+/// Без сохранённого архива (`save_archive_for_resume`) оборванный приём
+/// начинается с нуля. Журнал переживает обрыв, и при повторе файлы, которые
+/// лежат на диске нетронутыми с прошлой записи, пропускаются без перезаписи, а
+/// отправитель может их вовсе не слать (`Message::ExtractManifest`). Удаляется
+/// после успешной распаковки
+pub(crate) struct ExtractJournal {
+    path: PathBuf,
+    /// Путь записи в архиве -> что записано
+    done: HashMap<PathBuf, JournalEntry>,
+    file: Option<std::fs::File>,
+}
+
+impl ExtractJournal {
+    fn journal_path(dir: &Path, filename: &str) -> PathBuf {
+        dir.join(format!("{}.{}", filename, JOURNAL_EXTENSION))
+    }
+    
+    /// Записи журнала архива `filename` размером `size` (None - журнала нет или он от другого архива)
+    fn read(dir: &Path, filename: &str, size: u64) -> Option<HashMap<PathBuf, JournalEntry>> {
+        let text = std::fs::read_to_string(Self::journal_path(dir, filename)).ok()?;
+        Self::parse(&text, size)
+    }
+    
+    /// Открыть журнал архива `filename` размером `size` в `dir`.
+    /// Журнал другого архива с тем же именем (размер не совпал) начинается заново
+    pub fn open(dir: &Path, filename: &str, size: u64) -> Self {
+        let path = Self::journal_path(dir, filename);
+        let done = Self::read(dir, filename, size).unwrap_or_default();
+        
+        let file = if done.is_empty() {
+            std::fs::File::create(&path).and_then(|mut f| writeln!(f, "{}", size).map(|_| f))
+        } else {
+            std::fs::OpenOptions::new().append(true).open(&path)
+        };
+        Self { path, done, file: file.ok() }
+    }
+    
+    /// Строки журнала: заголовок с размером архива, дальше
+    /// `размер<TAB>mtime записи<TAB>mtime файла на диске<TAB>путь`
+    fn parse(text: &str, size: u64) -> Option<HashMap<PathBuf, JournalEntry>> {
+        let mut lines = text.lines();
+        if lines.next()?.parse::<u64>().ok()? != size {
+            return None;
+        }
+        // Недописанная последняя строка (обрыв посреди записи) просто не разберётся
+        Some(lines.filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let entry = JournalEntry {
+                size: fields.next()?.parse().ok()?,
+                mtime: fields.next()?.parse().ok()?,
+                written: fields.next()?.parse().ok()?,
+            };
+            Some((PathBuf::from(fields.next()?), entry))
+        }).collect())
+    }
+    
+    /// Записи, которые лежат в `output_dir` нетронутыми с прошлой попытки, -
+    /// для `Message::ExtractManifest`. Сам журнал не меняется
+    pub fn manifest(dir: &Path, filename: &str, size: u64, output_dir: &Path, limits: &PathLimits) -> Vec<ManifestEntry> {
+        let Some(done) = Self::read(dir, filename, size) else {
+            return Vec::new();
+        };
+        done.into_iter()
+            .filter(|(path, entry)| {
+                matches!(limits.resolve(output_dir, path), Ok(Some(full_path)) if entry.on_disk(&full_path))
+            })
+            .filter_map(|(path, entry)| Some(ManifestEntry {
+                path: path.to_str()?.to_string(),
+                size: entry.size,
+                mtime: entry.mtime,
+            }))
+            .collect()
+    }
+    
+    /// Сколько файлов и байт уже распаковано в прошлые попытки
+    pub fn restored(&self) -> (usize, u64) {
+        (self.done.len(), self.done.values().map(|entry| entry.size).sum())
+    }
+    
+    /// Запись `path` (размер и mtime из заголовка tar) уже распакована в `full_path`
+    /// и с тех пор не менялась
+    pub fn is_done(&self, path: &Path, full_path: &Path, size: u64, mtime: u64) -> bool {
+        self.done.get(path).is_some_and(|entry| {
+            entry.size == size && entry.mtime == mtime && entry.on_disk(full_path)
+        })
+    }
+    
+    /// Отметить запись `path` распакованной в `full_path`
+    pub fn record(&mut self, path: &Path, full_path: &Path, size: u64, mtime: u64) {
+        let Some(path_str) = path.to_str().filter(|p| !p.contains(['\n', '\t'])) else {
+            return;
+        };
+        let Some((_, written)) = disk_state(full_path) else {
+            return;
+        };
+        if let Some(file) = &mut self.file {
+            let _ = writeln!(file, "{}\t{}\t{}\t{}", size, mtime, written, path_str);
+        }
+    }
+    
+    /// Распаковка завершена - журнал больше не нужен
+    pub fn finish(self) {
+        drop(self.file);
+        let _ = std::fs::remove_file(&self.path);
+    }
+    
+    /// Удалить журнал архива, распакованного другим путём (из сохранённого архива)
+    pub fn discard(dir: &Path, filename: &str) {
+        let _ = std::fs::remove_file(Self::journal_path(dir, filename));
+    }
+}
+
 /// Распаковка tar.lz4 из канала (потоковая, без буферизации всего файла)
 pub(crate) fn extract_from_channel(
-    rx: std_mpsc::Receiver<Vec<u8>>,
-    output_dir: &PathBuf,
+    rx: ChunkReceiver,
+    output_dir: &Path,
     filename: &str,
+    limits: &PathLimits,
+    mut journal: ExtractJournal,
     event_tx: &mpsc::UnboundedSender<TransferEvent>,
 ) -> Result<(), String> {
     use std::fs::{self, File};
@@ -95,8 +292,7 @@ pub(crate) fn extract_from_channel(
     // Tar archive поверх LZ4 decoder
     let mut archive = tar::Archive::new(lz4_reader);
     
-    let mut files_count = 0usize;
-    let mut total_size = 0u64;
+    let mut result = crate::extract::ExtractResult::default();
     
     // Читаем и распаковываем файлы по одному - ПОТОКОВО!
     for entry_result in archive.entries().map_err(|e| format!("Ошибка чтения tar: {}", e))? {
@@ -105,20 +301,58 @@ pub(crate) fn extract_from_channel(
         let path = entry.path()
             .map_err(|e| format!("Ошибка пути: {}", e))?
             .to_path_buf();
-        let full_path = output_dir.join(&path);
+        let full_path = match limits.resolve(output_dir, &path) {
+            Ok(Some(full_path)) => full_path,
+            Ok(None) => continue,
+            Err(reason) => {
+                // Пропускаем только эту запись, остальной архив распаковываем
+                let _ = event_tx.send(TransferEvent::ExtractionEntrySkipped(
+                    filename.to_string(),
+                    path.display().to_string(),
+                    reason.clone(),
+                ));
+                result.skipped.push((path.display().to_string(), reason));
+                continue;
+            }
+        };
         
-        if entry.header().entry_type().is_dir() {
-            fs::create_dir_all(&full_path)
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            create_dir_within(output_dir, &full_path)
                 .map_err(|e| format!("Ошибка создания папки: {}", e))?;
-        } else if entry.header().entry_type().is_file() {
-            // Создаём родительскую директорию
-            if let Some(parent) = full_path.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Ошибка создания папки: {}", e))?;
+            result.dirs_count += 1;
+        } else if entry_type.is_symlink() {
+            let target = entry.link_name()
+                .map_err(|e| format!("Ошибка пути: {}", e))?
+                .unwrap_or_default()
+                .into_owned();
+            match unpack_symlink(output_dir, &full_path, &target) {
+                Ok(true) => result.symlinks_count += 1,
+                Ok(false) => {}
+                Err(reason) => {
+                    let _ = event_tx.send(TransferEvent::ExtractionEntrySkipped(
+                        filename.to_string(),
+                        path.display().to_string(),
+                        reason.clone(),
+                    ));
+                    result.skipped.push((path.display().to_string(), reason));
+                }
             }
+        } else if entry_type.is_file() {
+            // Создаём родительскую директорию (не через ссылки)
+            prepare_file_path(output_dir, &full_path)
+                .map_err(|e| format!("Ошибка создания папки: {}", e))?;
             
             // Получаем размер до распаковки
             let size = entry.header().size().unwrap_or(0);
+            let mtime = entry.header().mtime().unwrap_or(0);
+            
+            // Распакован в прошлую попытку: данные записи tar пропустит сам
+            if journal.is_done(&path, &full_path, size, mtime) {
+                result.files_count += 1;
+                result.total_size += size;
+                continue;
+            }
             
             // Распаковываем файл напрямую на диск - ПОТОКОВО!
             let mut file = File::create(&full_path)
@@ -133,16 +367,20 @@ pub(crate) fn extract_from_channel(
                 let mode = entry.header().mode().unwrap_or(0o644);
                 let _ = fs::set_permissions(&full_path, fs::Permissions::from_mode(mode));
             }
+            if limits.xattrs {
+                restore_pax_xattrs(&mut entry, &full_path, event_tx);
+            }
+            journal.record(&path, &full_path, size, mtime);
             
-            files_count += 1;
-            total_size += size;
+            result.files_count += 1;
+            result.total_size += size;
         }
     }
     
+    journal.finish();
     let _ = event_tx.send(TransferEvent::ExtractionCompleted(
         filename.to_string(),
-        files_count,
-        total_size,
+        result,
     ));
     
     Ok(())
@@ -150,9 +388,11 @@ pub(crate) fn extract_from_channel(
 
 /// Распаковка tar.zst из канала (потоковая, без буферизации всего файла)
 pub(crate) fn extract_from_channel_zst(
-    rx: std_mpsc::Receiver<Vec<u8>>,
-    output_dir: &PathBuf,
+    rx: ChunkReceiver,
+    output_dir: &Path,
     filename: &str,
+    limits: &PathLimits,
+    mut journal: ExtractJournal,
     event_tx: &mpsc::UnboundedSender<TransferEvent>,
 ) -> Result<(), String> {
     use std::fs::{self, File};
@@ -188,8 +428,7 @@ pub(crate) fn extract_from_channel_zst(
     // Tar archive поверх ZST decoder
     let mut archive = tar::Archive::new(zst_reader);
     
-    let mut files_count = 0usize;
-    let mut total_size = 0u64;
+    let mut result = crate::extract::ExtractResult::default();
     
     // Читаем и распаковываем файлы по одному - ПОТОКОВО!
     let entries = match archive.entries() {
@@ -221,20 +460,58 @@ pub(crate) fn extract_from_channel_zst(
         let path = entry.path()
             .map_err(|e| format!("Ошибка пути: {}", e))?
             .to_path_buf();
-        let full_path = output_dir.join(&path);
+        let full_path = match limits.resolve(output_dir, &path) {
+            Ok(Some(full_path)) => full_path,
+            Ok(None) => continue,
+            Err(reason) => {
+                // Пропускаем только эту запись, остальной архив распаковываем
+                let _ = event_tx.send(TransferEvent::ExtractionEntrySkipped(
+                    filename.to_string(),
+                    path.display().to_string(),
+                    reason.clone(),
+                ));
+                result.skipped.push((path.display().to_string(), reason));
+                continue;
+            }
+        };
         
-        if entry.header().entry_type().is_dir() {
-            fs::create_dir_all(&full_path)
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            create_dir_within(output_dir, &full_path)
                 .map_err(|e| format!("Ошибка создания папки: {}", e))?;
-        } else if entry.header().entry_type().is_file() {
-            // Создаём родительскую директорию
-            if let Some(parent) = full_path.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Ошибка создания папки: {}", e))?;
+            result.dirs_count += 1;
+        } else if entry_type.is_symlink() {
+            let target = entry.link_name()
+                .map_err(|e| format!("Ошибка пути: {}", e))?
+                .unwrap_or_default()
+                .into_owned();
+            match unpack_symlink(output_dir, &full_path, &target) {
+                Ok(true) => result.symlinks_count += 1,
+                Ok(false) => {}
+                Err(reason) => {
+                    let _ = event_tx.send(TransferEvent::ExtractionEntrySkipped(
+                        filename.to_string(),
+                        path.display().to_string(),
+                        reason.clone(),
+                    ));
+                    result.skipped.push((path.display().to_string(), reason));
+                }
             }
+        } else if entry_type.is_file() {
+            // Создаём родительскую директорию (не через ссылки)
+            prepare_file_path(output_dir, &full_path)
+                .map_err(|e| format!("Ошибка создания папки: {}", e))?;
             
             // Получаем размер до распаковки
             let size = entry.header().size().unwrap_or(0);
+            let mtime = entry.header().mtime().unwrap_or(0);
+            
+            // Распакован в прошлую попытку: данные записи tar пропустит сам
+            if journal.is_done(&path, &full_path, size, mtime) {
+                result.files_count += 1;
+                result.total_size += size;
+                continue;
+            }
             
             // Распаковываем файл напрямую на диск - ПОТОКОВО!
             let mut file = File::create(&full_path)
@@ -249,37 +526,161 @@ pub(crate) fn extract_from_channel_zst(
                 let mode = entry.header().mode().unwrap_or(0o644);
                 let _ = fs::set_permissions(&full_path, fs::Permissions::from_mode(mode));
             }
+            if limits.xattrs {
+                restore_pax_xattrs(&mut entry, &full_path, event_tx);
+            }
+            journal.record(&path, &full_path, size, mtime);
             
-            files_count += 1;
-            total_size += size;
+            result.files_count += 1;
+            result.total_size += size;
         }
     }
     
+    journal.finish();
     let _ = event_tx.send(TransferEvent::ExtractionCompleted(
         filename.to_string(),
-        files_count,
-        total_size,
+        result,
     ));
     
     Ok(())
 }
 
-/// ИСТИННАЯ потоковая распаковка tar.lz4 через транспорт с поддержкой резюме
-pub(crate) async fn receive_and_extract_streaming_transport(
-    stream: &mut dyn TransportStream,
-    save_dir: &PathBuf,
+/// Распаковка одиночного .lz4/.zst из канала: файл пишется сразу распакованным
+pub(crate) fn extract_single_from_channel(
+    rx: ChunkReceiver,
+    archive_type: ArchiveType,
+    output_dir: &Path,
     filename: &str,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) -> Result<(), String> {
+    let result = decompress_single_file(ChannelReader::new(rx), archive_type, output_dir, filename)
+        .map_err(|e| format!("Ошибка распаковки {}: {}", archive_type.name(), e))?;
+    
+    let _ = event_tx.send(TransferEvent::ExtractionCompleted(
+        filename.to_string(),
+        result,
+    ));
+    
+    Ok(())
+}
+
+/// Распаковать одиночный сжатый файл в `output_dir/<имя без .lz4/.zst>`
+fn decompress_single_file<R: Read>(
+    reader: R,
+    archive_type: ArchiveType,
+    output_dir: &Path,
+    filename: &str,
+) -> std::io::Result<ExtractResult> {
+    let output_name = crate::extract::single_file_output_name(filename).unwrap_or(filename);
+    let output_path = output_dir.join(output_name.replace('/', std::path::MAIN_SEPARATOR_STR));
+    crate::extract::decompress_single_to_file(reader, archive_type, &output_path)
+}
+
+/// Что распаковывать из канала и куда (уходит в поток распаковщика)
+struct ChannelExtract {
+    archive_type: ArchiveType,
+    output_dir: PathBuf,
+    filename: String,
     size: u64,
-    compressed: bool,
-    save_archive: bool, // Сохранять архив для возможности резюме
+    /// Папка журнала `ExtractJournal` (для tar)
+    journal_dir: PathBuf,
+    limits: PathLimits,
+}
+
+/// Распаковка из канала декодером по типу архива. Для tar ведётся журнал
+/// `ExtractJournal` в `journal_dir`
+fn extract_from_channel_by_type(
+    rx: ChunkReceiver,
+    extract: ChannelExtract,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) -> Result<(), String> {
+    let ChannelExtract { archive_type, output_dir, filename, size, journal_dir, limits } = extract;
+    let (output_dir, filename) = (&output_dir, filename.as_str());
+    if matches!(archive_type, ArchiveType::Lz4 | ArchiveType::Zst) {
+        return extract_single_from_channel(rx, archive_type, output_dir, filename, event_tx);
+    }
+    
+    let journal = ExtractJournal::open(&journal_dir, filename, size);
+    let (files, bytes) = journal.restored();
+    if files > 0 {
+        let _ = event_tx.send(TransferEvent::FileReceived(
+            format!("🔄 Уже распаковано в прошлый раз: {} файл(ов), {} - не перезаписываются", files, crate::utils::format_size(bytes)),
+            0
+        ));
+    }
+    match archive_type {
+        ArchiveType::TarZst => extract_from_channel_zst(rx, output_dir, filename, &limits, journal, event_tx),
+        _ => extract_from_channel(rx, output_dir, filename, &limits, journal, event_tx),
+    }
+}
+
+/// Сообщить о записях, пропущенных распаковкой с диска (см. `ExtractResult::skipped`)
+pub(crate) fn report_skipped_entries(filename: &str, result: &ExtractResult, event_tx: &mpsc::UnboundedSender<TransferEvent>) {
+    for (entry, reason) in &result.skipped {
+        let _ = event_tx.send(TransferEvent::ExtractionEntrySkipped(filename.to_string(), entry.clone(), reason.clone()));
+    }
+}
+
+/// Распаковка сохранённого сырого архива (после резюме): имя `.toolza-part`
+/// не говорит о формате, поэтому тип берётся из исходного имени
+fn extract_raw_file(
+    raw_path: &Path,
+    archive_type: ArchiveType,
+    output_dir: &Path,
+    filename: &str,
+    limits: &PathLimits,
+) -> std::io::Result<ExtractResult> {
+    match archive_type {
+        ArchiveType::Lz4 | ArchiveType::Zst => {
+            let reader = std::io::BufReader::new(std::fs::File::open(raw_path)?);
+            decompress_single_file(reader, archive_type, output_dir, filename)
+        }
+        ArchiveType::TarZst => crate::extract::extract_tar_zst_streaming(raw_path, output_dir, None, limits),
+        _ => crate::extract::extract_tar_lz4_streaming(raw_path, output_dir, None, limits),
+    }
+}
+
+/// Архив для распаковки на лету: что объявил отправитель и куда распаковывать
+pub(crate) struct IncomingArchive<'a> {
+    pub filename: &'a str,
+    pub size: u64,
+    /// Кодек чанков, None - без сжатия
+    pub codec: Option<CompressionCodec>,
+    /// Куда распаковывать (extract_dir или save_dir)
+    pub extract_dir: &'a Path,
+    /// Куда писать сырой архив (save_dir или temp_dir)
+    pub archive_dir: &'a Path,
+    /// Сохранять архив для возможности резюме
+    pub save_archive: bool,
+    /// Отправитель понимает ExtractManifest (FEATURE_EXTRACT_MANIFEST)
+    pub offer_manifest: bool,
+    /// Команда после распаковки (в неё передаётся extract_dir)
+    pub post_hook: &'a PostHook,
+    pub path_limits: PathLimits,
+    /// Номер файла для Progress, см. FileReceiveStarted
+    pub file_idx: usize,
+}
+
+/// ИСТИННАЯ потоковая распаковка (tar.lz4, tar.zst, .lz4, .zst) через транспорт с поддержкой резюме
+pub(crate) async fn receive_and_extract_streaming_transport(
+    stream: &mut dyn TransportStream,
+    archive: IncomingArchive<'_>,
     event_tx: &mpsc::UnboundedSender<TransferEvent>,
     stop_flag: &std::sync::Arc<std::sync::atomic::AtomicBool>,
 ) -> Result<(), String> {
     use std::sync::atomic::Ordering;
     use tokio::io::{AsyncWriteExt, AsyncSeekExt};
     
-    // Путь к сырому архиву (для резюме) - только если включено сохранение
-    let raw_file_path = save_dir.join(filename);
+    let IncomingArchive {
+        filename, size, codec, extract_dir, archive_dir, save_archive, offer_manifest, post_hook, path_limits, file_idx,
+    } = archive;
+    
+    // Имя архива дальше клеится к папкам приёма и распаковки
+    super::handlers::safe_relative_path(filename)?;
+    
+    // Путь к сырому архиву (для резюме) - только если включено сохранение.
+    // Это всегда .toolza-part: после распаковки он удаляется
+    let raw_file_path = super::handlers::part_path(&archive_dir.join(filename));
     
     // Проверяем есть ли частичный файл для резюме (только если сохраняем)
     let resume_offset = if save_archive {
@@ -297,7 +698,18 @@ pub(crate) async fn receive_and_extract_streaming_transport(
         0 // Резюме отключено
     };
     
-    // Отправляем ResumeAck или Ack
+    // Определяем тип архива для выбора правильной функции распаковки
+    let archive_type = ArchiveType::from_filename(filename);
+    
+    // Записи tar, распакованные в прошлую попытку, отправитель может не слать.
+    // Только без сохранения архива: иначе сохранённое не совпало бы с файлом отправителя
+    let manifest = if offer_manifest && !save_archive && matches!(archive_type, ArchiveType::TarLz4 | ArchiveType::TarZst) {
+        ExtractJournal::manifest(archive_dir, filename, size, extract_dir, &path_limits)
+    } else {
+        Vec::new()
+    };
+    
+    // Отправляем ResumeAck, ExtractManifest или Ack
     if resume_offset > 0 {
         let resume_ack = crate::protocol::Message::ResumeAck { offset: resume_offset };
         let data = resume_ack.to_bytes().map_err(|e| e.to_string())?;
@@ -306,6 +718,13 @@ pub(crate) async fn receive_and_extract_streaming_transport(
             format!("🔄 Возобновление с {:.2} ГБ", resume_offset as f64 / 1024.0 / 1024.0 / 1024.0),
             0
         ));
+    } else if !manifest.is_empty() {
+        let _ = event_tx.send(TransferEvent::FileReceived(
+            format!("🔄 Уже распаковано {} файл(ов): отправитель их не пришлёт", manifest.len()),
+            0
+        ));
+        let data = Message::ExtractManifest { entries: manifest }.to_bytes().map_err(|e| e.to_string())?;
+        stream.write_all(&data).await.map_err(|e| e.to_string())?;
     } else {
         super::send_ack_transport(stream).await?;
     }
@@ -332,25 +751,24 @@ pub(crate) async fn receive_and_extract_streaming_transport(
     };
     
     // Создаём канал для передачи данных в распаковщик (только если начинаем с нуля)
-    let (tx, rx) = std_mpsc::sync_channel::<Vec<u8>>(32);
+    let (tx, rx) = chunk_channel(EXTRACT_BUFFER_LIMIT);
     let streaming_extract = resume_offset == 0; // Потоковая распаковка только с начала
     
     // Запускаем распаковщик в отдельном потоке (если не резюме)
-    let output_dir = save_dir.clone();
+    let extract = ChannelExtract {
+        archive_type,
+        output_dir: extract_dir.to_path_buf(),
+        filename: filename.to_string(),
+        size,
+        journal_dir: archive_dir.to_path_buf(),
+        limits: path_limits,
+    };
     let event_tx_clone = event_tx.clone();
-    let filename_clone = filename.to_string();
-    
-    // Определяем тип архива для выбора правильной функции распаковки
-    let archive_type = crate::extract::ArchiveType::from_filename(filename);
-    let is_tar_zst = archive_type == crate::extract::ArchiveType::TarZst;
     
     let extract_handle = if streaming_extract {
         Some(std::thread::spawn(move || {
-            if is_tar_zst {
-                extract_from_channel_zst(rx, &output_dir, &filename_clone, &event_tx_clone)
-            } else {
-                extract_from_channel(rx, &output_dir, &filename_clone, &event_tx_clone)
-            }
+            // Время включает ожидание данных из сети: распаковка идёт параллельно приёму
+            metrics::time_extraction(|| extract_from_channel_by_type(rx, extract, &event_tx_clone))
         }))
     } else {
         drop(rx); // Не используем канал при резюме
@@ -435,8 +853,8 @@ pub(crate) async fn receive_and_extract_streaming_transport(
                     return Err("⛔ Остановлено пользователем".to_string());
                 }
                 
-                let chunk_data = if compressed {
-                    match compression::decompress(&data) {
+                let chunk_data = if let Some(codec) = codec {
+                    match codec.decompress(&data) {
                         Ok(d) => d,
                         Err(e) => {
                             if let Some(ref mut f) = raw_file { let _ = f.flush().await; }
@@ -457,17 +875,18 @@ pub(crate) async fn receive_and_extract_streaming_transport(
                 }
                 
                 received_bytes += chunk_data.len() as u64;
+                METRICS.add_bytes_received(chunk_data.len() as u64);
                 
                 if last_progress_update.elapsed().as_secs() >= 1 {
                     let _ = event_tx.send(TransferEvent::Progress(
-                        0, 0, received_bytes, size, received_bytes,
+                        0, file_idx, received_bytes, size, received_bytes,
                     ));
                     last_progress_update = Instant::now();
                 }
                 
                 // Отправляем в распаковщик только если потоковая распаковка
                 if streaming_extract {
-                    match tx.send(chunk_data) {
+                    match tx.send(chunk_data).await {
                         Ok(()) => {},
                         Err(_) => {
                             // Распаковщик завершился - это может быть ошибка распаковки
@@ -482,6 +901,16 @@ pub(crate) async fn receive_and_extract_streaming_transport(
                     }
                 }
             }
+            // Распакованное на лету не сверяется: отправитель предупредит об этом
+            Message::FileHash { .. } => {
+                let reply = Message::Error("SHA-256 не сверяется: архив распаковывается на лету".to_string());
+                let data = reply.to_bytes().map_err(|e| e.to_string())?;
+                if let Err(e) = stream.write_all(&data).await {
+                    if let Some(ref mut f) = raw_file { let _ = f.flush().await; }
+                    network_error = Some(e.to_string());
+                    break;
+                }
+            }
             Message::FileEnd => {
                 // Сбрасываем буфер файла
                 if let Some(ref mut f) = raw_file { let _ = f.flush().await; }
@@ -490,7 +919,7 @@ pub(crate) async fn receive_and_extract_streaming_transport(
                 let speed_mbps = if elapsed > 0.0 { (received_bytes - resume_offset) as f64 / elapsed / 1024.0 / 1024.0 } else { 0.0 };
                 
                 let _ = event_tx.send(TransferEvent::Progress(
-                    0, 0, received_bytes, size, received_bytes,
+                    0, file_idx, received_bytes, size, received_bytes,
                 ));
                 
                 // Закрываем канал
@@ -514,6 +943,7 @@ pub(crate) async fn receive_and_extract_streaming_transport(
                                     speed_mbps),
                                 received_bytes
                             ));
+                            post_hook.spawn(extract_dir, event_tx);
                         }
                         Ok(Err(e)) => {
                             let _ = event_tx.send(TransferEvent::ExtractionError(
@@ -538,21 +968,25 @@ pub(crate) async fn receive_and_extract_streaming_transport(
                     ));
                     
                     // Запускаем распаковку из файла
-                    let output_dir = save_dir.clone();
+                    let output_dir = extract_dir.to_path_buf();
                     let event_tx_clone = event_tx.clone();
                     let filename_clone = filename.to_string();
                     let raw_path = raw_file_path.clone();
+                    let journal_dir = archive_dir.to_path_buf();
+                    let post_hook = post_hook.clone();
                     
                     tokio::task::spawn_blocking(move || {
-                        match crate::extract::extract_tar_lz4_simple(&raw_path, &output_dir) {
+                        match metrics::time_extraction(|| extract_raw_file(&raw_path, archive_type, &output_dir, &filename_clone, &path_limits)) {
                             Ok(result) => {
+                                ExtractJournal::discard(&journal_dir, &filename_clone);
+                                report_skipped_entries(&filename_clone, &result, &event_tx_clone);
                                 let _ = event_tx_clone.send(TransferEvent::ExtractionCompleted(
                                     filename_clone,
-                                    result.files_count,
-                                    result.total_size,
+                                    result,
                                 ));
                                 // Удаляем raw файл после распаковки
                                 let _ = std::fs::remove_file(&raw_path);
+                                post_hook.spawn(&output_dir, &event_tx_clone);
                             }
                             Err(e) => {
                                 let _ = event_tx_clone.send(TransferEvent::ExtractionError(
@@ -600,189 +1034,177 @@ pub(crate) async fn receive_and_extract_streaming_transport(
     }
 }
 
-/// ИСТИННАЯ потоковая распаковка tar.lz4 (для TCP)
-pub(crate) async fn receive_and_extract_streaming_tcp(
-    reader: &mut tokio::net::tcp::OwnedReadHalf,
-    writer: &mut tokio::net::tcp::OwnedWriteHalf,
-    save_dir: &PathBuf,
-    filename: &str,
-    size: u64,
-    compressed: bool,
-    event_tx: &mpsc::UnboundedSender<TransferEvent>,
-) -> Result<(), String> {
-    use tokio::io::AsyncWriteExt;
-    
-    // Отправляем Ack
-    let ack = Message::Ack.to_bytes().map_err(|e| e.to_string())?;
-    writer.write_all(&ack).await.map_err(|e| e.to_string())?;
-    
-    let _ = event_tx.send(TransferEvent::ExtractionStarted(filename.to_string()));
-    
-    // Создаём канал для передачи данных в распаковщик
-    let (tx, rx) = std_mpsc::sync_channel::<Vec<u8>>(32);
-    
-    // Запускаем распаковщик в отдельном потоке
-    let output_dir = save_dir.clone();
-    let event_tx_clone = event_tx.clone();
-    let filename_clone = filename.to_string();
-    
-    // Определяем тип архива для выбора правильной функции распаковки
-    let archive_type = crate::extract::ArchiveType::from_filename(&filename);
-    let is_tar_zst = archive_type == crate::extract::ArchiveType::TarZst;
-    
-    let extract_handle = std::thread::spawn(move || {
-        if is_tar_zst {
-            extract_from_channel_zst(rx, &output_dir, &filename_clone, &event_tx_clone)
-        } else {
-            extract_from_channel(rx, &output_dir, &filename_clone, &event_tx_clone)
-        }
-    });
-    
-    // Читаем данные из сети и отправляем в канал
-    let mut received_bytes = 0u64;
-    let start_time = Instant::now();
-    let mut last_progress_update = Instant::now();
-    #[allow(unused_assignments)]
-    let mut network_error: Option<String> = None;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
     
-    loop {
-        let mut len_buf = [0u8; 4];
-        match reader.read_exact(&mut len_buf).await {
-            Ok(_) => {}
-            Err(e) => {
-                network_error = Some(e.to_string());
-                break;
+    /// Приём быстрее распаковки: очередь не растёт выше лимита, рантайм не блокируется
+    #[tokio::test]
+    async fn test_chunk_channel_bounds_queue() {
+        const LIMIT: usize = 1024 * 1024;
+        const CHUNK: usize = 256 * 1024;
+        const CHUNKS: usize = 64;
+        
+        let (tx, rx) = chunk_channel(LIMIT);
+        let slow_extractor = std::thread::spawn(move || {
+            let mut reader = ChannelReader::new(rx);
+            let mut buf = vec![0u8; 64 * 1024];
+            let mut total = 0;
+            loop {
+                let n = reader.read(&mut buf).unwrap();
+                if n == 0 {
+                    return total;
+                }
+                total += n;
+                std::thread::sleep(std::time::Duration::from_millis(1));
             }
-        }
-        let len = u32::from_le_bytes(len_buf) as usize;
+        });
         
-        let mut data = vec![0u8; len];
-        match reader.read_exact(&mut data).await {
-            Ok(_) => {}
-            Err(e) => {
-                network_error = Some(e.to_string());
-                break;
+        // Таймер в том же однопоточном рантайме тикает, пока приём ждёт распаковщика
+        let ticks = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let ticker = tokio::spawn({
+            let ticks = ticks.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                    ticks.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
             }
+        });
+        
+        let mut max_queued = 0;
+        for _ in 0..CHUNKS {
+            tx.send(vec![1u8; CHUNK]).await.unwrap();
+            max_queued = max_queued.max(LIMIT - tx.budget.available_permits());
         }
+        drop(tx);
         
-        let msg = match Message::from_bytes(&data) {
-            Ok(m) => m,
-            Err(e) => {
-                network_error = Some(e.to_string());
-                break;
-            }
+        assert_eq!(tokio::task::spawn_blocking(move || slow_extractor.join().unwrap()).await.unwrap(), CHUNK * CHUNKS);
+        assert!(max_queued <= LIMIT, "в очереди {} байт", max_queued);
+        assert!(ticks.load(std::sync::atomic::Ordering::Relaxed) > 0);
+        ticker.abort();
+    }
+    
+    #[test]
+    fn test_extract_journal_skips_extracted_files() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in [("a.txt", &b"hello"[..]), ("b.txt", &b"world!"[..])] {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(content.len() as u64);
+            builder.append_data(&mut header, name, content).unwrap();
+        }
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+        let archive = encoder.finish().unwrap();
+        let size = archive.len() as u64;
+        
+        let out = tempfile::tempdir().unwrap();
+        let journal_dir = tempfile::tempdir().unwrap();
+        
+        // Прошлая попытка успела записать a.txt и b.txt и оборвалась
+        let (a, b) = (out.path().join("a.txt"), out.path().join("b.txt"));
+        std::fs::write(&a, b"HELLO").unwrap();
+        std::fs::write(&b, b"WORLD!").unwrap();
+        let record = || {
+            let mut journal = ExtractJournal::open(journal_dir.path(), "data.tar.lz4", size);
+            journal.record(Path::new("a.txt"), &a, 5, 0);
+            journal.record(Path::new("b.txt"), &b, 6, 0);
         };
+        record();
         
-        match msg {
-            Message::FileChunk { data, original_size: _ } => {
-                let chunk_data = if compressed {
-                    match compression::decompress(&data) {
-                        Ok(d) => d,
-                        Err(e) => {
-                            network_error = Some(e);
-                            break;
-                        }
-                    }
-                } else {
-                    data
-                };
-                received_bytes += chunk_data.len() as u64;
-                
-                if last_progress_update.elapsed().as_secs() >= 1 {
-                    let _ = event_tx.send(TransferEvent::Progress(
-                        0, 0, received_bytes, size, received_bytes,
-                    ));
-                    last_progress_update = Instant::now();
-                }
-                
-                if tx.send(chunk_data).is_err() {
-                    return Err("Ошибка: распаковщик завершился раньше времени".to_string());
-                }
-            }
-            Message::FileEnd => {
-                let elapsed = start_time.elapsed().as_secs_f64();
-                let speed_mbps = if elapsed > 0.0 { received_bytes as f64 / elapsed / 1024.0 / 1024.0 } else { 0.0 };
-                
-                let _ = event_tx.send(TransferEvent::Progress(
-                    0, 0, received_bytes, size, received_bytes,
-                ));
-                
-                // Закрываем канал и ждём завершения распаковки
-                drop(tx);
-                let _ = event_tx.send(TransferEvent::FileReceived(
-                    "⏳ Завершение распаковки...".to_string(), 0
-                ));
-                
-                match extract_handle.join() {
-                    Ok(Ok(())) => {
-                        let _ = event_tx.send(TransferEvent::FileReceived(
-                            format!("✅ Потоковая распаковка завершена: {:.2} ГБ @ {:.1} MB/s", 
-                                received_bytes as f64 / 1024.0 / 1024.0 / 1024.0,
-                                speed_mbps),
-                            received_bytes
-                        ));
-                    }
-                    Ok(Err(e)) => {
-                        let _ = event_tx.send(TransferEvent::ExtractionError(
-                            filename.to_string(),
-                            e,
-                        ));
-                    }
-                    Err(_) => {
-                        let _ = event_tx.send(TransferEvent::ExtractionError(
-                            filename.to_string(),
-                            "Поток распаковки завершился с паникой".to_string(),
-                        ));
-                    }
-                }
-                
-                writer.write_all(&ack).await.map_err(|e| e.to_string())?;
-                return Ok(());
-            }
-            _ => {
-                network_error = Some("Неожиданное сообщение при получении файла".to_string());
-                break;
+        // Журнал другого архива с тем же именем не используется
+        assert_eq!(ExtractJournal::open(journal_dir.path(), "data.tar.lz4", size + 1).restored(), (0, 0));
+        record();
+        
+        // b.txt с тех пор изменён (тот же размер, другой mtime) - его распакуем заново
+        let earlier = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        std::fs::File::options().write(true).open(&b).unwrap().set_modified(earlier).unwrap();
+        let manifest = ExtractJournal::manifest(journal_dir.path(), "data.tar.lz4", size, out.path(), &PathLimits::default());
+        assert_eq!(manifest, vec![ManifestEntry { path: "a.txt".to_string(), size: 5, mtime: 0 }]);
+        
+        let (tx, rx) = chunk_channel(EXTRACT_BUFFER_LIMIT);
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(tx.send(archive)).unwrap();
+        drop(tx);
+        let extract = ChannelExtract {
+            archive_type: ArchiveType::TarLz4,
+            output_dir: out.path().to_path_buf(),
+            filename: "data.tar.lz4".to_string(),
+            size,
+            journal_dir: journal_dir.path().to_path_buf(),
+            limits: PathLimits::default(),
+        };
+        extract_from_channel_by_type(rx, extract, &event_tx).unwrap();
+        
+        // a.txt не перезаписан, b.txt распакован заново, журнал удалён
+        assert_eq!(std::fs::read(out.path().join("a.txt")).unwrap(), b"HELLO");
+        assert_eq!(std::fs::read(out.path().join("b.txt")).unwrap(), b"world!");
+        assert_eq!(std::fs::read_dir(journal_dir.path()).unwrap().count(), 0);
+        let mut completed = None;
+        while let Ok(event) = event_rx.try_recv() {
+            if let TransferEvent::ExtractionCompleted(_, result) = event {
+                completed = Some(result.files_count);
             }
         }
+        assert_eq!(completed, Some(2));
     }
     
-    // Если вышли из цикла с ошибкой сети - всё равно ждём завершения распаковки
-    drop(tx);
-    let _ = event_tx.send(TransferEvent::FileReceived(
-        format!("⚠️ Клиент отключился, завершаем распаковку ({:.2} ГБ получено)...", 
-            received_bytes as f64 / 1024.0 / 1024.0 / 1024.0),
-        0
-    ));
-    
-    match extract_handle.join() {
-        Ok(Ok(())) => {
-            let elapsed = start_time.elapsed().as_secs_f64();
-            let speed_mbps = if elapsed > 0.0 { received_bytes as f64 / elapsed / 1024.0 / 1024.0 } else { 0.0 };
-            let _ = event_tx.send(TransferEvent::FileReceived(
-                format!("✅ Распаковка завершена (частичная): {:.2} ГБ @ {:.1} MB/s", 
-                    received_bytes as f64 / 1024.0 / 1024.0 / 1024.0,
-                    speed_mbps),
-                received_bytes
-            ));
-        }
-        Ok(Err(e)) => {
-            let _ = event_tx.send(TransferEvent::ExtractionError(
-                filename.to_string(),
-                format!("Ошибка распаковки после отключения: {}", e),
-            ));
+    #[tokio::test]
+    async fn test_sender_skips_entries_from_manifest() {
+        use crate::network::receiver::spawn_test_server;
+        use crate::network::sender::send_files_to_target_with_options;
+        use crate::network::{SendOptions, ServerOptions};
+        use crate::protocol::FileInfo;
+        
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in [("a.txt", &b"hello"[..]), ("b.txt", &b"world!"[..])] {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(content.len() as u64);
+            builder.append_data(&mut header, name, content).unwrap();
         }
-        Err(_) => {
-            let _ = event_tx.send(TransferEvent::ExtractionError(
-                filename.to_string(),
-                "Поток распаковки завершился с паникой".to_string(),
-            ));
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+        let archive = encoder.finish().unwrap();
+        let archive_path = src.path().join("data.tar.lz4");
+        std::fs::write(&archive_path, &archive).unwrap();
+        
+        // Прошлая попытка успела распаковать a.txt и оборвалась
+        let a = dst.path().join("a.txt");
+        std::fs::write(&a, b"HELLO").unwrap();
+        ExtractJournal::open(dst.path(), "data.tar.lz4", archive.len() as u64).record(Path::new("a.txt"), &a, 5, 0);
+        
+        let options = ServerOptions::builder().auto_extract_tar_lz4(true).build().unwrap();
+        let (server, mut server_rx) = spawn_test_server(dst.path(), options).await;
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let files = vec![FileInfo::new(archive_path).unwrap()];
+        send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx).await.unwrap();
+        
+        // a.txt отправитель не прислал вовсе: распакован только b.txt
+        assert_eq!(std::fs::read(&a).unwrap(), b"HELLO");
+        assert_eq!(std::fs::read(dst.path().join("b.txt")).unwrap(), b"world!");
+        let mut completed = None;
+        while let Ok(event) = server_rx.try_recv() {
+            if let TransferEvent::ExtractionCompleted(_, result) = event {
+                completed = Some(result.files_count);
+            }
         }
+        assert_eq!(completed, Some(1));
+        
+        server.stop().await;
     }
     
-    if let Some(err) = network_error {
-        Err(err)
-    } else {
-        Ok(())
+    #[tokio::test]
+    async fn test_chunk_channel_send_fails_after_extractor_exit() {
+        let (tx, rx) = chunk_channel(1024);
+        tx.send(vec![0u8; 1024]).await.unwrap();
+        drop(rx);
+        // Очередь отброшена вместе с разрешениями - отправка не зависает
+        assert!(tx.send(vec![0u8; 1024]).await.is_err());
     }
 }
-
@@ -0,0 +1,351 @@
+//! Веб-интерфейс получателя (`ServerOptions::web_addr`, фича `webui`)
+//!
+//! Крохотная страница, куда браузер перетаскивает файлы. Они приходят
+//! multipart-запросом и сохраняются в `save_dir` как при обычной передаче:
+//! шаблон имени, безопасные имена, распаковка архивов, post-hook, аудит.
+//! Только приём - отдавать файлы страница не умеет.
+//!
+//! Загрузка принимается только с токеном, который сервер вписывает в свою страницу
+//! (`X-Toolza-Token`), и без чужого `Origin`: иначе любой открытый в браузере сайт
+//! мог бы отправить форму на адрес получателя в локальной сети. `Host` - только
+//! IP-адрес получателя или его имя: чужой домен, перенаправленный через DNS на
+//! адрес получателя (DNS rebinding), получил бы и страницу с токеном
+
+use crate::network::events::TransferEvent;
+use crate::network::metrics::METRICS;
+use crate::protocol::ConnectionIntent;
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Multipart, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::Html;
+use axum::routing::get;
+use axum::Router;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use super::audit::AuditLog;
+use super::handlers::{checked_save_name, finalize_part, part_path, spawn_extract};
+use super::hook::PostHook;
+use super::options::ServerOptions;
+use super::session::ClientSession;
+
+/// Страница загрузки (`{{TOKEN}}` - токен сервера)
+const UPLOAD_PAGE: &str = include_str!("web.html");
+
+/// Заголовок с токеном страницы в `POST /`
+const TOKEN_HEADER: &str = "x-toolza-token";
+
+/// Всё, что нужно обработчику загрузки
+struct WebState {
+    save_dir: PathBuf,
+    options: ServerOptions,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+    audit: AuditLog,
+    hook: PostHook,
+    /// Токен, выданный страницей этого запуска
+    token: String,
+    /// Адрес, на котором слушает страница
+    local_addr: SocketAddr,
+    /// Допустимые имена в `Host` (в нижнем регистре), кроме IP-адресов
+    hosts: Vec<String>,
+}
+
+/// Запустить веб-интерфейс на `addr`: `GET /` - страница, `POST /` - загрузка.
+///
+/// Адрес занимается сразу (ошибка - если порт занят), запросы обслуживаются
+/// в фоне до установки `stop_flag`. Возвращает фактический адрес.
+pub(crate) async fn spawn_web_ui(
+    addr: SocketAddr,
+    save_dir: PathBuf,
+    options: ServerOptions,
+    stop_flag: Arc<AtomicBool>,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+    audit: &AuditLog,
+    hook: &PostHook,
+) -> Result<SocketAddr, String> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Не удалось запустить веб-интерфейс на {}: {}", addr, e))?;
+    let local_addr = listener.local_addr().map_err(|e| e.to_string())?;
+    
+    let token = crate::utils::random_token().map_err(|e| format!("Не удалось создать токен страницы: {}", e))?;
+    let hosts = allowed_hosts(&options.web_hosts);
+    let state = Arc::new(WebState { save_dir, options, event_tx: event_tx.clone(), audit: audit.clone(), hook: hook.clone(), token, local_addr, hosts });
+    let app = Router::new()
+        .route("/", get(page).post(upload))
+        // Размер файлов не ограничиваем: они пишутся на диск по мере прихода
+        .layer(DefaultBodyLimit::disable())
+        .with_state(state);
+    
+    tokio::spawn(async move {
+        let stopped = async move {
+            while !stop_flag.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+        };
+        let _ = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(stopped)
+            .await;
+    });
+    
+    Ok(local_addr)
+}
+
+/// `GET /`: страница с токеном этого запуска
+async fn page(State(state): State<Arc<WebState>>, headers: HeaderMap) -> Result<Html<String>, (StatusCode, String)> {
+    check_host(&headers, state.local_addr, &state.hosts).map_err(|reason| (StatusCode::FORBIDDEN, reason))?;
+    Ok(Html(UPLOAD_PAGE.replace("{{TOKEN}}", &state.token)))
+}
+
+/// `POST /`: принять файлы из multipart-формы
+async fn upload(
+    State(state): State<Arc<WebState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<String, (StatusCode, String)> {
+    if let Err(reason) = check_host(&headers, state.local_addr, &state.hosts).and_then(|()| check_request(&headers, &state.token)) {
+        state.audit.rejected(&peer.to_string(), None, &reason);
+        return Err((StatusCode::FORBIDDEN, reason));
+    }
+    let session = ClientSession::new(peer.to_string(), format!("{} [HTTP]", peer), state.event_tx.clone(), state.audit.clone(), state.hook.clone());
+    session.classify(ConnectionIntent::Transfer);
+    
+    let result = receive_uploads(&state, &session, &mut multipart).await;
+    if let Err(e) = &result {
+        session.audit.error(&session.peer, e);
+        let _ = state.event_tx.send(TransferEvent::ConnectionError(0, e.clone()));
+    }
+    session.close();
+    
+    result
+        .map(|count| format!("Принято файлов: {}\n", count))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+/// Сохранить все файлы формы, вернуть их число
+async fn receive_uploads(state: &WebState, session: &ClientSession, multipart: &mut Multipart) -> Result<usize, String> {
+    let mut count = 0;
+    while let Some(mut field) = multipart.next_field().await.map_err(|e| e.to_string())? {
+        // Поля без файла (и пустые имена) пропускаем; путь, если браузер его прислал, отбрасываем
+        let Some(filename) = field.file_name().and_then(upload_name) else {
+            continue;
+        };
+        
+        // Размер заранее неизвестен - предел проверяется по мере приёма
+        let name = match checked_save_name(&state.options, &filename, None, 0, &session.peer, &state.event_tx) {
+            Ok((name, _)) => name,
+            Err(reason) => {
+                session.audit.rejected(&session.peer, Some(&filename), &reason);
+                let _ = state.event_tx.send(TransferEvent::FileRejected(filename.clone(), reason.clone()));
+                return Err(format!("{}: {}", filename, reason));
+            }
+        };
+        let file_path = state.save_dir.join(name.replace('/', std::path::MAIN_SEPARATOR_STR));
+        if let Some(parent) = file_path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Не удалось создать папку: {}", e))?;
+        }
+        
+        // Как и при обычной передаче: итоговое имя появляется только у полного файла
+        let part_file_path = part_path(&file_path);
+        let mut file = tokio::fs::File::create(&part_file_path)
+            .await
+            .map_err(|e| format!("Не удалось создать файл: {}", e))?;
+        let mut size = 0u64;
+        let written: Result<(), String> = async {
+            while let Some(chunk) = field.chunk().await.map_err(|e| e.to_string())? {
+                file.write_all(&chunk).await.map_err(|e| format!("Ошибка записи: {}", e))?;
+                METRICS.add_bytes_received(chunk.len() as u64);
+                size += chunk.len() as u64;
+                if let Some(reason) = state.options.reject_reason(&name, size) {
+                    session.audit.rejected(&session.peer, Some(&filename), &reason);
+                    let _ = state.event_tx.send(TransferEvent::FileRejected(filename.clone(), reason.clone()));
+                    return Err(format!("{}: {}", filename, reason));
+                }
+            }
+            file.flush().await.map_err(|e| format!("Ошибка записи: {}", e))
+        }.await;
+        drop(file);
+        METRICS.record_transfer(written.is_ok());
+        if let Err(e) = written {
+            // Браузер докачивать не умеет - частичный файл не нужен
+            let _ = tokio::fs::remove_file(&part_file_path).await;
+            return Err(e);
+        }
+        finalize_part(&part_file_path, &file_path).await?;
+        
+        session.audit.file_received(&session.peer, &name, size);
+        let _ = state.event_tx.send(TransferEvent::FileReceived(name, size));
+        if state.options.should_extract(&filename) {
+            spawn_extract(file_path, filename, &state.options, &state.save_dir, state.options.extract_limits(), &session.hook, &state.event_tx);
+        } else {
+            session.hook.spawn(&file_path, &state.event_tx);
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Запрос со страницы этого получателя: верный токен и `Origin` (если браузер
+/// его прислал) того же хоста, что `Host`
+fn check_request(headers: &HeaderMap, token: &str) -> Result<(), String> {
+    let value = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if value(TOKEN_HEADER) != Some(token) {
+        return Err("Загрузка без токена страницы".to_string());
+    }
+    if let Some(origin) = value(header::ORIGIN.as_str()) {
+        let origin_host = origin.strip_prefix("http://").unwrap_or(origin);
+        if value(header::HOST.as_str()) != Some(origin_host) {
+            return Err(format!("Загрузка со стороннего сайта: {}", origin));
+        }
+    }
+    Ok(())
+}
+
+/// Имена страницы: `localhost`, имя машины (и `.local` для mDNS) и `web_hosts`
+fn allowed_hosts(extra: &[String]) -> Vec<String> {
+    let mut hosts = vec!["localhost".to_string()];
+    if let Ok(hostname) = dns_lookup::get_hostname() {
+        hosts.push(format!("{}.local", hostname));
+        hosts.push(hostname);
+    }
+    hosts.extend(extra.iter().map(|name| name.trim().trim_end_matches('.').to_string()));
+    hosts.iter().map(|name| name.to_ascii_lowercase()).collect()
+}
+
+/// `Host` - адрес самой страницы: IP, на котором она слушает (любой IP машины,
+/// если слушает на всех), или одно из допустимых имён. Порт не сверяется
+fn check_host(headers: &HeaderMap, local_addr: SocketAddr, hosts: &[String]) -> Result<(), String> {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| "Запрос без заголовка Host".to_string())?;
+    // "имя:порт", "[IPv6]:порт" или без порта
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    let name = name.trim_start_matches('[').trim_end_matches(']').trim_end_matches('.').to_ascii_lowercase();
+    let allowed = match name.parse::<IpAddr>() {
+        Ok(ip) => local_addr.ip().is_unspecified() || ip == local_addr.ip(),
+        Err(_) => hosts.contains(&name),
+    };
+    if !allowed {
+        return Err(format!("Чужое имя страницы: {}", host));
+    }
+    Ok(())
+}
+
+/// Имя загружаемого файла без пути (браузеры на Windows иногда шлют путь целиком)
+fn upload_name(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    (!name.is_empty() && name != "." && name != "..").then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    
+    #[test]
+    fn test_upload_name() {
+        assert_eq!(upload_name("photo.jpg").as_deref(), Some("photo.jpg"));
+        assert_eq!(upload_name("C:\\Users\\me\\photo.jpg").as_deref(), Some("photo.jpg"));
+        assert_eq!(upload_name("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(upload_name(".."), None);
+        assert_eq!(upload_name(""), None);
+    }
+    
+    #[test]
+    fn test_check_host() {
+        let hosts = allowed_hosts(&["Files.LAN".to_string()]);
+        let check = |host: &str, local: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::HOST, host.parse().unwrap());
+            check_host(&headers, local.parse().unwrap(), &hosts).is_ok()
+        };
+        assert!(check("localhost:8080", "127.0.0.1:8080"));
+        assert!(check("files.lan:8080", "0.0.0.0:8080"));
+        assert!(check("192.168.1.5:8080", "0.0.0.0:8080"));
+        assert!(check("[::1]:8080", "[::]:8080"));
+        assert!(check("127.0.0.1", "127.0.0.1:80"));
+        // Домен атакующего, указывающий на адрес получателя
+        assert!(!check("rebind.example:8080", "0.0.0.0:8080"));
+        assert!(!check("192.168.1.6:8080", "192.168.1.5:8080"));
+        assert!(check_host(&HeaderMap::new(), "127.0.0.1:8080".parse().unwrap(), &hosts).is_err());
+    }
+    
+    /// Один HTTP-запрос, ответ целиком
+    async fn http(addr: SocketAddr, request: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+    
+    /// POST формы с одним файлом; `headers` - дополнительные заголовки
+    fn upload_request(headers: &str) -> String {
+        let boundary = "toolzaboundary";
+        let body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"note.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\nhello from a browser\r\n--{b}--\r\n",
+            b = boundary
+        );
+        format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{}\
+             Content-Type: multipart/form-data; boundary={}\r\nContent-Length: {}\r\n\r\n{}",
+            headers, boundary, body.len(), body
+        )
+    }
+    
+    #[tokio::test]
+    async fn test_web_upload_saves_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let audit = AuditLog::default();
+        let hook = PostHook::new(&ServerOptions::default());
+        let addr = spawn_web_ui(
+            "127.0.0.1:0".parse().unwrap(), dir.path().to_path_buf(), ServerOptions::default(), stop_flag.clone(), &tx, &audit, &hook,
+        ).await.unwrap();
+        
+        // Страница под чужим именем не отдаётся
+        let page = http(addr, "GET / HTTP/1.1\r\nHost: rebind.example\r\nConnection: close\r\n\r\n").await;
+        assert!(page.starts_with("HTTP/1.1 403"), "{}", page);
+        
+        // Токен - со страницы
+        let page = http(addr, "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await;
+        let token = page.split("name=\"toolza-token\" content=\"").nth(1).unwrap().split('"').next().unwrap().to_string();
+        assert_eq!(token.len(), 64);
+        
+        // Без токена и с чужого сайта загрузка не принимается
+        for headers in [String::new(), format!("X-Toolza-Token: {}\r\nOrigin: http://evil.example\r\n", token)] {
+            let response = http(addr, &upload_request(&headers)).await;
+            assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+        }
+        assert!(!dir.path().join("note.txt").exists());
+        
+        let headers = format!("X-Toolza-Token: {}\r\nOrigin: http://localhost\r\n", token);
+        let response = http(addr, &upload_request(&headers)).await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert_eq!(std::fs::read_to_string(dir.path().join("note.txt")).unwrap(), "hello from a browser");
+        let mut received = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                TransferEvent::ClientConnected(_, intent) => assert_eq!(intent, ConnectionIntent::Transfer),
+                TransferEvent::FileReceived(name, size) => received.push((name, size)),
+                _ => {}
+            }
+        }
+        assert_eq!(received, vec![("note.txt".to_string(), 20)]);
+        
+        stop_flag.store(true, Ordering::SeqCst);
+    }
+}
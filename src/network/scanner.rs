@@ -1,22 +1,45 @@
 //! Сканирование локальной сети
 
 use super::events::TransferEvent;
+use super::transport::{self, SocketTuning, TransportStream, TransportType};
+use crate::protocol::{ConnectionIntent, Message};
 use crate::utils::get_local_ip;
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::time::Duration;
-use tokio::net::TcpStream;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
-/// Подсеть для сканирования (первые 3 октета)
-#[derive(Clone, Debug)]
+/// Таймаут проверки одного адреса при сканировании
+const PROBE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Сколько ждать имени найденного сервера от обратного DNS
+const HOSTNAME_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Тип службы, под которым получатели объявляют себя в mDNS
+pub const MDNS_SERVICE_TYPE: &str = "_toolza._tcp.local.";
+
+/// Сколько по умолчанию слушать ответы mDNS при поиске
+pub const MDNS_BROWSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Подсеть для сканирования (первые 3 октета и диапазон хостов)
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Subnet {
     pub octets: [u8; 3],
+    /// Первый проверяемый хост (последний октет)
+    pub first: u8,
+    /// Последний проверяемый хост (включительно)
+    pub last: u8,
 }
 
 impl Subnet {
-    /// Создать подсеть из первых трёх октетов
+    /// Создать подсеть из первых трёх октетов (хосты 1-254)
     pub fn new(a: u8, b: u8, c: u8) -> Self {
-        Self { octets: [a, b, c] }
+        Self::with_range(a, b, c, 1, 254)
+    }
+    
+    /// Создать подсеть с диапазоном хостов first..=last
+    pub fn with_range(a: u8, b: u8, c: u8, first: u8, last: u8) -> Self {
+        Self { octets: [a, b, c], first, last }
     }
     
     /// Парсить строку подсети
@@ -25,9 +48,18 @@ impl Subnet {
     /// - "192.168.1.0"
     /// - "192.168.1.0/24"
     /// - "192.168.1.x"
+    /// - "192.168.1.10-20" и "192.168.1.10-192.168.1.20" (диапазон хостов)
+    /// - "192.168.1.15" (один хост)
     pub fn parse(s: &str) -> Option<Self> {
-        // Убираем /24 и подобные суффиксы
-        let s = s.split('/').next().unwrap_or(s);
+        // Убираем /24 и подобные суффиксы - CIDR всегда означает всю подсеть
+        let (s, cidr) = match s.split_once('/') {
+            Some((addr, _)) => (addr, true),
+            None => (s, false),
+        };
+        let (s, range_end) = match s.split_once('-') {
+            Some((start, end)) => (start, Some(end)),
+            None => (s, None),
+        };
         
         let parts: Vec<&str> = s.split('.').collect();
         if parts.len() < 3 {
@@ -37,8 +69,39 @@ impl Subnet {
         let a: u8 = parts[0].parse().ok()?;
         let b: u8 = parts[1].parse().ok()?;
         let c: u8 = parts[2].parse().ok()?;
+        let host: Option<u8> = parts.get(3).and_then(|h| h.parse().ok());
+        
+        if let Some(end) = range_end {
+            if cidr {
+                return None;
+            }
+            let first = host?;
+            let last = if end.contains('.') {
+                // Полный адрес конца диапазона - должен быть в той же /24
+                let end_ip: Ipv4Addr = end.parse().ok()?;
+                let o = end_ip.octets();
+                if [o[0], o[1], o[2]] != [a, b, c] {
+                    return None;
+                }
+                o[3]
+            } else {
+                end.parse().ok()?
+            };
+            if first > last {
+                return None;
+            }
+            return Some(Self::with_range(a, b, c, first, last));
+        }
         
-        Some(Self::new(a, b, c))
+        match host {
+            Some(h) if h != 0 && !cidr => Some(Self::with_range(a, b, c, h, h)),
+            _ => Some(Self::new(a, b, c)),
+        }
+    }
+    
+    /// Количество проверяемых хостов
+    pub fn host_count(&self) -> usize {
+        (self.last as usize + 1).saturating_sub(self.first as usize)
     }
     
     /// Получить базовый адрес как строку
@@ -54,7 +117,14 @@ impl Subnet {
 
 impl std::fmt::Display for Subnet {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}.{}.{}.0/24", self.octets[0], self.octets[1], self.octets[2])
+        let [a, b, c] = self.octets;
+        if self.first == 1 && self.last == 254 {
+            write!(f, "{}.{}.{}.0/24", a, b, c)
+        } else if self.first == self.last {
+            write!(f, "{}.{}.{}.{}", a, b, c, self.first)
+        } else {
+            write!(f, "{}.{}.{}.{}-{}", a, b, c, self.first, self.last)
+        }
     }
 }
 
@@ -93,41 +163,43 @@ pub async fn scan_subnets(
     
     let total_subnets = subnets.len();
     let mut found_servers = Vec::new();
+    let mut lookups = Vec::new();
     let mut last_progress_update = std::time::Instant::now();
     
     for (subnet_idx, subnet) in subnets.iter().enumerate() {
         let base_ip = subnet.base();
         
         let _ = event_tx.send(TransferEvent::ScanProgress(
-            format!("Подсеть {}/{}: {}0/24", subnet_idx + 1, total_subnets, base_ip),
+            format!("Подсеть {}/{}: {}", subnet_idx + 1, total_subnets, subnet),
             0,
         ));
         
         // Сканируем пакетами по 32 адреса
-        let batch_size = 32;
+        let hosts: Vec<u8> = (subnet.first..=subnet.last).collect();
+        let total_hosts = hosts.len().max(1);
         
-        for batch_start in (1u8..255).step_by(batch_size) {
-            let batch_end = (batch_start as usize + batch_size).min(255) as u8;
-            
+        for (batch_idx, batch) in hosts.chunks(32).enumerate() {
             let mut handles = Vec::new();
             
-            for i in batch_start..batch_end {
+            for &i in batch {
                 let ip = subnet.ip(i);
                 let handle = tokio::spawn(check_server(ip, port));
                 handles.push(handle);
             }
+            let batch_end = batch.last().copied().unwrap_or(subnet.last);
+            let done = batch_idx * 32 + batch.len();
             
             // Собираем результаты пакета
             for handle in handles {
                 if let Ok(Some(addr)) = handle.await {
-                    found_servers.push(addr.clone());
-                    let _ = event_tx.send(TransferEvent::ServerFound(addr));
+                    report_found(&addr, &event_tx, &mut lookups);
+                    found_servers.push(addr);
                 }
             }
             
             // Обновляем прогресс раз в секунду
             if last_progress_update.elapsed().as_secs() >= 1 {
-                let subnet_progress = (batch_end as f32 / 254.0) * 100.0;
+                let subnet_progress = (done as f32 / total_hosts as f32) * 100.0;
                 let total_progress = ((subnet_idx as f32 + subnet_progress / 100.0) / total_subnets as f32 * 100.0) as u8;
                 let _ = event_tx.send(TransferEvent::ScanProgress(
                     format!("{}{}", base_ip, batch_end),
@@ -138,25 +210,239 @@ pub async fn scan_subnets(
         }
     }
     
+    // Имена - до ScanCompleted, каждое ждём не дольше HOSTNAME_TIMEOUT
+    for lookup in lookups {
+        let _ = lookup.await;
+    }
+    let _ = event_tx.send(TransferEvent::ScanCompleted);
+    
+    Ok(found_servers)
+}
+
+/// Сканировать явный список адресов (IP или IP:порт)
+///
+/// Адреса без порта проверяются на `port`. Пустые строки пропускаются.
+pub async fn scan_hosts(
+    hosts: Vec<String>,
+    port: u16,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+) -> Result<Vec<String>, String> {
+    let addrs: Vec<String> = hosts
+        .iter()
+        .map(|h| h.trim())
+        .filter(|h| !h.is_empty())
+        .map(|h| with_default_port(h, port))
+        .collect();
+    
+    if addrs.is_empty() {
+        return Err("Не указаны адреса для сканирования".to_string());
+    }
+    
+    let total = addrs.len();
+    let mut found_servers = Vec::new();
+    let mut lookups = Vec::new();
+    let mut last_progress_update = std::time::Instant::now();
+    
+    // Сканируем пакетами по 32 адреса
+    for (batch_idx, batch) in addrs.chunks(32).enumerate() {
+        let handles: Vec<_> = batch
+            .iter()
+            .map(|addr| tokio::spawn(check_addr(addr.clone())))
+            .collect();
+        
+        for handle in handles {
+            if let Ok(Some(addr)) = handle.await {
+                report_found(&addr, &event_tx, &mut lookups);
+                found_servers.push(addr);
+            }
+        }
+        
+        // Обновляем прогресс раз в секунду
+        if last_progress_update.elapsed().as_secs() >= 1 {
+            let done = (batch_idx * 32 + batch.len()).min(total);
+            let _ = event_tx.send(TransferEvent::ScanProgress(
+                batch.last().cloned().unwrap_or_default(),
+                (done as f32 / total as f32 * 100.0) as u8,
+            ));
+            last_progress_update = std::time::Instant::now();
+        }
+    }
+    
+    // Имена - до ScanCompleted, каждое ждём не дольше HOSTNAME_TIMEOUT
+    for lookup in lookups {
+        let _ = lookup.await;
+    }
     let _ = event_tx.send(TransferEvent::ScanCompleted);
     
     Ok(found_servers)
 }
 
+/// Сообщить о найденном сервере и начать поиск его имени в фоне,
+/// чтобы медленный резолвер не тормозил сканирование
+fn report_found(addr: &str, event_tx: &mpsc::UnboundedSender<TransferEvent>, lookups: &mut Vec<JoinHandle<()>>) {
+    let _ = event_tx.send(TransferEvent::ServerFound(addr.to_string()));
+    let (addr, event_tx) = (addr.to_string(), event_tx.clone());
+    lookups.push(tokio::spawn(async move {
+        if let Some(hostname) = lookup_hostname(&addr).await {
+            let _ = event_tx.send(TransferEvent::ServerFoundNamed(addr, hostname));
+        }
+    }));
+}
+
+/// Имя хоста по адресу "IP:порт" или "IP" через обратный DNS.
+/// None - имени нет, резолвер не ответил за `HOSTNAME_TIMEOUT` или адрес не IP
+pub async fn lookup_hostname(addr: &str) -> Option<String> {
+    let ip = addr.parse::<SocketAddr>().map(|addr| addr.ip()).or_else(|_| addr.parse::<IpAddr>()).ok()?;
+    let lookup = tokio::task::spawn_blocking(move || dns_lookup::lookup_addr(&ip));
+    let name = tokio::time::timeout(HOSTNAME_TIMEOUT, lookup).await.ok()?.ok()?.ok()?;
+    // Без записи резолвер возвращает сам адрес
+    (name != ip.to_string()).then_some(name)
+}
+
+/// Подпись сервера для списков: "desktop-pc (192.168.1.50:9527)" или просто адрес
+pub fn server_label(addr: &str, hostname: Option<&str>) -> String {
+    match hostname {
+        Some(hostname) => format!("{} ({})", hostname, addr),
+        None => addr.to_string(),
+    }
+}
+
+/// Объявление получателя в mDNS (фича `mdns`); снимается при удалении
+#[cfg(feature = "mdns")]
+pub struct MdnsAdvertisement {
+    daemon: mdns_sd::ServiceDaemon,
+    fullname: String,
+}
+
+#[cfg(feature = "mdns")]
+impl MdnsAdvertisement {
+    /// Полное имя службы, например `desktop-pc-9527._toolza._tcp.local.`
+    pub fn fullname(&self) -> &str {
+        &self.fullname
+    }
+}
+
+#[cfg(feature = "mdns")]
+impl Drop for MdnsAdvertisement {
+    fn drop(&mut self) {
+        // Команды демон выполняет по порядку: прощальный пакет уйдёт до остановки
+        let _ = self.daemon.unregister(&self.fullname);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// Объявить получателя на `port` как `_toolza._tcp.local` со всеми адресами машины
+#[cfg(feature = "mdns")]
+pub fn advertise_mdns(port: u16) -> Result<MdnsAdvertisement, String> {
+    let daemon = mdns_sd::ServiceDaemon::new().map_err(|e| format!("mDNS недоступен: {}", e))?;
+    let host = dns_lookup::get_hostname().unwrap_or_else(|_| "toolza".to_string());
+    // Порт в имени: несколько получателей на одной машине не конфликтуют
+    let info = mdns_sd::ServiceInfo::new(
+        MDNS_SERVICE_TYPE,
+        &format!("{}-{}", host, port),
+        &format!("{}.local.", host),
+        (),
+        port,
+        None,
+    )
+    .map_err(|e| format!("mDNS: {}", e))?
+    .enable_addr_auto();
+    let fullname = info.get_fullname().to_string();
+    daemon.register(info).map_err(|e| format!("mDNS: {}", e))?;
+    Ok(MdnsAdvertisement { daemon, fullname })
+}
+
+/// Найти получателей через mDNS за `timeout`: в отличие от сканирования
+/// подсети находит и хосты в других подсетях того же сегмента.
+///
+/// Сообщает `ServerFound` и `ServerFoundNamed` (имя из mDNS, без обратного DNS),
+/// в конце - `ScanCompleted`. Адреса только IPv4
+#[cfg(feature = "mdns")]
+pub async fn discover_mdns(
+    timeout: Duration,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+) -> Result<Vec<String>, String> {
+    use mdns_sd::ServiceEvent;
+    
+    let daemon = mdns_sd::ServiceDaemon::new().map_err(|e| format!("mDNS недоступен: {}", e))?;
+    let events = daemon.browse(MDNS_SERVICE_TYPE).map_err(|e| format!("mDNS: {}", e))?;
+    let _ = event_tx.send(TransferEvent::ScanProgress("mDNS".to_string(), 0));
+    
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut found_servers = Vec::new();
+    while let Ok(Ok(event)) = tokio::time::timeout_at(deadline, events.recv_async()).await {
+        let ServiceEvent::ServiceResolved(info) = event else { continue };
+        let hostname = info.get_hostname().trim_end_matches('.').trim_end_matches(".local");
+        for ip in info.get_addresses_v4() {
+            let addr = format!("{}:{}", ip, info.get_port());
+            if found_servers.contains(&addr) {
+                continue;
+            }
+            let _ = event_tx.send(TransferEvent::ServerFound(addr.clone()));
+            if !hostname.is_empty() {
+                let _ = event_tx.send(TransferEvent::ServerFoundNamed(addr.clone(), hostname.to_string()));
+            }
+            found_servers.push(addr);
+        }
+    }
+    
+    let _ = daemon.stop_browse(MDNS_SERVICE_TYPE);
+    let _ = daemon.shutdown();
+    let _ = event_tx.send(TransferEvent::ScanCompleted);
+    Ok(found_servers)
+}
+
+/// Без фичи `mdns` поиск недоступен
+#[cfg(not(feature = "mdns"))]
+pub async fn discover_mdns(
+    _timeout: Duration,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+) -> Result<Vec<String>, String> {
+    let _ = event_tx.send(TransferEvent::ScanCompleted);
+    Err("Поиск через mDNS недоступен: соберите с --features mdns".to_string())
+}
+
+/// Добавить порт к адресу, если он не указан. Неразборчивый адрес проверяется
+/// как есть - он просто окажется недоступен
+fn with_default_port(host: &str, port: u16) -> String {
+    super::parse_target_addr(host, port).unwrap_or_else(|_| host.to_string())
+}
+
 /// Проверить, доступен ли сервер на данном адресе
 async fn check_server(ip: Ipv4Addr, port: u16) -> Option<String> {
-    let addr = format!("{}:{}", ip, port);
-    
+    check_addr(format!("{}:{}", ip, port)).await
+}
+
+/// Проверить, доступен ли сервер по адресу вида "хост:порт"
+async fn check_addr(addr: String) -> Option<String> {
     // Пробуем подключиться с коротким таймаутом
-    let connect_future = TcpStream::connect(&addr);
-    let timeout = tokio::time::timeout(Duration::from_millis(100), connect_future);
-    
-    match timeout.await {
-        Ok(Ok(_stream)) => Some(addr),
-        _ => None,
+    match transport::connect_with_timeout(TransportType::Tcp, &addr, PROBE_TIMEOUT, SocketTuning::default()).await {
+        Ok(mut stream) => {
+            // Представляемся проверкой, чтобы получатель не считал нас передачей.
+            // Ответ не важен (старый получатель отвечает ошибкой) - только бы дождаться
+            // его до закрытия соединения
+            let _ = tokio::time::timeout(PROBE_TIMEOUT, send_probe_hello(&mut *stream)).await;
+            Some(addr)
+        }
+        Err(_) => None,
     }
 }
 
+/// Отправить Hello с `ConnectionIntent::Probe` и прочитать ответ
+async fn send_probe_hello(stream: &mut dyn TransportStream) -> std::io::Result<()> {
+    let data = Message::Hello { features: 0, intent: ConnectionIntent::Probe }
+        .to_bytes()
+        .map_err(std::io::Error::other)?;
+    stream.write_all(&data).await?;
+    stream.flush().await?;
+    
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await?;
+    let mut reply = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+    stream.read_exact(&mut reply).await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -282,5 +568,115 @@ mod tests {
         let subnets = parse_subnets("192.168.1,invalid,10.0.0");
         assert_eq!(subnets.len(), 2); // Невалидная подсеть пропущена
     }
+    
+    #[test]
+    fn test_parse_subnets_short_range() {
+        let subnets = parse_subnets("192.168.1.10-20");
+        assert_eq!(subnets, vec![Subnet::with_range(192, 168, 1, 10, 20)]);
+        assert_eq!(subnets[0].host_count(), 11);
+        assert_eq!(format!("{}", subnets[0]), "192.168.1.10-20");
+    }
+    
+    #[test]
+    fn test_parse_subnets_full_range() {
+        let subnets = parse_subnets("192.168.1.10-192.168.1.20");
+        assert_eq!(subnets, vec![Subnet::with_range(192, 168, 1, 10, 20)]);
+        
+        // Диапазон через границу /24 не поддерживается
+        assert!(parse_subnets("192.168.1.10-192.168.2.20").is_empty());
+        // Перевёрнутый диапазон
+        assert!(parse_subnets("192.168.1.20-10").is_empty());
+    }
+    
+    #[test]
+    fn test_parse_subnets_single_hosts() {
+        let subnets = parse_subnets("192.168.1.15,10.0.0.7");
+        assert_eq!(subnets[0], Subnet::with_range(192, 168, 1, 15, 15));
+        assert_eq!(subnets[1], Subnet::with_range(10, 0, 0, 7, 7));
+        assert_eq!(subnets[0].host_count(), 1);
+        assert_eq!(format!("{}", subnets[0]), "192.168.1.15");
+    }
+    
+    #[test]
+    fn test_parse_subnets_mixed_cidr_range_host() {
+        let subnets = parse_subnets("10.0.0.0/24, 192.168.1.10-20; 172.16.0.5 bogus-1");
+        assert_eq!(subnets.len(), 3);
+        assert_eq!(subnets[0], Subnet::new(10, 0, 0));
+        assert_eq!(subnets[0].host_count(), 254);
+        assert_eq!(subnets[1], Subnet::with_range(192, 168, 1, 10, 20));
+        assert_eq!(subnets[2], Subnet::with_range(172, 16, 0, 5, 5));
+    }
+    
+    // === Тесты scan_hosts ===
+    
+    #[test]
+    fn test_with_default_port() {
+        assert_eq!(with_default_port("192.168.1.5", 9527), "192.168.1.5:9527");
+        assert_eq!(with_default_port("192.168.1.5:8000", 9527), "192.168.1.5:8000");
+        assert_eq!(with_default_port("fe80::1", 9527), "[fe80::1]:9527");
+    }
+    
+    #[tokio::test]
+    async fn test_scan_hosts_finds_listener() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        
+        let found = scan_hosts(vec![format!("127.0.0.1:{}", port), "".to_string()], 1, tx)
+            .await
+            .unwrap();
+        
+        assert_eq!(found, vec![format!("127.0.0.1:{}", port)]);
+        assert!(matches!(rx.recv().await, Some(TransferEvent::ServerFound(_))));
+        // Имя (если у 127.0.0.1 оно есть) - до завершения
+        let mut event = rx.recv().await;
+        if let Some(TransferEvent::ServerFoundNamed(addr, _)) = &event {
+            assert_eq!(*addr, format!("127.0.0.1:{}", port));
+            event = rx.recv().await;
+        }
+        assert!(matches!(event, Some(TransferEvent::ScanCompleted)));
+    }
+    
+    #[test]
+    fn test_server_label() {
+        assert_eq!(server_label("192.168.1.50:9527", Some("desktop-pc")), "desktop-pc (192.168.1.50:9527)");
+        assert_eq!(server_label("192.168.1.50:9527", None), "192.168.1.50:9527");
+    }
+    
+    #[tokio::test]
+    async fn test_lookup_hostname_not_ip() {
+        assert_eq!(lookup_hostname("not-an-ip:9527").await, None);
+    }
+    
+    #[tokio::test]
+    async fn test_scan_hosts_empty() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        assert!(scan_hosts(vec!["  ".to_string()], 9527, tx).await.is_err());
+    }
+    
+    #[cfg(feature = "mdns")]
+    #[tokio::test]
+    async fn test_mdns_advertise_and_discover() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let advertisement = advertise_mdns(port).unwrap();
+        assert!(advertisement.fullname().ends_with(MDNS_SERVICE_TYPE));
+        
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let found = discover_mdns(MDNS_BROWSE_TIMEOUT, tx).await.unwrap();
+        assert!(found.iter().any(|addr| addr.ends_with(&format!(":{}", port))), "{:?}", found);
+        
+        let mut completed = false;
+        while let Ok(event) = rx.try_recv() {
+            completed |= matches!(event, TransferEvent::ScanCompleted);
+        }
+        assert!(completed);
+    }
+    
+    #[cfg(not(feature = "mdns"))]
+    #[tokio::test]
+    async fn test_discover_mdns_without_feature() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        assert!(discover_mdns(MDNS_BROWSE_TIMEOUT, tx).await.is_err());
+        assert!(matches!(rx.recv().await, Some(TransferEvent::ScanCompleted)));
+    }
 }
-
@@ -1,29 +1,391 @@
 //! Логика отправки файлов
 
-use crate::protocol::{Message, FileInfo};
-use crate::stats::{DEFAULT_CHUNK_SIZE, MIN_CHUNK_SIZE, MAX_CHUNK_SIZE};
-use super::compression;
-use super::events::TransferEvent;
-use super::transport::{TransportType, TransportStream};
-use std::time::Instant;
+use crate::protocol::{ConnectionIntent, ManifestEntry, Message, FileInfo, SyncFileEntry, FEATURE_CHUNK_PROBE, FEATURE_COMPACT_FRAMING, FEATURE_EXTRACT_MANIFEST, FEATURE_FILE_CODEC, FEATURE_FILE_LINK, FEATURE_FILE_META, FEATURE_NO_EXTRACT, FEATURE_PACKED_LISTS, FEATURE_RAW_FILE_NAME, FEATURE_SYNC_ROOTS, FEATURE_VERIFY_INTEGRITY, LIST_PACK_THRESHOLD};
+use crate::stats::{DEFAULT_CHUNK_SIZE, MIN_CHUNK_SIZE, MAX_CHUNK_SIZE, MAX_FIXED_CHUNK_SIZE};
+use super::cancel::{CancelToken, TargetCancels};
+use super::completion::CompletionMap;
+use super::compression::{self, CompressionCodec, CompressionMode, COMPRESSION_SAMPLE_SIZE};
+use super::events::{FileWarning, SkipReason, TransferEvent};
+use super::quota::QuotaTracker;
+use super::throttle::RateLimiter;
+use super::transport::{CompactStream, SocketTuning, TransportType, TransportStream, DEFAULT_CONNECT_TIMEOUT};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Semaphore};
+
+/// Сколько файлов ленивого обхода ждут отправки (память не растёт с размером дерева)
+const FILE_ITER_BUFFER: usize = 256;
+
+/// Сколько получателей обслуживается одновременно по умолчанию
+pub const DEFAULT_MAX_PARALLEL_TARGETS: usize = 8;
+
+/// Пауза перед первым повтором подключения по умолчанию
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Предел роста паузы между повторами подключения
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
 
 /// Опции отправки
 #[derive(Clone, Debug)]
 pub struct SendOptions {
-    pub use_compression: bool,
+    /// Сжатие LZ4: выключено, для всех файлов или по решению для каждого файла
+    pub compression: CompressionMode,
+    /// Кодек сжатия: LZ4 или zstd с уровнем. Для zstd нужен получатель,
+    /// подтвердивший `FEATURE_FILE_CODEC`; остальные получают LZ4
+    pub codec: CompressionCodec,
+    /// Докачивать прерванные файлы; без неё получатель принимает всё заново
     pub enable_resume: bool,
     pub transport_type: TransportType,
+    /// Не передавать повторно файлы с одинаковым содержимым (Message::FileLink).
+    /// Получатель без `FEATURE_FILE_LINK` получает дубликаты целиком
+    pub dedupe: bool,
+    /// Перед дедупликацией сравнивать файлы побайтно, а не только по хэшу
+    pub verify_dedupe: bool,
+    /// Фиксированный размер чанка без адаптации (None - адаптивный).
+    /// На резюме не влияет: границы чанков не входят в состояние резюме
+    pub fixed_chunk_size: Option<usize>,
+    /// Сколько ждать подключения к получателю
+    pub connect_timeout: Duration,
+    /// Сколько раз повторить неудавшееся подключение (0 - не повторять), например
+    /// пока получатель загружается. Каждая следующая пауза вдвое длиннее
+    pub connect_retries: u32,
+    /// Пауза перед первым повтором подключения
+    pub retry_delay: Duration,
+    /// Сколько ждать данных от получателя посреди передачи (None - без ограничения).
+    /// Замолчавший получатель обрывает соединение ошибкой вместо вечного ожидания
+    pub timeout: Option<Duration>,
+    /// Не прерывать передачу, если файл не удалось открыть: сообщить FileError и перейти к следующему
+    pub continue_on_error: bool,
+    /// Запасные транспорты: при обрыве посреди файла переподключаемся через следующий
+    /// и продолжаем с принятого получателем offset. Получатель должен их принимать
+    pub transport_fallback: Vec<TransportType>,
+    /// Настройки TCP-сокета (nodelay, буферы); для других транспортов не используются
+    pub socket_tuning: SocketTuning,
+    /// Просить получателя не распаковывать ни один архив этой передачи, как
+    /// `FileInfo::no_extract` у каждого. Старый получатель распакует как обычно
+    pub no_extract: bool,
+    /// Считать контрольную сумму переданного содержимого (событие `TargetChecksum`).
+    /// Файлы хэшируются при чтении; докачанные и пропущенные перечитываются целиком
+    pub checksum: bool,
+    /// Дневной лимит отправленных байт (по сети, после сжатия). При исчерпании
+    /// передача не начинается, а начатая прерывается; учёт общий, см. `QuotaTracker`
+    pub daily_quota: Option<u64>,
+    /// Сколько получателей передаются одновременно; остальные ждут в очереди
+    /// (событие `TargetQueued`) и начинают по мере освобождения
+    pub max_parallel_targets: usize,
+    /// Сколько соединений открывать к одному получателю: файлы раздаются им из
+    /// общей очереди. При нескольких соединениях дубликаты и карта переданных
+    /// файлов не используются, а предел скорости делится между соединениями
+    pub connections_per_target: usize,
+    /// Предложить получателю компактную раскладку кадров (`Message::Hello`):
+    /// меньше служебных байт на мелких файлах. Старый получатель - обычная раскладка
+    pub compact_framing: bool,
+    /// Передавать расширенные атрибуты `user.*` - только Unix. Уходят лишь
+    /// получателю, подтвердившему `FEATURE_FILE_META` (`receive --xattrs`)
+    pub preserve_xattrs: bool,
+    /// Сжимать большие списки файлов (`ResumeProbe`, `DeleteFiles`) в `Message::Packed`,
+    /// если получатель подтвердил `FEATURE_PACKED_LISTS`; иначе списки идут как есть
+    pub pack_lists: bool,
+    /// Запоминать полностью переданные файлы (`CompletionMap`): повторная отправка
+    /// того же набора на того же получателя пропускает их без проверки хэша
+    pub remember_progress: bool,
+    /// Папка карт переданных файлов (None - рядом с настройками)
+    pub progress_dir: Option<PathBuf>,
+    /// Досылать SHA-256 всего файла (`Message::FileHash`): получатель сверяет его и удаляет
+    /// повреждённую копию (событие `FileCorrupted`). При докачке начало файла перечитывается.
+    /// Получатель, который хэш не сверяет, - событие `FileWarning`
+    pub verify_integrity: bool,
+    /// Предел скорости отправки одному получателю, байт в секунду (None - без предела).
+    /// Размер чанка тогда подбирается под предел, а не по скорости сети
+    pub max_bytes_per_sec: Option<u64>,
 }
 
 impl Default for SendOptions {
     fn default() -> Self {
         Self {
-            use_compression: false,
+            compression: CompressionMode::Off,
+            codec: CompressionCodec::Lz4,
             enable_resume: true,
             transport_type: TransportType::default(),
+            dedupe: true,
+            verify_dedupe: false,
+            fixed_chunk_size: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            connect_retries: 0,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            timeout: None,
+            continue_on_error: false,
+            transport_fallback: Vec::new(),
+            socket_tuning: SocketTuning::default(),
+            no_extract: false,
+            checksum: false,
+            daily_quota: None,
+            max_parallel_targets: DEFAULT_MAX_PARALLEL_TARGETS,
+            connections_per_target: 1,
+            compact_framing: false,
+            preserve_xattrs: false,
+            pack_lists: true,
+            remember_progress: false,
+            progress_dir: None,
+            verify_integrity: false,
+            max_bytes_per_sec: None,
+        }
+    }
+}
+
+impl SendOptions {
+    /// Построитель с настройками по умолчанию
+    pub fn builder() -> SendOptionsBuilder {
+        SendOptionsBuilder::default()
+    }
+    
+    /// Фиксированный размер чанка, ограниченный [MIN_CHUNK_SIZE, MAX_FIXED_CHUNK_SIZE]
+    pub fn fixed_chunk_size(&self) -> Option<usize> {
+        self.fixed_chunk_size
+            .map(|size| size.clamp(MIN_CHUNK_SIZE, MAX_FIXED_CHUNK_SIZE))
+    }
+    
+    /// Возможности (`FEATURE_*`), которые просим в `Message::Hello` (0 - Hello не нужен)
+    pub fn wanted_features(&self) -> u32 {
+        // NoExtract может понадобиться любому архиву, в том числе из send_files_iter
+        let mut features = FEATURE_RAW_FILE_NAME | FEATURE_NO_EXTRACT;
+        if self.compact_framing {
+            features |= FEATURE_COMPACT_FRAMING;
+        }
+        if self.enable_resume {
+            // Архив, распакованный в прошлый раз частично, шлём без готовых записей
+            features |= FEATURE_EXTRACT_MANIFEST;
+        }
+        if self.codec != CompressionCodec::Lz4 && self.compression != CompressionMode::Off {
+            features |= FEATURE_FILE_CODEC;
+        }
+        if self.preserve_xattrs {
+            features |= FEATURE_FILE_META;
+        }
+        if self.dedupe {
+            features |= FEATURE_FILE_LINK;
+        }
+        if self.verify_integrity {
+            features |= FEATURE_VERIFY_INTEGRITY;
+        }
+        // Проба нужна, только если размер чанка не задан заранее
+        if self.fixed_chunk_size.is_none() && self.max_bytes_per_sec.is_none() {
+            features |= FEATURE_CHUNK_PROBE;
+        }
+        features
+    }
+    
+    /// Опции для соединения, где получатель подтвердил только `features`:
+    /// возможности, которых он не знает, выключены
+    fn negotiated(&self, features: u32) -> Self {
+        let mut options = self.clone();
+        if features & FEATURE_FILE_CODEC == 0 {
+            options.codec = CompressionCodec::Lz4;
+        }
+        // Получатель без согласия на xattr получает файлы без них
+        if features & FEATURE_FILE_META == 0 {
+            options.preserve_xattrs = false;
+        }
+        if features & FEATURE_VERIFY_INTEGRITY == 0 {
+            options.verify_integrity = false;
+        }
+        options
+    }
+    
+    /// Проверить согласованность опций
+    pub fn validate(&self) -> Result<(), String> {
+        if self.verify_dedupe && !self.dedupe {
+            return Err("Побайтная сверка дубликатов требует дедупликации".to_string());
+        }
+        if self.connect_timeout.is_zero() {
+            return Err("Таймаут подключения должен быть больше нуля".to_string());
+        }
+        if self.connect_retries > 0 && self.retry_delay.is_zero() {
+            return Err("Пауза между попытками подключения должна быть больше нуля".to_string());
+        }
+        if self.timeout.is_some_and(|t| t.is_zero()) {
+            return Err("Таймаут ожидания данных должен быть больше нуля".to_string());
+        }
+        if self.daily_quota == Some(0) {
+            return Err("Дневной лимит должен быть больше нуля".to_string());
+        }
+        if self.max_bytes_per_sec == Some(0) {
+            return Err("Предел скорости должен быть больше нуля".to_string());
+        }
+        if self.max_parallel_targets == 0 {
+            return Err("Число одновременных получателей должно быть больше нуля".to_string());
         }
+        if self.connections_per_target == 0 {
+            return Err("Число соединений с получателем должно быть больше нуля".to_string());
+        }
+        self.codec.validate()
+    }
+}
+
+/// Построитель `SendOptions`: начинает с `Default`, проверяет опции в `build()`
+#[derive(Clone, Debug, Default)]
+pub struct SendOptionsBuilder {
+    options: SendOptions,
+}
+
+impl SendOptionsBuilder {
+    pub fn compression(mut self, compression: CompressionMode) -> Self {
+        self.options.compression = compression;
+        self
+    }
+    
+    pub fn codec(mut self, codec: CompressionCodec) -> Self {
+        self.options.codec = codec;
+        self
+    }
+    
+    pub fn enable_resume(mut self, enabled: bool) -> Self {
+        self.options.enable_resume = enabled;
+        self
+    }
+    
+    pub fn transport(mut self, transport_type: TransportType) -> Self {
+        self.options.transport_type = transport_type;
+        self
+    }
+    
+    /// Не передавать повторно одинаковые файлы
+    pub fn dedupe(mut self, enabled: bool) -> Self {
+        self.options.dedupe = enabled;
+        self
+    }
+    
+    /// Сравнивать дубликаты побайтно (требует `dedupe`)
+    pub fn verify_dedupe(mut self, enabled: bool) -> Self {
+        self.options.verify_dedupe = enabled;
+        self
+    }
+    
+    /// Фиксированный размер чанка без адаптации
+    pub fn fixed_chunk_size(mut self, size: usize) -> Self {
+        self.options.fixed_chunk_size = Some(size);
+        self
+    }
+    
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.options.connect_timeout = timeout;
+        self
+    }
+    
+    /// Повторять неудавшееся подключение до `retries` раз
+    pub fn connect_retries(mut self, retries: u32) -> Self {
+        self.options.connect_retries = retries;
+        self
+    }
+    
+    /// Пауза перед первым повтором подключения (дальше удваивается)
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.options.retry_delay = delay;
+        self
+    }
+    
+    /// Обрывать соединение, если получатель молчит дольше `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+    
+    /// Пропускать файлы, которые не удалось открыть
+    pub fn continue_on_error(mut self, enabled: bool) -> Self {
+        self.options.continue_on_error = enabled;
+        self
+    }
+    
+    /// Запасные транспорты при обрыве
+    pub fn transport_fallback(mut self, transports: Vec<TransportType>) -> Self {
+        self.options.transport_fallback = transports;
+        self
+    }
+    
+    pub fn socket_tuning(mut self, socket_tuning: SocketTuning) -> Self {
+        self.options.socket_tuning = socket_tuning;
+        self
+    }
+    
+    /// Просить получателя не распаковывать архивы
+    pub fn no_extract(mut self, enabled: bool) -> Self {
+        self.options.no_extract = enabled;
+        self
+    }
+    
+    /// Считать контрольную сумму переданного
+    pub fn checksum(mut self, enabled: bool) -> Self {
+        self.options.checksum = enabled;
+        self
+    }
+    
+    /// Дневной лимит отправленных байт
+    pub fn daily_quota(mut self, limit: u64) -> Self {
+        self.options.daily_quota = Some(limit);
+        self
+    }
+    
+    /// Сколько получателей передаются одновременно
+    pub fn max_parallel_targets(mut self, limit: usize) -> Self {
+        self.options.max_parallel_targets = limit;
+        self
+    }
+    
+    /// Сколько соединений открывать к одному получателю
+    pub fn connections_per_target(mut self, count: usize) -> Self {
+        self.options.connections_per_target = count;
+        self
+    }
+    
+    /// Компактная раскладка кадров, если получатель её поддерживает
+    pub fn compact_framing(mut self, enabled: bool) -> Self {
+        self.options.compact_framing = enabled;
+        self
+    }
+    
+    /// Передавать расширенные атрибуты `user.*`
+    pub fn preserve_xattrs(mut self, enabled: bool) -> Self {
+        self.options.preserve_xattrs = enabled;
+        self
+    }
+    
+    /// Сжимать большие списки файлов
+    pub fn pack_lists(mut self, enabled: bool) -> Self {
+        self.options.pack_lists = enabled;
+        self
+    }
+    
+    /// Запоминать переданные файлы для быстрой докачки папки
+    pub fn remember_progress(mut self, enabled: bool) -> Self {
+        self.options.remember_progress = enabled;
+        self
+    }
+    
+    /// Папка карт переданных файлов
+    pub fn progress_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.progress_dir = Some(dir.into());
+        self
+    }
+    
+    /// Проверять SHA-256 каждого файла у получателя
+    pub fn verify_integrity(mut self, enabled: bool) -> Self {
+        self.options.verify_integrity = enabled;
+        self
+    }
+    
+    /// Предел скорости отправки, байт в секунду
+    pub fn max_bytes_per_sec(mut self, limit: u64) -> Self {
+        self.options.max_bytes_per_sec = Some(limit);
+        self
+    }
+    
+    /// Собрать опции, проверив их согласованность
+    pub fn build(self) -> Result<SendOptions, String> {
+        self.options.validate()?;
+        Ok(self.options)
     }
 }
 
@@ -36,9 +398,8 @@ pub async fn send_files_to_target(
     event_tx: mpsc::UnboundedSender<TransferEvent>,
 ) -> Result<(), String> {
     let options = SendOptions {
-        use_compression,
-        enable_resume: true,
-        transport_type: TransportType::default(),
+        compression: use_compression.into(),
+        ..Default::default()
     };
     
     send_files_to_target_with_options(target_id, addr, files, options, event_tx).await
@@ -52,48 +413,8 @@ pub async fn send_files_to_target_with_options(
     options: SendOptions,
     event_tx: mpsc::UnboundedSender<TransferEvent>,
 ) -> Result<(), String> {
-    // Подключаемся через выбранный транспорт
-    let mut stream = super::transport::connect(options.transport_type, &addr)
-        .await
-        .map_err(|e| format!("Ошибка подключения [{}]: {}", options.transport_type.name(), e))?;
-    
-    let _ = event_tx.send(TransferEvent::Connected(target_id, format!("{} [{}]", addr, options.transport_type.name())));
-    
-    // Адаптивный размер чанка
-    let mut chunk_size = DEFAULT_CHUNK_SIZE;
-    
-    for (idx, file) in files.iter().enumerate() {
-        let _ = event_tx.send(TransferEvent::FileStarted(target_id, idx));
-        
-        match send_single_file_transport(
-            &mut *stream,
-            file,
-            target_id,
-            idx,
-            &options,
-            &mut chunk_size,
-            &event_tx,
-        ).await {
-            Ok(skipped) => {
-                if skipped {
-                    let _ = event_tx.send(TransferEvent::FileSkipped(target_id, idx));
-                } else {
-                    let _ = event_tx.send(TransferEvent::FileCompleted(target_id, idx));
-                }
-            }
-            Err(e) => {
-                let _ = event_tx.send(TransferEvent::FileError(target_id, idx, e.clone()));
-                return Err(e);
-            }
-        }
-    }
-    
-    // Отправляем сигнал завершения
-    let done_msg = Message::Done.to_bytes().map_err(|e| e.to_string())?;
-    stream.write_all(&done_msg).await.map_err(|e| e.to_string())?;
-    
-    let _ = event_tx.send(TransferEvent::TargetCompleted(target_id));
-    Ok(())
+    let stop_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    send_files_to_target_with_stop(target_id, addr, files, options, event_tx, stop_flag).await
 }
 
 /// Отправить файлы на несколько серверов параллельно
@@ -105,13 +426,23 @@ pub async fn send_files_to_multiple(
 ) {
     let stop_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
     let options = SendOptions {
-        use_compression,
-        enable_resume: true,
-        transport_type: TransportType::default(),
+        compression: use_compression.into(),
+        ..Default::default()
     };
     send_files_to_multiple_with_stop(targets, files, options, event_tx, stop_flag).await;
 }
 
+/// Отправить файлы на несколько серверов с отменой через `CancelToken`
+pub async fn send_files_to_multiple_with_cancel(
+    targets: Vec<String>,
+    files: Vec<FileInfo>,
+    options: SendOptions,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+    cancel: CancelToken,
+) {
+    send_files_to_multiple_with_stop(targets, files, options, event_tx, cancel.flag()).await
+}
+
 /// Отправить файлы на несколько серверов параллельно с поддержкой остановки
 pub async fn send_files_to_multiple_with_stop(
     targets: Vec<String>,
@@ -120,16 +451,56 @@ pub async fn send_files_to_multiple_with_stop(
     event_tx: mpsc::UnboundedSender<TransferEvent>,
     stop_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
 ) {
+    send_files_to_multiple_with_targets(targets, files, options, event_tx, stop_flag, TargetCancels::new()).await
+}
+
+/// Отправить файлы на несколько серверов с остановкой всей рассылки
+/// (`stop_flag`) и отдельных получателей (`cancels`).
+///
+/// Отменённый получатель завершается `ConnectionError`, остальные продолжают
+pub async fn send_files_to_multiple_with_targets(
+    targets: Vec<String>,
+    files: Vec<FileInfo>,
+    options: SendOptions,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+    stop_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    cancels: TargetCancels,
+) {
+    use std::sync::atomic::Ordering;
+    
     let mut handles = Vec::new();
+    // Не больше max_parallel_targets соединений и чтений файлов одновременно
+    let slots = std::sync::Arc::new(Semaphore::new(options.max_parallel_targets.max(1)));
     
     for (target_id, addr) in targets.into_iter().enumerate() {
         let files = files.clone();
         let event_tx = event_tx.clone();
-        let stop_flag = stop_flag.clone();
+        let target_stop = cancels.flag(target_id);
         let options = options.clone();
+        let slots = slots.clone();
         
         let handle = tokio::spawn(async move {
-            if let Err(e) = send_files_to_target_with_stop(target_id, addr, files, options, event_tx.clone(), stop_flag).await {
+            let _permit = match slots.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => {
+                    let _ = event_tx.send(TransferEvent::TargetQueued(target_id));
+                    match slots.acquire_owned().await {
+                        Ok(permit) => permit,
+                        Err(_) => return,
+                    }
+                }
+            };
+            if target_stop.load(Ordering::SeqCst) {
+                let _ = event_tx.send(TransferEvent::ConnectionError(target_id, "Остановлено пользователем".to_string()));
+                return;
+            }
+            // Зависшее соединение флаг может не увидеть (запись стоит) - тогда
+            // после CANCEL_GRACE передача на этого получателя просто бросается
+            let result = tokio::select! {
+                result = send_files_to_target_with_stop(target_id, addr, files, options, event_tx.clone(), target_stop.clone()) => result,
+                _ = cancelled_with_grace(&target_stop) => Err("Остановлено пользователем".to_string()),
+            };
+            if let Err(e) = result {
                 let _ = event_tx.send(TransferEvent::ConnectionError(target_id, e));
             }
         });
@@ -137,14 +508,174 @@ pub async fn send_files_to_multiple_with_stop(
         handles.push(handle);
     }
     
+    // Общая остановка доходит до передач через флаги получателей
+    let watcher = tokio::spawn({
+        let cancels = cancels.clone();
+        async move {
+            while !stop_flag.load(Ordering::SeqCst) {
+                tokio::time::sleep(STOP_POLL_INTERVAL).await;
+            }
+            cancels.cancel_all();
+        }
+    });
+    
     // Ждём завершения всех передач
     for handle in handles {
         let _ = handle.await;
     }
+    watcher.abort();
     
     let _ = event_tx.send(TransferEvent::AllCompleted);
 }
 
+/// Как часто проверять флаги остановки рассылки
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Сколько ждать штатной остановки получателя, прежде чем бросить соединение
+const CANCEL_GRACE: Duration = Duration::from_secs(2);
+
+/// Завершиться через `CANCEL_GRACE` после установки флага
+async fn cancelled_with_grace(flag: &std::sync::atomic::AtomicBool) {
+    while !flag.load(std::sync::atomic::Ordering::SeqCst) {
+        tokio::time::sleep(STOP_POLL_INTERVAL).await;
+    }
+    tokio::time::sleep(CANCEL_GRACE).await;
+}
+
+/// Спросить получателя, что из `files` у него уже есть: offset докачки
+/// для каждого файла (0 - с начала, size - файл уже получен). Файлы не передаются
+pub async fn probe_resume(addr: &str, files: &[FileInfo], options: &SendOptions) -> Result<Vec<u64>, String> {
+    let (mut stream, features) = connect_for_lists(addr, options, 0).await?;
+    let pack = features & FEATURE_PACKED_LISTS != 0;
+    
+    let mut entries = Vec::with_capacity(files.len());
+    for file in files {
+        entries.push(SyncFileEntry {
+            relative_path: file.relative_path.clone(),
+            size: file.size,
+            quick_hash: compute_quick_hash(&file.path).await.unwrap_or(0),
+        });
+    }
+    let probe = Message::ResumeProbe { files: entries };
+    let data = if pack { probe.to_bytes_packed(LIST_PACK_THRESHOLD) } else { probe.to_bytes() };
+    let data = data.map_err(|e| e.to_string())?;
+    stream.write_all(&data).await.map_err(|e| e.to_string())?;
+    
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+    let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+    stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    
+    let offsets = match Message::from_bytes(&data).map_err(|e| e.to_string())? {
+        Message::ResumeProbeResult { offsets } if offsets.len() == files.len() => offsets,
+        Message::Error(e) => return Err(e),
+        _ => return Err("Неожиданный ответ".to_string()),
+    };
+    
+    let done = Message::Done.to_bytes().map_err(|e| e.to_string())?;
+    let _ = stream.write_all(&done).await;
+    Ok(offsets)
+}
+
+/// Сравнить `files` с содержимым папки получателя (`SyncRequest`).
+///
+/// Чужие файлы получателя в `remote_only` не попадают: учитываются только пути
+/// под теми же корнями (первый компонент пути), что и у отправляемых файлов,
+/// и под `roots` - корнями, в которых локально сейчас может не быть ни одного файла
+pub async fn fetch_sync_diff(addr: &str, files: &[FileInfo], roots: &[String], options: &SendOptions) -> Result<crate::sync::SyncDiff, String> {
+    let roots: HashSet<&str> = files.iter()
+        .map(|f| f.relative_path.split('/').next().unwrap_or_default())
+        .chain(roots.iter().map(String::as_str))
+        .collect();
+    let (mut stream, features) = connect_for_lists(addr, options, FEATURE_SYNC_ROOTS).await?;
+    
+    // Получатель без SyncRoots присылает всю папку - лишнее отбрасывается ниже
+    let request = if features & FEATURE_SYNC_ROOTS != 0 {
+        let mut roots: Vec<String> = roots.iter().map(|root| root.to_string()).collect();
+        roots.sort();
+        Message::SyncRoots { roots }
+    } else {
+        Message::SyncRequest
+    };
+    let data = request.to_bytes().map_err(|e| e.to_string())?;
+    stream.write_all(&data).await.map_err(|e| e.to_string())?;
+    
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+    let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+    stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    
+    let entries = match Message::from_bytes(&data).map_err(|e| e.to_string())? {
+        Message::SyncFileList { files } => files,
+        Message::Error(e) => return Err(e),
+        _ => return Err("Неожиданный ответ".to_string()),
+    };
+    
+    let done = Message::Done.to_bytes().map_err(|e| e.to_string())?;
+    let _ = stream.write_all(&done).await;
+    
+    let remote: Vec<crate::sync::RemoteFileInfo> = entries.into_iter()
+        .filter(|e| roots.contains(e.relative_path.split('/').next().unwrap_or_default()))
+        .map(|e| crate::sync::RemoteFileInfo {
+            relative_path: e.relative_path,
+            size: e.size,
+            modified: 0,
+            quick_hash: e.quick_hash,
+        })
+        .collect();
+    
+    let sources: Vec<(PathBuf, String)> = files.iter()
+        .map(|f| (f.path.clone(), f.relative_path.clone()))
+        .collect();
+    let local = tokio::task::spawn_blocking(move || {
+        sources.into_iter()
+            .map(|(path, relative)| crate::sync::SyncFileInfo::from_path(&path, relative)
+                .map_err(|e| format!("{}: {}", path.display(), e)))
+            .collect::<Result<Vec<_>, String>>()
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    
+    Ok(crate::sync::compute_sync_diff(&local, &remote))
+}
+
+/// Удалить у получателя файлы, которых больше нет у отправителя (`SyncDiff::remote_only`).
+/// Пустой список - ничего не делает и не подключается
+pub async fn delete_remote_files(addr: &str, paths: &[String], options: &SendOptions) -> Result<(), String> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let (mut stream, features) = connect_for_lists(addr, options, 0).await?;
+    let pack = features & FEATURE_PACKED_LISTS != 0;
+    
+    let request = Message::DeleteFiles { paths: paths.to_vec() };
+    let data = if pack { request.to_bytes_packed(LIST_PACK_THRESHOLD) } else { request.to_bytes() };
+    let data = data.map_err(|e| e.to_string())?;
+    stream.write_all(&data).await.map_err(|e| e.to_string())?;
+    
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+    let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+    stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    
+    let result = match Message::from_bytes(&data).map_err(|e| e.to_string())? {
+        Message::Ack => Ok(()),
+        Message::Error(e) => Err(e),
+        _ => Err("Неожиданный ответ".to_string()),
+    };
+    
+    let done = Message::Done.to_bytes().map_err(|e| e.to_string())?;
+    let _ = stream.write_all(&done).await;
+    result
+}
+
+/// Подключиться для запроса со списком файлов, попросив в Hello `features`
+/// (и сжатие списков с `pack_lists`). Возвращает подтверждённые получателем возможности
+async fn connect_for_lists(addr: &str, options: &SendOptions, features: u32) -> Result<(Box<dyn TransportStream>, u32), String> {
+    let wanted = features | if options.pack_lists { FEATURE_PACKED_LISTS } else { 0 };
+    connect_negotiated(options.transport_type, addr, options, wanted).await
+}
+
 /// Отправить файлы на один сервер с поддержкой остановки
 pub async fn send_files_to_target_with_stop(
     target_id: usize,
@@ -154,201 +685,866 @@ pub async fn send_files_to_target_with_stop(
     event_tx: mpsc::UnboundedSender<TransferEvent>,
     stop_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
 ) -> Result<(), String> {
-    use std::sync::atomic::Ordering;
+    if options.connections_per_target > 1 && files.len() > 1 {
+        return send_files_parallel(target_id, addr, files, options, event_tx, stop_flag).await;
+    }
+    let mut connection = Connection::connect_with_events(target_id, &addr, options, event_tx.clone(), stop_flag).await?;
+    let checksum = connection.send_batch(&files).await?;
+    connection.close().await?;
     
-    // Подключаемся через выбранный транспорт
-    let mut stream = super::transport::connect(options.transport_type, &addr)
-        .await
-        .map_err(|e| format!("Ошибка подключения [{}]: {}", options.transport_type.name(), e))?;
+    if let Some(checksum) = checksum {
+        let _ = event_tx.send(TransferEvent::TargetChecksum(target_id, checksum));
+    }
+    let _ = event_tx.send(TransferEvent::TargetCompleted(target_id));
+    Ok(())
+}
+
+/// Отправить файлы одному получателю по нескольким соединениям (`connections_per_target`).
+///
+/// Каждое соединение берёт следующий файл из общей очереди, поэтому мелкие файлы
+/// не ждут подтверждений друг друга. Ошибка одного соединения останавливает выдачу
+/// файлов остальным: они дописывают текущий файл и закрываются
+async fn send_files_parallel(
+    target_id: usize,
+    addr: String,
+    files: Vec<FileInfo>,
+    mut options: SendOptions,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+    stop_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
+) -> Result<(), String> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
     
-    let _ = event_tx.send(TransferEvent::Connected(target_id, format!("{} [{}]", addr, options.transport_type.name())));
+    let count = options.connections_per_target.min(files.len());
+    // Предел задан на получателя - делим его поровну
+    options.max_bytes_per_sec = options.max_bytes_per_sec.map(|limit| (limit / count as u64).max(1));
+    let checksum = options.checksum;
     
-    let mut chunk_size = DEFAULT_CHUNK_SIZE;
+    let files = Arc::new(files);
+    let next = Arc::new(AtomicUsize::new(0));
+    let hashes = Arc::new(Mutex::new(vec![None; files.len()]));
+    let mut handles = Vec::new();
     
-    for (idx, file) in files.iter().enumerate() {
-        // Проверяем флаг остановки
-        if stop_flag.load(Ordering::SeqCst) {
-            return Err("Остановлено пользователем".to_string());
-        }
-        
-        let _ = event_tx.send(TransferEvent::FileStarted(target_id, idx));
+    for _ in 0..count {
+        let (files, next, hashes) = (files.clone(), next.clone(), hashes.clone());
+        let (addr, options, event_tx, stop_flag) = (addr.clone(), options.clone(), event_tx.clone(), stop_flag.clone());
         
-        match send_single_file_transport_with_stop(
-            &mut *stream,
-            file,
-            target_id,
-            idx,
-            &options,
-            &mut chunk_size,
-            &event_tx,
-            &stop_flag,
-        ).await {
-            Ok(skipped) => {
-                if skipped {
-                    let _ = event_tx.send(TransferEvent::FileSkipped(target_id, idx));
-                } else {
-                    let _ = event_tx.send(TransferEvent::FileCompleted(target_id, idx));
+        handles.push(tokio::spawn(async move {
+            let result = async {
+                let mut connection = Connection::connect_with_events(target_id, &addr, options, event_tx, stop_flag).await?;
+                loop {
+                    let idx = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(file) = files.get(idx) else { break };
+                    connection.start_file(idx)?;
+                    let hash = connection.send_one(idx, file).await?;
+                    hashes.lock().unwrap()[idx] = hash;
                 }
+                connection.close().await
+            }.await;
+            if result.is_err() {
+                // Очередь пуста для всех - остальные соединения закончат текущий файл
+                next.store(files.len(), Ordering::SeqCst);
             }
-            Err(e) => {
-                let _ = event_tx.send(TransferEvent::FileError(target_id, idx, e.clone()));
-                return Err(e);
-            }
-        }
+            result
+        }));
     }
     
-    // Отправляем сигнал завершения
-    let done_msg = Message::Done.to_bytes().map_err(|e| e.to_string())?;
-    stream.write_all(&done_msg).await.map_err(|e| e.to_string())?;
+    // Первая ошибка - ошибка всей передачи
+    let mut result = Ok(());
+    for handle in handles {
+        let worker = handle.await.map_err(|e| e.to_string()).and_then(|r| r);
+        if result.is_ok() {
+            result = worker;
+        }
+    }
+    result?;
     
+    if checksum {
+        let hashes = std::mem::take(&mut *hashes.lock().unwrap());
+        if let Some(hashes) = hashes.into_iter().collect::<Option<Vec<u64>>>() {
+            let _ = event_tx.send(TransferEvent::TargetChecksum(target_id, combine_checksums(&files, &hashes)));
+        }
+    }
     let _ = event_tx.send(TransferEvent::TargetCompleted(target_id));
     Ok(())
 }
 
-/// Отправить один файл через транспорт. Возвращает true если файл был пропущен
-async fn send_single_file_transport(
-    stream: &mut dyn TransportStream,
-    file: &FileInfo,
+/// Соединение с получателем, которое переживает несколько отправок.
+///
+/// `Done` уходит только в `close`, поэтому повторные пакеты файлов
+/// не платят за подключение и согласование заново
+pub struct Connection {
+    stream: Box<dyn TransportStream>,
+    /// Возможности (`FEATURE_*`), которые получатель подтвердил в HelloAck
+    features: u32,
+    addr: String,
     target_id: usize,
-    file_idx: usize,
-    options: &SendOptions,
-    chunk_size: &mut usize,
-    event_tx: &mpsc::UnboundedSender<TransferEvent>,
-) -> Result<bool, String> {
-    let stop_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
-    send_single_file_transport_with_stop(stream, file, target_id, file_idx, options, chunk_size, event_tx, &stop_flag).await
+    options: SendOptions,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+    stop_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    quota: Option<QuotaTracker>,
+    fallbacks: std::vec::IntoIter<TransportType>,
+    /// Размер чанка, подобранный в прошлых пакетах (None - ещё не подбирался)
+    chunk_size: Option<usize>,
+    /// Карта переданных файлов текущего пакета (`remember_progress`)
+    completion: Option<CompletionMap>,
+    /// Предел скорости (`max_bytes_per_sec`), общий для всех пакетов соединения
+    limiter: Option<RateLimiter>,
 }
 
-/// Отправить один файл через транспорт с поддержкой остановки
-async fn send_single_file_transport_with_stop(
-    stream: &mut dyn TransportStream,
-    file: &FileInfo,
-    target_id: usize,
-    file_idx: usize,
-    options: &SendOptions,
-    chunk_size: &mut usize,
-    event_tx: &mpsc::UnboundedSender<TransferEvent>,
-    stop_flag: &std::sync::Arc<std::sync::atomic::AtomicBool>,
-) -> Result<bool, String> {
-    use std::sync::atomic::Ordering;
+impl Connection {
+    /// Подключиться к получателю (события передачи не отправляются)
+    pub async fn connect(addr: &str, options: SendOptions) -> Result<Self, String> {
+        let (event_tx, _) = mpsc::unbounded_channel();
+        let stop_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        Self::connect_with_events(0, addr, options, event_tx, stop_flag).await
+    }
     
-    // Открываем файл
-    let mut f = tokio::fs::File::open(&file.path)
-        .await
-        .map_err(|e| format!("Не удалось открыть файл: {}", e))?;
-    
-    // Вычисляем быстрый хэш для синхронизации
-    let quick_hash = compute_quick_hash(&file.path).await.unwrap_or(0);
-    
-    // Отправляем заголовок
-    let start_msg = Message::FileStart {
-        filename: file.relative_path.clone(),
-        size: file.size,
-        compressed: options.use_compression,
-        offset: 0,
-        quick_hash,
-    };
-    let data = start_msg.to_bytes().map_err(|e| e.to_string())?;
-    stream.write_all(&data).await.map_err(|e| e.to_string())?;
+    /// Подключиться к получателю с событиями для `target_id` и флагом остановки
+    pub async fn connect_with_events(
+        target_id: usize,
+        addr: &str,
+        options: SendOptions,
+        event_tx: mpsc::UnboundedSender<TransferEvent>,
+        stop_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    ) -> Result<Self, String> {
+        // Лимит исчерпан - не подключаемся вовсе
+        let quota = options.daily_quota.map(QuotaTracker::shared);
+        if let Some(quota) = &quota {
+            quota.check()?;
+        }
+        
+        // Подключаемся через выбранный транспорт
+        let _ = event_tx.send(TransferEvent::Connecting(target_id));
+        let (stream, features) = connect_with_retries(target_id, addr, &options, &event_tx, &stop_flag).await?;
+        let fallbacks = options.transport_fallback.clone().into_iter();
+        let limiter = options.max_bytes_per_sec.map(RateLimiter::new);
+        
+        let _ = event_tx.send(TransferEvent::Connected(target_id, connection_label(&*stream, addr)));
+        // До первого ответа получателя идёт согласование (FileStart -> Ack)
+        let _ = event_tx.send(TransferEvent::Handshaking(target_id));
+        
+        Ok(Self {
+            stream,
+            features,
+            addr: addr.to_string(),
+            target_id,
+            options,
+            event_tx,
+            stop_flag,
+            quota,
+            fallbacks,
+            chunk_size: None,
+            completion: None,
+            limiter,
+        })
+    }
     
-    // Ждём ответ (может быть Ack или ResumeAck)
-    let start_offset = wait_resume_ack_transport(stream).await?;
+    /// Адрес получателя, как его передали в `connect`
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
     
-    // Если offset == size, файл уже актуален
-    if start_offset >= file.size {
-        return Ok(true); // Файл пропущен
+    /// Размер чанка, который не подбирается: фиксированный или под предел скорости
+    fn pinned_chunk_size(&self) -> Option<usize> {
+        self.options.fixed_chunk_size().or(self.limiter.as_ref().map(RateLimiter::chunk_size))
     }
     
-    // Если есть offset, сообщаем о возобновлении
-    if start_offset > 0 {
-        let _ = event_tx.send(TransferEvent::FileResumed(target_id, file_idx, start_offset));
-        f.seek(std::io::SeekFrom::Start(start_offset)).await.map_err(|e| e.to_string())?;
+    /// Отправить пакет файлов, оставив соединение открытым.
+    ///
+    /// По окончании пакета приходит `TargetCompleted` (и `TargetChecksum`, если включено)
+    pub async fn send_files(&mut self, files: Vec<FileInfo>) -> Result<(), String> {
+        let checksum = self.send_batch(&files).await?;
+        if let Some(checksum) = checksum {
+            let _ = self.event_tx.send(TransferEvent::TargetChecksum(self.target_id, checksum));
+        }
+        let _ = self.event_tx.send(TransferEvent::TargetCompleted(self.target_id));
+        Ok(())
     }
     
-    // Отправляем данные с адаптивным размером чанка
-    let mut buffer = vec![0u8; MAX_CHUNK_SIZE];
-    let mut transferred: u64 = start_offset;
-    let mut total_original: u64 = 0;
-    let mut total_compressed: u64 = 0;
-    let mut last_speed_check = Instant::now();
-    let mut last_progress_update = Instant::now();
-    let mut bytes_since_check: u64 = 0;
+    /// Завершить сессию: отправить `Done` и закрыть соединение
+    pub async fn close(mut self) -> Result<(), String> {
+        let done_msg = Message::Done.to_bytes().map_err(|e| e.to_string())?;
+        self.stream.write_all(&done_msg).await.map_err(|e| e.to_string())?;
+        let _ = self.stream.flush().await;
+        Ok(())
+    }
     
-    loop {
-        // Проверяем флаг остановки
-        if stop_flag.load(Ordering::SeqCst) {
-            return Err("Остановлено пользователем".to_string());
+    /// Отправить файлы по мере их появления из `files` (например, `walk_folder`),
+    /// не собирая весь список в памяти. Возвращает число файлов в пакете.
+    ///
+    /// Дедупликация, подбор размера чанка и контрольная сумма требуют полного
+    /// списка, поэтому здесь не используются
+    pub async fn send_files_iter<I>(&mut self, files: I) -> Result<usize, String>
+    where
+        I: Iterator<Item = std::io::Result<FileInfo>> + Send + 'static,
+    {
+        if let Some(quota) = &self.quota {
+            quota.check()?;
         }
+        let size = self.pinned_chunk_size().or(self.chunk_size).unwrap_or(DEFAULT_CHUNK_SIZE);
+        self.chunk_size = Some(size);
         
-        // Читаем чанк текущего размера
-        let read_size = (*chunk_size).min(buffer.len());
-        let n = f.read(&mut buffer[..read_size]).await.map_err(|e| e.to_string())?;
-        if n == 0 {
-            break;
+        // Обход папки блокирующий - ведём его в отдельном потоке
+        let (file_tx, mut file_rx) = mpsc::channel(FILE_ITER_BUFFER);
+        let producer = tokio::task::spawn_blocking(move || {
+            for file in files {
+                if file_tx.blocking_send(file).is_err() {
+                    break; // Передача прервана
+                }
+            }
+        });
+        
+        let mut count = 0;
+        let result = loop {
+            let Some(file) = file_rx.recv().await else { break Ok(count) };
+            let idx = count;
+            count += 1;
+            let file = match file {
+                Ok(file) => file,
+                Err(e) => {
+                    let e = format!("Ошибка обхода папки: {}", e);
+                    let _ = self.event_tx.send(TransferEvent::FileError(self.target_id, idx, e.clone()));
+                    if self.options.continue_on_error {
+                        continue;
+                    }
+                    break Err(e);
+                }
+            };
+            if let Err(e) = self.start_file(idx) {
+                break Err(e);
+            }
+            if let Err(e) = self.send_one(idx, &file).await {
+                break Err(e);
+            }
+        };
+        drop(file_rx);
+        let _ = producer.await;
+        
+        let count = result?;
+        let _ = self.event_tx.send(TransferEvent::TargetCompleted(self.target_id));
+        Ok(count)
+    }
+    
+    /// Передать файлы пакета. Возвращает контрольную сумму пакета,
+    /// если она включена и передано всё
+    async fn send_batch(&mut self, files: &[FileInfo]) -> Result<Option<u64>, String> {
+        if let Some(quota) = &self.quota {
+            quota.check()?;
         }
         
-        // Сжимаем данные если включено
-        let (chunk_data, original_size) = if options.use_compression {
-            let compressed = compression::compress(&buffer[..n]);
-            (compressed, n)
-        } else {
-            (buffer[..n].to_vec(), n)
+        let size = match (self.pinned_chunk_size(), self.chunk_size) {
+            (Some(size), _) => size,
+            // Подобранный в прошлом пакете размер продолжает адаптироваться
+            (None, Some(size)) => size,
+            // Проба окупается только на заметном объёме и только с получателем,
+            // подтвердившим её в Hello. Неудачная проба - не повод бросать передачу
+            (None, None) if self.features & FEATURE_CHUNK_PROBE != 0
+                && files.iter().map(|f| f.size).sum::<u64>() >= PROBE_MIN_TOTAL_SIZE =>
+            {
+                probe_chunk_size(&mut *self.stream).await.unwrap_or(DEFAULT_CHUNK_SIZE)
+            }
+            (None, None) => DEFAULT_CHUNK_SIZE,
         };
+        self.chunk_size = Some(size);
         
-        let compressed_size = chunk_data.len();
-        total_original += original_size as u64;
-        total_compressed += compressed_size as u64;
+        // Файлы с одинаковым содержимым: idx -> индекс первого такого файла
+        let duplicates = if self.options.dedupe {
+            find_duplicates(files, self.options.verify_dedupe).await
+        } else {
+            HashMap::new()
+        };
         
-        let chunk_msg = Message::FileChunk {
-            data: chunk_data,
-            original_size,
+        // Что передано в прошлый раз (ключ карты требует stat каждого файла)
+        self.completion = if self.options.remember_progress {
+            let (dir, addr, list) = (self.options.progress_dir.clone(), self.addr.clone(), files.to_vec());
+            tokio::task::spawn_blocking(move || CompletionMap::load(dir.as_deref(), &addr, &list)).await.ok()
+        } else {
+            None
         };
-        let data = chunk_msg.to_bytes().map_err(|e| e.to_string())?;
-        stream.write_all(&data).await.map_err(|e| e.to_string())?;
         
-        transferred += n as u64;
-        bytes_since_check += n as u64;
-        
-        // Отправляем прогресс раз в секунду (не чаще)
-        if last_progress_update.elapsed().as_secs() >= 1 {
-            let _ = event_tx.send(TransferEvent::Progress(
-                target_id,
-                file_idx,
-                transferred,
-                total_original,
-                total_compressed,
-            ));
-            last_progress_update = Instant::now();
-        }
-        
-        // Адаптируем размер чанка каждые 100ms
-        let elapsed = last_speed_check.elapsed();
-        if elapsed.as_millis() >= 100 {
-            let speed = bytes_since_check as f64 / elapsed.as_secs_f64();
-            adapt_chunk_size(chunk_size, speed);
-            last_speed_check = Instant::now();
-            bytes_since_check = 0;
-        }
-    }
-    
-    // Финальное обновление прогресса (100%)
-    let _ = event_tx.send(TransferEvent::Progress(
-        target_id,
-        file_idx,
-        transferred,
-        total_original,
-        total_compressed,
-    ));
-    
-    // Отправляем конец файла
-    let end_msg = Message::FileEnd;
-    let data = end_msg.to_bytes().map_err(|e| e.to_string())?;
-    stream.write_all(&data).await.map_err(|e| e.to_string())?;
+        // Хэши содержимого для контрольной суммы (None - файл не передан)
+        let mut content_hashes: Vec<Option<u64>> = vec![None; files.len()];
+        let target_id = self.target_id;
+        
+        for (idx, file) in files.iter().enumerate() {
+            self.start_file(idx)?;
+            
+            // Передан целиком в прошлый раз - не спрашиваем получателя
+            if self.completion.as_ref().is_some_and(|map| map.is_done(idx)) {
+                if self.options.checksum {
+                    content_hashes[idx] = compute_full_hash(&file.path).await.ok();
+                }
+                let _ = self.event_tx.send(TransferEvent::FileSkipped(target_id, idx, SkipReason::AlreadyComplete));
+                continue;
+            }
+            
+            // Дубликат уже отправленного файла - просим получателя скопировать его
+            if let Some(&original_idx) = duplicates.get(&idx) {
+                let from = &files[original_idx].relative_path;
+                if *from == file.relative_path {
+                    // Тот же файл под тем же именем - получатель его уже имеет
+                    content_hashes[idx] = content_hashes[original_idx];
+                    self.mark_done(idx);
+                    let _ = self.event_tx.send(TransferEvent::FileSkipped(target_id, idx, SkipReason::Duplicate));
+                    continue;
+                }
+                // Получатель без FEATURE_FILE_LINK FileLink не прочитает - передаём целиком
+                let linked = if self.features & FEATURE_FILE_LINK != 0 {
+                    send_file_link(&mut *self.stream, from, &file.relative_path).await
+                } else {
+                    Ok(false)
+                };
+                match linked {
+                    Ok(true) => {
+                        content_hashes[idx] = content_hashes[original_idx];
+                        self.mark_done(idx);
+                        let _ = self.event_tx.send(TransferEvent::FileDeduplicated(target_id, idx, original_idx));
+                        continue;
+                    }
+                    Ok(false) => {} // Получатель не смог скопировать - передаём целиком
+                    Err(e) => {
+                        let _ = self.event_tx.send(TransferEvent::FileError(target_id, idx, e.clone()));
+                        return Err(e);
+                    }
+                }
+            }
+            
+            content_hashes[idx] = self.send_one(idx, file).await?;
+        }
+        if let Some(map) = self.completion.take() {
+            map.finish();
+        }
+        
+        // Сумма - только если передано всё: иначе она не совпадёт с копией у получателя
+        if self.options.checksum {
+            if let Some(hashes) = content_hashes.into_iter().collect::<Option<Vec<u64>>>() {
+                return Ok(Some(combine_checksums(files, &hashes)));
+            }
+        }
+        Ok(None)
+    }
+    
+    /// Отметить файл переданным в карте пакета (если она ведётся)
+    fn mark_done(&mut self, idx: usize) {
+        if let Some(map) = &mut self.completion {
+            map.mark(idx);
+        }
+    }
+    
+    /// Начать очередной файл: проверить флаг остановки и сообщить `FileStarted`
+    fn start_file(&self, idx: usize) -> Result<(), String> {
+        if self.stop_flag.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err("Остановлено пользователем".to_string());
+        }
+        let _ = self.event_tx.send(TransferEvent::FileStarted(self.target_id, idx));
+        Ok(())
+    }
+    
+    /// Передать один файл, при обрыве - через запасной транспорт.
+    ///
+    /// Возвращает хэш содержимого для контрольной суммы (None - сумма не нужна
+    /// или файл пропущен, потому что не открылся, а включено `continue_on_error`)
+    async fn send_one(&mut self, idx: usize, file: &FileInfo) -> Result<Option<u64>, String> {
+        use std::sync::atomic::Ordering;
+        
+        let target_id = self.target_id;
+        
+        // Файл открываем до FileStart: если он недоступен, поток ещё не затронут
+        // и можно перейти к следующему. Ошибки посреди файла рассинхронизируют
+        // протокол, поэтому всегда прерывают передачу
+        let mut f = match tokio::fs::File::open(&file.path).await {
+            Ok(f) => f,
+            Err(e) => {
+                let e = format!("Не удалось открыть файл: {}", e);
+                let _ = self.event_tx.send(TransferEvent::FileError(target_id, idx, e.clone()));
+                if self.options.continue_on_error {
+                    return Ok(None);
+                }
+                return Err(e);
+            }
+        };
+        
+        // Имя не в UTF-8: сырые байты пути уходят перед FileStart. Получатель
+        // без FEATURE_RAW_FILE_NAME их не поймёт - файл сохранится под lossy-именем
+        let raw_name = file.raw_relative_path();
+        if raw_name.is_some() && self.features & FEATURE_RAW_FILE_NAME == 0 {
+            let _ = self.event_tx.send(TransferEvent::FileWarning(target_id, idx, FileWarning::LossyName));
+        }
+        if self.options.verify_integrity && self.features & FEATURE_VERIFY_INTEGRITY == 0 {
+            let _ = self.event_tx.send(TransferEvent::FileWarning(target_id, idx, FileWarning::NotVerified));
+        }
+        
+        let mut content_hash = None;
+        let result = loop {
+            let result = self.send_file_once(
+                file,
+                raw_name.as_deref().filter(|_| self.features & FEATURE_RAW_FILE_NAME != 0),
+                idx,
+                f,
+                &mut content_hash,
+            ).await;
+            if let Some(quota) = &self.quota {
+                let _ = quota.save();
+            }
+            
+            // Обрыв посреди файла: пробуем запасной транспорт, получатель
+            // ответит ResumeAck с уже принятым offset
+            let Err(e) = &result else { break result };
+            if self.stop_flag.load(Ordering::SeqCst) {
+                break result;
+            }
+            match reconnect_fallback(&mut self.fallbacks, target_id, &self.addr, e, &self.options, &self.event_tx).await {
+                Some((stream, features)) => (self.stream, self.features) = (stream, features),
+                None => break result,
+            }
+            f = tokio::fs::File::open(&file.path)
+                .await
+                .map_err(|e| format!("Не удалось открыть файл: {}", e))?;
+        };
+        
+        match result {
+            Ok(SentFile::Rejected(reason)) => {
+                // Отказ - правило получателя, а не сбой: остальные файлы идут дальше
+                let e = format!("Получатель отклонил файл: {}", reason);
+                let _ = self.event_tx.send(TransferEvent::FileError(target_id, idx, e));
+                Ok(None)
+            }
+            Ok(SentFile::Corrupted) => {
+                // Поток не рассинхронизирован: получатель ждёт следующий файл
+                let e = "Файл повреждён при передаче (SHA-256 не совпал)".to_string();
+                let _ = self.event_tx.send(TransferEvent::FileCorrupted(target_id, idx));
+                let _ = self.event_tx.send(TransferEvent::FileError(target_id, idx, e.clone()));
+                if self.options.continue_on_error {
+                    return Ok(None);
+                }
+                Err(e)
+            }
+            Ok(sent) => {
+                if self.options.checksum && content_hash.is_none() {
+                    // Файл прочитан не с начала (докачка, пропуск) - хэшируем заново
+                    content_hash = compute_full_hash(&file.path).await.ok();
+                }
+                self.mark_done(idx);
+                if matches!(sent, SentFile::Skipped) {
+                    // Полный offset в ResumeAck означает совпадение размера и хэша у получателя
+                    let _ = self.event_tx.send(TransferEvent::FileSkipped(target_id, idx, SkipReason::UpToDate));
+                } else {
+                    let _ = self.event_tx.send(TransferEvent::FileCompleted(target_id, idx));
+                }
+                Ok(content_hash.filter(|_| self.options.checksum))
+            }
+            Err(e) => {
+                let _ = self.event_tx.send(TransferEvent::FileError(target_id, idx, e.clone()));
+                Err(e)
+            }
+        }
+    }
+    
+    /// Отправить один файл (одна попытка, без запасного транспорта).
+    /// `raw_name` - сырые байты имени для RawFileName, если получатель их понимает
+    async fn send_file_once(
+        &mut self,
+        file: &FileInfo,
+        raw_name: Option<&[u8]>,
+        file_idx: usize,
+        mut f: tokio::fs::File,
+        content_hash: &mut Option<u64>,
+    ) -> Result<SentFile, String> {
+        use std::sync::atomic::Ordering;
+        
+        let options = self.options.negotiated(self.features);
+        let features = self.features;
+        let target_id = self.target_id;
+        let stream = &mut *self.stream;
+        let chunk_size = self.chunk_size.get_or_insert(DEFAULT_CHUNK_SIZE);
+        let quota = self.quota.as_ref();
+        let mut limiter = self.limiter.as_mut();
+        let (event_tx, stop_flag) = (&self.event_tx, &self.stop_flag);
+        
+        // Вычисляем быстрый хэш для синхронизации (0 - получатель не докачивает)
+        let quick_hash = if options.enable_resume {
+            compute_quick_hash(&file.path).await.unwrap_or(0)
+        } else {
+            0
+        };
+        
+        // Сначала сырые байты пути. Если получатель не может его воспроизвести,
+        // файл сохранится под lossy-именем из FileStart
+        if let Some(raw) = raw_name {
+            send_raw_file_name(stream, raw).await?;
+        }
+        
+        if options.preserve_xattrs {
+            let path = file.path.clone();
+            let xattrs = tokio::task::spawn_blocking(move || crate::utils::read_xattrs(&path))
+                .await
+                .unwrap_or_default();
+            send_file_meta(stream, xattrs).await?;
+        }
+        
+        // Просьба не распаковывать - только к этому FileStart
+        let is_archive = crate::extract::ArchiveType::from_filename(&file.relative_path) != crate::extract::ArchiveType::Unknown;
+        if (file.no_extract || options.no_extract) && is_archive && features & FEATURE_NO_EXTRACT != 0 {
+            send_no_extract(stream).await?;
+        }
+        
+        let compressed = match options.compression {
+            CompressionMode::Off => false,
+            CompressionMode::On => true,
+            CompressionMode::Auto => sample_worth_compressing(&mut f, &file.name).await?,
+        };
+        // Не-LZ4 кодек объявляется заранее; старый получатель его не знает - тогда LZ4
+        let codec = match options.codec {
+            CompressionCodec::Lz4 => CompressionCodec::Lz4,
+            codec if !compressed => codec,
+            codec => {
+                if send_file_codec(stream, codec).await? { codec } else { CompressionCodec::Lz4 }
+            }
+        };
+        
+        // Отправляем заголовок
+        let start_msg = Message::FileStart {
+            filename: file.relative_path.clone(),
+            size: file.size,
+            compressed,
+            offset: 0,
+            quick_hash,
+        };
+        let data = start_msg.to_bytes().map_err(|e| e.to_string())?;
+        stream.write_all(&data).await.map_err(|e| e.to_string())?;
+        
+        // Ждём ответ (может быть Ack, ResumeAck, список уже распакованного или отказ)
+        let mut repacked = false;
+        let resume_offset = match wait_resume_ack_transport(stream).await? {
+            StartReply::Accepted(offset) => offset,
+            StartReply::Manifest(entries) => {
+                if let Some(archive) = repack_archive(file, entries).await {
+                    f = archive;
+                    repacked = true;
+                }
+                None
+            }
+            StartReply::Rejected(reason) => return Ok(SentFile::Rejected(reason)),
+        };
+        
+        // Если ResumeAck с offset == size, файл уже актуален. Простой Ack на пустой
+        // файл означает, что получатель его создаёт - нужно дослать FileEnd
+        if resume_offset.is_some_and(|offset| offset >= file.size) {
+            return Ok(SentFile::Skipped);
+        }
+        let start_offset = resume_offset.unwrap_or(0);
+        
+        // SHA-256 для получателя - всегда по всему файлу
+        let mut sha = options.verify_integrity.then(Sha256::new);
+        
+        // Если есть offset, сообщаем о возобновлении
+        if start_offset > 0 {
+            let _ = event_tx.send(TransferEvent::FileResumed(target_id, file_idx, start_offset));
+            match sha.as_mut() {
+                // Уже принятое начало не отправляем, но хэшируем
+                Some(sha) => {
+                    f.seek(std::io::SeekFrom::Start(0)).await.map_err(|e| e.to_string())?;
+                    crate::utils::sha256_prefix(&mut f, start_offset, sha).await.map_err(|e| e.to_string())?;
+                }
+                None => {
+                    f.seek(std::io::SeekFrom::Start(start_offset)).await.map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        
+        // Хэш содержимого попутно с чтением - только если файл читается с начала и целиком
+        let mut hasher = (options.checksum && start_offset == 0 && !repacked).then(FnvHasher::new);
+        
+        // Отправляем данные с адаптивным размером чанка
+        let mut buffer = vec![0u8; MAX_CHUNK_SIZE.max(*chunk_size)];
+        let mut transferred: u64 = start_offset;
+        let mut total_original: u64 = 0;
+        let mut total_compressed: u64 = 0;
+        let mut last_speed_check = Instant::now();
+        let mut last_progress_update = Instant::now();
+        let mut bytes_since_check: u64 = 0;
+        
+        loop {
+            // Проверяем флаг остановки
+            if stop_flag.load(Ordering::SeqCst) {
+                return Err("Остановлено пользователем".to_string());
+            }
+            if let Some(quota) = quota {
+                quota.check()?;
+            }
+            
+            // Читаем чанк текущего размера
+            let read_size = (*chunk_size).min(buffer.len());
+            let n = f.read(&mut buffer[..read_size]).await.map_err(|e| e.to_string())?;
+            if n == 0 {
+                break;
+            }
+            if let Some(hasher) = hasher.as_mut() {
+                hasher.update(&buffer[..n]);
+            }
+            if let Some(sha) = sha.as_mut() {
+                sha.update(&buffer[..n]);
+            }
+            
+            // Сжимаем данные если включено
+            let (chunk_data, original_size) = if compressed {
+                let compressed = codec.compress(&buffer[..n]);
+                (compressed, n)
+            } else {
+                (buffer[..n].to_vec(), n)
+            };
+            
+            let compressed_size = chunk_data.len();
+            total_original += original_size as u64;
+            total_compressed += compressed_size as u64;
+            
+            let chunk_msg = Message::FileChunk {
+                data: chunk_data,
+                original_size,
+            };
+            let data = chunk_msg.to_bytes().map_err(|e| e.to_string())?;
+            stream.write_all(&data).await.map_err(|e| e.to_string())?;
+            if let Some(quota) = quota {
+                quota.add(data.len() as u64);
+            }
+            if let Some(limiter) = limiter.as_deref_mut() {
+                limiter.consume(data.len()).await;
+            }
+            
+            transferred += n as u64;
+            bytes_since_check += n as u64;
+            
+            // Отправляем прогресс раз в секунду (не чаще)
+            if last_progress_update.elapsed().as_secs() >= 1 {
+                let _ = event_tx.send(TransferEvent::Progress(
+                    target_id,
+                    file_idx,
+                    transferred,
+                    total_original,
+                    total_compressed,
+                ));
+                last_progress_update = Instant::now();
+            }
+            
+            // Адаптируем размер чанка каждые 100ms (если он не зафиксирован и не задан пределом скорости)
+            let elapsed = last_speed_check.elapsed();
+            if options.fixed_chunk_size.is_none() && limiter.is_none() && elapsed.as_millis() >= 100 {
+                let speed = bytes_since_check as f64 / elapsed.as_secs_f64();
+                adapt_chunk_size(chunk_size, speed);
+                last_speed_check = Instant::now();
+                bytes_since_check = 0;
+            }
+        }
+        
+        // Финальное обновление прогресса (100%)
+        let _ = event_tx.send(TransferEvent::Progress(
+            target_id,
+            file_idx,
+            transferred,
+            total_original,
+            total_compressed,
+        ));
+        
+        // Хэш - перед FileEnd: получатель сверит его, закрывая файл
+        if let Some(sha) = sha {
+            if !send_file_hash(stream, sha.finalize().into()).await? {
+                let _ = event_tx.send(TransferEvent::FileWarning(target_id, file_idx, FileWarning::NotVerified));
+            }
+        }
+        
+        // Отправляем конец файла
+        let end_msg = Message::FileEnd;
+        let data = end_msg.to_bytes().map_err(|e| e.to_string())?;
+        stream.write_all(&data).await.map_err(|e| e.to_string())?;
+        
+        // Ждём подтверждение
+        if !wait_file_end_ack_transport(stream).await? {
+            return Ok(SentFile::Corrupted);
+        }
+        
+        *content_hash = hasher.map(FnvHasher::finish);
+        Ok(SentFile::Sent)
+    }
+}
+
+/// Переподключиться через первый доступный запасной транспорт.
+///
+/// Возвращает поток и подтверждённые получателем возможности
+/// или None, если запасных транспортов не осталось.
+async fn reconnect_fallback(
+    fallbacks: &mut impl Iterator<Item = TransportType>,
+    target_id: usize,
+    addr: &str,
+    reason: &str,
+    options: &SendOptions,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+) -> Option<(Box<dyn TransportStream>, u32)> {
+    let mut reason = reason.to_string();
+    for transport_type in fallbacks {
+        let _ = event_tx.send(TransferEvent::TransportFallback(target_id, transport_type, reason.clone()));
+        match connect_negotiated(transport_type, addr, options, options.wanted_features()).await {
+            Ok((stream, features)) => {
+                let _ = event_tx.send(TransferEvent::Connected(target_id, connection_label(&*stream, addr)));
+                return Some((stream, features));
+            }
+            Err(e) => reason = e,
+        }
+    }
+    None
+}
+
+/// Подпись соединения для `Connected`: фактический адрес получателя и транспорт
+fn connection_label(stream: &dyn TransportStream, addr: &str) -> String {
+    let peer = stream.peer_addr().map_or_else(|_| addr.to_string(), |peer| peer.to_string());
+    format!("{} [{}]", peer, stream.transport_name())
+}
+
+/// Подключиться, повторяя неудачные попытки до `connect_retries` раз.
+///
+/// Перед каждым повтором приходит `ConnectionRetrying`, пауза начинается с `retry_delay`
+/// и удваивается (не дольше `MAX_RETRY_DELAY`). Остановка прерывает паузу
+async fn connect_with_retries(
+    target_id: usize,
+    addr: &str,
+    options: &SendOptions,
+    event_tx: &mpsc::UnboundedSender<TransferEvent>,
+    stop_flag: &std::sync::atomic::AtomicBool,
+) -> Result<(Box<dyn TransportStream>, u32), String> {
+    let mut attempt = 0;
+    loop {
+        match connect_negotiated(options.transport_type, addr, options, options.wanted_features()).await {
+            Ok(negotiated) => return Ok(negotiated),
+            Err(e) if attempt >= options.connect_retries => return Err(e),
+            Err(_) => {}
+        }
+        attempt += 1;
+        let _ = event_tx.send(TransferEvent::ConnectionRetrying(target_id, attempt));
+        if !sleep_unless_stopped(retry_backoff(options.retry_delay, attempt), stop_flag).await {
+            return Err("Остановлено пользователем".to_string());
+        }
+    }
+}
+
+/// Пауза перед повтором `attempt` (с 1): `delay`, 2x`delay`, 4x`delay`... до `MAX_RETRY_DELAY`
+/// (если сама `delay` не больше)
+fn retry_backoff(delay: Duration, attempt: u32) -> Duration {
+    let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+    delay.saturating_mul(factor).min(MAX_RETRY_DELAY.max(delay))
+}
+
+/// Подождать `duration`, проверяя флаг остановки. false - остановлено раньше
+async fn sleep_unless_stopped(duration: Duration, stop_flag: &std::sync::atomic::AtomicBool) -> bool {
+    use std::sync::atomic::Ordering;
+    
+    let deadline = tokio::time::Instant::now() + duration;
+    while !stop_flag.load(Ordering::SeqCst) {
+        let now = tokio::time::Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        tokio::time::sleep((deadline - now).min(STOP_POLL_INTERVAL)).await;
+    }
+    false
+}
+
+/// Подключиться и, если нужны возможности сверх базового протокола
+/// (`features`, обычно `SendOptions::wanted_features`), согласовать их в Hello.
+///
+/// Старый получатель отвечает на Hello ошибкой (остаёмся без возможностей)
+/// или закрывает соединение - тогда переподключаемся без согласования.
+/// Возвращает поток и возможности, которые получатель подтвердил
+async fn connect_negotiated(
+    transport_type: TransportType,
+    addr: &str,
+    options: &SendOptions,
+    features: u32,
+) -> Result<(Box<dyn TransportStream>, u32), String> {
+    let connect = || async {
+        super::transport::connect_with_timeout(transport_type, addr, options.connect_timeout, options.socket_tuning)
+            .await
+            .map(|stream| super::transport::with_io_timeout(stream, options.timeout))
+            .map_err(|e| format!("Ошибка подключения [{}]: {}", transport_type.name(), e))
+    };
+    let mut stream = connect().await?;
+    if features == 0 {
+        return Ok((stream, 0));
+    }
+    
+    match send_hello(&mut *stream, features).await {
+        Ok(Message::HelloAck { features }) if features & FEATURE_COMPACT_FRAMING != 0 => {
+            Ok((Box::new(CompactStream::new(stream)), features))
+        }
+        Ok(Message::HelloAck { features }) => Ok((stream, features)),
+        Ok(_) => Ok((stream, 0)),
+        Err(_) => Ok((connect().await?, 0)),
+    }
+}
+
+/// Отправить Hello с запросом `features` и дождаться ответа получателя
+pub(crate) async fn send_hello(stream: &mut dyn TransportStream, features: u32) -> std::io::Result<Message> {
+    let data = Message::Hello { features, intent: ConnectionIntent::Transfer }
+        .to_bytes()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    stream.write_all(&data).await?;
+    stream.flush().await?;
+    
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await?;
+    let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+    stream.read_exact(&mut data).await?;
+    Message::from_bytes(&data).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+}
+
+/// Чем закончилась отправка одного файла
+enum SentFile {
+    Sent,
+    /// Уже есть у получателя целиком
+    Skipped,
+    /// SHA-256 у получателя не совпал (`verify_integrity`), копия удалена
+    Corrupted,
+    /// Получатель не принимает такие файлы (причина)
+    Rejected(String),
+}
+
+/// Архив без записей, которые получатель уже распаковал (`Message::ExtractManifest`),
+/// во временном файле. None - пересобрать не вышло: шлём архив как есть,
+/// получатель сам пропустит распакованное
+async fn repack_archive(file: &FileInfo, entries: Vec<ManifestEntry>) -> Option<tokio::fs::File> {
+    use std::io::{Seek, SeekFrom};
     
-    // Ждём подтверждение
-    wait_ack_transport(stream).await?;
+    let path = file.path.clone();
+    let archive_type = crate::extract::ArchiveType::from_filename(&file.name);
+    let repacked = tokio::task::spawn_blocking(move || -> std::io::Result<std::fs::File> {
+        let done: HashSet<(String, u64, u64)> = entries
+            .into_iter()
+            .map(|entry| (entry.path, entry.size, entry.mtime))
+            .collect();
+        let mut output = tempfile::tempfile()?;
+        crate::extract::repack_tar_without(&path, archive_type, &mut output, |path, size, mtime| {
+            done.contains(&(path.to_string(), size, mtime))
+        })?;
+        output.seek(SeekFrom::Start(0))?;
+        Ok(output)
+    }).await;
+    repacked.ok()?.ok().map(tokio::fs::File::from_std)
+}
+
+/// Режим Auto: прочитать начало файла и решить, сжимать ли его.
+/// Позиция чтения возвращается в начало файла
+async fn sample_worth_compressing(f: &mut tokio::fs::File, filename: &str) -> Result<bool, String> {
+    let mut sample = vec![0u8; COMPRESSION_SAMPLE_SIZE];
+    let mut filled = 0;
+    while filled < sample.len() {
+        let n = f.read(&mut sample[filled..]).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    f.seek(std::io::SeekFrom::Start(0)).await.map_err(|e| e.to_string())?;
     
-    Ok(false) // Файл был передан
+    Ok(compression::is_worth_compressing(&sample[..filled], filename))
 }
 
 /// Вычислить быстрый хэш файла
@@ -383,81 +1579,1740 @@ async fn compute_quick_hash(path: &std::path::Path) -> std::io::Result<u64> {
     Ok(hasher.finish())
 }
 
-/// Простой FNV-1a хэшер
-struct FnvHasher {
-    hash: u64,
+/// Найти файлы с одинаковым содержимым внутри одной передачи.
+///
+/// Возвращает `file_idx -> индекс первого файла с таким же содержимым`.
+/// Кандидаты отбираются по размеру и быстрому хэшу, затем сверяется хэш
+/// всего содержимого, а при `verify` - ещё и побайтно.
+async fn find_duplicates(files: &[FileInfo], verify: bool) -> HashMap<usize, usize> {
+    let mut duplicates = HashMap::new();
+    let mut seen: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    let mut full_hashes: HashMap<usize, u64> = HashMap::new();
+    
+    for (idx, file) in files.iter().enumerate() {
+        // Пустые файлы дешевле передать, чем сравнивать
+        if file.size == 0 {
+            continue;
+        }
+        let Ok(quick_hash) = compute_quick_hash(&file.path).await else {
+            continue;
+        };
+        
+        let candidates = seen.entry((file.size, quick_hash)).or_default();
+        let mut original = None;
+        for &candidate in candidates.iter() {
+            if same_content(files, candidate, idx, &mut full_hashes, verify).await {
+                original = Some(candidate);
+                break;
+            }
+        }
+        
+        match original {
+            Some(original_idx) => {
+                duplicates.insert(idx, original_idx);
+            }
+            None => candidates.push(idx),
+        }
+    }
+    
+    duplicates
 }
 
-impl FnvHasher {
-    fn new() -> Self {
-        Self { hash: 0xcbf29ce484222325 }
+/// Совпадает ли содержимое двух файлов (хэш всего файла, опционально - побайтно)
+async fn same_content(
+    files: &[FileInfo],
+    a: usize,
+    b: usize,
+    full_hashes: &mut HashMap<usize, u64>,
+    verify: bool,
+) -> bool {
+    if files[a].path == files[b].path {
+        return true;
     }
     
-    fn update(&mut self, data: &[u8]) {
-        const FNV_PRIME: u64 = 0x100000001b3;
-        for byte in data {
-            self.hash ^= *byte as u64;
-            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+    for idx in [a, b] {
+        if let std::collections::hash_map::Entry::Vacant(entry) = full_hashes.entry(idx) {
+            match compute_full_hash(&files[idx].path).await {
+                Ok(hash) => {
+                    entry.insert(hash);
+                }
+                Err(_) => return false,
+            }
         }
     }
     
-    fn finish(self) -> u64 {
-        self.hash
+    if full_hashes[&a] != full_hashes[&b] {
+        return false;
     }
+    
+    !verify || files_equal(&files[a].path, &files[b].path).await.unwrap_or(false)
 }
 
-/// Адаптировать размер чанка на основе скорости
-fn adapt_chunk_size(chunk_size: &mut usize, speed_bytes_per_sec: f64) {
-    // Целевое время чанка: 50-100ms
-    let target_time_ms = 75.0;
+/// Хэш всего содержимого файла (FNV-1a)
+async fn compute_full_hash(path: &std::path::Path) -> std::io::Result<u64> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = FnvHasher::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
     
-    if speed_bytes_per_sec > 0.0 {
-        let optimal = (speed_bytes_per_sec * target_time_ms / 1000.0) as usize;
-        let new_size = optimal.clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
-        
-        // Плавное изменение
-        if new_size > *chunk_size {
-            *chunk_size = (*chunk_size * 3 / 2).min(new_size);
-        } else if new_size < *chunk_size {
-            *chunk_size = (*chunk_size * 2 / 3).max(new_size);
+    loop {
+        let n = file.read(&mut buffer).await?;
+        if n == 0 {
+            break;
         }
-        
-        *chunk_size = (*chunk_size).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+        hasher.update(&buffer[..n]);
     }
+    
+    Ok(hasher.finish())
 }
 
-/// Ждать Ack или ResumeAck через транспорт, возвращает offset
-async fn wait_resume_ack_transport(stream: &mut dyn TransportStream) -> Result<u64, String> {
-    let mut len_buf = [0u8; 4];
-    stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
-    let len = u32::from_le_bytes(len_buf) as usize;
+/// Контрольная сумма набора файлов - та же, что отправитель сообщает в `TargetChecksum`.
+///
+/// FNV-1a по парам (относительный путь, хэш содержимого), отсортированным по пути:
+/// порядок файлов не важен, повторы одного пути учитываются один раз. Подходит
+/// для проверки, что принятая копия совпадает с записью в истории
+pub async fn transfer_checksum(files: &[FileInfo]) -> std::io::Result<u64> {
+    let mut hashes = Vec::with_capacity(files.len());
+    for file in files {
+        hashes.push(compute_full_hash(&file.path).await?);
+    }
+    Ok(combine_checksums(files, &hashes))
+}
+
+/// Свести хэши содержимого файлов в одну контрольную сумму
+fn combine_checksums(files: &[FileInfo], hashes: &[u64]) -> u64 {
+    let mut entries: Vec<(&str, u64)> = files.iter()
+        .map(|f| f.relative_path.as_str())
+        .zip(hashes.iter().copied())
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
     
-    let mut data = vec![0u8; len];
-    stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    let mut seen = HashSet::new();
+    let mut hasher = FnvHasher::new();
+    for (path, hash) in entries {
+        if !seen.insert(path) {
+            continue;
+        }
+        hasher.update(path.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&hash.to_le_bytes());
+    }
+    hasher.finish()
+}
+
+/// Побайтное сравнение двух файлов
+async fn files_equal(a: &std::path::Path, b: &std::path::Path) -> std::io::Result<bool> {
+    let mut file_a = tokio::fs::File::open(a).await?;
+    let mut file_b = tokio::fs::File::open(b).await?;
+    let mut buf_a = vec![0u8; 1024 * 1024];
+    let mut buf_b = vec![0u8; 1024 * 1024];
     
-    let msg = Message::from_bytes(&data).map_err(|e| e.to_string())?;
-    match msg {
-        Message::Ack => Ok(0),
-        Message::ResumeAck { offset } => Ok(offset),
-        Message::Cancel => Err("⛔ Получатель отменил передачу".to_string()),
-        Message::Error(e) => Err(e),
-        _ => Err("Неожиданный ответ".to_string()),
+    loop {
+        let n_a = read_full(&mut file_a, &mut buf_a).await?;
+        let n_b = read_full(&mut file_b, &mut buf_b).await?;
+        if n_a != n_b || buf_a[..n_a] != buf_b[..n_b] {
+            return Ok(false);
+        }
+        if n_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Заполнить буфер целиком (меньше - только в конце файла)
+async fn read_full(file: &mut tokio::fs::File, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let n = file.read(&mut buffer[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
     }
+    Ok(filled)
 }
 
-async fn wait_ack_transport(stream: &mut dyn TransportStream) -> Result<(), String> {
+/// Отправить запрос и дождаться Ack. Error получателя - `ErrorKind::Unsupported`
+/// с его текстом (отказ выполнить, соединение цело), Cancel - `Interrupted`,
+/// любой другой ответ - `InvalidData`
+async fn request_ack(stream: &mut dyn TransportStream, msg: &Message) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind};
+    
+    let data = msg.to_bytes().map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    stream.write_all(&data).await?;
+    
     let mut len_buf = [0u8; 4];
-    stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+    stream.read_exact(&mut len_buf).await?;
     let len = u32::from_le_bytes(len_buf) as usize;
     
     let mut data = vec![0u8; len];
-    stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    stream.read_exact(&mut data).await?;
     
-    let msg = Message::from_bytes(&data).map_err(|e| e.to_string())?;
-    match msg {
+    match Message::from_bytes(&data).map_err(|e| Error::new(ErrorKind::InvalidData, e))? {
         Message::Ack => Ok(()),
-        Message::Cancel => Err("⛔ Получатель отменил передачу".to_string()),
-        Message::Error(e) => Err(e),
-        _ => Err("Неожиданный ответ".to_string()),
+        Message::Error(e) => Err(Error::new(ErrorKind::Unsupported, e)),
+        Message::Cancel => Err(Error::new(ErrorKind::Interrupted, "⛔ Получатель отменил передачу")),
+        _ => Err(Error::new(ErrorKind::InvalidData, "Неожиданный ответ")),
+    }
+}
+
+/// `request_ack`, где отказ получателя не ошибка: Ok(false)
+async fn request_optional(stream: &mut dyn TransportStream, msg: &Message) -> Result<bool, String> {
+    match request_ack(stream, msg).await {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => Ok(false),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Попросить получателя скопировать уже переданный файл (только после `FEATURE_FILE_LINK`).
+/// Возвращает false, если получатель не смог: файла нет или имя не прошло его правила
+async fn send_file_link(stream: &mut dyn TransportStream, from: &str, to: &str) -> Result<bool, String> {
+    let msg = Message::FileLink {
+        from: from.to_string(),
+        to: to.to_string(),
+    };
+    request_optional(stream, &msg).await
+}
+
+/// Передать сырые байты пути для следующего FileStart.
+/// Error от получателя не фатален: файл сохранится под lossy-именем
+async fn send_raw_file_name(stream: &mut dyn TransportStream, bytes: &[u8]) -> Result<(), String> {
+    request_optional(stream, &Message::RawFileName { bytes: bytes.to_vec() }).await.map(|_| ())
+}
+
+/// Передать SHA-256 файла перед FileEnd. Ok(false) - получатель этот файл
+/// не сверяет (например, распаковывает архив на лету)
+async fn send_file_hash(stream: &mut dyn TransportStream, sha256: [u8; 32]) -> Result<bool, String> {
+    request_optional(stream, &Message::FileHash { sha256 }).await
+}
+
+/// Объявить кодек чанков следующего FileStart. Ok(false) - получатель
+/// его не поддерживает (старая версия), файл нужно сжать LZ4
+async fn send_file_codec(stream: &mut dyn TransportStream, codec: CompressionCodec) -> Result<bool, String> {
+    request_optional(stream, &Message::FileCodec { codec }).await
+}
+
+/// Попросить сохранить архив следующего FileStart как есть (только после `FEATURE_NO_EXTRACT`)
+async fn send_no_extract(stream: &mut dyn TransportStream) -> Result<(), String> {
+    request_ack(stream, &Message::NoExtract).await.map_err(|e| e.to_string())
+}
+
+/// Передать расширенные атрибуты для следующего FileStart
+async fn send_file_meta(stream: &mut dyn TransportStream, xattrs: Vec<(String, Vec<u8>)>) -> Result<(), String> {
+    match request_ack(stream, &Message::FileMeta { xattrs }).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+            Err(format!("Получатель не принял атрибуты файла: {}", e))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Простой FNV-1a хэшер
+pub(super) struct FnvHasher {
+    hash: u64,
+}
+
+impl FnvHasher {
+    pub(super) fn new() -> Self {
+        Self { hash: 0xcbf29ce484222325 }
+    }
+    
+    pub(super) fn update(&mut self, data: &[u8]) {
+        const FNV_PRIME: u64 = 0x100000001b3;
+        for byte in data {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    
+    pub(super) fn finish(self) -> u64 {
+        self.hash
+    }
+}
+
+/// Размеры чанка, которые пробуются в начале соединения (по возрастанию)
+const PROBE_CHUNK_SIZES: [usize; 3] = [DEFAULT_CHUNK_SIZE, 256 * 1024, MAX_CHUNK_SIZE];
+
+/// Объём пробы на каждый размер
+const PROBE_BURST_BYTES: usize = 1024 * 1024;
+
+/// Проба дольше этого - канал медленный, большие размеры не пробуем
+const PROBE_MAX_DURATION: Duration = Duration::from_millis(250);
+
+/// Меньший объём передачи отправляется без пробы
+const PROBE_MIN_TOTAL_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Подобрать начальный размер чанка: по пачке `ChunkProbe` каждого размера
+/// с замером до ответа на ping. Возвращает размер с лучшей скоростью,
+/// дальше он подстраивается как обычно (`adapt_chunk_size`)
+async fn probe_chunk_size(stream: &mut dyn TransportStream) -> Result<usize, String> {
+    let ping = Message::Ack.to_bytes().map_err(|e| e.to_string())?;
+    let mut best = (DEFAULT_CHUNK_SIZE, 0.0);
+    
+    for size in PROBE_CHUNK_SIZES {
+        let probe = Message::ChunkProbe { data: vec![0u8; size] }
+            .to_bytes()
+            .map_err(|e| e.to_string())?;
+        
+        let start = Instant::now();
+        for _ in 0..(PROBE_BURST_BYTES / size).max(1) {
+            stream.write_all(&probe).await.map_err(|e| e.to_string())?;
+        }
+        stream.write_all(&ping).await.map_err(|e| e.to_string())?;
+        stream.flush().await.map_err(|e| e.to_string())?;
+        
+        // Ответ на ping приходит, когда получатель прочитал всю пачку
+        let mut len_buf = [0u8; 4];
+        stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+        let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+        stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+        match Message::from_bytes(&data).map_err(|e| e.to_string())? {
+            Message::Ack => {}
+            Message::Error(e) => return Err(e),
+            Message::Cancel => return Err("⛔ Получатель отменил передачу".to_string()),
+            _ => return Err("Неожиданный ответ".to_string()),
+        }
+        
+        let elapsed = start.elapsed();
+        let speed = PROBE_BURST_BYTES as f64 / elapsed.as_secs_f64().max(1e-6);
+        if speed > best.1 {
+            best = (size, speed);
+        }
+        if elapsed > PROBE_MAX_DURATION {
+            break;
+        }
+    }
+    
+    Ok(best.0)
+}
+
+/// Адаптировать размер чанка на основе скорости
+fn adapt_chunk_size(chunk_size: &mut usize, speed_bytes_per_sec: f64) {
+    // Целевое время чанка: 50-100ms
+    let target_time_ms = 75.0;
+    
+    if speed_bytes_per_sec > 0.0 {
+        let optimal = (speed_bytes_per_sec * target_time_ms / 1000.0) as usize;
+        let new_size = optimal.clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+        
+        // Плавное изменение
+        if new_size > *chunk_size {
+            *chunk_size = (*chunk_size * 3 / 2).min(new_size);
+        } else if new_size < *chunk_size {
+            *chunk_size = (*chunk_size * 2 / 3).max(new_size);
+        }
+        
+        *chunk_size = (*chunk_size).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+    }
+}
+
+/// Ответ получателя на FileStart
+enum StartReply {
+    /// Ack (None) или ResumeAck с offset
+    Accepted(Option<u64>),
+    /// `Message::ExtractManifest`: эти записи архива уже распакованы, остальное - с начала
+    Manifest(Vec<ManifestEntry>),
+    /// `Message::Rejected` с причиной
+    Rejected(String),
+}
+
+/// Ждать Ack или ResumeAck через транспорт (или отказ от файла)
+async fn wait_resume_ack_transport(stream: &mut dyn TransportStream) -> Result<StartReply, String> {
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+    let len = u32::from_le_bytes(len_buf) as usize;
+    
+    let mut data = vec![0u8; len];
+    stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    
+    let msg = Message::from_bytes(&data).map_err(|e| e.to_string())?;
+    match msg {
+        Message::Ack => Ok(StartReply::Accepted(None)),
+        Message::ResumeAck { offset } => Ok(StartReply::Accepted(Some(offset))),
+        Message::ExtractManifest { entries } => Ok(StartReply::Manifest(entries)),
+        Message::Rejected { reason } => Ok(StartReply::Rejected(reason)),
+        Message::Cancel => Err("⛔ Получатель отменил передачу".to_string()),
+        Message::Error(e) => Err(e),
+        _ => Err("Неожиданный ответ".to_string()),
+    }
+}
+
+/// Ответ на FileEnd: true - Ack, false - ChecksumMismatch (копия у получателя удалена)
+async fn wait_file_end_ack_transport(stream: &mut dyn TransportStream) -> Result<bool, String> {
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+    let len = u32::from_le_bytes(len_buf) as usize;
+    
+    let mut data = vec![0u8; len];
+    stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    
+    let msg = Message::from_bytes(&data).map_err(|e| e.to_string())?;
+    match msg {
+        Message::Ack => Ok(true),
+        Message::ChecksumMismatch => Ok(false),
+        Message::Cancel => Err("⛔ Получатель отменил передачу".to_string()),
+        Message::Error(e) => Err(e),
+        _ => Err("Неожиданный ответ".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::receiver::spawn_test_server;
+    
+    fn file_info(dir: &std::path::Path, name: &str, content: &[u8]) -> FileInfo {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        FileInfo::new(path).unwrap()
+    }
+    
+    #[test]
+    fn test_builder() {
+        let options = SendOptions::builder()
+            .compression(CompressionMode::Auto)
+            .fixed_chunk_size(1024 * 1024)
+            .checksum(true)
+            .build()
+            .unwrap();
+        assert_eq!(options.compression, CompressionMode::Auto);
+        assert_eq!(options.fixed_chunk_size(), Some(1024 * 1024));
+        assert!(options.checksum);
+        assert!(options.dedupe);
+        
+        assert!(SendOptions::builder().dedupe(false).verify_dedupe(true).build().is_err());
+        assert!(SendOptions::builder().connect_timeout(Duration::ZERO).build().is_err());
+        assert!(SendOptions::builder().timeout(Duration::ZERO).build().is_err());
+        assert!(SendOptions::builder().daily_quota(0).build().is_err());
+        assert!(SendOptions::builder().max_bytes_per_sec(0).build().is_err());
+        assert!(SendOptions::builder().codec(CompressionCodec::Zstd { level: 0 }).build().is_err());
+    }
+    
+    #[test]
+    fn test_fixed_chunk_size_clamped() {
+        let mut options = SendOptions::default();
+        assert_eq!(options.fixed_chunk_size(), None);
+        
+        options.fixed_chunk_size = Some(1024 * 1024);
+        assert_eq!(options.fixed_chunk_size(), Some(1024 * 1024));
+        
+        options.fixed_chunk_size = Some(1);
+        assert_eq!(options.fixed_chunk_size(), Some(MIN_CHUNK_SIZE));
+        
+        options.fixed_chunk_size = Some(usize::MAX);
+        assert_eq!(options.fixed_chunk_size(), Some(MAX_FIXED_CHUNK_SIZE));
+    }
+    
+    #[tokio::test]
+    async fn test_find_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = vec![42u8; 10_000];
+        let mut changed = data.clone();
+        changed[5000] = 0; // Отличие в середине - быстрый хэш его не видит
+        
+        let files = vec![
+            file_info(dir.path(), "a.bin", &data),
+            file_info(dir.path(), "b.bin", &changed),
+            file_info(dir.path(), "c.bin", &data),
+            file_info(dir.path(), "empty1", b""),
+            file_info(dir.path(), "empty2", b""),
+        ];
+        
+        let duplicates = find_duplicates(&files, false).await;
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates.get(&2), Some(&0));
+        
+        let duplicates = find_duplicates(&files, true).await;
+        assert_eq!(duplicates.get(&2), Some(&0));
+        assert!(!duplicates.contains_key(&1));
+    }
+    
+    #[tokio::test]
+    async fn test_connecting_event_before_connection_error() {
+        // Свободный порт, на котором никто не слушает
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        
+        let result = send_files_to_target_with_options(
+            0,
+            format!("127.0.0.1:{}", port),
+            Vec::new(),
+            SendOptions::default(),
+            tx,
+        ).await;
+        
+        assert!(result.is_err());
+        assert!(matches!(rx.recv().await, Some(TransferEvent::Connecting(0))));
+        assert!(rx.try_recv().is_err());
+    }
+    
+    #[test]
+    fn test_retry_backoff_doubles_up_to_limit() {
+        let delay = Duration::from_secs(1);
+        assert_eq!(retry_backoff(delay, 1), Duration::from_secs(1));
+        assert_eq!(retry_backoff(delay, 3), Duration::from_secs(4));
+        assert_eq!(retry_backoff(delay, 40), MAX_RETRY_DELAY);
+        assert_eq!(retry_backoff(Duration::from_secs(90), 2), Duration::from_secs(90));
+        assert!(SendOptions::builder().connect_retries(3).retry_delay(Duration::ZERO).build().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_connect_retries_until_receiver_starts() {
+        let dst = tempfile::tempdir().unwrap();
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let (server_tx, _server_rx) = mpsc::unbounded_channel();
+        let stop_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        
+        // Получатель поднимается уже после первой неудачной попытки
+        let server = tokio::spawn({
+            let (dir, stop_flag) = (dst.path().to_path_buf(), stop_flag.clone());
+            async move {
+                tokio::time::sleep(Duration::from_millis(150)).await;
+                super::super::run_server_with_stop(port, dir, false, server_tx, stop_flag).await
+            }
+        });
+        
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let options = SendOptions {
+            connect_retries: 5,
+            retry_delay: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let result = send_files_to_target_with_options(0, format!("127.0.0.1:{}", port), Vec::new(), options, tx).await;
+        assert!(result.is_ok(), "{:?}", result);
+        
+        let mut retries = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let TransferEvent::ConnectionRetrying(0, attempt) = event {
+                retries.push(attempt);
+            }
+        }
+        assert!(!retries.is_empty());
+        assert_eq!(retries, (1..=retries.len() as u32).collect::<Vec<_>>());
+        
+        stop_flag.store(true, std::sync::atomic::Ordering::SeqCst);
+        let _ = server.await;
+    }
+    
+    #[tokio::test]
+    async fn test_connect_retry_backoff_stops_on_flag() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let stop_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let options = SendOptions {
+            connect_retries: 10,
+            retry_delay: Duration::from_secs(30),
+            ..Default::default()
+        };
+        
+        tokio::spawn({
+            let stop_flag = stop_flag.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                stop_flag.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+        });
+        
+        // Долгая пауза перед повтором прерывается остановкой
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let started = Instant::now();
+        let result = send_files_to_target_with_stop(0, format!("127.0.0.1:{}", port), Vec::new(), options, tx, stop_flag).await;
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+    
+    #[tokio::test]
+    async fn test_max_parallel_targets_queues_the_rest() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let targets = vec![format!("127.0.0.1:{}", port); 3];
+        let options = SendOptions { max_parallel_targets: 1, ..Default::default() };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_multiple_with_stop(targets, Vec::new(), options, tx, Default::default()).await;
+        
+        // Одновременно подключается только один получатель, двое ждут в очереди
+        let (mut active, mut queued, mut started) = (0, 0, 0);
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                TransferEvent::TargetQueued(_) => queued += 1,
+                TransferEvent::Connecting(_) => {
+                    active += 1;
+                    started += 1;
+                    assert_eq!(active, 1);
+                }
+                TransferEvent::ConnectionError(..) => active -= 1,
+                _ => {}
+            }
+        }
+        assert_eq!((queued, started), (2, 3));
+        assert!(SendOptions::builder().max_parallel_targets(0).build().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_continue_on_error_skips_unreadable_file() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let mut files = vec![
+            file_info(src.path(), "a.txt", b"first"),
+            file_info(src.path(), "b.txt", b"missing"),
+            file_info(src.path(), "c.txt", b"third"),
+        ];
+        std::fs::remove_file(&files[1].path).unwrap();
+        files[1].size = 0;
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let options = SendOptions { continue_on_error: true, ..Default::default() };
+        let result = send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await;
+        assert!(result.is_ok(), "{:?}", result);
+        
+        let mut failed = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let TransferEvent::FileError(_, idx, _) = event {
+                failed.push(idx);
+            }
+        }
+        assert_eq!(failed, vec![1]);
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(std::fs::read(dst.path().join("c.txt")).unwrap(), b"third");
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_cancel_target_keeps_others_running() {
+        let src = tempfile::tempdir().unwrap();
+        let content = vec![7u8; 2 * 1024 * 1024];
+        let files = vec![file_info(src.path(), "data.bin", &content)];
+        
+        let mut targets = Vec::new();
+        let mut servers = Vec::new();
+        let mut dirs = Vec::new();
+        for _ in 0..2 {
+            let dst = tempfile::tempdir().unwrap();
+            let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+            targets.push(server.addr.clone());
+            servers.push(server);
+            dirs.push(dst);
+        }
+        
+        // ~2 секунды на получателя: есть время отменить второго посреди файла
+        let options = SendOptions { max_bytes_per_sec: Some(1024 * 1024), ..Default::default() };
+        let cancels = TargetCancels::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let transfer = tokio::spawn(send_files_to_multiple_with_targets(
+            targets, files, options, tx, Default::default(), cancels.clone(),
+        ));
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        cancels.cancel_target(1);
+        transfer.await.unwrap();
+        
+        let (mut completed, mut failed) = (Vec::new(), Vec::new());
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                TransferEvent::TargetCompleted(id) => completed.push(id),
+                TransferEvent::ConnectionError(id, _) => failed.push(id),
+                _ => {}
+            }
+        }
+        assert_eq!(completed, vec![0]);
+        assert_eq!(failed, vec![1]);
+        assert_eq!(std::fs::read(dirs[0].path().join("data.bin")).unwrap(), content);
+        assert!(!dirs[1].path().join("data.bin").exists());
+        
+        for server in servers {
+            server.stop().await;
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_transport_fallback_resumes_after_failure() {
+        use tokio::io::AsyncWriteExt;
+        use tokio::net::{TcpListener, TcpStream};
+        
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let content: Vec<u8> = (0..4 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+        let files = vec![file_info(src.path(), "big.bin", &content)];
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        let server_port = server.port;
+        
+        // Прокси между отправителем и получателем: первое соединение рвётся
+        // после 1 МБ - так выглядит отказ транспорта посреди файла
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut first = true;
+            loop {
+                let (mut client, _) = proxy.accept().await.unwrap();
+                let mut upstream = TcpStream::connect(("127.0.0.1", server_port)).await.unwrap();
+                if first {
+                    first = false;
+                    let (mut client_rd, mut client_wr) = client.split();
+                    let (mut up_rd, mut up_wr) = upstream.split();
+                    let forward = async {
+                        let mut buf = vec![0u8; 64 * 1024];
+                        let mut total = 0;
+                        while total < 1024 * 1024 {
+                            let n = client_rd.read(&mut buf).await.unwrap_or(0);
+                            if n == 0 {
+                                break;
+                            }
+                            up_wr.write_all(&buf[..n]).await.unwrap();
+                            total += n;
+                        }
+                    };
+                    tokio::select! {
+                        _ = forward => {}
+                        _ = tokio::io::copy(&mut up_rd, &mut client_wr) => {}
+                    }
+                    // Даём старому обработчику получателя дописать .toolza-part
+                    drop(client);
+                    drop(upstream);
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                } else {
+                    tokio::spawn(async move {
+                        let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+                    });
+                }
+            }
+        });
+        
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let options = SendOptions {
+            fixed_chunk_size: Some(64 * 1024),
+            transport_fallback: vec![TransportType::Tcp],
+            ..Default::default()
+        };
+        let result = send_files_to_target_with_options(0, proxy_addr, files, options, tx).await;
+        assert!(result.is_ok(), "{:?}", result);
+        
+        let mut fell_back = false;
+        let mut resumed_at = 0;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                TransferEvent::TransportFallback(_, transport_type, _) => {
+                    assert_eq!(transport_type, TransportType::Tcp);
+                    fell_back = true;
+                }
+                TransferEvent::FileResumed(_, _, offset) => resumed_at = offset,
+                _ => {}
+            }
+        }
+        assert!(fell_back);
+        assert!(resumed_at > 0, "передача должна продолжиться с места обрыва");
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(std::fs::read(dst.path().join("big.bin")).unwrap(), content);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_skip_reasons() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let file = file_info(src.path(), "a.txt", b"content");
+        // Один и тот же файл дважды в списке
+        let files = vec![file.clone(), file];
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let addr = server.addr.clone();
+        let skipped = |rx: &mut mpsc::UnboundedReceiver<TransferEvent>| {
+            let mut reasons = Vec::new();
+            while let Ok(event) = rx.try_recv() {
+                if let TransferEvent::FileSkipped(_, idx, reason) = event {
+                    reasons.push((idx, reason));
+                }
+            }
+            reasons
+        };
+        
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr.clone(), files.clone(), SendOptions::default(), tx).await.unwrap();
+        assert_eq!(skipped(&mut rx), vec![(1, SkipReason::Duplicate)]);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        // Повторная отправка: файл у получателя уже есть
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files[..1].to_vec(), SendOptions::default(), tx).await.unwrap();
+        assert_eq!(skipped(&mut rx), vec![(0, SkipReason::UpToDate)]);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_remember_progress_skips_completed() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let store = tempfile::tempdir().unwrap();
+        let files = vec![file_info(src.path(), "a.txt", b"first"), file_info(src.path(), "b.txt", b"second")];
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        let addr = server.addr.clone();
+        
+        // Прошлая отправка успела передать первый файл
+        let mut map = CompletionMap::load(Some(store.path()), &addr, &files);
+        map.mark(0);
+        drop(map);
+        
+        let options = SendOptions::builder().remember_progress(true).progress_dir(store.path()).build().unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, options, tx).await.unwrap();
+        
+        let mut skipped = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let TransferEvent::FileSkipped(_, idx, reason) = event {
+                skipped.push((idx, reason));
+            }
+        }
+        // Первый файл получателю даже не предлагался
+        assert_eq!(skipped, vec![(0, SkipReason::AlreadyComplete)]);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!dst.path().join("a.txt").exists());
+        assert_eq!(std::fs::read(dst.path().join("b.txt")).unwrap(), b"second");
+        // Передано всё - карта удалена
+        assert_eq!(std::fs::read_dir(store.path()).unwrap().count(), 0);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_max_bytes_per_sec_throttles() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let content: Vec<u8> = (0..5 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let files = vec![file_info(src.path(), "big.bin", &content)];
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        // 5 MB при 1 MB/s: запас всего 100 мс, остальное - по пределу
+        let options = SendOptions::builder().max_bytes_per_sec(1024 * 1024).build().unwrap();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let start = Instant::now();
+        send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(4), "{:?}", start.elapsed());
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(std::fs::read(dst.path().join("big.bin")).unwrap(), content);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_probe_resume_and_fresh_send() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let content = vec![7u8; 64 * 1024];
+        let files = vec![
+            file_info(src.path(), "done.bin", &content),
+            file_info(src.path(), "partial.bin", &content),
+            file_info(src.path(), "new.bin", &content[..100]),
+        ];
+        std::fs::write(dst.path().join("done.bin"), &content).unwrap();
+        std::fs::write(dst.path().join("partial.bin.toolza-part"), &content[..1000]).unwrap();
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let addr = server.addr.clone();
+        let offsets = probe_resume(&addr, &files, &SendOptions::default()).await.unwrap();
+        assert_eq!(offsets, vec![content.len() as u64, 1000, 0]);
+        
+        // Путь за пределы папки приёма не проверяется, даже если ведёт к готовому файлу
+        let mut escaping = files[0].clone();
+        escaping.relative_path = format!("../{}/done.bin", dst.path().file_name().unwrap().to_string_lossy());
+        let offsets = probe_resume(&addr, &[escaping], &SendOptions::default()).await.unwrap();
+        assert_eq!(offsets, vec![0]);
+        
+        // Заново: ничего не пропускается и не докачивается
+        let options = SendOptions { enable_resume: false, dedupe: false, ..Default::default() };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, options, tx).await.unwrap();
+        while let Ok(event) = rx.try_recv() {
+            assert!(!matches!(event, TransferEvent::FileSkipped(..) | TransferEvent::FileResumed(..)), "{:?}", event);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(std::fs::read(dst.path().join("partial.bin")).unwrap(), content);
+        
+        server.stop().await;
+    }
+    
+    /// Запись упирается в место: `.toolza-part` - ссылка на /dev/full (ENOSPC на запись)
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_disk_full_cancels_sender_and_keeps_part() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let files = vec![file_info(src.path(), "big.bin", &vec![1u8; 4 * 1024 * 1024])];
+        let part = dst.path().join("big.bin.toolza-part");
+        std::os::unix::fs::symlink("/dev/full", &part).unwrap();
+        
+        let (server, mut server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let result = tokio::time::timeout(
+            Duration::from_secs(10),
+            send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx),
+        ).await.expect("отправитель не остановился");
+        assert!(result.unwrap_err().contains("отменил"));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        let mut disk_full = None;
+        while let Ok(event) = server_rx.try_recv() {
+            if let TransferEvent::DiskFull(name, _) = event {
+                disk_full = Some(name);
+            }
+        }
+        assert_eq!(disk_full.as_deref(), Some("big.bin"));
+        // Частичный файл не удалён и не переименован
+        assert!(std::fs::symlink_metadata(&part).is_ok());
+        assert!(!dst.path().join("big.bin").exists());
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_checksum_matches_received_copy() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let files = vec![
+            file_info(src.path(), "a.txt", b"first"),
+            file_info(src.path(), "b.txt", b"second"),
+            file_info(src.path(), "c.txt", b"first"), // Уйдёт как FileLink
+        ];
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let options = SendOptions { checksum: true, ..Default::default() };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files.clone(), options, tx).await.unwrap();
+        let mut checksum = None;
+        while let Ok(event) = rx.try_recv() {
+            if let TransferEvent::TargetChecksum(0, value) = event {
+                checksum = Some(value);
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        // Копия у получателя, в другом порядке, даёт ту же сумму
+        let received: Vec<FileInfo> = ["c.txt", "b.txt", "a.txt"].iter()
+            .map(|name| FileInfo::new(dst.path().join(name)).unwrap())
+            .collect();
+        assert_eq!(checksum, Some(transfer_checksum(&received).await.unwrap()));
+        assert_eq!(checksum, Some(transfer_checksum(&files).await.unwrap()));
+        
+        std::fs::write(dst.path().join("b.txt"), b"changed").unwrap();
+        assert_ne!(checksum, Some(transfer_checksum(&received).await.unwrap()));
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_file_link_uses_receiver_names() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let files = vec![
+            file_info(src.path(), "a.txt", b"same"),
+            file_info(src.path(), "b.txt", b"same"), // Уйдёт как FileLink
+            file_info(src.path(), "c.txt", b"other"),
+            file_info(src.path(), "c.bin", b"other"), // Копия под запрещённым именем
+        ];
+        
+        let options = super::super::ServerOptions {
+            name_template: Some("in_{name}".to_string()),
+            blocked_extensions: vec!["bin".to_string()],
+            ..Default::default()
+        };
+        let (server, mut server_rx) = spawn_test_server(dst.path(), options).await;
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let _ = send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        let mut linked = Vec::new();
+        while let Ok(event) = server_rx.try_recv() {
+            if let TransferEvent::FileReceived(name, _) = event {
+                if name.contains("(копия") {
+                    linked.push(name);
+                }
+            }
+        }
+        assert_eq!(linked, vec!["in_b.txt (копия in_a.txt)"]);
+        assert_eq!(std::fs::read(dst.path().join("in_b.txt")).unwrap(), b"same");
+        assert!(!dst.path().join("b.txt").exists());
+        assert!(!dst.path().join("in_c.bin").exists());
+        assert!(!dst.path().join("c.bin").exists());
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_no_extract_keeps_archive_packed() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(5);
+        builder.append_data(&mut header, "inner.txt", &b"hello"[..]).unwrap();
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        std::io::Write::write_all(&mut encoder, &builder.into_inner().unwrap()).unwrap();
+        let archive = encoder.finish().unwrap();
+        let files = vec![file_info(src.path(), "backup.tar.lz4", &archive)];
+        
+        let server_options = super::super::ServerOptions {
+            extract_options: super::super::ExtractOptions { tar_lz4: true, ..Default::default() },
+            ..Default::default()
+        };
+        let (server, _server_rx) = spawn_test_server(dst.path(), server_options).await;
+        
+        // Получатель распаковывает tar.lz4, но отправитель просит оставить архив
+        let options = SendOptions { no_extract: true, ..Default::default() };
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        assert_eq!(std::fs::read(dst.path().join("backup.tar.lz4")).unwrap(), archive);
+        assert!(!dst.path().join("inner.txt").exists());
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_no_extract_per_file() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        
+        let archive = |name: &str| {
+            let mut builder = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(5);
+            builder.append_data(&mut header, name, &b"hello"[..]).unwrap();
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+            std::io::Write::write_all(&mut encoder, &builder.into_inner().unwrap()).unwrap();
+            encoder.finish().unwrap()
+        };
+        let backup = archive("backup.txt");
+        let mut kept = file_info(src.path(), "backup.tar.lz4", &backup);
+        kept.no_extract = true;
+        let files = vec![kept, file_info(src.path(), "photos.tar.lz4", &archive("photo.txt"))];
+        
+        let server_options = super::super::ServerOptions {
+            extract_options: super::super::ExtractOptions { tar_lz4: true, ..Default::default() },
+            ..Default::default()
+        };
+        let (server, _server_rx) = spawn_test_server(dst.path(), server_options).await;
+        
+        // Одна сессия: первый архив остаётся упакованным, второй распаковывается
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        assert_eq!(std::fs::read(dst.path().join("backup.tar.lz4")).unwrap(), backup);
+        assert!(!dst.path().join("backup.txt").exists());
+        assert_eq!(std::fs::read(dst.path().join("photo.txt")).unwrap(), b"hello");
+        assert!(!dst.path().join("photos.tar.lz4").exists());
+        
+        server.stop().await;
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_receiver_sanitizes_names() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let files = vec![
+            file_info(src.path(), "report:v2.txt", b"v2"),
+            file_info(src.path(), "aux", b"device"),
+            file_info(src.path(), "plain.txt", b"plain"),
+        ];
+        
+        let server_options = super::super::ServerOptions::builder().sanitize_names(true).build().unwrap();
+        let (server, mut server_rx) = spawn_test_server(dst.path(), server_options).await;
+        
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        assert_eq!(std::fs::read(dst.path().join("report_v2.txt")).unwrap(), b"v2");
+        assert_eq!(std::fs::read(dst.path().join("aux_")).unwrap(), b"device");
+        assert_eq!(std::fs::read(dst.path().join("plain.txt")).unwrap(), b"plain");
+        
+        let mut renamed = Vec::new();
+        while let Ok(event) = server_rx.try_recv() {
+            if let TransferEvent::FileRenamed(name, saved_as) = event {
+                renamed.push((name, saved_as));
+            }
+        }
+        assert_eq!(renamed, [
+            ("report:v2.txt".to_string(), "report_v2.txt".to_string()),
+            ("aux".to_string(), "aux_".to_string()),
+        ]);
+        
+        server.stop().await;
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_preserve_xattrs() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let tagged = file_info(src.path(), "tagged.txt", b"tagged");
+        if xattr::set(&tagged.path, "user.toolza.origin", b"lab").is_err() {
+            return; // ФС без user xattr
+        }
+        
+        // Архив с xattr в PAX-заголовке, как от `tar --xattrs`
+        let record = |key: &str, value: &str| {
+            let base = key.len() + value.len() + 3;
+            let mut len = base + base.to_string().len();
+            len = base + len.to_string().len();
+            format!("{} {}={}\n", len, key, value)
+        };
+        let pax = record("SCHILY.xattr.user.toolza.origin", "archive");
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(tar::EntryType::XHeader);
+        header.set_size(pax.len() as u64);
+        builder.append_data(&mut header, "PaxHeaders/inner.txt", pax.as_bytes()).unwrap();
+        let mut header = tar::Header::new_ustar();
+        header.set_mode(0o644);
+        header.set_size(5);
+        builder.append_data(&mut header, "inner.txt", &b"hello"[..]).unwrap();
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        std::io::Write::write_all(&mut encoder, &builder.into_inner().unwrap()).unwrap();
+        let files = vec![tagged, file_info(src.path(), "backup.tar.lz4", &encoder.finish().unwrap())];
+        
+        let server_options = super::super::ServerOptions::builder()
+            .auto_extract_tar_lz4(true)
+            .accept_xattrs(true)
+            .build()
+            .unwrap();
+        let (server, _server_rx) = spawn_test_server(dst.path(), server_options).await;
+        
+        let options = SendOptions::builder().preserve_xattrs(true).build().unwrap();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        let origin = |name: &str| xattr::get(dst.path().join(name), "user.toolza.origin").unwrap();
+        assert_eq!(origin("tagged.txt").as_deref(), Some(&b"lab"[..]));
+        assert_eq!(origin("inner.txt").as_deref(), Some(&b"archive"[..]));
+        
+        server.stop().await;
+    }
+    
+    /// Получатель без `accept_xattrs` не подтверждает FEATURE_FILE_META:
+    /// файл принимается, атрибуты не пишутся
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_xattrs_need_receiver_consent() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let tagged = file_info(src.path(), "tagged.txt", b"tagged");
+        if xattr::set(&tagged.path, "user.toolza.origin", b"lab").is_err() {
+            return; // ФС без user xattr
+        }
+        let (server, _server_rx) = spawn_test_server(dst.path(), Default::default()).await;
+        
+        let options = SendOptions::builder().preserve_xattrs(true).build().unwrap();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), vec![tagged], options, tx).await.unwrap();
+        
+        let saved = dst.path().join("tagged.txt");
+        assert_eq!(std::fs::read(&saved).unwrap(), b"tagged");
+        assert_eq!(xattr::get(&saved, "user.toolza.origin").unwrap(), None);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_extract_dir_separates_archives() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let unpacked = dst.path().join("unpacked");
+        
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(5);
+        builder.append_data(&mut header, "inner.txt", &b"hello"[..]).unwrap();
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        std::io::Write::write_all(&mut encoder, &builder.into_inner().unwrap()).unwrap();
+        let files = vec![
+            file_info(src.path(), "backup.tar.lz4", &encoder.finish().unwrap()),
+            file_info(src.path(), "notes.txt", b"plain"),
+        ];
+        
+        let server_options = super::super::ServerOptions::builder()
+            .auto_extract_tar_lz4(true)
+            .extract_dir(&unpacked)
+            .build()
+            .unwrap();
+        let (server, _server_rx) = spawn_test_server(dst.path(), server_options).await;
+        
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        // Архив распакован в свою папку, обычный файл - в папку сохранения
+        assert_eq!(std::fs::read(unpacked.join("inner.txt")).unwrap(), b"hello");
+        assert_eq!(std::fs::read(dst.path().join("notes.txt")).unwrap(), b"plain");
+        assert!(!dst.path().join("inner.txt").exists());
+        assert!(!unpacked.join("notes.txt").exists());
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_single_zst_streamed_to_plain_file() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let content = b"single compressed payload ".repeat(4096);
+        let packed = zstd::encode_all(&content[..], 3).unwrap();
+        let files = vec![file_info(src.path(), "dump.sql.zst", &packed)];
+        
+        let server_options = super::super::ServerOptions {
+            extract_options: super::super::ExtractOptions { zst: true, ..Default::default() },
+            ..Default::default()
+        };
+        let (server, _server_rx) = spawn_test_server(dst.path(), server_options).await;
+        
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        // Распакован на лету: только файл без расширения, сжатая копия не пишется
+        assert_eq!(std::fs::read(dst.path().join("dump.sql")).unwrap(), content);
+        assert_eq!(std::fs::read_dir(dst.path()).unwrap().count(), 1);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_chunk_probe_before_first_file() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let content: Vec<u8> = (0..PROBE_MIN_TOTAL_SIZE as usize).map(|i| (i % 251) as u8).collect();
+        let files = vec![file_info(src.path(), "big.bin", &content)];
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        let addr = server.addr.clone();
+        
+        // Проба выбирает один из пробуемых размеров, получатель на неё не отвечает
+        let mut stream = super::super::transport::connect(TransportType::Tcp, &addr).await.unwrap();
+        let size = probe_chunk_size(&mut *stream).await.unwrap();
+        assert!(PROBE_CHUNK_SIZES.contains(&size));
+        drop(stream);
+        
+        // После пробы файл передаётся как обычно
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, SendOptions::default(), tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(std::fs::read(dst.path().join("big.bin")).unwrap(), content);
+        
+        server.stop().await;
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_non_utf8_filename_keeps_raw_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        std::fs::write(src.path().join(name), b"latin-1").unwrap();
+        let files = vec![FileInfo::new(src.path().join(name)).unwrap()];
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        // Файл лежит под исходным именем, а не под lossy "caf\u{FFFD}.txt"
+        assert_eq!(std::fs::read(dst.path().join(name)).unwrap(), b"latin-1");
+        assert_eq!(std::fs::read_dir(dst.path()).unwrap().count(), 1);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_empty_files_end_to_end() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let files = vec![
+            file_info(src.path(), "empty.txt", b""),
+            file_info(src.path(), "empty2.txt", b""),
+            file_info(src.path(), "after.txt", b"after"),
+        ];
+        // У получателя лежит старая непустая версия - её должен заменить пустой файл
+        std::fs::write(dst.path().join("empty2.txt"), b"old").unwrap();
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let addr = server.addr.clone();
+        let outcomes = |rx: &mut mpsc::UnboundedReceiver<TransferEvent>| {
+            let mut completed = Vec::new();
+            let mut skipped = Vec::new();
+            while let Ok(event) = rx.try_recv() {
+                match event {
+                    TransferEvent::FileCompleted(_, idx) => completed.push(idx),
+                    TransferEvent::FileSkipped(_, idx, _) => skipped.push(idx),
+                    TransferEvent::FileResumed(_, idx, _) => panic!("пустой файл {} не должен резюмироваться", idx),
+                    _ => {}
+                }
+            }
+            (completed, skipped)
+        };
+        
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr.clone(), files.clone(), SendOptions::default(), tx).await.unwrap();
+        assert_eq!(outcomes(&mut rx), (vec![0, 1, 2], vec![]));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        for name in ["empty.txt", "empty2.txt"] {
+            let meta = std::fs::metadata(dst.path().join(name)).unwrap();
+            assert!(meta.is_file());
+            assert_eq!(meta.len(), 0);
+        }
+        assert_eq!(std::fs::read(dst.path().join("after.txt")).unwrap(), b"after");
+        
+        // Повторная отправка: пустые файлы уже есть у получателя
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, SendOptions::default(), tx).await.unwrap();
+        assert_eq!(outcomes(&mut rx), (vec![], vec![0, 1, 2]));
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_auto_compression_per_file() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let text = b"2024-01-01 INFO request handled\n".repeat(4096);
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let random: Vec<u8> = (0..128 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let files = vec![
+            file_info(src.path(), "server.log", &text),
+            file_info(src.path(), "noise.bin", &random),
+        ];
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let options = SendOptions { compression: CompressionMode::Auto, ..Default::default() };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        // Последний Progress по каждому файлу: (исходный размер, переданный)
+        let mut totals = HashMap::new();
+        while let Ok(event) = rx.try_recv() {
+            if let TransferEvent::Progress(_, idx, _, original, compressed) = event {
+                totals.insert(idx, (original, compressed));
+            }
+        }
+        let (log_original, log_sent) = totals[&0];
+        assert!(log_sent < log_original / 2, "текстовый лог должен сжиматься");
+        let (noise_original, noise_sent) = totals[&1];
+        assert_eq!(noise_sent, noise_original, "случайные данные не должны сжиматься");
+        
+        assert_eq!(std::fs::read(dst.path().join("server.log")).unwrap(), text);
+        assert_eq!(std::fs::read(dst.path().join("noise.bin")).unwrap(), random);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_zstd_codec_send() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let text = b"2024-01-01 INFO request handled\n".repeat(4096);
+        let files = vec![file_info(src.path(), "server.log", &text)];
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let options = SendOptions {
+            compression: CompressionMode::On,
+            codec: CompressionCodec::Zstd { level: 19 },
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        let mut sent = None;
+        while let Ok(event) = rx.try_recv() {
+            if let TransferEvent::Progress(_, 0, _, original, compressed) = event {
+                sent = Some((original, compressed));
+            }
+        }
+        let (original, compressed) = sent.unwrap();
+        assert!(compressed < original / 2);
+        assert_eq!(std::fs::read(dst.path().join("server.log")).unwrap(), text);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_compact_framing_send() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let mut files: Vec<FileInfo> = (0..50)
+            .map(|i| file_info(src.path(), &format!("tiny_{:02}.txt", i), format!("файл {}", i).as_bytes()))
+            .collect();
+        let big = vec![3u8; 300 * 1024];
+        files.push(file_info(src.path(), "big.bin", &big));
+        files.push(file_info(src.path(), "big_copy.bin", &big));
+        std::fs::write(dst.path().join("big.bin.toolza-part"), &big[..1000]).unwrap();
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let options = SendOptions { compact_framing: true, compression: CompressionMode::On, ..Default::default() };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+        let mut resumed = false;
+        while let Ok(event) = rx.try_recv() {
+            resumed |= matches!(event, TransferEvent::FileResumed(_, _, 1000));
+        }
+        assert!(resumed);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        assert_eq!(std::fs::read_to_string(dst.path().join("tiny_42.txt")).unwrap(), "файл 42");
+        assert_eq!(std::fs::read(dst.path().join("big.bin")).unwrap(), big);
+        assert_eq!(std::fs::read(dst.path().join("big_copy.bin")).unwrap(), big);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_connection_reused_across_batches() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let first = vec![file_info(src.path(), "first.txt", b"first batch")];
+        let second = vec![
+            file_info(src.path(), "second.txt", b"second batch"),
+            file_info(src.path(), "third.txt", b"third"),
+        ];
+        
+        let (server, mut server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let mut connection = Connection::connect(&server.addr, SendOptions::default()).await.unwrap();
+        connection.send_files(first).await.unwrap();
+        connection.send_files(second).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(std::fs::read_to_string(dst.path().join("first.txt")).unwrap(), "first batch");
+        assert_eq!(std::fs::read_to_string(dst.path().join("third.txt")).unwrap(), "third");
+        
+        // Между пакетами соединение не закрывалось
+        let mut disconnects = 0;
+        while let Ok(event) = server_rx.try_recv() {
+            disconnects += matches!(event, TransferEvent::ClientDisconnected(..)) as usize;
+        }
+        assert_eq!(disconnects, 0);
+        
+        connection.close().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let mut disconnects = 0;
+        while let Ok(event) = server_rx.try_recv() {
+            disconnects += matches!(event, TransferEvent::ClientDisconnected(..)) as usize;
+        }
+        assert_eq!(disconnects, 1);
+        
+        server.stop().await;
+    }
+    
+    #[tokio::test]
+    async fn test_send_files_iter_from_walk() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let folder = src.path().join("tree");
+        std::fs::create_dir_all(folder.join("nested")).unwrap();
+        for i in 0..20 {
+            std::fs::write(folder.join("nested").join(format!("{}.txt", i)), format!("файл {}", i)).unwrap();
+        }
+        std::fs::write(folder.join("top.txt"), "top").unwrap();
+        
+        let (server, _server_rx) = spawn_test_server(dst.path(), super::super::ServerOptions::default()).await;
+        
+        let mut connection = Connection::connect(&server.addr, SendOptions::default()).await.unwrap();
+        let count = connection.send_files_iter(crate::protocol::walk_folder(&folder)).await.unwrap();
+        assert_eq!(count, 21);
+        connection.close().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        assert_eq!(std::fs::read_to_string(dst.path().join("tree/top.txt")).unwrap(), "top");
+        assert_eq!(std::fs::read_to_string(dst.path().join("tree/nested/7.txt")).unwrap(), "файл 7");
+        
+        server.stop().await;
+    }
+    
+    /// Последний номер варианта первой версии протокола (`SpeedTestResult`)
+    const BASELINE_LAST_TAG: u32 = 14;
+    
+    /// Получатель первой версии протокола: неизвестный вариант - ошибка разбора
+    /// и закрытое соединение. Возвращает номера вариантов, принятых до `Done`
+    fn spawn_baseline_receiver(listener: tokio::net::TcpListener) -> tokio::task::JoinHandle<Vec<u32>> {
+        use tokio::io::AsyncWriteExt;
+        
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut tags = Vec::new();
+                loop {
+                    let mut len_buf = [0u8; 4];
+                    if stream.read_exact(&mut len_buf).await.is_err() {
+                        break;
+                    }
+                    let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+                    stream.read_exact(&mut data).await.unwrap();
+                    let tag = u32::from_le_bytes(data[..4].try_into().unwrap());
+                    if tag > BASELINE_LAST_TAG {
+                        break;
+                    }
+                    tags.push(tag);
+                    match Message::from_bytes(&data).unwrap() {
+                        Message::FileStart { .. } | Message::FileEnd => {
+                            stream.write_all(&Message::Ack.to_bytes().unwrap()).await.unwrap();
+                        }
+                        Message::Done => return tags,
+                        _ => {}
+                    }
+                }
+            }
+        })
+    }
+    
+    /// Получатель без `FEATURE_FILE_CODEC` не получает FileCodec: вместо zstd - LZ4
+    #[tokio::test]
+    async fn test_zstd_not_sent_to_baseline_receiver() {
+        let src = tempfile::tempdir().unwrap();
+        let files = vec![file_info(src.path(), "a.txt", &b"zstd please ".repeat(1000))];
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let receiver = spawn_baseline_receiver(listener);
+        
+        let options = SendOptions {
+            compression: CompressionMode::On,
+            codec: CompressionCodec::Zstd { level: 3 },
+            ..Default::default()
+        };
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, options, tx).await.unwrap();
+        
+        // FileStart, FileChunk, FileEnd, Done - ничего сверх первой версии
+        assert_eq!(receiver.await.unwrap(), vec![0, 1, 2, 6]);
+    }
+    
+    #[tokio::test]
+    async fn test_chunk_probe_not_sent_to_baseline_receiver() {
+        let src = tempfile::tempdir().unwrap();
+        let files = vec![file_info(src.path(), "big.bin", &vec![7u8; PROBE_MIN_TOTAL_SIZE as usize])];
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let receiver = spawn_baseline_receiver(listener);
+        
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, SendOptions::default(), tx).await.unwrap();
+        
+        // Без пробы: сразу FileStart, куски и FileEnd
+        let tags = receiver.await.unwrap();
+        assert_eq!(tags.first(), Some(&0));
+        assert_eq!(&tags[tags.len() - 2..], &[2, 6]);
+    }
+    
+    /// Получатель без `FEATURE_PACKED_LISTS` (закрывает соединение на Hello)
+    /// получает большой список несжатым
+    #[tokio::test]
+    async fn test_packed_lists_need_hello() {
+        use tokio::io::AsyncWriteExt;
+        
+        let src = tempfile::tempdir().unwrap();
+        let file = file_info(src.path(), "a.bin", b"a");
+        let files: Vec<FileInfo> = (0..2000)
+            .map(|i| FileInfo { relative_path: format!("some/rather/long/folder/name/file_{:05}.bin", i), ..file.clone() })
+            .collect();
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let old_receiver = tokio::spawn(async move {
+            let (first, _) = listener.accept().await.unwrap();
+            drop(first);
+            let (mut second, _) = listener.accept().await.unwrap();
+            let mut len_buf = [0u8; 4];
+            second.read_exact(&mut len_buf).await.unwrap();
+            let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+            second.read_exact(&mut data).await.unwrap();
+            assert!(!Message::is_packed(&data));
+            let Message::ResumeProbe { files } = Message::from_bytes(&data).unwrap() else {
+                panic!("ожидался ResumeProbe");
+            };
+            let reply = Message::ResumeProbeResult { offsets: vec![0; files.len()] };
+            second.write_all(&reply.to_bytes().unwrap()).await.unwrap();
+        });
+        
+        let offsets = probe_resume(&addr, &files, &SendOptions::default()).await.unwrap();
+        assert_eq!(offsets.len(), files.len());
+        old_receiver.await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_file_meta_not_sent_to_baseline_receiver() {
+        let src = tempfile::tempdir().unwrap();
+        let files = vec![file_info(src.path(), "a.txt", b"attributes")];
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let receiver = spawn_baseline_receiver(listener);
+        
+        let options = SendOptions::builder().preserve_xattrs(true).build().unwrap();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, options, tx).await.unwrap();
+        
+        assert_eq!(receiver.await.unwrap(), vec![0, 1, 2, 6]);
+    }
+    
+    /// Получатель без `FEATURE_FILE_LINK` не получает FileLink: дубликат уходит целиком
+    #[tokio::test]
+    async fn test_file_link_not_sent_to_baseline_receiver() {
+        let src = tempfile::tempdir().unwrap();
+        let files = vec![
+            file_info(src.path(), "a.txt", b"same content"),
+            file_info(src.path(), "b.txt", b"same content"),
+        ];
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let receiver = spawn_baseline_receiver(listener);
+        
+        let (tx, _rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, SendOptions::default(), tx).await.unwrap();
+        
+        assert_eq!(receiver.await.unwrap(), vec![0, 1, 2, 0, 1, 2, 6]);
+    }
+    
+    /// Получатель без FEATURE_VERIFY_INTEGRITY: FileHash не отправляется, файл не проверен
+    #[tokio::test]
+    async fn test_file_hash_not_sent_to_baseline_receiver() {
+        let src = tempfile::tempdir().unwrap();
+        let files = vec![file_info(src.path(), "a.txt", b"content")];
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let receiver = spawn_baseline_receiver(listener);
+        
+        let options = SendOptions { verify_integrity: true, ..Default::default() };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, options, tx).await.unwrap();
+        
+        assert_eq!(receiver.await.unwrap(), vec![0, 1, 2, 6]);
+        let mut warned = false;
+        while let Ok(event) = rx.try_recv() {
+            warned |= matches!(event, TransferEvent::FileWarning(0, 0, FileWarning::NotVerified));
+        }
+        assert!(warned);
+    }
+    
+    /// Архив, распакованный на лету, получатель не сверяет - и говорит об этом
+    #[tokio::test]
+    async fn test_streamed_archive_reported_not_verified() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let content = b"verified payload ".repeat(4096);
+        let packed = zstd::encode_all(&content[..], 3).unwrap();
+        let files = vec![
+            file_info(src.path(), "dump.sql.zst", &packed),
+            file_info(src.path(), "plain.txt", b"plain"),
+        ];
+        
+        let server_options = super::super::ServerOptions {
+            extract_options: super::super::ExtractOptions { zst: true, ..Default::default() },
+            verify_integrity: true,
+            ..Default::default()
+        };
+        let (server, _server_rx) = spawn_test_server(dst.path(), server_options).await;
+        
+        let options = SendOptions { verify_integrity: true, ..Default::default() };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        let mut warned = Vec::new();
+        let mut completed = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                TransferEvent::FileWarning(0, idx, FileWarning::NotVerified) => warned.push(idx),
+                TransferEvent::FileCompleted(0, idx) => completed.push(idx),
+                _ => {}
+            }
+        }
+        assert_eq!(warned, vec![0]);
+        assert_eq!(completed, vec![0, 1]);
+        assert_eq!(std::fs::read(dst.path().join("dump.sql")).unwrap(), content);
+        assert_eq!(std::fs::read(dst.path().join("plain.txt")).unwrap(), b"plain");
+        
+        server.stop().await;
+    }
+    
+    /// Получатель без FEATURE_RAW_FILE_NAME: только lossy-имя в FileStart и предупреждение
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_raw_file_name_not_sent_to_baseline_receiver() {
+        use std::os::unix::ffi::OsStrExt;
+        
+        let src = tempfile::tempdir().unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        std::fs::write(src.path().join(name), b"latin-1").unwrap();
+        let files = vec![FileInfo::new(src.path().join(name)).unwrap()];
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let receiver = spawn_baseline_receiver(listener);
+        
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        send_files_to_target_with_options(0, addr, files, SendOptions::default(), tx).await.unwrap();
+        
+        assert_eq!(receiver.await.unwrap(), vec![0, 1, 2, 6]);
+        let mut warned = false;
+        while let Ok(event) = rx.try_recv() {
+            warned |= matches!(event, TransferEvent::FileWarning(0, 0, FileWarning::LossyName));
+        }
+        assert!(warned);
+    }
+    
+    /// Старый получатель не знает Hello и закрывает соединение - переподключаемся без него
+    #[tokio::test]
+    async fn test_compact_framing_falls_back_on_old_receiver() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let old_receiver = tokio::spawn(async move {
+            let (mut first, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 64];
+            let _ = tokio::io::AsyncReadExt::read(&mut first, &mut buf).await;
+            drop(first);
+            
+            let (mut second, _) = listener.accept().await.unwrap();
+            let mut len_buf = [0u8; 4];
+            second.read_exact(&mut len_buf).await.unwrap();
+            let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+            second.read_exact(&mut data).await.unwrap();
+            Message::from_bytes(&data).unwrap()
+        });
+        
+        let options = SendOptions { compact_framing: true, ..Default::default() };
+        let (mut stream, features) = connect_negotiated(TransportType::Tcp, &addr, &options, options.wanted_features()).await.unwrap();
+        assert_eq!(features, 0);
+        let data = Message::Done.to_bytes().unwrap();
+        stream.write_all(&data).await.unwrap();
+        
+        assert!(matches!(old_receiver.await.unwrap(), Message::Done));
     }
 }
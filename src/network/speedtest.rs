@@ -1,10 +1,13 @@
 //! Модуль спидтеста для измерения скорости между клиентами
 
-use std::time::Instant;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
 use tokio::sync::mpsc;
+use tokio::time::timeout;
 
+use crate::network::transport::{SocketTuning, TcpTransport};
 use crate::network::TransferEvent;
 use crate::protocol::{Message, DEFAULT_PORT};
 
@@ -14,6 +17,48 @@ pub const DEFAULT_SPEEDTEST_SIZE: u64 = 10 * 1024 * 1024;
 /// Размер чанка для спидтеста (64 KB)
 const SPEEDTEST_CHUNK_SIZE: usize = 64 * 1024;
 
+/// Допустимое отклонение объёма upload от запрошенного размера
+const SPEEDTEST_SIZE_TOLERANCE: u64 = SPEEDTEST_CHUNK_SIZE as u64;
+
+/// Сколько сервер ждёт очередной кадр upload, прежде чем считать клиента зависшим
+const SPEEDTEST_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Начальное состояние генератора данных спидтеста (одинаковое у клиента и сервера)
+const SPEEDTEST_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Детерминированный генератор данных спидтеста (xorshift64).
+///
+/// Данные несжимаемые, поэтому сжатие на пути не завышает скорость
+struct PayloadGenerator {
+    state: u64,
+}
+
+impl PayloadGenerator {
+    fn new(seed: u64) -> Self {
+        // Нулевое состояние xorshift выдаёт одни нули
+        Self { state: seed.max(1) }
+    }
+    
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+    
+    /// Следующие `len` байт последовательности
+    fn next_chunk(&mut self, len: usize) -> Vec<u8> {
+        let mut chunk = Vec::with_capacity(len + 8);
+        while chunk.len() < len {
+            chunk.extend_from_slice(&self.next_u64().to_le_bytes());
+        }
+        chunk.truncate(len);
+        chunk
+    }
+}
+
 /// Результат спидтеста
 #[derive(Debug, Clone)]
 pub struct SpeedTestResult {
@@ -41,20 +86,41 @@ pub async fn run_speedtest(
     size: u64,
     event_tx: mpsc::UnboundedSender<TransferEvent>,
 ) -> Result<SpeedTestResult, String> {
-    let target = if addr.contains(':') {
-        addr.to_string()
-    } else {
-        format!("{}:{}", addr, DEFAULT_PORT)
-    };
+    run_speedtest_tuned(addr, size, SocketTuning::default(), event_tx).await
+}
+
+/// Спидтест с настройками TCP-сокета - чтобы сравнить их влияние на скорость
+pub async fn run_speedtest_tuned(
+    addr: &str,
+    size: u64,
+    tuning: SocketTuning,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+) -> Result<SpeedTestResult, String> {
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    run_speedtest_with_stop(addr, size, tuning, event_tx, stop_flag).await
+}
+
+/// Спидтест с поддержкой остановки.
+///
+/// При установке `stop_flag` серверу уходит `Cancel`, обе стороны штатно
+/// завершают тест (соединение сервера не зависает), приходит `SpeedTestCancelled`
+pub async fn run_speedtest_with_stop(
+    addr: &str,
+    size: u64,
+    tuning: SocketTuning,
+    event_tx: mpsc::UnboundedSender<TransferEvent>,
+    stop_flag: Arc<AtomicBool>,
+) -> Result<SpeedTestResult, String> {
+    let target = super::parse_target_addr(addr, DEFAULT_PORT)?;
 
     let _ = event_tx.send(TransferEvent::SpeedTestStarted(target.clone()));
 
     // Подключаемся
-    let stream = TcpStream::connect(&target)
+    let stream = TcpTransport::with_tuning(tuning)
+        .connect(&target)
         .await
-        .map_err(|e| format!("Ошибка подключения: {}", e))?;
-    
-    stream.set_nodelay(true).ok();
+        .map_err(|e| format!("Ошибка подключения: {}", e))?
+        .into_inner();
     let (mut reader, mut writer) = stream.into_split();
 
     // Измеряем latency (ping)
@@ -66,31 +132,30 @@ pub async fn run_speedtest(
     writer.write_all(&request_bytes).await.map_err(|e| e.to_string())?;
 
     // Ждём подтверждения готовности
-    let mut len_buf = [0u8; 4];
-    reader.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
-    let len = u32::from_le_bytes(len_buf) as usize;
-    let mut data = vec![0u8; len];
-    reader.read_exact(&mut data).await.map_err(|e| e.to_string())?;
-    
-    match Message::from_bytes(&data) {
-        Ok(Message::SpeedTestReady) => {}
-        Ok(Message::Error(e)) => return Err(e),
+    match read_message(&mut reader).await? {
+        Message::SpeedTestReady => {}
+        Message::Error(e) => return Err(e),
         _ => return Err("Неожиданный ответ сервера".to_string()),
     }
 
     // === Upload test ===
     let _ = event_tx.send(TransferEvent::SpeedTestProgress("upload".to_string(), 0));
-    let upload_speed = test_upload(&mut writer, size, &event_tx).await?;
+    let Some(upload_speed) = test_upload(&mut writer, size, &event_tx, &stop_flag).await? else {
+        return Err(cancelled(&event_tx));
+    };
 
     // Ждём подтверждения
-    reader.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
-    let len = u32::from_le_bytes(len_buf) as usize;
-    let mut data = vec![0u8; len];
-    reader.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    match read_message(&mut reader).await? {
+        Message::Cancel => return Err("Спидтест прерван сервером".to_string()),
+        Message::Error(e) => return Err(e),
+        _ => {}
+    }
     
     // === Download test ===
     let _ = event_tx.send(TransferEvent::SpeedTestProgress("download".to_string(), 0));
-    let download_speed = test_download(&mut reader, size, &event_tx).await?;
+    let Some(download_speed) = test_download(&mut reader, &mut writer, size, &event_tx, &stop_flag).await? else {
+        return Err(cancelled(&event_tx));
+    };
 
     // Отправляем Ack
     let ack = Message::Ack.to_bytes().map_err(|e| e.to_string())?;
@@ -111,6 +176,40 @@ pub async fn run_speedtest(
     Ok(result)
 }
 
+/// Сообщить об отмене спидтеста
+fn cancelled(event_tx: &mpsc::UnboundedSender<TransferEvent>) -> String {
+    let _ = event_tx.send(TransferEvent::SpeedTestCancelled);
+    "Спидтест отменён".to_string()
+}
+
+/// Прочитать одно сообщение протокола
+async fn read_message(reader: &mut tokio::net::tcp::OwnedReadHalf) -> Result<Message, String> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
+    let len = u32::from_le_bytes(len_buf) as usize;
+    let mut data = vec![0u8; len];
+    reader.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    Message::from_bytes(&data).map_err(|e| e.to_string())
+}
+
+/// Отправить Cancel и дождаться, пока сервер его подтвердит
+/// (данные, отправленные до этого, пропускаются)
+async fn cancel_download(
+    reader: &mut tokio::net::tcp::OwnedReadHalf,
+    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+) -> Result<(), String> {
+    let cancel = Message::Cancel.to_bytes().map_err(|e| e.to_string())?;
+    writer.write_all(&cancel).await.map_err(|e| e.to_string())?;
+    writer.flush().await.map_err(|e| e.to_string())?;
+    
+    loop {
+        match read_message(reader).await? {
+            Message::SpeedTestData { .. } => {}
+            _ => return Ok(()),
+        }
+    }
+}
+
 /// Измерить latency (ping)
 async fn measure_latency(
     reader: &mut tokio::net::tcp::OwnedReadHalf,
@@ -127,11 +226,7 @@ async fn measure_latency(
         writer.write_all(&ping).await.map_err(|e| e.to_string())?;
         
         // Ждём ответ
-        let mut len_buf = [0u8; 4];
-        reader.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
-        let len = u32::from_le_bytes(len_buf) as usize;
-        let mut data = vec![0u8; len];
-        reader.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+        read_message(reader).await?;
         
         total_latency += start.elapsed().as_secs_f64() * 1000.0;
     }
@@ -139,23 +234,32 @@ async fn measure_latency(
     Ok(total_latency / PING_COUNT as f64)
 }
 
-/// Тест upload скорости
+/// Тест upload скорости (None - отменён)
 async fn test_upload(
     writer: &mut tokio::net::tcp::OwnedWriteHalf,
     size: u64,
     event_tx: &mpsc::UnboundedSender<TransferEvent>,
-) -> Result<f64, String> {
-    let chunk = vec![0xABu8; SPEEDTEST_CHUNK_SIZE];
+    stop_flag: &AtomicBool,
+) -> Result<Option<f64>, String> {
+    let mut payload = PayloadGenerator::new(SPEEDTEST_SEED);
     let mut sent = 0u64;
     let start = Instant::now();
     let mut last_update = Instant::now();
 
     while sent < size {
+        if stop_flag.load(Ordering::SeqCst) {
+            // Сервер получит Cancel вместо SpeedTestEnd и вернётся к приёму команд
+            let cancel = Message::Cancel.to_bytes().map_err(|e| e.to_string())?;
+            writer.write_all(&cancel).await.map_err(|e| e.to_string())?;
+            writer.flush().await.map_err(|e| e.to_string())?;
+            return Ok(None);
+        }
+        
         let remaining = (size - sent) as usize;
         let to_send = remaining.min(SPEEDTEST_CHUNK_SIZE);
         
         let msg = Message::SpeedTestData {
-            data: chunk[..to_send].to_vec(),
+            data: payload.next_chunk(to_send),
         };
         let msg_bytes = msg.to_bytes().map_err(|e| e.to_string())?;
         writer.write_all(&msg_bytes).await.map_err(|e| e.to_string())?;
@@ -181,28 +285,29 @@ async fn test_upload(
     let elapsed = start.elapsed().as_secs_f64();
     let speed_mbps = (size as f64 / 1024.0 / 1024.0) / elapsed;
 
-    Ok(speed_mbps)
+    Ok(Some(speed_mbps))
 }
 
-/// Тест download скорости
+/// Тест download скорости (None - отменён)
 async fn test_download(
     reader: &mut tokio::net::tcp::OwnedReadHalf,
+    writer: &mut tokio::net::tcp::OwnedWriteHalf,
     size: u64,
     event_tx: &mpsc::UnboundedSender<TransferEvent>,
-) -> Result<f64, String> {
+    stop_flag: &AtomicBool,
+) -> Result<Option<f64>, String> {
     let mut received = 0u64;
     let start = Instant::now();
     let mut last_update = Instant::now();
 
     loop {
-        let mut len_buf = [0u8; 4];
-        reader.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
-        let len = u32::from_le_bytes(len_buf) as usize;
-        let mut data = vec![0u8; len];
-        reader.read_exact(&mut data).await.map_err(|e| e.to_string())?;
-
-        match Message::from_bytes(&data) {
-            Ok(Message::SpeedTestData { data: chunk }) => {
+        if stop_flag.load(Ordering::SeqCst) {
+            cancel_download(reader, writer).await?;
+            return Ok(None);
+        }
+        
+        match read_message(reader).await? {
+            Message::SpeedTestData { data: chunk } => {
                 received += chunk.len() as u64;
                 // Обновляем прогресс раз в секунду
                 if last_update.elapsed().as_secs() >= 1 {
@@ -211,8 +316,9 @@ async fn test_download(
                     last_update = Instant::now();
                 }
             }
-            Ok(Message::SpeedTestEnd) => break,
-            Ok(Message::Error(e)) => return Err(e),
+            Message::SpeedTestEnd => break,
+            Message::Cancel => return Err("Спидтест прерван сервером".to_string()),
+            Message::Error(e) => return Err(e),
             _ => return Err("Неожиданное сообщение".to_string()),
         }
     }
@@ -223,34 +329,31 @@ async fn test_download(
     let elapsed = start.elapsed().as_secs_f64();
     let speed_mbps = (received as f64 / 1024.0 / 1024.0) / elapsed;
 
-    Ok(speed_mbps)
+    Ok(Some(speed_mbps))
 }
 
-/// Обработать запрос спидтеста на стороне сервера
+/// Обработать запрос спидтеста на стороне сервера.
+///
+/// Cancel от клиента (или `stop_flag` сервера) завершает тест без ошибки,
+/// после этого соединение снова принимает команды
 pub async fn handle_speedtest_server(
     reader: &mut tokio::net::tcp::OwnedReadHalf,
     writer: &mut tokio::net::tcp::OwnedWriteHalf,
     size: u64,
+    stop_flag: &AtomicBool,
 ) -> Result<(), String> {
     // Отправляем готовность
     let ready = Message::SpeedTestReady.to_bytes().map_err(|e| e.to_string())?;
     writer.write_all(&ready).await.map_err(|e| e.to_string())?;
 
     // === Принимаем upload ===
-    let mut _received = 0u64;
-    loop {
-        let mut len_buf = [0u8; 4];
-        reader.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
-        let len = u32::from_le_bytes(len_buf) as usize;
-        let mut data = vec![0u8; len];
-        reader.read_exact(&mut data).await.map_err(|e| e.to_string())?;
-
-        match Message::from_bytes(&data) {
-            Ok(Message::SpeedTestData { data: chunk }) => {
-                _received += chunk.len() as u64;
-            }
-            Ok(Message::SpeedTestEnd) => break,
-            _ => return Err("Неожиданное сообщение в upload".to_string()),
+    match receive_upload(reader, size, SPEEDTEST_READ_TIMEOUT, stop_flag).await {
+        Ok(UploadOutcome::Done) => {}
+        Ok(UploadOutcome::Cancelled) => return Ok(()),
+        Ok(UploadOutcome::Stopped) => return send_server_cancel(writer).await,
+        Err(e) => {
+            send_server_error(writer, &e).await;
+            return Err(e);
         }
     }
 
@@ -259,15 +362,34 @@ pub async fn handle_speedtest_server(
     writer.write_all(&ack).await.map_err(|e| e.to_string())?;
 
     // === Отправляем download ===
-    let chunk = vec![0xCDu8; SPEEDTEST_CHUNK_SIZE];
+    let mut payload = PayloadGenerator::new(SPEEDTEST_SEED);
     let mut sent = 0u64;
 
     while sent < size {
+        if stop_flag.load(Ordering::SeqCst) {
+            return send_server_cancel(writer).await;
+        }
+        
+        // Клиент мог прислать Cancel посреди download: проверяем без ожидания
+        let mut len_buf = [0u8; 4];
+        if let Ok(read) = timeout(Duration::ZERO, reader.read(&mut len_buf)).await {
+            let read = read.map_err(|e| e.to_string())?;
+            if read == 0 {
+                return Ok(()); // Клиент отключился
+            }
+            reader.read_exact(&mut len_buf[read..]).await.map_err(|e| e.to_string())?;
+            let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+            reader.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+            if let Ok(Message::Cancel) = Message::from_bytes(&data) {
+                return send_server_cancel(writer).await;
+            }
+        }
+        
         let remaining = (size - sent) as usize;
         let to_send = remaining.min(SPEEDTEST_CHUNK_SIZE);
         
         let msg = Message::SpeedTestData {
-            data: chunk[..to_send].to_vec(),
+            data: payload.next_chunk(to_send),
         };
         let msg_bytes = msg.to_bytes().map_err(|e| e.to_string())?;
         writer.write_all(&msg_bytes).await.map_err(|e| e.to_string())?;
@@ -280,37 +402,122 @@ pub async fn handle_speedtest_server(
     writer.write_all(&end).await.map_err(|e| e.to_string())?;
     writer.flush().await.map_err(|e| e.to_string())?;
 
-    // Ждём Ack
-    let mut len_buf = [0u8; 4];
-    reader.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
-    let len = u32::from_le_bytes(len_buf) as usize;
-    let mut data = vec![0u8; len];
-    reader.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    // Ждём Ack (или Cancel, если клиент отменил в самом конце)
+    read_message(reader).await?;
 
     Ok(())
 }
 
+/// Чем закончился приём upload на сервере
+#[derive(Debug, PartialEq, Eq)]
+enum UploadOutcome {
+    /// Получен SpeedTestEnd, объём в пределах допуска
+    Done,
+    /// Клиент отменил тест
+    Cancelled,
+    /// Сервер остановлен (`stop_flag`)
+    Stopped,
+}
+
+/// Учёт объёма upload относительно согласованного `size`
+struct UploadLimit {
+    size: u64,
+    received: u64,
+}
+
+impl UploadLimit {
+    fn new(size: u64) -> Self {
+        Self { size, received: 0 }
+    }
+    
+    /// Учесть очередной кадр данных (ошибка, если клиент прислал больше, чем договаривались)
+    fn add(&mut self, len: usize) -> Result<(), String> {
+        self.received = self.received.saturating_add(len as u64);
+        if self.received > self.size.saturating_add(SPEEDTEST_SIZE_TOLERANCE) {
+            return Err(format!(
+                "Клиент прислал больше данных, чем запрошено: {} из {} байт",
+                self.received, self.size
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Проверить объём при SpeedTestEnd (ошибка, если данных меньше, чем договаривались)
+    fn finish(&self) -> Result<(), String> {
+        if self.received < self.size.saturating_sub(SPEEDTEST_SIZE_TOLERANCE) {
+            return Err(format!(
+                "Upload завершён раньше времени: получено {} из {} байт",
+                self.received, self.size
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Ошибка ожидания кадра upload
+fn upload_timeout_error(read_timeout: Duration) -> String {
+    format!("Клиент не присылает данные спидтеста дольше {} с", read_timeout.as_secs_f32())
+}
+
+/// Принять upload (TCP): не больше `size` с допуском, каждый кадр не дольше `read_timeout`
+async fn receive_upload(
+    reader: &mut tokio::net::tcp::OwnedReadHalf,
+    size: u64,
+    read_timeout: Duration,
+    stop_flag: &AtomicBool,
+) -> Result<UploadOutcome, String> {
+    let mut limit = UploadLimit::new(size);
+    loop {
+        let message = timeout(read_timeout, read_message(reader)).await
+            .map_err(|_| upload_timeout_error(read_timeout))??;
+        match message {
+            Message::SpeedTestData { data } => limit.add(data.len())?,
+            Message::SpeedTestEnd => {
+                limit.finish()?;
+                return Ok(UploadOutcome::Done);
+            }
+            Message::Cancel => return Ok(UploadOutcome::Cancelled),
+            _ => return Err("Неожиданное сообщение в upload".to_string()),
+        }
+        if stop_flag.load(Ordering::SeqCst) {
+            return Ok(UploadOutcome::Stopped);
+        }
+    }
+}
+
+/// Сообщить клиенту об ошибке спидтеста (TCP, без гарантии доставки)
+async fn send_server_error(writer: &mut tokio::net::tcp::OwnedWriteHalf, error: &str) {
+    if let Ok(data) = Message::Error(error.to_string()).to_bytes() {
+        let _ = writer.write_all(&data).await;
+        let _ = writer.flush().await;
+    }
+}
+
+/// Сообщить клиенту об остановке теста (TCP)
+async fn send_server_cancel(writer: &mut tokio::net::tcp::OwnedWriteHalf) -> Result<(), String> {
+    let cancel = Message::Cancel.to_bytes().map_err(|e| e.to_string())?;
+    writer.write_all(&cancel).await.map_err(|e| e.to_string())?;
+    writer.flush().await.map_err(|e| e.to_string())
+}
+
 /// Обработать запрос спидтеста через абстрактный транспорт
 pub async fn handle_speedtest_server_transport(
     stream: &mut dyn super::transport::TransportStream,
     size: u64,
+    stop_flag: &AtomicBool,
 ) -> Result<(), String> {
     // Отправляем готовность
     let ready = Message::SpeedTestReady.to_bytes().map_err(|e| e.to_string())?;
     stream.write_all(&ready).await.map_err(|e| e.to_string())?;
 
     // === Принимаем upload ===
-    loop {
-        let mut len_buf = [0u8; 4];
-        stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
-        let len = u32::from_le_bytes(len_buf) as usize;
-        let mut data = vec![0u8; len];
-        stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
-
-        match Message::from_bytes(&data) {
-            Ok(Message::SpeedTestData { data: _ }) => {}
-            Ok(Message::SpeedTestEnd) => break,
-            _ => return Err("Неожиданное сообщение в upload".to_string()),
+    match receive_upload_transport(stream, size, SPEEDTEST_READ_TIMEOUT, stop_flag).await {
+        Ok(UploadOutcome::Done) => {}
+        Ok(UploadOutcome::Cancelled) => return Ok(()),
+        Ok(UploadOutcome::Stopped) => return send_server_cancel_transport(stream).await,
+        Err(e) => {
+            send_server_error_transport(stream, &e).await;
+            return Err(e);
         }
     }
 
@@ -319,15 +526,34 @@ pub async fn handle_speedtest_server_transport(
     stream.write_all(&ack).await.map_err(|e| e.to_string())?;
 
     // === Отправляем download ===
-    let chunk = vec![0xCDu8; SPEEDTEST_CHUNK_SIZE];
+    let mut payload = PayloadGenerator::new(SPEEDTEST_SEED);
     let mut sent = 0u64;
 
     while sent < size {
+        if stop_flag.load(Ordering::SeqCst) {
+            return send_server_cancel_transport(stream).await;
+        }
+        
+        // Клиент мог прислать Cancel посреди download: проверяем без ожидания
+        let mut len_buf = [0u8; 4];
+        if let Ok(read) = timeout(Duration::ZERO, stream.read(&mut len_buf)).await {
+            let read = read.map_err(|e| e.to_string())?;
+            if read == 0 {
+                return Ok(()); // Клиент отключился
+            }
+            stream.read_exact(&mut len_buf[read..]).await.map_err(|e| e.to_string())?;
+            let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+            stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+            if let Ok(Message::Cancel) = Message::from_bytes(&data) {
+                return send_server_cancel_transport(stream).await;
+            }
+        }
+        
         let remaining = (size - sent) as usize;
         let to_send = remaining.min(SPEEDTEST_CHUNK_SIZE);
         
         let msg = Message::SpeedTestData {
-            data: chunk[..to_send].to_vec(),
+            data: payload.next_chunk(to_send),
         };
         let msg_bytes = msg.to_bytes().map_err(|e| e.to_string())?;
         stream.write_all(&msg_bytes).await.map_err(|e| e.to_string())?;
@@ -340,14 +566,61 @@ pub async fn handle_speedtest_server_transport(
     stream.write_all(&end).await.map_err(|e| e.to_string())?;
     stream.flush().await.map_err(|e| e.to_string())?;
 
-    // Ждём Ack
+    // Ждём Ack (или Cancel, если клиент отменил в самом конце)
+    read_message_transport(stream).await?;
+
+    Ok(())
+}
+
+/// Прочитать одно сообщение протокола из транспорта
+async fn read_message_transport(stream: &mut dyn super::transport::TransportStream) -> Result<Message, String> {
     let mut len_buf = [0u8; 4];
     stream.read_exact(&mut len_buf).await.map_err(|e| e.to_string())?;
     let len = u32::from_le_bytes(len_buf) as usize;
     let mut data = vec![0u8; len];
     stream.read_exact(&mut data).await.map_err(|e| e.to_string())?;
+    Message::from_bytes(&data).map_err(|e| e.to_string())
+}
 
-    Ok(())
+/// Принять upload через транспорт - как `receive_upload`
+async fn receive_upload_transport(
+    stream: &mut dyn super::transport::TransportStream,
+    size: u64,
+    read_timeout: Duration,
+    stop_flag: &AtomicBool,
+) -> Result<UploadOutcome, String> {
+    let mut limit = UploadLimit::new(size);
+    loop {
+        let message = timeout(read_timeout, read_message_transport(stream)).await
+            .map_err(|_| upload_timeout_error(read_timeout))??;
+        match message {
+            Message::SpeedTestData { data } => limit.add(data.len())?,
+            Message::SpeedTestEnd => {
+                limit.finish()?;
+                return Ok(UploadOutcome::Done);
+            }
+            Message::Cancel => return Ok(UploadOutcome::Cancelled),
+            _ => return Err("Неожиданное сообщение в upload".to_string()),
+        }
+        if stop_flag.load(Ordering::SeqCst) {
+            return Ok(UploadOutcome::Stopped);
+        }
+    }
+}
+
+/// Сообщить клиенту об ошибке спидтеста (транспорт, без гарантии доставки)
+async fn send_server_error_transport(stream: &mut dyn super::transport::TransportStream, error: &str) {
+    if let Ok(data) = Message::Error(error.to_string()).to_bytes() {
+        let _ = stream.write_all(&data).await;
+        let _ = stream.flush().await;
+    }
+}
+
+/// Сообщить клиенту об остановке теста (транспорт)
+async fn send_server_cancel_transport(stream: &mut dyn super::transport::TransportStream) -> Result<(), String> {
+    let cancel = Message::Cancel.to_bytes().map_err(|e| e.to_string())?;
+    stream.write_all(&cancel).await.map_err(|e| e.to_string())?;
+    stream.flush().await.map_err(|e| e.to_string())
 }
 
 #[cfg(test)]
@@ -373,5 +646,177 @@ mod tests {
         assert_eq!(DEFAULT_SPEEDTEST_SIZE, 10 * 1024 * 1024);
         assert_eq!(SPEEDTEST_CHUNK_SIZE, 64 * 1024);
     }
+
+    #[tokio::test]
+    async fn test_server_stops_download_on_client_cancel() {
+        use crate::network::transport::TcpStreamWrapper;
+        const CANCEL_TEST_SIZE: u64 = 32 * 1024 * 1024;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = TcpStreamWrapper::new(stream);
+            // Большой download: без Cancel сервер отправлял бы его заметно дольше
+            handle_speedtest_server_transport(&mut stream, CANCEL_TEST_SIZE, &AtomicBool::new(false)).await
+        });
+        
+        let (mut reader, mut writer) = tokio::net::TcpStream::connect(addr).await.unwrap().into_split();
+        assert!(matches!(read_message(&mut reader).await.unwrap(), Message::SpeedTestReady));
+        // Upload должен соответствовать запрошенному размеру
+        let chunk = Message::SpeedTestData { data: vec![0u8; SPEEDTEST_CHUNK_SIZE] }.to_bytes().unwrap();
+        for _ in 0..CANCEL_TEST_SIZE / SPEEDTEST_CHUNK_SIZE as u64 {
+            writer.write_all(&chunk).await.unwrap();
+        }
+        writer.write_all(&Message::SpeedTestEnd.to_bytes().unwrap()).await.unwrap();
+        assert!(matches!(read_message(&mut reader).await.unwrap(), Message::Ack));
+        assert!(matches!(read_message(&mut reader).await.unwrap(), Message::SpeedTestData { .. }));
+        
+        cancel_download(&mut reader, &mut writer).await.unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), server).await
+            .expect("сервер завис после Cancel")
+            .unwrap();
+        assert!(result.is_ok(), "{:?}", result);
+    }
+    
+    #[test]
+    fn test_payload_generator() {
+        let mut a = PayloadGenerator::new(SPEEDTEST_SEED);
+        let mut b = PayloadGenerator::new(SPEEDTEST_SEED);
+        let first = a.next_chunk(SPEEDTEST_CHUNK_SIZE);
+        assert_eq!(first, b.next_chunk(SPEEDTEST_CHUNK_SIZE));
+        assert_eq!(a.next_chunk(13).len(), 13);
+        // Чанки не повторяются и не состоят из одинаковых байт
+        assert_ne!(first, a.next_chunk(SPEEDTEST_CHUNK_SIZE));
+        assert!(first.iter().any(|&byte| byte != first[0]));
+        
+        // Несжимаемость: zlib не уменьшает данные заметно
+        use std::io::Write;
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&first).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert!(compressed.len() as f64 > first.len() as f64 * 0.99);
+    }
+    
+    #[test]
+    fn test_upload_limit() {
+        let mut limit = UploadLimit::new(4 * SPEEDTEST_CHUNK_SIZE as u64);
+        assert!(limit.finish().is_err());
+        for _ in 0..4 {
+            limit.add(SPEEDTEST_CHUNK_SIZE).unwrap();
+        }
+        assert!(limit.finish().is_ok());
+        // Допуск в один чанк
+        limit.add(SPEEDTEST_CHUNK_SIZE).unwrap();
+        assert!(limit.add(1).is_err());
+    }
+    
+    /// Сервер-транспорт и сырое TCP-соединение клиента к нему
+    async fn upload_pair(
+        size: u64,
+        read_timeout: Duration,
+    ) -> (
+        tokio::task::JoinHandle<Result<UploadOutcome, String>>,
+        tokio::net::tcp::OwnedReadHalf,
+        tokio::net::tcp::OwnedWriteHalf,
+    ) {
+        use crate::network::transport::TcpStreamWrapper;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = TcpStreamWrapper::new(stream);
+            receive_upload_transport(&mut stream, size, read_timeout, &AtomicBool::new(false)).await
+        });
+        let (reader, writer) = tokio::net::TcpStream::connect(addr).await.unwrap().into_split();
+        (server, reader, writer)
+    }
+    
+    #[tokio::test]
+    async fn test_upload_rejects_oversized_data() {
+        let (server, _reader, mut writer) = upload_pair(SPEEDTEST_CHUNK_SIZE as u64, Duration::from_secs(5)).await;
+        let chunk = Message::SpeedTestData { data: vec![0u8; SPEEDTEST_CHUNK_SIZE] }.to_bytes().unwrap();
+        // Клиент игнорирует размер и шлёт без конца
+        let flood = tokio::spawn(async move {
+            while writer.write_all(&chunk).await.is_ok() {}
+        });
+        
+        let result = tokio::time::timeout(Duration::from_secs(5), server).await
+            .expect("сервер принимает upload без ограничения")
+            .unwrap();
+        assert!(result.unwrap_err().contains("больше данных"));
+        flood.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_upload_rejects_short_data() {
+        let (server, _reader, mut writer) = upload_pair(4 * SPEEDTEST_CHUNK_SIZE as u64, Duration::from_secs(5)).await;
+        let chunk = Message::SpeedTestData { data: vec![0u8; SPEEDTEST_CHUNK_SIZE] }.to_bytes().unwrap();
+        writer.write_all(&chunk).await.unwrap();
+        writer.write_all(&Message::SpeedTestEnd.to_bytes().unwrap()).await.unwrap();
+        
+        let result = server.await.unwrap();
+        assert!(result.unwrap_err().contains("раньше времени"));
+    }
+    
+    #[tokio::test]
+    async fn test_upload_times_out_without_end() {
+        let (server, _reader, mut writer) = upload_pair(4 * SPEEDTEST_CHUNK_SIZE as u64, Duration::from_millis(200)).await;
+        let chunk = Message::SpeedTestData { data: vec![0u8; SPEEDTEST_CHUNK_SIZE] }.to_bytes().unwrap();
+        writer.write_all(&chunk).await.unwrap();
+        // Соединение остаётся открытым, но SpeedTestEnd так и не приходит
+        
+        let result = tokio::time::timeout(Duration::from_secs(5), server).await
+            .expect("сервер ждёт upload бесконечно")
+            .unwrap();
+        assert!(result.unwrap_err().contains("не присылает"));
+    }
+    
+    #[tokio::test]
+    async fn test_upload_accepts_exact_size() {
+        let (server, _reader, mut writer) = upload_pair(2 * SPEEDTEST_CHUNK_SIZE as u64 + 100, Duration::from_secs(5)).await;
+        for len in [SPEEDTEST_CHUNK_SIZE, SPEEDTEST_CHUNK_SIZE, 100] {
+            let chunk = Message::SpeedTestData { data: vec![0u8; len] }.to_bytes().unwrap();
+            writer.write_all(&chunk).await.unwrap();
+        }
+        writer.write_all(&Message::SpeedTestEnd.to_bytes().unwrap()).await.unwrap();
+        
+        assert_eq!(server.await.unwrap(), Ok(UploadOutcome::Done));
+    }
+    
+    #[tokio::test]
+    async fn test_cancelled_speedtest_leaves_server_usable() {
+        use crate::network::receiver::spawn_test_server;
+        use crate::network::ServerOptions;
+        
+        let dir = tempfile::tempdir().unwrap();
+        let (server, _server_rx) = spawn_test_server(dir.path(), ServerOptions::default()).await;
+        
+        let target = server.addr.clone();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let flag = stop_flag.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            flag.store(true, Ordering::SeqCst);
+        });
+        // Такой объём за 200 мс не передать - тест будет отменён посреди upload
+        let result = run_speedtest_with_stop(&target, 1 << 40, SocketTuning::default(), tx, stop_flag).await;
+        assert!(result.is_err());
+        
+        let mut cancelled = false;
+        while let Ok(event) = rx.try_recv() {
+            cancelled |= matches!(event, TransferEvent::SpeedTestCancelled);
+        }
+        assert!(cancelled);
+        
+        // Сервер не завис и принимает следующий тест
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let result = tokio::time::timeout(Duration::from_secs(10), run_speedtest(&target, 256 * 1024, tx)).await
+            .expect("повторный спидтест завис");
+        assert!(result.is_ok(), "{:?}", result);
+        server.stop().await;
+    }
 }
 
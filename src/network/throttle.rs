@@ -0,0 +1,87 @@
+//! Ограничение скорости отправки (`SendOptions::max_bytes_per_sec`)
+//!
+//! Token bucket: разрешение на байты копится со скоростью лимита, но не больше
+//! чем на `BURST` вперёд. Чанк, которому не хватило запаса, уходит в долг, а
+//! отправитель ждёт, пока долг не покроется, - так средняя скорость не выше
+//! лимита при любом размере чанка.
+
+use crate::stats::{MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+use std::time::{Duration, Instant};
+
+/// Сколько времени работы на полной скорости можно накопить в простое
+const BURST: Duration = Duration::from_millis(100);
+
+/// Ограничитель скорости одного соединения
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Байт в секунду
+    rate: f64,
+    /// Запас (отрицательный - долг), байт
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    /// Лимит `bytes_per_sec` байт в секунду; запас сначала полный
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let rate = bytes_per_sec.max(1) as f64;
+        Self { rate, tokens: rate * BURST.as_secs_f64(), last: Instant::now() }
+    }
+    
+    /// Размер чанка под лимит: столько, сколько копится за `BURST`.
+    /// Адаптация по скорости под лимитом не нужна - она видела бы скорость лимита
+    pub fn chunk_size(&self) -> usize {
+        ((self.rate * BURST.as_secs_f64()) as usize).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)
+    }
+    
+    /// Учесть отправку `bytes` и подождать, если запас исчерпан
+    pub async fn consume(&mut self, bytes: usize) {
+        let delay = self.take(bytes, Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    /// Списать `bytes` на момент `now`, вернуть, сколько ждать
+    fn take(&mut self, bytes: usize, now: Instant) -> Duration {
+        let burst = self.rate * BURST.as_secs_f64();
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(burst) - bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_rate_limiter_delay() {
+        let mut limiter = RateLimiter::new(1_000_000);
+        let start = limiter.last;
+        
+        // Запас 100 мс: первые 100 KB без ожидания
+        assert_eq!(limiter.take(100_000, start), Duration::ZERO);
+        // Дальше - ровно по лимиту
+        assert_eq!(limiter.take(500_000, start), Duration::from_millis(500));
+        // Через 500 мс долг покрыт, следующие 250 KB - 250 мс
+        assert_eq!(limiter.take(250_000, start + Duration::from_millis(500)), Duration::from_millis(250));
+        
+        // Долгий простой не копит больше 100 мс запаса
+        let later = start + Duration::from_secs(60);
+        assert_eq!(limiter.take(100_000, later), Duration::ZERO);
+        assert_eq!(limiter.take(100_000, later), Duration::from_millis(100));
+    }
+    
+    #[test]
+    fn test_rate_limiter_chunk_size() {
+        assert_eq!(RateLimiter::new(1_000_000).chunk_size(), 100_000);
+        assert_eq!(RateLimiter::new(1).chunk_size(), MIN_CHUNK_SIZE);
+        assert_eq!(RateLimiter::new(u64::MAX).chunk_size(), MAX_CHUNK_SIZE);
+    }
+}
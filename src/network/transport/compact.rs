@@ -0,0 +1,342 @@
+//! Компактная раскладка сообщений поверх любого транспорта
+//!
+//! Включается, если обе стороны согласились в `Message::Hello` (бит
+//! `FEATURE_COMPACT_FRAMING`). Остальной код по-прежнему пишет и читает
+//! стандартные кадры (длина u32 + bincode) - обёртка перекодирует их на лету.
+//!
+//! Кадр: длина varint (LEB128), затем тег и поля:
+//! - `FileChunk`: данные; размер до сжатия (varint) - только если отличается от длины данных
+//! - `FileStart`: длина общего с прошлым FileStart префикса имени и остаток имени,
+//!   размер и offset - varint, флаги одним байтом, quick_hash - 8 байт LE
+//! - `FileEnd`, `Ack`: только тег
+//! - остальные сообщения: bincode как есть
+
+use super::TransportStream;
+use crate::protocol::Message;
+use async_trait::async_trait;
+use std::io;
+use std::net::SocketAddr;
+
+const TAG_BINCODE: u8 = 0;
+const TAG_CHUNK: u8 = 1;
+const TAG_CHUNK_SIZED: u8 = 2;
+const TAG_FILE_END: u8 = 3;
+const TAG_ACK: u8 = 4;
+const TAG_FILE_START: u8 = 5;
+
+const FLAG_COMPRESSED: u8 = 1;
+
+fn invalid_data(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(input: &mut &[u8]) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input.split_first().ok_or_else(|| invalid_data("обрезанный varint"))?;
+        *input = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("слишком длинный varint"))
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+    if input.len() < len {
+        return Err(invalid_data("обрезанный кадр"));
+    }
+    let (head, rest) = input.split_at(len);
+    *input = rest;
+    Ok(head)
+}
+
+/// Кодек одного направления: помнит имя прошлого FileStart
+#[derive(Default)]
+pub struct CompactCodec {
+    last_filename: String,
+}
+
+impl CompactCodec {
+    /// Закодировать сообщение в компактный кадр (с длиной)
+    pub fn encode(&mut self, msg: &Message) -> io::Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        match msg {
+            Message::FileChunk { data, original_size } => {
+                if *original_size == data.len() {
+                    payload.push(TAG_CHUNK);
+                } else {
+                    payload.push(TAG_CHUNK_SIZED);
+                    write_varint(&mut payload, *original_size as u64);
+                }
+                payload.extend_from_slice(data);
+            }
+            Message::FileEnd => payload.push(TAG_FILE_END),
+            Message::Ack => payload.push(TAG_ACK),
+            Message::FileStart { filename, size, compressed, offset, quick_hash } => {
+                let mut prefix = filename.bytes()
+                    .zip(self.last_filename.bytes())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                while !filename.is_char_boundary(prefix) {
+                    prefix -= 1;
+                }
+                payload.push(TAG_FILE_START);
+                write_varint(&mut payload, prefix as u64);
+                write_varint(&mut payload, (filename.len() - prefix) as u64);
+                payload.extend_from_slice(&filename.as_bytes()[prefix..]);
+                write_varint(&mut payload, *size);
+                payload.push(if *compressed { FLAG_COMPRESSED } else { 0 });
+                write_varint(&mut payload, *offset);
+                payload.extend_from_slice(&quick_hash.to_le_bytes());
+                self.last_filename.clone_from(filename);
+            }
+            other => {
+                payload.push(TAG_BINCODE);
+                payload.extend(bincode::serialize(other).map_err(invalid_data)?);
+            }
+        }
+        
+        let mut frame = Vec::with_capacity(payload.len() + 5);
+        write_varint(&mut frame, payload.len() as u64);
+        frame.extend(payload);
+        Ok(frame)
+    }
+    
+    /// Разобрать содержимое компактного кадра (без длины)
+    pub fn decode(&mut self, payload: &[u8]) -> io::Result<Message> {
+        let (&tag, mut input) = payload.split_first().ok_or_else(|| invalid_data("пустой кадр"))?;
+        let msg = match tag {
+            TAG_CHUNK => Message::FileChunk { data: input.to_vec(), original_size: input.len() },
+            TAG_CHUNK_SIZED => {
+                let original_size = read_varint(&mut input)? as usize;
+                Message::FileChunk { data: input.to_vec(), original_size }
+            }
+            TAG_FILE_END => Message::FileEnd,
+            TAG_ACK => Message::Ack,
+            TAG_FILE_START => {
+                let prefix = read_varint(&mut input)? as usize;
+                let suffix_len = read_varint(&mut input)? as usize;
+                let suffix = std::str::from_utf8(take(&mut input, suffix_len)?).map_err(invalid_data)?;
+                let prefix = self.last_filename.get(..prefix)
+                    .ok_or_else(|| invalid_data("неверный префикс имени"))?;
+                let filename = format!("{}{}", prefix, suffix);
+                let size = read_varint(&mut input)?;
+                let flags = take(&mut input, 1)?[0];
+                let offset = read_varint(&mut input)?;
+                let quick_hash = u64::from_le_bytes(take(&mut input, 8)?.try_into().unwrap());
+                self.last_filename.clone_from(&filename);
+                Message::FileStart {
+                    filename,
+                    size,
+                    compressed: flags & FLAG_COMPRESSED != 0,
+                    offset,
+                    quick_hash,
+                }
+            }
+            TAG_BINCODE => Message::from_bytes(input).map_err(invalid_data)?,
+            _ => return Err(invalid_data(format!("неизвестный тег кадра {}", tag))),
+        };
+        Ok(msg)
+    }
+}
+
+/// Поток с компактной раскладкой: снаружи - стандартные кадры, на проводе - компактные
+pub struct CompactStream {
+    inner: Box<dyn TransportStream>,
+    encoder: CompactCodec,
+    decoder: CompactCodec,
+    /// Ещё не целый стандартный кадр от вызывающего кода
+    write_buf: Vec<u8>,
+    /// Принятый кадр в стандартном виде и позиция чтения в нём
+    read_buf: Vec<u8>,
+    read_pos: usize,
+}
+
+impl CompactStream {
+    pub fn new(inner: Box<dyn TransportStream>) -> Self {
+        Self {
+            inner,
+            encoder: CompactCodec::default(),
+            decoder: CompactCodec::default(),
+            write_buf: Vec::new(),
+            read_buf: Vec::new(),
+            read_pos: 0,
+        }
+    }
+    
+    /// Принять следующий компактный кадр. false - соединение закрыто между кадрами
+    async fn fill(&mut self) -> io::Result<bool> {
+        let mut byte = [0u8; 1];
+        if self.inner.read(&mut byte).await? == 0 {
+            return Ok(false);
+        }
+        let mut len = 0u64;
+        let mut shift = 0;
+        loop {
+            len |= ((byte[0] & 0x7f) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift > 28 {
+                return Err(invalid_data("слишком длинный кадр"));
+            }
+            self.inner.read_exact(&mut byte).await?;
+        }
+        if len > u32::MAX as u64 {
+            return Err(invalid_data("слишком длинный кадр"));
+        }
+        
+        let mut payload = vec![0u8; len as usize];
+        self.inner.read_exact(&mut payload).await?;
+        let msg = self.decoder.decode(&payload)?;
+        self.read_buf = msg.to_bytes().map_err(invalid_data)?;
+        self.read_pos = 0;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl TransportStream for CompactStream {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.read_pos == self.read_buf.len() && !self.fill().await? {
+            return Ok(0);
+        }
+        let n = buf.len().min(self.read_buf.len() - self.read_pos);
+        buf[..n].copy_from_slice(&self.read_buf[self.read_pos..self.read_pos + n]);
+        self.read_pos += n;
+        Ok(n)
+    }
+    
+    async fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let n = self.read(&mut buf[filled..]).await?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "соединение закрыто"));
+            }
+            filled += n;
+        }
+        Ok(())
+    }
+    
+    async fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.write_buf.extend_from_slice(buf);
+        while self.write_buf.len() >= 4 {
+            let len = u32::from_le_bytes(self.write_buf[..4].try_into().unwrap()) as usize;
+            if self.write_buf.len() < 4 + len {
+                break;
+            }
+            let msg = Message::from_bytes(&self.write_buf[4..4 + len]).map_err(invalid_data)?;
+            self.write_buf.drain(..4 + len);
+            let frame = self.encoder.encode(&msg)?;
+            self.inner.write_all(&frame).await?;
+        }
+        Ok(())
+    }
+    
+    async fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().await
+    }
+    
+    async fn shutdown(&mut self) -> io::Result<()> {
+        self.inner.shutdown().await
+    }
+    
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+    
+    fn transport_name(&self) -> &'static str {
+        self.inner.transport_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn roundtrip(messages: &[Message]) -> Vec<Message> {
+        let mut encoder = CompactCodec::default();
+        let mut decoder = CompactCodec::default();
+        messages.iter().map(|msg| {
+            let frame = encoder.encode(msg).unwrap();
+            let mut input = &frame[..];
+            let len = read_varint(&mut input).unwrap() as usize;
+            assert_eq!(len, input.len());
+            decoder.decode(input).unwrap()
+        }).collect()
+    }
+    
+    fn file_start(filename: &str) -> Message {
+        Message::FileStart {
+            filename: filename.to_string(),
+            size: 300,
+            compressed: true,
+            offset: 7,
+            quick_hash: 0x0102030405060708,
+        }
+    }
+    
+    #[test]
+    fn test_codec_roundtrip() {
+        let messages = vec![
+            file_start("docs/отчёт.txt"),
+            Message::FileChunk { data: vec![1, 2, 3], original_size: 3 },
+            Message::FileChunk { data: vec![4, 5], original_size: 1000 },
+            Message::FileEnd,
+            file_start("docs/отвод.txt"), // Общий префикс обрывается внутри "ч"/"в"
+            file_start("a.txt"),
+            Message::Ack,
+            Message::ResumeAck { offset: 42 },
+            Message::Done,
+        ];
+        let decoded = roundtrip(&messages);
+        for (msg, back) in messages.iter().zip(&decoded) {
+            assert_eq!(bincode::serialize(msg).unwrap(), bincode::serialize(back).unwrap());
+        }
+    }
+    
+    #[test]
+    fn test_tiny_files_overhead() {
+        // 1000 файлов по 100 байт: FileStart, FileChunk, FileEnd и Ack в ответ
+        let mut messages = Vec::new();
+        for i in 0..1000 {
+            messages.push(file_start(&format!("project/src/module_{:02}/file_{:04}.rs", i / 100, i)));
+            messages.push(Message::FileChunk { data: vec![0u8; 100], original_size: 100 });
+            messages.push(Message::FileEnd);
+            messages.push(Message::Ack);
+        }
+        let payload = 1000 * 100;
+        let standard: usize = messages.iter().map(|msg| msg.to_bytes().unwrap().len()).sum();
+        let mut codec = CompactCodec::default();
+        let compact: usize = messages.iter().map(|msg| codec.encode(msg).unwrap().len()).sum();
+        
+        // Служебные байты сверх данных - втрое меньше
+        assert!((compact - payload) * 3 < standard - payload, "standard {} compact {}", standard, compact);
+    }
+    
+    #[test]
+    fn test_rejects_bad_frames() {
+        let mut codec = CompactCodec::default();
+        assert!(codec.decode(&[]).is_err());
+        assert!(codec.decode(&[99]).is_err());
+        // Префикс длиннее прошлого имени
+        assert!(codec.decode(&[TAG_FILE_START, 5, 0]).is_err());
+        assert!(codec.decode(&[TAG_CHUNK_SIZED, 0x80]).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 //! KCP транспорт - быстрый надёжный UDP (требует feature "kcp")
 
-use super::{TransportListener, TransportStream};
+use super::{TransportListener, TransportStream, TransportType};
 use async_trait::async_trait;
 use std::io;
 use std::net::SocketAddr;
@@ -11,11 +11,13 @@ use tokio_kcp::{KcpConfig, KcpListener, KcpStream};
 /// KCP поток
 pub struct KcpStreamWrapper {
     stream: KcpStream,
+    peer_addr: SocketAddr,
+    local_addr: SocketAddr,
 }
 
 impl KcpStreamWrapper {
-    pub fn new(stream: KcpStream) -> Self {
-        Self { stream }
+    pub fn new(stream: KcpStream, peer_addr: SocketAddr, local_addr: SocketAddr) -> Self {
+        Self { stream, peer_addr, local_addr }
     }
 }
 
@@ -41,6 +43,18 @@ impl TransportStream for KcpStreamWrapper {
     async fn shutdown(&mut self) -> io::Result<()> {
         self.stream.shutdown().await
     }
+    
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.peer_addr)
+    }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+    
+    fn transport_name(&self) -> &'static str {
+        TransportType::Kcp.name()
+    }
 }
 
 /// KCP слушатель
@@ -53,18 +67,24 @@ impl TransportListener for KcpListenerWrapper {
     async fn accept(&mut self) -> io::Result<(Box<dyn TransportStream>, String)> {
         let (stream, addr) = self.listener.accept().await
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-        Ok((Box::new(KcpStreamWrapper::new(stream)), addr.to_string()))
+        let local_addr = self.listener.local_addr()?;
+        Ok((Box::new(KcpStreamWrapper::new(stream, addr, local_addr)), addr.to_string()))
     }
     
     async fn accept_timeout(&mut self, duration: Duration) -> io::Result<Option<(Box<dyn TransportStream>, String)>> {
         match timeout(duration, self.listener.accept()).await {
             Ok(Ok((stream, addr))) => {
-                Ok(Some((Box::new(KcpStreamWrapper::new(stream)), addr.to_string())))
+                let local_addr = self.listener.local_addr()?;
+                Ok(Some((Box::new(KcpStreamWrapper::new(stream, addr, local_addr)), addr.to_string())))
             }
             Ok(Err(e)) => Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
             Err(_) => Ok(None),
         }
     }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
 }
 
 /// KCP транспорт
@@ -88,13 +108,16 @@ impl KcpTransport {
         let socket_addr: SocketAddr = addr.parse()
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
         
-        let stream = KcpStream::connect(&self.config, socket_addr).await
+        // Свой сокет, чтобы знать локальный адрес соединения
+        let bind_addr: SocketAddr = if socket_addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().unwrap();
+        let socket = tokio::net::UdpSocket::bind(bind_addr).await?;
+        let local_addr = socket.local_addr()?;
+        let stream = KcpStream::connect_with_socket(&self.config, socket, socket_addr).await
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-        Ok(KcpStreamWrapper::new(stream))
+        Ok(KcpStreamWrapper::new(stream, socket_addr, local_addr))
     }
     
-    pub async fn bind(&self, port: u16) -> io::Result<KcpListenerWrapper> {
-        let addr: SocketAddr = format!("0.0.0.0:{}", port).parse().unwrap();
+    pub async fn bind(&self, addr: SocketAddr) -> io::Result<KcpListenerWrapper> {
         let listener = KcpListener::bind(self.config.clone(), addr).await
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
         Ok(KcpListenerWrapper { listener })
@@ -2,13 +2,17 @@
 
 mod tcp;
 mod udp;
+mod compact;
+mod timeout;
 #[cfg(feature = "quic")]
 mod quic;
 #[cfg(feature = "kcp")]
 mod kcp;
 
-pub use tcp::{TcpTransport, TcpStreamWrapper};
+pub use tcp::{SocketTuning, TcpTransport, TcpStreamWrapper};
 pub use udp::UdpTransport;
+pub use compact::{CompactCodec, CompactStream};
+pub use timeout::{with_io_timeout, TimeoutStream};
 #[cfg(feature = "quic")]
 pub use quic::QuicTransport;
 #[cfg(feature = "kcp")]
@@ -16,6 +20,15 @@ pub use kcp::KcpTransport;
 
 use async_trait::async_trait;
 use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Таймаут подключения по умолчанию
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Сколько GUI ждёт данных от замолчавшей стороны посреди передачи.
+/// С запасом на паузы вроде проверки SHA-256 большого файла перед ответом
+pub const DEFAULT_IO_TIMEOUT: Duration = Duration::from_secs(300);
 
 /// Тип транспортного протокола
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
@@ -101,6 +114,16 @@ pub trait TransportStream: Send {
     
     /// Закрыть соединение
     async fn shutdown(&mut self) -> io::Result<()>;
+    
+    /// Адрес другой стороны - с кем соединение установлено на самом деле
+    /// (после NAT/прокси может отличаться от адреса, к которому подключались)
+    fn peer_addr(&self) -> io::Result<SocketAddr>;
+    
+    /// Локальный адрес соединения
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+    
+    /// Имя транспорта ("TCP", "QUIC", ...), как `TransportType::name`
+    fn transport_name(&self) -> &'static str;
 }
 
 /// Абстракция слушателя (сервера)
@@ -111,13 +134,25 @@ pub trait TransportListener: Send {
     
     /// Принять с таймаутом (для проверки stop_flag)
     async fn accept_timeout(&mut self, timeout: std::time::Duration) -> io::Result<Option<(Box<dyn TransportStream>, String)>>;
+    
+    /// Адрес, на котором слушаем (при привязке к порту 0 - фактический порт)
+    fn local_addr(&self) -> io::Result<SocketAddr>;
 }
 
 /// Создать транспорт по типу
 pub async fn connect(transport_type: TransportType, addr: &str) -> io::Result<Box<dyn TransportStream>> {
+    connect_tuned(transport_type, addr, SocketTuning::default()).await
+}
+
+/// Создать транспорт по типу с настройками TCP-сокета (для других транспортов не используются)
+pub async fn connect_tuned(
+    transport_type: TransportType,
+    addr: &str,
+    tuning: SocketTuning,
+) -> io::Result<Box<dyn TransportStream>> {
     match transport_type {
         TransportType::Tcp => {
-            let transport = TcpTransport::new();
+            let transport = TcpTransport::with_tuning(tuning);
             Ok(Box::new(transport.connect(addr).await?))
         }
         TransportType::Udp => {
@@ -136,25 +171,50 @@ pub async fn connect(transport_type: TransportType, addr: &str) -> io::Result<Bo
     }
 }
 
-/// Создать слушатель по типу
-pub async fn bind(transport_type: TransportType, port: u16) -> io::Result<Box<dyn TransportListener>> {
+/// Подключиться с ограничением времени (ошибка TimedOut, если узел не ответил)
+pub async fn connect_with_timeout(
+    transport_type: TransportType,
+    addr: &str,
+    timeout: Duration,
+    tuning: SocketTuning,
+) -> io::Result<Box<dyn TransportStream>> {
+    match tokio::time::timeout(timeout, connect_tuned(transport_type, addr, tuning)).await {
+        Ok(result) => result,
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("превышено время подключения ({} с)", timeout.as_secs_f32()),
+        )),
+    }
+}
+
+/// Создать слушатель по типу на адресе `addr` (интерфейс и порт)
+pub async fn bind(transport_type: TransportType, addr: SocketAddr) -> io::Result<Box<dyn TransportListener>> {
+    bind_tuned(transport_type, addr, SocketTuning::default()).await
+}
+
+/// Создать слушатель по типу с настройками TCP-сокета
+pub async fn bind_tuned(
+    transport_type: TransportType,
+    addr: SocketAddr,
+    tuning: SocketTuning,
+) -> io::Result<Box<dyn TransportListener>> {
     match transport_type {
         TransportType::Tcp => {
-            let transport = TcpTransport::new();
-            Ok(Box::new(transport.bind(port).await?))
+            let transport = TcpTransport::with_tuning(tuning);
+            Ok(Box::new(transport.bind(addr).await?))
         }
         TransportType::Udp => {
-            Ok(Box::new(UdpTransport::bind(port).await?))
+            Ok(Box::new(UdpTransport::bind(addr).await?))
         }
         #[cfg(feature = "quic")]
         TransportType::Quic => {
             let transport = QuicTransport::new();
-            Ok(Box::new(transport.bind(port).await?))
+            Ok(Box::new(transport.bind(addr).await?))
         }
         #[cfg(feature = "kcp")]
         TransportType::Kcp => {
             let transport = KcpTransport::new();
-            Ok(Box::new(transport.bind(port).await?))
+            Ok(Box::new(transport.bind(addr).await?))
         }
     }
 }
@@ -1,8 +1,8 @@
 //! QUIC транспорт (требует feature "quic")
 
-use super::{TransportListener, TransportStream};
+use super::{TransportListener, TransportStream, TransportType};
 use async_trait::async_trait;
-use quinn::{ClientConfig, Endpoint, ServerConfig, RecvStream, SendStream};
+use quinn::{ClientConfig, Connection, Endpoint, ServerConfig, RecvStream, SendStream};
 use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -12,11 +12,13 @@ use tokio::time::{timeout, Duration};
 pub struct QuicStreamWrapper {
     send: SendStream,
     recv: RecvStream,
+    peer_addr: SocketAddr,
+    local_addr: SocketAddr,
 }
 
 impl QuicStreamWrapper {
-    pub fn new(send: SendStream, recv: RecvStream) -> Self {
-        Self { send, recv }
+    pub fn new(send: SendStream, recv: RecvStream, peer_addr: SocketAddr, local_addr: SocketAddr) -> Self {
+        Self { send, recv, peer_addr, local_addr }
     }
 }
 
@@ -46,6 +48,27 @@ impl TransportStream for QuicStreamWrapper {
         self.send.finish()
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
     }
+    
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.peer_addr)
+    }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+    
+    fn transport_name(&self) -> &'static str {
+        TransportType::Quic.name()
+    }
+}
+
+/// Локальный адрес соединения: порт endpoint и IP, на который пришли пакеты (если известен)
+fn connection_local_addr(connection: &Connection, endpoint: &Endpoint) -> io::Result<SocketAddr> {
+    let mut addr = endpoint.local_addr()?;
+    if let Some(ip) = connection.local_ip() {
+        addr.set_ip(ip);
+    }
+    Ok(addr)
 }
 
 /// QUIC слушатель
@@ -62,12 +85,13 @@ impl TransportListener for QuicListenerWrapper {
         let connection = incoming.await
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
         
-        let addr = connection.remote_address().to_string();
+        let peer_addr = connection.remote_address();
+        let local_addr = connection_local_addr(&connection, &self.endpoint)?;
         
         let (send, recv) = connection.accept_bi().await
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
         
-        Ok((Box::new(QuicStreamWrapper::new(send, recv)), addr))
+        Ok((Box::new(QuicStreamWrapper::new(send, recv, peer_addr, local_addr)), peer_addr.to_string()))
     }
     
     async fn accept_timeout(&mut self, duration: Duration) -> io::Result<Option<(Box<dyn TransportStream>, String)>> {
@@ -76,6 +100,10 @@ impl TransportListener for QuicListenerWrapper {
             Err(_) => Ok(None),
         }
     }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.endpoint.local_addr()
+    }
 }
 
 /// QUIC транспорт
@@ -106,14 +134,14 @@ impl QuicTransport {
         let (send, recv) = connection.open_bi().await
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
         
-        Ok(QuicStreamWrapper::new(send, recv))
+        let local_addr = connection_local_addr(&connection, &endpoint)?;
+        Ok(QuicStreamWrapper::new(send, recv, connection.remote_address(), local_addr))
     }
     
-    pub async fn bind(&self, port: u16) -> io::Result<QuicListenerWrapper> {
+    pub async fn bind(&self, addr: SocketAddr) -> io::Result<QuicListenerWrapper> {
         let (server_config, _cert) = configure_server()
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
         
-        let addr: SocketAddr = format!("0.0.0.0:{}", port).parse().unwrap();
         let endpoint = Endpoint::server(server_config, addr)
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
         
@@ -1,12 +1,56 @@
 //! TCP транспорт
 
-use super::{TransportListener, TransportStream};
+use super::{TransportListener, TransportStream, TransportType};
 use async_trait::async_trait;
+use socket2::SockRef;
 use std::io;
+use std::net::SocketAddr;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
 use tokio::time::{timeout, Duration};
 
+/// Настройки TCP-сокета
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketTuning {
+    /// TCP_NODELAY: отключить алгоритм Нейгла (меньше задержка на мелких сообщениях)
+    pub nodelay: bool,
+    /// SO_SNDBUF в байтах (None - как решит ОС)
+    pub send_buf: Option<usize>,
+    /// SO_RCVBUF в байтах (None - как решит ОС).
+    /// На каналах с большим RTT буфер должен вмещать скорость × RTT
+    pub recv_buf: Option<usize>,
+}
+
+impl Default for SocketTuning {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            send_buf: None,
+            recv_buf: None,
+        }
+    }
+}
+
+impl SocketTuning {
+    /// Буферы задаются до connect/listen: от SO_RCVBUF зависит масштаб окна TCP
+    fn apply_buffers(&self, socket: &SockRef<'_>) -> io::Result<()> {
+        if let Some(size) = self.send_buf {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buf {
+            socket.set_recv_buffer_size(size)?;
+        }
+        Ok(())
+    }
+    
+    /// Применить к установленному соединению
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        let socket = SockRef::from(stream);
+        self.apply_buffers(&socket)?;
+        socket.set_tcp_nodelay(self.nodelay)
+    }
+}
+
 /// TCP поток
 pub struct TcpStreamWrapper {
     stream: TcpStream,
@@ -14,11 +58,20 @@ pub struct TcpStreamWrapper {
 
 impl TcpStreamWrapper {
     pub fn new(stream: TcpStream) -> Self {
-        // Отключаем алгоритм Нейгла для меньшей задержки
-        stream.set_nodelay(true).ok();
+        Self::with_tuning(stream, &SocketTuning::default())
+    }
+    
+    /// Обернуть поток, применив настройки сокета (ошибки настройки не фатальны)
+    pub fn with_tuning(stream: TcpStream, tuning: &SocketTuning) -> Self {
+        tuning.apply(&stream).ok();
         Self { stream }
     }
     
+    /// Забрать внутренний TcpStream
+    pub fn into_inner(self) -> TcpStream {
+        self.stream
+    }
+    
     /// Получить ссылку на внутренний TcpStream
     pub fn inner(&self) -> &TcpStream {
         &self.stream
@@ -52,53 +105,178 @@ impl TransportStream for TcpStreamWrapper {
     async fn shutdown(&mut self) -> io::Result<()> {
         self.stream.shutdown().await
     }
+    
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.local_addr()
+    }
+    
+    fn transport_name(&self) -> &'static str {
+        TransportType::Tcp.name()
+    }
 }
 
 /// TCP слушатель
 pub struct TcpListenerWrapper {
     listener: TcpListener,
+    tuning: SocketTuning,
 }
 
 #[async_trait]
 impl TransportListener for TcpListenerWrapper {
     async fn accept(&mut self) -> io::Result<(Box<dyn TransportStream>, String)> {
         let (stream, addr) = self.listener.accept().await?;
-        Ok((Box::new(TcpStreamWrapper::new(stream)), addr.to_string()))
+        Ok((Box::new(TcpStreamWrapper::with_tuning(stream, &self.tuning)), addr.to_string()))
     }
     
     async fn accept_timeout(&mut self, duration: Duration) -> io::Result<Option<(Box<dyn TransportStream>, String)>> {
         match timeout(duration, self.listener.accept()).await {
             Ok(Ok((stream, addr))) => {
-                Ok(Some((Box::new(TcpStreamWrapper::new(stream)), addr.to_string())))
+                Ok(Some((Box::new(TcpStreamWrapper::with_tuning(stream, &self.tuning)), addr.to_string())))
             }
             Ok(Err(e)) => Err(e),
             Err(_) => Ok(None), // Timeout
         }
     }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
 }
 
 /// TCP транспорт
-#[derive(Clone)]
-pub struct TcpTransport;
+#[derive(Clone, Default)]
+pub struct TcpTransport {
+    tuning: SocketTuning,
+}
 
 impl TcpTransport {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+    
+    /// Транспорт с настройками сокета для connect и accept
+    pub fn with_tuning(tuning: SocketTuning) -> Self {
+        Self { tuning }
     }
     
     pub async fn connect(&self, addr: &str) -> io::Result<TcpStreamWrapper> {
-        let stream = TcpStream::connect(addr).await?;
-        Ok(TcpStreamWrapper::new(stream))
+        let mut last_error = None;
+        for addr in tokio::net::lookup_host(addr).await? {
+            match self.connect_addr(addr).await {
+                Ok(stream) => return Ok(TcpStreamWrapper::with_tuning(stream, &self.tuning)),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "адрес не найден")
+        }))
+    }
+    
+    async fn connect_addr(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+        self.tuning.apply_buffers(&SockRef::from(&socket))?;
+        socket.connect(addr).await
     }
     
-    pub async fn bind(&self, port: u16) -> io::Result<TcpListenerWrapper> {
-        let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-        Ok(TcpListenerWrapper { listener })
+    pub async fn bind(&self, addr: SocketAddr) -> io::Result<TcpListenerWrapper> {
+        let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+        // Как у TcpListener::bind: на Unix порт можно сразу занять повторно
+        #[cfg(unix)]
+        socket.set_reuseaddr(true)?;
+        // `[::]` - все интерфейсы обоих стеков: IPv4-клиенты тоже подключаются
+        // (на Windows и при net.ipv6.bindv6only=1 это выключено по умолчанию)
+        if addr.ip().is_unspecified() && addr.is_ipv6() {
+            SockRef::from(&socket).set_only_v6(false)?;
+        }
+        // Принятые соединения наследуют буферы слушателя
+        self.tuning.apply_buffers(&SockRef::from(&socket))?;
+        socket.bind(addr)?;
+        let listener = socket.listen(1024)?;
+        Ok(TcpListenerWrapper { listener, tuning: self.tuning })
     }
 }
 
-impl Default for TcpTransport {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_tuning_applied_on_connect_and_accept() {
+        let tuning = SocketTuning {
+            nodelay: false,
+            send_buf: Some(256 * 1024),
+            recv_buf: Some(256 * 1024),
+        };
+        let transport = TcpTransport::with_tuning(tuning);
+        let mut listener = transport.bind(SocketAddr::from(([0, 0, 0, 0], 0))).await.unwrap();
+        let port = listener.listener.local_addr().unwrap().port();
+        
+        let client = transport.connect(&format!("127.0.0.1:{}", port)).await.unwrap();
+        let (_server, _) = listener.accept().await.unwrap();
+        
+        let client = client.into_inner();
+        assert!(!client.nodelay().unwrap());
+        // ОС может округлить размер (Linux удваивает), но не уменьшить ниже запрошенного
+        let socket = SockRef::from(&client);
+        assert!(socket.send_buffer_size().unwrap() >= 256 * 1024);
+        assert!(socket.recv_buffer_size().unwrap() >= 256 * 1024);
+    }
+    
+    #[tokio::test]
+    async fn test_bind_specific_interface() {
+        let transport = TcpTransport::new();
+        let mut listener = transport.bind(SocketAddr::from(([127, 0, 0, 1], 0))).await.unwrap();
+        let local = listener.listener.local_addr().unwrap();
+        assert!(local.ip().is_loopback());
+        
+        let _client = transport.connect(&local.to_string()).await.unwrap();
+        let (_server, peer) = listener.accept().await.unwrap();
+        assert!(peer.starts_with("127.0.0.1:"));
+    }
+    
+    #[tokio::test]
+    async fn test_bind_ipv6_unspecified_accepts_ipv4() {
+        let transport = TcpTransport::new();
+        // Без IPv6 в системе проверять нечего
+        let Ok(mut listener) = transport.bind("[::]:0".parse().unwrap()).await else {
+            return;
+        };
+        let port = listener.listener.local_addr().unwrap().port();
+        
+        let _client = transport.connect(&format!("127.0.0.1:{}", port)).await.unwrap();
+        let (_server, peer) = listener.accept().await.unwrap();
+        assert!(peer.contains("127.0.0.1"), "{}", peer);
+    }
+    
+    #[tokio::test]
+    async fn test_connection_addresses() {
+        let transport = TcpTransport::new();
+        let mut listener = transport.bind(SocketAddr::from(([127, 0, 0, 1], 0))).await.unwrap();
+        let port = listener.listener.local_addr().unwrap().port();
+        
+        let client = transport.connect(&format!("localhost:{}", port)).await.unwrap();
+        let (server, peer) = listener.accept().await.unwrap();
+        
+        // Адреса сторон зеркальны, имя хоста разрешено в фактический адрес
+        assert_eq!(client.peer_addr().unwrap(), server.local_addr().unwrap());
+        assert_eq!(server.peer_addr().unwrap(), client.local_addr().unwrap());
+        assert_eq!(server.peer_addr().unwrap().to_string(), peer);
+        assert_eq!(client.peer_addr().unwrap().port(), port);
+        assert_eq!(client.transport_name(), "TCP");
+    }
+    
+    #[tokio::test]
+    async fn test_default_tuning_sets_nodelay() {
+        let transport = TcpTransport::new();
+        let mut listener = transport.bind(SocketAddr::from(([0, 0, 0, 0], 0))).await.unwrap();
+        let port = listener.listener.local_addr().unwrap().port();
+        
+        let client = transport.connect(&format!("localhost:{}", port)).await.unwrap();
+        let _ = listener.accept().await.unwrap();
+        assert!(client.inner().nodelay().unwrap());
     }
 }
@@ -0,0 +1,148 @@
+//! Ограничение времени ожидания на уже установленном соединении
+//!
+//! Если другая сторона замолчала посреди передачи, чтение ждало бы вечно.
+//! `TimeoutStream` обрывает любое чтение или запись, которые не продвинулись
+//! за заданное время, ошибкой `TimedOut`. Срок отсчитывается заново после каждой
+//! порции данных: большой кадр на медленной, но живой сети не обрывается
+
+use super::TransportStream;
+use async_trait::async_trait;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Сколько байт `write_all` отдаёт за одно ожидание
+const WRITE_STEP: usize = 64 * 1024;
+
+/// Поток, в котором каждая операция ограничена по времени
+pub struct TimeoutStream<S: TransportStream + ?Sized = dyn TransportStream> {
+    inner: Box<S>,
+    timeout: Duration,
+}
+
+impl<S: TransportStream + ?Sized> TimeoutStream<S> {
+    pub fn new(inner: Box<S>, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+    
+    /// Вернуть обёрнутый поток
+    pub fn into_inner(self) -> Box<S> {
+        self.inner
+    }
+}
+
+/// Обернуть поток в `TimeoutStream`, если ограничение задано (None - как есть)
+pub fn with_io_timeout(stream: Box<dyn TransportStream>, timeout: Option<Duration>) -> Box<dyn TransportStream> {
+    match timeout {
+        Some(timeout) => Box::new(TimeoutStream::new(stream, timeout)),
+        None => stream,
+    }
+}
+
+/// Выполнить операцию потока не дольше `timeout`
+async fn limited<T>(timeout: Duration, op: impl Future<Output = io::Result<T>>) -> io::Result<T> {
+    match tokio::time::timeout(timeout, op).await {
+        Ok(result) => result,
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("превышено время ожидания: другая сторона не отвечает {} с", timeout.as_secs_f32()),
+        )),
+    }
+}
+
+#[async_trait]
+impl<S: TransportStream + ?Sized> TransportStream for TimeoutStream<S> {
+    async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        limited(self.timeout, self.inner.read(buf)).await
+    }
+    
+    async fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        // По одному `read` на ожидание, чтобы срок шёл от последних пришедших байт
+        let mut filled = 0;
+        while filled < buf.len() {
+            let n = limited(self.timeout, self.inner.read(&mut buf[filled..])).await?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "соединение закрыто"));
+            }
+            filled += n;
+        }
+        Ok(())
+    }
+    
+    async fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        for step in buf.chunks(WRITE_STEP) {
+            limited(self.timeout, self.inner.write_all(step)).await?;
+        }
+        Ok(())
+    }
+    
+    async fn flush(&mut self) -> io::Result<()> {
+        limited(self.timeout, self.inner.flush()).await
+    }
+    
+    async fn shutdown(&mut self) -> io::Result<()> {
+        limited(self.timeout, self.inner.shutdown()).await
+    }
+    
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+    
+    fn transport_name(&self) -> &'static str {
+        self.inner.transport_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::transport::TcpTransport;
+    
+    #[tokio::test]
+    async fn test_read_times_out_on_silent_peer() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local = listener.local_addr().unwrap();
+        
+        let client = TcpTransport::new().connect(&local.to_string()).await.unwrap();
+        let (_server, _) = listener.accept().await.unwrap();
+        
+        // Сервер молчит - чтение обрывается по таймауту, а не висит
+        let mut stream = with_io_timeout(Box::new(client), Some(Duration::from_millis(100)));
+        let mut buf = [0u8; 4];
+        let err = stream.read_exact(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+    
+    #[tokio::test]
+    async fn test_read_exact_deadline_resets_on_progress() {
+        use tokio::io::AsyncWriteExt;
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local = listener.local_addr().unwrap();
+        
+        let client = TcpTransport::new().connect(&local.to_string()).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        
+        // Кадр приходит дольше таймаута, но байт за байтом чаще него - чтение не обрывается
+        let writer = tokio::spawn(async move {
+            for byte in 0..8u8 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                server.write_all(&[byte]).await.unwrap();
+            }
+            server
+        });
+        let mut stream = TimeoutStream::new(Box::new(client), Duration::from_millis(200));
+        let mut buf = [0u8; 8];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, [0, 1, 2, 3, 4, 5, 6, 7]);
+        let _server = writer.await.unwrap();
+        
+        // Обёрнутый поток можно забрать обратно
+        let _client: Box<crate::network::transport::TcpStreamWrapper> = stream.into_inner();
+    }
+}
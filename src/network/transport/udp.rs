@@ -1,7 +1,7 @@
 //! UDP транспорт - быстрый, но без гарантии доставки
 //! ⚠️ Не рекомендуется для больших файлов - возможна потеря пакетов!
 
-use super::{TransportListener, TransportStream};
+use super::{TransportListener, TransportStream, TransportType};
 use async_trait::async_trait;
 use std::io;
 use std::net::SocketAddr;
@@ -113,6 +113,18 @@ impl TransportStream for UdpStreamWrapper {
         // UDP не имеет shutdown, просто ничего не делаем
         Ok(())
     }
+    
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.peer_addr)
+    }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+    
+    fn transport_name(&self) -> &'static str {
+        TransportType::Udp.name()
+    }
 }
 
 /// UDP слушатель
@@ -123,8 +135,7 @@ pub struct UdpListenerWrapper {
 }
 
 impl UdpListenerWrapper {
-    pub async fn bind(port: u16) -> io::Result<Self> {
-        let addr: SocketAddr = format!("0.0.0.0:{}", port).parse().unwrap();
+    pub async fn bind(addr: SocketAddr) -> io::Result<Self> {
         let socket = UdpSocket::bind(addr).await?;
         
         Ok(Self {
@@ -158,6 +169,10 @@ impl TransportListener for UdpListenerWrapper {
             Err(_) => Ok(None),
         }
     }
+    
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
 }
 
 /// UDP транспорт
@@ -172,8 +187,8 @@ impl UdpTransport {
         UdpStreamWrapper::connect(addr).await
     }
     
-    pub async fn bind(port: u16) -> io::Result<UdpListenerWrapper> {
-        UdpListenerWrapper::bind(port).await
+    pub async fn bind(addr: SocketAddr) -> io::Result<UdpListenerWrapper> {
+        UdpListenerWrapper::bind(addr).await
     }
 }
 
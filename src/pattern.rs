@@ -0,0 +1,308 @@
+//! Простые glob-шаблоны для путей: `*`, `?` и `**`
+//!
+//! - `*` - любые символы внутри одного компонента пути (кроме `/`)
+//! - `?` - один любой символ (кроме `/`)
+//! - `**` - отдельный компонент, совпадает с любым числом папок (в том числе ни с одной)
+//!
+//! Шаблон сравнивается с путём целиком: `*.toml` совпадает только с файлом
+//! в корне, для любой глубины пишите `**/*.toml`. Разделитель - `/`,
+//! обратные слэши Windows в пути приводятся к нему.
+//!
+//! `IgnoreRules` - исключения в духе `.gitignore` поверх тех же шаблонов.
+
+use std::path::Path;
+
+/// Файл исключений в корне отправляемой папки
+pub const IGNORE_FILE_NAME: &str = ".toolzaignore";
+
+/// Glob-шаблон пути
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    source: String,
+    /// Сравнивать как обычную строку (без подстановок)
+    literal: bool,
+}
+
+impl Pattern {
+    /// Создать шаблон
+    pub fn new(source: &str) -> Result<Self, String> {
+        let source = normalize(source);
+        if source.is_empty() {
+            return Err("Пустой шаблон".to_string());
+        }
+        Ok(Self { source, literal: false })
+    }
+    
+    /// Шаблон, совпадающий ровно с этим путём (`*` и `?` не раскрываются)
+    pub fn literal(path: &str) -> Self {
+        Self { source: normalize(path), literal: true }
+    }
+    
+    /// Исходный текст шаблона
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+    
+    /// Совпадает ли путь (через `/`) с шаблоном
+    pub fn matches(&self, path: &str) -> bool {
+        let path = normalize(path);
+        if self.literal {
+            return path == self.source;
+        }
+        
+        let pattern: Vec<&str> = self.source.split('/').collect();
+        let components: Vec<&str> = path.split('/').collect();
+        match_components(&pattern, &components)
+    }
+    
+    /// Совпадает ли путь файловой системы с шаблоном
+    pub fn matches_path(&self, path: &Path) -> bool {
+        self.matches(&path.to_string_lossy())
+    }
+}
+
+/// Разбор списка шаблонов (первая ошибка прерывает разбор)
+pub fn parse_patterns<S: AsRef<str>>(sources: &[S]) -> Result<Vec<Pattern>, String> {
+    sources.iter().map(|s| Pattern::new(s.as_ref())).collect()
+}
+
+/// Совпадает ли путь хотя бы с одним шаблоном
+pub fn matches_any(patterns: &[Pattern], path: &str) -> bool {
+    patterns.iter().any(|p| p.matches(path))
+}
+
+/// Фильтр путей из включающих и исключающих шаблонов
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    /// Если не пусто - проходят только пути, совпавшие хотя бы с одним
+    pub include: Vec<Pattern>,
+    /// Совпавшие пути не проходят, даже если подошли под `include`
+    pub exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    /// Разобрать шаблоны фильтра
+    pub fn new<S: AsRef<str>>(include: &[S], exclude: &[S]) -> Result<Self, String> {
+        Ok(Self {
+            include: parse_patterns(include)?,
+            exclude: parse_patterns(exclude)?,
+        })
+    }
+    
+    /// Фильтр ничего не отсеивает
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+    
+    /// Проходит ли путь через фильтр
+    pub fn allows(&self, path: &str) -> bool {
+        (self.include.is_empty() || matches_any(&self.include, path)) && !matches_any(&self.exclude, path)
+    }
+}
+
+/// Правила исключения в духе `.gitignore` (упрощённое подмножество):
+///
+/// - пустые строки и строки с `#` пропускаются
+/// - `!` в начале возвращает исключённое раньше: решает последнее совпавшее правило
+/// - `/` в конце - только папки; всё внутри исключённой папки тоже исключено
+/// - шаблон без `/` совпадает с именем на любой глубине (`*.log`),
+///   со `/` - от корня папки (`docs/*.pdf`, `/build`)
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreRules {
+    /// Разобрать текст файла исключений
+    pub fn parse(text: &str) -> Self {
+        Self::from_patterns(text.lines())
+    }
+    
+    /// Правила из отдельных строк (синтаксис как в файле)
+    pub fn from_patterns<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> Self {
+        let rules = lines.into_iter().filter_map(|line| IgnoreRule::parse(line.as_ref())).collect();
+        Self { rules }
+    }
+    
+    /// Прочитать `.toolzaignore` из корня папки; нет файла - нет правил
+    pub fn load(root: &Path) -> std::io::Result<Self> {
+        match std::fs::read_to_string(root.join(IGNORE_FILE_NAME)) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// Добавить правила после своих (они проверяются позже и потому важнее)
+    pub fn extend(&mut self, other: IgnoreRules) {
+        self.rules.extend(other.rules);
+    }
+    
+    /// Нет ни одного правила
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+    
+    /// Исключён ли путь относительно корня папки (через `/`)
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let path = normalize(path);
+        // Исключённая папка исключает всё внутри, `!` этого не отменяет
+        let parent_ignored = path
+            .match_indices('/')
+            .any(|(end, _)| self.matches(&path[..end], true));
+        parent_ignored || self.matches(&path, is_dir)
+    }
+    
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.pattern.matches(path))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+impl IgnoreRule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let dir_only = line.ends_with('/');
+        let body = line.trim_end_matches('/');
+        // Без `/` внутри - имя на любой глубине, иначе путь от корня
+        let source = if body.contains('/') {
+            body.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", body)
+        };
+        let pattern = Pattern::new(&source).ok()?;
+        Some(Self { pattern, negated, dir_only })
+    }
+}
+
+/// `\` -> `/`, без `./` в начале и `/` в конце
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    path.to_string()
+}
+
+fn match_components(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_components(rest, &path[skip..])),
+        Some((segment, rest)) => {
+            !path.is_empty()
+                && match_segment(segment.as_bytes(), path[0].as_bytes())
+                && match_components(rest, &path[1..])
+        }
+    }
+}
+
+/// Сопоставление одного компонента с `*` и `?`
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| match_segment(rest, &name[skip..])),
+        Some((b'?', rest)) => match std::str::from_utf8(name).ok().and_then(|s| s.chars().next()) {
+            // `?` - один символ, а не один байт
+            Some(c) => match_segment(rest, &name[c.len_utf8()..]),
+            None => !name.is_empty() && match_segment(rest, &name[1..]),
+        },
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn pattern(s: &str) -> Pattern {
+        Pattern::new(s).unwrap()
+    }
+    
+    #[test]
+    fn test_star_stays_in_component() {
+        assert!(pattern("*.toml").matches("config.toml"));
+        assert!(!pattern("*.toml").matches("app/config.toml"));
+        assert!(pattern("app/*.toml").matches("app/config.toml"));
+        assert!(pattern("file?.txt").matches("file1.txt"));
+        assert!(!pattern("file?.txt").matches("file12.txt"));
+    }
+    
+    #[test]
+    fn test_double_star() {
+        let p = pattern("**/config.toml");
+        assert!(p.matches("config.toml"));
+        assert!(p.matches("a/b/config.toml"));
+        assert!(!p.matches("a/b/config.toml.bak"));
+        
+        let docs = pattern("docs/**");
+        assert!(docs.matches("docs/a.md"));
+        assert!(docs.matches("docs/sub/b.md"));
+        assert!(!docs.matches("src/docs.md"));
+        
+        assert!(pattern("**/*.tmp").matches("x/y/z.tmp"));
+    }
+    
+    #[test]
+    fn test_normalization_and_literal() {
+        assert!(pattern("./docs/").matches("docs"));
+        assert!(pattern("a/*.txt").matches("a\\b.txt"));
+        
+        let literal = Pattern::literal("weird[*].txt");
+        assert!(literal.matches("weird[*].txt"));
+        assert!(!literal.matches("weird[x].txt"));
+        
+        assert!(Pattern::new("").is_err());
+    }
+    
+    #[test]
+    fn test_path_filter() {
+        let filter = PathFilter::new(&["docs/**", "**/*.md"], &["**/*.tmp", "docs/drafts/**"]).unwrap();
+        assert!(filter.allows("docs/guide.pdf"));
+        assert!(filter.allows("src/README.md"));
+        assert!(!filter.allows("src/main.rs"));
+        assert!(!filter.allows("docs/cache/x.tmp"));
+        assert!(!filter.allows("docs/drafts/intro.md"));
+        
+        let exclude_only = PathFilter::new(&[] as &[&str], &["**/*.tmp"]).unwrap();
+        assert!(exclude_only.allows("src/main.rs"));
+        assert!(!exclude_only.allows("a.tmp"));
+        assert!(PathFilter::default().is_empty());
+        assert!(PathFilter::new(&[""], &[]).is_err());
+    }
+    
+    #[test]
+    fn test_ignore_rules() {
+        let rules = IgnoreRules::parse("# сборка\n*.log\nbuild/\n\n!keep.log\n/docs/*.pdf\n");
+        assert!(rules.is_ignored("app.log", false));
+        assert!(rules.is_ignored("logs/2024/app.log", false));
+        assert!(!rules.is_ignored("logs/keep.log", false));
+        
+        // `build/` - только папка, зато с любым содержимым и на любой глубине
+        assert!(rules.is_ignored("build", true));
+        assert!(!rules.is_ignored("build", false));
+        assert!(rules.is_ignored("build/out/main.o", false));
+        assert!(rules.is_ignored("crates/core/build/x.bin", false));
+        assert!(!rules.is_ignored("builder/x.bin", false));
+        
+        // Шаблон со `/` - от корня
+        assert!(rules.is_ignored("docs/manual.pdf", false));
+        assert!(!rules.is_ignored("src/docs/manual.pdf", false));
+        
+        assert!(IgnoreRules::from_patterns(["", "# только комментарий"]).is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use crate::pattern::IgnoreRules;
 
 /// Версия протокола
 pub const PROTOCOL_VERSION: u8 = 2;
@@ -6,7 +7,17 @@ pub const PROTOCOL_VERSION: u8 = 2;
 /// Порт по умолчанию
 pub const DEFAULT_PORT: u16 = 9527;
 
-/// Сообщения протокола
+/// Сообщения протокола.
+///
+/// На проводе: длина u32 little-endian, затем bincode (стандартная конфигурация:
+/// номер варианта u32 LE, целые фиксированной ширины LE, строки и Vec - с длиной u64).
+/// Номера вариантов и порядок полей - часть протокола: перестановка или вставка
+/// в середину ломает совместимость со старыми версиями. Новые варианты - только
+/// в конец. Новые поля в существующие варианты не добавляются: bincode не знает
+/// `#[serde(default)]`, и старая сторона сообщение не прочитает. Нужен новый вариант
+/// или бит возможности (`FEATURE_*`), согласованный в `Message::Hello`, - такое
+/// сообщение уходит только получателю, который бит подтвердил.
+/// Точная раскладка закреплена в `test_wire_format_golden` и `test_baseline_wire_format`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Message {
     /// Начало передачи файла
@@ -74,6 +85,254 @@ pub enum Message {
         download_speed: f64,
         latency_ms: f64,
     },
+    
+    // === Дедупликация ===
+    
+    /// Содержимое `to` совпадает с уже переданным `from` - скопировать
+    /// на стороне получателя вместо повторной передачи.
+    /// Ответ: Ack, либо Error (тогда отправитель передаёт файл целиком).
+    /// Только после `FEATURE_FILE_LINK` в HelloAck
+    FileLink {
+        from: String,
+        to: String,
+    },
+    
+    // === Имена не в UTF-8 ===
+    
+    /// Сырые байты относительного пути для следующего FileStart, если имя
+    /// не в UTF-8 (в FileStart тогда лишь его lossy-версия). Байты - как есть
+    /// на Unix, WTF-8 на Windows; разделитель `/`.
+    /// Ответ: Ack, либо Error (тогда файл сохраняется под lossy-именем).
+    /// Только после `FEATURE_RAW_FILE_NAME` в HelloAck
+    RawFileName {
+        bytes: Vec<u8>,
+    },
+    
+    // === Подбор размера чанка ===
+    
+    /// Данные пробы в начале соединения: получатель их отбрасывает без ответа.
+    /// За пачкой проб отправитель шлёт Ack и замеряет время до ответного Ack.
+    /// Только после `FEATURE_CHUNK_PROBE` в HelloAck
+    ChunkProbe {
+        data: Vec<u8>,
+    },
+    
+    // === Проверка докачки ===
+    
+    /// Что из этих файлов уже есть у получателя - перед отправкой, ничего не передаёт.
+    /// Ответ: ResumeProbeResult
+    ResumeProbe {
+        files: Vec<SyncFileEntry>,
+    },
+    /// Offset докачки для каждого файла из ResumeProbe, в том же порядке
+    /// (0 - с начала, size - файл уже получен)
+    ResumeProbeResult {
+        offsets: Vec<u64>,
+    },
+    
+    // === Согласование возможностей ===
+    
+    /// Первое сообщение сессии: какие возможности (`FEATURE_*`) просит отправитель
+    /// и зачем он подключился. Ответ: HelloAck; старый получатель отвечает Error
+    /// или закрывает соединение
+    Hello {
+        features: u32,
+        intent: ConnectionIntent,
+    },
+    /// Возможности, которые получатель включил - пересечение с запрошенными.
+    /// Всё после HelloAck идёт уже в согласованном формате
+    HelloAck {
+        features: u32,
+    },
+    
+    // === Метаданные файла ===
+    
+    /// Расширенные атрибуты `user.*` для следующего FileStart: получатель
+    /// записывает их на файл после сохранения. Перед архивом, который
+    /// распаковывается на лету, - ещё и просьба восстановить xattr из
+    /// PAX-заголовков tar. Только после `FEATURE_FILE_META` в HelloAck; ответ: Ack
+    FileMeta {
+        xattrs: Vec<(String, Vec<u8>)>,
+    },
+    
+    // === Сжатые списки ===
+    
+    /// Другое сообщение-список (`SyncFileList`, `ResumeProbe`, `ResumeProbeResult`, `DeleteFiles`),
+    /// сжатое LZ4: bincode исходного сообщения с префиксом размера. Номер этого
+    /// варианта и есть признак сжатия - `from_bytes` распаковывает его сам.
+    /// Только после `FEATURE_PACKED_LISTS` в HelloAck
+    Packed {
+        data: Vec<u8>,
+    },
+    
+    // === Проверка целостности ===
+    
+    /// Ответ на FileEnd после `FileHash`: SHA-256 принятого не совпал, копия удалена
+    ChecksumMismatch,
+    
+    // === Правила получателя ===
+    
+    /// Ответ на FileStart: получатель не принимает этот файл (`blocked_extensions`,
+    /// `max_file_size`). Данные файла не отправляются, дальше - следующий файл
+    Rejected {
+        reason: String,
+    },
+    
+    // === Кодек сжатия ===
+    
+    /// Кодек чанков следующего FileStart с `compressed`, если это не LZ4.
+    /// Ответ: Ack, либо Error (тогда файл сжимается LZ4). Шлётся только
+    /// получателю, подтвердившему `FEATURE_FILE_CODEC`: старый его не понимает
+    FileCodec {
+        codec: crate::network::compression::CompressionCodec,
+    },
+    
+    // === Удаление при синхронизации ===
+    
+    /// Удалить у получателя эти файлы (пути от папки приёма через `/`):
+    /// их больше нет у отправителя (`send --sync --delete`). Папки не удаляются.
+    /// Ответ: Ack, либо Error со списком того, что удалить не удалось
+    DeleteFiles {
+        paths: Vec<String>,
+    },
+    
+    // === Синхронизация по корням ===
+    
+    /// Как SyncRequest, но получатель обходит только эти папки и файлы верхнего
+    /// уровня (имена без `/`) - остальное в папке приёма его не касается.
+    /// Ответ: SyncFileList. Только после `FEATURE_SYNC_ROOTS` в HelloAck
+    SyncRoots {
+        roots: Vec<String>,
+    },
+    
+    // === Ретрансляция ===
+    
+    /// От ретранслятора сразу после HelloAck: сколько узлов файлы сессии уже
+    /// прошли. Получатель с `relay_to` после `MAX_RELAY_HOPS` их дальше не пересылает.
+    /// Только после `FEATURE_RELAY_HOPS` в HelloAck; ответ: Ack
+    RelayHops {
+        hops: u32,
+    },
+    
+    // === Докачка потоковой распаковки ===
+    
+    /// Ответ на FileStart архива tar.lz4/tar.zst вместо Ack: эти записи уже
+    /// распакованы в прошлую попытку. Отправитель может выслать архив без них
+    /// в том же формате; полный архив тоже принимается.
+    /// Только после `FEATURE_EXTRACT_MANIFEST` в HelloAck
+    ExtractManifest {
+        entries: Vec<ManifestEntry>,
+    },
+    
+    // === Проверка целостности ===
+    
+    /// SHA-256 всего файла - после последнего FileChunk, перед FileEnd.
+    /// Ответ: Ack (получатель сверит его на FileEnd и при расхождении ответит
+    /// ChecksumMismatch), либо Error (файл не проверяется, например архив
+    /// распаковывается на лету). Только после `FEATURE_VERIFY_INTEGRITY` в HelloAck
+    FileHash {
+        sha256: [u8; 32],
+    },
+    
+    // === Без распаковки ===
+    
+    /// Сохранить архив следующего FileStart как есть, даже если получатель
+    /// распаковывает такие архивы. Ответ: Ack. Только после `FEATURE_NO_EXTRACT` в HelloAck
+    NoExtract,
+}
+
+/// Списки больше этого размера (bincode) сжимаются в `Message::Packed`
+pub const LIST_PACK_THRESHOLD: usize = 64 * 1024;
+
+/// Предел распакованного размера `Message::Packed` (защита от LZ4-бомбы)
+const PACKED_MAX_SIZE: usize = 256 * 1024 * 1024;
+
+/// Компактная раскладка кадров (см. `transport::CompactStream`)
+pub const FEATURE_COMPACT_FRAMING: u32 = 1;
+
+/// Получатель понимает `Message::NoExtract`: без этого бита отправитель не может
+/// попросить сохранить архив как есть, и тот распаковывается как обычно
+pub const FEATURE_NO_EXTRACT: u32 = 2;
+
+/// Получатель понимает `Message::FileCodec`: без этого бита чанки сжимаются только LZ4
+pub const FEATURE_FILE_CODEC: u32 = 4;
+
+/// Получатель принимает `Message::FileMeta`. Подтверждает только получатель,
+/// которому разрешено записывать xattr (`ServerOptions::accept_xattrs`)
+pub const FEATURE_FILE_META: u32 = 8;
+
+/// Получатель понимает `Message::ChunkProbe`: без этого бита проба не делается,
+/// передача начинается с `DEFAULT_CHUNK_SIZE`
+pub const FEATURE_CHUNK_PROBE: u32 = 16;
+
+/// Обе стороны понимают `Message::Packed`: без этого бита списки идут несжатыми
+pub const FEATURE_PACKED_LISTS: u32 = 32;
+
+/// Получатель понимает `Message::SyncRoots`: без этого бита отправитель шлёт
+/// SyncRequest и сам отбрасывает чужие папки из списка
+pub const FEATURE_SYNC_ROOTS: u32 = 64;
+
+/// Получатель понимает `Message::RelayHops`
+pub const FEATURE_RELAY_HOPS: u32 = 128;
+
+/// Отправитель понимает `Message::ExtractManifest` в ответ на FileStart
+pub const FEATURE_EXTRACT_MANIFEST: u32 = 256;
+
+/// Получатель понимает `Message::FileLink`: без этого бита дубликаты передаются целиком
+pub const FEATURE_FILE_LINK: u32 = 512;
+
+/// Получатель понимает `Message::RawFileName`: без этого бита имена не в UTF-8
+/// передаются только lossy-версией из FileStart
+pub const FEATURE_RAW_FILE_NAME: u32 = 1024;
+
+/// Получатель сверяет SHA-256 из `Message::FileHash`. Подтверждает только
+/// получатель с `ServerOptions::verify_integrity`
+pub const FEATURE_VERIFY_INTEGRITY: u32 = 2048;
+
+/// Возможности, которые поддерживает эта версия
+pub const SUPPORTED_FEATURES: u32 = FEATURE_COMPACT_FRAMING
+    | FEATURE_NO_EXTRACT
+    | FEATURE_FILE_CODEC
+    | FEATURE_FILE_META
+    | FEATURE_CHUNK_PROBE
+    | FEATURE_PACKED_LISTS
+    | FEATURE_SYNC_ROOTS
+    | FEATURE_RELAY_HOPS
+    | FEATURE_EXTRACT_MANIFEST
+    | FEATURE_FILE_LINK
+    | FEATURE_RAW_FILE_NAME
+    | FEATURE_VERIFY_INTEGRITY;
+
+/// Зачем клиент подключился к получателю (объявляется в `Message::Hello`)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionIntent {
+    /// Передача файлов
+    Transfer,
+    /// Проверка доступности при сканировании сети: после HelloAck соединение закрывается
+    Probe,
+    /// Спидтест
+    SpeedTest,
+}
+
+impl ConnectionIntent {
+    /// Вид соединения по первому сообщению: Hello объявляет его явно,
+    /// у клиентов без Hello - по тому, с чего они начинают
+    pub fn of_first_message(msg: &Message) -> Self {
+        match msg {
+            Message::Hello { intent, .. } => *intent,
+            // Спидтест начинается с замера задержки (Ack) или сразу с запроса
+            Message::Ack | Message::SpeedTestRequest { .. } => Self::SpeedTest,
+            _ => Self::Transfer,
+        }
+    }
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Transfer => "передача",
+            Self::Probe => "проверка",
+            Self::SpeedTest => "спидтест",
+        }
+    }
 }
 
 /// Запись о файле для синхронизации
@@ -84,6 +343,15 @@ pub struct SyncFileEntry {
     pub quick_hash: u64,
 }
 
+/// Запись архива, уже распакованная получателем (`Message::ExtractManifest`):
+/// путь в архиве, размер и mtime из заголовка tar
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub mtime: u64,
+}
+
 impl Message {
     /// Сериализовать сообщение в байты с префиксом длины
     pub fn to_bytes(&self) -> Result<Vec<u8>, bincode::Error> {
@@ -94,10 +362,52 @@ impl Message {
         result.extend(data);
         Ok(result)
     }
-
-    /// Десериализовать сообщение из байтов (без префикса длины)
+    
+    /// Сериализовать как `to_bytes`, но список больше `threshold` байт
+    /// отправить сжатым (`Message::Packed`), если сжатие его уменьшает
+    pub fn to_bytes_packed(&self, threshold: usize) -> Result<Vec<u8>, bincode::Error> {
+        if !matches!(self, Self::SyncFileList { .. } | Self::ResumeProbe { .. } | Self::ResumeProbeResult { .. } | Self::DeleteFiles { .. }) {
+            return self.to_bytes();
+        }
+        let data = bincode::serialize(self)?;
+        if data.len() <= threshold {
+            return self.to_bytes();
+        }
+        let packed = lz4_flex::block::compress_prepend_size(&data);
+        if packed.len() >= data.len() {
+            return self.to_bytes();
+        }
+        Self::Packed { data: packed }.to_bytes()
+    }
+    
+    /// Десериализовать сообщение из байтов (без префикса длины).
+    /// `Packed` распаковывается: возвращается исходное сообщение
     pub fn from_bytes(data: &[u8]) -> Result<Self, bincode::Error> {
-        bincode::deserialize(data)
+        match bincode::deserialize(data)? {
+            Self::Packed { data } => {
+                let invalid = |text: &str| Box::new(bincode::ErrorKind::Custom(text.to_string()));
+                let size = data.get(..4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+                if size.is_none_or(|size| size > PACKED_MAX_SIZE) {
+                    return Err(invalid("Сжатое сообщение слишком большое"));
+                }
+                let inner = lz4_flex::block::decompress_size_prepended(&data)
+                    .map_err(|e| invalid(&format!("Ошибка распаковки сообщения: {}", e)))?;
+                match bincode::deserialize(&inner)? {
+                    Self::Packed { .. } => Err(invalid("Вложенное сжатое сообщение")),
+                    msg => Ok(msg),
+                }
+            }
+            msg => Ok(msg),
+        }
+    }
+    
+    /// Пришло ли сообщение сжатым (`data` - без префикса длины)
+    pub fn is_packed(data: &[u8]) -> bool {
+        // Номер варианта - из самого Packed, а не записанным числом
+        let Ok(packed) = bincode::serialize(&Self::Packed { data: Vec::new() }) else {
+            return false;
+        };
+        data.get(..4).is_some_and(|tag| tag == &packed[..4])
     }
 }
 
@@ -122,6 +432,8 @@ pub struct FileInfo {
     pub size: u64,
     pub transferred: u64,
     pub status: FileStatus,
+    /// Просить получателя не распаковывать этот архив (`Message::NoExtract`)
+    pub no_extract: bool,
 }
 
 impl FileInfo {
@@ -140,6 +452,7 @@ impl FileInfo {
             size: metadata.len(),
             transferred: 0,
             status: FileStatus::Pending,
+            no_extract: false,
         })
     }
     
@@ -158,9 +471,47 @@ impl FileInfo {
             size: metadata.len(),
             transferred: 0,
             status: FileStatus::Pending,
+            no_extract: false,
         })
     }
-
+    
+    /// Сырые байты относительного пути, если имя не в UTF-8 и
+    /// `relative_path` - его lossy-версия (None - имя передаётся как есть)
+    pub fn raw_relative_path(&self) -> Option<Vec<u8>> {
+        let count = self.relative_path.split('/').count();
+        let components: Vec<&std::ffi::OsStr> = self.path.iter().collect();
+        let tail = components.get(components.len().checked_sub(count)?..)?;
+        
+        if tail.iter().all(|c| c.to_str().is_some()) {
+            return None;
+        }
+        
+        // relative_path переименован (не совпадает с путём на диске) - оставляем как есть
+        let lossy: Vec<String> = tail.iter().map(|c| c.to_string_lossy().to_string()).collect();
+        if lossy.join("/") != self.relative_path {
+            return None;
+        }
+        
+        let raw: Vec<Vec<u8>> = tail.iter().map(|c| os_str_to_wire(c)).collect();
+        Some(raw.join(&b'/'))
+    }
+    
+    /// Сохранить файл у получателя под другим именем (`send --output-name`).
+    ///
+    /// Разделители `\` приводятся к `/`, пустые компоненты, `.` и `..`
+    /// отбрасываются, так что подпуть остаётся внутри папки получателя
+    pub fn set_remote_name(&mut self, name: &str) -> Result<(), String> {
+        let components: Vec<&str> = name
+            .split(['/', '\\'])
+            .filter(|c| !c.trim().is_empty() && *c != "." && *c != "..")
+            .collect();
+        if components.is_empty() {
+            return Err(format!("Недопустимое имя для получателя: '{}'", name));
+        }
+        self.relative_path = components.join("/");
+        Ok(())
+    }
+    
     pub fn progress(&self) -> f32 {
         if self.size == 0 {
             return 1.0;
@@ -169,46 +520,270 @@ impl FileInfo {
     }
 }
 
-/// Рекурсивно собрать все файлы из папки
+/// Имя файла в виде для передачи: байты как есть на Unix, WTF-8 на Windows
+pub fn os_str_to_wire(s: &std::ffi::OsStr) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        s.as_bytes().to_vec()
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        wide_to_wtf8(&s.encode_wide().collect::<Vec<u16>>())
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        s.to_string_lossy().into_owned().into_bytes()
+    }
+}
+
+/// Восстановить имя из байт, полученных от отправителя.
+/// None - на этой системе такое имя не представимо
+pub fn os_string_from_wire(bytes: &[u8]) -> Option<std::ffi::OsString> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(std::ffi::OsStr::from_bytes(bytes).to_os_string())
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStringExt;
+        wtf8_to_wide(bytes).map(|wide| std::ffi::OsString::from_wide(&wide))
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        String::from_utf8(bytes.to_vec()).ok().map(Into::into)
+    }
+}
+
+/// Относительный путь из `Message::RawFileName`.
+/// None - имя не представимо, либо путь абсолютный или выходит за папку (`..`)
+pub fn relative_path_from_wire(bytes: &[u8]) -> Option<std::path::PathBuf> {
+    let mut path = std::path::PathBuf::new();
+    for component in bytes.split(|&b| b == b'/') {
+        if component.is_empty() || component == b"." || component == b".." {
+            return None;
+        }
+        path.push(os_string_from_wire(component)?);
+    }
+    (path.components().count() > 0 && path.is_relative()).then_some(path)
+}
+
+/// UTF-16 (возможно с непарными суррогатами) -> WTF-8
+#[cfg_attr(not(windows), allow(dead_code))]
+fn wide_to_wtf8(wide: &[u16]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(wide.len());
+    for unit in char::decode_utf16(wide.iter().copied()) {
+        match unit {
+            Ok(c) => {
+                let mut buf = [0u8; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            Err(e) => {
+                // Непарный суррогат кодируется как обычная 3-байтовая последовательность
+                let u = e.unpaired_surrogate();
+                out.extend_from_slice(&[0xE0 | (u >> 12) as u8, 0x80 | ((u >> 6) & 0x3F) as u8, 0x80 | (u & 0x3F) as u8]);
+            }
+        }
+    }
+    out
+}
+
+/// WTF-8 -> UTF-16. None - байты не являются WTF-8 (например, Latin-1 с Linux)
+#[cfg_attr(not(windows), allow(dead_code))]
+fn wtf8_to_wide(bytes: &[u8]) -> Option<Vec<u16>> {
+    let mut wide = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let (len, init) = match b {
+            0x00..=0x7F => (1, b as u32),
+            0xC2..=0xDF => (2, (b & 0x1F) as u32),
+            0xE0..=0xEF => (3, (b & 0x0F) as u32),
+            0xF0..=0xF4 => (4, (b & 0x07) as u32),
+            _ => return None,
+        };
+        let cont = bytes.get(i + 1..i + len)?;
+        let mut code = init;
+        for &c in cont {
+            if c & 0xC0 != 0x80 {
+                return None;
+            }
+            code = (code << 6) | (c & 0x3F) as u32;
+        }
+        // Отсекаем избыточные (overlong) кодировки и значения вне Unicode
+        let min = [0, 0, 0x80, 0x800, 0x10000][len];
+        if code < min || code > 0x10FFFF {
+            return None;
+        }
+        if code >= 0x10000 {
+            let c = code - 0x10000;
+            wide.push(0xD800 | (c >> 10) as u16);
+            wide.push(0xDC00 | (c & 0x3FF) as u16);
+        } else {
+            wide.push(code as u16);
+        }
+        i += len;
+    }
+    Some(wide)
+}
+
+/// Опции сбора файлов из папки
+#[derive(Debug, Clone)]
+pub struct CollectOptions {
+    /// Включать скрытые и системные файлы и папки.
+    /// По умолчанию `true` - как и раньше, собирается всё содержимое папки
+    pub include_hidden: bool,
+    /// Исключения в синтаксисе `.toolzaignore` (`*.log`, `target/`); проверяются
+    /// после правил из `.toolzaignore` в корне папки, который читается всегда
+    pub ignore_patterns: Vec<String>,
+}
+
+impl Default for CollectOptions {
+    fn default() -> Self {
+        Self { include_hidden: true, ignore_patterns: Vec::new() }
+    }
+}
+
+/// Скрытый ли файл или папка: имя начинается с точки,
+/// а на Windows - ещё и атрибуты hidden/system
+pub fn is_hidden(path: &std::path::Path) -> bool {
+    let dotfile = path
+        .file_name()
+        .map(|n| n.to_string_lossy().starts_with('.'))
+        .unwrap_or(false);
+    
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+        if let Ok(meta) = std::fs::symlink_metadata(path) {
+            if meta.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0 {
+                return true;
+            }
+        }
+    }
+    
+    dotfile
+}
+
+/// Рекурсивно собрать все файлы из папки (кроме исключённых в `.toolzaignore`)
 pub fn collect_files_from_folder(folder: &std::path::Path) -> std::io::Result<Vec<FileInfo>> {
-    let mut files = Vec::new();
-    let folder_name = folder
+    collect_files_from_folder_with_options(folder, &CollectOptions::default())
+}
+
+/// Собрать файлы из папки, исключив ещё и `ignore_patterns` (синтаксис `.toolzaignore`)
+pub fn collect_files_from_folder_with_ignore(
+    folder: &std::path::Path,
+    ignore_patterns: Vec<String>,
+) -> std::io::Result<Vec<FileInfo>> {
+    collect_files_from_folder_with_options(folder, &CollectOptions { ignore_patterns, ..Default::default() })
+}
+
+/// Рекурсивно собрать файлы из папки с учётом опций
+pub fn collect_files_from_folder_with_options(
+    folder: &std::path::Path,
+    options: &CollectOptions,
+) -> std::io::Result<Vec<FileInfo>> {
+    walk_folder_with_options(folder, options).collect()
+}
+
+/// Лениво обойти папку: файлы выдаются по одному, в том же порядке,
+/// что и у `collect_files_from_folder`. Для деревьев из миллионов файлов
+pub fn walk_folder(root: &std::path::Path) -> impl Iterator<Item = std::io::Result<FileInfo>> {
+    walk_folder_with_options(root, &CollectOptions::default())
+}
+
+/// Лениво обойти папку с учётом опций
+pub fn walk_folder_with_options(
+    root: &std::path::Path,
+    options: &CollectOptions,
+) -> impl Iterator<Item = std::io::Result<FileInfo>> {
+    let folder_name = root
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_else(|| "folder".to_string());
-    
-    collect_files_recursive(folder, &folder_name, &mut files)?;
-    Ok(files)
+    FolderWalk {
+        root: Some((root.to_path_buf(), folder_name)),
+        stack: Vec::new(),
+        options: options.clone(),
+        ignore: IgnoreRules::default(),
+    }
 }
 
-fn collect_files_recursive(
-    current_path: &std::path::Path,
-    relative_base: &str,
-    files: &mut Vec<FileInfo>,
-) -> std::io::Result<()> {
-    for entry in std::fs::read_dir(current_path)? {
-        let entry = entry?;
-        let path = entry.path();
-        let file_name = entry.file_name().to_string_lossy().to_string();
-        
-        // Формируем относительный путь
-        let relative_path = if relative_base.is_empty() {
-            file_name.clone()
-        } else {
-            format!("{}/{}", relative_base, file_name)
-        };
+/// Обход в глубину: в памяти только открытые папки текущей ветки
+struct FolderWalk {
+    /// Корень, который ещё не открыт
+    root: Option<(std::path::PathBuf, String)>,
+    /// Открытые папки и их относительные пути
+    stack: Vec<(std::fs::ReadDir, String)>,
+    options: CollectOptions,
+    /// Правила `.toolzaignore` и `ignore_patterns`, читаются при открытии корня
+    ignore: IgnoreRules,
+}
+
+impl Iterator for FolderWalk {
+    type Item = std::io::Result<FileInfo>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((root, name)) = self.root.take() {
+            self.ignore = match IgnoreRules::load(&root) {
+                Ok(rules) => rules,
+                Err(e) => return Some(Err(e)),
+            };
+            self.ignore.extend(IgnoreRules::from_patterns(&self.options.ignore_patterns));
+            match std::fs::read_dir(&root) {
+                Ok(entries) => self.stack.push((entries, name)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
         
-        if path.is_dir() {
-            // Рекурсивно обходим подпапки
-            collect_files_recursive(&path, &relative_path, files)?;
-        } else if path.is_file() {
-            // Добавляем файл
-            if let Ok(info) = FileInfo::with_relative_path(path, relative_path) {
-                files.push(info);
+        loop {
+            let (entries, relative_base) = self.stack.last_mut()?;
+            let entry = match entries.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            
+            if !self.options.include_hidden && is_hidden(&path) {
+                continue;
+            }
+            
+            // Формируем относительный путь
+            let relative_path = if relative_base.is_empty() {
+                file_name
+            } else {
+                format!("{}/{}", relative_base, file_name)
+            };
+            
+            // Путь от корня папки - без её имени в начале
+            let is_dir = path.is_dir();
+            let inside_root = relative_path.split_once('/').map_or("", |(_, rest)| rest);
+            if self.ignore.is_ignored(inside_root, is_dir) {
+                continue;
+            }
+            
+            if is_dir {
+                // Спускаемся в подпапку
+                match std::fs::read_dir(&path) {
+                    Ok(entries) => self.stack.push((entries, relative_path)),
+                    Err(e) => return Some(Err(e)),
+                }
+            } else if path.is_file() {
+                if let Ok(info) = FileInfo::with_relative_path(path, relative_path) {
+                    return Some(Ok(info));
+                }
             }
         }
     }
-    Ok(())
 }
 
 #[cfg(test)]
@@ -216,8 +791,198 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
     
+    #[test]
+    fn test_set_remote_name() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("local.txt");
+        std::fs::write(&path, b"x").unwrap();
+        let mut file = FileInfo::new(path).unwrap();
+        
+        file.set_remote_name("report.txt").unwrap();
+        assert_eq!(file.relative_path, "report.txt");
+        
+        // Подпуть очищается от абсолютного начала, `..` и обратных слэшей
+        file.set_remote_name("/../docs\\./2024/report.txt").unwrap();
+        assert_eq!(file.relative_path, "docs/2024/report.txt");
+        
+        assert!(file.set_remote_name("../").is_err());
+        assert!(file.set_remote_name("  ").is_err());
+        assert_eq!(file.relative_path, "docs/2024/report.txt");
+    }
+    
+    // === Тесты имён не в UTF-8 ===
+    
+    #[test]
+    fn test_wtf8_roundtrip() {
+        let wide: Vec<u16> = "файл 🚀".encode_utf16().chain([0xD800, 0x61]).collect();
+        let wtf8 = wide_to_wtf8(&wide);
+        assert_eq!(&wtf8[..wtf8.len() - 4], "файл 🚀".as_bytes());
+        assert_eq!(wtf8_to_wide(&wtf8), Some(wide));
+        
+        // Latin-1 байты с Linux - не WTF-8
+        assert_eq!(wtf8_to_wide(b"caf\xe9"), None);
+    }
+    
+    #[test]
+    fn test_relative_path_from_wire_rejects_escape() {
+        assert_eq!(relative_path_from_wire(b"a/b.txt"), Some(std::path::PathBuf::from("a").join("b.txt")));
+        assert_eq!(relative_path_from_wire(b"../etc/passwd"), None);
+        assert_eq!(relative_path_from_wire(b"/etc/passwd"), None);
+        assert_eq!(relative_path_from_wire(b""), None);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_raw_relative_path_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = TempDir::new().unwrap();
+        let folder = dir.path().join("data");
+        std::fs::create_dir(&folder).unwrap();
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        std::fs::write(folder.join(name), b"x").unwrap();
+        std::fs::write(folder.join("plain.txt"), b"y").unwrap();
+        
+        let mut files = collect_files_from_folder(&folder).unwrap();
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        
+        assert_eq!(files[0].relative_path, "data/caf\u{FFFD}.txt");
+        assert_eq!(files[0].raw_relative_path(), Some(b"data/caf\xe9.txt".to_vec()));
+        assert_eq!(files[1].raw_relative_path(), None);
+        
+        let restored = relative_path_from_wire(&files[0].raw_relative_path().unwrap()).unwrap();
+        assert_eq!(restored, std::path::Path::new("data").join(name));
+        
+        // Переименованный файл передаётся под новым именем
+        files[0].relative_path = "renamed.txt".to_string();
+        assert_eq!(files[0].raw_relative_path(), None);
+    }
+    
     // === Тесты Message ===
     
+    #[test]
+    fn test_packed_list_roundtrip() {
+        let files: Vec<SyncFileEntry> = (0..100_000)
+            .map(|i| SyncFileEntry {
+                relative_path: format!("project/src/module_{}/file_{}.rs", i / 100, i),
+                size: i as u64 * 17,
+                quick_hash: i as u64,
+            })
+            .collect();
+        let msg = Message::SyncFileList { files };
+        
+        let plain = msg.to_bytes().unwrap();
+        let packed = msg.to_bytes_packed(LIST_PACK_THRESHOLD).unwrap();
+        assert!(packed.len() < plain.len() / 2);
+        let len = u32::from_le_bytes([packed[0], packed[1], packed[2], packed[3]]) as usize;
+        assert_eq!(len, packed.len() - 4);
+        assert!(Message::is_packed(&packed[4..]));
+        assert!(!Message::is_packed(&plain[4..]));
+        
+        match Message::from_bytes(&packed[4..]).unwrap() {
+            Message::SyncFileList { files } => {
+                assert_eq!(files.len(), 100_000);
+                assert_eq!(files[12_345].relative_path, "project/src/module_123/file_12345.rs");
+                assert_eq!(files[12_345].size, 12_345 * 17);
+                assert_eq!(files[99_999].quick_hash, 99_999);
+            }
+            _ => panic!("Неверный тип сообщения"),
+        }
+    }
+    
+    #[test]
+    fn test_packed_only_large_lists() {
+        // Маленький список и не-списки идут как обычно
+        let small = Message::ResumeProbeResult { offsets: vec![0; 10] };
+        assert_eq!(small.to_bytes_packed(LIST_PACK_THRESHOLD).unwrap(), small.to_bytes().unwrap());
+        let chunk = Message::FileChunk { data: vec![0; 1024 * 1024], original_size: 0 };
+        assert_eq!(chunk.to_bytes_packed(LIST_PACK_THRESHOLD).unwrap(), chunk.to_bytes().unwrap());
+        
+        // Размер в префиксе больше предела - ошибка, а не выделение памяти
+        let mut data = (u32::MAX).to_le_bytes().to_vec();
+        data.extend_from_slice(&[0; 16]);
+        let bomb = Message::Packed { data }.to_bytes().unwrap();
+        assert!(Message::from_bytes(&bomb[4..]).is_err());
+    }
+    
+    /// Эталонные байты: любое изменение раскладки - несовместимость с другими версиями
+    #[test]
+    fn test_wire_format_golden() {
+        let file_start = Message::FileStart {
+            filename: "a.txt".to_string(),
+            size: 1024,
+            compressed: true,
+            offset: 0,
+            quick_hash: 0x0102030405060708,
+        };
+        let expected: Vec<u8> = [
+            &[42, 0, 0, 0][..],             // длина сообщения
+            &[0, 0, 0, 0],                  // вариант 0: FileStart
+            &[5, 0, 0, 0, 0, 0, 0, 0],      // длина имени
+            b"a.txt",
+            &[0, 4, 0, 0, 0, 0, 0, 0],      // size = 1024
+            &[1],                           // compressed
+            &[0, 0, 0, 0, 0, 0, 0, 0],      // offset
+            &[8, 7, 6, 5, 4, 3, 2, 1],      // quick_hash
+        ].concat();
+        assert_eq!(file_start.to_bytes().unwrap(), expected);
+        
+        assert_eq!(Message::FileEnd.to_bytes().unwrap(), [4, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(Message::Ack.to_bytes().unwrap(), [4, 0, 0, 0, 3, 0, 0, 0]);
+        // Признак сжатого списка - номер варианта Packed
+        assert!(Message::is_packed(&Message::Packed { data: Vec::new() }.to_bytes().unwrap()[4..]));
+        // Кодек: вариант 26, внутри Zstd (1) и уровень
+        let codec = Message::FileCodec { codec: crate::network::CompressionCodec::Zstd { level: 19 } };
+        assert_eq!(codec.to_bytes().unwrap(), [12, 0, 0, 0, 26, 0, 0, 0, 1, 0, 0, 0, 19, 0, 0, 0]);
+        
+        // И обратно: эталон читается текущей версией
+        assert!(matches!(
+            Message::from_bytes(&expected[4..]).unwrap(),
+            Message::FileStart { size: 1024, quick_hash: 0x0102030405060708, .. }
+        ));
+    }
+    
+    /// Байты первой версии протокола: их шлёт и ждёт старая сторона.
+    /// Записаны вручную, а не получены из текущего кода
+    #[test]
+    fn test_baseline_wire_format() {
+        let baseline: Vec<(Message, Vec<u8>)> = vec![
+            (
+                Message::FileStart { filename: "a".to_string(), size: 7, compressed: false, offset: 3, quick_hash: 9 },
+                [&[0, 0, 0, 0][..], &[1, 0, 0, 0, 0, 0, 0, 0], b"a", &[7, 0, 0, 0, 0, 0, 0, 0], &[0],
+                    &[3, 0, 0, 0, 0, 0, 0, 0], &[9, 0, 0, 0, 0, 0, 0, 0]].concat(),
+            ),
+            (
+                Message::FileChunk { data: vec![1, 2, 3], original_size: 5 },
+                [&[1, 0, 0, 0][..], &[3, 0, 0, 0, 0, 0, 0, 0], &[1, 2, 3], &[5, 0, 0, 0, 0, 0, 0, 0]].concat(),
+            ),
+            (Message::FileEnd, vec![2, 0, 0, 0]),
+            (Message::Ack, vec![3, 0, 0, 0]),
+            (Message::ResumeAck { offset: 500 }, vec![4, 0, 0, 0, 0xf4, 1, 0, 0, 0, 0, 0, 0]),
+            (
+                Message::Error("no".to_string()),
+                [&[5, 0, 0, 0][..], &[2, 0, 0, 0, 0, 0, 0, 0], b"no"].concat(),
+            ),
+            (Message::Done, vec![6, 0, 0, 0]),
+            (Message::Cancel, vec![7, 0, 0, 0]),
+            (Message::SyncRequest, vec![8, 0, 0, 0]),
+            (
+                Message::SyncFileList { files: vec![SyncFileEntry { relative_path: "a".to_string(), size: 7, quick_hash: 9 }] },
+                [&[9, 0, 0, 0][..], &[1, 0, 0, 0, 0, 0, 0, 0], &[1, 0, 0, 0, 0, 0, 0, 0], b"a",
+                    &[7, 0, 0, 0, 0, 0, 0, 0], &[9, 0, 0, 0, 0, 0, 0, 0]].concat(),
+            ),
+            (Message::SpeedTestRequest { size: 1 }, vec![10, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]),
+            (Message::SpeedTestReady, vec![11, 0, 0, 0]),
+            (Message::SpeedTestEnd, vec![13, 0, 0, 0]),
+        ];
+        for (msg, bytes) in baseline {
+            // Старая сторона читает то, что шлёт текущая версия...
+            assert_eq!(msg.to_bytes().unwrap()[4..], bytes[..], "{:?}", msg);
+            // ...а текущая - то, что шлёт старая
+            let decoded = Message::from_bytes(&bytes).unwrap_or_else(|e| panic!("{:?}: {}", msg, e));
+            assert_eq!(bincode::serialize(&decoded).unwrap(), bincode::serialize(&msg).unwrap());
+        }
+    }
+    
     #[test]
     fn test_message_file_start_serialization() {
         let msg = Message::FileStart {
@@ -309,6 +1074,60 @@ mod tests {
         assert!(matches!(decoded, Message::Done));
     }
     
+    #[test]
+    fn test_message_file_link_serialization() {
+        let msg = Message::FileLink {
+            from: "a/big.iso".to_string(),
+            to: "b/copy.iso".to_string(),
+        };
+        let bytes = msg.to_bytes().unwrap();
+        let decoded = Message::from_bytes(&bytes[4..]).unwrap();
+        
+        match decoded {
+            Message::FileLink { from, to } => {
+                assert_eq!(from, "a/big.iso");
+                assert_eq!(to, "b/copy.iso");
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+    
+    #[test]
+    fn test_message_delete_files_serialization() {
+        let msg = Message::DeleteFiles {
+            paths: vec!["docs/old.txt".to_string(), "stale.bin".to_string()],
+        };
+        let bytes = msg.to_bytes().unwrap();
+        let decoded = Message::from_bytes(&bytes[4..]).unwrap();
+        
+        match decoded {
+            Message::DeleteFiles { paths } => assert_eq!(paths, vec!["docs/old.txt", "stale.bin"]),
+            _ => panic!("Wrong message type"),
+        }
+    }
+    
+    #[test]
+    fn test_message_sync_roots_serialization() {
+        let msg = Message::SyncRoots { roots: vec!["docs".to_string(), "notes.txt".to_string()] };
+        let bytes = msg.to_bytes().unwrap();
+        
+        match Message::from_bytes(&bytes[4..]).unwrap() {
+            Message::SyncRoots { roots } => assert_eq!(roots, vec!["docs", "notes.txt"]),
+            _ => panic!("Wrong message type"),
+        }
+    }
+    
+    #[test]
+    fn test_message_extract_manifest_serialization() {
+        let entry = ManifestEntry { path: "dir/a.txt".to_string(), size: 5, mtime: 1_700_000_000 };
+        let bytes = Message::ExtractManifest { entries: vec![entry.clone()] }.to_bytes().unwrap();
+        
+        match Message::from_bytes(&bytes[4..]).unwrap() {
+            Message::ExtractManifest { entries } => assert_eq!(entries, vec![entry]),
+            _ => panic!("Wrong message type"),
+        }
+    }
+    
     #[test]
     fn test_sync_file_list_serialization() {
         let msg = Message::SyncFileList {
@@ -447,5 +1266,88 @@ mod tests {
         let files = collect_files_from_folder(dir.path()).unwrap();
         assert!(files.is_empty());
     }
+    
+    #[test]
+    fn test_collect_files_hidden_option() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".env"), "SECRET=1").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(".git").join("HEAD"), "ref").unwrap();
+        
+        // По умолчанию скрытые файлы включены (как раньше)
+        let files = collect_files_from_folder(dir.path()).unwrap();
+        assert_eq!(files.len(), 3);
+        
+        let options = CollectOptions { include_hidden: false, ..Default::default() };
+        let files = collect_files_from_folder_with_options(dir.path(), &options).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "main.rs");
+    }
+    
+    #[test]
+    fn test_collect_files_toolzaignore() {
+        let dir = TempDir::new().unwrap();
+        for sub in ["target/debug", "node_modules/pkg", "src", "logs"] {
+            std::fs::create_dir_all(dir.path().join(sub)).unwrap();
+        }
+        std::fs::write(dir.path().join("target/debug/app"), "bin").unwrap();
+        std::fs::write(dir.path().join("node_modules/pkg/index.js"), "js").unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("logs/run.log"), "log").unwrap();
+        std::fs::write(dir.path().join("build.log"), "log").unwrap();
+        std::fs::write(dir.path().join(".toolzaignore"), "target/\nnode_modules/\n*.log\n").unwrap();
+        
+        let names = |files: Vec<FileInfo>| {
+            let root = dir.path().file_name().unwrap().to_string_lossy().to_string();
+            let mut names: Vec<String> = files
+                .into_iter()
+                .map(|f| f.relative_path.strip_prefix(&format!("{}/", root)).unwrap().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        
+        let files = collect_files_from_folder(dir.path()).unwrap();
+        assert_eq!(names(files), vec![".toolzaignore", "src/main.rs"]);
+        
+        // Явные шаблоны добавляются к файлу, `!` возвращает исключённое им
+        let files = collect_files_from_folder_with_ignore(
+            dir.path(),
+            vec!["src/".to_string(), "!build.log".to_string()],
+        ).unwrap();
+        assert_eq!(names(files), vec![".toolzaignore", "build.log"]);
+    }
+    
+    #[test]
+    fn test_walk_folder_matches_collect() {
+        let dir = TempDir::new().unwrap();
+        let deep = dir.path().join("a").join("b");
+        std::fs::create_dir_all(&deep).unwrap();
+        std::fs::write(dir.path().join("root.txt"), "root").unwrap();
+        std::fs::write(dir.path().join("a").join("mid.txt"), "mid").unwrap();
+        std::fs::write(deep.join("leaf.txt"), "leaf").unwrap();
+        
+        let walked: Vec<String> = walk_folder(dir.path())
+            .map(|f| f.unwrap().relative_path)
+            .collect();
+        let collected: Vec<String> = collect_files_from_folder(dir.path()).unwrap()
+            .into_iter()
+            .map(|f| f.relative_path)
+            .collect();
+        assert_eq!(walked, collected);
+        assert_eq!(walked.len(), 3);
+        
+        // Отсутствующая папка - ошибка первым же элементом
+        let mut missing = walk_folder(&dir.path().join("missing"));
+        assert!(missing.next().unwrap().is_err());
+        assert!(missing.next().is_none());
+    }
+    
+    #[test]
+    fn test_is_hidden() {
+        assert!(is_hidden(std::path::Path::new("/tmp/.env")));
+        assert!(!is_hidden(std::path::Path::new("/tmp/main.rs")));
+    }
 }
 
@@ -0,0 +1,109 @@
+//! Настройки интерфейса - сохранение и загрузка
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Частота кадров по умолчанию во время передачи
+pub const DEFAULT_MAX_FPS: u32 = 30;
+/// Варианты ограничения частоты кадров для выбора в интерфейсе
+pub const MAX_FPS_CHOICES: [u32; 4] = [10, 30, 60, 120];
+
+/// Сохраняемые настройки GUI
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Компактный режим: без боковой панели и лога
+    pub compact_mode: bool,
+    /// Максимум кадров в секунду, пока приходят события передачи
+    pub max_fps: u32,
+    /// Звук по завершении передачи или распаковки
+    pub completion_sound: bool,
+    /// Свой файл звука (None - встроенный)
+    pub completion_sound_path: Option<PathBuf>,
+    /// Дневной лимит отправки в байтах (None - без лимита)
+    pub daily_quota: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            compact_mode: false,
+            max_fps: DEFAULT_MAX_FPS,
+            completion_sound: false,
+            completion_sound_path: None,
+            daily_quota: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Минимальный интервал между кадрами (0 в настройках считается за 1 FPS)
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.max_fps.max(1)
+    }
+    
+    /// Загрузить настройки из файла (при ошибке - значения по умолчанию)
+    pub fn load() -> Self {
+        fs::read_to_string(settings_file_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+    
+    /// Сохранить настройки в файл
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = settings_file_path();
+        
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(&path, json)
+    }
+}
+
+/// Получить путь к файлу настроек
+fn settings_file_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("toolza_sender")
+        .join("settings.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_settings_missing_fields_use_defaults() {
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings, Settings::default());
+        assert!(!settings.completion_sound);
+    }
+    
+    #[test]
+    fn test_settings_serialization() {
+        let settings = Settings {
+            compact_mode: true,
+            max_fps: 60,
+            completion_sound: true,
+            completion_sound_path: Some(PathBuf::from("/tmp/done.ogg")),
+            daily_quota: Some(5 * 1024 * 1024 * 1024),
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        let restored: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, settings);
+    }
+    
+    #[test]
+    fn test_frame_interval() {
+        let settings = Settings::default();
+        assert_eq!(settings.frame_interval(), Duration::from_secs(1) / 30);
+        
+        let broken = Settings { max_fps: 0, ..Settings::default() };
+        assert_eq!(broken.frame_interval(), Duration::from_secs(1));
+    }
+}
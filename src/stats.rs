@@ -1,11 +1,26 @@
 //! Статистика передачи - скорость, ETA, сжатие
 
+use crate::i18n::Language;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Размер окна для расчёта скорости (последние N измерений)
 const SPEED_WINDOW_SIZE: usize = 10;
 
+/// Постоянная времени сглаживания скорости для ETA (EWMA), секунды
+const SPEED_SMOOTHING_SECS: f64 = 5.0;
+
+/// На сколько ETA может отойти от естественного отсчёта за секунду:
+/// доля от текущего ETA, но не меньше секунды
+const ETA_MAX_DRIFT_PER_SEC: f64 = 0.1;
+
+/// Интервал точек ряда скорости для итоговой статистики
+const THROUGHPUT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Сколько точек ряда скорости хранить (сутки по секунде), дальше ряд не растёт
+const MAX_THROUGHPUT_SAMPLES: usize = 24 * 60 * 60;
+
 /// Минимальный размер чанка
 pub const MIN_CHUNK_SIZE: usize = 16 * 1024; // 16 KB
 
@@ -15,6 +30,9 @@ pub const MAX_CHUNK_SIZE: usize = 512 * 1024; // 512 KB
 /// Начальный размер чанка
 pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024; // 64 KB
 
+/// Максимальный фиксированный размер чанка (без адаптации, для быстрых стабильных сетей)
+pub const MAX_FIXED_CHUNK_SIZE: usize = 16 * 1024 * 1024; // 16 MB
+
 /// Статистика передачи
 #[derive(Clone, Debug)]
 pub struct TransferStats {
@@ -30,6 +48,14 @@ pub struct TransferStats {
     pub bytes_after_compression: u64,
     /// История скорости для сглаживания
     speed_samples: VecDeque<(Instant, u64)>,
+    /// Экспоненциально сглаженная скорость (байт/сек) для ETA
+    smoothed_speed: Option<f64>,
+    /// Показываемый ETA (секунды) и момент его расчёта
+    displayed_eta: Option<(Instant, f64)>,
+    /// Скорость по интервалам `THROUGHPUT_SAMPLE_INTERVAL` за всю передачу (байт/сек)
+    throughput_samples: Vec<f64>,
+    /// Начало текущего интервала ряда скорости и переданные к нему байты
+    throughput_interval: Option<(Instant, u64)>,
     /// Текущий адаптивный размер чанка
     pub current_chunk_size: usize,
     /// Количество переданных файлов
@@ -48,6 +74,10 @@ impl TransferStats {
             bytes_before_compression: 0,
             bytes_after_compression: 0,
             speed_samples: VecDeque::with_capacity(SPEED_WINDOW_SIZE + 1),
+            smoothed_speed: None,
+            displayed_eta: None,
+            throughput_samples: Vec::new(),
+            throughput_interval: None,
             current_chunk_size: DEFAULT_CHUNK_SIZE,
             files_completed: 0,
             files_total,
@@ -56,12 +86,27 @@ impl TransferStats {
     
     /// Обновить прогресс передачи
     pub fn update(&mut self, bytes_transferred: u64, bytes_original: u64, bytes_compressed: u64) {
+        self.update_at(Instant::now(), bytes_transferred, bytes_original, bytes_compressed);
+    }
+    
+    fn update_at(&mut self, now: Instant, bytes_transferred: u64, bytes_original: u64, bytes_compressed: u64) {
         self.transferred_bytes = bytes_transferred;
         self.bytes_before_compression += bytes_original;
         self.bytes_after_compression += bytes_compressed;
         
+        // Сглаженная скорость и ETA - по приросту с прошлой точки
+        if let Some(&(last_time, last_bytes)) = self.speed_samples.back() {
+            let dt = now.duration_since(last_time).as_secs_f64();
+            if dt >= 0.001 {
+                let speed = bytes_transferred.saturating_sub(last_bytes) as f64 / dt;
+                self.update_smoothed_speed(speed, dt);
+                self.update_eta(now);
+            }
+        }
+        
+        self.record_throughput(now, bytes_transferred);
+        
         // Добавляем точку в историю скорости
-        let now = Instant::now();
         self.speed_samples.push_back((now, bytes_transferred));
         
         // Ограничиваем размер окна
@@ -91,32 +136,135 @@ impl TransferStats {
         bytes_diff as f64 / duration
     }
     
+    /// Сглаженная скорость (байт/сек): EWMA с постоянной времени в несколько
+    /// секунд. Для ETA; живое значение - `speed_bytes_per_sec`
+    pub fn smoothed_speed(&self) -> f64 {
+        self.smoothed_speed.unwrap_or(0.0)
+    }
+    
+    fn update_smoothed_speed(&mut self, speed: f64, dt: f64) {
+        // Вес новой точки зависит от интервала: частые обновления не ускоряют реакцию
+        let alpha = 1.0 - (-dt / SPEED_SMOOTHING_SECS).exp();
+        self.smoothed_speed = Some(match self.smoothed_speed {
+            Some(previous) => previous + alpha * (speed - previous),
+            None => speed,
+        });
+    }
+    
+    /// Пересчитать ETA: он идёт как обратный отсчёт и отклоняется от него
+    /// не быстрее `ETA_MAX_DRIFT_PER_SEC`, поэтому не скачет на неровном канале
+    fn update_eta(&mut self, now: Instant) {
+        let speed = self.smoothed_speed();
+        let remaining = self.total_bytes.saturating_sub(self.transferred_bytes);
+        if remaining == 0 {
+            self.displayed_eta = Some((now, 0.0));
+            return;
+        }
+        if speed < 1.0 {
+            self.displayed_eta = None;
+            return;
+        }
+        
+        let target = remaining as f64 / speed;
+        let eta = match self.displayed_eta {
+            Some((time, previous)) => {
+                let dt = now.duration_since(time).as_secs_f64();
+                let expected = (previous - dt).max(0.0);
+                let limit = dt * (expected * ETA_MAX_DRIFT_PER_SEC).max(1.0);
+                expected + (target - expected).clamp(-limit, limit)
+            }
+            None => target,
+        };
+        self.displayed_eta = Some((now, eta));
+    }
+    
+    /// Закрыть интервал ряда скорости, если он набрался
+    fn record_throughput(&mut self, now: Instant, bytes_transferred: u64) {
+        let Some((start, start_bytes)) = self.throughput_interval else {
+            self.throughput_interval = Some((now, bytes_transferred));
+            return;
+        };
+        let dt = now.duration_since(start);
+        if dt < THROUGHPUT_SAMPLE_INTERVAL {
+            return;
+        }
+        if self.throughput_samples.len() < MAX_THROUGHPUT_SAMPLES {
+            let speed = bytes_transferred.saturating_sub(start_bytes) as f64 / dt.as_secs_f64();
+            self.throughput_samples.push(speed);
+        }
+        self.throughput_interval = Some((now, bytes_transferred));
+    }
+    
+    /// Ряд скорости за всю передачу: байт/сек по секундным интервалам
+    pub fn throughput_samples(&self) -> &[f64] {
+        &self.throughput_samples
+    }
+    
+    /// Перцентиль скорости по ряду (`percentile` от 0 до 100, ближайший ранг).
+    /// None - передача короче одного интервала
+    pub fn throughput_percentile(&self, percentile: f64) -> Option<f64> {
+        percentile_of(&self.throughput_samples, percentile)
+    }
+    
+    /// Итоги по ряду скорости для сводки и истории
+    pub fn throughput_summary(&self) -> Option<ThroughputSummary> {
+        Some(ThroughputSummary {
+            p50: self.throughput_percentile(50.0)?,
+            p95: self.throughput_percentile(95.0)?,
+            min: self.throughput_percentile(0.0)?,
+            max: self.throughput_percentile(100.0)?,
+        })
+    }
+    
     /// Получить скорость в удобном формате
     pub fn speed_formatted(&self) -> String {
         format_speed(self.speed_bytes_per_sec())
     }
     
-    /// Получить оставшееся время (ETA)
+    /// Скорость с подписями на заданном языке
+    pub fn speed_formatted_in(&self, language: Language) -> String {
+        format_speed_with(self.speed_bytes_per_sec(), language)
+    }
+    
+    /// Получить оставшееся время (ETA) по сглаженной скорости
     pub fn eta(&self) -> Option<Duration> {
-        let speed = self.speed_bytes_per_sec();
-        if speed < 1.0 {
-            return None;
-        }
-        
-        let remaining = self.total_bytes.saturating_sub(self.transferred_bytes);
-        let seconds = remaining as f64 / speed;
-        
-        Some(Duration::from_secs_f64(seconds))
+        self.displayed_eta.map(|(_, seconds)| Duration::from_secs_f64(seconds))
     }
     
     /// Получить ETA в удобном формате
     pub fn eta_formatted(&self) -> String {
+        self.eta_formatted_in(Language::Russian)
+    }
+    
+    /// ETA с подписями на заданном языке
+    pub fn eta_formatted_in(&self, language: Language) -> String {
         match self.eta() {
-            Some(duration) => format_duration(duration),
+            Some(duration) => format_duration_with(duration, language),
             None => "∞".to_string(),
         }
     }
     
+    /// Ожидаемый момент завершения. Считается от момента расчёта ETA,
+    /// поэтому между обновлениями не сдвигается
+    pub fn eta_clock_time(&self) -> Option<SystemTime> {
+        let (computed_at, seconds) = self.displayed_eta?;
+        let computed_at = SystemTime::now().checked_sub(computed_at.elapsed())?;
+        Some(computed_at + Duration::from_secs_f64(seconds))
+    }
+    
+    /// Время завершения и оставшийся срок: "14:32 UTC (через 2ч 10м)"
+    pub fn eta_clock_formatted_in(&self, language: Language) -> String {
+        match (self.eta_clock_time(), self.eta()) {
+            (Some(clock), Some(eta)) => format!(
+                "{} ({} {})",
+                format_clock_time_with(clock, SystemTime::now(), language),
+                language.units().in_time,
+                format_duration_with(eta, language),
+            ),
+            _ => "∞".to_string(),
+        }
+    }
+    
     /// Получить прошедшее время
     pub fn elapsed(&self) -> Duration {
         self.start_time.elapsed()
@@ -127,6 +275,11 @@ impl TransferStats {
         format_duration(self.elapsed())
     }
     
+    /// Прошедшее время с подписями на заданном языке
+    pub fn elapsed_formatted_in(&self, language: Language) -> String {
+        format_duration_with(self.elapsed(), language)
+    }
+    
     /// Получить процент завершения
     pub fn progress_percent(&self) -> f32 {
         if self.total_bytes == 0 {
@@ -186,6 +339,48 @@ impl TransferStats {
     }
 }
 
+/// Итоги скорости передачи: перцентили и крайние значения (байт/сек)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ThroughputSummary {
+    pub p50: f64,
+    pub p95: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ThroughputSummary {
+    /// Строка для лога и истории: "p50 10 MB/s, p95 12 MB/s, мин 1 MB/s, макс 14 MB/s"
+    pub fn formatted_in(&self, language: Language) -> String {
+        let (min_label, max_label) = match language {
+            Language::Russian => ("мин", "макс"),
+            Language::Ukrainian => ("мін", "макс"),
+            Language::English => ("min", "max"),
+            Language::German => ("min", "max"),
+            Language::French => ("min", "max"),
+        };
+        format!(
+            "p50 {}, p95 {}, {} {}, {} {}",
+            format_speed_with(self.p50, language),
+            format_speed_with(self.p95, language),
+            min_label,
+            format_speed_with(self.min, language),
+            max_label,
+            format_speed_with(self.max, language),
+        )
+    }
+}
+
+/// Перцентиль выборки методом ближайшего ранга (None - выборка пуста)
+fn percentile_of(samples: &[f64], percentile: f64) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.saturating_sub(1)])
+}
+
 impl Default for TransferStats {
     fn default() -> Self {
         Self::new(0, 0)
@@ -194,26 +389,56 @@ impl Default for TransferStats {
 
 /// Форматировать скорость
 pub fn format_speed(bytes_per_sec: f64) -> String {
+    format_speed_with(bytes_per_sec, Language::English)
+}
+
+/// Форматировать скорость с подписями единиц на заданном языке
+pub fn format_speed_with(bytes_per_sec: f64, language: Language) -> String {
+    let units = language.units();
+    let [b, kb, mb, gb] = units.bytes;
+    let per = units.per_second;
     if bytes_per_sec < 1024.0 {
-        format!("{:.0} B/s", bytes_per_sec)
+        format!("{:.0} {}{}", bytes_per_sec, b, per)
     } else if bytes_per_sec < 1024.0 * 1024.0 {
-        format!("{:.1} KB/s", bytes_per_sec / 1024.0)
+        format!("{:.1} {}{}", bytes_per_sec / 1024.0, kb, per)
     } else if bytes_per_sec < 1024.0 * 1024.0 * 1024.0 {
-        format!("{:.1} MB/s", bytes_per_sec / 1024.0 / 1024.0)
+        format!("{:.1} {}{}", bytes_per_sec / 1024.0 / 1024.0, mb, per)
     } else {
-        format!("{:.2} GB/s", bytes_per_sec / 1024.0 / 1024.0 / 1024.0)
+        format!("{:.2} {}{}", bytes_per_sec / 1024.0 / 1024.0 / 1024.0, gb, per)
+    }
+}
+
+/// Время суток (UTC) момента `time`; если это не сегодня (относительно `now`) - с датой
+pub fn format_clock_time_with(time: SystemTime, now: SystemTime, language: Language) -> String {
+    let secs = |t: SystemTime| t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (time, now) = (secs(time), secs(now));
+    let clock = format!("{:02}:{:02} UTC", time % 86400 / 3600, time % 3600 / 60);
+    if time / 86400 == now / 86400 {
+        return clock;
+    }
+    let (_, month, day) = crate::history::civil_date(time);
+    if language.units().day_first {
+        format!("{:02}.{:02} {}", day, month, clock)
+    } else {
+        format!("{:02}-{:02} {}", month, day, clock)
     }
 }
 
 /// Форматировать длительность
 pub fn format_duration(duration: Duration) -> String {
+    format_duration_with(duration, Language::Russian)
+}
+
+/// Форматировать длительность с подписями единиц на заданном языке
+pub fn format_duration_with(duration: Duration, language: Language) -> String {
+    let units = language.units();
     let secs = duration.as_secs();
     if secs < 60 {
-        format!("{}с", secs)
+        format!("{}{}", secs, units.seconds)
     } else if secs < 3600 {
-        format!("{}м {}с", secs / 60, secs % 60)
+        format!("{}{} {}{}", secs / 60, units.minutes, secs % 60, units.seconds)
     } else {
-        format!("{}ч {}м", secs / 3600, (secs % 3600) / 60)
+        format!("{}{} {}{}", secs / 3600, units.hours, (secs % 3600) / 60, units.minutes)
     }
 }
 
@@ -250,6 +475,13 @@ mod tests {
         assert_eq!(format_speed(2.5 * 1024.0 * 1024.0 * 1024.0), "2.50 GB/s");
     }
     
+    #[test]
+    fn test_format_speed_with_languages() {
+        assert_eq!(format_speed_with(500.0, Language::English), "500 B/s");
+        assert_eq!(format_speed_with(1024.0 * 1024.0, Language::Russian), "1.0 МБ/с");
+        assert_eq!(format_speed_with(1536.0, Language::Ukrainian), "1.5 КБ/с");
+    }
+    
     // === Тесты format_duration ===
     
     #[test]
@@ -273,6 +505,16 @@ mod tests {
         assert_eq!(format_duration(Duration::from_secs(7200)), "2ч 0м");
     }
     
+    #[test]
+    fn test_format_duration_with_languages() {
+        let d = Duration::from_secs(3725);
+        assert_eq!(format_duration_with(d, Language::Russian), "1ч 2м");
+        assert_eq!(format_duration_with(d, Language::Ukrainian), "1г 2хв");
+        assert_eq!(format_duration_with(d, Language::English), "1h 2m");
+        assert_eq!(format_duration_with(Duration::from_secs(125), Language::English), "2m 5s");
+        assert_eq!(format_duration_with(Duration::from_secs(45), Language::Ukrainian), "45с");
+    }
+    
     // === Тесты TransferStats ===
     
     #[test]
@@ -367,6 +609,116 @@ mod tests {
         assert_eq!(stats.eta_formatted(), "∞");
     }
     
+    #[test]
+    fn test_eta_clock_time() {
+        let mut stats = TransferStats::new(1000, 1);
+        assert!(stats.eta_clock_time().is_none());
+        assert_eq!(stats.eta_clock_formatted_in(Language::English), "∞");
+        
+        let start = Instant::now() - Duration::from_secs(2);
+        stats.update_at(start, 0, 0, 0);
+        stats.update_at(start + Duration::from_secs(1), 100, 0, 0);
+        let eta = stats.eta().unwrap();
+        let clock = stats.eta_clock_time().unwrap();
+        
+        // Отсчёт от момента расчёта, а не от текущего вызова
+        let expected = SystemTime::now() - Duration::from_secs(1) + eta;
+        let diff = |a: SystemTime, b: SystemTime| a.duration_since(b).unwrap_or_else(|e| e.duration());
+        assert!(diff(clock, expected) < Duration::from_millis(200));
+        // Повторный вызов без обновления - тот же момент (с точностью до часов системы)
+        assert!(diff(stats.eta_clock_time().unwrap(), clock) < Duration::from_millis(1));
+        assert!(stats.eta_clock_formatted_in(Language::English).contains("UTC (in "));
+    }
+    
+    #[test]
+    fn test_format_clock_time() {
+        // 2024-03-05 14:32:10 UTC
+        let time = UNIX_EPOCH + Duration::from_secs(1709649130);
+        assert_eq!(format_clock_time_with(time, time - Duration::from_secs(3600), Language::Russian), "14:32 UTC");
+        let yesterday = time - Duration::from_secs(86400);
+        assert_eq!(format_clock_time_with(time, yesterday, Language::Russian), "05.03 14:32 UTC");
+        assert_eq!(format_clock_time_with(time, yesterday, Language::English), "03-05 14:32 UTC");
+    }
+    
+    #[test]
+    fn test_eta_stable_on_noisy_link() {
+        const MB: u64 = 1024 * 1024;
+        let total = 2000 * MB;
+        let mut stats = TransferStats::new(total, 1);
+        let start = Instant::now();
+        
+        // Неровный канал: в среднем ~10 МБ/с, отдельные секунды от 1 до 25 МБ/с
+        let pattern = [10, 1, 25, 4, 18, 2, 12, 8];
+        let mut transferred = 0;
+        let mut etas = Vec::new();
+        for second in 0..60u64 {
+            stats.update_at(start + Duration::from_secs(second), transferred, 0, 0);
+            if let Some(eta) = stats.eta() {
+                etas.push(eta.as_secs_f64());
+            }
+            transferred += pattern[second as usize % pattern.len()] * MB;
+        }
+        
+        // Мгновенная скорость скачет в 25 раз, ETA меняется плавно
+        for pair in etas.windows(2) {
+            let (previous, current) = (pair[0], pair[1]);
+            assert!((current - previous).abs() <= previous * ETA_MAX_DRIFT_PER_SEC + 1.0 + 1e-6);
+        }
+        
+        // И остаётся правдоподобным: осталось ~1500 МБ при ~10 МБ/с
+        let smoothed = stats.smoothed_speed() / MB as f64;
+        assert!((5.0..15.0).contains(&smoothed), "smoothed speed {}", smoothed);
+        let eta = stats.eta().unwrap().as_secs_f64();
+        assert!((75.0..300.0).contains(&eta), "eta {}", eta);
+    }
+    
+    #[test]
+    fn test_throughput_percentiles() {
+        const MB: u64 = 1024 * 1024;
+        let mut stats = TransferStats::new(0, 1);
+        let start = Instant::now();
+        assert_eq!(stats.throughput_summary(), None);
+        
+        // 20 секунд: 1..=20 МБ/с в перемешанном порядке
+        let speeds = [7, 3, 15, 1, 20, 11, 9, 4, 18, 2, 13, 6, 17, 5, 10, 19, 8, 14, 12, 16];
+        let mut transferred = 0;
+        stats.update_at(start, 0, 0, 0);
+        for (second, speed) in speeds.iter().enumerate() {
+            transferred += speed * MB;
+            // Промежуточные точки не дают лишних интервалов
+            stats.update_at(start + Duration::from_millis(second as u64 * 1000 + 500), transferred - speed * MB / 2, 0, 0);
+            stats.update_at(start + Duration::from_secs(second as u64 + 1), transferred, 0, 0);
+        }
+        assert_eq!(stats.throughput_samples().len(), speeds.len());
+        
+        let mb = |value: Option<f64>| value.map(|v| (v / MB as f64).round() as u64);
+        assert_eq!(mb(stats.throughput_percentile(50.0)), Some(10));
+        assert_eq!(mb(stats.throughput_percentile(95.0)), Some(19));
+        
+        let summary = stats.throughput_summary().unwrap();
+        assert_eq!(mb(Some(summary.min)), Some(1));
+        assert_eq!(mb(Some(summary.max)), Some(20));
+        assert!(summary.formatted_in(Language::English).starts_with("p50 10"));
+    }
+    
+    #[test]
+    fn test_percentile_of() {
+        assert_eq!(percentile_of(&[], 50.0), None);
+        assert_eq!(percentile_of(&[5.0], 95.0), Some(5.0));
+        assert_eq!(percentile_of(&[4.0, 1.0, 3.0, 2.0], 0.0), Some(1.0));
+        assert_eq!(percentile_of(&[4.0, 1.0, 3.0, 2.0], 50.0), Some(2.0));
+        assert_eq!(percentile_of(&[4.0, 1.0, 3.0, 2.0], 100.0), Some(4.0));
+    }
+    
+    #[test]
+    fn test_eta_zero_when_done() {
+        let mut stats = TransferStats::new(1000, 1);
+        let start = Instant::now();
+        stats.update_at(start, 0, 0, 0);
+        stats.update_at(start + Duration::from_secs(1), 1000, 0, 0);
+        assert_eq!(stats.eta(), Some(Duration::ZERO));
+    }
+    
     // === Тесты констант ===
     
     #[test]
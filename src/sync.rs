@@ -1,13 +1,16 @@
 //! Режим синхронизации - передача только изменённых файлов
 
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{File, FileType};
 use std::io::{self, BufReader, Read};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 /// Размер блока для хэширования (4 KB)
 const HASH_BLOCK_SIZE: usize = 4 * 1024;
 
+/// Предел файлов и папок, которые получатель обходит ради `SyncFileList`
+pub const MAX_REMOTE_LISTING: usize = 1_000_000;
+
 /// Информация о файле для синхронизации
 #[derive(Clone, Debug)]
 pub struct SyncFileInfo {
@@ -48,6 +51,13 @@ impl SyncFileInfo {
             return true;
         }
         
+        // Два пустых файла одинаковы независимо от хэша: у пустого файла
+        // quick_hash равен 0, а другая сторона могла посчитать его иначе
+        if self.size == 0 {
+            return false;
+        }
+        
+        
         // Разные хэши - нужно обновлять
         if self.quick_hash != remote.quick_hash {
             return true;
@@ -197,6 +207,79 @@ fn collect_sync_info_recursive(
     Ok(())
 }
 
+/// Файлы папки получателя для `SyncFileList`: пути от `root` через `/`.
+///
+/// `roots` - только эти имена верхнего уровня (None - вся папка); имя с `/`,
+/// `..` или префиксом диска пропускается. Служебные файлы приёма (`*.toolza-part`,
+/// журналы распаковки) и символические ссылки не входят. Больше `max_entries`
+/// файлов и папок - ошибка
+pub fn collect_remote_listing(root: &Path, roots: Option<&[String]>, max_entries: usize) -> io::Result<Vec<SyncFileInfo>> {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    let mut seen = 0;
+    
+    match roots {
+        None => dirs.push((root.to_path_buf(), String::new())),
+        Some(roots) => {
+            for name in roots {
+                let mut components = Path::new(name).components();
+                let (Some(Component::Normal(_)), None) = (components.next(), components.next()) else {
+                    continue;
+                };
+                let path = root.join(name);
+                // Корня может и не быть: тогда у получателя под ним пусто
+                let Ok(meta) = std::fs::symlink_metadata(&path) else {
+                    continue;
+                };
+                seen += 1;
+                visit_listing_entry(path, name.clone(), meta.file_type(), &mut dirs, &mut files);
+            }
+        }
+    }
+    
+    while let Some((dir, relative_base)) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            seen += 1;
+            if seen > max_entries {
+                return Err(io::Error::other(format!("в папке больше {} файлов и папок", max_entries)));
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative = if relative_base.is_empty() {
+                name
+            } else {
+                format!("{}/{}", relative_base, name)
+            };
+            visit_listing_entry(entry.path(), relative, entry.file_type()?, &mut dirs, &mut files);
+        }
+    }
+    
+    Ok(files)
+}
+
+/// Папку - в очередь обхода, обычный файл - в список; ссылки не трогаем
+fn visit_listing_entry(
+    path: PathBuf,
+    relative: String,
+    file_type: FileType,
+    dirs: &mut Vec<(PathBuf, String)>,
+    files: &mut Vec<SyncFileInfo>,
+) {
+    if file_type.is_dir() {
+        dirs.push((path, relative));
+    } else if file_type.is_file() && !is_service_file(&path) {
+        if let Ok(info) = SyncFileInfo::from_path(&path, relative) {
+            files.push(info);
+        }
+    }
+}
+
+/// Служебный файл приёма: расширение `toolza-*`
+fn is_service_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.to_string_lossy().starts_with("toolza-"))
+}
+
 /// Сравнить локальные и удалённые файлы
 pub fn compute_sync_diff(
     local: &[SyncFileInfo],
@@ -305,6 +388,35 @@ mod tests {
         assert!(info.quick_hash != 0);
     }
     
+    #[test]
+    fn test_sync_empty_file() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("empty.txt");
+        std::fs::write(&file_path, "").unwrap();
+        
+        let info = SyncFileInfo::from_path(&file_path, "empty.txt".to_string()).unwrap();
+        assert_eq!(info.size, 0);
+        assert_eq!(info.quick_hash, 0);
+        
+        // Пустой файл на другой стороне - обновлять нечего, даже если хэш посчитан иначе
+        let remote_empty = RemoteFileInfo {
+            relative_path: "empty.txt".to_string(),
+            size: 0,
+            modified: 0,
+            quick_hash: 0xcbf29ce484222325,
+        };
+        assert!(!info.needs_update(&remote_empty));
+        
+        // Непустой файл на другой стороне заменяется пустым
+        let remote_full = RemoteFileInfo { size: 10, quick_hash: 0, ..remote_empty };
+        assert!(info.needs_update(&remote_full));
+        
+        // Отсутствующий пустой файл передаётся
+        let diff = compute_sync_diff(std::slice::from_ref(&info), &[]);
+        assert_eq!(diff.to_transfer.len(), 1);
+        assert_eq!(diff.transfer_size(), 0);
+    }
+    
     #[test]
     fn test_sync_file_info_needs_update_different_size() {
         let local = SyncFileInfo {
@@ -551,5 +663,60 @@ mod tests {
         assert_eq!(diff.remote_only.len(), 1);
         assert_eq!(diff.remote_only[0], "deleted.txt");
     }
+    
+    #[test]
+    fn test_collect_remote_listing_skips_service_files() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+        std::fs::write(dir.path().join("sub/b.txt"), b"bb").unwrap();
+        std::fs::write(dir.path().join("c.bin.toolza-part"), b"partial").unwrap();
+        
+        let mut paths: Vec<_> = collect_remote_listing(dir.path(), None, MAX_REMOTE_LISTING)
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.relative_path, f.size))
+            .collect();
+        paths.sort();
+        
+        assert_eq!(paths, vec![("a.txt".to_string(), 1), ("sub/b.txt".to_string(), 2)]);
+    }
+    
+    #[test]
+    fn test_collect_remote_listing_roots_and_limits() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("sub")).unwrap();
+        std::fs::create_dir_all(dir.path().join("other")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+        std::fs::write(dir.path().join("sub/b.txt"), b"bb").unwrap();
+        std::fs::write(dir.path().join("other/c.txt"), b"c").unwrap();
+        
+        let roots = ["sub".to_string(), "a.txt".to_string(), "../x".to_string(), "missing".to_string()];
+        let mut paths: Vec<_> = collect_remote_listing(dir.path(), Some(&roots), MAX_REMOTE_LISTING)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.relative_path)
+            .collect();
+        paths.sort();
+        assert_eq!(paths, vec!["a.txt", "sub/b.txt"]);
+        
+        assert!(collect_remote_listing(dir.path(), None, 3).is_err());
+        
+        // Ссылки (и на папки снаружи) в список не попадают
+        #[cfg(unix)]
+        {
+            let outside = TempDir::new().unwrap();
+            std::fs::write(outside.path().join("secret.txt"), b"s").unwrap();
+            std::os::unix::fs::symlink(outside.path(), dir.path().join("sub/link")).unwrap();
+            std::os::unix::fs::symlink(outside.path(), dir.path().join("linked")).unwrap();
+            let roots = ["sub".to_string(), "linked".to_string()];
+            let paths: Vec<_> = collect_remote_listing(dir.path(), Some(&roots), MAX_REMOTE_LISTING)
+                .unwrap()
+                .into_iter()
+                .map(|f| f.relative_path)
+                .collect();
+            assert_eq!(paths, vec!["sub/b.txt"]);
+        }
+    }
 }
 
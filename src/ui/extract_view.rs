@@ -1,7 +1,7 @@
 //! UI для локальной распаковки архивов
 
 use crate::app::App;
-use toolza_sender::utils::truncate_string;
+use toolza_sender::utils::{format_size, truncate_string};
 use eframe::egui;
 
 impl App {
@@ -34,8 +34,59 @@ impl App {
         
         ui.add_space(5.0);
         
-        if ui.button(t.select_archive).clicked() && !self.extract_running {
-            self.select_archive_dialog();
+        ui.horizontal(|ui| {
+            if ui.button(t.select_archive).clicked() && !self.extract_running {
+                self.select_archive_dialog();
+            }
+            
+            let can_list = self.extract_archive_path.is_some() && !self.extract_listing_running;
+            if ui.add_enabled(can_list, egui::Button::new(t.show_contents)).clicked() {
+                self.load_archive_listing();
+            }
+            if self.extract_listing_running {
+                ui.label("⏳");
+            }
+        });
+        
+        // === Содержимое архива ===
+        if let Some(ref entries) = self.extract_listing {
+            ui.add_space(10.0);
+            ui.label(t.archive_contents);
+            egui::ScrollArea::vertical()
+                .id_salt("archive_listing")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for entry in entries {
+                        // Отступ по глубине вложенности - простое дерево
+                        let name = entry.name.trim_end_matches('/');
+                        let depth = name.matches('/').count();
+                        let leaf = name.rsplit('/').next().unwrap_or(name);
+                        ui.horizontal(|ui| {
+                            ui.add_space(depth as f32 * 16.0);
+                            if entry.is_dir {
+                                ui.label(format!("📁 {}", leaf));
+                            } else {
+                                // Отмеченные файлы распаковываются выборочно
+                                let mut checked = self.extract_selected.contains(&entry.name);
+                                if ui.add_enabled(
+                                    !self.extract_running,
+                                    egui::Checkbox::new(&mut checked, format!("📄 {}", leaf)),
+                                ).changed() {
+                                    if checked {
+                                        self.extract_selected.insert(entry.name.clone());
+                                    } else {
+                                        self.extract_selected.remove(&entry.name);
+                                    }
+                                }
+                                ui.label(egui::RichText::new(format_size(entry.size)).color(egui::Color32::GRAY));
+                            }
+                        });
+                    }
+                });
+            
+            if !self.extract_selected.is_empty() {
+                ui.label(format!("{} {}", t.selected_entries, self.extract_selected.len()));
+            }
         }
         
         ui.add_space(15.0);
@@ -63,15 +114,21 @@ impl App {
         ui.horizontal(|ui| {
             if self.extract_running {
                 // Кнопка остановки
-                if ui.button(t.stop).clicked() {
+                if ui.button(t.stop)
+                    .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_STOP))
+                    .clicked()
+                {
                     self.stop_extraction();
                 }
-                ui.spinner();
+                ui.label("⏳");
                 ui.label(egui::RichText::new("Распаковка...").color(egui::Color32::YELLOW));
             } else {
                 // Кнопка распаковки
                 let can_extract = self.extract_archive_path.is_some();
-                if ui.add_enabled(can_extract, egui::Button::new(t.start_extraction)).clicked() {
+                if ui.add_enabled(can_extract, egui::Button::new(t.start_extraction))
+                    .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_START))
+                    .clicked()
+                {
                     self.start_local_extraction();
                 }
             }
@@ -92,6 +149,11 @@ impl App {
             };
             
             ui.label(egui::RichText::new(result).color(color));
+            
+            if result.starts_with("✅") && ui.button(t.open_folder).clicked() {
+                let destination = self.extract_destination.clone();
+                self.open_in_file_manager(&destination);
+            }
         }
     }
 }
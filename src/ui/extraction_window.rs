@@ -1,7 +1,6 @@
 //! Окно для отображения процесса распаковки на лету
 
 use crate::app::App;
-use toolza_sender::utils::format_size;
 use eframe::egui;
 
 impl App {
@@ -38,15 +37,23 @@ impl App {
                         ui.label(egui::RichText::new(format!("{}", self.extraction_files_count)).strong());
                     });
                     
+                    if self.extraction_dirs_count > 0 || self.extraction_symlinks_count > 0 {
+                        ui.horizontal(|ui| {
+                            ui.label("Папок / ссылок:");
+                            ui.label(egui::RichText::new(format!("{} / {}",
+                                self.extraction_dirs_count, self.extraction_symlinks_count)).strong());
+                        });
+                    }
+                    
                     ui.horizontal(|ui| {
                         ui.label("Общий размер:");
-                        ui.label(egui::RichText::new(format_size(self.extraction_total_size)).strong());
+                        ui.label(egui::RichText::new(self.format_size(self.extraction_total_size)).strong());
                     });
                     
                     ui.add_space(10.0);
                     
-                    // Индикатор прогресса
-                    ui.spinner();
+                    // Индикатор прогресса (статичный: спиннер перерисовывает окно каждый кадр)
+                    ui.label("⏳");
                     ui.label(egui::RichText::new("Распаковка в процессе...").color(egui::Color32::YELLOW));
                 });
             });
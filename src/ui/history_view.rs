@@ -1,7 +1,7 @@
 //! UI для истории передач
 
-use crate::app::App;
-use toolza_sender::history::Direction;
+use crate::app::{App, HistoryFilter, HistoryPeriod};
+use toolza_sender::history::{current_timestamp, Direction, HistoryEntry, HistoryFilters, TransferHistory};
 use toolza_sender::i18n::Language;
 use eframe::egui;
 
@@ -11,31 +11,40 @@ impl App {
         ui.heading(t.history_title);
         ui.add_space(10.0);
         
-        // Общая статистика
-        let stats = self.history.total_stats();
+        self.render_history_filters(ui);
+        ui.add_space(10.0);
+        
+        // Статистика по отобранным записям
+        let stats = TransferHistory::stats_for(self.filtered_history());
         
         let total_label = match self.language {
             Language::Russian => format!("Всего: {} передач | ✅ {} успешных", stats.total_transfers, stats.successful_transfers),
             Language::Ukrainian => format!("Всього: {} передач | ✅ {} успішних", stats.total_transfers, stats.successful_transfers),
             Language::English => format!("Total: {} transfers | ✅ {} successful", stats.total_transfers, stats.successful_transfers),
+            Language::German => format!("Gesamt: {} Übertragungen | ✅ {} erfolgreich", stats.total_transfers, stats.successful_transfers),
+            Language::French => format!("Total : {} transferts | ✅ {} réussis", stats.total_transfers, stats.successful_transfers),
         };
         ui.horizontal(|ui| {
             ui.label(total_label);
         });
         
         let sent_label = match self.language {
-            Language::Russian => format!("📤 Отправлено: {} файлов, {}", stats.files_sent, toolza_sender::utils::format_size(stats.total_sent)),
-            Language::Ukrainian => format!("📤 Надіслано: {} файлів, {}", stats.files_sent, toolza_sender::utils::format_size(stats.total_sent)),
-            Language::English => format!("📤 Sent: {} files, {}", stats.files_sent, toolza_sender::utils::format_size(stats.total_sent)),
+            Language::Russian => format!("📤 Отправлено: {} файлов, {}", stats.files_sent, self.format_size(stats.total_sent)),
+            Language::Ukrainian => format!("📤 Надіслано: {} файлів, {}", stats.files_sent, self.format_size(stats.total_sent)),
+            Language::English => format!("📤 Sent: {} files, {}", stats.files_sent, self.format_size(stats.total_sent)),
+            Language::German => format!("📤 Gesendet: {} Dateien, {}", stats.files_sent, self.format_size(stats.total_sent)),
+            Language::French => format!("📤 Envoyé : {} fichiers, {}", stats.files_sent, self.format_size(stats.total_sent)),
         };
         ui.horizontal(|ui| {
             ui.label(sent_label);
         });
         
         let received_label = match self.language {
-            Language::Russian => format!("📥 Получено: {} файлов, {}", stats.files_received, toolza_sender::utils::format_size(stats.total_received)),
-            Language::Ukrainian => format!("📥 Отримано: {} файлів, {}", stats.files_received, toolza_sender::utils::format_size(stats.total_received)),
-            Language::English => format!("📥 Received: {} files, {}", stats.files_received, toolza_sender::utils::format_size(stats.total_received)),
+            Language::Russian => format!("📥 Получено: {} файлов, {}", stats.files_received, self.format_size(stats.total_received)),
+            Language::Ukrainian => format!("📥 Отримано: {} файлів, {}", stats.files_received, self.format_size(stats.total_received)),
+            Language::English => format!("📥 Received: {} files, {}", stats.files_received, self.format_size(stats.total_received)),
+            Language::German => format!("📥 Empfangen: {} Dateien, {}", stats.files_received, self.format_size(stats.total_received)),
+            Language::French => format!("📥 Reçu : {} fichiers, {}", stats.files_received, self.format_size(stats.total_received)),
         };
         ui.horizontal(|ui| {
             ui.label(received_label);
@@ -45,12 +54,23 @@ impl App {
         ui.separator();
         ui.add_space(10.0);
         
-        // Кнопка очистки
+        let export_hint = match self.language {
+            Language::Russian => "Экспорт истории в CSV",
+            Language::Ukrainian => "Експорт історії в CSV",
+            Language::English => "Export history to CSV",
+            Language::German => "Verlauf als CSV exportieren",
+            Language::French => "Exporter l'historique en CSV",
+        };
+        
+        // Кнопки очистки и экспорта
         let t = self.t();
         ui.horizontal(|ui| {
             if ui.button(t.clear_history).clicked() {
                 self.history.clear();
             }
+            if ui.button("📄 CSV").on_hover_text(export_hint).clicked() {
+                self.export_history_dialog();
+            }
         });
         
         ui.add_space(10.0);
@@ -61,35 +81,58 @@ impl App {
             ui.colored_label(egui::Color32::GRAY, t.no_history);
             return;
         }
+        let entries = self.filtered_history();
+        if entries.is_empty() {
+            ui.colored_label(egui::Color32::GRAY, t.history_no_matches);
+            return;
+        }
         
         let files_label = match self.language {
             Language::Russian => "файл(ов)",
             Language::Ukrainian => "файл(ів)",
             Language::English => "file(s)",
+            Language::German => "Datei(en)",
+            Language::French => "fichier(s)",
         };
         
         let compression_label = match self.language {
             Language::Russian => "Сжатие",
             Language::Ukrainian => "Стиснення",
             Language::English => "Compression",
+            Language::German => "Komprimierung",
+            Language::French => "Compression",
         };
         
         let addrs_label = match self.language {
             Language::Russian => "адр.",
             Language::Ukrainian => "адр.",
             Language::English => "addr.",
+            Language::German => "Adr.",
+            Language::French => "adr.",
+        };
+        
+        let checksum_label = match self.language {
+            Language::Russian => "Контрольная сумма",
+            Language::Ukrainian => "Контрольна сума",
+            Language::English => "Checksum",
+            Language::German => "Prüfsumme",
+            Language::French => "Somme de contrôle",
         };
         
         let error_label = match self.language {
             Language::Russian => "Ошибка",
             Language::Ukrainian => "Помилка",
             Language::English => "Error",
+            Language::German => "Fehler",
+            Language::French => "Erreur",
         };
         
+        let language = self.language;
+        
         egui::ScrollArea::vertical()
             .id_salt("history_scroll")
             .show(ui, |ui| {
-                for entry in &self.history.entries {
+                for entry in entries {
                     ui.group(|ui| {
                         ui.horizontal(|ui| {
                             // Иконка направления
@@ -102,10 +145,11 @@ impl App {
                             let status_icon = if entry.success { "✅" } else { "❌" };
                             
                             ui.label(format!("{} {}", icon, status_icon));
-                            ui.label(&entry.formatted_time());
+                            ui.label(entry.formatted_time_relative_in(language))
+                                .on_hover_text(entry.formatted_time_in(language));
                             
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                ui.label(&entry.formatted_speed());
+                                ui.label(entry.formatted_speed_in(language));
                             });
                         });
                         
@@ -114,7 +158,7 @@ impl App {
                                 "{} {}, {}",
                                 entry.files_count,
                                 files_label,
-                                entry.formatted_size()
+                                entry.formatted_size_in(language)
                             ));
                             
                             if entry.compression_ratio < 0.99 {
@@ -124,9 +168,10 @@ impl App {
                         });
                         
                         ui.horizontal(|ui| {
-                            ui.label(format!("⏱ {}", entry.formatted_duration()));
+                            ui.label(format!("⏱ {}", entry.formatted_duration_in(language)));
                             
-                            if !entry.addresses.is_empty() {
+                            // Получатели с итогами показаны отдельной строкой ниже
+                            if entry.targets.is_empty() && !entry.addresses.is_empty() {
                                 let addrs = entry.addresses.join(", ");
                                 if addrs.len() > 40 {
                                     ui.label(format!("| {} {}", entry.addresses.len(), addrs_label))
@@ -137,6 +182,32 @@ impl App {
                             }
                         });
                         
+                        // Итог по каждому получателю: зелёная или красная точка
+                        if !entry.targets.is_empty() {
+                            ui.horizontal_wrapped(|ui| {
+                                for target in &entry.targets {
+                                    let color = if target.success {
+                                        egui::Color32::from_rgb(100, 200, 100)
+                                    } else {
+                                        egui::Color32::from_rgb(200, 100, 100)
+                                    };
+                                    ui.colored_label(color, "●");
+                                    let label = ui.label(&target.address);
+                                    if let Some(err) = &target.error {
+                                        label.on_hover_text(err);
+                                    }
+                                }
+                            });
+                        }
+                        
+                        if let Some(throughput) = &entry.throughput {
+                            ui.label(format!("📈 {}", throughput.formatted_in(language)));
+                        }
+                        
+                        if let Some(checksum) = &entry.checksum {
+                            ui.label(format!("🔐 {}: {}", checksum_label, checksum));
+                        }
+                        
                         if let Some(err) = &entry.error {
                             ui.colored_label(egui::Color32::RED, format!("{}: {}", error_label, err));
                         }
@@ -145,4 +216,55 @@ impl App {
                 }
             });
     }
+    
+    /// Выбор вида передач, срока и поиск по адресу
+    fn render_history_filters(&mut self, ui: &mut egui::Ui) {
+        let t = self.t();
+        let filter_label = |filter: HistoryFilter| match filter {
+            HistoryFilter::All => t.history_filter_all,
+            HistoryFilter::Sent => t.direction_sent,
+            HistoryFilter::Received => t.direction_received,
+            HistoryFilter::Failed => t.history_filter_failed,
+        };
+        let period_label = |period: HistoryPeriod| match period {
+            HistoryPeriod::All => t.history_period_all,
+            HistoryPeriod::Day => t.history_period_day,
+            HistoryPeriod::Week => t.history_period_week,
+            HistoryPeriod::Month => t.history_period_month,
+        };
+        
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("history_filter_select")
+                .selected_text(filter_label(self.history_filter))
+                .show_ui(ui, |ui| {
+                    for filter in [HistoryFilter::All, HistoryFilter::Sent, HistoryFilter::Received, HistoryFilter::Failed] {
+                        ui.selectable_value(&mut self.history_filter, filter, filter_label(filter));
+                    }
+                });
+            egui::ComboBox::from_id_salt("history_period_select")
+                .selected_text(period_label(self.history_period))
+                .show_ui(ui, |ui| {
+                    for period in [HistoryPeriod::All, HistoryPeriod::Day, HistoryPeriod::Week, HistoryPeriod::Month] {
+                        ui.selectable_value(&mut self.history_period, period, period_label(period));
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut self.history_search)
+                .hint_text(t.history_search_hint)
+                .desired_width(180.0));
+        });
+    }
+    
+    /// Записи истории, подходящие под выбранные фильтры
+    fn filtered_history(&self) -> Vec<&HistoryEntry> {
+        let from = self.history_period.secs().map_or(0, |secs| current_timestamp().saturating_sub(secs));
+        let entries = self.history
+            .filter_by_date_range(from, u64::MAX)
+            .search_address(&self.history_search);
+        match self.history_filter {
+            HistoryFilter::All => entries.collect(),
+            HistoryFilter::Sent => entries.filter_by_direction(Direction::Send).collect(),
+            HistoryFilter::Received => entries.filter_by_direction(Direction::Receive).collect(),
+            HistoryFilter::Failed => entries.filter_failed().collect(),
+        }
+    }
 }
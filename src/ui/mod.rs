@@ -12,12 +12,36 @@ use crate::app::{App, Mode};
 #[allow(unused_imports)]
 use toolza_sender::protocol::FileStatus;
 use toolza_sender::i18n::Language;
+use toolza_sender::settings::MAX_FPS_CHOICES;
 use eframe::egui;
 
+/// Ширина окна, ниже которой компактный режим включается автоматически
+const COMPACT_WIDTH: f32 = 700.0;
+/// Как часто проверять события во время операции, если новых не было
+const IDLE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+// === Горячие клавиши ===
+const SHORTCUT_ADD_FILES: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+const SHORTCUT_ADD_FOLDER: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT), egui::Key::O);
+const SHORTCUT_START: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Enter);
+const SHORTCUT_STOP: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Escape);
+/// Режимы по клавишам 1-5 (в порядке боковой панели)
+const MODE_KEYS: [(egui::Key, Mode); 5] = [
+    (egui::Key::Num1, Mode::Send),
+    (egui::Key::Num2, Mode::Receive),
+    (egui::Key::Num3, Mode::Extract),
+    (egui::Key::Num4, Mode::SpeedTest),
+    (egui::Key::Num5, Mode::History),
+];
+
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Обрабатываем события
-        self.process_events();
+        let got_events = self.process_events();
         
         // Обрабатываем результаты файловых диалогов
         self.process_dialog_results();
@@ -25,26 +49,87 @@ impl eframe::App for App {
         // Обрабатываем Drag & Drop
         self.handle_drag_drop(ctx);
         
-        // Запрашиваем перерисовку при активных операциях (раз в секунду, не чаще!)
-        if self.is_running || self.is_scanning || self.speedtest_running || self.extraction_window_open {
-            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        // Горячие клавиши
+        self.handle_shortcuts(ctx);
+        
+        // Перерисовка: есть события - следующий кадр не раньше лимита FPS,
+        // нет - редкий опрос канала, пока идёт операция; в простое кадров нет вовсе
+        let active = self.is_running || self.is_scanning || self.speedtest_running
+            || self.extraction_window_open || self.extract_running || self.extract_listing_running;
+        if got_events {
+            ctx.request_repaint_after(self.settings.frame_interval());
+        } else if active {
+            ctx.request_repaint_after(IDLE_POLL_INTERVAL);
         }
         
         // Окно распаковки
         self.render_extraction_window(ctx);
         
-        // Боковая панель
-        self.render_sidebar(ctx);
-        
-        // Нижняя панель с логом (фиксированная высота)
-        self.render_log_panel(ctx);
+        // В компактном режиме остаются только элементы текущего режима
+        let compact = self.settings.compact_mode || ctx.screen_rect().width() < COMPACT_WIDTH;
+        if !compact {
+            // Боковая панель
+            self.render_sidebar(ctx);
+            
+            // Нижняя панель с логом (фиксированная высота)
+            self.render_log_panel(ctx);
+        }
         
         // Основная панель (занимает оставшееся место)
-        self.render_main_panel(ctx);
+        self.render_main_panel(ctx, compact);
     }
 }
 
 impl App {
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // Ctrl+Shift+O проверяем раньше Ctrl+O: consume_shortcut не различает лишний Shift
+        let add_folder = ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_ADD_FOLDER));
+        let add_files = ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_ADD_FILES));
+        let start = ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_START));
+        let stop = ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_STOP));
+        
+        if (add_files || add_folder) && self.mode == Mode::Send && self.can_edit() {
+            if add_folder {
+                self.add_folder_dialog();
+            } else {
+                self.add_files_dialog();
+            }
+        }
+        
+        if start && self.can_edit() && !self.speedtest_running && !self.extract_running {
+            match self.mode {
+                Mode::Send if self.targets.is_empty() => self.start_scan(),
+                Mode::Send if !self.files.is_empty() => self.start_send(),
+                Mode::Receive => self.start_receive(),
+                Mode::Extract if self.extract_archive_path.is_some() => self.start_local_extraction(),
+                Mode::SpeedTest => self.start_speedtest(),
+                _ => {}
+            }
+        }
+        
+        if stop {
+            if self.is_running || self.is_scanning || self.speedtest_running {
+                self.stop();
+            } else if self.extract_running {
+                self.stop_extraction();
+            }
+        }
+        
+        // Цифры не перехватываем, пока вводится текст
+        if !ctx.wants_keyboard_input() {
+            for (key, mode) in MODE_KEYS {
+                if ctx.input(|i| i.key_pressed(key) && i.modifiers.is_none()) {
+                    self.mode = mode;
+                }
+            }
+        }
+    }
+    
+    /// Подсказка с горячей клавишей для кнопки
+    fn shortcut_hint(&self, ctx: &egui::Context, shortcut: &egui::KeyboardShortcut) -> String {
+        format!("{}: {}", self.t().shortcut, ctx.format_shortcut(shortcut))
+    }
+    
     fn handle_drag_drop(&mut self, ctx: &egui::Context) {
         // Проверяем dropped файлы
         ctx.input(|i| {
@@ -75,6 +160,8 @@ impl App {
                 Language::Russian => "📁 Перетащите файлы или папки сюда",
                 Language::Ukrainian => "📁 Перетягніть файли або теки сюди",
                 Language::English => "📁 Drag files or folders here",
+                Language::German => "📁 Dateien oder Ordner hierher ziehen",
+                Language::French => "📁 Glissez des fichiers ou dossiers ici",
             };
             painter.text(
                 screen_rect.center(),
@@ -101,17 +188,27 @@ impl App {
                     Language::Russian => "Режим",
                     Language::Ukrainian => "Режим",
                     Language::English => "Mode",
+                    Language::German => "Modus",
+                    Language::French => "Mode",
                 };
                 ui.heading(mode_label);
                 ui.add_space(10.0);
                 
-                ui.selectable_value(&mut self.mode, Mode::Send, format!("📤 {}", t.mode_send));
-                ui.selectable_value(&mut self.mode, Mode::Receive, format!("📥 {}", t.mode_receive));
-                ui.selectable_value(&mut self.mode, Mode::Extract, format!("📦 {}", t.mode_extract));
-                ui.selectable_value(&mut self.mode, Mode::SpeedTest, format!("🚀 {}", t.mode_speedtest));
-                ui.selectable_value(&mut self.mode, Mode::History, format!("📊 {}", t.mode_history));
+                ui.selectable_value(&mut self.mode, Mode::Send, format!("📤 {}", t.mode_send))
+                    .on_hover_text(format!("{}: 1", t.shortcut));
+                ui.selectable_value(&mut self.mode, Mode::Receive, format!("📥 {}", t.mode_receive))
+                    .on_hover_text(format!("{}: 2", t.shortcut));
+                ui.selectable_value(&mut self.mode, Mode::Extract, format!("📦 {}", t.mode_extract))
+                    .on_hover_text(format!("{}: 3", t.shortcut));
+                ui.selectable_value(&mut self.mode, Mode::SpeedTest, format!("🚀 {}", t.mode_speedtest))
+                    .on_hover_text(format!("{}: 4", t.shortcut));
+                ui.selectable_value(&mut self.mode, Mode::History, format!("📊 {}", t.mode_history))
+                    .on_hover_text(format!("{}: 5", t.shortcut));
                 
-                ui.add_space(20.0);
+                ui.add_space(10.0);
+                self.render_transport_status(ui);
+                
+                ui.add_space(10.0);
                 ui.separator();
                 
                 // Статистика передачи (если активна)
@@ -121,26 +218,13 @@ impl App {
                         Language::Russian => "📈 Статистика:",
                         Language::Ukrainian => "📈 Статистика:",
                         Language::English => "📈 Statistics:",
+                        Language::German => "📈 Statistik:",
+                        Language::French => "📈 Statistiques :",
                     };
                     ui.label(stats_label);
                     ui.add_space(5.0);
                     
-                    ui.horizontal(|ui| {
-                        ui.label("⚡");
-                        ui.label(&self.current_speed());
-                    });
-                    
-                    ui.horizontal(|ui| {
-                        ui.label("⏱");
-                        ui.label(&self.current_eta());
-                    });
-                    
-                    if self.use_compression {
-                        ui.horizontal(|ui| {
-                            ui.label("📦");
-                            ui.label(&self.compression_stats());
-                        });
-                    }
+                    widgets::transfer_stats_widget(ui, &self.stats, self.language, self.t());
                     
                     ui.add_space(10.0);
                     ui.separator();
@@ -189,6 +273,8 @@ impl App {
                                 Language::Russian => "Лог пуст",
                                 Language::Ukrainian => "Лог порожній",
                                 Language::English => "Log is empty",
+                                Language::German => "Protokoll ist leer",
+                                Language::French => "Le journal est vide",
                             };
                             ui.colored_label(egui::Color32::GRAY, empty_log);
                         }
@@ -196,11 +282,16 @@ impl App {
             });
     }
     
-    fn render_main_panel(&mut self, ctx: &egui::Context) {
+    fn render_main_panel(&mut self, ctx: &egui::Context, compact: bool) {
         egui::CentralPanel::default().show(ctx, |ui| {
             // Кнопки выбора языка вверху
             self.render_language_selector(ui);
             
+            // Без боковой панели режим и статус показываем здесь
+            if compact {
+                self.render_compact_mode_bar(ui);
+            }
+            
             ui.separator();
             ui.add_space(5.0);
             
@@ -215,6 +306,7 @@ impl App {
     }
     
     fn render_language_selector(&mut self, ui: &mut egui::Ui) {
+        let t = self.t();
         ui.horizontal(|ui| {
             ui.label("🌐");
             for lang in Language::all() {
@@ -222,9 +314,83 @@ impl App {
                 let selected = self.language == *lang;
                 
                 if ui.selectable_label(selected, text).clicked() {
-                    self.language = *lang;
+                    self.set_language(*lang);
                 }
             }
+            
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let mut changed = false;
+                if ui.selectable_label(self.settings.compact_mode, "🗕")
+                    .on_hover_text(t.compact_mode)
+                    .clicked()
+                {
+                    self.settings.compact_mode = !self.settings.compact_mode;
+                    changed = true;
+                }
+                
+                // Звук доступен только в сборке с фичей `sound`
+                if cfg!(feature = "sound") {
+                    let bell = if self.settings.completion_sound { "🔔" } else { "🔕" };
+                    let response = ui.selectable_label(self.settings.completion_sound, bell)
+                        .on_hover_text(t.completion_sound);
+                    if response.clicked() {
+                        self.settings.completion_sound = !self.settings.completion_sound;
+                        changed = true;
+                    }
+                    response.context_menu(|ui| {
+                        if ui.button(t.choose_sound).clicked() {
+                            self.select_completion_sound_dialog();
+                            ui.close_menu();
+                        }
+                        if ui.add_enabled(self.settings.completion_sound_path.is_some(), egui::Button::new(t.default_sound)).clicked() {
+                            self.settings.completion_sound_path = None;
+                            changed = true;
+                            ui.close_menu();
+                        }
+                    });
+                }
+                
+                egui::ComboBox::from_id_salt("max_fps_select")
+                    .selected_text(format!("{} FPS", self.settings.max_fps))
+                    .width(70.0)
+                    .show_ui(ui, |ui| {
+                        for fps in MAX_FPS_CHOICES {
+                            changed |= ui.selectable_value(&mut self.settings.max_fps, fps, format!("{} FPS", fps)).changed();
+                        }
+                    })
+                    .response
+                    .on_hover_text(t.max_fps);
+                
+                if changed {
+                    if let Err(e) = self.settings.save() {
+                        self.log(format!("❌ Не удалось сохранить настройки: {}", e));
+                    }
+                }
+            });
+        });
+    }
+    
+    /// Выбор режима и статус для компактного режима
+    fn render_compact_mode_bar(&mut self, ui: &mut egui::Ui) {
+        let t = self.t();
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("compact_mode_select")
+                .selected_text(match self.mode {
+                    Mode::Send => format!("📤 {}", t.mode_send),
+                    Mode::Receive => format!("📥 {}", t.mode_receive),
+                    Mode::Extract => format!("📦 {}", t.mode_extract),
+                    Mode::SpeedTest => format!("🚀 {}", t.mode_speedtest),
+                    Mode::History => format!("📊 {}", t.mode_history),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.mode, Mode::Send, format!("📤 {}", t.mode_send));
+                    ui.selectable_value(&mut self.mode, Mode::Receive, format!("📥 {}", t.mode_receive));
+                    ui.selectable_value(&mut self.mode, Mode::Extract, format!("📦 {}", t.mode_extract));
+                    ui.selectable_value(&mut self.mode, Mode::SpeedTest, format!("🚀 {}", t.mode_speedtest));
+                    ui.selectable_value(&mut self.mode, Mode::History, format!("📊 {}", t.mode_history));
+                });
+            self.render_transport_status(ui);
+            ui.label(&self.status_message);
         });
     }
 }
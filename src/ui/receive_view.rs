@@ -2,7 +2,10 @@
 
 use crate::app::App;
 use eframe::egui;
+use toolza_sender::extract::ArchiveType;
 use toolza_sender::network::TransportType;
+use toolza_sender::protocol::DEFAULT_PORT;
+use toolza_sender::utils::build_send_command;
 
 impl App {
     pub fn render_receive_mode(&mut self, ui: &mut egui::Ui) {
@@ -18,6 +21,17 @@ impl App {
             ui.label(format!("[{}]", self.transport_type.name()));
         });
         
+        // Что запустить на стороне отправителя
+        let port = self.listen_port.parse().unwrap_or(DEFAULT_PORT);
+        let command = build_send_command(&self.local_ip, port, self.transport_type);
+        ui.horizontal(|ui| {
+            ui.label(t.sender_command);
+            ui.code(&command);
+            if ui.small_button(t.copy_command).clicked() {
+                ui.ctx().copy_text(command.clone());
+            }
+        });
+        
         ui.add_space(5.0);
         
         // Порт
@@ -59,6 +73,7 @@ impl App {
                     egui::RadioButton::new(self.transport_type == transport, label),
                 ).on_hover_text(tooltip).clicked() {
                     self.transport_type = transport;
+                    self.resume_probe_stale = true;
                 }
             }
         });
@@ -97,6 +112,11 @@ impl App {
                 egui::Checkbox::new(&mut self.auto_extract_lz4, "lz4"),
             ).on_hover_text(t.extract_tooltip_lz4);
             
+            ui.add_enabled(
+                self.can_edit(),
+                egui::Checkbox::new(&mut self.auto_extract_zst, "zst"),
+            ).on_hover_text(t.extract_tooltip_zst);
+            
             ui.add_enabled(
                 self.can_edit(),
                 egui::Checkbox::new(&mut self.auto_extract_tar, "tar"),
@@ -107,10 +127,21 @@ impl App {
                 egui::Checkbox::new(&mut self.auto_extract_zip, "zip"),
             ).on_hover_text(t.extract_tooltip_zip);
             
+            // rar выключен, пока сборка не умеет его распаковывать
             ui.add_enabled(
-                self.can_edit(),
+                self.can_edit() && (ArchiveType::Rar.is_supported() || self.auto_extract_rar),
                 egui::Checkbox::new(&mut self.auto_extract_rar, "rar"),
-            ).on_hover_text(t.extract_tooltip_rar);
+            ).on_hover_text(t.extract_tooltip_rar)
+                .on_disabled_hover_text(t.extract_tooltip_rar);
+        });
+        
+        let t = self.t();
+        ui.horizontal(|ui| {
+            ui.label(t.strip_components);
+            ui.add_enabled(
+                self.can_edit(),
+                egui::DragValue::new(&mut self.extract_strip_components).range(0..=16),
+            ).on_hover_text(t.strip_components_tooltip);
         });
         
         // Опция сохранения архива для резюме (только если включена потоковая распаковка tar.lz4 или tar.zst)
@@ -130,11 +161,17 @@ impl App {
         let t = self.t();
         ui.horizontal(|ui| {
             if self.is_running {
-                if ui.button(t.stop_server).clicked() {
+                if ui.button(t.stop_server)
+                    .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_STOP))
+                    .clicked()
+                {
                     self.stop();
                 }
             } else {
-                if ui.button(t.start_server).clicked() {
+                if ui.button(t.start_server)
+                    .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_START))
+                    .clicked()
+                {
                     self.start_receive();
                 }
             }
@@ -142,9 +179,45 @@ impl App {
         
         ui.add_space(10.0);
         
+        // Статистика во время приёма
+        if self.is_running && self.stats.total_bytes > 0 {
+            super::widgets::transfer_stats_widget(ui, &self.stats, self.language, self.t());
+            ui.add_space(5.0);
+        }
+        
+        // Диск заполнен: файл сохранён частично, докачается после освобождения места
+        if let Some((name, kept)) = self.disk_full.clone() {
+            let t = self.t();
+            ui.colored_label(
+                egui::Color32::from_rgb(255, 140, 0),
+                t.disk_full.replacen("{}", &name, 1).replacen("{}", &self.format_size(kept), 1),
+            );
+            ui.horizontal(|ui| {
+                ui.label(t.disk_full_hint);
+                if ui.button(t.open_folder).clicked() {
+                    let path = self.save_directory.clone();
+                    self.open_in_file_manager(&path);
+                }
+                if ui.small_button("✖").clicked() {
+                    self.disk_full = None;
+                }
+            });
+            ui.add_space(5.0);
+        }
+        
         // Полученные файлы
         let t = self.t();
-        ui.heading(t.received_files);
+        ui.horizontal(|ui| {
+            ui.heading(t.received_files);
+            if ui.button(t.open_folder).clicked() {
+                // Выделяем последний полученный файл, если он на месте
+                let last = self.received_files.last()
+                    .map(|(name, _)| self.save_directory.join(name))
+                    .filter(|path| path.exists());
+                let path = last.unwrap_or_else(|| self.save_directory.clone());
+                self.open_in_file_manager(&path);
+            }
+        });
         self.render_received_files(ui);
     }
 }
@@ -2,7 +2,9 @@
 
 use crate::app::App;
 use eframe::egui;
-use toolza_sender::network::TransportType;
+use toolza_sender::network::{server_label, QuotaTracker, TransportType};
+use toolza_sender::protocol::DEFAULT_PORT;
+use toolza_sender::utils::build_receive_command;
 
 impl App {
     pub fn render_send_mode(&mut self, ui: &mut egui::Ui) {
@@ -32,11 +34,24 @@ impl App {
             ui.label(t.your_ip);
             ui.label(&self.local_ip);
             ui.label(format!("  {}", t.port));
-            ui.add_enabled(
+            if ui.add_enabled(
                 self.can_edit(),
                 egui::TextEdit::singleline(&mut self.target_port)
                     .desired_width(60.0),
-            );
+            ).changed() {
+                self.resume_probe_stale = true;
+            }
+        });
+        
+        // Что запустить на стороне получателя
+        let port = self.target_port.parse().unwrap_or(DEFAULT_PORT);
+        let command = build_receive_command(&self.server_options(), port);
+        ui.horizontal(|ui| {
+            ui.label(t.receiver_command);
+            ui.code(&command);
+            if ui.small_button(t.copy_command).clicked() {
+                ui.ctx().copy_text(command.clone());
+            }
         });
     }
     
@@ -123,9 +138,10 @@ impl App {
                     t.address == server_ip || t.address == *server
                 );
                 
+                let label = server_label(server, self.server_names.get(server).map(String::as_str));
                 if already_added {
-                    ui.add_enabled(false, egui::Button::new(format!("✓ {}", server)));
-                } else if ui.button(format!("🖥 {}", server)).clicked() {
+                    ui.add_enabled(false, egui::Button::new(format!("✓ {}", label)));
+                } else if ui.button(format!("🖥 {}", label)).clicked() {
                     self.add_target(server_ip.to_string());
                 }
             }
@@ -137,11 +153,17 @@ impl App {
         
         // Строка 1: Кнопки управления файлами
         ui.horizontal(|ui| {
-            if ui.add_enabled(self.can_edit(), egui::Button::new(t.files)).clicked() {
+            if ui.add_enabled(self.can_edit(), egui::Button::new(t.files))
+                .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_ADD_FILES))
+                .clicked()
+            {
                 self.add_files_dialog();
             }
             
-            if ui.add_enabled(self.can_edit(), egui::Button::new(t.folder)).clicked() {
+            if ui.add_enabled(self.can_edit(), egui::Button::new(t.folder))
+                .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_ADD_FOLDER))
+                .clicked()
+            {
                 self.add_folder_dialog();
             }
             
@@ -163,14 +185,26 @@ impl App {
             ).on_hover_text(t.lz4_tooltip);
             
             ui.add_enabled(
+                self.can_edit() && self.use_compression,
+                egui::Checkbox::new(&mut self.auto_compression, t.auto_compression),
+            ).on_hover_text(t.auto_compression_tooltip);
+            
+            if ui.add_enabled(
                 self.can_edit(),
                 egui::Checkbox::new(&mut self.preserve_structure, t.preserve_structure),
-            ).on_hover_text(t.preserve_structure_tooltip);
+            ).on_hover_text(t.preserve_structure_tooltip).changed() {
+                self.resume_probe_stale = true;
+            }
             
             ui.add_enabled(
                 self.can_edit(),
                 egui::Checkbox::new(&mut self.sync_mode, t.sync_mode),
             ).on_hover_text(t.sync_mode_tooltip);
+            
+            ui.add_enabled(
+                self.can_edit(),
+                egui::Checkbox::new(&mut self.include_hidden, t.include_hidden),
+            ).on_hover_text(t.include_hidden_tooltip);
         });
         
         // Строка 3: Выбор протокола
@@ -199,23 +233,89 @@ impl App {
                     egui::RadioButton::new(self.transport_type == transport, label),
                 ).on_hover_text(tooltip).clicked() {
                     self.transport_type = transport;
+                    self.resume_probe_stale = true;
                 }
             }
         });
         
+        // Другое имя у получателя - только когда выбран один файл
+        if self.files.len() == 1 {
+            let t = self.t();
+            ui.horizontal(|ui| {
+                ui.label(t.output_name);
+                if ui.add_enabled(
+                    self.can_edit(),
+                    egui::TextEdit::singleline(&mut self.output_name_input)
+                        .hint_text(t.output_name_hint)
+                        .desired_width(200.0),
+                ).on_hover_text(t.output_name_tooltip).changed() {
+                    self.resume_probe_stale = true;
+                }
+            });
+        }
+        
+        // Дневной лимит трафика
+        let t = self.t();
+        ui.horizontal(|ui| {
+            ui.label(t.daily_quota);
+            let response = ui.add_enabled(
+                self.can_edit(),
+                egui::TextEdit::singleline(&mut self.daily_quota_input)
+                    .hint_text(t.daily_quota_hint)
+                    .desired_width(80.0),
+            );
+            if response.lost_focus() {
+                self.apply_daily_quota();
+            }
+            if let Some(limit) = self.settings.daily_quota {
+                let remaining = QuotaTracker::shared(limit).remaining();
+                ui.label(format!("{} {} / {}", t.quota_remaining, self.format_size(remaining), self.format_size(limit)));
+            }
+        });
+        
         ui.add_space(5.0);
         
+        // Прерванная ранее передача: докачать или начать заново
+        if !self.is_running {
+            self.update_resume_probe();
+        }
+        let t = self.t();
+        if let Some(probe) = self.resume_probe.as_ref().filter(|_| !self.is_running) {
+            let complete = probe.offsets.iter().zip(&self.files).filter(|(&offset, file)| offset >= file.size).count();
+            let partial = probe.offsets.iter().zip(&self.files).filter(|(&offset, file)| offset > 0 && offset < file.size).count();
+            let target = probe.target.clone();
+            ui.horizontal(|ui| {
+                ui.label(t.resume_found.replace("{}", &target));
+                ui.label(t.resume_summary.replacen("{}", &complete.to_string(), 1).replacen("{}", &partial.to_string(), 1));
+            });
+            ui.horizontal(|ui| {
+                if ui.button(t.resume_continue).on_hover_text(t.resume_continue_tooltip).clicked() {
+                    self.start_send_with_resume(true);
+                }
+                if ui.button(t.resume_fresh).on_hover_text(t.resume_fresh_tooltip).clicked() {
+                    self.start_send_with_resume(false);
+                }
+            });
+            ui.add_space(5.0);
+        }
+        
         // Строка 4: Кнопка отправки
         let t = self.t();
         ui.horizontal(|ui| {
             if self.is_running {
-                if ui.button(t.stop).clicked() {
+                if ui.button(t.stop)
+                    .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_STOP))
+                    .clicked()
+                {
                     self.stop();
                 }
             } else {
                 let can_send = !self.files.is_empty() && !self.targets.is_empty();
                 let btn_text = t.send_to_recipients.replace("{}", &self.targets.len().to_string());
-                if ui.add_enabled(can_send, egui::Button::new(btn_text)).clicked() {
+                if ui.add_enabled(can_send, egui::Button::new(btn_text))
+                    .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_START))
+                    .clicked()
+                {
                     self.start_send();
                 }
             }
@@ -225,34 +325,7 @@ impl App {
         
         // Статистика во время передачи
         if self.is_running {
-            let t = self.t();
-            ui.horizontal(|ui| {
-                let total_size: u64 = self.files.iter().map(|f| f.size).sum();
-                let transferred: u64 = self.files.iter().map(|f| f.transferred).sum();
-                let percent = if total_size > 0 {
-                    (transferred as f64 / total_size as f64 * 100.0) as u32
-                } else {
-                    100
-                };
-                
-                ui.add(
-                    egui::ProgressBar::new(percent as f32 / 100.0)
-                        .desired_width(200.0)
-                        .text(format!(
-                            "{} / {} ({}%)",
-                            toolza_sender::utils::format_size(transferred),
-                            toolza_sender::utils::format_size(total_size),
-                            percent
-                        )),
-                );
-                
-                ui.label(format!("⚡ {}", self.current_speed()));
-                ui.label(format!("{} {}", t.eta, self.current_eta()));
-                
-                if self.use_compression && self.bytes_original > 0 {
-                    ui.label(format!("{} {}", t.compression_stats, self.compression_stats()));
-                }
-            });
+            super::widgets::transfer_stats_widget(ui, &self.stats, self.language, self.t());
             ui.add_space(5.0);
         }
         
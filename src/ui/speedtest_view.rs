@@ -2,6 +2,7 @@
 
 use crate::app::App;
 use toolza_sender::i18n::Language;
+use toolza_sender::network::server_label;
 use eframe::egui;
 
 impl App {
@@ -14,6 +15,8 @@ impl App {
             Language::Russian => "Измерение скорости соединения между двумя экземплярами программы.",
             Language::Ukrainian => "Вимірювання швидкості з'єднання між двома екземплярами програми.",
             Language::English => "Measuring connection speed between two instances of the program.",
+            Language::German => "Messung der Verbindungsgeschwindigkeit zwischen zwei Instanzen des Programms.",
+            Language::French => "Mesure du débit de la connexion entre deux instances du programme.",
         };
         ui.label(description);
         ui.add_space(5.0);
@@ -22,6 +25,8 @@ impl App {
             Language::Russian => "💡 На целевом компьютере должен быть запущен режим \"Принять\"",
             Language::Ukrainian => "💡 На цільовому комп'ютері повинен бути запущений режим \"Прийом\"",
             Language::English => "💡 The target computer must be running in \"Receive\" mode",
+            Language::German => "💡 Auf dem Zielrechner muss der Modus \"Empfangen\" laufen",
+            Language::French => "💡 L'ordinateur cible doit être en mode \"Réception\"",
         };
         ui.colored_label(egui::Color32::GRAY, hint);
         ui.add_space(15.0);
@@ -37,11 +42,13 @@ impl App {
                     .desired_width(200.0),
             );
             ui.label(":");
-            ui.add_enabled(
+            if ui.add_enabled(
                 !self.speedtest_running,
                 egui::TextEdit::singleline(&mut self.target_port)
                     .desired_width(60.0),
-            );
+            ).changed() {
+                self.resume_probe_stale = true;
+            }
         });
         
         ui.add_space(10.0);
@@ -52,12 +59,15 @@ impl App {
                 Language::Russian => "Найденные серверы",
                 Language::Ukrainian => "Знайдені сервери",
                 Language::English => "Found servers",
+                Language::German => "Gefundene Server",
+                Language::French => "Serveurs trouvés",
             };
             ui.collapsing(found_label, |ui| {
                 for server in self.found_servers.clone() {
-                    if ui.selectable_label(false, &server).clicked() {
+                    let label = server_label(&server, self.server_names.get(&server).map(String::as_str));
+                    if ui.selectable_label(false, label).clicked() {
                         // Извлекаем IP без порта
-                        let ip = server.split(':').next().unwrap_or(&server).to_string();
+                        let ip = server.rsplit_once(':').map(|(ip, _)| ip).unwrap_or(&server).to_string();
                         self.speedtest_target = ip;
                     }
                 }
@@ -69,11 +79,17 @@ impl App {
         let t = self.t();
         ui.horizontal(|ui| {
             if self.speedtest_running {
-                if ui.button(t.stop).clicked() {
+                if ui.button(t.stop)
+                    .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_STOP))
+                    .clicked()
+                {
                     self.stop();
                 }
             } else {
-                if ui.button(t.start_test).clicked() {
+                if ui.button(t.start_test)
+                    .on_hover_text(self.shortcut_hint(ui.ctx(), &super::SHORTCUT_START))
+                    .clicked()
+                {
                     self.start_speedtest();
                 }
             }
@@ -96,6 +112,8 @@ impl App {
                 Language::Russian => "⏳ Тестирование...",
                 Language::Ukrainian => "⏳ Тестування...",
                 Language::English => "⏳ Testing...",
+                Language::German => "⏳ Test läuft...",
+                Language::French => "⏳ Test en cours...",
             };
             ui.heading(testing_label);
             ui.add_space(10.0);
@@ -111,7 +129,7 @@ impl App {
             ui.label(format!("{}: {}%", direction, self.speedtest_progress));
             
             let progress = self.speedtest_progress as f32 / 100.0;
-            ui.add(egui::ProgressBar::new(progress).animate(true));
+            ui.add(egui::ProgressBar::new(progress));
         }
         
         // Результаты
@@ -121,6 +139,8 @@ impl App {
                 Language::Russian => "📊 Результаты",
                 Language::Ukrainian => "📊 Результати",
                 Language::English => "📊 Results",
+                Language::German => "📊 Ergebnisse",
+                Language::French => "📊 Résultats",
             };
             ui.heading(results_label);
             ui.add_space(10.0);
@@ -185,6 +205,8 @@ impl App {
                 Language::Russian => "Качество соединения:",
                 Language::Ukrainian => "Якість з'єднання:",
                 Language::English => "Connection quality:",
+                Language::German => "Verbindungsqualität:",
+                Language::French => "Qualité de la connexion :",
             };
             ui.horizontal(|ui| {
                 ui.label(quality_label);
@@ -202,6 +224,8 @@ fn get_connection_quality(upload: f64, download: f64, latency: f64, lang: Langua
             Language::Russian => "🌟 Превосходно",
             Language::Ukrainian => "🌟 Чудово",
             Language::English => "🌟 Excellent",
+            Language::German => "🌟 Hervorragend",
+            Language::French => "🌟 Excellente",
         };
         (label, egui::Color32::from_rgb(100, 255, 100))
     } else if avg_speed >= 50.0 && latency < 2.0 {
@@ -209,6 +233,8 @@ fn get_connection_quality(upload: f64, download: f64, latency: f64, lang: Langua
             Language::Russian => "✅ Отлично",
             Language::Ukrainian => "✅ Відмінно",
             Language::English => "✅ Great",
+            Language::German => "✅ Sehr gut",
+            Language::French => "✅ Très bonne",
         };
         (label, egui::Color32::from_rgb(150, 250, 150))
     } else if avg_speed >= 20.0 && latency < 5.0 {
@@ -216,6 +242,8 @@ fn get_connection_quality(upload: f64, download: f64, latency: f64, lang: Langua
             Language::Russian => "👍 Хорошо",
             Language::Ukrainian => "👍 Добре",
             Language::English => "👍 Good",
+            Language::German => "👍 Gut",
+            Language::French => "👍 Bonne",
         };
         (label, egui::Color32::from_rgb(200, 250, 100))
     } else if avg_speed >= 5.0 && latency < 10.0 {
@@ -223,6 +251,8 @@ fn get_connection_quality(upload: f64, download: f64, latency: f64, lang: Langua
             Language::Russian => "⚠️ Нормально",
             Language::Ukrainian => "⚠️ Нормально",
             Language::English => "⚠️ Normal",
+            Language::German => "⚠️ Normal",
+            Language::French => "⚠️ Moyenne",
         };
         (label, egui::Color32::from_rgb(250, 200, 100))
     } else {
@@ -230,6 +260,8 @@ fn get_connection_quality(upload: f64, download: f64, latency: f64, lang: Langua
             Language::Russian => "❌ Медленно",
             Language::Ukrainian => "❌ Повільно",
             Language::English => "❌ Slow",
+            Language::German => "❌ Langsam",
+            Language::French => "❌ Lente",
         };
         (label, egui::Color32::from_rgb(250, 100, 100))
     }
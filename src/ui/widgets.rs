@@ -1,22 +1,67 @@
 //! Общие виджеты UI
 
-use crate::app::{App, TargetStatus};
+use crate::app::{App, TargetStatus, TransportHealth};
+use toolza_sender::i18n::{Language, Translations};
 use toolza_sender::protocol::FileStatus;
-use toolza_sender::utils::{format_size, truncate_string};
+use toolza_sender::stats::TransferStats;
+use toolza_sender::utils::{format_size_with, truncate_string, UnitStyle};
 use eframe::egui;
 
+/// Прогресс, скорость, ETA и сжатие передачи
+pub fn transfer_stats_widget(ui: &mut egui::Ui, stats: &TransferStats, language: Language, tr: &Translations) {
+    let fraction = stats.progress_percent() / 100.0;
+    
+    ui.horizontal_wrapped(|ui| {
+        ui.add(
+            egui::ProgressBar::new(fraction)
+                .desired_width(ui.available_width().min(200.0))
+                .text(format!(
+                    "{} / {} ({:.0}%)",
+                    format_size_with(stats.transferred_bytes, UnitStyle::Binary, language),
+                    format_size_with(stats.total_bytes, UnitStyle::Binary, language),
+                    fraction * 100.0
+                )),
+        );
+        
+        ui.label(format!("⚡ {}", stats.speed_formatted_in(language)));
+        ui.label(format!("{} {}", tr.eta, stats.eta_clock_formatted_in(language)));
+        
+        let ratio = stats.compression_ratio();
+        if ratio < 0.99 {
+            ui.label(format!("{} -{:.1}%", tr.compression_stats, (1.0 - ratio) * 100.0));
+        }
+    });
+}
+
 impl App {
+    /// Индикатор транспорта и состояния соединения
+    pub fn render_transport_status(&self, ui: &mut egui::Ui) {
+        let t = self.t();
+        let (color, hint) = match self.transport_health() {
+            TransportHealth::Idle => (egui::Color32::GRAY, t.health_idle),
+            TransportHealth::Flowing => (egui::Color32::GREEN, t.health_flowing),
+            TransportHealth::Stalled => (egui::Color32::YELLOW, t.health_stalled),
+            TransportHealth::Error => (egui::Color32::RED, t.health_error),
+        };
+        ui.horizontal(|ui| {
+            ui.colored_label(color, "●");
+            ui.label(self.transport_type.name());
+        }).response.on_hover_text(hint);
+    }
+    
     /// Отрисовать список получателей
     pub fn render_targets_list(&mut self, ui: &mut egui::Ui) {
         if self.targets.is_empty() {
             return;
         }
+        let t = self.t();
         
         egui::ScrollArea::vertical()
             .id_salt("targets_scroll")
             .max_height(100.0)
             .show(ui, |ui| {
                 let mut to_remove = None;
+                let mut to_cancel = None;
                 
                 for (idx, target) in self.targets.iter().enumerate() {
                     ui.horizontal(|ui| {
@@ -24,6 +69,7 @@ impl App {
                         let (icon, color) = match &target.status {
                             TargetStatus::Pending => ("⏳", egui::Color32::GRAY),
                             TargetStatus::Connecting => ("🔄", egui::Color32::YELLOW),
+                            TargetStatus::Handshaking => ("🤝", egui::Color32::YELLOW),
                             TargetStatus::Transferring => ("📤", egui::Color32::LIGHT_BLUE),
                             TargetStatus::Completed => ("✅", egui::Color32::GREEN),
                             TargetStatus::Error(_) => ("❌", egui::Color32::RED),
@@ -32,6 +78,16 @@ impl App {
                         ui.label(&target.address);
                         
                         // Прогресс для активных
+                        // Долгое подключение не должно выглядеть как зависание
+                        let phase = match target.status {
+                            TargetStatus::Connecting => Some(t.connecting),
+                            TargetStatus::Handshaking => Some(t.handshaking),
+                            _ => None,
+                        };
+                        if let Some(phase) = phase {
+                            ui.colored_label(egui::Color32::YELLOW, format!("{} {}s", phase, target.status_secs()));
+                        }
+                        
                         if target.status == TargetStatus::Transferring {
                             let progress = target.files_completed as f32 / self.files.len().max(1) as f32;
                             ui.add(
@@ -50,11 +106,23 @@ impl App {
                         if self.can_edit() && ui.small_button("❌").clicked() {
                             to_remove = Some(idx);
                         }
+                        
+                        // Остановка одного получателя во время отправки
+                        let active = !matches!(target.status, TargetStatus::Completed | TargetStatus::Error(_));
+                        if self.is_running && active && !self.target_cancels.is_target_cancelled(idx)
+                            && ui.small_button("✖").on_hover_text(t.cancel_target).clicked()
+                        {
+                            to_cancel = Some(idx);
+                        }
                     });
                 }
                 
                 if let Some(idx) = to_remove {
                     self.targets.remove(idx);
+                    self.resume_probe_stale = true;
+                }
+                if let Some(idx) = to_cancel {
+                    self.cancel_target(idx);
                 }
             });
     }
@@ -75,6 +143,10 @@ impl App {
                 }
                 
                 let mut to_remove = None;
+                let t = self.t();
+                let resume_offsets = self.resume_probe.as_ref()
+                    .filter(|_| !self.is_running)
+                    .map(|probe| &probe.offsets);
                 
                 for (idx, file) in self.files.iter().enumerate() {
                     ui.horizontal(|ui| {
@@ -92,7 +164,7 @@ impl App {
                         ui.label(path_display).on_hover_text(&file.relative_path);
                         
                         // Размер
-                        ui.label(format!("({})", format_size(file.size)));
+                        ui.label(format!("({})", self.format_size(file.size)));
                         
                         // Прогресс
                         if file.status == FileStatus::Transferring {
@@ -103,6 +175,15 @@ impl App {
                             );
                         } else if file.status == FileStatus::Completed {
                             ui.label("✓");
+                        } else if let Some(&offset) = resume_offsets.and_then(|offsets| offsets.get(idx)) {
+                            // Найденная у получателя докачка
+                            if offset >= file.size && file.size > 0 {
+                                ui.colored_label(egui::Color32::GRAY, format!("✓ {}", t.resume_already_there));
+                            } else if offset > 0 {
+                                let percent = offset as f64 / file.size as f64 * 100.0;
+                                ui.colored_label(egui::Color32::LIGHT_BLUE, format!("↻ {:.0}%", percent))
+                                    .on_hover_text(self.format_size(offset));
+                            }
                         }
                         
                         // Кнопка удаления
@@ -114,20 +195,37 @@ impl App {
                 
                 if let Some(idx) = to_remove {
                     self.files.remove(idx);
+                    self.resume_probe_stale = true;
                 }
             });
     }
     
     /// Отрисовать список полученных файлов
-    pub fn render_received_files(&self, ui: &mut egui::Ui) {
+    pub fn render_received_files(&mut self, ui: &mut egui::Ui) {
         let available_height = ui.available_height().max(100.0);
+        let t = self.t();
+        let mut to_reveal = None;
         
         egui::ScrollArea::vertical()
             .id_salt("received_files_scroll")
             .max_height(available_height)
             .auto_shrink([false, false])
             .show(ui, |ui| {
-                if self.received_files.is_empty() {
+                // Принимаемые сейчас - сверху, с прогрессом
+                for incoming in &self.incoming_files {
+                    ui.horizontal(|ui| {
+                        ui.label("📥");
+                        ui.label(truncate_string(&incoming.name, 50)).on_hover_text(&incoming.name);
+                        let progress = incoming.received as f32 / incoming.size.max(1) as f32;
+                        ui.add(
+                            egui::ProgressBar::new(progress)
+                                .desired_width(120.0)
+                                .text(format!("{} / {}", self.format_size(incoming.received), self.format_size(incoming.size))),
+                        );
+                    });
+                }
+                
+                if self.received_files.is_empty() && self.incoming_files.is_empty() {
                     ui.colored_label(egui::Color32::GRAY, "Пока нет полученных файлов");
                 } else {
                     for (name, size) in &self.received_files {
@@ -135,12 +233,28 @@ impl App {
                             ui.label("✅");
                             // Обрезаем длинные пути (безопасно для UTF-8)
                             let name_display = truncate_string(name, 50);
-                            ui.label(name_display).on_hover_text(name);
-                            ui.label(format!("({})", format_size(*size)));
+                            let path = self.save_directory.join(name);
+                            ui.add(egui::Label::new(name_display).sense(egui::Sense::click()))
+                                .on_hover_text(name)
+                                .context_menu(|ui| {
+                                    // Перетаскивание наружу egui не поддерживает - копируем путь
+                                    if ui.button(t.copy_path).clicked() {
+                                        ui.ctx().copy_text(path.display().to_string());
+                                        ui.close_menu();
+                                    }
+                                    if ui.button(t.show_in_folder).clicked() {
+                                        to_reveal = Some(path.clone());
+                                        ui.close_menu();
+                                    }
+                                });
+                            ui.label(format!("({})", self.format_size(*size)));
                         });
                     }
                 }
             });
+        
+        if let Some(path) = to_reveal {
+            self.open_in_file_manager(&path);
+        }
     }
 }
-
@@ -1,5 +1,10 @@
 //! Вспомогательные утилиты
 
+use crate::extract::{DEFAULT_MAX_PATH_COMPONENTS, DEFAULT_MAX_PATH_LEN};
+use crate::i18n::Language;
+use crate::network::{ServerOptions, TransportType};
+use crate::protocol::DEFAULT_PORT;
+
 /// Безопасно обрезает строку до max_chars символов (не байт!) с начала
 /// Если строка длиннее - показывает "..." и конец строки
 pub fn truncate_string(s: &str, max_chars: usize) -> String {
@@ -12,20 +17,399 @@ pub fn truncate_string(s: &str, max_chars: usize) -> String {
     }
 }
 
+/// Система единиц для размеров
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnitStyle {
+    /// Степени 1024
+    #[default]
+    Binary,
+    /// Степени 1000
+    Decimal,
+}
+
 /// Форматирование размера файла в человекочитаемый вид
 pub fn format_size(size: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-    
-    if size >= GB {
-        format!("{:.2} ГБ", size as f64 / GB as f64)
-    } else if size >= MB {
-        format!("{:.2} МБ", size as f64 / MB as f64)
-    } else if size >= KB {
-        format!("{:.2} КБ", size as f64 / KB as f64)
+    format_size_with(size, UnitStyle::Binary, Language::Russian)
+}
+
+/// Форматирование размера с выбором системы единиц и языка подписей
+pub fn format_size_with(size: u64, style: UnitStyle, language: Language) -> String {
+    let kb: u64 = match style {
+        UnitStyle::Binary => 1024,
+        UnitStyle::Decimal => 1000,
+    };
+    let mb = kb * kb;
+    let gb = mb * kb;
+    
+    let [b_label, kb_label, mb_label, gb_label] = language.units().bytes;
+    
+    if size >= gb {
+        format!("{:.2} {}", size as f64 / gb as f64, gb_label)
+    } else if size >= mb {
+        format!("{:.2} {}", size as f64 / mb as f64, mb_label)
+    } else if size >= kb {
+        format!("{:.2} {}", size as f64 / kb as f64, kb_label)
+    } else {
+        format!("{} {}", size, b_label)
+    }
+}
+
+/// Разобрать размер вида "1MB", "512K", "64 KiB", "4096" (двоичные единицы)
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    
+    let multiplier: u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1024,
+        "M" | "MB" | "MIB" => 1024 * 1024,
+        "G" | "GB" | "GIB" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    
+    Some((number * multiplier as f64) as u64)
+}
+
+/// Разобрать выбор из нумерованного списка: "1,3", "2-4", "all" (номера с 1).
+/// Возвращает индексы с 0, без повторов, в порядке ввода
+pub fn parse_selection(input: &str, count: usize) -> Result<Vec<usize>, String> {
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("all") || input == "*" {
+        return Ok((0..count).collect());
+    }
+    
+    let number = |s: &str| match s.trim().parse::<usize>() {
+        Ok(n) if (1..=count).contains(&n) => Ok(n - 1),
+        _ => Err(format!("Нет такого номера: {} (от 1 до {})", s.trim(), count)),
+    };
+    let mut selected = Vec::new();
+    for part in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (number(first)?, number(last)?),
+            None => (number(part)?, number(part)?),
+        };
+        if first > last {
+            return Err(format!("Неверный диапазон: {}", part));
+        }
+        for idx in first..=last {
+            if !selected.contains(&idx) {
+                selected.push(idx);
+            }
+        }
+    }
+    if selected.is_empty() {
+        return Err("Ничего не выбрано".to_string());
+    }
+    Ok(selected)
+}
+
+/// Правила имён файлов целевой системы
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetOs {
+    /// Запрещены `<>:"/\\|?*`, управляющие символы, точка или пробел в конце
+    /// и зарезервированные имена (CON, PRN, AUX, NUL, COM1-9, LPT1-9)
+    Windows,
+    /// Запрещены только `/` и нулевой байт
+    Unix,
+}
+
+impl TargetOs {
+    /// Система, на которой запущена программа
+    pub fn current() -> Self {
+        if cfg!(windows) { Self::Windows } else { Self::Unix }
+    }
+}
+
+/// Зарезервированные имена устройств Windows (с любым расширением)
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Сделать имя файла (один компонент пути) допустимым на `target_os`.
+///
+/// Запрещённые символы заменяются на `_`, к зарезервированному имени
+/// добавляется `_` (`aux.txt` -> `aux_.txt`). Допустимое имя не меняется
+pub fn sanitize_filename(name: &str, target_os: TargetOs) -> String {
+    let mut safe: String = match target_os {
+        TargetOs::Windows => name
+            .chars()
+            .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+            .collect(),
+        TargetOs::Unix => name.chars().map(|c| if c == '/' || c == '\0' { '_' } else { c }).collect(),
+    };
+    
+    if target_os == TargetOs::Windows {
+        // Windows молча отбрасывает точки и пробелы в конце имени
+        let kept = safe.trim_end_matches(['.', ' ']).len();
+        if kept < safe.len() {
+            let trailing = safe.len() - kept;
+            safe.truncate(kept);
+            safe.push_str(&"_".repeat(trailing));
+        }
+        
+        let stem_len = safe.find('.').unwrap_or(safe.len());
+        let stem = safe[..stem_len].trim_end();
+        if WINDOWS_RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved)) {
+            safe.insert(stem.len(), '_');
+        }
+    }
+    
+    if safe.is_empty() {
+        return "_".to_string();
+    }
+    safe
+}
+
+/// Пространство имён xattr, которое передаётся и восстанавливается.
+/// `security.*`, `trusted.*` и `system.*` (ACL, SELinux, capabilities) не трогаем:
+/// их значения меняют права доступа к файлу на стороне получателя
+pub const XATTR_ALLOWED_PREFIX: &str = "user.";
+
+/// Расширенные атрибуты файла из пространства `user.*`. Атрибуты, которые
+/// не удалось прочитать, и имена не в UTF-8 пропускаются; не на Unix - пусто
+pub fn read_xattrs(path: &std::path::Path) -> Vec<(String, Vec<u8>)> {
+    #[cfg(unix)]
+    {
+        let Ok(names) = xattr::list(path) else {
+            return Vec::new();
+        };
+        names
+            .filter_map(|name| {
+                let name = name.into_string().ok()?;
+                if !name.starts_with(XATTR_ALLOWED_PREFIX) {
+                    return None;
+                }
+                let value = xattr::get(path, &name).ok()??;
+                Some((name, value))
+            })
+            .collect()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Vec::new()
+    }
+}
+
+/// Записать расширенные атрибуты `user.*` на файл - все, какие получится.
+/// Err перечисляет отвергнутые: другие пространства имён, нет прав,
+/// ФС не поддерживает xattr
+pub fn apply_xattrs(path: &std::path::Path, xattrs: &[(String, Vec<u8>)]) -> Result<(), String> {
+    #[cfg(unix)]
+    let failed: Vec<&str> = xattrs
+        .iter()
+        .filter(|(name, value)| !name.starts_with(XATTR_ALLOWED_PREFIX) || xattr::set(path, name, value).is_err())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    #[cfg(not(unix))]
+    let failed: Vec<&str> = xattrs.iter().map(|(name, _)| name.as_str()).collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Не удалось записать атрибуты {}: {}", path.display(), failed.join(", ")))
+    }
+}
+
+/// Расширенные атрибуты из PAX-заголовков записи tar (`SCHILY.xattr.<имя>`) -
+/// в том же виде, что `read_xattrs`
+pub fn pax_xattrs<R: std::io::Read>(entry: &mut tar::Entry<R>) -> Vec<(String, Vec<u8>)> {
+    let Ok(Some(extensions)) = entry.pax_extensions() else {
+        return Vec::new();
+    };
+    extensions
+        .filter_map(|extension| {
+            let extension = extension.ok()?;
+            let name = extension.key().ok()?.strip_prefix("SCHILY.xattr.")?.to_string();
+            Some((name, extension.value_bytes().to_vec()))
+        })
+        .collect()
+}
+
+/// Открыть путь в файловом менеджере ОС.
+/// Для файла открывается содержащая его папка (с выделением файла, где это поддерживается)
+pub fn reveal_in_file_manager(path: &std::path::Path) -> Result<(), String> {
+    if !path.exists() {
+        return Err(format!("Путь не существует: {}", path.display()));
+    }
+    if path.is_dir() {
+        return opener::open(path).map_err(|e| format!("Не удалось открыть {}: {}", path.display(), e));
+    }
+    
+    #[cfg(target_os = "windows")]
+    let selected = std::process::Command::new("explorer")
+        .arg(format!("/select,{}", path.display()))
+        .spawn()
+        .is_ok();
+    #[cfg(target_os = "macos")]
+    let selected = std::process::Command::new("open")
+        .arg("-R")
+        .arg(path)
+        .spawn()
+        .is_ok();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let selected = false; // Единого способа выделить файл нет - открываем папку
+    
+    if selected {
+        return Ok(());
+    }
+    let folder = path.parent().unwrap_or(path);
+    opener::open(folder).map_err(|e| format!("Не удалось открыть {}: {}", folder.display(), e))
+}
+
+/// Встроенный звук завершения передачи
+#[cfg(feature = "sound")]
+const NOTIFICATION_SOUND: &[u8] = include_bytes!("../assets/complete.wav");
+
+/// Проиграть звук уведомления в фоновом потоке, не блокируя вызывающего.
+///
+/// `path` - свой файл (wav/ogg), при ошибке его чтения играет встроенный звук.
+/// Без фичи `sound` или без аудиоустройства ничего не делает.
+pub fn play_notification_sound(path: Option<&std::path::Path>) {
+    #[cfg(feature = "sound")]
+    {
+        let path = path.map(|p| p.to_path_buf());
+        std::thread::spawn(move || {
+            let _ = play_sound_blocking(path.as_deref());
+        });
+    }
+    #[cfg(not(feature = "sound"))]
+    let _ = path;
+}
+
+#[cfg(feature = "sound")]
+fn play_sound_blocking(path: Option<&std::path::Path>) -> Result<(), String> {
+    use rodio::{Decoder, OutputStream, Sink, Source};
+    
+    let (_stream, handle) = OutputStream::try_default().map_err(|e| e.to_string())?;
+    let sink = Sink::try_new(&handle).map_err(|e| e.to_string())?;
+    
+    let custom = path
+        .and_then(|p| std::fs::File::open(p).ok())
+        .and_then(|f| Decoder::new(std::io::BufReader::new(f)).ok());
+    match custom {
+        Some(source) => sink.append(source.convert_samples::<f32>()),
+        None => {
+            let source = Decoder::new(std::io::Cursor::new(NOTIFICATION_SOUND)).map_err(|e| e.to_string())?;
+            sink.append(source.convert_samples::<f32>());
+        }
+    }
+    sink.sleep_until_end();
+    Ok(())
+}
+
+/// Команда `toolza_cli receive` для получателя с настройками `options`.
+///
+/// Флаги со значениями по умолчанию опускаются. Автораспаковку CLI умеет только
+/// для tar.lz4/tar.zst (`--extract`), папку сохранения получатель выбирает сам
+pub fn build_receive_command(options: &ServerOptions, port: u16) -> String {
+    let mut args = vec!["toolza_cli".to_string(), "receive".to_string()];
+    let mut flag = |name: &str, value: Option<String>| {
+        args.push(name.to_string());
+        args.extend(value.map(|v| shell_quote(&v)));
+    };
+    
+    if port != DEFAULT_PORT {
+        flag("--port", Some(port.to_string()));
+    }
+    if options.transport_type != TransportType::Tcp {
+        flag("--transport", Some(options.transport_type.name().to_lowercase()));
+    }
+    if options.bind_addr != crate::network::DEFAULT_BIND_ADDR {
+        flag("--bind", Some(options.bind_addr.to_string()));
+    }
+    
+    let extract = &options.extract_options;
+    if extract.tar_lz4 || extract.tar_zst {
+        flag("--extract", None);
+        if extract.strip_components > 0 {
+            flag("--strip", Some(extract.strip_components.to_string()));
+        }
+        if let Some(extract_dir) = &options.extract_dir {
+            flag("--extract-to", Some(extract_dir.display().to_string()));
+        }
+    }
+    if let Some(temp_dir) = &options.temp_dir {
+        flag("--temp-dir", Some(temp_dir.display().to_string()));
+    }
+    if let Some(hook) = &options.post_hook {
+        flag("--post-hook", Some(hook.join(" ")));
+        if let Some(timeout) = options.hook_timeout.filter(|&t| t != crate::network::DEFAULT_HOOK_TIMEOUT) {
+            flag("--hook-timeout", Some(timeout.as_secs().to_string()));
+        }
+        if options.max_hooks != crate::network::DEFAULT_MAX_HOOKS {
+            flag("--max-hooks", Some(options.max_hooks.to_string()));
+        }
+    }
+    
+    let limits = &options.path_limits;
+    if limits.max_components != DEFAULT_MAX_PATH_COMPONENTS {
+        flag("--max-path-depth", Some(limits.max_components.to_string()));
+    }
+    if limits.max_path_len != DEFAULT_MAX_PATH_LEN {
+        flag("--max-path-len", Some(limits.max_path_len.to_string()));
+    }
+    if limits.long_paths {
+        flag("--long-paths", None);
+    }
+    if let Some(addr) = options.metrics_addr {
+        flag("--metrics-addr", Some(addr.to_string()));
+    }
+    if let Some(timeout) = options.timeout {
+        flag("--timeout", Some(timeout.as_secs().to_string()));
+    }
+    if let Some(template) = &options.name_template {
+        flag("--name-template", Some(template.clone()));
+    }
+    if let Some(path) = &options.audit_log {
+        flag("--audit-log", Some(path.display().to_string()));
+    }
+    if options.sanitize_names && !cfg!(windows) {
+        flag("--safe-names", None);
+    }
+    if !options.relay_to.is_empty() {
+        flag("--relay-to", Some(options.relay_to.join(",")));
+    }
+    
+    let tuning = &options.socket_tuning;
+    if let Some(size) = tuning.send_buf {
+        flag("--send-buf", Some(size.to_string()));
+    }
+    if let Some(size) = tuning.recv_buf {
+        flag("--recv-buf", Some(size.to_string()));
+    }
+    if !tuning.nodelay {
+        flag("--no-nodelay", None);
+    }
+    
+    args.join(" ")
+}
+
+/// Шаблон команды `toolza_cli send` на этот получатель (`<FILES>` - подставить файлы)
+pub fn build_send_command(host: &str, port: u16, transport_type: TransportType) -> String {
+    let target = if port == DEFAULT_PORT {
+        host.to_string()
     } else {
-        format!("{} Б", size)
+        format!("{}:{}", host, port)
+    };
+    let mut command = format!("toolza_cli send -t {}", shell_quote(&target));
+    if transport_type != TransportType::Tcp {
+        command.push_str(&format!(" --transport {}", transport_type.name().to_lowercase()));
+    }
+    command.push_str(" <FILES>");
+    command
+}
+
+/// Аргумент для shell: в одинарных кавычках, если есть спецсимволы
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
     }
 }
 
@@ -46,10 +430,70 @@ pub fn get_local_ip_string() -> String {
         .unwrap_or_else(|| "Не определён".to_string())
 }
 
+/// Случайный секрет из системного генератора: 32 байта в hex
+pub fn random_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(std::io::Error::other)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Дочитать в SHA-256 первые `len` байт из `reader` (начало докачиваемого файла).
+/// После вызова позиция чтения - `len`
+pub async fn sha256_prefix(
+    reader: &mut (impl tokio::io::AsyncRead + Unpin),
+    len: u64,
+    hasher: &mut sha2::Sha256,
+) -> std::io::Result<()> {
+    use sha2::Digest;
+    use tokio::io::AsyncReadExt;
+    
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut left = len;
+    while left > 0 {
+        let want = left.min(buffer.len() as u64) as usize;
+        let n = reader.read(&mut buffer[..want]).await?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        hasher.update(&buffer[..n]);
+        left -= n as u64;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_sanitize_filename_forbidden_chars() {
+        assert_eq!(sanitize_filename("report:v2.txt", TargetOs::Windows), "report_v2.txt");
+        assert_eq!(sanitize_filename("a<b>c|d?e*f\"g\\h", TargetOs::Windows), "a_b_c_d_e_f_g_h");
+        assert_eq!(sanitize_filename("tab\there", TargetOs::Windows), "tab_here");
+        assert_eq!(sanitize_filename("notes. ", TargetOs::Windows), "notes__");
+        assert_eq!(sanitize_filename("отчёт 2024.pdf", TargetOs::Windows), "отчёт 2024.pdf");
+        
+        // На Unix двоеточие допустимо
+        assert_eq!(sanitize_filename("report:v2.txt", TargetOs::Unix), "report:v2.txt");
+        assert_eq!(sanitize_filename("a\0b", TargetOs::Unix), "a_b");
+        assert_eq!(sanitize_filename("", TargetOs::Unix), "_");
+    }
+    
+    #[test]
+    fn test_sanitize_filename_reserved_names() {
+        assert_eq!(sanitize_filename("aux", TargetOs::Windows), "aux_");
+        assert_eq!(sanitize_filename("CON", TargetOs::Windows), "CON_");
+        assert_eq!(sanitize_filename("nul.txt", TargetOs::Windows), "nul_.txt");
+        assert_eq!(sanitize_filename("Com1.tar.gz", TargetOs::Windows), "Com1_.tar.gz");
+        assert_eq!(sanitize_filename("lpt9", TargetOs::Windows), "lpt9_");
+        
+        // Похожие, но допустимые имена не трогаем
+        assert_eq!(sanitize_filename("auxiliary.txt", TargetOs::Windows), "auxiliary.txt");
+        assert_eq!(sanitize_filename("com10", TargetOs::Windows), "com10");
+        assert_eq!(sanitize_filename("my.con", TargetOs::Windows), "my.con");
+        assert_eq!(sanitize_filename("aux", TargetOs::Unix), "aux");
+    }
+    
     #[test]
     fn test_format_size_bytes() {
         assert_eq!(format_size(0), "0 Б");
@@ -79,6 +523,55 @@ mod tests {
         assert_eq!(format_size(1024u64 * 1024 * 1024 * 100), "100.00 ГБ");
     }
     
+    #[test]
+    fn test_format_size_with_languages() {
+        assert_eq!(format_size_with(512, UnitStyle::Binary, Language::English), "512 B");
+        assert_eq!(format_size_with(2048, UnitStyle::Binary, Language::English), "2.00 KB");
+        assert_eq!(format_size_with(1024 * 1024, UnitStyle::Binary, Language::Russian), "1.00 МБ");
+        assert_eq!(format_size_with(1024 * 1024 * 1024, UnitStyle::Binary, Language::Ukrainian), "1.00 ГБ");
+        assert_eq!(format_size_with(1, UnitStyle::Binary, Language::Ukrainian), "1 Б");
+    }
+    
+    #[test]
+    fn test_format_size_with_decimal_boundary() {
+        assert_eq!(format_size_with(999, UnitStyle::Decimal, Language::English), "999 B");
+        assert_eq!(format_size_with(1000, UnitStyle::Decimal, Language::English), "1.00 KB");
+        assert_eq!(format_size_with(1000, UnitStyle::Binary, Language::English), "1000 B");
+        assert_eq!(format_size_with(1024, UnitStyle::Decimal, Language::English), "1.02 KB");
+        assert_eq!(format_size_with(1024, UnitStyle::Binary, Language::English), "1.00 KB");
+        assert_eq!(format_size_with(1_000_000, UnitStyle::Decimal, Language::Russian), "1.00 МБ");
+    }
+    
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("512K"), Some(512 * 1024));
+        assert_eq!(parse_size("1MB"), Some(1024 * 1024));
+        assert_eq!(parse_size("1.5 mb"), Some(1024 * 1024 * 3 / 2));
+        assert_eq!(parse_size("2GiB"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("10XB"), None);
+        assert_eq!(parse_size("MB"), None);
+    }
+    
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("2", 3), Ok(vec![1]));
+        assert_eq!(parse_selection(" 3, 1 ", 3), Ok(vec![2, 0]));
+        assert_eq!(parse_selection("1-3,2", 4), Ok(vec![0, 1, 2]));
+        assert_eq!(parse_selection("all", 2), Ok(vec![0, 1]));
+        assert!(parse_selection("0", 3).is_err());
+        assert!(parse_selection("4", 3).is_err());
+        assert!(parse_selection("3-1", 3).is_err());
+        assert!(parse_selection("", 3).is_err());
+        assert!(parse_selection("abc", 3).is_err());
+    }
+    
+    #[test]
+    fn test_reveal_missing_path() {
+        let result = reveal_in_file_manager(std::path::Path::new("/nonexistent/toolza/file.txt"));
+        assert!(result.unwrap_err().contains("не существует"));
+    }
+    
     #[test]
     fn test_get_local_ip_returns_valid_or_none() {
         // Этот тест просто проверяет что функция не паникует
@@ -96,5 +589,82 @@ mod tests {
         // Либо IP адрес, либо "Не определён"
         assert!(ip_str.contains('.') || ip_str == "Не определён");
     }
+    
+    #[test]
+    fn test_build_receive_command() {
+        assert_eq!(build_receive_command(&ServerOptions::default(), DEFAULT_PORT), "toolza_cli receive");
+        
+        let options = ServerOptions {
+            transport_type: TransportType::Udp,
+            extract_options: crate::network::ExtractOptions {
+                tar_lz4: true,
+                strip_components: 1,
+                ..Default::default()
+            },
+            temp_dir: Some(std::path::PathBuf::from("/tmp/my partial")),
+            extract_dir: Some(std::path::PathBuf::from("/srv/unpacked")),
+            socket_tuning: crate::network::SocketTuning { recv_buf: Some(4 * 1024 * 1024), ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(
+            build_receive_command(&options, 9000),
+            "toolza_cli receive --port 9000 --transport udp --extract --strip 1 --extract-to /srv/unpacked --temp-dir '/tmp/my partial' --recv-buf 4194304",
+        );
+        
+        let options = ServerOptions { bind_addr: "10.8.0.2".parse().unwrap(), ..Default::default() };
+        assert_eq!(build_receive_command(&options, DEFAULT_PORT), "toolza_cli receive --bind 10.8.0.2");
+        
+        let options = ServerOptions { audit_log: Some(std::path::PathBuf::from("/var/log/toolza.jsonl")), ..Default::default() };
+        assert_eq!(build_receive_command(&options, DEFAULT_PORT), "toolza_cli receive --audit-log /var/log/toolza.jsonl");
+        
+        let options = ServerOptions { relay_to: vec!["10.0.0.7".to_string(), "10.0.0.8:9000".to_string()], ..Default::default() };
+        assert_eq!(build_receive_command(&options, DEFAULT_PORT), "toolza_cli receive --relay-to 10.0.0.7,10.0.0.8:9000");
+        
+        let options = ServerOptions { timeout: Some(std::time::Duration::from_secs(300)), ..Default::default() };
+        assert_eq!(build_receive_command(&options, DEFAULT_PORT), "toolza_cli receive --timeout 300");
+    }
+    
+    #[test]
+    fn test_build_send_command() {
+        assert_eq!(
+            build_send_command("192.168.1.5", DEFAULT_PORT, TransportType::Tcp),
+            "toolza_cli send -t 192.168.1.5 <FILES>",
+        );
+        assert_eq!(
+            build_send_command("192.168.1.5", 9000, TransportType::Udp),
+            "toolza_cli send -t 192.168.1.5:9000 --transport udp <FILES>",
+        );
+    }
+    
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("plain/path-1.txt"), "plain/path-1.txt");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_xattrs_only_user_namespace() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, b"data").unwrap();
+        if xattr::set(&path, "user.toolza.probe", b"1").is_err() {
+            return; // ФС без user xattr
+        }
+        
+        let xattrs = vec![
+            ("user.toolza.origin".to_string(), b"lab".to_vec()),
+            ("trusted.toolza".to_string(), b"x".to_vec()),
+            ("security.capability".to_string(), b"x".to_vec()),
+        ];
+        let err = apply_xattrs(&path, &xattrs).unwrap_err();
+        assert!(err.contains("trusted.toolza") && err.contains("security.capability"));
+        assert_eq!(xattr::get(&path, "user.toolza.origin").unwrap().as_deref(), Some(&b"lab"[..]));
+        assert_eq!(xattr::get(&path, "trusted.toolza").unwrap(), None);
+        
+        let names: Vec<String> = read_xattrs(&path).into_iter().map(|(name, _)| name).collect();
+        assert!(names.iter().all(|name| name.starts_with(XATTR_ALLOWED_PREFIX)));
+    }
 }
 
@@ -4,8 +4,50 @@ use std::path::PathBuf;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::net::TcpListener;
-use toolza_sender::protocol::{FileInfo, Message};
-use toolza_sender::network::TransferEvent;
+use toolza_sender::protocol::{ConnectionIntent, FileInfo, Message};
+use toolza_sender::network::{SkipReason, TransferEvent, TransportType};
+use toolza_sender::extract::ExtractResult;
+
+/// Получатель для теста на свободном порту `127.0.0.1`. Порт занят до возврата,
+/// поэтому ждать готовности не нужно; при drop сервер останавливается
+struct TestServer {
+    addr: String,
+    port: u16,
+    cancel: toolza_sender::network::CancelToken,
+    handle: Option<tokio::task::JoinHandle<Result<(), String>>>,
+}
+
+impl TestServer {
+    /// Остановить сервер и дождаться его завершения
+    async fn stop(mut self) {
+        self.cancel.cancel();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.await;
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+async fn spawn_test_server(
+    save_dir: &std::path::Path,
+    options: toolza_sender::network::ServerOptions,
+) -> (TestServer, mpsc::UnboundedReceiver<TransferEvent>) {
+    use toolza_sender::network::{bind_server, serve, CancelToken, ServerOptions};
+    
+    let options = ServerOptions { bind_addr: std::net::Ipv4Addr::LOCALHOST.into(), ..options };
+    let listener = bind_server(0, &options).await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (event_tx, event_rx) = mpsc::unbounded_channel();
+    let cancel = CancelToken::new();
+    let handle = tokio::spawn(serve(listener, save_dir.to_path_buf(), options, event_tx, cancel.flag()));
+    let server = TestServer { addr: format!("127.0.0.1:{}", port), port, cancel, handle: Some(handle) };
+    (server, event_rx)
+}
 
 /// Тест: базовая сериализация/десериализация протокола
 #[test]
@@ -351,6 +393,548 @@ async fn test_real_file_transfer() {
     assert_eq!(received, test_content);
 }
 
+/// Тест: сервер останавливается по `CancelToken`
+#[tokio::test]
+async fn test_run_server_with_cancel() {
+    use toolza_sender::network::{run_server_with_cancel, CancelToken, ServerOptions};
+    
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let port = {
+        let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap().port()
+    };
+    
+    let (tx, _rx) = mpsc::unbounded_channel();
+    let cancel = CancelToken::new();
+    let server = tokio::spawn(run_server_with_cancel(
+        port,
+        temp_dir.path().to_path_buf(),
+        ServerOptions::default(),
+        tx,
+        cancel.clone(),
+    ));
+    
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!server.is_finished());
+    
+    cancel.cancel();
+    let result = tokio::time::timeout(Duration::from_secs(2), server).await
+        .expect("сервер не остановился")
+        .unwrap();
+    assert!(result.is_ok());
+}
+
+/// Тест: журнал аудита получателя фиксирует подключение, файл и отключение
+#[tokio::test]
+async fn test_receiver_audit_log() {
+    use toolza_sender::network::{send_files_to_multiple, ServerOptions};
+    
+    let src = tempfile::TempDir::new().unwrap();
+    let dst = tempfile::TempDir::new().unwrap();
+    let file = src.path().join("report.txt");
+    std::fs::write(&file, b"audit me").unwrap();
+    let audit_path = dst.path().join("audit.jsonl");
+    
+    let options = ServerOptions::builder().audit_log(&audit_path).build().unwrap();
+    let (server, _server_rx) = spawn_test_server(&dst.path().join("files"), options).await;
+    
+    let (tx, _rx) = mpsc::unbounded_channel();
+    let files = vec![FileInfo::new(file).unwrap()];
+    send_files_to_multiple(vec![server.addr.clone()], files, false, tx).await;
+    
+    let mut events = Vec::new();
+    for _ in 0..100 {
+        let text = std::fs::read_to_string(&audit_path).unwrap_or_default();
+        events = text.lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect();
+        if events.iter().any(|e| e["event"] == "disconnected") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    server.stop().await;
+    
+    let kinds: Vec<&str> = events.iter().map(|e| e["event"].as_str().unwrap()).collect();
+    assert_eq!(kinds, ["connected", "file_received", "disconnected"]);
+    assert_eq!(events[1]["file"], "report.txt");
+    assert_eq!(events[1]["size"], 8);
+    assert!(events[0]["peer"].as_str().unwrap().starts_with("127.0.0.1:"));
+}
+
+/// Тест: обрыв посреди файла и докачка с места обрыва (`check_resume` / `ResumeAck`)
+#[tokio::test]
+async fn test_resume_after_dropped_connection() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use toolza_sender::network::{ServerOptions};
+    use toolza_sender::network::sender::{send_files_to_target_with_options, SendOptions};
+    
+    let src = tempfile::TempDir::new().unwrap();
+    let dst = tempfile::TempDir::new().unwrap();
+    let content: Vec<u8> = (0..256 * 1024).map(|i| (i * 7 % 251) as u8).collect();
+    let half = content.len() / 2;
+    std::fs::write(src.path().join("data.bin"), &content).unwrap();
+    
+    let (server, _server_rx) = spawn_test_server(dst.path(), ServerOptions::default()).await;
+    let addr = server.addr.clone();
+    
+    // Первая попытка: половина файла, затем соединение рвётся
+    let mut stream = tokio::net::TcpStream::connect(&addr).await.unwrap();
+    let start = Message::FileStart {
+        filename: "data.bin".to_string(),
+        size: content.len() as u64,
+        compressed: false,
+        offset: 0,
+        quick_hash: 1,
+    };
+    stream.write_all(&start.to_bytes().unwrap()).await.unwrap();
+    let mut ack = [0u8; 8];
+    stream.read_exact(&mut ack).await.unwrap();
+    assert!(matches!(Message::from_bytes(&ack[4..]).unwrap(), Message::Ack));
+    let chunk = Message::FileChunk { data: content[..half].to_vec(), original_size: half };
+    stream.write_all(&chunk.to_bytes().unwrap()).await.unwrap();
+    drop(stream);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!dst.path().join("data.bin").exists());
+    
+    // Вторая попытка настоящим отправителем: получатель отвечает ResumeAck с половиной
+    let files = vec![FileInfo::new(src.path().join("data.bin")).unwrap()];
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    send_files_to_target_with_options(0, addr, files, SendOptions::default(), tx).await.unwrap();
+    
+    let mut resumed_at = None;
+    let mut read_after_resume = None;
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            TransferEvent::FileResumed(0, 0, offset) => resumed_at = Some(offset),
+            TransferEvent::Progress(0, 0, transferred, original, _) => {
+                assert!(transferred >= half as u64, "докачка не должна начинаться с нуля");
+                read_after_resume = Some(original);
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(resumed_at, Some(half as u64));
+    // Повторно прочитана и отправлена только вторая половина
+    assert_eq!(read_after_resume, Some((content.len() - half) as u64));
+    
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(std::fs::read(dst.path().join("data.bin")).unwrap(), content);
+    assert!(!dst.path().join("data.bin.toolza-part").exists());
+    
+    server.stop().await;
+}
+
+/// Испортить байт в середине файла: первые и последние 4KB (их видит quick hash) не меняются
+fn corrupt_middle(path: &std::path::Path) {
+    let mut data = std::fs::read(path).unwrap();
+    let middle = data.len() / 2;
+    data[middle] ^= 0xFF;
+    std::fs::write(path, data).unwrap();
+}
+
+/// Тест: копия у получателя испорчена в середине, а quick hash совпадает.
+///
+/// Известный пробел: quick hash (FNV по размеру, первым и последним 4KB) середину
+/// не видит, поэтому готовый файл считается актуальным и не перекачивается,
+/// а `.toolza-part` при докачке не сверяется вовсе. Тест фиксирует текущее
+/// поведение; с более сильным хэшем ожидания должны смениться на перекачку
+#[tokio::test]
+async fn test_corrupted_receiver_copy_with_colliding_quick_hash() {
+    use toolza_sender::network::{ServerOptions};
+    use toolza_sender::network::sender::{send_files_to_target_with_options, SendOptions};
+    
+    let src = tempfile::TempDir::new().unwrap();
+    let dst = tempfile::TempDir::new().unwrap();
+    let content: Vec<u8> = (0..64 * 1024).map(|i| (i * 13 % 251) as u8).collect();
+    let half = content.len() / 2;
+    std::fs::write(src.path().join("full.bin"), &content).unwrap();
+    std::fs::write(src.path().join("part.bin"), &content).unwrap();
+    // Одинаковые файлы ушли бы ссылкой (дедупликация), а не FileStart
+    let options = SendOptions { dedupe: false, ..Default::default() };
+    
+    // Готовый файл нужного размера с испорченной серединой
+    std::fs::write(dst.path().join("full.bin"), &content).unwrap();
+    corrupt_middle(&dst.path().join("full.bin"));
+    // Недокачанная первая половина, тоже испорченная
+    std::fs::write(dst.path().join("part.bin.toolza-part"), &content[..half]).unwrap();
+    corrupt_middle(&dst.path().join("part.bin.toolza-part"));
+    
+    let (server, _server_rx) = spawn_test_server(dst.path(), ServerOptions::default()).await;
+    
+    let files = vec![
+        FileInfo::new(src.path().join("full.bin")).unwrap(),
+        FileInfo::new(src.path().join("part.bin")).unwrap(),
+    ];
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+    
+    let mut skipped = false;
+    let mut resumed_at = None;
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            TransferEvent::FileSkipped(0, 0, SkipReason::UpToDate) => skipped = true,
+            TransferEvent::FileResumed(0, 1, offset) => resumed_at = Some(offset),
+            _ => {}
+        }
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    
+    // Пробел: испорченный готовый файл принят за актуальный
+    assert!(skipped);
+    let full = std::fs::read(dst.path().join("full.bin")).unwrap();
+    assert_ne!(full, content);
+    assert_eq!(full.len(), content.len());
+    
+    // Пробел: докачка продолжила испорченную половину, вторая половина верна
+    assert_eq!(resumed_at, Some(half as u64));
+    let part = std::fs::read(dst.path().join("part.bin")).unwrap();
+    assert_ne!(&part[..half], &content[..half]);
+    assert_eq!(&part[half..], &content[half..]);
+    
+    server.stop().await;
+}
+
+/// Тест: verify_integrity ловит испорченное начало при докачке - копия удаляется,
+/// повторная отправка передаёт файл заново
+#[tokio::test]
+async fn test_verify_integrity_rejects_corrupted_resume() {
+    use toolza_sender::network::{ServerOptions};
+    use toolza_sender::network::sender::{send_files_to_target_with_options, SendOptions};
+    
+    let src = tempfile::TempDir::new().unwrap();
+    let dst = tempfile::TempDir::new().unwrap();
+    let content: Vec<u8> = (0..64 * 1024).map(|i| (i * 13 % 251) as u8).collect();
+    let half = content.len() / 2;
+    std::fs::write(src.path().join("part.bin"), &content).unwrap();
+    std::fs::write(dst.path().join("part.bin.toolza-part"), &content[..half]).unwrap();
+    corrupt_middle(&dst.path().join("part.bin.toolza-part"));
+    
+    let (server, _server_rx) = spawn_test_server(dst.path(), ServerOptions { verify_integrity: true, ..Default::default() }).await;
+    
+    let options = SendOptions { verify_integrity: true, continue_on_error: true, ..Default::default() };
+    let files = vec![FileInfo::new(src.path().join("part.bin")).unwrap()];
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    send_files_to_target_with_options(0, server.addr.clone(), files.clone(), options.clone(), tx).await.unwrap();
+    
+    let mut corrupted = false;
+    let mut completed = false;
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            TransferEvent::FileCorrupted(0, 0) => corrupted = true,
+            TransferEvent::FileCompleted(0, 0) => completed = true,
+            _ => {}
+        }
+    }
+    assert!(corrupted);
+    assert!(!completed);
+    assert!(!dst.path().join("part.bin").exists());
+    assert!(!dst.path().join("part.bin.toolza-part").exists());
+    
+    // Испорченного начала больше нет - файл передаётся целиком и верно
+    let (tx, _rx) = mpsc::unbounded_channel();
+    send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(std::fs::read(dst.path().join("part.bin")).unwrap(), content);
+    
+    server.stop().await;
+}
+
+/// Тест: правила получателя отклоняют файл до записи на диск, остальные принимаются
+#[tokio::test]
+async fn test_receiver_rejects_blocked_files() {
+    use toolza_sender::network::{ServerOptions};
+    use toolza_sender::network::sender::{send_files_to_target_with_options, SendOptions};
+    
+    let src = tempfile::TempDir::new().unwrap();
+    let dst = tempfile::TempDir::new().unwrap();
+    std::fs::write(src.path().join("setup.exe"), b"MZ executable").unwrap();
+    std::fs::write(src.path().join("big.bin"), vec![7u8; 4096]).unwrap();
+    std::fs::write(src.path().join("notes.txt"), b"hello").unwrap();
+    
+    let options = ServerOptions {
+        blocked_extensions: vec!["exe".to_string()],
+        max_file_size: Some(1024),
+        ..Default::default()
+    };
+    let (server, mut server_rx) = spawn_test_server(dst.path(), options).await;
+    
+    let files = vec![
+        FileInfo::new(src.path().join("setup.exe")).unwrap(),
+        FileInfo::new(src.path().join("big.bin")).unwrap(),
+        FileInfo::new(src.path().join("notes.txt")).unwrap(),
+    ];
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx).await.unwrap();
+    
+    let mut errors = Vec::new();
+    let mut completed = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            TransferEvent::FileError(0, idx, e) => errors.push((idx, e)),
+            TransferEvent::FileCompleted(0, idx) => completed.push(idx),
+            _ => {}
+        }
+    }
+    assert_eq!(completed, vec![2]);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].0, 0);
+    assert!(errors[0].1.contains(".exe"), "{}", errors[0].1);
+    assert_eq!(errors[1].0, 1);
+    
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let mut rejected = Vec::new();
+    while let Ok(event) = server_rx.try_recv() {
+        if let TransferEvent::FileRejected(name, _) = event {
+            rejected.push(name);
+        }
+    }
+    assert_eq!(rejected, vec!["setup.exe".to_string(), "big.bin".to_string()]);
+    let mut saved: Vec<_> = std::fs::read_dir(dst.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+    saved.sort();
+    assert_eq!(saved, vec![std::ffi::OsString::from("notes.txt")]);
+    
+    server.stop().await;
+}
+
+/// Тест: получатель сообщает о каждом файле и ведёт прогресс по его номеру
+#[tokio::test]
+async fn test_receiver_progress_per_file() {
+    use toolza_sender::network::{ServerOptions};
+    use toolza_sender::network::sender::{send_files_to_target_with_options, SendOptions};
+    
+    let src = tempfile::TempDir::new().unwrap();
+    let dst = tempfile::TempDir::new().unwrap();
+    std::fs::write(src.path().join("a.bin"), vec![1u8; 3000]).unwrap();
+    std::fs::write(src.path().join("b.bin"), vec![2u8; 5000]).unwrap();
+    
+    let (server, mut server_rx) = spawn_test_server(dst.path(), ServerOptions::default()).await;
+    
+    let files = vec![
+        FileInfo::new(src.path().join("a.bin")).unwrap(),
+        FileInfo::new(src.path().join("b.bin")).unwrap(),
+    ];
+    let (tx, _rx) = mpsc::unbounded_channel();
+    send_files_to_target_with_options(0, server.addr.clone(), files, SendOptions::default(), tx).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    
+    let mut started = Vec::new();
+    let mut ended = Vec::new();
+    let mut last_progress = std::collections::HashMap::new();
+    while let Ok(event) = server_rx.try_recv() {
+        match event {
+            TransferEvent::FileReceiveStarted(idx, name, size) => started.push((idx, name, size)),
+            TransferEvent::FileReceiveEnded(idx) => ended.push(idx),
+            TransferEvent::Progress(0, idx, transferred, size, _) => {
+                last_progress.insert(idx, (transferred, size));
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(started.len(), 2);
+    assert_ne!(started[0].0, started[1].0);
+    assert_eq!((started[0].1.as_str(), started[0].2), ("a.bin", 3000));
+    assert_eq!((started[1].1.as_str(), started[1].2), ("b.bin", 5000));
+    assert_eq!(last_progress.get(&started[0].0), Some(&(3000, 3000)));
+    assert_eq!(last_progress.get(&started[1].0), Some(&(5000, 5000)));
+    // Строки прогресса закрываются: первая - началом второго файла, вторая - отключением
+    assert_eq!(ended, vec![started[0].0, started[1].0]);
+    
+    server.stop().await;
+}
+
+/// Тест: мелкие файлы раздаются нескольким соединениям с одним получателем
+#[tokio::test]
+async fn test_multiple_connections_per_target() {
+    use toolza_sender::network::{ServerOptions};
+    use toolza_sender::network::sender::{send_files_to_target_with_options, SendOptions};
+    
+    let src = tempfile::TempDir::new().unwrap();
+    let dst = tempfile::TempDir::new().unwrap();
+    let files: Vec<FileInfo> = (0..100)
+        .map(|i| {
+            let path = src.path().join(format!("small_{:03}.txt", i));
+            std::fs::write(&path, format!("файл {}", i)).unwrap();
+            FileInfo::new(path).unwrap()
+        })
+        .collect();
+    
+    let (server, mut server_rx) = spawn_test_server(dst.path(), ServerOptions::default()).await;
+    
+    let options = SendOptions { connections_per_target: 4, checksum: true, ..Default::default() };
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    send_files_to_target_with_options(0, server.addr.clone(), files, options, tx).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    
+    let mut completed = std::collections::HashSet::new();
+    let (mut connected, mut target_done, mut checksum) = (0, 0, false);
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            TransferEvent::Connected(0, _) => connected += 1,
+            TransferEvent::FileCompleted(0, idx) => assert!(completed.insert(idx)),
+            TransferEvent::TargetCompleted(0) => target_done += 1,
+            TransferEvent::TargetChecksum(0, _) => checksum = true,
+            _ => {}
+        }
+    }
+    assert_eq!(connected, 4);
+    assert_eq!(completed.len(), 100);
+    assert_eq!(target_done, 1);
+    assert!(checksum);
+    
+    let mut clients = 0;
+    while let Ok(event) = server_rx.try_recv() {
+        if let TransferEvent::ClientConnected(_, ConnectionIntent::Transfer) = event {
+            clients += 1;
+        }
+    }
+    assert_eq!(clients, 4);
+    for i in 0..100 {
+        let content = std::fs::read_to_string(dst.path().join(format!("small_{:03}.txt", i))).unwrap();
+        assert_eq!(content, format!("файл {}", i));
+    }
+    
+    server.stop().await;
+}
+
+/// Тест: разница для синхронизации по списку файлов получателя
+#[tokio::test]
+async fn test_fetch_sync_diff() {
+    use toolza_sender::network::{fetch_sync_diff, ServerOptions, SendOptions};
+    
+    let src = tempfile::TempDir::new().unwrap();
+    let dst = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(src.path().join("dir")).unwrap();
+    std::fs::create_dir_all(dst.path().join("dir")).unwrap();
+    for (name, content) in [("same.txt", "одинаковый"), ("changed.txt", "новая версия"), ("new.txt", "новый"), ("dir/keep.txt", "в папке")] {
+        std::fs::write(src.path().join(name), content).unwrap();
+    }
+    for (name, content) in [("same.txt", "одинаковый"), ("changed.txt", "старая версия"), ("dir/keep.txt", "в папке"), ("dir/old.txt", "удалён у отправителя"), ("other.txt", "чужой")] {
+        std::fs::write(dst.path().join(name), content).unwrap();
+    }
+    let files: Vec<FileInfo> = ["same.txt", "changed.txt", "new.txt", "dir/keep.txt"]
+        .iter()
+        .map(|name| {
+            let mut info = FileInfo::new(src.path().join(name)).unwrap();
+            info.relative_path = name.to_string();
+            info
+        })
+        .collect();
+    
+    let (server, _server_rx) = spawn_test_server(dst.path(), ServerOptions::default()).await;
+    
+    let diff = fetch_sync_diff(&server.addr.clone(), &files, &[], &SendOptions::default()).await.unwrap();
+    let mut to_transfer: Vec<_> = diff.to_transfer.iter().map(|f| f.relative_path.as_str()).collect();
+    to_transfer.sort();
+    let mut unchanged = diff.unchanged.clone();
+    unchanged.sort();
+    
+    assert_eq!(to_transfer, vec!["changed.txt", "new.txt"]);
+    assert_eq!(unchanged, vec!["dir/keep.txt", "same.txt"]);
+    // other.txt не из отправляемых корней - в разницу не попадает
+    assert_eq!(diff.remote_only, vec!["dir/old.txt"]);
+    assert_eq!(diff.transfer_size(), ("новая версия".len() + "новый".len()) as u64);
+    
+    server.stop().await;
+}
+
+/// Тест: имя файла с `..` или абсолютным путём обрывает передачу, за папку приёма ничего не пишется
+#[tokio::test]
+async fn test_receiver_rejects_path_traversal() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use toolza_sender::network::{ServerOptions};
+    
+    let root = tempfile::TempDir::new().unwrap();
+    let dst = root.path().join("dst");
+    std::fs::create_dir_all(&dst).unwrap();
+    
+    let (server, mut server_rx) = spawn_test_server(&dst, ServerOptions::default()).await;
+    
+    let absolute = root.path().join("absolute.txt").to_string_lossy().to_string();
+    let names = ["../escape.txt".to_string(), "sub/../../escape2.txt".to_string(), absolute, "../evil.tar.lz4".to_string()];
+    for name in &names {
+        let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", server.port)).await.unwrap();
+        let start = Message::FileStart {
+            filename: name.clone(),
+            size: 4,
+            compressed: false,
+            offset: 0,
+            quick_hash: 0,
+        };
+        stream.write_all(&start.to_bytes().unwrap()).await.unwrap();
+        // Вместо Ack получатель закрывает соединение
+        let mut reply = [0u8; 8];
+        assert!(stream.read_exact(&mut reply).await.is_err(), "{}", name);
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    
+    let mut errors = 0;
+    while let Ok(event) = server_rx.try_recv() {
+        if let TransferEvent::ConnectionError(_, e) = event {
+            assert!(e.contains("небезопасный путь"), "{}", e);
+            errors += 1;
+        }
+    }
+    assert_eq!(errors, names.len());
+    let mut entries: Vec<_> = std::fs::read_dir(root.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+    entries.sort();
+    assert_eq!(entries, vec!["dst"]);
+    
+    server.stop().await;
+}
+
+/// Тест: удаление при синхронизации не выходит за папку получателя
+#[tokio::test]
+async fn test_delete_remote_files() {
+    use toolza_sender::network::{delete_remote_files, ServerOptions, SendOptions};
+    
+    let root = tempfile::TempDir::new().unwrap();
+    let dst = root.path().join("dst");
+    std::fs::create_dir_all(dst.join("dir")).unwrap();
+    std::fs::write(dst.join("dir/old.txt"), "удалён у отправителя").unwrap();
+    std::fs::write(root.path().join("outside.txt"), "не трогать").unwrap();
+    
+    // Пустой список - даже не подключается
+    delete_remote_files("127.0.0.1:1", &[], &SendOptions::default()).await.unwrap();
+    
+    // Получатель без --allow-delete ничего не удаляет
+    let (server, _) = spawn_test_server(&dst, ServerOptions::default()).await;
+    let paths = vec!["dir/old.txt".to_string()];
+    let err = delete_remote_files(&server.addr.clone(), &paths, &SendOptions::default()).await.unwrap_err();
+    assert!(err.contains("--allow-delete"), "{}", err);
+    assert!(dst.join("dir/old.txt").exists());
+    server.stop().await;
+    
+    // Слишком длинный список отклоняется целиком
+    let options = ServerOptions::builder().allow_delete(true).max_delete_files(1).build().unwrap();
+    let (server, _) = spawn_test_server(&dst, options).await;
+    let paths = vec!["dir/old.txt".to_string(), "dir/other.txt".to_string()];
+    let err = delete_remote_files(&server.addr.clone(), &paths, &SendOptions::default()).await.unwrap_err();
+    assert!(err.contains("Слишком много"), "{}", err);
+    assert!(dst.join("dir/old.txt").exists());
+    server.stop().await;
+    
+    let options = ServerOptions::builder().allow_delete(true).build().unwrap();
+    let (server, mut server_rx) = spawn_test_server(&dst, options).await;
+    
+    let paths = vec!["dir/old.txt".to_string(), "../outside.txt".to_string()];
+    let err = delete_remote_files(&server.addr.clone(), &paths, &SendOptions::default()).await.unwrap_err();
+    assert!(err.contains("../outside.txt"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    
+    assert!(!dst.join("dir/old.txt").exists());
+    assert!(root.path().join("outside.txt").exists());
+    let mut deleted = Vec::new();
+    while let Ok(event) = server_rx.try_recv() {
+        if let TransferEvent::FileDeleted(path) = event {
+            deleted.push(path);
+        }
+    }
+    assert_eq!(deleted, vec!["dir/old.txt"]);
+    
+    server.stop().await;
+}
+
 /// Тест: передача протокольного сообщения через TCP
 #[tokio::test]
 async fn test_protocol_message_transfer() {
@@ -396,7 +980,7 @@ async fn test_protocol_message_transfer() {
     // Проверяем полученное сообщение
     let received = receive_handle.await.unwrap();
     match received {
-        Message::FileStart { filename, size, compressed, offset, quick_hash } => {
+        Message::FileStart { filename, size, compressed, offset, quick_hash, .. } => {
             assert_eq!(filename, "test.txt");
             assert_eq!(size, 1024);
             assert!(compressed);
@@ -527,27 +1111,47 @@ fn test_compression_large_data() {
 #[test]
 fn test_transfer_event_types() {
     let events = vec![
+        TransferEvent::Connecting(0),
+        TransferEvent::ConnectionRetrying(0, 1),
         TransferEvent::Connected(0, "addr".to_string()),
+        TransferEvent::Handshaking(0),
         TransferEvent::FileStarted(0, 0),
         TransferEvent::Progress(0, 0, 100, 200, 150),
         TransferEvent::FileCompleted(0, 0),
         TransferEvent::FileError(0, 0, "error".to_string()),
+        TransferEvent::FileCorrupted(0, 0),
         TransferEvent::TargetCompleted(0),
         TransferEvent::AllCompleted,
         TransferEvent::ConnectionError(0, "error".to_string()),
-        TransferEvent::FileSkipped(0, 0),
+        TransferEvent::FileSkipped(0, 0, SkipReason::UpToDate),
         TransferEvent::FileResumed(0, 0, 500),
-        TransferEvent::Disconnected,
+        TransferEvent::TransportFallback(0, TransportType::Tcp, "error".to_string()),
+        TransferEvent::ClientConnected("addr".to_string(), ConnectionIntent::Transfer),
+        TransferEvent::ClientDisconnected("addr".to_string(), ConnectionIntent::Transfer),
+        TransferEvent::ClientProbed("addr".to_string()),
+        TransferEvent::FileReceiveStarted(0, "file".to_string(), 100),
+        TransferEvent::FileReceiveEnded(0),
         TransferEvent::FileReceived("file".to_string(), 100),
+        TransferEvent::FileRejected("file".to_string(), "reason".to_string()),
         TransferEvent::ExtractionStarted("archive".to_string()),
-        TransferEvent::ExtractionCompleted("archive".to_string(), 10, 1000),
+        TransferEvent::ExtractionCompleted("archive".to_string(), ExtractResult {
+            files_count: 10,
+            total_size: 1000,
+            dirs_count: 2,
+            symlinks_count: 1,
+            skipped: Vec::new(),
+        }),
         TransferEvent::ExtractionError("archive".to_string(), "error".to_string()),
+        TransferEvent::ExtractionEntrySkipped("archive".to_string(), "a/b".to_string(), "error".to_string()),
+        TransferEvent::HookCompleted("file".to_string(), "output".to_string()),
+        TransferEvent::HookError("file".to_string(), "error".to_string()),
         TransferEvent::ServerFound("addr".to_string()),
+        TransferEvent::ServerFoundNamed("addr".to_string(), "host".to_string()),
         TransferEvent::ScanProgress("ip".to_string(), 50),
         TransferEvent::ScanCompleted,
     ];
     
     // Просто проверяем что все типы существуют и создаются
-    assert_eq!(events.len(), 18);
+    assert_eq!(events.len(), 32);
 }
 